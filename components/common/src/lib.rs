@@ -0,0 +1,2 @@
+//! Formats which are shared between the server and the data proxies
+pub mod session_token;
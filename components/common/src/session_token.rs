@@ -0,0 +1,73 @@
+use anyhow::{anyhow, bail, Result};
+use base64::engine::general_purpose;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Name of the user attributes with the ids of session-scoped tokens, e.g. the tokens behind
+/// storage role credentials. Requests with credentials of these tokens have to present a
+/// valid session token. Tokens of the API have no field for this, so the server lists each
+/// session-scoped token of a user as an attribute with this name and the token id as value.
+pub const SESSION_SCOPED_TOKEN_ATTRIBUTE: &str = "session-scoped-token";
+
+fn session_mac(secret_key: &str, access_key: &str, expires_at: i64) -> Result<HmacSha256> {
+    let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes())?;
+    mac.update(format!("{access_key}:{expires_at}").as_bytes());
+    Ok(mac)
+}
+
+/// Creates the session token of storage role credentials in the format `<expires_at>.<signature>`.
+///
+/// The signature is the url-safe base64 encoded HMAC-SHA256 of `<access_key>:<expires_at>`
+/// with the secret key of the credentials.
+pub fn sign_session_token(secret_key: &str, access_key: &str, expires_at: i64) -> Result<String> {
+    let mac = session_mac(secret_key, access_key, expires_at)?;
+    Ok(format!(
+        "{expires_at}.{}",
+        general_purpose::URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+    ))
+}
+
+/// Validates the session token of storage role credentials which must not be expired at `now`.
+pub fn verify_session_token(
+    secret_key: &str,
+    access_key: &str,
+    token: &str,
+    now: i64,
+) -> Result<()> {
+    let (expires_at, signature) = token
+        .split_once('.')
+        .ok_or_else(|| anyhow!("Malformed session token"))?;
+    let expires_at = expires_at.parse::<i64>()?;
+    if expires_at < now {
+        bail!("Session token expired")
+    }
+
+    session_mac(secret_key, access_key, expires_at)?
+        .verify_slice(&general_purpose::URL_SAFE_NO_PAD.decode(signature)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_token() {
+        let now = 1_700_000_000;
+        let token = sign_session_token("secret", "ACCESSKEY", now + 300).unwrap();
+
+        assert!(verify_session_token("secret", "ACCESSKEY", &token, now).is_ok());
+        // Expired
+        assert!(verify_session_token("secret", "ACCESSKEY", &token, now + 301).is_err());
+        // Issued for other credentials
+        assert!(verify_session_token("other", "ACCESSKEY", &token, now).is_err());
+        assert!(verify_session_token("secret", "OTHERKEY", &token, now).is_err());
+        // Extended expiry invalidates the signature
+        let (_, signature) = token.split_once('.').unwrap();
+        let tampered = format!("{}.{signature}", now + 3600);
+        assert!(verify_session_token("secret", "ACCESSKEY", &tampered, now).is_err());
+    }
+}
@@ -2,6 +2,7 @@ use super::auth_helpers;
 use super::rule_engine::RuleEngine;
 use super::rule_structs::ObjectRuleInputBuilder;
 use super::rule_structs::RootRuleInputBuilder;
+use crate::auth::crypto::verify_cdn_origin_token;
 use crate::auth::rule_structs::BundleRuleInputBuilder;
 use crate::auth::rule_structs::PackageObjectRuleInputBuilder;
 use crate::caching::cache::Cache;
@@ -16,14 +17,17 @@ use crate::structs::PubKey;
 use crate::structs::ResourceStates;
 use crate::structs::TypedId;
 use crate::structs::UserState;
+use crate::CONFIG;
 use anyhow::anyhow;
 use anyhow::bail;
 use anyhow::Result;
 use aruna_rust_api::api::storage::models::v2::DataClass;
+use chrono::Utc;
 use diesel_ulid::DieselUlid;
 use http::HeaderMap;
 use http::HeaderValue;
 use http::Method;
+use jsonwebtoken::errors::ErrorKind;
 use jsonwebtoken::Algorithm;
 use jsonwebtoken::EncodingKey;
 use jsonwebtoken::Header;
@@ -43,6 +47,9 @@ use std::time::SystemTime;
 use tracing::error;
 use tracing::trace;
 
+/// Header of origin fetches from the CDN
+pub const CDN_ORIGIN_TOKEN_HEADER: &str = "x-aruna-cdn-origin-token";
+
 pub struct AuthHandler {
     cache: Arc<Cache>,
     self_id: DieselUlid,
@@ -52,7 +59,7 @@ pub struct AuthHandler {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct ArunaTokenClaims {
-    iss: String, // Currently always 'aruna'
+    iss: String, // Server issuer or DataProxy_ID
     sub: String, // User_ID / DataProxy_ID
     exp: usize,  // Expiration timestamp
     aud: String, // Valid audiences
@@ -221,7 +228,7 @@ impl AuthHandler {
         dec_key: &DecodingKey,
     ) -> Result<ArunaTokenClaims, anyhow::Error> {
         let mut validation = Validation::new(Algorithm::EdDSA);
-        validation.set_audience(&["proxy"]);
+        validation.set_audience(&CONFIG.proxy.token_audiences);
 
         let token = decode::<ArunaTokenClaims>(
             token,
@@ -230,7 +237,11 @@ impl AuthHandler {
         )
         .map_err(|e| {
             error!(error = ?e, msg = e.to_string());
-            e
+            match e.kind() {
+                // Token of another instance
+                ErrorKind::InvalidAudience => anyhow!("Invalid token audience"),
+                _ => e.into(),
+            }
         })?;
         Ok(token.claims)
     }
@@ -438,6 +449,9 @@ impl AuthHandler {
                 Some(user).into()
             } else if resource_states.require_object()?.data_class == DataClass::Public {
                 UserState::Anonymous
+            } else if self.check_cdn_origin_token(method, headers)? {
+                // End-user urls are validated at the CDN edge
+                UserState::Anonymous
             } else {
                 return Err(s3_error!(AccessDenied, "Missing access key"));
             };
@@ -467,6 +481,44 @@ impl AuthHandler {
         ))
     }
 
+    /// Checks if the request is an origin fetch of the configured CDN.
+    /// Returns false if no CDN origin token is provided.
+    #[tracing::instrument(level = "trace", skip(self, headers))]
+    pub fn check_cdn_origin_token(
+        &self,
+        method: &Method,
+        headers: &HeaderMap<HeaderValue>,
+    ) -> Result<bool, S3Error> {
+        let Some(token) = headers.get(CDN_ORIGIN_TOKEN_HEADER) else {
+            return Ok(false);
+        };
+        let Some(cdn_origin) = CONFIG
+            .frontend
+            .as_ref()
+            .and_then(|frontend| frontend.cdn_origin.as_ref())
+        else {
+            return Err(s3_error!(AccessDenied, "CDN origin access not enabled"));
+        };
+        if !is_method_read(method) {
+            return Err(s3_error!(MethodNotAllowed, "Method not allowed"));
+        }
+
+        let secret = cdn_origin.get_secret().map_err(|e| {
+            error!(error = ?e, msg = e.to_string());
+            s3_error!(InternalError, "CDN origin secret not set")
+        })?;
+        let token = token
+            .to_str()
+            .map_err(|_| s3_error!(AccessDenied, "Invalid CDN origin token"))?;
+        verify_cdn_origin_token(secret, &self.self_id, token, Utc::now().timestamp()).map_err(
+            |e| {
+                error!(error = ?e, msg = e.to_string());
+                s3_error!(AccessDenied, "Invalid CDN origin token")
+            },
+        )?;
+        Ok(true)
+    }
+
     #[tracing::instrument(level = "trace", skip(self, key_name, creds, headers))]
     pub async fn handle_package_objects(
         &self,
@@ -634,7 +686,7 @@ impl AuthHandler {
         let claims = ArunaTokenClaims {
             iss: self.self_id.to_string(),
             sub: user_id.into(),
-            aud: "aruna".to_string(),
+            aud: CONFIG.proxy.server_audience.clone(),
             exp: SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)?
                 .add(Duration::from_secs(15 * 60))
@@ -657,7 +709,7 @@ impl AuthHandler {
         let claims = ArunaTokenClaims {
             iss: self.self_id.to_string(),
             sub: self.self_id.to_string(),
-            aud: "aruna".to_string(),
+            aud: CONFIG.proxy.server_audience.clone(),
             exp: SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)?
                 .add(Duration::from_secs(60 * 60 * 24 * 365 * 10))
@@ -683,7 +735,7 @@ impl AuthHandler {
         let claims = ArunaTokenClaims {
             iss: self.self_id.to_string(),
             sub: self.self_id.to_string(),
-            aud: "proxy".to_string(),
+            aud: CONFIG.proxy.token_audiences[0].clone(),
             exp: SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)?
                 .add(Duration::from_secs(15 * 60))
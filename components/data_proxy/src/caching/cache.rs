@@ -2,9 +2,15 @@ use super::grpc_query_handler::GrpcQueryHandler;
 use crate::auth::auth::AuthHandler;
 use crate::caching::grpc_query_handler::sort_objects;
 use crate::data_backends::storage_backend::StorageBackend;
-use crate::database::persistence::delete_parts_by_upload_id;
+use crate::database::persistence::{
+    delete_parts_by_upload_id, get_consumed_links, insert_consumed_link,
+};
+use crate::replication::on_access::OnAccessReplication;
 use crate::replication::replication_handler::ReplicationMessage;
 use crate::s3_frontend::data_handler::DataHandler;
+#[cfg(feature = "row-ranges")]
+use crate::s3_frontend::utils::object_accessor::LineIndex;
+use crate::s3_frontend::utils::single_use::SingleUseLinks;
 use crate::structs::{
     AccessKeyPermissions, Bundle, DbPermissionLevel, LocationBinding, ObjectType, TypedId,
     UploadPart, User,
@@ -51,6 +57,10 @@ pub struct Cache {
     // Parts sorted by upload_id
     multi_parts: DashMap<String, Vec<UploadPart>>,
 
+    // Line indexes by location id, only used without persistence
+    #[cfg(feature = "row-ranges")]
+    line_indexes: DashMap<DieselUlid, Arc<LineIndex>, RandomState>,
+
     // Maps with path / key as key and set of all ObjectIds as value
     // /project1/collection1/dataset1 -> ObjectID
     // /project1/collection1/exaset1/object1 -> ObjectID
@@ -74,6 +84,8 @@ pub struct Cache {
     pub(crate) aruna_client: RwLock<Option<Arc<GrpcQueryHandler>>>,
     pub(crate) auth: RwLock<Option<AuthHandler>>,
     pub(crate) sender: Sender<ReplicationMessage>,
+    pub(crate) on_access: OnAccessReplication,
+    pub(crate) single_use: Arc<SingleUseLinks>,
     backend: Option<Arc<Box<dyn StorageBackend>>>,
 
     pub(crate) self_arc: RwLock<Option<Arc<Cache>>>,
@@ -107,12 +119,16 @@ impl Cache {
             resources: DashMap::default(),
             bundles: DashMap::default(),
             multi_parts: DashMap::default(),
+            #[cfg(feature = "row-ranges")]
+            line_indexes: DashMap::default(),
             paths: SkipMap::new(),
             pubkeys: DashMap::default(),
             persistence: RwLock::new(None),
             aruna_client: RwLock::new(None),
             auth: RwLock::new(None),
             sender,
+            on_access: OnAccessReplication::default(),
+            single_use: Arc::new(SingleUseLinks::default()),
             backend,
             self_arc: RwLock::new(None),
         });
@@ -178,6 +194,27 @@ impl Cache {
         let persistence = self.sync_with_persistence(persistence).await?;
         let mut guard = self.persistence.write().await;
         *guard = Some(persistence);
+
+        // Consumed single-use links have to survive restarts
+        let (sender, receiver) = async_channel::bounded(1000);
+        self.single_use.set_consumed_sender(sender);
+        let cache = self.get_cache().await?;
+        tokio::spawn(
+            async move {
+                while let Ok(link) = receiver.recv().await {
+                    if let Some(persistence) = cache.persistence.read().await.as_ref() {
+                        let result = match persistence.get_client().await {
+                            Ok(client) => insert_consumed_link(client.client(), &link).await,
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = result {
+                            error!(error = ?e, msg = "Unable to persist consumed link");
+                        }
+                    }
+                }
+            }
+            .instrument(info_span!("persist_consumed_links")),
+        );
         Ok(())
     }
 
@@ -238,6 +275,10 @@ impl Cache {
         self.sync_pubkeys(PubKey::get_all(&client).await?).await?;
         debug!("synced pubkeys");
 
+        self.single_use
+            .load(get_consumed_links(&client, chrono::Utc::now().timestamp()).await?);
+        debug!("synced consumed links");
+
         // Sort objects from database before sync
         let mut database_objects = Object::get_all(&client).await?;
         sort_objects(&mut database_objects);
@@ -288,6 +329,7 @@ impl Cache {
                                     backend,
                                     before_location,
                                     None,
+                                    None,
                                 )
                                 .await
                             }
@@ -345,20 +387,22 @@ impl Cache {
             .get(user_id)
             .ok_or_else(|| anyhow!("User not found"))?
             .clone();
-        let permissions = if user_id.to_string().as_str() == access_key {
+        let (permissions, session_scoped) = if user_id.to_string().as_str() == access_key {
             let mut user_info = user.write().await;
             user_info.1.push(access_key.to_string());
-            user_info.0.personal_permissions.clone()
+            (user_info.0.personal_permissions.clone(), false)
         } else {
             let mut user_info = user.write().await;
+            let token_id = DieselUlid::from_str(access_key)?;
             let token = user_info
                 .0
                 .tokens
-                .get(&DieselUlid::from_str(access_key)?)
+                .get(&token_id)
                 .cloned()
                 .ok_or_else(|| anyhow!("Access key not found"))?;
+            let session_scoped = user_info.0.session_tokens.contains(&token_id);
             user_info.1.push(access_key.to_string());
-            token
+            (token, session_scoped)
         };
         let new_secret = thread_rng()
             .sample_iter(&Alphanumeric)
@@ -372,6 +416,7 @@ impl Cache {
             is_service_account: user.read().await.0.is_service_account,
             secret: new_secret.clone(),
             permissions,
+            session_scoped,
         };
 
         if let Some(pers) = self.persistence.read().await.as_ref() {
@@ -806,14 +851,23 @@ impl Cache {
 
         // Remove data from storage backend
         if let Some(s3_backend) = &self.backend {
-            if let Some(resource) = self.resources.get(&id) {
-                let (_, loc) = resource.value();
-                if let Some(location) = loc.read().await.as_ref() {
-                    s3_backend.delete_object(location.clone()).await?;
+            let loc = self
+                .resources
+                .get(&id)
+                .map(|resource| resource.value().1.clone());
+            if let Some(loc) = loc {
+                let location = loc.read().await.clone();
+                if let Some(location) = location {
+                    // Data of deduplicated uploads is kept for the other objects
+                    if location.ref_count <= 1 || !self.is_location_shared(&id, &location.id).await
+                    {
+                        s3_backend.delete_object(location).await?;
+                    }
                 }
             }
         }
         // Remove object and location from cache
+        self.on_access.remove_stub(&id);
         let old = self
             .resources
             .remove(&id)
@@ -1076,6 +1130,83 @@ impl Cache {
         Ok(())
     }
 
+    /// Binds the location of `source_id` to an object which uploaded the same data
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub async fn share_location(
+        &self,
+        source_id: DieselUlid,
+        object_id: DieselUlid,
+        location_id: DieselUlid,
+    ) -> Result<()> {
+        let (_, source) = self
+            .resources
+            .get(&source_id)
+            .ok_or_else(|| anyhow!("Resource not found {}", source_id))?
+            .value()
+            .clone();
+        let location = {
+            let mut source = source.write().await;
+            let location = source
+                .as_mut()
+                .filter(|location| location.id == location_id)
+                .ok_or_else(|| anyhow!("Shared location changed"))?;
+            location.ref_count = location.ref_count.max(1) + 1;
+            location.clone()
+        };
+        self.add_location_with_binding(object_id, location).await
+    }
+
+    /// Checks if other objects than `object_id` are bound to the location
+    async fn is_location_shared(&self, object_id: &DieselUlid, location_id: &DieselUlid) -> bool {
+        let locations = self
+            .resources
+            .iter()
+            .filter(|entry| entry.key() != object_id)
+            .map(|entry| entry.value().1.clone())
+            .collect::<Vec<_>>();
+        for location in locations {
+            if location
+                .read()
+                .await
+                .as_ref()
+                .is_some_and(|location| &location.id == location_id)
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[cfg(feature = "row-ranges")]
+    #[tracing::instrument(level = "trace", skip(self, index))]
+    pub async fn add_line_index(&self, index: LineIndex) -> Result<()> {
+        if let Some(persistence) = self.persistence.read().await.as_ref() {
+            index
+                .upsert(persistence.get_client().await?.client())
+                .await?;
+        } else {
+            self.line_indexes.insert(index.id, Arc::new(index));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "row-ranges")]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub async fn get_line_index(&self, location_id: &DieselUlid) -> Result<Option<Arc<LineIndex>>> {
+        if let Some(persistence) = self.persistence.read().await.as_ref() {
+            Ok(
+                LineIndex::get_opt(location_id, persistence.get_client().await?.client())
+                    .await?
+                    .map(Arc::new),
+            )
+        } else {
+            Ok(self
+                .line_indexes
+                .get(location_id)
+                .map(|index| index.value().clone()))
+        }
+    }
+
     #[tracing::instrument(level = "trace", skip(self, object_id, location))]
     pub async fn update_location(
         &self,
@@ -1094,6 +1225,13 @@ impl Cache {
             bail!("Resource not found")
         };
 
+        #[cfg(feature = "row-ranges")]
+        if let Some(old_id) = old_location_id {
+            if old_id != location.id {
+                self.line_indexes.remove(&old_id);
+            }
+        }
+
         if let Some(persistence) = self.persistence.read().await.as_ref() {
             location
                 .upsert(persistence.get_client().await?.client())
@@ -1,9 +1,12 @@
+use crate::grpc_api::connections::with_keepalive;
+use crate::grpc_api::request_id::{current_request_id, REQUEST_ID_KEY};
 use crate::replication::replication_handler::Direction;
 use crate::replication::replication_handler::ReplicationMessage;
 use crate::structs::Object as DPObject;
 use crate::structs::ObjectType;
 use crate::structs::PubKey;
 use crate::structs::TypedRelation;
+use crate::CONFIG;
 use anyhow::anyhow;
 use anyhow::Result;
 use aruna_rust_api::api::dataproxy::services::v2::dataproxy_replication_service_client::DataproxyReplicationServiceClient;
@@ -129,10 +132,13 @@ impl GrpcQueryHandler {
                 e
             })?
         };
-        let channel = endpoint.connect().await.map_err(|e| {
-            error!(error = ?e, msg = e.to_string());
-            e
-        })?;
+        let channel = with_keepalive(endpoint, &CONFIG.proxy.grpc)
+            .connect()
+            .await
+            .map_err(|e| {
+                error!(error = ?e, msg = e.to_string());
+                e
+            })?;
 
         let project_service = ProjectServiceClient::new(channel.clone());
 
@@ -202,6 +208,13 @@ impl GrpcQueryHandler {
             e
         })?;
         md.append(key, value);
+
+        // Forward the correlation id of the currently handled request
+        if let Some(request_id) = current_request_id() {
+            if let Ok(value) = AsciiMetadataValue::try_from(request_id) {
+                md.insert(REQUEST_ID_KEY, value);
+            }
+        }
         Ok(())
     }
 
@@ -2,7 +2,10 @@ use anyhow::{anyhow, bail, Result};
 use base64::engine::general_purpose;
 use base64::Engine;
 use diesel_ulid::DieselUlid;
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
@@ -10,6 +13,7 @@ pub struct Config {
     pub persistence: Option<Persistence>,
     pub frontend: Option<Frontend>,
     pub backend: Backend,
+    pub storage_tiers: Option<StorageTiers>,
     pub rules: Option<Vec<Rule>>,
 }
 
@@ -18,7 +22,9 @@ impl Config {
         let Config {
             proxy,
             persistence,
+            frontend,
             backend,
+            storage_tiers,
             ..
         } = self;
 
@@ -26,7 +32,13 @@ impl Config {
         if let Some(persistence) = persistence {
             persistence.validate()?;
         }
+        if let Some(frontend) = frontend {
+            frontend.validate()?;
+        }
         backend.validate()?;
+        if let Some(storage_tiers) = storage_tiers {
+            storage_tiers.validate()?;
+        }
         Ok(())
     }
 
@@ -47,6 +59,193 @@ pub struct Proxy {
     pub aruna_url: Option<String>,
     pub grpc_server: String,
     pub replication_interval: Option<u64>,
+    pub max_concurrent_replications: Option<usize>,
+    // Bandwidth limit for all replications in bytes per second
+    pub replication_bandwidth_limit: Option<u64>,
+    pub replication_queue_size: Option<usize>,
+    #[serde(default)]
+    pub replication_mode: ReplicationMode,
+    // Audience of tokens signed for the server
+    #[serde(default = "default_server_audience")]
+    pub server_audience: String,
+    // Accepted audiences of tokens, the first is used for proxy-to-proxy tokens
+    #[serde(default = "default_token_audiences")]
+    pub token_audiences: Vec<String>,
+    // Server-side imports of objects from remote urls, disabled if not set.
+    // Not served yet, ImportObjectFromUrl is not part of the API release.
+    pub url_import: Option<UrlImport>,
+    #[serde(default)]
+    pub grpc: GrpcSettings,
+}
+
+fn default_server_audience() -> String {
+    "aruna".to_string()
+}
+
+fn default_token_audiences() -> Vec<String> {
+    vec!["proxy".to_string()]
+}
+
+/// Restrictions for server-side fetches of remote urls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlImport {
+    #[serde(default = "default_import_schemes")]
+    pub allowed_schemes: Vec<String>,
+    // Hosts (including their subdomains) urls can be fetched from, any public host if empty
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    // Internal networks which can be fetched from nonetheless, e.g. for trusted mirrors
+    #[serde(default)]
+    pub allowed_networks: Vec<IpNet>,
+    // Maximum size of fetched data in bytes
+    #[serde(default = "default_import_max_size")]
+    pub max_size: u64,
+    // Seconds until a fetch (including the transfer) is aborted
+    #[serde(default = "default_import_timeout")]
+    pub timeout: u64,
+    #[serde(default = "default_import_max_redirects")]
+    pub max_redirects: usize,
+}
+
+fn default_import_schemes() -> Vec<String> {
+    vec!["https".to_string()]
+}
+
+fn default_import_max_size() -> u64 {
+    107374182400 // 100 GiB
+}
+
+fn default_import_timeout() -> u64 {
+    3600
+}
+
+fn default_import_max_redirects() -> usize {
+    5
+}
+
+impl UrlImport {
+    fn validate(&self) -> Result<()> {
+        if self.allowed_schemes.is_empty() {
+            bail!("url_import allowed_schemes cannot be empty")
+        }
+        if self
+            .allowed_schemes
+            .iter()
+            .any(|scheme| scheme != "http" && scheme != "https")
+        {
+            bail!("url_import only supports the schemes http and https")
+        }
+        if self.max_size == 0 {
+            bail!("url_import max_size must be at least 1")
+        }
+        if self.timeout == 0 {
+            bail!("url_import timeout must be at least 1")
+        }
+        Ok(())
+    }
+}
+
+/// HTTP/2 settings of the gRPC server, unset values use the defaults of hyper.
+/// The keepalive settings also apply to the connection to the Aruna server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrpcSettings {
+    // Seconds between keepalive pings, disabled with 0
+    #[serde(default = "default_keepalive_interval")]
+    pub keepalive_interval: u64,
+    // Seconds until connections without ping acknowledgement are closed
+    pub keepalive_timeout: Option<u64>,
+    // Ping the Aruna server also without running requests, the server side always pings idle connections
+    #[serde(default)]
+    pub keepalive_while_idle: bool,
+    pub max_concurrent_streams: Option<u32>,
+    pub initial_stream_window_size: Option<u32>,
+    pub initial_connection_window_size: Option<u32>,
+    // Seconds until connections are closed and clients have to reconnect
+    pub max_connection_age: Option<u64>,
+}
+
+fn default_keepalive_interval() -> u64 {
+    15
+}
+
+impl Default for GrpcSettings {
+    fn default() -> Self {
+        GrpcSettings {
+            keepalive_interval: default_keepalive_interval(),
+            keepalive_timeout: None,
+            keepalive_while_idle: false,
+            max_concurrent_streams: None,
+            initial_stream_window_size: None,
+            initial_connection_window_size: None,
+            max_connection_age: None,
+        }
+    }
+}
+
+impl GrpcSettings {
+    // Largest HTTP/2 flow control window (2^31 - 1)
+    const MAX_WINDOW_SIZE: u32 = 2_147_483_647;
+    // HTTP/2 default flow control window, connection windows can not be smaller
+    const DEFAULT_WINDOW_SIZE: u32 = 65_535;
+
+    fn validate(&self) -> Result<()> {
+        if self.keepalive_timeout == Some(0) {
+            bail!("grpc keepalive_timeout must be at least 1")
+        }
+        if self.keepalive_interval == 0
+            && (self.keepalive_timeout.is_some() || self.keepalive_while_idle)
+        {
+            bail!("grpc keepalive_timeout and keepalive_while_idle need keepalive pings")
+        }
+        if self.max_concurrent_streams == Some(0) {
+            bail!("grpc max_concurrent_streams must be at least 1")
+        }
+        if self
+            .initial_stream_window_size
+            .is_some_and(|size| size == 0 || size > Self::MAX_WINDOW_SIZE)
+        {
+            bail!(
+                "grpc initial_stream_window_size must be between 1 and {}",
+                Self::MAX_WINDOW_SIZE
+            )
+        }
+        if self.initial_connection_window_size.is_some_and(|size| {
+            !(Self::DEFAULT_WINDOW_SIZE..=Self::MAX_WINDOW_SIZE).contains(&size)
+        }) {
+            bail!(
+                "grpc initial_connection_window_size must be between {} and {}",
+                Self::DEFAULT_WINDOW_SIZE,
+                Self::MAX_WINDOW_SIZE
+            )
+        }
+        if self.max_connection_age == Some(0) {
+            bail!("grpc max_connection_age must be at least 1")
+        }
+        Ok(())
+    }
+
+    pub fn get_keepalive_interval(&self) -> Option<Duration> {
+        (self.keepalive_interval > 0).then(|| Duration::from_secs(self.keepalive_interval))
+    }
+
+    pub fn get_keepalive_timeout(&self) -> Option<Duration> {
+        self.keepalive_timeout.map(Duration::from_secs)
+    }
+
+    pub fn get_max_connection_age(&self) -> Option<Duration> {
+        self.max_connection_age.map(Duration::from_secs)
+    }
+}
+
+/// When the data of objects replicated to this proxy is transferred
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReplicationMode {
+    // Data is pulled in batches as soon as the object is available
+    #[default]
+    Eager,
+    // Only metadata is kept until the data is pulled with the first download
+    OnAccess,
 }
 
 impl Proxy {
@@ -75,6 +274,37 @@ impl Proxy {
             return Err(anyhow::anyhow!("serial must be at least 1"));
         }
 
+        if self.max_concurrent_replications == Some(0) {
+            return Err(anyhow::anyhow!(
+                "max_concurrent_replications must be at least 1"
+            ));
+        }
+
+        if self.replication_queue_size == Some(0) {
+            return Err(anyhow::anyhow!("replication_queue_size must be at least 1"));
+        }
+
+        if self.replication_bandwidth_limit == Some(0) {
+            return Err(anyhow::anyhow!(
+                "replication_bandwidth_limit must be at least 1"
+            ));
+        }
+
+        if self.server_audience.is_empty() {
+            return Err(anyhow::anyhow!("server_audience cannot be empty"));
+        }
+
+        if self.token_audiences.is_empty() || self.token_audiences.iter().any(|aud| aud.is_empty())
+        {
+            return Err(anyhow::anyhow!("token_audiences cannot be empty"));
+        }
+
+        if let Some(url_import) = &self.url_import {
+            url_import.validate()?;
+        }
+
+        self.grpc.validate()?;
+
         Ok(())
     }
 
@@ -169,6 +399,307 @@ pub struct Frontend {
     pub server: String,
     pub hostname: String,
     pub cors_exception: Option<String>,
+    // Reverse proxies whose X-Forwarded-For header is trusted to resolve client ips
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNet>,
+    pub cdn_origin: Option<CdnOrigin>,
+    pub access_log: Option<AccessLog>,
+    // Stored checksums of objects which are returned as headers of downloads
+    #[serde(default = "default_checksum_headers")]
+    pub checksum_headers: Vec<ChecksumAlgorithm>,
+    pub download_limits: Option<DownloadLimits>,
+    // Parts of a multipart upload which are verified in the backend at the same time
+    #[serde(default = "default_part_verification_concurrency")]
+    pub part_verification_concurrency: usize,
+    pub consolidation: Option<Consolidation>,
+    pub resume_tokens: Option<ResumeTokens>,
+}
+
+fn default_checksum_headers() -> Vec<ChecksumAlgorithm> {
+    vec![ChecksumAlgorithm::Sha256]
+}
+
+fn default_part_verification_concurrency() -> usize {
+    16
+}
+
+/// Hash algorithms of the checksums of downloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    Md5,
+    Sha256,
+}
+
+impl Frontend {
+    fn validate(&mut self) -> Result<()> {
+        if let Some(cdn_origin) = &mut self.cdn_origin {
+            cdn_origin.validate()?;
+        }
+        if let Some(access_log) = &self.access_log {
+            access_log.validate()?;
+        }
+        if let Some(download_limits) = &self.download_limits {
+            download_limits.validate()?;
+        }
+        if self.part_verification_concurrency == 0 {
+            bail!("part_verification_concurrency must be at least 1")
+        }
+        if let Some(consolidation) = &self.consolidation {
+            consolidation.validate()?;
+        }
+        if let Some(resume_tokens) = &mut self.resume_tokens {
+            resume_tokens.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Rewrites finished multipart objects of opted-in projects into a single backend blob
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Consolidation {
+    // Largest stored size in bytes of objects which are consolidated
+    #[serde(default = "default_consolidation_max_size")]
+    pub max_size: u64,
+    // Seconds the previous layout is kept for running downloads after the swap
+    #[serde(default = "default_consolidation_grace_period")]
+    pub grace_period: u64,
+}
+
+fn default_consolidation_max_size() -> u64 {
+    1024 * 1024 * 1024
+}
+
+fn default_consolidation_grace_period() -> u64 {
+    600
+}
+
+impl Consolidation {
+    // Largest object which can be written with a single put (5 GiB)
+    const MAX_SINGLE_PUT: u64 = 5 * 1024 * 1024 * 1024;
+
+    fn validate(&self) -> Result<()> {
+        if self.max_size > Self::MAX_SINGLE_PUT {
+            bail!("consolidation max_size must be at most 5 GiB")
+        }
+        Ok(())
+    }
+}
+
+/// Bandwidth limits of downloads in bytes per second, limited downloads are slowed down
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DownloadLimits {
+    // Limit of all downloads of one token or user, anonymous downloads only share the global limit
+    pub per_token: Option<u64>,
+    // Limit of all downloads together
+    pub global: Option<u64>,
+    // Access keys of trusted tokens which are not limited
+    #[serde(default)]
+    pub exempt_access_keys: Vec<String>,
+    // Seconds between logs of the download throughput
+    #[serde(default = "default_download_metrics_interval")]
+    pub metrics_interval: u64,
+}
+
+fn default_download_metrics_interval() -> u64 {
+    60
+}
+
+impl DownloadLimits {
+    fn validate(&self) -> Result<()> {
+        if self.per_token == Some(0) || self.global == Some(0) {
+            bail!("download_limits must be at least 1 byte per second")
+        }
+        if self.metrics_interval == 0 {
+            bail!("download_limits metrics_interval must be at least 1")
+        }
+        Ok(())
+    }
+}
+
+/// Where access log records are written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogTarget {
+    Stdout,
+    // Structured tracing events
+    Log,
+    // Batches of records stored as objects in a backend bucket
+    Bucket,
+}
+
+/// Fields of the S3 server access log format in canonical order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessLogField {
+    BucketOwner,
+    Bucket,
+    Time,
+    RemoteIp,
+    Requester,
+    RequestId,
+    Operation,
+    Key,
+    RequestUri,
+    HttpStatus,
+    ErrorCode,
+    BytesSent,
+    ObjectSize,
+    TotalTime,
+    TurnAroundTime,
+    Referer,
+    UserAgent,
+    VersionId,
+    // Identity which created the presigned url of a request, not part of the S3 format
+    Attribution,
+}
+
+impl AccessLogField {
+    pub const ALL: [AccessLogField; 18] = [
+        AccessLogField::BucketOwner,
+        AccessLogField::Bucket,
+        AccessLogField::Time,
+        AccessLogField::RemoteIp,
+        AccessLogField::Requester,
+        AccessLogField::RequestId,
+        AccessLogField::Operation,
+        AccessLogField::Key,
+        AccessLogField::RequestUri,
+        AccessLogField::HttpStatus,
+        AccessLogField::ErrorCode,
+        AccessLogField::BytesSent,
+        AccessLogField::ObjectSize,
+        AccessLogField::TotalTime,
+        AccessLogField::TurnAroundTime,
+        AccessLogField::Referer,
+        AccessLogField::UserAgent,
+        AccessLogField::VersionId,
+    ];
+}
+
+/// S3 server access logs of the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessLog {
+    pub target: AccessLogTarget,
+    pub bucket: Option<String>,
+    #[serde(default)]
+    pub prefix: String,
+    // Fraction of requests which are logged
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f64,
+    // Defaults to all fields
+    pub fields: Option<Vec<AccessLogField>>,
+    // Records buffered in memory, further records are dropped
+    #[serde(default = "default_access_log_buffer")]
+    pub buffer_size: usize,
+    // Seconds between writes of buffered records to the bucket
+    #[serde(default = "default_access_log_flush_interval")]
+    pub flush_interval: u64,
+}
+
+fn default_sample_rate() -> f64 {
+    1.0
+}
+
+fn default_access_log_buffer() -> usize {
+    10000
+}
+
+fn default_access_log_flush_interval() -> u64 {
+    60
+}
+
+impl AccessLog {
+    fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.sample_rate) {
+            bail!("access_log sample_rate must be between 0 and 1")
+        }
+        if self.buffer_size == 0 {
+            bail!("access_log buffer_size must be at least 1")
+        }
+        if self.flush_interval == 0 {
+            bail!("access_log flush_interval must be at least 1")
+        }
+        if self.target == AccessLogTarget::Bucket && self.bucket.is_none() {
+            bail!("access_log bucket must be set for target bucket")
+        }
+        if self.fields.as_ref().is_some_and(|fields| fields.is_empty()) {
+            bail!("access_log fields must not be empty")
+        }
+        Ok(())
+    }
+
+    pub fn get_fields(&self) -> Vec<AccessLogField> {
+        self.fields
+            .clone()
+            .unwrap_or_else(|| AccessLogField::ALL.to_vec())
+    }
+}
+
+/// Resume tokens of downloads which pin the object revision for resumed range requests
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResumeTokens {
+    pub secret: Option<String>,
+    // Seconds a download can be resumed with its token
+    #[serde(default = "default_resume_token_ttl")]
+    pub ttl: i64,
+}
+
+fn default_resume_token_ttl() -> i64 {
+    86400
+}
+
+impl ResumeTokens {
+    fn validate(&mut self) -> Result<()> {
+        if self.secret.is_none() {
+            let env_var = dotenvy::var("RESUME_TOKEN_SECRET").map_err(|e| {
+                tracing::error!(error = ?e, msg = e.to_string());
+                e
+            })?;
+            self.secret = Some(env_var);
+        }
+        if self.secret.as_ref().is_some_and(|secret| secret.len() < 32) {
+            bail!("Resume token secret must be at least 32 characters long")
+        }
+        if self.ttl < 1 {
+            bail!("resume_tokens ttl must be at least 1")
+        }
+        Ok(())
+    }
+
+    pub fn get_secret(&self) -> Result<&str> {
+        self.secret
+            .as_deref()
+            .ok_or_else(|| anyhow!("Resume token secret not set"))
+    }
+}
+
+/// Origin authentication for a CDN in front of the S3 frontend
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CdnOrigin {
+    pub secret: Option<String>,
+}
+
+impl CdnOrigin {
+    fn validate(&mut self) -> Result<()> {
+        if self.secret.is_none() {
+            let env_var = dotenvy::var("CDN_ORIGIN_SECRET").map_err(|e| {
+                tracing::error!(error = ?e, msg = e.to_string());
+                e
+            })?;
+            self.secret = Some(env_var);
+        }
+        if self.secret.as_ref().is_some_and(|secret| secret.len() < 32) {
+            bail!("CDN origin secret must be at least 32 characters long")
+        }
+        Ok(())
+    }
+
+    pub fn get_secret(&self) -> Result<&str> {
+        self.secret
+            .as_deref()
+            .ok_or_else(|| anyhow!("CDN origin secret not set"))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -185,6 +716,19 @@ pub enum Backend {
         dropbox_bucket: Option<String>,
         backend_scheme: String,
         tmp: Option<String>,
+        // Prefix for all object keys, allows multiple endpoints to share a bucket
+        object_prefix: Option<String>,
+        #[serde(default)]
+        download_mode: DownloadMode,
+        // Seconds until presigned urls of redirected downloads expire
+        #[serde(default = "default_redirect_expiry")]
+        redirect_expiry: u64,
+        // Watched file with the current access_key and secret_key, replaces the
+        // credentials of the environment and is reloaded on changes
+        credentials_file: Option<String>,
+        // Seconds between checks of the credentials file
+        #[serde(default = "default_credentials_interval")]
+        credentials_interval: u64,
     },
     FileSystem {
         root_path: String,
@@ -196,15 +740,73 @@ pub enum Backend {
     },
 }
 
+/// Additional backends objects can be moved to without changing their id,
+/// e.g. a cold S3 storage next to a fast filesystem
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageTiers {
+    // Backends by the name of their tier
+    pub backends: HashMap<String, Backend>,
+    // Seconds the source copy is kept for running downloads after a migration
+    #[serde(default = "default_migration_grace_period")]
+    pub grace_period: u64,
+}
+
+fn default_migration_grace_period() -> u64 {
+    600
+}
+
+impl StorageTiers {
+    fn validate(&mut self) -> Result<()> {
+        for (name, backend) in self.backends.iter_mut() {
+            if name.is_empty() {
+                bail!("storage_tiers names must not be empty")
+            }
+            backend.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// How the S3 frontend serves downloads
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DownloadMode {
+    // The proxy streams the data of all downloads
+    #[default]
+    Proxy,
+    // Downloads of objects which are neither encrypted nor compressed are redirected
+    // to a presigned url of the S3 host, the data of all other objects is still streamed
+    Redirect,
+}
+
+fn default_redirect_expiry() -> u64 {
+    300
+}
+
+fn default_credentials_interval() -> u64 {
+    30
+}
+
 impl Backend {
+    // Longest validity of presigned S3 urls (7 days)
+    const MAX_REDIRECT_EXPIRY: u64 = 604_800;
+
     fn validate(&mut self) -> Result<()> {
         match self {
             Self::S3 {
                 access_key,
                 secret_key,
                 host,
+                redirect_expiry,
                 ..
             } => {
+                if !(1..=Self::MAX_REDIRECT_EXPIRY).contains(redirect_expiry) {
+                    bail!(
+                        "redirect_expiry must be between 1 and {}",
+                        Self::MAX_REDIRECT_EXPIRY
+                    )
+                }
+
                 if host.is_none() {
                     let env_var = dotenvy::var("AWS_S3_HOST").map_err(|e| {
                         tracing::error!(error = ?e, msg = e.to_string());
@@ -243,6 +845,32 @@ impl Backend {
         }
     }
 
+    /// Returns the normalized object key prefix (without leading slash, with trailing slash)
+    pub fn get_object_prefix(&self) -> Option<String> {
+        match self {
+            Self::S3 {
+                object_prefix: Some(prefix),
+                ..
+            } => {
+                let prefix = prefix.trim_matches('/');
+                if prefix.is_empty() {
+                    None
+                } else {
+                    Some(format!("{}/", prefix))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Concurrent uploads of the same data with a declared checksum write it once
+    pub fn is_deduplicating(&self) -> bool {
+        match self {
+            Self::S3 { deduplication, .. } => *deduplication,
+            Self::FileSystem { .. } => false,
+        }
+    }
+
     #[allow(dead_code)]
     pub fn is_encrypted(&self) -> bool {
         match self {
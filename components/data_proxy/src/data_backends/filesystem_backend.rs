@@ -39,6 +39,12 @@ impl FSBackend {
     #[tracing::instrument(level = "debug")]
     #[allow(dead_code)]
     pub async fn new(_endpoint_id: String) -> Result<Self> {
+        Self::from_config(_endpoint_id, &CONFIG.backend).await
+    }
+
+    /// Creates the backend of the default or of an additional storage tier
+    #[tracing::instrument(level = "debug", skip(config))]
+    pub async fn from_config(_endpoint_id: String, config: &Backend) -> Result<Self> {
         let Backend::FileSystem {
             root_path,
             encryption,
@@ -46,7 +52,7 @@ impl FSBackend {
             dropbox_folder,
             backend_scheme,
             tmp,
-        } = &CONFIG.backend
+        } = config
         else {
             return Err(anyhow!("Invalid backend"));
         };
@@ -151,7 +157,7 @@ impl StorageBackend for FSBackend {
         let mut reader = tokio::io::BufReader::new(file);
         let mut buf = BytesMut::with_capacity(1024 * 16);
 
-        while reader.read_buf(&mut buf).await.is_ok() {
+        while reader.read_buf(&mut buf).await? > 0 {
             sender.send(Ok(buf.split().freeze())).await.map_err(|e| {
                 tracing::error!(error = ?e, msg = e.to_string());
                 e
@@ -249,6 +255,27 @@ impl StorageBackend for FSBackend {
         });
     }
 
+    #[tracing::instrument(level = "trace", skip(self, _location, upload_id))]
+    async fn head_multipart_part(
+        &self,
+        _location: ObjectLocation,
+        upload_id: String,
+        part_number: i32,
+    ) -> Result<i64> {
+        let len = tokio::fs::metadata(
+            Path::new(&self.base_path)
+                .join(&upload_id)
+                .join(format!(".{}.part", part_number)),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!(error = ?e, msg = e.to_string());
+            e
+        })?
+        .len() as i64;
+        Ok(len)
+    }
+
     #[tracing::instrument(level = "trace", skip(self, location, parts, upload_id))]
     async fn finish_multipart_upload(
         &self,
@@ -309,6 +336,16 @@ impl StorageBackend for FSBackend {
         self.check_and_create_bucket(bucket).await
     }
 
+    // Files can not be downloaded without the proxy
+    async fn presign_get_object(
+        &self,
+        _location: ObjectLocation,
+        _content_disposition: Option<String>,
+        _content_type: Option<String>,
+    ) -> Result<Option<String>> {
+        Ok(None)
+    }
+
     #[tracing::instrument(level = "trace", skip(self, location))]
     /// Delete a object from the storage system
     /// # Arguments
@@ -336,7 +373,8 @@ impl StorageBackend for FSBackend {
     ) -> Result<ObjectLocation> {
         if temp {
             // No pithos for temp
-            let file_format = FileFormat::from_bools(false, self.encryption, false);
+            let file_format =
+                FileFormat::from_choice(false, self.encryption, false, obj.get_encryption_choice());
             return Ok(ObjectLocation {
                 id: DieselUlid::generate(),
                 bucket: self.temp.clone(),
@@ -354,8 +392,12 @@ impl StorageBackend for FSBackend {
 
         let (bucket, key) = self.schema.to_names(names);
 
-        let file_format =
-            FileFormat::from_bools(self.use_pithos, self.encryption, self.compression);
+        let file_format = FileFormat::from_choice(
+            self.use_pithos,
+            self.encryption,
+            self.compression,
+            obj.get_encryption_choice(),
+        );
 
         Ok(ObjectLocation {
             id: DieselUlid::generate(),
@@ -0,0 +1,379 @@
+use crate::caching::cache::Cache;
+use crate::data_backends::storage_backend::StorageBackend;
+use crate::structs::{ObjectLocation, PartETag};
+use anyhow::{anyhow, bail, Result};
+use bytes::{Bytes, BytesMut};
+use diesel_ulid::DieselUlid;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+// Size of the parts written to the target tier, objects of up to 640 GB fit into 10000 parts
+const MIGRATION_PART_SIZE: usize = 64 * 1024 * 1024;
+
+/// Location of the stored bytes of `location` in another storage tier. Bucket, key
+/// and format stay the same, so that interrupted migrations find their copy again.
+pub fn tier_location(location: &ObjectLocation, tier: Option<String>) -> ObjectLocation {
+    ObjectLocation {
+        id: DieselUlid::generate(),
+        upload_id: None,
+        tier,
+        ..location.clone()
+    }
+}
+
+fn same_blob(a: &ObjectLocation, b: &ObjectLocation) -> bool {
+    a.tier == b.tier && a.bucket == b.bucket && a.key == b.key
+}
+
+/// Reads the stored bytes of a location, returns their size and sha256
+async fn hash_stored(
+    backend: &Arc<Box<dyn StorageBackend>>,
+    location: &ObjectLocation,
+) -> Result<(i64, String)> {
+    let (sender, receiver) = async_channel::bounded(10);
+    let read = backend.get_object(location.clone(), None, sender);
+    let hash = async move {
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        while let Ok(chunk) = receiver.recv().await {
+            let chunk = chunk.map_err(|e| anyhow!(e))?;
+            hasher.update(&chunk);
+            size += chunk.len() as i64;
+        }
+        Ok::<(i64, String), anyhow::Error>((size, hex::encode(hasher.finalize())))
+    };
+    let (read, hash) = tokio::join!(read, hash);
+    read.and(hash)
+}
+
+/// Checks the stored size and disk hash of a location, locations without
+/// disk hash can not be verified and are never complete
+async fn is_complete(backend: &Arc<Box<dyn StorageBackend>>, location: &ObjectLocation) -> bool {
+    let Some(expected) = &location.disk_hash else {
+        return false;
+    };
+    if !backend
+        .head_object(location.clone())
+        .await
+        .is_ok_and(|size| size == location.disk_content_len)
+    {
+        return false;
+    }
+    hash_stored(backend, location)
+        .await
+        .is_ok_and(|(size, sha256)| {
+            size == location.disk_content_len && expected.eq_ignore_ascii_case(&sha256)
+        })
+}
+
+/// Streams the stored bytes of `source` into `target`, larger objects are written
+/// as multipart upload. Returns the size and sha256 of the copied bytes.
+async fn copy_stored(
+    backend: &Arc<Box<dyn StorageBackend>>,
+    source: &ObjectLocation,
+    target: &ObjectLocation,
+    part_size: usize,
+) -> Result<(i64, String)> {
+    let (sender, receiver) = async_channel::bounded(10);
+    let read = backend.get_object(source.clone(), None, sender);
+    let write = async move {
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        let mut buffer = BytesMut::new();
+        let mut upload_id = None;
+        let mut etags = Vec::new();
+        loop {
+            let chunk = match receiver.recv().await {
+                Ok(chunk) => Some(chunk.map_err(|e| anyhow!(e))?),
+                Err(_) => None,
+            };
+            let finished = chunk.is_none();
+            if let Some(chunk) = chunk {
+                hasher.update(&chunk);
+                size += chunk.len() as i64;
+                buffer.extend_from_slice(&chunk);
+            }
+            // The last part of a multipart upload may be smaller
+            while buffer.len() >= part_size
+                || (finished && upload_id.is_some() && !buffer.is_empty())
+            {
+                let part = buffer.split_to(part_size.min(buffer.len())).freeze();
+                let id = match &upload_id {
+                    Some(id) => id.clone(),
+                    None => {
+                        let id = backend.init_multipart_upload(target.clone()).await?;
+                        upload_id = Some(id.clone());
+                        id
+                    }
+                };
+                let part_len = part.len() as i64;
+                etags.push(
+                    backend
+                        .upload_multi_object(
+                            single_chunk(part),
+                            target.clone(),
+                            id,
+                            part_len,
+                            etags.len() as i32 + 1,
+                        )
+                        .await?,
+                );
+            }
+            if finished {
+                break;
+            }
+        }
+        match upload_id {
+            Some(upload_id) => {
+                backend
+                    .finish_multipart_upload(target.clone(), etags, upload_id)
+                    .await?
+            }
+            None => {
+                let buffer = buffer.freeze();
+                let content_len = buffer.len() as i64;
+                backend
+                    .put_object(single_chunk(buffer), target.clone(), content_len)
+                    .await?
+            }
+        }
+        Ok::<(i64, String), anyhow::Error>((size, hex::encode(hasher.finalize())))
+    };
+    let (read, write) = tokio::join!(read, write);
+    read.and(write)
+}
+
+fn single_chunk(chunk: Bytes) -> async_channel::Receiver<Result<Bytes>> {
+    let (sender, receiver) = async_channel::bounded(1);
+    // Capacity is sufficient for the single chunk
+    let _ = sender.try_send(Ok(chunk));
+    receiver
+}
+
+async fn migrate_location_with(
+    backend: Arc<Box<dyn StorageBackend>>,
+    location: &ObjectLocation,
+    tier: Option<String>,
+    part_size: usize,
+) -> Result<ObjectLocation> {
+    if location.tier == tier {
+        bail!("Object is already stored in this tier");
+    }
+    let target = tier_location(location, tier);
+    if is_complete(&backend, &target).await {
+        debug!(?target, "Resuming migration with complete copy");
+        return Ok(target);
+    }
+
+    let verified = copy_stored(&backend, location, &target, part_size)
+        .await
+        .and_then(|(size, sha256)| {
+            if size != location.disk_content_len {
+                bail!(
+                    "Migrated {size} bytes instead of {}",
+                    location.disk_content_len
+                );
+            }
+            if let Some(expected) = &location.disk_hash {
+                if !expected.eq_ignore_ascii_case(&sha256) {
+                    bail!("Migrated data has sha256 {sha256} instead of {expected}");
+                }
+            }
+            Ok(())
+        });
+    if let Err(e) = verified {
+        // The source stays in use
+        if let Err(err) = backend.delete_object(target).await {
+            error!(error = ?err, "Unable to remove incomplete migration");
+        }
+        return Err(e);
+    }
+    Ok(target)
+}
+
+/// Copies the stored bytes of a location unchanged into a storage tier (`None` is
+/// the default backend). The copy is verified against the stored size and disk hash
+/// and removed again if it does not match. A complete copy of an interrupted
+/// migration is verified and reused.
+pub async fn migrate_location(
+    backend: Arc<Box<dyn StorageBackend>>,
+    location: &ObjectLocation,
+    tier: Option<String>,
+) -> Result<ObjectLocation> {
+    migrate_location_with(backend, location, tier, MIGRATION_PART_SIZE).await
+}
+
+/// Moves the data of an object into a storage tier and swaps the location once the
+/// copy is verified. Downloads which already resolved the source keep reading it
+/// until it is deleted after the grace period. Objects which are already stored in
+/// the tier are returned unchanged, so failed migrations can simply be repeated.
+pub async fn migrate_object(
+    cache: Arc<Cache>,
+    backend: Arc<Box<dyn StorageBackend>>,
+    object_id: DieselUlid,
+    tier: Option<String>,
+    grace_period: Duration,
+) -> Result<ObjectLocation> {
+    let location = cache
+        .get_location(&object_id)
+        .await
+        .ok_or_else(|| anyhow!("Location not found"))?;
+    if location.tier == tier {
+        debug!(?object_id, ?tier, "Object is already stored in this tier");
+        return Ok(location);
+    }
+    if location.is_temporary || location.ref_count > 1 {
+        bail!("Only finished objects which do not share their data can be migrated");
+    }
+
+    let new_location = migrate_location(backend.clone(), &location, tier).await?;
+    if cache
+        .get_location(&object_id)
+        .await
+        .map(|current| current.id)
+        != Some(location.id)
+    {
+        // The object was changed while the copy was written
+        backend.delete_object(new_location).await?;
+        bail!("Location changed during migration");
+    }
+    cache
+        .update_location(object_id, new_location.clone())
+        .await?;
+    info!(
+        ?object_id,
+        from = ?location.tier,
+        to = ?new_location.tier,
+        size = location.disk_content_len,
+        "Migrated object"
+    );
+
+    tokio::spawn(async move {
+        tokio::time::sleep(grace_period).await;
+        // The object may have been migrated back into the source meanwhile
+        if cache
+            .get_location(&object_id)
+            .await
+            .is_some_and(|current| same_blob(&current, &location))
+        {
+            return;
+        }
+        if let Err(e) = backend.delete_object(location).await {
+            error!(error = ?e, ?object_id, "Unable to remove migrated source");
+        }
+    });
+    Ok(new_location)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Backend;
+    use crate::data_backends::mock_backend::MockBackend;
+    use crate::data_backends::{filesystem_backend::FSBackend, tiered_backend::TieredBackend};
+    use std::collections::HashMap;
+    use std::sync::atomic::Ordering;
+
+    async fn download(
+        backend: &Arc<Box<dyn StorageBackend>>,
+        location: &ObjectLocation,
+    ) -> Result<Vec<u8>> {
+        let (sender, receiver) = async_channel::unbounded();
+        backend.get_object(location.clone(), None, sender).await?;
+        let mut data = Vec::new();
+        while let Ok(chunk) = receiver.recv().await {
+            data.extend_from_slice(&chunk.map_err(|e| anyhow!(e))?);
+        }
+        Ok(data)
+    }
+
+    #[tokio::test]
+    async fn test_migrate_between_filesystem_and_s3() {
+        let root = std::env::temp_dir().join(format!("migration-{}", DieselUlid::generate()));
+        let filesystem = FSBackend::from_config(
+            "endpoint".to_string(),
+            &Backend::FileSystem {
+                root_path: root.to_string_lossy().to_string(),
+                encryption: false,
+                compression: false,
+                dropbox_folder: None,
+                backend_scheme: "file://{{PROJECT_NAME}}/{{OBJECT_NAME}}".to_string(),
+                tmp: None,
+            },
+        )
+        .await
+        .unwrap();
+        let s3 = MockBackend::default();
+        let backend: Arc<Box<dyn StorageBackend>> = Arc::new(Box::new(TieredBackend::new(
+            Box::new(filesystem),
+            HashMap::from([(
+                "cold".to_string(),
+                Box::new(s3.clone()) as Box<dyn StorageBackend>,
+            )]),
+        )));
+
+        let data = (0..250_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let mut location = ObjectLocation {
+            id: DieselUlid::generate(),
+            bucket: "project".to_string(),
+            key: "object".to_string(),
+            raw_content_len: data.len() as i64,
+            disk_content_len: data.len() as i64,
+            disk_hash: Some(hex::encode(Sha256::digest(&data))),
+            ..Default::default()
+        };
+        backend
+            .put_object(
+                single_chunk(Bytes::from(data.clone())),
+                location.clone(),
+                data.len() as i64,
+            )
+            .await
+            .unwrap();
+
+        // Moved into the cold tier in multiple parts, the source is still readable
+        let cold = migrate_location_with(backend.clone(), &location, Some("cold".into()), 100_000)
+            .await
+            .unwrap();
+        assert_ne!(cold.id, location.id);
+        assert_eq!(cold.tier.as_deref(), Some("cold"));
+        assert_eq!(s3.writes.load(Ordering::SeqCst), 1);
+        assert_eq!(download(&backend, &cold).await.unwrap(), data);
+        assert_eq!(download(&backend, &location).await.unwrap(), data);
+
+        // A repeated migration reuses the verified copy
+        let resumed = migrate_location(backend.clone(), &location, Some("cold".into()))
+            .await
+            .unwrap();
+        assert_eq!(resumed.key, cold.key);
+        assert_eq!(s3.writes.load(Ordering::SeqCst), 1);
+
+        // And back into the filesystem once the source was removed
+        backend.delete_object(location.clone()).await.unwrap();
+        assert!(download(&backend, &location).await.is_err());
+        let hot = migrate_location(backend.clone(), &cold, None)
+            .await
+            .unwrap();
+        assert_eq!(hot.tier, None);
+        assert_eq!(download(&backend, &hot).await.unwrap(), data);
+
+        // Copies which do not match the stored hash are discarded
+        location.disk_hash = Some(hex::encode(Sha256::digest(b"other")));
+        s3.delete_object(cold.clone()).await.unwrap();
+        assert_eq!(s3.object_count(), 0);
+        assert!(
+            migrate_location(backend.clone(), &location, Some("cold".into()))
+                .await
+                .is_err()
+        );
+        assert_eq!(s3.object_count(), 0);
+        assert!(migrate_location(backend.clone(), &hot, None).await.is_err());
+        assert!(migrate_location(backend, &location, Some("unknown".into()))
+            .await
+            .is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
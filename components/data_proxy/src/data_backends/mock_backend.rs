@@ -0,0 +1,220 @@
+use crate::data_backends::storage_backend::StorageBackend;
+use crate::structs::{Object, ObjectLocation, PartETag};
+use anyhow::{anyhow, bail, Result};
+use async_channel::{Receiver, Sender};
+use bytes::{Bytes, BytesMut};
+use diesel_ulid::DieselUlid;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// In-memory object storage for tests, clones share the stored data.
+/// Objects keep the parts they were written in and are addressed by bucket and key.
+#[derive(Debug, Default, Clone)]
+pub struct MockBackend {
+    objects: Arc<Mutex<HashMap<String, Vec<Bytes>>>>,
+    uploads: Arc<Mutex<HashMap<String, BTreeMap<i32, Bytes>>>>,
+    /// Number of completed object writes
+    pub writes: Arc<AtomicUsize>,
+    running: Arc<AtomicUsize>,
+    /// Maximum of parallel part lookups
+    pub max_running: Arc<AtomicUsize>,
+    // Completions fail after writing this many bytes
+    fail_finish_after: Option<i64>,
+}
+
+fn path(location: &ObjectLocation) -> String {
+    format!("{}/{}", location.bucket, location.key)
+}
+
+async fn collect(recv: Receiver<Result<Bytes>>) -> Result<Bytes> {
+    let mut data = BytesMut::new();
+    while let Ok(chunk) = recv.recv().await {
+        data.extend_from_slice(&chunk?);
+    }
+    Ok(data.freeze())
+}
+
+impl MockBackend {
+    /// Parts of the object stored at the location
+    pub fn parts(&self, location: &ObjectLocation) -> Option<Vec<Bytes>> {
+        self.objects.lock().unwrap().get(&path(location)).cloned()
+    }
+
+    /// Number of stored objects
+    pub fn object_count(&self) -> usize {
+        self.objects.lock().unwrap().len()
+    }
+
+    /// Lets completions of multipart uploads fail after writing `written` bytes
+    pub fn with_failing_finish(self, written: Option<i64>) -> Self {
+        MockBackend {
+            fail_finish_after: written,
+            ..self
+        }
+    }
+
+    /// Adds an uploaded part of `size` bytes to an upload
+    pub fn add_part(&self, upload_id: &str, part_number: i32, size: usize) {
+        self.uploads
+            .lock()
+            .unwrap()
+            .entry(upload_id.to_string())
+            .or_default()
+            .insert(part_number, Bytes::from(vec![0; size]));
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageBackend for MockBackend {
+    async fn put_object(
+        &self,
+        recv: Receiver<Result<Bytes>>,
+        location: ObjectLocation,
+        content_len: i64,
+    ) -> Result<()> {
+        let data = collect(recv).await?;
+        if data.len() as i64 != content_len {
+            bail!("Unexpected content length");
+        }
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        self.objects
+            .lock()
+            .unwrap()
+            .insert(path(&location), vec![data]);
+        Ok(())
+    }
+
+    async fn get_object(
+        &self,
+        location: ObjectLocation,
+        _range: Option<String>,
+        sender: Sender<Result<Bytes, Box<dyn std::error::Error + Send + Sync>>>,
+    ) -> Result<()> {
+        let parts = self.parts(&location).ok_or_else(|| anyhow!("NoSuchKey"))?;
+        for part in parts {
+            for chunk in part.chunks(1000) {
+                sender.send(Ok(Bytes::copy_from_slice(chunk))).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn head_object(&self, location: ObjectLocation) -> Result<i64> {
+        let parts = self.parts(&location).ok_or_else(|| anyhow!("NoSuchKey"))?;
+        Ok(parts.iter().map(|part| part.len() as i64).sum())
+    }
+
+    async fn presign_get_object(
+        &self,
+        _location: ObjectLocation,
+        _content_disposition: Option<String>,
+        _content_type: Option<String>,
+    ) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    async fn init_multipart_upload(&self, _location: ObjectLocation) -> Result<String> {
+        let upload_id = DieselUlid::generate().to_string();
+        self.uploads
+            .lock()
+            .unwrap()
+            .insert(upload_id.clone(), BTreeMap::new());
+        Ok(upload_id)
+    }
+
+    async fn upload_multi_object(
+        &self,
+        recv: Receiver<Result<Bytes>>,
+        _location: ObjectLocation,
+        upload_id: String,
+        _content_len: i64,
+        part_number: i32,
+    ) -> Result<PartETag> {
+        let data = collect(recv).await?;
+        self.uploads
+            .lock()
+            .unwrap()
+            .get_mut(&upload_id)
+            .ok_or_else(|| anyhow!("NoSuchUpload"))?
+            .insert(part_number, data);
+        Ok(PartETag {
+            part_number,
+            etag: format!("etag-{part_number}"),
+        })
+    }
+
+    async fn head_multipart_part(
+        &self,
+        _location: ObjectLocation,
+        upload_id: String,
+        part_number: i32,
+    ) -> Result<i64> {
+        let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_running.fetch_max(running, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        self.uploads
+            .lock()
+            .unwrap()
+            .get(&upload_id)
+            .and_then(|upload| upload.get(&part_number))
+            .map(|part| part.len() as i64)
+            .ok_or_else(|| anyhow!("NoSuchPart"))
+    }
+
+    async fn finish_multipart_upload(
+        &self,
+        location: ObjectLocation,
+        parts: Vec<PartETag>,
+        upload_id: String,
+    ) -> Result<()> {
+        let mut uploads = self.uploads.lock().unwrap();
+        let uploaded = uploads
+            .get(&upload_id)
+            .ok_or_else(|| anyhow!("NoSuchUpload"))?;
+        assert_eq!(parts.len(), uploaded.len());
+        if let Some(written) = self.fail_finish_after {
+            // The parts are kept for a retry
+            self.objects.lock().unwrap().insert(
+                path(&location),
+                vec![Bytes::from(vec![0; written as usize])],
+            );
+            bail!("Backend failed during completion");
+        }
+        let uploaded = uploads.remove(&upload_id).unwrap_or_default();
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        self.objects
+            .lock()
+            .unwrap()
+            .insert(path(&location), uploaded.into_values().collect());
+        Ok(())
+    }
+
+    async fn create_bucket(&self, _bucket: String) -> Result<()> {
+        Ok(())
+    }
+
+    async fn delete_object(&self, location: ObjectLocation) -> Result<()> {
+        self.objects.lock().unwrap().remove(&path(&location));
+        Ok(())
+    }
+
+    async fn initialize_location(
+        &self,
+        obj: &Object,
+        expected_size: Option<i64>,
+        _names: [Option<(DieselUlid, String)>; 4],
+        temp: bool,
+    ) -> Result<ObjectLocation> {
+        Ok(ObjectLocation {
+            id: DieselUlid::generate(),
+            bucket: if temp { "temp" } else { "mock" }.to_string(),
+            key: obj.id.to_string().to_ascii_lowercase(),
+            raw_content_len: expected_size.unwrap_or_default(),
+            is_temporary: temp,
+            ..Default::default()
+        })
+    }
+}
@@ -1,4 +1,8 @@
 pub mod filesystem_backend;
 pub mod location_handler;
+pub mod migration;
+#[cfg(test)]
+pub mod mock_backend;
 pub mod s3_backend;
 pub mod storage_backend;
+pub mod tiered_backend;
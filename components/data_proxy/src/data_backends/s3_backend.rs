@@ -1,6 +1,6 @@
 use super::location_handler::CompiledVariant;
 use super::storage_backend::StorageBackend;
-use crate::config::Backend;
+use crate::config::{Backend, DownloadMode};
 use crate::helpers::random_string;
 use crate::structs::FileFormat;
 use crate::structs::Object;
@@ -11,21 +11,128 @@ use anyhow::anyhow;
 use anyhow::Result;
 use async_channel::{Receiver, Sender};
 use async_trait::async_trait;
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::presigning::PresigningConfig;
 use aws_sdk_s3::primitives::SdkBody;
 use aws_sdk_s3::{
-    config::Region,
+    config::{Credentials, Region},
     primitives::ByteStream,
     types::{CompletedMultipartUpload, CompletedPart},
     Client,
 };
 use diesel_ulid::DieselUlid;
 use rand::random;
-use tracing::error;
+use serde::Deserialize;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Content of a watched credentials file
+#[derive(Clone, Deserialize)]
+pub struct S3Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+/// S3 client which can be replaced at runtime to rotate credentials.
+/// Every request uses a clone of the current client, so requests which
+/// are already running finish with the client they were started with.
+#[derive(Debug, Clone)]
+pub struct S3ClientHandle {
+    client: Arc<RwLock<Client>>,
+    // Bucket of the HeadBucket request which validates new credentials
+    validation_bucket: String,
+}
+
+impl S3ClientHandle {
+    pub fn new(client: Client, validation_bucket: String) -> Self {
+        S3ClientHandle {
+            client: Arc::new(RwLock::new(client)),
+            validation_bucket,
+        }
+    }
+
+    pub fn get(&self) -> Client {
+        // Poisoned lock can only occur on panic while holding the lock
+        self.client.read().unwrap().clone()
+    }
+
+    /// Replaces the client if a HeadBucket request with the new client succeeds,
+    /// otherwise the current client stays in use
+    pub async fn replace(&self, client: Client) -> Result<()> {
+        match client
+            .head_bucket()
+            .bucket(&self.validation_bucket)
+            .send()
+            .await
+        {
+            Ok(_) => {}
+            // Missing buckets are created on demand, the request itself was authorized
+            Err(err) if err.as_service_error().is_some_and(|err| err.is_not_found()) => {}
+            Err(err) => {
+                return Err(anyhow!(
+                    "Validation of the new S3 client failed: {}",
+                    DisplayErrorContext(&err)
+                ))
+            }
+        }
+        *self.client.write().unwrap() = client;
+        Ok(())
+    }
+
+    /// Replaces the client with one using the given static credentials
+    pub async fn rotate_credentials(&self, credentials: S3Credentials) -> Result<()> {
+        let config = self
+            .get()
+            .config()
+            .to_builder()
+            .credentials_provider(Credentials::new(
+                credentials.access_key,
+                credentials.secret_key,
+                None,
+                None,
+                "credentials-file",
+            ))
+            .build();
+        self.replace(Client::from_conf(config)).await
+    }
+
+    /// Checks the credentials file periodically and rotates the credentials if it changed
+    pub fn watch_credentials_file(&self, path: String, interval: Duration) {
+        let handle = self.clone();
+        tokio::spawn(async move {
+            let mut last_modified = None;
+            loop {
+                match std::fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                    Ok(modified) if Some(modified) != last_modified => {
+                        last_modified = Some(modified);
+                        match handle.rotate_from_file(&path).await {
+                            Ok(()) => info!(path, "S3 credentials reloaded"),
+                            Err(err) => error!(
+                                path,
+                                error = ?err,
+                                "S3 credentials not reloaded, keeping the current credentials"
+                            ),
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => error!(path, error = ?err, "Error reading S3 credentials file"),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    async fn rotate_from_file(&self, path: &str) -> Result<()> {
+        let credentials = toml::from_str(&tokio::fs::read_to_string(path).await?)?;
+        self.rotate_credentials(credentials).await
+    }
+}
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct S3Backend {
-    pub s3_client: Client,
+    pub s3_client: S3ClientHandle,
     endpoint_id: String,
     temp: String,
     schema: CompiledVariant,
@@ -33,11 +140,20 @@ pub struct S3Backend {
     encryption: bool,
     compression: bool,
     dropbox: Option<String>,
+    object_prefix: Option<String>,
+    download_mode: DownloadMode,
+    redirect_expiry: Duration,
 }
 
 impl S3Backend {
     #[tracing::instrument]
     pub async fn new(endpoint_id: String) -> Result<Self> {
+        Self::from_config(endpoint_id, &CONFIG.backend).await
+    }
+
+    /// Creates the backend of the default or of an additional storage tier
+    #[tracing::instrument(skip(config))]
+    pub async fn from_config(endpoint_id: String, config: &Backend) -> Result<Self> {
         let Backend::S3 {
             tmp,
             backend_scheme,
@@ -46,8 +162,12 @@ impl S3Backend {
             compression,
             dropbox_bucket,
             force_path_style,
+            download_mode,
+            redirect_expiry,
+            credentials_file,
+            credentials_interval,
             ..
-        } = &CONFIG.backend
+        } = config
         else {
             return Err(anyhow!("Invalid backend"));
         };
@@ -75,7 +195,13 @@ impl S3Backend {
                 .build(),
         };
 
-        let s3_client = Client::from_conf(s3_config);
+        let s3_client = S3ClientHandle::new(Client::from_conf(s3_config), temp.clone());
+        if let Some(path) = credentials_file {
+            s3_client.watch_credentials_file(
+                path.clone(),
+                Duration::from_secs((*credentials_interval).max(1)),
+            );
+        }
 
         let handler = S3Backend {
             s3_client,
@@ -86,6 +212,9 @@ impl S3Backend {
             encryption: *encryption,
             compression: *compression,
             dropbox: dropbox_bucket.clone(),
+            object_prefix: config.get_object_prefix(),
+            download_mode: *download_mode,
+            redirect_expiry: Duration::from_secs(*redirect_expiry),
         };
         Ok(handler)
     }
@@ -112,9 +241,10 @@ impl StorageBackend for S3Backend {
 
         match self
             .s3_client
+            .get()
             .put_object()
             .set_bucket(Some(location.bucket))
-            .set_key(Some(location.key))
+            .set_key(Some(self.prefixed_key(&location.key)))
             .set_content_length(Some(content_len))
             .body(bytestream)
             .send()
@@ -140,14 +270,29 @@ impl StorageBackend for S3Backend {
         range: Option<String>,
         sender: Sender<Result<bytes::Bytes, Box<dyn std::error::Error + Send + Sync>>>,
     ) -> Result<()> {
-        let object = self
-            .s3_client
-            .get_object()
-            .set_bucket(Some(location.bucket))
-            .set_key(Some(location.key))
-            .set_range(range);
+        let client = self.s3_client.get();
+        let request = |key: String| {
+            client
+                .get_object()
+                .bucket(&location.bucket)
+                .key(key)
+                .set_range(range.clone())
+                .send()
+        };
+        let mut result = request(self.prefixed_key(&location.key)).await;
+        // Objects written before the object prefix was configured use their plain key
+        let missing = match &result {
+            Err(err) => err
+                .as_service_error()
+                .is_some_and(|err| err.is_no_such_key()),
+            Ok(_) => false,
+        };
+        if missing && self.object_prefix.is_some() {
+            tracing::debug!(key = %location.key, "Using legacy object key without prefix");
+            result = request(location.key.clone()).await;
+        }
 
-        let mut object_request = match object.send().await {
+        let mut object_request = match result {
             Ok(value) => value,
             Err(err) => {
                 error!(error = ?err, "Error getting object");
@@ -173,18 +318,63 @@ impl StorageBackend for S3Backend {
 
     #[tracing::instrument(level = "trace", skip(self, location))]
     async fn head_object(&self, location: ObjectLocation) -> Result<i64> {
-        let object = self
+        let client = self.s3_client.get();
+        let request = |key: String| {
+            client
+                .head_object()
+                .bucket(&location.bucket)
+                .key(key)
+                .send()
+        };
+        let mut result = request(self.prefixed_key(&location.key)).await;
+        // Objects written before the object prefix was configured use their plain key
+        let missing = match &result {
+            Err(err) => err.as_service_error().is_some_and(|err| err.is_not_found()),
+            Ok(_) => false,
+        };
+        if missing && self.object_prefix.is_some() {
+            tracing::debug!(key = %location.key, "Using legacy object key without prefix");
+            result = request(location.key.clone()).await;
+        }
+        let object = result.map_err(|e| {
+            error!(error = ?e, msg = e.to_string());
+            e
+        })?;
+        Ok(object.content_length().unwrap_or_default())
+    }
+
+    // Presigned urls are only created if downloads are redirected to the s3 host
+    #[tracing::instrument(level = "trace", skip(self, location))]
+    async fn presign_get_object(
+        &self,
+        location: ObjectLocation,
+        content_disposition: Option<String>,
+        content_type: Option<String>,
+    ) -> Result<Option<String>> {
+        if self.download_mode != DownloadMode::Redirect {
+            return Ok(None);
+        }
+        // Presigned urls can not fall back to the plain key, objects written before the
+        // object prefix was configured are downloaded through the proxy instead
+        let key = self.prefixed_key(&location.key);
+        if self.object_prefix.is_some() && !self.key_exists(&location.bucket, &key).await {
+            return Ok(None);
+        }
+        let request = self
             .s3_client
-            .head_object()
-            .set_bucket(Some(location.bucket))
-            .set_key(Some(location.key))
-            .send()
+            .get()
+            .get_object()
+            .bucket(location.bucket)
+            .key(key)
+            .set_response_content_disposition(content_disposition)
+            .set_response_content_type(content_type)
+            .presigned(PresigningConfig::expires_in(self.redirect_expiry)?)
             .await
             .map_err(|e| {
                 error!(error = ?e, msg = e.to_string());
                 e
             })?;
-        Ok(object.content_length().unwrap_or_default())
+        Ok(Some(request.uri().to_string()))
     }
 
     // Initiates a multipart upload in s3 and returns the associated upload id.
@@ -195,9 +385,10 @@ impl StorageBackend for S3Backend {
 
         let multipart = self
             .s3_client
+            .get()
             .create_multipart_upload()
             .set_bucket(Some(location.bucket))
-            .set_key(Some(location.key))
+            .set_key(Some(self.prefixed_key(&location.key)))
             .send()
             .await
             .map_err(|e| {
@@ -222,9 +413,10 @@ impl StorageBackend for S3Backend {
 
         let upload = self
             .s3_client
+            .get()
             .upload_part()
             .set_bucket(Some(location.bucket))
-            .set_key(Some(location.key))
+            .set_key(Some(self.prefixed_key(&location.key)))
             .set_part_number(Some(part_number))
             .set_content_length(Some(content_len))
             .set_upload_id(Some(upload_id))
@@ -245,6 +437,40 @@ impl StorageBackend for S3Backend {
         });
     }
 
+    // Lists exactly the requested part, S3 has no head request for single parts
+    #[tracing::instrument(level = "trace", skip(self, location, upload_id))]
+    async fn head_multipart_part(
+        &self,
+        location: ObjectLocation,
+        upload_id: String,
+        part_number: i32,
+    ) -> Result<i64> {
+        let parts = self
+            .s3_client
+            .get()
+            .list_parts()
+            .bucket(location.bucket)
+            .key(self.prefixed_key(&location.key))
+            .upload_id(upload_id)
+            .part_number_marker((part_number - 1).to_string())
+            .max_parts(1)
+            .send()
+            .await
+            .map_err(|e| {
+                error!(error = ?e, msg = e.to_string());
+                e
+            })?;
+        parts
+            .parts()
+            .iter()
+            .find(|part| part.part_number() == Some(part_number))
+            .and_then(|part| part.size())
+            .ok_or_else(|| {
+                error!(part_number, "Part not found");
+                anyhow!("Part {part_number} not found")
+            })
+    }
+
     #[tracing::instrument(level = "trace", skip(self, location, parts))]
     async fn finish_multipart_upload(
         &self,
@@ -266,9 +492,10 @@ impl StorageBackend for S3Backend {
 
         match self
             .s3_client
+            .get()
             .complete_multipart_upload()
             .bucket(location.bucket)
-            .key(location.key)
+            .key(self.prefixed_key(&location.key))
             .upload_id(upload_id)
             .multipart_upload(
                 CompletedMultipartUpload::builder()
@@ -296,16 +523,25 @@ impl StorageBackend for S3Backend {
     /// # Arguments
     /// * `location` - The location of the object
     async fn delete_object(&self, location: ObjectLocation) -> Result<()> {
-        self.s3_client
-            .delete_object()
-            .bucket(location.bucket)
-            .key(location.key)
-            .send()
-            .await
-            .map_err(|e| {
-                error!(error = ?e, msg = e.to_string());
-                e
-            })?;
+        // Deletes succeed for missing keys, so objects still stored under their plain key
+        // are deleted with it as well
+        let mut keys = vec![self.prefixed_key(&location.key)];
+        if self.object_prefix.is_some() {
+            keys.push(location.key.clone());
+        }
+        for key in keys {
+            self.s3_client
+                .get()
+                .delete_object()
+                .bucket(&location.bucket)
+                .key(key)
+                .send()
+                .await
+                .map_err(|e| {
+                    error!(error = ?e, msg = e.to_string());
+                    e
+                })?;
+        }
         Ok(())
     }
 
@@ -321,7 +557,8 @@ impl StorageBackend for S3Backend {
     ) -> Result<ObjectLocation> {
         if temp {
             // No pithos for temp
-            let file_format = FileFormat::from_bools(false, self.encryption, false);
+            let file_format =
+                FileFormat::from_choice(false, self.encryption, false, obj.get_encryption_choice());
             return Ok(ObjectLocation {
                 id: DieselUlid::generate(),
                 bucket: self.temp.clone(),
@@ -339,8 +576,12 @@ impl StorageBackend for S3Backend {
 
         let (bucket, key) = self.schema.to_names(names);
 
-        let file_format =
-            FileFormat::from_bools(self.use_pithos, self.encryption, self.compression);
+        let file_format = FileFormat::from_choice(
+            self.use_pithos,
+            self.encryption,
+            self.compression,
+            obj.get_encryption_choice(),
+        );
 
         Ok(ObjectLocation {
             id: DieselUlid::generate(),
@@ -358,13 +599,21 @@ impl S3Backend {
     pub async fn check_and_create_bucket(&self, bucket: String) -> Result<()> {
         match self
             .s3_client
+            .get()
             .get_bucket_location()
             .bucket(bucket.clone())
             .send()
             .await
         {
             Ok(_) => Ok(()),
-            Err(e1) => match self.s3_client.create_bucket().bucket(bucket).send().await {
+            Err(e1) => match self
+                .s3_client
+                .get()
+                .create_bucket()
+                .bucket(bucket)
+                .send()
+                .await
+            {
                 Ok(_) => Ok(()),
                 Err(err) => {
                     error!(?e1, ?err, "Error creating bucket");
@@ -374,8 +623,137 @@ impl S3Backend {
         }
     }
 
+    /// Adds the configured object prefix to a key
+    pub fn prefixed_key(&self, key: &str) -> String {
+        prefix_key(self.object_prefix.as_deref(), key)
+    }
+
+    async fn key_exists(&self, bucket: &str, key: &str) -> bool {
+        self.s3_client
+            .get()
+            .head_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .is_ok()
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     pub fn get_random_bucket(&self) -> String {
         format!("{}-{:x}", self.endpoint_id, random::<u8>()).to_ascii_lowercase()
     }
 }
+
+fn prefix_key(prefix: Option<&str>, key: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{}{}", prefix, key),
+        None => key.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prefix_key, S3ClientHandle, S3Credentials};
+    use crate::config::{Backend, DownloadMode};
+    use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+    use aws_sdk_s3::Client;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server, StatusCode};
+    use std::collections::HashSet;
+    use std::convert::Infallible;
+    use std::sync::{Arc, Mutex};
+
+    /// S3 host which only authorizes requests signed with one of the `valid` access keys
+    fn mock_s3(valid: Arc<Mutex<HashSet<String>>>) -> String {
+        let make_service = make_service_fn(move |_| {
+            let valid = valid.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let authorization = request
+                        .headers()
+                        .get("authorization")
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string();
+                    let authorized = valid
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .any(|key| authorization.contains(&format!("Credential={key}/")));
+                    async move {
+                        let status = if authorized {
+                            StatusCode::OK
+                        } else {
+                            StatusCode::FORBIDDEN
+                        };
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(status)
+                                .body(Body::empty())
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let host = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        host
+    }
+
+    fn credentials(access_key: &str) -> S3Credentials {
+        S3Credentials {
+            access_key: access_key.to_string(),
+            secret_key: "secret".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_credentials_rotation() {
+        let valid = Arc::new(Mutex::new(HashSet::from(["old".to_string()])));
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("RegionOne"))
+            .endpoint_url(mock_s3(valid.clone()))
+            .force_path_style(true)
+            .credentials_provider(Credentials::new("old", "secret", None, None, "test"))
+            .build();
+        let handle = S3ClientHandle::new(Client::from_conf(config), "tmp".to_string());
+        let in_flight = handle.get();
+
+        // Unknown credentials are rejected and the current credentials stay in use
+        assert!(handle.rotate_credentials(credentials("new")).await.is_err());
+        assert!(handle.replace(handle.get()).await.is_ok());
+
+        // Valid credentials replace the client for new requests only
+        valid.lock().unwrap().insert("new".to_string());
+        handle.rotate_credentials(credentials("new")).await.unwrap();
+        valid.lock().unwrap().remove("old");
+        assert!(handle.replace(handle.get()).await.is_ok());
+        assert!(handle.replace(in_flight).await.is_err());
+    }
+
+    #[test]
+    fn test_object_prefix() {
+        let backend: Backend = toml::from_str(
+            r#"
+            [s3]
+            encryption = false
+            compression = false
+            deduplication = false
+            backend_scheme = "s3://{{PROJECT_ID}}/{{OBJECT_NAME}}"
+            object_prefix = "/endpoint-a/"
+            "#,
+        )
+        .unwrap();
+        let prefix = backend.get_object_prefix();
+        assert_eq!(prefix.as_deref(), Some("endpoint-a/"));
+        assert_eq!(
+            prefix_key(prefix.as_deref(), "coll/obj.txt"),
+            "endpoint-a/coll/obj.txt"
+        );
+        assert_eq!(prefix_key(None, "coll/obj.txt"), "coll/obj.txt");
+    }
+}
@@ -41,6 +41,20 @@ pub trait StorageBackend: Debug + Send + Sync {
     /// Gets meta information about a specific object
     async fn head_object(&self, location: ObjectLocation) -> Result<i64>;
 
+    /// Creates a presigned url to download the stored data directly from the storage system.
+    /// Returns None if the backend does not redirect downloads
+    /// # Arguments
+    ///
+    /// * `location` - The location of the object
+    /// * `content_disposition` - Content-Disposition returned by the storage system
+    /// * `content_type` - Content-Type returned by the storage system
+    async fn presign_get_object(
+        &self,
+        location: ObjectLocation,
+        content_disposition: Option<String>,
+        content_type: Option<String>,
+    ) -> Result<Option<String>>;
+
     /// Initiates a multipart upload.
     /// Returns the UploadID of the multipart upload
     /// This is modelled after other multipart upload mechanisms like from S3
@@ -68,6 +82,19 @@ pub trait StorageBackend: Debug + Send + Sync {
         part_number: i32,
     ) -> Result<PartETag>;
 
+    /// Gets the stored size of an uploaded part of a multipart upload
+    /// # Arguments
+    ///
+    /// * `location` - The location of the object
+    /// * `upload_id` - The upload id of the multipart uploads
+    /// * `part_number` - The number of the uploaded part
+    async fn head_multipart_part(
+        &self,
+        location: ObjectLocation,
+        upload_id: String,
+        part_number: i32,
+    ) -> Result<i64>;
+
     /// Finishes multipart uploads
     /// # Arguments
     ///
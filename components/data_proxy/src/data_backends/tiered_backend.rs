@@ -0,0 +1,161 @@
+use crate::config::{Backend, StorageTiers};
+use crate::data_backends::{
+    filesystem_backend::FSBackend, s3_backend::S3Backend, storage_backend::StorageBackend,
+};
+use crate::structs::{Object, ObjectLocation, PartETag};
+use anyhow::{anyhow, Result};
+use async_channel::{Receiver, Sender};
+use async_trait::async_trait;
+use diesel_ulid::DieselUlid;
+use std::collections::HashMap;
+
+/// Default backend of the proxy with additional storage tiers. Every call is
+/// served by the backend of the tier of its location, new locations are always
+/// initialized in the default backend.
+#[derive(Debug)]
+pub struct TieredBackend {
+    default: Box<dyn StorageBackend>,
+    tiers: HashMap<String, Box<dyn StorageBackend>>,
+}
+
+impl TieredBackend {
+    pub fn new(
+        default: Box<dyn StorageBackend>,
+        tiers: HashMap<String, Box<dyn StorageBackend>>,
+    ) -> Self {
+        TieredBackend { default, tiers }
+    }
+
+    /// Creates the backends of all configured storage tiers
+    pub async fn from_config(
+        endpoint_id: String,
+        default: Box<dyn StorageBackend>,
+        config: &StorageTiers,
+    ) -> Result<Self> {
+        let mut tiers: HashMap<String, Box<dyn StorageBackend>> = HashMap::new();
+        for (name, backend) in &config.backends {
+            let tier: Box<dyn StorageBackend> = match backend {
+                Backend::S3 { .. } => {
+                    Box::new(S3Backend::from_config(endpoint_id.clone(), backend).await?)
+                }
+                Backend::FileSystem { .. } => {
+                    Box::new(FSBackend::from_config(endpoint_id.clone(), backend).await?)
+                }
+            };
+            tiers.insert(name.clone(), tier);
+        }
+        Ok(Self::new(default, tiers))
+    }
+
+    fn backend(&self, location: &ObjectLocation) -> Result<&dyn StorageBackend> {
+        match &location.tier {
+            None => Ok(self.default.as_ref()),
+            Some(tier) => self
+                .tiers
+                .get(tier)
+                .map(|backend| backend.as_ref())
+                .ok_or_else(|| anyhow!("Unknown storage tier {tier}")),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for TieredBackend {
+    async fn put_object(
+        &self,
+        recv: Receiver<Result<bytes::Bytes>>,
+        location: ObjectLocation,
+        content_len: i64,
+    ) -> Result<()> {
+        self.backend(&location)?
+            .put_object(recv, location, content_len)
+            .await
+    }
+
+    async fn get_object(
+        &self,
+        location: ObjectLocation,
+        range: Option<String>,
+        sender: Sender<Result<bytes::Bytes, Box<dyn std::error::Error + Send + Sync>>>,
+    ) -> Result<()> {
+        self.backend(&location)?
+            .get_object(location, range, sender)
+            .await
+    }
+
+    async fn head_object(&self, location: ObjectLocation) -> Result<i64> {
+        self.backend(&location)?.head_object(location).await
+    }
+
+    async fn presign_get_object(
+        &self,
+        location: ObjectLocation,
+        content_disposition: Option<String>,
+        content_type: Option<String>,
+    ) -> Result<Option<String>> {
+        self.backend(&location)?
+            .presign_get_object(location, content_disposition, content_type)
+            .await
+    }
+
+    async fn init_multipart_upload(&self, location: ObjectLocation) -> Result<String> {
+        self.backend(&location)?
+            .init_multipart_upload(location)
+            .await
+    }
+
+    async fn upload_multi_object(
+        &self,
+        recv: Receiver<Result<bytes::Bytes>>,
+        location: ObjectLocation,
+        upload_id: String,
+        content_len: i64,
+        part_number: i32,
+    ) -> Result<PartETag> {
+        self.backend(&location)?
+            .upload_multi_object(recv, location, upload_id, content_len, part_number)
+            .await
+    }
+
+    async fn head_multipart_part(
+        &self,
+        location: ObjectLocation,
+        upload_id: String,
+        part_number: i32,
+    ) -> Result<i64> {
+        self.backend(&location)?
+            .head_multipart_part(location, upload_id, part_number)
+            .await
+    }
+
+    async fn finish_multipart_upload(
+        &self,
+        location: ObjectLocation,
+        parts: Vec<PartETag>,
+        upload_id: String,
+    ) -> Result<()> {
+        self.backend(&location)?
+            .finish_multipart_upload(location, parts, upload_id)
+            .await
+    }
+
+    async fn create_bucket(&self, bucket: String) -> Result<()> {
+        self.default.create_bucket(bucket).await
+    }
+
+    async fn delete_object(&self, location: ObjectLocation) -> Result<()> {
+        self.backend(&location)?.delete_object(location).await
+    }
+
+    async fn initialize_location(
+        &self,
+        obj: &Object,
+        expected_size: Option<i64>,
+        names: [Option<(DieselUlid, String)>; 4],
+        temp: bool,
+    ) -> Result<ObjectLocation> {
+        self.default
+            .initialize_location(obj, expected_size, names, temp)
+            .await
+    }
+}
@@ -8,6 +8,7 @@ use std::fmt::{Debug, Display, Formatter};
 use tokio_postgres::Client;
 use tracing::error;
 
+use crate::s3_frontend::utils::single_use::SingleUseLink;
 use crate::structs::LocationBinding;
 use crate::structs::UploadPart;
 
@@ -29,6 +30,8 @@ pub enum Table {
     ObjectLocations,
     Permissions,
     Multiparts,
+    #[cfg(feature = "row-ranges")]
+    LineIndexes,
 }
 
 impl Display for Table {
@@ -41,6 +44,8 @@ impl Display for Table {
             Table::ObjectLocations => write!(f, "object_locations"),
             Table::Permissions => write!(f, "permissions"),
             Table::Multiparts => write!(f, "multiparts"),
+            #[cfg(feature = "row-ranges")]
+            Table::LineIndexes => write!(f, "line_indexes"),
         }
     }
 }
@@ -220,6 +225,30 @@ pub async fn delete_parts_by_upload_id(client: &Client, upload_id: String) -> Re
     Ok(())
 }
 
+pub async fn insert_consumed_link(client: &Client, link: &SingleUseLink) -> Result<()> {
+    let query =
+        "INSERT INTO consumed_links (id, expires_at) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING;";
+    let prepared = client.prepare(query).await?;
+    client
+        .execute(&prepared, &[&link.id, &link.expires_at])
+        .await?;
+    Ok(())
+}
+
+/// Consumed single-use links which did not expire before `now`
+pub async fn get_consumed_links(client: &Client, now: i64) -> Result<Vec<SingleUseLink>> {
+    let query = "SELECT id, expires_at FROM consumed_links WHERE expires_at > $1;";
+    let prepared = client.prepare(query).await?;
+    let rows = client.query(&prepared, &[&now]).await?;
+    Ok(rows
+        .iter()
+        .map(|row| SingleUseLink {
+            id: row.get(0),
+            expires_at: row.get(1),
+        })
+        .collect())
+}
+
 impl LocationBinding {
     pub async fn insert_binding(&self, client: &Client) -> Result<()> {
         let query =
@@ -1,6 +1,8 @@
 use diesel_ulid::DieselUlid;
 use postgres_types::Json;
 
+#[cfg(feature = "row-ranges")]
+use crate::s3_frontend::utils::object_accessor::LineIndex;
 use crate::structs::{AccessKeyPermissions, Object, ObjectLocation, PubKey, UploadPart, User};
 
 use super::persistence::{GenericBytes, Table, WithGenericBytes};
@@ -168,3 +170,33 @@ impl TryInto<GenericBytes<DieselUlid, Self>> for UploadPart {
         })
     }
 }
+
+#[cfg(feature = "row-ranges")]
+impl TryFrom<GenericBytes<DieselUlid, Self>> for LineIndex {
+    type Error = anyhow::Error;
+    #[tracing::instrument(level = "trace", skip(value))]
+    fn try_from(value: GenericBytes<DieselUlid, Self>) -> Result<Self, Self::Error> {
+        Ok(value.data.0)
+    }
+}
+
+#[cfg(feature = "row-ranges")]
+impl TryInto<GenericBytes<DieselUlid, Self>> for LineIndex {
+    type Error = anyhow::Error;
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn try_into(self) -> Result<GenericBytes<DieselUlid, Self>, Self::Error> {
+        Ok(GenericBytes {
+            id: self.id,
+            data: Json(self),
+            table: Self::get_table(),
+        })
+    }
+}
+
+#[cfg(feature = "row-ranges")]
+impl WithGenericBytes<DieselUlid, Self> for LineIndex {
+    #[tracing::instrument(level = "trace", skip())]
+    fn get_table() -> Table {
+        Table::LineIndexes
+    }
+}
@@ -40,4 +40,15 @@ CREATE TABLE IF NOT EXISTS multiparts (
 CREATE TABLE IF NOT EXISTS permissions (
     id TEXT NOT NULL PRIMARY KEY, 
     data JSONB NOT NULL -- The actual data
-);
\ No newline at end of file
+);
+
+CREATE TABLE IF NOT EXISTS line_indexes (
+    id UUID NOT NULL PRIMARY KEY, -- The id of the indexed location
+    data JSONB NOT NULL, -- The actual data
+    CONSTRAINT fk_indexed_locations FOREIGN KEY (id) REFERENCES object_locations(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS consumed_links (
+    id TEXT NOT NULL PRIMARY KEY, -- The id of a single-use presigned url
+    expires_at BIGINT NOT NULL -- Unix timestamp of the url expiry
+);
@@ -0,0 +1,142 @@
+use crate::config::GrpcSettings;
+use anyhow::{anyhow, Result};
+use futures_util::{Stream, StreamExt};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::Sleep;
+use tonic::transport::server::{Connected, TcpIncoming};
+use tonic::transport::{Endpoint, Server};
+use tracing::info;
+
+/// Server builder with the configured HTTP/2 settings
+pub fn server_builder(settings: &GrpcSettings) -> Server {
+    info!(
+        keepalive_interval = ?settings.get_keepalive_interval(),
+        keepalive_timeout = ?settings.get_keepalive_timeout(),
+        keepalive_while_idle = settings.keepalive_while_idle,
+        max_concurrent_streams = ?settings.max_concurrent_streams,
+        initial_stream_window_size = ?settings.initial_stream_window_size,
+        initial_connection_window_size = ?settings.initial_connection_window_size,
+        max_connection_age = ?settings.get_max_connection_age(),
+        "grpc settings"
+    );
+    Server::builder()
+        .http2_keepalive_interval(settings.get_keepalive_interval())
+        .http2_keepalive_timeout(settings.get_keepalive_timeout())
+        .max_concurrent_streams(settings.max_concurrent_streams)
+        .initial_stream_window_size(settings.initial_stream_window_size)
+        .initial_connection_window_size(settings.initial_connection_window_size)
+}
+
+/// Applies the keepalive settings to a connection to the Aruna server
+pub fn with_keepalive(endpoint: Endpoint, settings: &GrpcSettings) -> Endpoint {
+    let Some(interval) = settings.get_keepalive_interval() else {
+        return endpoint;
+    };
+    let endpoint = endpoint
+        .http2_keep_alive_interval(interval)
+        .keep_alive_while_idle(settings.keepalive_while_idle);
+    match settings.get_keepalive_timeout() {
+        Some(timeout) => endpoint.keep_alive_timeout(timeout),
+        None => endpoint,
+    }
+}
+
+/// Accepted connections, which are closed after the maximum connection age
+pub fn incoming(
+    settings: &GrpcSettings,
+    addr: SocketAddr,
+) -> Result<
+    impl Stream<
+        Item = std::io::Result<
+            AgedConnection<impl AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static>,
+        >,
+    >,
+> {
+    let max_age = settings.get_max_connection_age();
+    let incoming = TcpIncoming::new(addr, false, None).map_err(|err| anyhow!(err))?;
+    Ok(incoming.map(move |conn| conn.map(|stream| AgedConnection::new(stream, max_age))))
+}
+
+/// Connection which reads EOF after its maximum age, which makes the server close it.
+/// Clients have to reconnect, e.g. to be balanced onto other instances.
+pub struct AgedConnection<IO> {
+    inner: IO,
+    deadline: Option<Pin<Box<Sleep>>>,
+}
+
+impl<IO> AgedConnection<IO> {
+    fn new(inner: IO, max_age: Option<Duration>) -> Self {
+        AgedConnection {
+            inner,
+            deadline: max_age.map(|age| Box::pin(tokio::time::sleep(age))),
+        }
+    }
+}
+
+impl<IO: Connected> Connected for AgedConnection<IO> {
+    type ConnectInfo = IO::ConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.inner.connect_info()
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for AgedConnection<IO> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if let Some(deadline) = self.deadline.as_mut() {
+            if deadline.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(Ok(()));
+            }
+        }
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for AgedConnection<IO> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_aged_connection() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut conn = AgedConnection::new(server, Some(Duration::from_millis(100)));
+
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        // Reads EOF after the maximum age although the peer is still connected
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        client.write_all(b"pong").await.unwrap();
+        assert_eq!(conn.read(&mut buf).await.unwrap(), 0);
+    }
+}
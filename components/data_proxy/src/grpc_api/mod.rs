@@ -1,4 +1,8 @@
 pub mod bundler;
+pub mod connections;
 pub mod ingestion_service;
 pub mod proxy_service;
+pub mod request_id;
+pub mod storage_migration;
+pub mod url_import;
 pub mod user_service;
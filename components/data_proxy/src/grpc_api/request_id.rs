@@ -0,0 +1,86 @@
+use diesel_ulid::DieselUlid;
+use std::task::{Context, Poll};
+use tonic::codegen::http::{HeaderValue, Request, Response};
+use tonic::codegen::{BoxFuture, Service};
+use tower::Layer;
+use tracing::info_span;
+use tracing::Instrument;
+
+pub const REQUEST_ID_KEY: &str = "x-request-id";
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Returns the correlation id of the request currently handled by this task
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Propagates the `x-request-id` of incoming requests (e.g. from the server)
+/// or mints a new one. The id is recorded in the tracing span of the request,
+/// forwarded with requests to the server and echoed back in the response metadata.
+#[derive(Debug, Clone, Default)]
+pub struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for RequestIdService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let request_id = request
+            .headers()
+            .get(REQUEST_ID_KEY)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LENGTH)
+            .map(str::to_string)
+            .unwrap_or_else(|| DieselUlid::generate().to_string());
+        let header = HeaderValue::from_str(&request_id).ok();
+        if let Some(header) = &header {
+            request.headers_mut().insert(REQUEST_ID_KEY, header.clone());
+        }
+
+        let span =
+            info_span!("grpc_request", request_id = %request_id, path = %request.uri().path());
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(
+            REQUEST_ID.scope(
+                request_id,
+                async move {
+                    let mut response = inner.call(request).await?;
+                    if let Some(header) = header {
+                        response.headers_mut().insert(REQUEST_ID_KEY, header);
+                    }
+                    Ok(response)
+                }
+                .instrument(span),
+            ),
+        )
+    }
+}
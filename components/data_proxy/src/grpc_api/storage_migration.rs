@@ -0,0 +1,95 @@
+use crate::auth::auth_helpers::get_token_from_md;
+use crate::data_backends::migration::migrate_object;
+use crate::grpc_api::proxy_service::DataproxyReplicationServiceImpl;
+use crate::CONFIG;
+use diesel_ulid::DieselUlid;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::error;
+
+/// Moves the data of an object into another storage tier of the proxy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrateObjectStorageRequest {
+    pub object_id: String,
+    // Name of a configured storage tier, the default backend if empty
+    pub target_tier: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrateObjectStorageResponse {
+    pub object_id: String,
+    pub tier: String,
+}
+
+impl DataproxyReplicationServiceImpl {
+    /// Copies the data of an object into the target tier and serves it from there once
+    /// the copy is verified, the source is deleted after the configured grace period.
+    pub async fn migrate_object_storage(
+        &self,
+        request: tonic::Request<MigrateObjectStorageRequest>,
+    ) -> Result<tonic::Response<MigrateObjectStorageResponse>, tonic::Status> {
+        let Some(config) = CONFIG.storage_tiers.as_ref() else {
+            error!(error = "No storage tiers configured");
+            return Err(tonic::Status::unimplemented("No storage tiers configured"));
+        };
+
+        if let Some(a) = self.cache.auth.read().await.as_ref() {
+            let token = get_token_from_md(request.metadata()).map_err(|e| {
+                error!(error = ?e, msg = e.to_string());
+                tonic::Status::unauthenticated(e.to_string())
+            })?;
+
+            let (u, _, pk) = a.check_permissions(&token).map_err(|_| {
+                error!(error = "Unable to authenticate user");
+                tonic::Status::unauthenticated("Unable to authenticate user")
+            })?;
+
+            if pk.is_proxy {
+                error!(error = "Proxy token is not allowed to migrate objects");
+                return Err(tonic::Status::unauthenticated(
+                    "Proxy token is not allowed to migrate objects",
+                ));
+            }
+
+            if !CONFIG.proxy.admin_ids.contains(&u) {
+                error!(error = "Only admins are allowed to migrate objects");
+                return Err(tonic::Status::unauthenticated("Invalid permissions"));
+            }
+        } else {
+            error!(error = "Unable to authenticate user, cache is empty");
+            return Err(tonic::Status::unauthenticated(
+                "Unable to authenticate user",
+            ));
+        }
+
+        let request = request.into_inner();
+        let object_id = DieselUlid::from_str(&request.object_id)
+            .map_err(|_| tonic::Status::invalid_argument("Unable to parse object_id"))?;
+        let tier = match request.target_tier.as_str() {
+            "" => None,
+            name if config.backends.contains_key(name) => Some(name.to_string()),
+            name => {
+                error!(tier = name, error = "Unknown storage tier");
+                return Err(tonic::Status::invalid_argument("Unknown storage tier"));
+            }
+        };
+
+        let location = migrate_object(
+            self.cache.clone(),
+            self.backend.clone(),
+            object_id,
+            tier,
+            Duration::from_secs(config.grace_period),
+        )
+        .await
+        .map_err(|e| {
+            error!(error = ?e, msg = e.to_string());
+            tonic::Status::internal(e.to_string())
+        })?;
+
+        Ok(tonic::Response::new(MigrateObjectStorageResponse {
+            object_id: object_id.to_string(),
+            tier: location.tier.unwrap_or_default(),
+        }))
+    }
+}
@@ -0,0 +1,644 @@
+use crate::auth::auth_helpers::get_token_from_md;
+use crate::caching::cache::Cache;
+use crate::config::UrlImport;
+use crate::data_backends::storage_backend::StorageBackend;
+use crate::grpc_api::ingestion_service::DataproxyIngestionServiceImpl;
+use crate::s3_frontend::utils::buffered_s3_sink::BufferedS3Sink;
+use crate::structs::{Object as ProxyObject, ObjectLocation, ObjectType, TypedRelation};
+use crate::CONFIG;
+use anyhow::{anyhow, bail, Result};
+use aruna_rust_api::api::storage::models::v2::{Hash, Hashalgorithm, KeyValue};
+use bytes::Bytes;
+use diesel_ulid::DieselUlid;
+use futures_util::StreamExt;
+use md5::{Digest, Md5};
+use pithos_lib::helpers::notifications::Message as PithosMessage;
+use pithos_lib::streamreadwrite::GenericStreamReadWriter;
+use pithos_lib::transformer::ReadWriter;
+use pithos_lib::transformers::encrypt::ChaCha20Enc;
+use pithos_lib::transformers::footer::FooterGenerator;
+use pithos_lib::transformers::hashing_transformer::HashingTransformer;
+use pithos_lib::transformers::pithos_comp_enc::PithosTransformer;
+use pithos_lib::transformers::size_probe::SizeProbe;
+use pithos_lib::transformers::zstd_comp::ZstdEnc;
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::pin;
+use tokio::sync::mpsc::Sender;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{error, info, info_span, trace, Instrument};
+use url::{Host, Url};
+
+// Minimum number of bytes between two progress reports
+const PROGRESS_INTERVAL: u64 = 16 * 1024 * 1024;
+
+/// Imports the data of a remote url as new object of a collection
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportObjectFromUrlRequest {
+    pub url: String,
+    pub collection_id: String,
+    // Defaults to the last path segment of the url
+    pub name: Option<String>,
+    pub key_values: Vec<KeyValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportStatus {
+    Fetching,
+    Finished,
+    Failed(String),
+}
+
+/// Progress of an url import, the last message reports the result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportProgress {
+    pub object_id: DieselUlid,
+    pub bytes_received: u64,
+    // Content-Length of the remote, if provided
+    pub total_bytes: Option<u64>,
+    pub status: ImportStatus,
+}
+
+pub type ImportProgressStream = ReceiverStream<Result<ImportProgress, tonic::Status>>;
+
+impl DataproxyIngestionServiceImpl {
+    /// Creates an object in the collection and streams the data of the url into the backend,
+    /// the object is finished with the calculated hashes and size afterwards.
+    pub async fn import_object_from_url(
+        &self,
+        request: tonic::Request<ImportObjectFromUrlRequest>,
+    ) -> Result<tonic::Response<ImportProgressStream>, tonic::Status> {
+        let Some(config) = CONFIG.proxy.url_import.clone() else {
+            error!(error = "Url imports are disabled");
+            return Err(tonic::Status::unimplemented("Url imports are disabled"));
+        };
+
+        let (user_id, tid) = if let Some(a) = self.cache.auth.read().await.as_ref() {
+            let token = get_token_from_md(request.metadata()).map_err(|e| {
+                error!(error = ?e, msg = e.to_string());
+                tonic::Status::unauthenticated(e.to_string())
+            })?;
+
+            let (u, tid, pk) = a.check_permissions(&token).map_err(|_| {
+                error!(error = "Unable to authenticate user");
+                tonic::Status::unauthenticated("Unable to authenticate user")
+            })?;
+
+            if pk.is_proxy {
+                error!(error = "Proxy token is not allowed to import objects");
+                return Err(tonic::Status::unauthenticated(
+                    "Proxy token is not allowed to import objects",
+                ));
+            }
+            (u, tid)
+        } else {
+            error!(error = "Unable to authenticate user, cache is empty");
+            return Err(tonic::Status::unauthenticated(
+                "Unable to authenticate user",
+            ));
+        };
+
+        let request = request.into_inner();
+        let url = Url::parse(&request.url).map_err(|e| {
+            error!(error = ?e, msg = e.to_string());
+            tonic::Status::invalid_argument("Invalid url")
+        })?;
+        // Blocked urls are rejected before anything is created
+        resolve_import_url(&url, &config).await.map_err(|e| {
+            error!(error = ?e, msg = e.to_string());
+            tonic::Status::permission_denied(e.to_string())
+        })?;
+
+        let collection_id = DieselUlid::from_str(&request.collection_id)
+            .map_err(|_| tonic::Status::invalid_argument("Unable to parse collection_id"))?;
+        let mut names = self
+            .cache
+            .get_single_parent(&collection_id)
+            .await
+            .map_err(|_| {
+                error!(error = "Unable to find collection");
+                tonic::Status::not_found("Unable to find collection")
+            })?;
+        // Collections are the last element of their own hierarchy
+        names[1] = names[3].take();
+        let Some((project_id, _)) = names[0].clone() else {
+            error!(error = "Collection is not part of a project");
+            return Err(tonic::Status::not_found(
+                "Collection is not part of a project",
+            ));
+        };
+        let (project, _) = self
+            .cache
+            .get_resource_cloned(&project_id, true)
+            .await
+            .map_err(|_| {
+                error!(error = "Unable to find project");
+                tonic::Status::not_found("Unable to find project")
+            })?;
+
+        let name = request
+            .name
+            .or_else(|| {
+                url.path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .filter(|segment| !segment.is_empty())
+                    .map(|segment| segment.to_string())
+            })
+            .ok_or_else(|| tonic::Status::invalid_argument("Missing object name"))?;
+        let mut object = ProxyObject::initialize_now(
+            name,
+            ObjectType::Object,
+            Some(TypedRelation::Collection(collection_id)),
+        );
+        object.key_values = request.key_values;
+        object.set_encryption_choice(
+            object.get_encryption_choice(),
+            project.enforces_encryption(),
+        );
+
+        // The server checks the permissions of the user for the collection
+        let object = self
+            .create_import_object(object, user_id, tid.clone())
+            .await
+            .map_err(|e| {
+                error!(error = ?e, msg = e.to_string());
+                tonic::Status::internal(e.to_string())
+            })?;
+        names[3] = Some((object.id, object.name.clone()));
+
+        let (sender, receiver) = tokio::sync::mpsc::channel(16);
+        let cache = self.cache.clone();
+        let backend = self.backend.clone();
+        let object_id = object.id;
+        tokio::spawn(
+            async move {
+                let result = import_url(
+                    cache,
+                    backend,
+                    object,
+                    names,
+                    url,
+                    &config,
+                    (user_id, tid),
+                    &sender,
+                )
+                .await;
+                let progress = result.unwrap_or_else(|e| {
+                    error!(error = ?e, msg = e.to_string());
+                    ImportProgress {
+                        object_id,
+                        bytes_received: 0,
+                        total_bytes: None,
+                        status: ImportStatus::Failed(e.to_string()),
+                    }
+                });
+                if sender.send(Ok(progress)).await.is_err() {
+                    trace!("Import progress receiver dropped");
+                }
+            }
+            .instrument(info_span!("url_import", object_id = %object_id)),
+        );
+
+        Ok(tonic::Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn create_import_object(
+        &self,
+        object: ProxyObject,
+        user_id: DieselUlid,
+        tid: Option<String>,
+    ) -> Result<ProxyObject> {
+        let token = sign_token(&self.cache, user_id, tid).await?;
+        match self.cache.aruna_client.read().await.as_ref() {
+            Some(handler) => handler.create_object(object, &token).await,
+            None => bail!("ArunaServer client not available"),
+        }
+    }
+}
+
+/// Fetches the url into a new location of the staging object and finishes it
+#[allow(clippy::too_many_arguments)]
+async fn import_url(
+    cache: Arc<Cache>,
+    backend: Arc<Box<dyn StorageBackend>>,
+    object: ProxyObject,
+    names: [Option<(DieselUlid, String)>; 4],
+    url: Url,
+    config: &UrlImport,
+    (user_id, tid): (DieselUlid, Option<String>),
+    progress: &Sender<Result<ImportProgress, tonic::Status>>,
+) -> Result<ImportProgress> {
+    info!(%url, "Importing object from url");
+    let response = fetch_url(url, config).await?;
+    let total_bytes = response.content_length();
+
+    let mut location = backend
+        .initialize_location(&object, total_bytes.map(|len| len as i64), names, false)
+        .await?;
+    let hashes = match store_response(
+        backend.clone(),
+        &object,
+        &mut location,
+        response,
+        config.max_size,
+        progress,
+    )
+    .await
+    {
+        Ok(hashes) => hashes,
+        Err(e) => {
+            // Partial data is removed, the staging object expires with its upload deadline
+            if let Err(err) = backend.delete_object(location).await {
+                error!(error = ?err, msg = "Unable to delete partial import");
+            }
+            return Err(e);
+        }
+    };
+
+    // Imports can outlast the lifetime of impersonating tokens
+    let token = sign_token(&cache, user_id, tid).await?;
+    let object = match cache.aruna_client.read().await.as_ref() {
+        Some(handler) => {
+            handler
+                .finish_object(object.id, location.raw_content_len, hashes, &token)
+                .await?
+        }
+        None => bail!("ArunaServer client not available"),
+    };
+    let bytes_received = location.raw_content_len as u64;
+    cache.add_location_with_binding(object.id, location).await?;
+    info!(object_id = %object.id, bytes_received, "Imported object from url");
+
+    Ok(ImportProgress {
+        object_id: object.id,
+        bytes_received,
+        total_bytes,
+        status: ImportStatus::Finished,
+    })
+}
+
+/// Streams the response into the location like a regular upload,
+/// returns the hashes of the raw data.
+async fn store_response(
+    backend: Arc<Box<dyn StorageBackend>>,
+    object: &ProxyObject,
+    location: &mut ObjectLocation,
+    response: reqwest::Response,
+    max_size: u64,
+    progress: &Sender<Result<ImportProgress, tonic::Status>>,
+) -> Result<Vec<Hash>> {
+    let total_bytes = response.content_length();
+
+    let (initial_sha_trans, initial_sha_recv) =
+        HashingTransformer::new_with_backchannel(Sha256::new(), "sha256".to_string());
+    let (initial_md5_trans, initial_md5_recv) =
+        HashingTransformer::new_with_backchannel(Md5::new(), "md5".to_string());
+    let (initial_size_trans, initial_size_recv) = SizeProbe::new();
+    let (final_sha_trans, final_sha_recv) =
+        HashingTransformer::new_with_backchannel(Sha256::new(), "sha256".to_string());
+    let (final_size_trans, final_size_recv) = SizeProbe::new();
+
+    // Enforces the maximum size for remotes without (or with a wrong) Content-Length
+    let object_id = object.id;
+    let progress_sender = progress.clone();
+    let mut received = 0u64;
+    let mut reported = 0u64;
+    let data = response.bytes_stream().map(
+        move |chunk| -> Result<Bytes, Box<dyn std::error::Error + Send + Sync>> {
+            let chunk = chunk.map_err(Box::new)?;
+            received += chunk.len() as u64;
+            if received > max_size {
+                return Err(
+                    anyhow!("Remote object exceeds the maximum size of {max_size} bytes").into(),
+                );
+            }
+            if received - reported >= PROGRESS_INTERVAL {
+                reported = received;
+                // Progress reports are skipped if the client is slow
+                let _ = progress_sender.try_send(Ok(ImportProgress {
+                    object_id,
+                    bytes_received: received,
+                    total_bytes,
+                    status: ImportStatus::Fetching,
+                }));
+            }
+            Ok(chunk)
+        },
+    );
+    pin!(data);
+
+    let (tx, rx) = async_channel::bounded(10);
+    let mut awr = GenericStreamReadWriter::new_with_sink(
+        data,
+        BufferedS3Sink::new(backend, location.clone(), None, None, false, None, false).0,
+    );
+    awr.add_message_receiver(rx)
+        .await
+        .map_err(|e| anyhow!("Internal notifier error: {e}"))?;
+
+    awr = awr.add_transformer(initial_sha_trans);
+    awr = awr.add_transformer(initial_md5_trans);
+    awr = awr.add_transformer(initial_size_trans);
+
+    if location.is_compressed() && !location.is_pithos() {
+        awr = awr.add_transformer(ZstdEnc::new());
+    }
+
+    if let Some(enc_key) = &location.get_encryption_key() {
+        if !location.is_pithos() {
+            awr = awr.add_transformer(ChaCha20Enc::new_with_fixed(*enc_key)?);
+        }
+    }
+
+    if location.is_pithos() {
+        let ctx =
+            object.get_file_context(Some(location.clone()), total_bytes.map(|len| len as i64))?;
+        tx.send(PithosMessage::FileContext(ctx))
+            .await
+            .map_err(|e| anyhow!("Internal notifier error: {e}"))?;
+        awr = awr.add_transformer(PithosTransformer::new());
+        awr = awr.add_transformer(FooterGenerator::new(None));
+    }
+    awr = awr.add_transformer(final_sha_trans);
+    awr = awr.add_transformer(final_size_trans);
+
+    awr.process()
+        .await
+        .map_err(|e| anyhow!("Internal data transformer processing error: {e}"))?;
+
+    let md5_initial = initial_md5_recv.try_recv()?;
+    let sha_initial = initial_sha_recv.try_recv()?;
+    let sha_final = final_sha_recv.try_recv()?;
+    let initial_size = initial_size_recv.try_recv()?;
+    let final_size = final_size_recv.try_recv()?;
+
+    location.raw_content_len = initial_size as i64;
+    location.disk_content_len = final_size as i64;
+    location.disk_hash = Some(sha_final);
+
+    Ok(vec![
+        Hash {
+            alg: Hashalgorithm::Sha256.into(),
+            hash: sha_initial,
+        },
+        Hash {
+            alg: Hashalgorithm::Md5.into(),
+            hash: md5_initial,
+        },
+    ])
+}
+
+async fn sign_token(cache: &Cache, user_id: DieselUlid, tid: Option<String>) -> Result<String> {
+    match cache.auth.read().await.as_ref() {
+        Some(auth) => auth.sign_impersonating_token(user_id.to_string(), tid),
+        None => bail!("Unable to sign token, authentication handler not available"),
+    }
+}
+
+/// Loopback, private, link-local and other non-public addresses
+pub fn is_internal_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || octets[0] == 0
+                || (octets[0] == 100 && (octets[1] & 0xc0) == 64) // Shared address space
+                || (octets[0] == 198 && (octets[1] & 0xfe) == 18) // Benchmarking
+                || octets[0] >= 240 // Reserved
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_internal_ip(IpAddr::V4(mapped));
+            }
+            let segments = ip.segments();
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00 // Unique local
+                || (segments[0] & 0xffc0) == 0xfe80 // Link-local
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8) // Documentation
+                || (segments[0] == 0x64 && segments[1] == 0xff9b) // NAT64 of arbitrary ipv4
+        }
+    }
+}
+
+/// Checks scheme and host of an url against the import configuration and resolves it.
+/// Urls resolving to internal addresses are rejected to prevent requests to internal services.
+pub async fn resolve_import_url(url: &Url, config: &UrlImport) -> Result<Vec<SocketAddr>> {
+    if !config
+        .allowed_schemes
+        .iter()
+        .any(|scheme| scheme == url.scheme())
+    {
+        bail!("Url scheme {} is not allowed", url.scheme())
+    }
+
+    let host = match url.host() {
+        Some(Host::Domain(domain)) => domain.to_ascii_lowercase(),
+        Some(Host::Ipv4(ip)) => ip.to_string(),
+        Some(Host::Ipv6(ip)) => ip.to_string(),
+        None => bail!("Url has no host"),
+    };
+    if !config.allowed_hosts.is_empty()
+        && !config.allowed_hosts.iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            host == allowed || host.ends_with(&format!(".{allowed}"))
+        })
+    {
+        bail!("Host {host} is not allowed")
+    }
+
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow!("Url has no port"))?;
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+        .await?
+        .collect();
+    if addrs.is_empty() {
+        bail!("Host {host} could not be resolved")
+    }
+    for addr in &addrs {
+        if is_internal_ip(addr.ip())
+            && !config
+                .allowed_networks
+                .iter()
+                .any(|network| network.contains(&addr.ip()))
+        {
+            bail!("Host {host} resolves to the internal address {}", addr.ip())
+        }
+    }
+    Ok(addrs)
+}
+
+/// Requests the url and follows redirects manually, so that every target is checked.
+/// Requests are pinned to the checked addresses to prevent DNS rebinding.
+pub async fn fetch_url(mut url: Url, config: &UrlImport) -> Result<reqwest::Response> {
+    for _ in 0..=config.max_redirects {
+        let addrs = resolve_import_url(&url, config).await?;
+        let mut builder = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .timeout(Duration::from_secs(config.timeout));
+        if let Some(Host::Domain(domain)) = url.host() {
+            builder = builder.resolve_to_addrs(domain, &addrs);
+        }
+        let response = builder.build()?.get(url.clone()).send().await?;
+
+        if response.status().is_redirection() {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or_else(|| anyhow!("Redirect without location"))?;
+            url = url.join(location)?;
+            trace!(%url, "Following redirect");
+            continue;
+        }
+        if !response.status().is_success() {
+            bail!("Fetching url failed with status {}", response.status())
+        }
+        if response
+            .content_length()
+            .is_some_and(|len| len > config.max_size)
+        {
+            bail!(
+                "Remote object exceeds the maximum size of {} bytes",
+                config.max_size
+            )
+        }
+        return Ok(response);
+    }
+    bail!("Too many redirects")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipnet::IpNet;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn config() -> UrlImport {
+        UrlImport {
+            allowed_schemes: vec!["http".to_string(), "https".to_string()],
+            allowed_hosts: vec![],
+            allowed_networks: vec![],
+            max_size: 1024,
+            timeout: 10,
+            max_redirects: 2,
+        }
+    }
+
+    // Serves /data, a redirect to /data and a redirect to an internal address
+    async fn serve() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let read = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..read]).to_string();
+                let (status, location, body) = if request.starts_with("GET /data ") {
+                    ("200 OK", None, "hello world")
+                } else if request.starts_with("GET /redirect ") {
+                    ("302 Found", Some("/data"), "")
+                } else {
+                    (
+                        "302 Found",
+                        Some("http://169.254.169.254/latest/meta-data"),
+                        "",
+                    )
+                };
+                let location = location
+                    .map(|location| format!("location: {location}\r\n"))
+                    .unwrap_or_default();
+                let response = format!(
+                    "HTTP/1.1 {status}\r\n{location}content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_internal_ips() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(is_internal_ip(IpAddr::from_str(ip).unwrap()), "{ip}");
+        }
+        for ip in ["1.1.1.1", "142.250.185.78", "2606:4700:4700::1111"] {
+            assert!(!is_internal_ip(IpAddr::from_str(ip).unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_url() {
+        let addr = serve().await;
+        let mut config = config();
+        config.allowed_networks = vec![IpNet::from_str("127.0.0.1/32").unwrap()];
+
+        // Redirects are followed
+        let url = Url::parse(&format!("http://{addr}/redirect")).unwrap();
+        let response = fetch_url(url, &config).await.unwrap();
+        assert_eq!(response.content_length(), Some(11));
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"hello world");
+
+        // Remotes exceeding the maximum size are rejected
+        config.max_size = 10;
+        let url = Url::parse(&format!("http://{addr}/data")).unwrap();
+        assert!(fetch_url(url, &config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_internal_url_blocked() {
+        let addr = serve().await;
+
+        // Internal addresses are blocked by default
+        for url in [
+            format!("http://{addr}/data"),
+            "http://169.254.169.254/latest/meta-data".to_string(),
+            "http://[::1]/data".to_string(),
+        ] {
+            let url = Url::parse(&url).unwrap();
+            assert!(resolve_import_url(&url, &config()).await.is_err());
+            assert!(fetch_url(url, &config()).await.is_err());
+        }
+
+        // Redirects to internal addresses are blocked
+        let mut config = config();
+        config.allowed_networks = vec![IpNet::from_str("127.0.0.1/32").unwrap()];
+        let url = Url::parse(&format!("http://{addr}/internal")).unwrap();
+        let err = fetch_url(url, &config).await.unwrap_err();
+        assert!(err.to_string().contains("internal address"));
+
+        // Schemes and hosts are restricted
+        config.allowed_schemes = vec!["https".to_string()];
+        config.allowed_hosts = vec!["example.org".to_string()];
+        let url = Url::parse(&format!("http://{addr}/data")).unwrap();
+        assert!(resolve_import_url(&url, &config).await.is_err());
+        let url = Url::parse("https://example.com/data").unwrap();
+        assert!(resolve_import_url(&url, &config).await.is_err());
+    }
+}
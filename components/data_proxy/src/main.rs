@@ -8,6 +8,8 @@ use caching::cache::Cache;
 use data_backends::{s3_backend::S3Backend, storage_backend::StorageBackend};
 use futures_util::TryFutureExt;
 use grpc_api::bundler::BundlerServiceImpl;
+use grpc_api::connections;
+use grpc_api::request_id::RequestIdLayer;
 use grpc_api::{
     proxy_service::DataproxyReplicationServiceImpl, user_service::DataproxyUserServiceImpl,
 };
@@ -16,7 +18,6 @@ use regex::Regex;
 use std::panic;
 use std::{net::SocketAddr, sync::Arc};
 use tokio::try_join;
-use tonic::transport::Server;
 use tracing::error;
 use tracing::info_span;
 use tracing::trace;
@@ -40,10 +41,11 @@ mod helpers;
 
 use crate::config::Config;
 use crate::data_backends::filesystem_backend::FSBackend;
+use crate::data_backends::tiered_backend::TieredBackend;
 use crate::grpc_api::ingestion_service::DataproxyIngestionServiceImpl;
+use crate::replication::limits::DEFAULT_REPLICATION_QUEUE_SIZE;
 use crate::replication::replication_handler::ReplicationHandler;
 use std::backtrace::Backtrace;
-use std::time::Duration;
 
 lazy_static! {
     static ref CONFIG: Config = {
@@ -106,10 +108,25 @@ async fn main() -> Result<()> {
         }
     };
 
+    // Objects of additional storage tiers are served by the backend of their tier
+    let backend: Box<dyn StorageBackend> = match &CONFIG.storage_tiers {
+        Some(tiers) => Box::new(
+            TieredBackend::from_config(CONFIG.proxy.endpoint_id.to_string(), backend, tiers)
+                .await?,
+        ),
+        None => backend,
+    };
+
     let storage_backend: Arc<Box<dyn StorageBackend>> = Arc::new(backend);
 
     trace!("init cache");
-    let (sender, receiver) = async_channel::bounded(1000);
+    // Senders wait for free space if the queue is full, no replication request is dropped
+    let (sender, receiver) = async_channel::bounded(
+        CONFIG
+            .proxy
+            .replication_queue_size
+            .unwrap_or(DEFAULT_REPLICATION_QUEUE_SIZE),
+    );
     let cache = Cache::new(
         CONFIG.proxy.aruna_url.clone(),
         CONFIG.persistence.is_some(),
@@ -148,6 +165,8 @@ async fn main() -> Result<()> {
                 frontend.hostname.to_string(),
                 storage_backend.clone(),
                 cache,
+                frontend.access_log.as_ref(),
+                frontend.download_limits.as_ref(),
             )
             .await?,
         )
@@ -157,11 +176,12 @@ async fn main() -> Result<()> {
     trace!("init grpc server");
 
     let proxy_grpc_addr = CONFIG.proxy.grpc_server.parse::<SocketAddr>()?;
+    let grpc_incoming = connections::incoming(&CONFIG.proxy.grpc, proxy_grpc_addr)?;
 
     let grpc_server_handle = tokio::spawn(
         async move {
-            let mut builder = Server::builder()
-                .http2_keepalive_interval(Some(Duration::from_secs(15)))
+            let mut builder = connections::server_builder(&CONFIG.proxy.grpc)
+                .layer(RequestIdLayer)
                 .add_service(DataproxyReplicationServiceServer::new(
                     DataproxyReplicationServiceImpl::new(
                         cache_clone.clone(),
@@ -187,7 +207,7 @@ async fn main() -> Result<()> {
                 )));
             };
 
-            builder.serve(proxy_grpc_addr).await
+            builder.serve_with_incoming(grpc_incoming).await
         }
         .instrument(info_span!("grpc_server_run")),
     )
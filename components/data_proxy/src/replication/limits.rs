@@ -0,0 +1,190 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tracing::info;
+
+pub const DEFAULT_MAX_CONCURRENT_REPLICATIONS: usize = 4;
+pub const DEFAULT_REPLICATION_QUEUE_SIZE: usize = 1000;
+
+/// Limits the number of parallel replications and the bandwidth used by them
+#[derive(Debug)]
+pub struct ReplicationLimiter {
+    semaphore: Arc<Semaphore>,
+    bandwidth: Option<BandwidthLimiter>,
+    metrics: ReplicationMetrics,
+}
+
+/// Permit for one replication, the replication is counted as in-flight until it is dropped
+#[derive(Debug)]
+pub struct ReplicationPermit<'a> {
+    _permit: OwnedSemaphorePermit,
+    metrics: &'a ReplicationMetrics,
+}
+
+impl Drop for ReplicationPermit<'_> {
+    fn drop(&mut self) {
+        self.metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl ReplicationLimiter {
+    pub fn new(max_concurrent: usize, bytes_per_second: Option<u64>) -> Self {
+        ReplicationLimiter {
+            semaphore: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            bandwidth: bytes_per_second.map(BandwidthLimiter::new),
+            metrics: ReplicationMetrics::new(),
+        }
+    }
+
+    /// Waits until a replication slot is free
+    pub async fn acquire(&self) -> anyhow::Result<ReplicationPermit<'_>> {
+        let permit = self.semaphore.clone().acquire_owned().await?;
+        self.metrics.in_flight.fetch_add(1, Ordering::Relaxed);
+        Ok(ReplicationPermit {
+            _permit: permit,
+            metrics: &self.metrics,
+        })
+    }
+
+    /// Records transferred bytes and waits until they fit into the bandwidth limit
+    pub async fn consume(&self, bytes: usize) {
+        if let Some(bandwidth) = &self.bandwidth {
+            bandwidth.consume(bytes as u64).await;
+        }
+        self.metrics
+            .transferred_bytes
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn metrics(&self) -> &ReplicationMetrics {
+        &self.metrics
+    }
+}
+
+#[derive(Debug)]
+pub struct ReplicationMetrics {
+    in_flight: AtomicUsize,
+    transferred_bytes: AtomicU64,
+    started: Instant,
+}
+
+impl ReplicationMetrics {
+    fn new() -> Self {
+        ReplicationMetrics {
+            in_flight: AtomicUsize::new(0),
+            transferred_bytes: AtomicU64::new(0),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    pub fn transferred_bytes(&self) -> u64 {
+        self.transferred_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Average replication throughput in bytes per second since startup
+    pub fn throughput(&self) -> f64 {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.transferred_bytes() as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    pub fn log(&self) {
+        info!(
+            in_flight = self.in_flight(),
+            transferred_bytes = self.transferred_bytes(),
+            throughput = self.throughput(),
+            "replication metrics"
+        );
+    }
+}
+
+/// Token bucket which refills with the configured bytes per second
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    bytes_per_second: u64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl BandwidthLimiter {
+    pub fn new(bytes_per_second: u64) -> Self {
+        let bytes_per_second = bytes_per_second.max(1);
+        BandwidthLimiter {
+            bytes_per_second,
+            state: Mutex::new(BucketState {
+                tokens: bytes_per_second as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub async fn consume(&self, bytes: u64) {
+        // Callers are served one after another, so large chunks can not be starved
+        let mut state = self.state.lock().await;
+        let rate = self.bytes_per_second as f64;
+        let now = Instant::now();
+        state.tokens =
+            (state.tokens + now.duration_since(state.last_refill).as_secs_f64() * rate).min(rate);
+        state.last_refill = now;
+        state.tokens -= bytes as f64;
+        if state.tokens < 0.0 {
+            // Chunks larger than the bucket are paid off by waiting
+            tokio::time::sleep(Duration::from_secs_f64(-state.tokens / rate)).await;
+            state.tokens = 0.0;
+            state.last_refill = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replication_concurrency_limit() {
+        let limiter = Arc::new(ReplicationLimiter::new(3, None));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+
+        let jobs = (0..20).map(|_| {
+            let limiter = limiter.clone();
+            let max_seen = max_seen.clone();
+            tokio::spawn(async move {
+                let _permit = limiter.acquire().await.unwrap();
+                max_seen.fetch_max(limiter.metrics().in_flight(), Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                limiter.consume(10).await;
+            })
+        });
+        for job in jobs.collect::<Vec<_>>() {
+            job.await.unwrap();
+        }
+
+        assert!(max_seen.load(Ordering::SeqCst) <= 3);
+        assert!(max_seen.load(Ordering::SeqCst) > 0);
+        assert_eq!(limiter.metrics().in_flight(), 0);
+        assert_eq!(limiter.metrics().transferred_bytes(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_replication_bandwidth_limit() {
+        let limiter = ReplicationLimiter::new(1, Some(1000));
+        let start = Instant::now();
+        // The first 1000 bytes are covered by the initial bucket
+        limiter.consume(1000).await;
+        limiter.consume(200).await;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
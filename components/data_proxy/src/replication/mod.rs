@@ -1 +1,3 @@
+pub mod limits;
+pub mod on_access;
 pub mod replication_handler;
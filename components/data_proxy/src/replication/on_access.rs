@@ -0,0 +1,145 @@
+use ahash::RandomState;
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use diesel_ulid::DieselUlid;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::Mutex;
+use tracing::trace;
+
+/// Pulls the data of a single object from another endpoint into the local backend
+#[async_trait::async_trait]
+pub trait ObjectPuller: Send + Sync {
+    async fn pull(&self, object_id: DieselUlid, endpoint_id: DieselUlid) -> Result<()>;
+}
+
+/// Objects which are replicated on first access. Until then only the object metadata
+/// and the endpoint holding the data are known.
+#[derive(Default)]
+pub struct OnAccessReplication {
+    // Map with ObjectId as key and source EndpointId as value
+    stubs: DashMap<DieselUlid, DieselUlid, RandomState>,
+    // Running pulls, concurrent accesses wait for the same pull
+    pulls: DashMap<DieselUlid, Arc<Mutex<()>>, RandomState>,
+    puller: OnceLock<Arc<dyn ObjectPuller>>,
+}
+
+impl OnAccessReplication {
+    pub fn set_puller(&self, puller: Arc<dyn ObjectPuller>) {
+        if self.puller.set(puller).is_err() {
+            tracing::warn!("On-access puller already set");
+        }
+    }
+
+    pub fn add_stub(&self, object_id: DieselUlid, source: DieselUlid) {
+        trace!(?object_id, ?source, "added on-access stub");
+        self.stubs.insert(object_id, source);
+    }
+
+    pub fn remove_stub(&self, object_id: &DieselUlid) {
+        self.stubs.remove(object_id);
+    }
+
+    /// Returns false while the data of the object is only available at the source endpoint
+    pub fn is_materialized(&self, object_id: &DieselUlid) -> bool {
+        !self.stubs.contains_key(object_id)
+    }
+
+    /// Pulls the object from its source if it is not materialized yet.
+    /// Only one pull per object is started, concurrent calls wait for it to finish.
+    /// Returns true if the object was pulled by this call.
+    pub async fn materialize(&self, object_id: &DieselUlid) -> Result<bool> {
+        if self.is_materialized(object_id) {
+            return Ok(false);
+        }
+        let pull = self.pulls.entry(*object_id).or_default().clone();
+        let _guard = pull.lock().await;
+
+        // Finished while waiting for the lock
+        let Some(source) = self.stubs.get(object_id).map(|entry| *entry.value()) else {
+            return Ok(false);
+        };
+        let puller = self
+            .puller
+            .get()
+            .ok_or_else(|| anyhow!("On-access replication not initialized"))?;
+        // Failed pulls keep the stub and are retried with the next access
+        puller.pull(*object_id, source).await?;
+        self.stubs.remove(object_id);
+        self.pulls.remove(object_id);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Stores pulled objects in memory, fails the first pull if requested
+    #[derive(Default)]
+    struct MemoryPuller {
+        pulls: AtomicUsize,
+        fail_once: AtomicBool,
+        local: std::sync::Mutex<HashMap<DieselUlid, DieselUlid>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ObjectPuller for MemoryPuller {
+        async fn pull(&self, object_id: DieselUlid, endpoint_id: DieselUlid) -> Result<()> {
+            self.pulls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            if self.fail_once.swap(false, Ordering::SeqCst) {
+                anyhow::bail!("source unavailable")
+            }
+            self.local.lock().unwrap().insert(object_id, endpoint_id);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_first_miss_then_hit() {
+        let replication = Arc::new(OnAccessReplication::default());
+        let puller = Arc::new(MemoryPuller::default());
+        replication.set_puller(puller.clone());
+        let object_id = DieselUlid::generate();
+        let source = DieselUlid::generate();
+        replication.add_stub(object_id, source);
+        assert!(!replication.is_materialized(&object_id));
+
+        // Concurrent first accesses result in a single pull
+        let accesses = (0..5).map(|_| {
+            let replication = replication.clone();
+            tokio::spawn(async move { replication.materialize(&object_id).await.unwrap() })
+        });
+        let pulled = futures::future::join_all(accesses)
+            .await
+            .into_iter()
+            .filter(|pulled| *pulled.as_ref().unwrap())
+            .count();
+        assert_eq!(pulled, 1);
+        assert_eq!(puller.pulls.load(Ordering::SeqCst), 1);
+        assert!(replication.is_materialized(&object_id));
+        assert_eq!(puller.local.lock().unwrap().get(&object_id), Some(&source));
+
+        // Later accesses are served locally
+        assert!(!replication.materialize(&object_id).await.unwrap());
+        assert_eq!(puller.pulls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_pull_is_retried() {
+        let replication = OnAccessReplication::default();
+        let puller = Arc::new(MemoryPuller::default());
+        puller.fail_once.store(true, Ordering::SeqCst);
+        replication.set_puller(puller.clone());
+        let object_id = DieselUlid::generate();
+        replication.add_stub(object_id, DieselUlid::generate());
+
+        assert!(replication.materialize(&object_id).await.is_err());
+        assert!(!replication.is_materialized(&object_id));
+        assert!(replication.materialize(&object_id).await.unwrap());
+        assert_eq!(puller.pulls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,3 +1,6 @@
+use crate::config::ReplicationMode;
+use crate::replication::limits::{ReplicationLimiter, DEFAULT_MAX_CONCURRENT_REPLICATIONS};
+use crate::replication::on_access::ObjectPuller;
 use crate::structs::FileFormat;
 use crate::CONFIG;
 use crate::{
@@ -51,11 +54,13 @@ pub struct DataChunk {
     pub checksum: String,
 }
 
+#[derive(Clone)]
 pub struct ReplicationHandler {
     pub receiver: Receiver<ReplicationMessage>,
     pub backend: Arc<Box<dyn StorageBackend>>,
     pub cache: Arc<Cache>,
     pub self_id: String,
+    pub limiter: Arc<ReplicationLimiter>,
 }
 
 #[derive(Clone, Debug)]
@@ -126,11 +131,19 @@ impl ReplicationHandler {
         self_id: String,
         cache: Arc<Cache>,
     ) -> Self {
+        let limiter = Arc::new(ReplicationLimiter::new(
+            CONFIG
+                .proxy
+                .max_concurrent_replications
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_REPLICATIONS),
+            CONFIG.proxy.replication_bandwidth_limit,
+        ));
         Self {
             receiver,
             backend,
             self_id,
             cache,
+            limiter,
         }
     }
 
@@ -140,15 +153,26 @@ impl ReplicationHandler {
         let queue: Arc<DashMap<DieselUlid, Vec<Direction>, RandomState>> =
             Arc::new(DashMap::default());
 
+        // Objects replicated on access are pulled by the S3 frontend
+        self.cache.on_access.set_puller(Arc::new(self.clone()));
+        let mode = CONFIG.proxy.replication_mode;
+
         // Push messages into DashMap for further processing
         let queue_clone = queue.clone();
         let receiver = self.receiver.clone();
+        let cache = self.cache.clone();
         let receive = tokio::spawn(async move {
             while let Ok(ReplicationMessage {
                 direction,
                 endpoint_id,
             }) = receiver.recv().await
             {
+                if let (ReplicationMode::OnAccess, Direction::Pull(object_id)) = (mode, &direction)
+                {
+                    // Only the source is stored until the object is downloaded
+                    cache.on_access.add_stub(*object_id, endpoint_id);
+                    continue;
+                }
                 if queue_clone.contains_key(&endpoint_id) {
                     queue_clone.alter(&endpoint_id, |_, mut objects| {
                         objects.push(direction.clone());
@@ -215,155 +239,234 @@ impl ReplicationHandler {
         &self,
         batch: Arc<DashMap<DieselUlid, Vec<Direction>, RandomState>>,
     ) -> Result<Vec<(DieselUlid, Vec<Direction>)>> {
+        // Snapshot of the current batch, new requests are queued while processing
+        let endpoints: Vec<(DieselUlid, Vec<Direction>)> = batch
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect();
+
+        // Endpoints are processed in parallel, bounded by max_concurrent_replications
+        let results = futures::future::join_all(
+            endpoints
+                .into_iter()
+                .map(|(endpoint_id, directions)| self.process_endpoint(endpoint_id, directions)),
+        )
+        .await;
+
         // Vec for collecting all processed and finished endpoint batches
         let mut result = Vec::new();
+        for endpoint_result in results {
+            match endpoint_result {
+                Ok(Some(finished)) => result.push(finished),
+                Ok(None) => {}
+                // Failed endpoints stay in the queue and are retried with the next batch
+                Err(err) => tracing::error!(error = ?err, msg = err.to_string()),
+            }
+        }
+        self.limiter.metrics().log();
 
-        // Iterates over each endpoint
-        for endpoint in batch.iter() {
-            let self_id = self.self_id.clone();
-            // Collects all objects for each direction
-            let pull: Vec<DieselUlid> = endpoint
-                .iter()
-                .filter_map(|object| match object {
-                    Direction::Pull(id) => Some(*id),
-                    Direction::Push(_) => None,
-                })
-                .collect();
-            // TODO: Push is currently not implemented
-            let _push: Vec<DieselUlid> = endpoint
-                .iter()
-                .filter_map(|object| match object {
-                    Direction::Push(id) => Some(*id),
-                    Direction::Pull(_) => None,
-                })
-                .collect();
-            // This is the initial message for the data transmission stream
-            let init_request = PullReplicationRequest {
-                message: Some(Message::InitMessage(InitMessage {
-                    dataproxy_id: self_id.clone(),
-                    object_ids: pull.iter().map(|o| o.to_string()).collect(),
-                })),
-            };
-            if let Some(query_handler) = self.cache.aruna_client.read().await.as_ref() {
-                let endpoint_id = *endpoint.key();
-                // This query handler returns a channel for sending messages into the input stream
-                // and the response stream
-                let (request_sender, mut response_stream) = query_handler
-                    .pull_replication(init_request, endpoint_id)
+        trace!(?result);
+        Ok(result)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, directions))]
+    async fn process_endpoint(
+        &self,
+        endpoint_id: DieselUlid,
+        directions: Vec<Direction>,
+    ) -> Result<Option<(DieselUlid, Vec<Direction>)>> {
+        // Waits until a replication slot is free
+        let _permit = self.limiter.acquire().await?;
+        let self_id = self.self_id.clone();
+        // Collects all objects for each direction
+        let pull: Vec<DieselUlid> = directions
+            .iter()
+            .filter_map(|object| match object {
+                Direction::Pull(id) => Some(*id),
+                Direction::Push(_) => None,
+            })
+            .collect();
+        // TODO: Push is currently not implemented
+        let _push: Vec<DieselUlid> = directions
+            .iter()
+            .filter_map(|object| match object {
+                Direction::Push(id) => Some(*id),
+                Direction::Pull(_) => None,
+            })
+            .collect();
+        // This is the initial message for the data transmission stream
+        let init_request = PullReplicationRequest {
+            message: Some(Message::InitMessage(InitMessage {
+                dataproxy_id: self_id.clone(),
+                object_ids: pull.iter().map(|o| o.to_string()).collect(),
+            })),
+        };
+        if let Some(query_handler) = self.cache.aruna_client.read().await.as_ref() {
+            // This query handler returns a channel for sending messages into the input stream
+            // and the response stream
+            let (request_sender, mut response_stream) = query_handler
+                .pull_replication(init_request, endpoint_id)
+                .await
+                .map_err(|e| {
+                    tracing::error!(error = ?e, msg = e.to_string());
+                    e
+                })?;
+
+            // This is the init message for object processing
+            let (start_sender, start_receiver) = async_channel::bounded(1);
+            // This channel is used to collect all processed objects and chunks
+            let (sync_sender, sync_receiver) = async_channel::bounded(100);
+            // This channel is only used to transmit the sync result to compare
+            // received vs requested objects
+            let (finish_sender, finish_receiver) = async_channel::bounded(1);
+
+            // This map collects for each object_id a channel for data transmission
+            // TODO: This could be used to make parallel requests later
+            let object_handler_map: ObjectHandler = Arc::new(DashMap::default());
+            for object in pull {
+                query_handler
+                    .update_replication_status(UpdateReplicationStatusRequest {
+                        object_id: object.to_string(),
+                        endpoint_id: self_id.clone(),
+                        status: ReplicationStatus::Running as i32,
+                    })
                     .await
                     .map_err(|e| {
                         tracing::error!(error = ?e, msg = e.to_string());
                         e
                     })?;
+                let (object_sdx, object_rcv) = async_channel::bounded(100);
+                object_handler_map.insert(
+                    object.to_string(),
+                    Arc::new(RwLock::new(ObjectState::new(
+                        object_sdx.clone(),
+                        object_rcv.clone(),
+                    ))),
+                );
+            }
 
-                // This is the init message for object processing
-                let (start_sender, start_receiver) = async_channel::bounded(1);
-                // This channel is used to collect all processed objects and chunks
-                let (sync_sender, sync_receiver) = async_channel::bounded(100);
-                // This channel is only used to transmit the sync result to compare
-                // received vs requested objects
-                let (finish_sender, finish_receiver) = async_channel::bounded(1);
-
-                // This map collects for each object_id a channel for data transmission
-                // TODO: This could be used to make parallel requests later
-                let object_handler_map: ObjectHandler = Arc::new(DashMap::default());
-                for object in pull {
-                    query_handler
-                        .update_replication_status(UpdateReplicationStatusRequest {
-                            object_id: object.to_string(),
-                            endpoint_id: self_id.clone(),
-                            status: ReplicationStatus::Running as i32,
-                        })
-                        .await
-                        .map_err(|e| {
-                            tracing::error!(error = ?e, msg = e.to_string());
-                            e
-                        })?;
-                    let (object_sdx, object_rcv) = async_channel::bounded(100);
-                    object_handler_map.insert(
-                        object.to_string(),
-                        Arc::new(RwLock::new(ObjectState::new(
-                            object_sdx.clone(),
-                            object_rcv.clone(),
-                        ))),
-                    );
-                }
-
-                trace!(?object_handler_map);
-                // Response handler:
-                // This is used to handle all requests and responses
-                // to the other data proxy
-                let data_map = object_handler_map.clone();
-                let sync_sender_clone = sync_sender.clone();
-                let request_sender_clone = request_sender.clone();
-                tokio::spawn(async move {
-                    let mut counter = 0;
-                    while let Some(response) = response_stream.message().await? {
-                        match response.message {
-                            Some(ResponseMessage::Handshake(_)) => {
-                                continue;
+            trace!(?object_handler_map);
+            // Response handler:
+            // This is used to handle all requests and responses
+            // to the other data proxy
+            let data_map = object_handler_map.clone();
+            let sync_sender_clone = sync_sender.clone();
+            let request_sender_clone = request_sender.clone();
+            tokio::spawn(async move {
+                let mut counter = 0;
+                while let Some(response) = response_stream.message().await? {
+                    match response.message {
+                        Some(ResponseMessage::Handshake(_)) => {
+                            continue;
+                        }
+                        Some(ResponseMessage::Skip(Skip { object_id })) => {
+                            // As long as servers are sending skip before any object info this should be safe
+                            data_map.remove(&object_id);
+                            if data_map.is_empty() {
+                                // send finish, if no object was processed
+                                sync_sender_clone.send(RcvSync::Finish).await.map_err(|e| {
+                                    tracing::error!(error = ?e, msg = e.to_string());
+                                    e
+                                })?;
+                                break;
                             }
-                            Some(ResponseMessage::Skip(Skip { object_id })) => {
-                                // As long as servers are sending skip before any object info this should be safe
-                                data_map.remove(&object_id);
-                                if data_map.is_empty() {
-                                    // send finish, if no object was processed
-                                    sync_sender_clone.send(RcvSync::Finish).await.map_err(|e| {
-                                        tracing::error!(error = ?e, msg = e.to_string());
-                                        e
-                                    })?;
-                                    break;
-                                }
+                        }
+                        Some(ResponseMessage::ObjectInfo(ObjectInfo {
+                            object_id,
+                            chunks,
+                            raw_size,
+                            ..
+                        })) => {
+                            counter += 1;
+                            trace!(object_id, chunks, raw_size);
+                            // If ObjectInfo is sent, an init msg is collected in sync ...
+                            let id = DieselUlid::from_str(&object_id).map_err(|e| {
+                                tracing::error!(error = ?e, msg = e.to_string());
+                                e
+                            })?;
+                            if let Some(entry) = data_map.get(&object_id) {
+                                let mut guard = entry.write().await;
+                                guard.update_state(chunks, raw_size);
+                            } else {
+                                // If no entry is found, abort sync
+                                request_sender_clone
+                                    .send(
+                                        PullReplicationRequest {
+                                            message: Some(
+                                                Message::ErrorMessage(
+                                                    aruna_rust_api::api::dataproxy::services::v2::ErrorMessage {
+                                                        error: Some(
+                                                            error_message::Error::Abort(Empty{})
+                                                        )
+                                                    }
+                                                )
+                                            )
+                                        }
+                                    )
+                                    .await.map_err(|e| {
+                                    tracing::error!(error = ?e, msg = e.to_string());
+                                    e
+                                })?;
                             }
-                            Some(ResponseMessage::ObjectInfo(ObjectInfo {
-                                object_id,
-                                chunks,
-                                raw_size,
-                                ..
-                            })) => {
-                                counter += 1;
-                                trace!(object_id, chunks, raw_size);
-                                // If ObjectInfo is sent, an init msg is collected in sync ...
-                                let id = DieselUlid::from_str(&object_id).map_err(|e| {
+                            sync_sender_clone
+                                .send(RcvSync::Info(id, chunks))
+                                .await
+                                .map_err(|e| {
                                     tracing::error!(error = ?e, msg = e.to_string());
                                     e
                                 })?;
-                                if let Some(entry) = data_map.get(&object_id) {
-                                    let mut guard = entry.write().await;
-                                    guard.update_state(chunks, raw_size);
-                                } else {
-                                    // If no entry is found, abort sync
-                                    request_sender_clone
-                                        .send(
-                                            PullReplicationRequest {
-                                                message: Some(
-                                                    Message::ErrorMessage(
-                                                        aruna_rust_api::api::dataproxy::services::v2::ErrorMessage {
-                                                            error: Some(
-                                                                error_message::Error::Abort(Empty{})
-                                                            )
-                                                        }
-                                                    )
-                                                )
-                                            }
-                                        )
-                                        .await.map_err(|e| {
-                                        tracing::error!(error = ?e, msg = e.to_string());
-                                        e
-                                    })?;
-                                }
+                            // ... and then ObjectInfo gets acknowledged
+                            request_sender_clone
+                                .send(PullReplicationRequest {
+                                    message: Some(Message::InfoAckMessage(InfoAckMessage {
+                                        object_id,
+                                    })),
+                                })
+                                .await
+                                .map_err(|e| {
+                                    tracing::error!(error = ?e, msg = e.to_string());
+                                    e
+                                })?;
+                            // This is needed to keep backend task in sync
+                            if counter == 1 {
+                                start_sender.send(true).await.map_err(|e| {
+                                    tracing::error!(error = ?e, msg = e.to_string());
+                                    e
+                                })?;
+                            }
+                        }
+                        Some(ResponseMessage::Chunk(Chunk {
+                            object_id,
+                            chunk_idx,
+                            data,
+                            checksum,
+                        })) => {
+                            // If an entry is created inside the object_handler_map ...
+                            if let Some(entry) = data_map.get(&object_id) {
+                                let sender = entry.read().await.get_sdx();
+                                // Chunks get processed
+                                let chunk = DataChunk {
+                                    object_id: object_id.clone(),
+                                    chunk_idx,
+                                    data,
+                                    checksum,
+                                };
+                                sender.send(chunk).await?;
+                                let id = DieselUlid::from_str(&object_id)?;
+                                // Message is send to sync
                                 sync_sender_clone
-                                    .send(RcvSync::Info(id, chunks))
+                                    .send(RcvSync::Chunk(id, chunk_idx))
                                     .await
                                     .map_err(|e| {
                                         tracing::error!(error = ?e, msg = e.to_string());
                                         e
                                     })?;
-                                // ... and then ObjectInfo gets acknowledged
+                                // Message is acknowledged
                                 request_sender_clone
                                     .send(PullReplicationRequest {
-                                        message: Some(Message::InfoAckMessage(InfoAckMessage {
+                                        message: Some(Message::ChunkAckMessage(ChunkAckMessage {
                                             object_id,
+                                            chunk_idx,
                                         })),
                                     })
                                     .await
@@ -371,350 +474,295 @@ impl ReplicationHandler {
                                         tracing::error!(error = ?e, msg = e.to_string());
                                         e
                                     })?;
-                                // This is needed to keep backend task in sync
-                                if counter == 1 {
-                                    start_sender.send(true).await.map_err(|e| {
+                            } else {
+                                // If no entry is found, ObjectInfo was not send
+                                request_sender_clone
+                                    .send(
+                                        PullReplicationRequest {
+                                            message: Some(
+                                                Message::ErrorMessage(
+                                                    aruna_rust_api::api::dataproxy::services::v2::ErrorMessage {
+                                                        error: Some(
+                                                            error_message::Error::RetryObjectId(
+                                                                object_id,
+                                                            )
+                                                        )
+                                                    }
+                                                )
+                                            )
+                                        }
+                                    )
+                                    .await.map_err(|e| {
                                         tracing::error!(error = ?e, msg = e.to_string());
                                         e
                                     })?;
-                                }
-                            }
-                            Some(ResponseMessage::Chunk(Chunk {
-                                object_id,
-                                chunk_idx,
-                                data,
-                                checksum,
-                            })) => {
-                                // If an entry is created inside the object_handler_map ...
-                                if let Some(entry) = data_map.get(&object_id) {
-                                    let sender = entry.read().await.get_sdx();
-                                    // Chunks get processed
-                                    let chunk = DataChunk {
-                                        object_id: object_id.clone(),
-                                        chunk_idx,
-                                        data,
-                                        checksum,
-                                    };
-                                    sender.send(chunk).await?;
-                                    let id = DieselUlid::from_str(&object_id)?;
-                                    // Message is send to sync
-                                    sync_sender_clone
-                                        .send(RcvSync::Chunk(id, chunk_idx))
-                                        .await
-                                        .map_err(|e| {
-                                            tracing::error!(error = ?e, msg = e.to_string());
-                                            e
-                                        })?;
-                                    // Message is acknowledged
-                                    request_sender_clone
-                                        .send(PullReplicationRequest {
-                                            message: Some(Message::ChunkAckMessage(
-                                                ChunkAckMessage {
-                                                    object_id,
-                                                    chunk_idx,
-                                                },
-                                            )),
-                                        })
-                                        .await
-                                        .map_err(|e| {
-                                            tracing::error!(error = ?e, msg = e.to_string());
-                                            e
-                                        })?;
-                                } else {
-                                    // If no entry is found, ObjectInfo was not send
-                                    request_sender_clone
-                                        .send(
-                                            PullReplicationRequest {
-                                                message: Some(
-                                                    Message::ErrorMessage(
-                                                        aruna_rust_api::api::dataproxy::services::v2::ErrorMessage {
-                                                            error: Some(
-                                                                error_message::Error::RetryObjectId(
-                                                                    object_id,
-                                                                )
-                                                            )
-                                                        }
-                                                    )
-                                                )
-                                            }
-                                        )
-                                        .await.map_err(|e| {
-                                            tracing::error!(error = ?e, msg = e.to_string());
-                                            e
-                                        })?;
-                                }
-                            }
-                            Some(ResponseMessage::FinishMessage(..)) => return Ok(()),
-                            None => {
-                                return Err(anyhow!(
-                                    "No message provided in PullReplicationResponse"
-                                ))
                             }
                         }
+                        Some(ResponseMessage::FinishMessage(..)) => return Ok(()),
+                        None => {
+                            return Err(anyhow!("No message provided in PullReplicationResponse"))
+                        }
                     }
-                    // Ok::<(), anyhow::Error>(())
-                    Err(anyhow!("Stream closed without FinishMessage"))
-                });
-
-                // Sync handler
-                tokio::spawn(async move {
-                    let mut sync = HashSet::default();
-                    // Every InfoMsg and ChunkMsg is stored
-                    while let Ok(msg) = sync_receiver.recv().await {
-                        match msg {
-                            info @ RcvSync::Info(..) => {
-                                sync.insert(info);
-                            }
-                            chunk @ RcvSync::Chunk(..) => {
-                                sync.insert(chunk);
-                            }
-                            // If finish is called, all stored messages will be returned
-                            RcvSync::Finish => {
-                                finish_sender.send(sync.clone()).await.map_err(|e| {
-                                    tracing::error!(error = ?e, msg = e.to_string());
-                                    e
-                                })?;
-                            }
+                }
+                // Ok::<(), anyhow::Error>(())
+                Err(anyhow!("Stream closed without FinishMessage"))
+            });
+
+            // Sync handler
+            tokio::spawn(async move {
+                let mut sync = HashSet::default();
+                // Every InfoMsg and ChunkMsg is stored
+                while let Ok(msg) = sync_receiver.recv().await {
+                    match msg {
+                        info @ RcvSync::Info(..) => {
+                            sync.insert(info);
+                        }
+                        chunk @ RcvSync::Chunk(..) => {
+                            sync.insert(chunk);
+                        }
+                        // If finish is called, all stored messages will be returned
+                        RcvSync::Finish => {
+                            finish_sender.send(sync.clone()).await.map_err(|e| {
+                                tracing::error!(error = ?e, msg = e.to_string());
+                                e
+                            })?;
                         }
                     }
-                    Ok::<(), anyhow::Error>(())
-                });
+                }
+                Ok::<(), anyhow::Error>(())
+            });
+
+            // Process each object
+            let cache = self.cache.clone();
+            let backend = self.backend.clone();
+            let limiter = self.limiter.clone();
+            let query_handler = query_handler.clone();
+            let request_sdx = request_sender.clone();
+            let finished_objects: Arc<DashMap<Direction, bool, RandomState>> =
+                Arc::new(DashMap::default()); // Syncs if object is already synced
+            let finished_clone = finished_objects.clone();
+            tokio::spawn(async move {
+                // For now, every entry of the object_handler_map is processed
+                // consecutively
+                while start_receiver.recv().await.is_ok() {
+                    let mut batch_counter = 0;
+                    loop {
+                        batch_counter += 1;
+                        let mut batch = Vec::new();
+                        for entry in object_handler_map.iter() {
+                            let (key, value) = entry.pair();
+                            batch.push((key.clone(), value.clone()));
+                        }
+                        for (id, object_state) in batch.iter() {
+                            trace!("processing: {}", id);
+                            let object_id = DieselUlid::from_str(id)?;
+
+                            // The object gets queried
+                            let (object, location) =
+                                cache.get_resource_cloned(&object_id, false).await?;
+                            trace!(?object);
+
+                            let mut location = if location.is_some() {
+                                finished_clone.insert(Direction::Pull(object_id), true);
+                                object_handler_map.remove(id);
+                                continue;
+                            } else if !object_state.read().await.is_synced() {
+                                trace!("skipping object");
+                                continue;
+                            } else {
+                                backend
+                                    .initialize_location(
+                                        &object,
+                                        object_state.read().await.get_size(),
+                                        cache.get_single_parent(&object.id).await?,
+                                        false,
+                                    )
+                                    .await
+                                    .map_err(|e| {
+                                        tracing::error!(error = ?e, msg = e.to_string());
+                                        e
+                                    })?
+                            };
+                            trace!("Load into backend");
+                            // Send Chunks get processed
+                            ReplicationHandler::load_into_backend(
+                                object_state.read().await.get_rcv(),
+                                request_sdx.clone(),
+                                sync_sender.clone(),
+                                &mut location,
+                                backend.clone(),
+                                limiter.clone(),
+                                object_state.read().await.get_chunks()?,
+                            )
+                            .await
+                            .map_err(|e| {
+                                tracing::error!(error = ?e, msg = e.to_string());
+                                e
+                            })?;
 
-                // Process each object
-                let cache = self.cache.clone();
-                let backend = self.backend.clone();
-                let query_handler = query_handler.clone();
-                let request_sdx = request_sender.clone();
-                let finished_objects: Arc<DashMap<Direction, bool, RandomState>> =
-                    Arc::new(DashMap::default()); // Syncs if object is already synced
-                let finished_clone = finished_objects.clone();
-                tokio::spawn(async move {
-                    // For now, every entry of the object_handler_map is processed
-                    // consecutively
-                    while start_receiver.recv().await.is_ok() {
-                        let mut batch_counter = 0;
-                        loop {
-                            batch_counter += 1;
-                            let mut batch = Vec::new();
-                            for entry in object_handler_map.iter() {
-                                let (key, value) = entry.pair();
-                                batch.push((key.clone(), value.clone()));
-                            }
-                            for (id, object_state) in batch.iter() {
-                                trace!("processing: {}", id);
-                                let object_id = DieselUlid::from_str(id)?;
-
-                                // The object gets queried
-                                let (object, location) =
-                                    cache.get_resource_cloned(&object_id, false).await?;
-                                trace!(?object);
-
-                                let mut location = if location.is_some() {
-                                    finished_clone.insert(Direction::Pull(object_id), true);
-                                    object_handler_map.remove(id);
-                                    continue;
-                                } else if !object_state.read().await.is_synced() {
-                                    trace!("skipping object");
-                                    continue;
-                                } else {
-                                    backend
-                                        .initialize_location(
-                                            &object,
-                                            object_state.read().await.get_size(),
-                                            cache.get_single_parent(&object.id).await?,
-                                            false,
-                                        )
-                                        .await
-                                        .map_err(|e| {
-                                            tracing::error!(error = ?e, msg = e.to_string());
-                                            e
-                                        })?
-                                };
-                                trace!("Load into backend");
-                                // Send Chunks get processed
-                                ReplicationHandler::load_into_backend(
-                                    object_state.read().await.get_rcv(),
-                                    request_sdx.clone(),
-                                    sync_sender.clone(),
-                                    &mut location,
-                                    backend.clone(),
-                                    object_state.read().await.get_chunks()?,
-                                )
+                            trace!("Upsert object");
+                            // TODO: This should probably happen after checking if all chunks were processed
+                            // Sync with cache and db
+                            cache.upsert_object(object.clone()).await?;
+
+                            cache.add_location_with_binding(object.id, location).await?;
+
+                            trace!("Update status");
+                            // Send UpdateStatus to server
+                            query_handler
+                                .update_replication_status(UpdateReplicationStatusRequest {
+                                    object_id: object.id.to_string(),
+                                    endpoint_id: self_id.clone(),
+                                    status: ReplicationStatus::Finished as i32,
+                                })
                                 .await
                                 .map_err(|e| {
                                     tracing::error!(error = ?e, msg = e.to_string());
                                     e
                                 })?;
-
-                                trace!("Upsert object");
-                                // TODO: This should probably happen after checking if all chunks were processed
-                                // Sync with cache and db
-                                cache.upsert_object(object.clone()).await?;
-
-                                cache.add_location_with_binding(object.id, location).await?;
-
-                                trace!("Update status");
-                                // Send UpdateStatus to server
-                                query_handler
-                                    .update_replication_status(UpdateReplicationStatusRequest {
-                                        object_id: object.id.to_string(),
-                                        endpoint_id: self_id.clone(),
-                                        status: ReplicationStatus::Finished as i32,
-                                    })
-                                    .await
-                                    .map_err(|e| {
+                            {
+                                trace!("before entry remove");
+                                object_handler_map.remove(id);
+                                trace!("after entry remove");
+                            }
+                            trace!( msg="Removed entry from map", map = ?object_handler_map);
+                        }
+                        if object_handler_map.is_empty() {
+                            trace!("Object handler map is empty, finishing replication... ");
+                            // Check if all chunks found in object infos are also processed
+                            sync_sender.send(RcvSync::Finish).await.map_err(|e| {
+                                tracing::error!(error = ?e, msg = e.to_string());
+                                e
+                            })?;
+                            break;
+                        } else if batch_counter > 20 {
+                            // Exit after arbitrary number of tries
+                            request_sdx.send(
+                                PullReplicationRequest {
+                                                message: Some(
+                                                    Message::ErrorMessage(
+                                                        aruna_rust_api::api::dataproxy::services::v2::ErrorMessage {
+                                                            error: Some(
+                                                                error_message::Error::Abort(Empty{})
+                                                            )
+                                                        }
+                                                    )
+                                                )
+                                            }
+                                    ).await.map_err(|e| {
                                         tracing::error!(error = ?e, msg = e.to_string());
                                         e
                                     })?;
-                                {
-                                    trace!("before entry remove");
-                                    object_handler_map.remove(id);
-                                    trace!("after entry remove");
-                                }
-                                trace!( msg="Removed entry from map", map = ?object_handler_map);
-                            }
-                            if object_handler_map.is_empty() {
-                                trace!("Object handler map is empty, finishing replication... ");
-                                // Check if all chunks found in object infos are also processed
-                                sync_sender.send(RcvSync::Finish).await.map_err(|e| {
-                                    tracing::error!(error = ?e, msg = e.to_string());
-                                    e
-                                })?;
-                                break;
-                            } else if batch_counter > 20 {
-                                // Exit after arbitrary number of tries
-                                request_sdx.send(
-                                    PullReplicationRequest {
-                                                    message: Some(
-                                                        Message::ErrorMessage(
-                                                            aruna_rust_api::api::dataproxy::services::v2::ErrorMessage {
-                                                                error: Some(
-                                                                    error_message::Error::Abort(Empty{})
-                                                                )
-                                                            }
-                                                        )
-                                                    )
-                                                }
-                                        ).await.map_err(|e| {
-                                            tracing::error!(error = ?e, msg = e.to_string());
-                                            e
-                                        })?;
-                                sync_sender.send(RcvSync::Finish).await.map_err(|e| {
-                                    tracing::error!(error = ?e, msg = e.to_string());
-                                    e
-                                })?;
-                                break;
-                            }
+                            sync_sender.send(RcvSync::Finish).await.map_err(|e| {
+                                tracing::error!(error = ?e, msg = e.to_string());
+                                e
+                            })?;
+                            break;
                         }
                     }
+                }
 
-                    Ok::<(), anyhow::Error>(())
+                Ok::<(), anyhow::Error>(())
+            });
+
+            //TODO:
+            // - If error, maybe set endpoint_status for each failed object to Error?
+            // -> Then we do not have to do this additional check while loading into backend
+            // -> User initiated replications then need to be implemented
+            //let mut finished_objects = Vec::new();
+            while let Ok(finished) = finish_receiver.recv().await {
+                // Collection ObjectInfo
+                let inits = finished.iter().filter_map(|msg| match msg {
+                    RcvSync::Info(object_id, chunks) => Some((object_id, chunks)),
+                    _ => None,
                 });
-
-                //TODO:
-                // - If error, maybe set endpoint_status for each failed object to Error?
-                // -> Then we do not have to do this additional check while loading into backend
-                // -> User initiated replications then need to be implemented
-                //let mut finished_objects = Vec::new();
-                while let Ok(finished) = finish_receiver.recv().await {
-                    // Collection ObjectInfo
-                    let inits = finished.iter().filter_map(|msg| match msg {
-                        RcvSync::Info(object_id, chunks) => Some((object_id, chunks)),
-                        _ => None,
-                    });
-                    // For each object, check if all chunks were processed
-                    for (object_id, chunks) in inits {
-                        let collected = finished
-                            .iter()
-                            .filter_map(|msg| match msg {
-                                RcvSync::Chunk(id, idx) if object_id == id => Some(idx),
-                                _ => None,
-                            })
-                            .collect::<Vec<_>>()
-                            .len();
-                        if *chunks as usize != collected {
-                            // Look if already synced
-                            if let Some(object) = finished_objects.get(&Direction::Pull(*object_id))
-                            {
-                                let (_, is_synced) = object.pair();
-                                if *is_synced {
-                                    continue;
-                                } else {
-                                    trace!("Not all chunks received, aborting ...");
-                                    // Send abort message if not all chunks were processed
-                                    request_sender
-                                            .send(
-                                                PullReplicationRequest {
-                                                    message: Some(
-                                                        Message::ErrorMessage(
-                                                            aruna_rust_api::api::dataproxy::services::v2::ErrorMessage {
-                                                                error: Some(
-                                                                    error_message::Error::Abort(Empty{})
-                                                                )
-                                                            }
-                                                        )
-                                                    )
-                                                }
-                                            )
-                                            .await.map_err(|e| {
-                                                tracing::error!(error = ?e, msg = e.to_string());
-                                                e
-                                            })?;
-                                    return Err(anyhow!("Not all chunks received, aborting sync"));
-                                }
+                // For each object, check if all chunks were processed
+                for (object_id, chunks) in inits {
+                    let collected = finished
+                        .iter()
+                        .filter_map(|msg| match msg {
+                            RcvSync::Chunk(id, idx) if object_id == id => Some(idx),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .len();
+                    if *chunks as usize != collected {
+                        // Look if already synced
+                        if let Some(object) = finished_objects.get(&Direction::Pull(*object_id)) {
+                            let (_, is_synced) = object.pair();
+                            if *is_synced {
+                                continue;
                             } else {
                                 trace!("Not all chunks received, aborting ...");
                                 // Send abort message if not all chunks were processed
                                 request_sender
-                                            .send(
-                                                PullReplicationRequest {
-                                                    message: Some(
-                                                        Message::ErrorMessage(
-                                                            aruna_rust_api::api::dataproxy::services::v2::ErrorMessage {
-                                                                error: Some(
-                                                                    error_message::Error::Abort(Empty{})
-                                                                )
-                                                            }
-                                                        )
+                                        .send(
+                                            PullReplicationRequest {
+                                                message: Some(
+                                                    Message::ErrorMessage(
+                                                        aruna_rust_api::api::dataproxy::services::v2::ErrorMessage {
+                                                            error: Some(
+                                                                error_message::Error::Abort(Empty{})
+                                                            )
+                                                        }
                                                     )
-                                                }
-                                            )
-                                            .await.map_err(|e| {
-                                                tracing::error!(error = ?e, msg = e.to_string());
-                                                e
-                                            })?;
+                                                )
+                                            }
+                                        )
+                                        .await.map_err(|e| {
+                                            tracing::error!(error = ?e, msg = e.to_string());
+                                            e
+                                        })?;
                                 return Err(anyhow!("Not all chunks received, aborting sync"));
                             }
+                        } else {
+                            trace!("Not all chunks received, aborting ...");
+                            // Send abort message if not all chunks were processed
+                            request_sender
+                                        .send(
+                                            PullReplicationRequest {
+                                                message: Some(
+                                                    Message::ErrorMessage(
+                                                        aruna_rust_api::api::dataproxy::services::v2::ErrorMessage {
+                                                            error: Some(
+                                                                error_message::Error::Abort(Empty{})
+                                                            )
+                                                        }
+                                                    )
+                                                )
+                                            }
+                                        )
+                                        .await.map_err(|e| {
+                                            tracing::error!(error = ?e, msg = e.to_string());
+                                            e
+                                        })?;
+                            return Err(anyhow!("Not all chunks received, aborting sync"));
                         }
-                        finished_objects.insert(Direction::Pull(*object_id), false);
                     }
-                    // Send finish message if everything was processed
-                    request_sender
-                        .send(PullReplicationRequest {
-                            message: Some(Message::FinishMessage(Empty {})),
-                        })
-                        .await
-                        .map_err(|e| {
-                            tracing::error!(error = ?e, msg = e.to_string());
-                            e
-                        })?;
+                    finished_objects.insert(Direction::Pull(*object_id), false);
                 }
-                trace!("Writing results");
-                if let Some(map) = Arc::into_inner(finished_objects) {
-                    let (objects, _): (Vec<Direction>, Vec<bool>) = map.into_iter().unzip();
-                    let finished_objects = Vec::from_iter(objects);
-                    result.push((endpoint_id, finished_objects));
-                    // It is not that much of a problem if this does not get written, because it
-                    // will be skipped when the next batch gets processed by the replication
-                    // handler
-                };
+                // Send finish message if everything was processed
+                request_sender
+                    .send(PullReplicationRequest {
+                        message: Some(Message::FinishMessage(Empty {})),
+                    })
+                    .await
+                    .map_err(|e| {
+                        tracing::error!(error = ?e, msg = e.to_string());
+                        e
+                    })?;
+            }
+            trace!("Writing results");
+            if let Some(map) = Arc::into_inner(finished_objects) {
+                let (objects, _): (Vec<Direction>, Vec<bool>) = map.into_iter().unzip();
+                let finished_objects = Vec::from_iter(objects);
+                // It is not that much of a problem if this does not get written, because it
+                // will be skipped when the next batch gets processed by the replication
+                // handler
+                return Ok(Some((endpoint_id, finished_objects)));
             };
-            // Write endpoint into results
-            //result.push(*endpoint.key());
-        }
-
-        trace!(?result);
-        Ok(result)
+        };
+        Ok(None)
     }
     async fn load_into_backend(
         data_receiver: Receiver<DataChunk>,
@@ -722,6 +770,7 @@ impl ReplicationHandler {
         sync_sender: Sender<RcvSync>,
         location: &mut ObjectLocation,
         backend: Arc<Box<dyn StorageBackend>>,
+        limiter: Arc<ReplicationLimiter>,
         max_chunks: i64,
     ) -> Result<()> {
         let mut expected = 0;
@@ -732,6 +781,8 @@ impl ReplicationHandler {
         tokio::spawn(
             async move {
                 while let Ok(data) = data_receiver.recv().await {
+                    // Throttles the replication to the configured bandwidth
+                    limiter.consume(data.data.len()).await;
                     let _trace_message = format!(
                         "Received chunk with idx {:?} for object with id {:?} and size {}, expected {}, max chunks {}",
                         data.chunk_idx,
@@ -903,3 +954,16 @@ impl ReplicationHandler {
         Ok(())
     }
 }
+
+#[async_trait::async_trait]
+impl ObjectPuller for ReplicationHandler {
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn pull(&self, object_id: DieselUlid, endpoint_id: DieselUlid) -> Result<()> {
+        self.process_endpoint(endpoint_id, vec![Direction::Pull(object_id)])
+            .await?;
+        if self.cache.get_location(&object_id).await.is_none() {
+            return Err(anyhow!("Object {object_id} was not replicated"));
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,16 @@
+use super::utils::attribution::check_attribution;
+use super::utils::client_ip::{check_cidr_restriction, ClientAddr};
+use super::utils::session_token::check_session_token;
+use super::utils::signed_cookie::{check_cookie_scope, get_signed_cookie};
+use crate::auth::crypto::{get_cookie_access_key, verify_session_cookie, SessionCookie};
 use crate::caching::cache::Cache;
+use crate::CONFIG;
 use s3s::{
-    auth::{S3Auth, S3AuthContext, SecretKey},
+    auth::{Credentials, S3Auth, S3AuthContext, SecretKey},
     s3_error, S3Result,
 };
 use std::sync::Arc;
-use tracing::debug;
+use tracing::{debug, info};
 
 /// Aruna authprovider
 pub struct AuthProvider {
@@ -16,6 +22,31 @@ impl AuthProvider {
     pub async fn new(cache: Arc<Cache>) -> Self {
         Self { cache }
     }
+
+    /// Verifies a signed cookie with the secret of its session, the credentials of the
+    /// session are gone once the session is revoked
+    async fn authorize_cookie(&self, cookie: &str) -> S3Result<(SessionCookie, Credentials)> {
+        let invalid = |e: anyhow::Error| {
+            debug!(error = ?e, "invalid signed cookie");
+            s3_error!(AccessDenied, "Invalid session cookie")
+        };
+        let access_key = get_cookie_access_key(cookie).map_err(invalid)?;
+        let secret_key = self
+            .cache
+            .get_secret(&access_key)
+            .await
+            .map_err(|_| s3_error!(AccessDenied, "Invalid session cookie"))?;
+        let session =
+            verify_session_cookie(secret_key.expose(), cookie, chrono::Utc::now().timestamp())
+                .map_err(invalid)?;
+        Ok((
+            session,
+            Credentials {
+                access_key,
+                secret_key,
+            },
+        ))
+    }
 }
 
 #[async_trait::async_trait]
@@ -35,12 +66,74 @@ impl S3Auth for AuthProvider {
     async fn check_access(&self, cx: &mut S3AuthContext<'_>) -> S3Result<()> {
         debug!(path = ?cx.s3_path());
 
+        // Presigned urls may be restricted to a client network
+        let trusted_proxies = CONFIG
+            .frontend
+            .as_ref()
+            .map(|frontend| frontend.trusted_proxies.as_slice())
+            .unwrap_or_default();
+        let client = cx.extensions_mut().get::<ClientAddr>().copied();
+        check_cidr_restriction(cx.uri(), cx.headers(), client.as_ref(), trusted_proxies)?;
+
         match self.cache.auth.read().await.as_ref() {
             Some(auth) => {
+                // Temporary credentials of storage roles expire with their session token
+                if let Some(credentials) = cx.credentials() {
+                    let session_scoped = self
+                        .cache
+                        .get_key_perms(&credentials.access_key)
+                        .await
+                        .is_some_and(|perms| perms.session_scoped);
+                    check_session_token(
+                        cx.uri(),
+                        cx.headers(),
+                        &credentials.access_key,
+                        credentials.secret_key.expose(),
+                        session_scoped,
+                    )?;
+                }
+                // Browsers of cookie sessions send their signed cookie instead of a signature
+                let cookie_session = match (cx.credentials(), get_signed_cookie(cx.headers())) {
+                    (None, Some(cookie)) => Some(self.authorize_cookie(&cookie).await?),
+                    _ => None,
+                };
+                let credentials = cookie_session
+                    .as_ref()
+                    .map(|(_, credentials)| credentials)
+                    .or(cx.credentials());
                 let result = auth
-                    .check_access(cx.credentials(), cx.method(), cx.s3_path(), cx.headers())
+                    .check_access(credentials, cx.method(), cx.s3_path(), cx.headers())
                     .await?;
+                if let Some((session, _)) = &cookie_session {
+                    check_cookie_scope(session, &result.objects_state.try_slice()?)?;
+                }
 
+                // Downloads of presigned urls are attributed to the identity which created them
+                let attribution = match cx.credentials() {
+                    Some(credentials) => check_attribution(
+                        cx.uri(),
+                        credentials.secret_key.expose(),
+                        result
+                            .objects_state
+                            .extract_object()
+                            .ok()
+                            .map(|(object, _)| object.id),
+                    )?
+                    .map(|attribution| (credentials.access_key.clone(), attribution)),
+                    None => None,
+                };
+                if let Some((access_key, attribution)) = attribution {
+                    info!(
+                        target: "s3_audit",
+                        access_key = %access_key,
+                        user_id = %attribution.user_id,
+                        token_id = ?attribution.token_id,
+                        resource_id = %attribution.resource_id,
+                        method = %cx.method(),
+                        "Presigned url used"
+                    );
+                    cx.extensions_mut().insert(attribution);
+                }
                 cx.extensions_mut().insert(result);
                 Ok(())
             }
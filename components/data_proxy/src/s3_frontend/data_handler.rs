@@ -1,6 +1,9 @@
 use crate::caching::cache::Cache;
 use crate::data_backends::storage_backend::StorageBackend;
 use crate::s3_frontend::utils::buffered_s3_sink::BufferedS3Sink;
+use crate::s3_frontend::utils::consolidation::spawn_consolidation;
+#[cfg(feature = "row-ranges")]
+use crate::s3_frontend::utils::object_accessor::{self, LineIndexer};
 use crate::structs::Object;
 use crate::structs::ObjectLocation;
 use anyhow::anyhow;
@@ -32,10 +35,29 @@ use tracing::Instrument;
 #[derive(Debug)]
 pub struct DataHandler {}
 
+/// The sha256 of a finalized upload differs from the one expected by the client
+#[derive(Debug)]
+pub struct ChecksumMismatch {
+    pub expected: String,
+    pub actual: String,
+}
+
+impl std::fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Expected sha256 {} but uploaded data has {}",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for ChecksumMismatch {}
+
 impl DataHandler {
     #[tracing::instrument(
         level = "trace",
-        skip(object, cache, backend, before_location, path_level)
+        skip(object, cache, backend, before_location, path_level, expected_sha256)
     )]
     pub async fn finalize_location(
         object: Object,
@@ -43,6 +65,7 @@ impl DataHandler {
         backend: Arc<Box<dyn StorageBackend>>,
         before_location: ObjectLocation,
         path_level: Option<[Option<(DieselUlid, String)>; 4]>,
+        expected_sha256: Option<String>,
     ) -> Result<()> {
         let token = if let Some(handler) = cache.auth.read().await.as_ref() {
             let Some(created_by) = object.created_by else {
@@ -75,6 +98,7 @@ impl DataHandler {
             })?
         };
 
+        let project_id = parents[0].as_ref().map(|(id, _)| *id);
         let mut new_location = backend
             .initialize_location(&object, None, parents, false)
             .await?;
@@ -107,6 +131,14 @@ impl DataHandler {
 
         trace!(part_lens = ?part_lens, "Part lengths");
 
+        #[cfg(feature = "row-ranges")]
+        let (line_indexer, line_index_recv) = if object_accessor::is_indexable(&object.name) {
+            let (indexer, recv) = LineIndexer::new(new_location.id);
+            (Some(indexer), Some(recv))
+        } else {
+            (None, None)
+        };
+
         let aswr_handle = tokio::spawn(
             async move {
                 let (tx, rx) = async_channel::bounded(10);
@@ -138,6 +170,11 @@ impl DataHandler {
 
                 asr = asr.add_transformer(uncompressed_probe);
 
+                #[cfg(feature = "row-ranges")]
+                if let Some(indexer) = line_indexer {
+                    asr = asr.add_transformer(indexer);
+                }
+
                 let (sha_transformer, sha_recv) =
                     HashingTransformer::new_with_backchannel(Sha256::new(), "sha256".to_string());
                 let (md5_transformer, md5_recv) =
@@ -231,6 +268,22 @@ impl DataHandler {
                 e
             })?;
 
+        if let Some(expected) = expected_sha256 {
+            if !expected.eq_ignore_ascii_case(&sha) {
+                error!(
+                    expected,
+                    actual = sha,
+                    "Checksum mismatch of finalized upload"
+                );
+                backend.delete_object(new_location).await?;
+                return Err(ChecksumMismatch {
+                    expected,
+                    actual: sha,
+                }
+                .into());
+            }
+        }
+
         new_location.disk_content_len = before_size as i64;
         new_location.raw_content_len = after_size as i64;
         new_location.disk_hash = Some(final_sha);
@@ -258,6 +311,11 @@ impl DataHandler {
 
             cache.update_location(object.id, new_location).await?;
 
+            #[cfg(feature = "row-ranges")]
+            if let Some(index) = line_index_recv.and_then(|recv| recv.try_recv().ok()) {
+                cache.add_line_index(index).await?;
+            }
+
             let upload_id = before_location
                 .upload_id
                 .as_ref()
@@ -266,6 +324,11 @@ impl DataHandler {
             backend.delete_object(before_location).await?;
 
             cache.delete_parts_by_upload_id(upload_id).await?;
+
+            // Many-part objects of opted-in projects are rewritten into a single blob
+            if let Some(project_id) = project_id {
+                spawn_consolidation(cache.clone(), backend.clone(), project_id, object.id).await;
+            }
         }
 
         Ok(())
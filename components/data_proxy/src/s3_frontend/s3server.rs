@@ -1,7 +1,13 @@
 use super::auth::AuthProvider;
 use super::s3service::ArunaS3Service;
+use super::utils::access_log::AccessLogger;
+use super::utils::checksum::with_checksum_trailer;
+use super::utils::client_ip::{resolve_client_ip, ClientAddr};
+use super::utils::redirect::with_redirect;
 use crate::caching::cache;
+use crate::config::{AccessLog, DownloadLimits};
 use crate::data_backends::storage_backend::StorageBackend;
+use crate::CONFIG;
 use crate::CORS_REGEX;
 use anyhow::Result;
 use futures_core::future::BoxFuture;
@@ -9,6 +15,7 @@ use futures_util::FutureExt;
 use http::HeaderValue;
 use http::Method;
 use http::StatusCode;
+use hyper::server::conn::AddrStream;
 use hyper::service::Service;
 use hyper::Server;
 use s3s::s3_error;
@@ -20,6 +27,7 @@ use s3s::S3Error;
 use std::convert::Infallible;
 use std::future::ready;
 use std::future::Ready;
+use std::net::IpAddr;
 use std::task::{Context, Poll};
 use std::{net::TcpListener, sync::Arc};
 use tracing::error;
@@ -30,20 +38,30 @@ use tracing::Instrument;
 pub struct S3Server {
     s3service: S3Service,
     address: String,
+    access_logger: Option<AccessLogger>,
 }
 
 #[derive(Clone)]
-pub struct WrappingService(SharedS3Service);
+pub struct WrappingService(SharedS3Service, Option<IpAddr>, Option<AccessLogger>); // Service, client address, access log
 
 impl S3Server {
-    #[tracing::instrument(level = "trace", skip(address, hostname, backend, cache))]
+    #[tracing::instrument(
+        level = "trace",
+        skip(address, hostname, backend, cache, access_log, download_limits)
+    )]
     pub async fn new(
         address: impl Into<String> + Copy,
         hostname: impl Into<String>,
         backend: Arc<Box<dyn StorageBackend>>,
         cache: Arc<cache::Cache>,
+        access_log: Option<&AccessLog>,
+        download_limits: Option<&DownloadLimits>,
     ) -> Result<Self> {
-        let s3service = ArunaS3Service::new(backend, cache.clone())
+        let hostname = hostname.into();
+        let access_logger = access_log.map(|config| {
+            AccessLogger::new(config, hostname.clone(), backend.clone(), cache.clone())
+        });
+        let s3service = ArunaS3Service::new(backend, cache.clone(), download_limits)
             .await
             .map_err(|e| {
                 error!(error = ?e, msg = e.to_string());
@@ -60,6 +78,7 @@ impl S3Server {
         Ok(Self {
             s3service: service,
             address: address.into(),
+            access_logger,
         })
     }
     #[tracing::instrument(level = "trace", skip(self))]
@@ -74,7 +93,10 @@ impl S3Server {
                 error!(error = ?e, msg = e.to_string());
                 tonic::Status::unauthenticated(e.to_string())
             })?
-            .serve(WrappingService(self.s3service.into_shared()).into_make_service());
+            .serve(
+                WrappingService(self.s3service.into_shared(), None, self.access_logger)
+                    .into_make_service(),
+            );
         info!("server is running at http(s)://{}/", self.address);
         Ok(tokio::spawn(server)
             .instrument(info_span!("s3_server_run"))
@@ -103,7 +125,7 @@ impl Service<hyper::Request<hyper::Body>> for WrappingService {
     }
 
     #[tracing::instrument(level = "trace", skip(self, req))]
-    fn call(&mut self, req: hyper::Request<hyper::Body>) -> Self::Future {
+    fn call(&mut self, mut req: hyper::Request<hyper::Body>) -> Self::Future {
         // Catch pre-flight OPTIONS requests
         if req.method() == Method::OPTIONS {
             let resp = Box::pin(async {
@@ -127,10 +149,27 @@ impl Service<hyper::Request<hyper::Body>> for WrappingService {
             }
         }
 
+        // Provide client address e.g. for presigned urls restricted to client networks
+        if let Some(client_addr) = self.1 {
+            req.extensions_mut().insert(ClientAddr(client_addr));
+        }
+
+        let access_log = self.2.as_ref().and_then(|logger| {
+            let trusted_proxies = CONFIG
+                .frontend
+                .as_ref()
+                .map(|frontend| frontend.trusted_proxies.as_slice())
+                .unwrap_or_default();
+            let remote_ip = self
+                .1
+                .map(|peer| resolve_client_ip(peer, req.headers(), trusted_proxies));
+            logger.start(&req, remote_ip)
+        });
+
         let mut service = self.0.clone();
         let resp = service.call(req);
         let res = resp.map(move |r| {
-            r.map(|mut r| {
+            let r = r.map(|mut r| {
                 if r.headers().contains_key("Transfer-Encoding") {
                     r.headers_mut().remove("Content-Length");
                 }
@@ -166,8 +205,18 @@ impl Service<hyper::Request<hyper::Body>> for WrappingService {
                     *status = StatusCode::from_u16(206).unwrap();
                 }
 
-                r.map(Body::from)
-            })
+                // Redirected downloads are returned as 302 (Found)
+                with_redirect(r).map(Body::from)
+            });
+            // Trailers are added last, wrapping the body as stream would drop them
+            match (access_log, r) {
+                (Some(access_log), Ok(r)) => Ok(with_checksum_trailer(access_log.finish(r))),
+                (Some(access_log), Err(err)) => {
+                    access_log.fail(&err);
+                    Err(err)
+                }
+                (None, r) => r.map(with_checksum_trailer),
+            }
         });
         res.boxed()
     }
@@ -191,8 +240,8 @@ impl WrappingService {
 #[derive(Clone)]
 pub struct MakeService<S>(S);
 
-impl<T, S: Clone> Service<T> for MakeService<S> {
-    type Response = S;
+impl Service<&AddrStream> for MakeService<WrappingService> {
+    type Response = WrappingService;
 
     type Error = Infallible;
 
@@ -204,7 +253,11 @@ impl<T, S: Clone> Service<T> for MakeService<S> {
     }
 
     #[tracing::instrument(level = "trace", skip(self))]
-    fn call(&mut self, _: T) -> Self::Future {
-        ready(Ok(self.0.clone()))
+    fn call(&mut self, conn: &AddrStream) -> Self::Future {
+        ready(Ok(WrappingService(
+            self.0 .0.clone(),
+            Some(conn.remote_addr().ip()),
+            self.0 .2.clone(),
+        )))
     }
 }
@@ -1,13 +1,35 @@
-use super::data_handler::DataHandler;
+use super::data_handler::{ChecksumMismatch, DataHandler};
+use super::utils::attestation::{verify_checksum_attestation, CHECKSUM_ATTESTATION_KEY};
+use super::utils::aws_chunked::{decode_body, decode_error, get_decoder, DecodeResult};
 use super::utils::buffered_s3_sink::BufferedS3Sink;
+use super::utils::checksum::{
+    get_checksum_headers, get_trailer_algorithm, INTERNAL_CHECKSUM_TRAILER,
+};
+use super::utils::content_disposition::{get_content_disposition, get_content_type};
+use super::utils::content_length_range::get_content_length_range;
+use super::utils::content_md5::verify_content_md5;
+#[cfg(feature = "row-ranges")]
+use super::utils::object_accessor::{self, LineIndexer};
 use super::utils::ranges::calculate_ranges;
+use super::utils::redirect::{is_redirectable, redirect_response};
+use super::utils::resume_token::{check_resume_token, issue_resume_token, RESUME_TOKEN_HEADER};
+use super::utils::throttle::DownloadThrottle;
 use crate::bundler::bundle_helper::get_bundle;
 use crate::caching::cache::Cache;
+use crate::config::{ChecksumAlgorithm, DownloadLimits, ResumeTokens};
 use crate::data_backends::storage_backend::StorageBackend;
+use crate::s3_frontend::utils::encryption::get_encryption_choice;
+use crate::s3_frontend::utils::in_flight::{
+    get_declared_sha256, verify_shared_body, FinishedUpload, InFlightKey, InFlightUpload,
+    InFlightUploads, IN_FLIGHT_WAIT_TIMEOUT,
+};
 use crate::s3_frontend::utils::list_objects::list_response;
+use crate::s3_frontend::utils::multipart::{complete_upload, copy_source_range, UploadCompletion};
+use crate::s3_frontend::utils::single_use::{get_single_use_link, SingleUseBody};
 use crate::structs::CheckAccessResult;
 use crate::structs::NewOrExistingObject;
 use crate::structs::Object as ProxyObject;
+use crate::structs::ObjectLocation;
 use crate::structs::ObjectsState;
 use crate::structs::PartETag;
 use crate::structs::TypedRelation;
@@ -20,13 +42,18 @@ use base64::engine::general_purpose;
 use base64::Engine;
 use bytes::BufMut;
 use bytes::BytesMut;
+use diesel_ulid::DieselUlid;
+use futures_util::Stream;
 use futures_util::TryStreamExt;
+use http::HeaderMap;
 use http::HeaderName;
 use http::HeaderValue;
+use http::Method;
 use md5::{Digest, Md5};
 use pithos_lib::helpers::footer_parser::Footer;
 use pithos_lib::helpers::footer_parser::FooterParser;
 use pithos_lib::helpers::notifications::Message as PithosMessage;
+use pithos_lib::helpers::structs::Range as ArunaRange;
 use pithos_lib::streamreadwrite::GenericStreamReadWriter;
 use pithos_lib::transformer::ReadWriter;
 use pithos_lib::transformers::async_sender_sink::AsyncSenderSink;
@@ -39,7 +66,9 @@ use pithos_lib::transformers::pithos_comp_enc::PithosTransformer;
 use pithos_lib::transformers::size_probe::SizeProbe;
 use pithos_lib::transformers::zstd_comp::ZstdEnc;
 use pithos_lib::transformers::zstd_decomp::ZstdDec;
+use s3s::auth::Credentials;
 use s3s::dto::*;
+use s3s::path::S3Path;
 use s3s::s3_error;
 use s3s::S3Error;
 use s3s::S3Request;
@@ -62,6 +91,9 @@ use tracing::Instrument;
 pub struct ArunaS3Service {
     backend: Arc<Box<dyn StorageBackend>>,
     cache: Arc<Cache>,
+    download_throttle: Option<Arc<DownloadThrottle>>,
+    // Shares the data of concurrent uploads if the backend deduplicates
+    in_flight_uploads: Option<Arc<InFlightUploads>>,
 }
 
 impl Debug for ArunaS3Service {
@@ -72,13 +104,309 @@ impl Debug for ArunaS3Service {
 }
 
 impl ArunaS3Service {
-    #[tracing::instrument(level = "trace", skip(backend, cache))]
-    pub async fn new(backend: Arc<Box<dyn StorageBackend>>, cache: Arc<Cache>) -> Result<Self> {
+    #[tracing::instrument(level = "trace", skip(backend, cache, download_limits))]
+    pub async fn new(
+        backend: Arc<Box<dyn StorageBackend>>,
+        cache: Arc<Cache>,
+        download_limits: Option<&DownloadLimits>,
+    ) -> Result<Self> {
         Ok(ArunaS3Service {
             backend: backend.clone(),
             cache,
+            download_throttle: download_limits.map(DownloadThrottle::start),
+            in_flight_uploads: CONFIG
+                .backend
+                .is_deduplicating()
+                .then(|| Arc::new(InFlightUploads::default())),
         })
     }
+
+    /// Parts of a multipart upload which are verified at the same time
+    fn part_verification_concurrency() -> usize {
+        CONFIG
+            .frontend
+            .as_ref()
+            .map(|frontend| frontend.part_verification_concurrency)
+            .unwrap_or(16)
+    }
+
+    /// Streams the plain data of a location, a range is translated into the
+    /// ranges of the stored chunks and cut out after decryption
+    async fn read_location(
+        &self,
+        location: &ObjectLocation,
+        range: Option<Range>,
+    ) -> S3Result<(
+        impl Stream<Item = Result<bytes::Bytes, S3Error>> + Send + Sync + 'static,
+        Option<ArunaRange>,
+    )> {
+        let location = location.clone();
+        let content_length = location.raw_content_len;
+        let (sender, receiver) = async_channel::bounded(10);
+
+        // Gets 128 kb chunks (last 2)
+
+        let footer: Option<Footer> = if location.is_pithos() {
+            let (footer_sender, footer_receiver) = async_channel::bounded(1000);
+            pin!(footer_receiver);
+            self.backend
+                .get_object(
+                    location.clone(),
+                    Some(format!("bytes=-{}", (65536 + 28) * 2)),
+                    footer_sender,
+                )
+                .await
+                .map_err(|_| {
+                    error!(error = "Unable to get encryption_footer");
+                    s3_error!(InternalError, "Unable to get encryption_footer")
+                })?;
+            let mut output = BytesMut::with_capacity((65536 + 28) * 2);
+            while let Ok(Ok(bytes)) = footer_receiver.recv().await {
+                output.put(bytes);
+            }
+
+            let mut parser = FooterParser::new(&output).unwrap();
+
+            let key = CONFIG.proxy.clone().get_private_key_x25519().map_err(|e| {
+                error!(?e, error = "Unable to get private key");
+                s3_error!(InternalError, "Unable to get private key")
+            })?;
+            parser = parser.add_recipient(&key);
+            parser = parser.parse().map_err(|e| {
+                error!(error = ?e, msg = "Unable to parse footer");
+                s3_error!(InternalError, "Unable to parse footer")
+            })?;
+
+            Some(parser.try_into().map_err(|_| {
+                error!(error = "Unable to convert footer");
+                s3_error!(InternalError, "Unable to convert footer")
+            })?)
+        } else {
+            None
+        };
+
+        let parts = if location.is_temporary {
+            let mut part_sizes = Vec::new();
+            let parts = self
+                .cache
+                .get_parts(location.upload_id.as_ref().ok_or_else(|| {
+                    error!(error = "Upload id must be specified");
+                    s3_error!(InvalidPart, "Upload id must be specified")
+                })?);
+
+            for parts in parts {
+                let full_chunks = (parts.size / (65536 + 28)) * (65536 + 28);
+                part_sizes.push(full_chunks);
+                if parts.size % (65536 + 28) != 0 {
+                    part_sizes.push(parts.size - full_chunks);
+                }
+            }
+            part_sizes
+        } else {
+            vec![footer
+                .as_ref()
+                .map(|f| {
+                    f.eof_metadata.disk_file_size
+                        - f.eof_metadata.toc_len
+                        - f.eof_metadata.encryption_len
+                        - 73
+                })
+                .unwrap_or_else(|| location.disk_content_len as u64)]
+        };
+
+        trace!("calculating ranges");
+        let (query_ranges, edit_list, actual_size, actual_range) = match calculate_ranges(
+            range,
+            content_length as u64,
+            parts
+                .first()
+                .copied()
+                .unwrap_or(location.disk_content_len as u64),
+            footer,
+            &location,
+        ) {
+            Ok((query_ranges, edit_list, actual_size, actual_range)) => {
+                (query_ranges, edit_list, actual_size, actual_range)
+            }
+            Err(err) => {
+                error!(error = ?err, "Unable to calculate ranges");
+                return Err(s3_error!(InternalError, "Unable to calculate ranges"));
+            }
+        };
+
+        trace!(?edit_list);
+
+        // Spawn get_object to fetch bytes from storage storage
+        let backend = self.backend.clone();
+        let loc_clone = location.clone();
+        trace!(?loc_clone, ?query_ranges, "spawning get_object");
+        tokio::spawn(
+            async move { backend.get_object(loc_clone, query_ranges, sender).await }
+                .instrument(info_span!("get_object")),
+        );
+        let (final_send, final_rcv) = async_channel::bounded(100);
+
+        let decryption_key = location.get_encryption_key();
+
+        trace!(parts = ?parts);
+        // Spawn final part
+        tokio::spawn(
+            async move {
+                pin!(receiver);
+                let mut asrw = GenericStreamReadWriter::new_with_sink(
+                    receiver,
+                    AsyncSenderSink::new(final_send),
+                );
+
+                if let Some(key) = decryption_key {
+                    asrw = asrw.add_transformer(ChaCha20DecParts::new_with_lengths(
+                        key,
+                        vec![actual_size],
+                    ));
+                }
+
+                if location.is_compressed() {
+                    asrw = asrw.add_transformer(ZstdDec::new());
+                }
+
+                if let Some(edit_list) = edit_list {
+                    asrw = asrw.add_transformer(Filter::new_with_edit_list(Some(edit_list)));
+                };
+
+                asrw.process().await.map_err(|e| {
+                    error!(error = ?e, msg = "Unable to process final part");
+                    s3_error!(InternalError, "Internal notifier error")
+                })?;
+
+                Ok::<_, anyhow::Error>(())
+            }
+            .instrument(info_span!("query_data")),
+        );
+
+        let final_rcv = final_rcv.map_err(|_| {
+            error!(error = "Unable to wrap final_rcv");
+            s3_error!(InternalError, "Internal processing error")
+        });
+
+        Ok((final_rcv, actual_range))
+    }
+
+    /// Encrypts and stores the data of a part of a multipart upload, returns the etag of the part
+    async fn upload_part_data(
+        &self,
+        object_id: DieselUlid,
+        location: ObjectLocation,
+        part_number: i32,
+        data: StreamingBlob,
+    ) -> S3Result<String> {
+        trace!("streaming data to backend");
+
+        let (sink, receiver) = BufferedS3Sink::new(
+            self.backend.clone(),
+            location.clone(),
+            location.upload_id.clone(),
+            Some(part_number),
+            true,
+            None,
+            true,
+        );
+
+        let mut awr = GenericStreamReadWriter::new_with_sink(data, sink);
+
+        let (before_probe, before_receiver) = SizeProbe::new();
+        awr = awr.add_transformer(before_probe);
+
+        let (after_probe, after_receiver) = SizeProbe::new();
+
+        if let Some(enc_key) = &location.get_encryption_key() {
+            trace!("adding chacha20 encryption");
+            awr = awr.add_transformer(ChaCha20Enc::new_with_fixed(*enc_key).map_err(|_| {
+                error!(error = "Unable to initialize ChaCha20Enc");
+                s3_error!(InternalError, "Internal data transformer encryption error")
+            })?);
+        }
+
+        awr = awr.add_transformer(after_probe);
+
+        awr.process().await.map_err(|_| {
+            error!(error = "Internal data transformer processing error");
+            s3_error!(InternalError, "Internal data transformer processing error")
+        })?;
+
+        let before_size = before_receiver.try_recv().map_err(|_| {
+            error!(error = "Unable to get size");
+            s3_error!(InternalError, "Unable to get size")
+        })?;
+
+        let after_size = after_receiver.try_recv().map_err(|_| {
+            error!(error = "Unable to get size");
+            s3_error!(InternalError, "Unable to get size")
+        })?;
+
+        self.cache
+            .create_multipart_upload(
+                location.upload_id.ok_or_else(|| {
+                    error!(error = "Unable to get upload_id");
+                    s3_error!(InternalError, "Unable to get upload_id")
+                })?,
+                object_id,
+                part_number as u64,
+                before_size,
+                after_size,
+            )
+            .await
+            .map_err(|_| {
+                error!(error = "Unable to create multipart upload");
+                s3_error!(InternalError, "Unable to create multipart upload")
+            })?;
+
+        let Some(receiver) = receiver else {
+            error!("receiver is none");
+            return Err(s3_error!(InternalError, "receiver is none"));
+        };
+        receiver.recv().await.map_err(|_| {
+            error!(error = "Unable to query etag");
+            s3_error!(InternalError, "Unable to query etag")
+        })
+    }
+
+    /// Decodes bodies of streaming uploads with trailing checksums (aws-chunked) while they
+    /// are stored. Chunk signatures and the trailing checksum are verified, the returned
+    /// result holds the error if the upload was rejected.
+    fn decode_streaming_body(
+        headers: &HeaderMap,
+        credentials: Option<&Credentials>,
+        body: &mut Option<StreamingBlob>,
+        content_length: &mut Option<i64>,
+    ) -> S3Result<Option<DecodeResult>> {
+        let secret_key = credentials.map(|credentials| credentials.secret_key.expose());
+        let Some((decoder, decoded_len)) = get_decoder(headers, secret_key)? else {
+            return Ok(None);
+        };
+        *content_length = decoded_len;
+        let Some(encoded) = body.take() else {
+            return Ok(None);
+        };
+        let (decoded, result) = decode_body(encoded, decoder);
+        *body = Some(decoded);
+        Ok(Some(result))
+    }
+
+    /// Algorithms of the stored checksums returned with downloads
+    fn checksum_algorithms() -> Vec<ChecksumAlgorithm> {
+        CONFIG
+            .frontend
+            .as_ref()
+            .map(|frontend| frontend.checksum_headers.clone())
+            .unwrap_or_default()
+    }
+
+    fn resume_tokens() -> Option<&'static ResumeTokens> {
+        CONFIG
+            .frontend
+            .as_ref()
+            .and_then(|frontend| frontend.resume_tokens.as_ref())
+    }
 }
 
 #[async_trait::async_trait]
@@ -110,6 +438,35 @@ impl S3 for ArunaS3Service {
             error!(error = "Unable to extract object location");
             s3_error!(InternalError, "Unable to extract object location")
         })?;
+        let trusts_client_checksums = match &objects_state {
+            ObjectsState::Regular { states, .. } => {
+                states.require_project()?.trusts_client_checksums()
+            }
+            _ => false,
+        };
+
+        // Retries of uploads which were finalized before the finish of the object failed
+        if old_location.upload_id.is_none()
+            && old_location.disk_hash.is_some()
+            && object.object_status == Status::Initializing
+        {
+            debug!(object_id = ?object.id, "Upload already finalized, retrying finish");
+            if let Some(handler) = self.cache.aruna_client.read().await.as_ref() {
+                if let Some(token) = &impersonating_token {
+                    handler
+                        .finish_object(object.id, old_location.raw_content_len, vec![], token)
+                        .await
+                        .map_err(|_| {
+                            error!(error = "Unable to finish object");
+                            s3_error!(InternalError, "Unable to create object")
+                        })?;
+                }
+            }
+            return Ok(S3Response::new(CompleteMultipartUploadOutput {
+                e_tag: Some(object.id.to_string()),
+                ..Default::default()
+            }));
+        }
 
         let parts = match req.input.multipart_upload {
             Some(parts) => parts.parts.ok_or_else(|| {
@@ -151,16 +508,52 @@ impl S3 for ArunaS3Service {
 
         let mut cumulative_size = 0;
         let mut disk_size = 0;
-        'outer: for part in parts {
-            for etag in etag_parts.iter() {
-                if part.part_number == etag.part_number as u64 {
-                    cumulative_size += part.raw_size;
-                    disk_size += part.size;
-                    continue 'outer;
-                }
+        let mut verified_parts = Vec::with_capacity(etag_parts.len());
+        let mut unlisted_parts = Vec::new();
+        for part in parts {
+            if let Some(etag) = etag_parts
+                .iter()
+                .find(|etag| part.part_number == etag.part_number as u64)
+            {
+                cumulative_size += part.raw_size;
+                disk_size += part.size;
+                verified_parts.push((etag.clone(), part));
+                continue;
             }
+            unlisted_parts.push(part.part_number);
+        }
+        if verified_parts.len() != etag_parts.len() {
+            error!(error = "Unknown part");
+            return Err(s3_error!(InvalidPart, "Unknown part"));
+        }
+        verified_parts.sort_by_key(|(etag, _)| etag.part_number);
+        let part_etags = verified_parts
+            .iter()
+            .map(|(etag, _)| etag.etag.clone())
+            .collect::<Vec<_>>();
+
+        // Parts are completed in the backend only if all of them are stored correctly.
+        // Failed completions keep the parts and leave the object unfinished for retries.
+        let completion = complete_upload(
+            self.backend.clone(),
+            old_location.clone(),
+            upload_id.to_string(),
+            verified_parts,
+            Self::part_verification_concurrency(),
+        )
+        .await
+        .map_err(|e| {
+            error!(error = ?e, "Unable to finish upload");
+            s3_error!(InvalidPart, "Unable to finish upload: {}", e)
+        })?;
+        if completion == UploadCompletion::AlreadyCompleted {
+            debug!(upload_id, "Retrying finish of completed upload");
+        }
+
+        // Parts which are not part of the completed upload are discarded
+        for part_number in unlisted_parts {
             self.cache
-                .delete_part(upload_id.to_string(), part.part_number)
+                .delete_part(upload_id.to_string(), part_number)
                 .await
                 .map_err(|_| {
                     error!(error = "Unable to delete part");
@@ -168,16 +561,52 @@ impl S3 for ArunaS3Service {
                 })?;
         }
 
-        // Does this object exists (including object id etc)
-        //req.input.multipart_upload.unwrap().
-        self.backend
-            .clone()
-            .finish_multipart_upload(old_location.clone(), etag_parts, upload_id.to_string())
-            .await
-            .map_err(|_| {
-                error!(error = "Unable to finish upload");
-                s3_error!(InternalError, "Unable to finish upload")
-            })?;
+        // Uploads composed of copied ranges are checked against the sha256 of the client
+        let expected_sha256 = req
+            .headers
+            .get(ChecksumAlgorithm::Sha256.header_name())
+            .map(|value| {
+                value.to_str().map(str::to_string).map_err(|_| {
+                    error!(error = "Invalid expected checksum");
+                    s3_error!(InvalidDigest, "Invalid expected checksum")
+                })
+            })
+            .transpose()?;
+
+        // Trusted projects accept the attested checksums of unencrypted uploads, which are
+        // corroborated by the part etags of the backend instead of waiting for the reread
+        let attested_hashes = match (
+            req.headers.get(CHECKSUM_ATTESTATION_KEY),
+            &expected_sha256,
+            req.headers.get(ChecksumAlgorithm::Md5.header_name()),
+        ) {
+            (Some(attestation), Some(sha256), Some(md5))
+                if trusts_client_checksums && old_location.get_encryption_key().is_none() =>
+            {
+                let (attestation, md5) = attestation
+                    .to_str()
+                    .ok()
+                    .zip(md5.to_str().ok())
+                    .ok_or_else(|| s3_error!(InvalidDigest, "Invalid checksum attestation"))?;
+                let secret_key = req
+                    .credentials
+                    .as_ref()
+                    .map(|credentials| credentials.secret_key.expose())
+                    .ok_or_else(|| s3_error!(AccessDenied, "Attestations require credentials"))?;
+                verify_checksum_attestation(attestation, secret_key, sha256, md5, &part_etags)?;
+                Some(vec![
+                    Hash {
+                        alg: Hashalgorithm::Sha256.into(),
+                        hash: sha256.to_ascii_lowercase(),
+                    },
+                    Hash {
+                        alg: Hashalgorithm::Md5.into(),
+                        hash: md5.to_ascii_lowercase(),
+                    },
+                ])
+            }
+            _ => None,
+        };
 
         let response = CompleteMultipartUploadOutput {
             e_tag: Some(object.id.to_string()),
@@ -195,11 +624,37 @@ impl S3 for ArunaS3Service {
                 s3_error!(InternalError, "Unable to update location")
             })?;
 
+        let path_level = objects_state.try_slice()?;
+        if expected_sha256.is_some() && attested_hashes.is_none() {
+            // The object is only finished if the uploaded data matches
+            DataHandler::finalize_location(
+                object.clone(),
+                self.cache.clone(),
+                self.backend.clone(),
+                old_location.clone(),
+                Some(path_level.clone()),
+                expected_sha256.clone(),
+            )
+            .await
+            .map_err(|e| match e.downcast_ref::<ChecksumMismatch>() {
+                Some(mismatch) => s3_error!(BadDigest, "{}", mismatch),
+                None => {
+                    error!(error = ?e, "Unable to finalize location");
+                    s3_error!(InternalError, "Unable to finalize location")
+                }
+            })?;
+        }
+
         if let Some(handler) = self.cache.aruna_client.read().await.as_ref() {
             if let Some(token) = &impersonating_token {
                 // Set id of new location to object id to satisfy FK constraint
                 let _ = handler
-                    .finish_object(object.id, cumulative_size as i64, vec![], token)
+                    .finish_object(
+                        object.id,
+                        cumulative_size as i64,
+                        attested_hashes.clone().unwrap_or_default(),
+                        token,
+                    )
                     .await
                     .map_err(|_| {
                         error!(error = "Unable to finish object");
@@ -208,13 +663,17 @@ impl S3 for ArunaS3Service {
             }
         }
 
-        tokio::spawn(DataHandler::finalize_location(
-            object,
-            self.cache.clone(),
-            self.backend.clone(),
-            old_location,
-            Some(objects_state.try_slice()?),
-        ));
+        if expected_sha256.is_none() || attested_hashes.is_some() {
+            // Attested uploads are moved into their final format without verification
+            tokio::spawn(DataHandler::finalize_location(
+                object,
+                self.cache.clone(),
+                self.backend.clone(),
+                old_location,
+                Some(path_level),
+                None,
+            ));
+        }
         debug!(?response);
         Ok(S3Response::new(response))
     }
@@ -338,6 +797,12 @@ impl S3 for ArunaS3Service {
 
         trace!(?new_object);
 
+        // Encryption choice of the upload, projects can enforce encryption
+        let enforced = states.require_project()?.enforces_encryption();
+        let encryption_choice =
+            get_encryption_choice(&req.input.metadata)?.or(new_object.get_encryption_choice());
+        new_object.set_encryption_choice(encryption_choice, enforced);
+
         let mut location = self
             .backend
             .initialize_location(&new_object, None, location_state, true)
@@ -449,8 +914,8 @@ impl S3 for ArunaS3Service {
     ) -> S3Result<S3Response<GetObjectOutput>> {
         let CheckAccessResult {
             objects_state,
+            user_state,
             headers,
-            ..
         } = req
             .extensions
             .get::<CheckAccessResult>()
@@ -505,104 +970,96 @@ impl S3 for ArunaS3Service {
             return Ok(resp);
         };
 
-        let location = location.ok_or_else(|| {
-            error!(error = "Unable to get resource");
-            s3_error!(NoSuchKey, "Object not found")
-        })?;
-        let mut content_length = location.raw_content_len;
-
-        let (sender, receiver) = async_channel::bounded(10);
-        let object = states.require_object()?;
-
-        // Gets 128 kb chunks (last 2)
-
-        let footer: Option<Footer> = if location.is_pithos() {
-            let (footer_sender, footer_receiver) = async_channel::bounded(1000);
-            pin!(footer_receiver);
-            self.backend
-                .get_object(
-                    location.clone(),
-                    Some(format!("bytes=-{}", (65536 + 28) * 2)),
-                    footer_sender,
-                )
-                .await
-                .map_err(|_| {
-                    error!(error = "Unable to get encryption_footer");
-                    s3_error!(InternalError, "Unable to get encryption_footer")
-                })?;
-            let mut output = BytesMut::with_capacity((65536 + 28) * 2);
-            while let Ok(Ok(bytes)) = footer_receiver.recv().await {
-                output.put(bytes);
-            }
-
-            let mut parser = FooterParser::new(&output).unwrap();
-
-            let key = CONFIG.proxy.clone().get_private_key_x25519().map_err(|e| {
-                error!(?e, error = "Unable to get private key");
-                s3_error!(InternalError, "Unable to get private key")
-            })?;
-            parser = parser.add_recipient(&key);
-            parser = parser.parse().map_err(|e| {
-                error!(error = ?e, msg = "Unable to parse footer");
-                s3_error!(InternalError, "Unable to parse footer")
-            })?;
-
-            Some(parser.try_into().map_err(|_| {
-                error!(error = "Unable to convert footer");
-                s3_error!(InternalError, "Unable to convert footer")
-            })?)
-        } else {
-            None
-        };
-
-        let parts = if location.is_temporary {
-            let mut part_sizes = Vec::new();
-            let parts = self
-                .cache
-                .get_parts(location.upload_id.as_ref().ok_or_else(|| {
-                    error!(error = "Upload id must be specified");
-                    s3_error!(InvalidPart, "Upload id must be specified")
-                })?);
-
-            for parts in parts {
-                let full_chunks = (parts.size / (65536 + 28)) * (65536 + 28);
-                part_sizes.push(full_chunks);
-                if parts.size % (65536 + 28) != 0 {
-                    part_sizes.push(parts.size - full_chunks);
-                }
-            }
-            part_sizes
-        } else {
-            vec![footer
-                .as_ref()
-                .map(|f| {
-                    f.eof_metadata.disk_file_size
-                        - f.eof_metadata.toc_len
-                        - f.eof_metadata.encryption_len
-                        - 73
-                })
-                .unwrap_or_else(|| location.disk_content_len as u64)]
+        // Objects replicated on access are pulled from their source with the first download
+        let location = match location {
+            Some(location) => Some(location),
+            None => {
+                let object_id = states.require_object()?.id;
+                self.cache
+                    .on_access
+                    .materialize(&object_id)
+                    .await
+                    .map_err(|e| {
+                        error!(error = ?e, msg = "Unable to replicate object");
+                        s3_error!(ServiceUnavailable, "Object is not available yet")
+                    })?;
+                self.cache.get_location_cloned(&object_id).await
+            }
         };
+        let location = location.ok_or_else(|| {
+            error!(error = "Unable to get resource");
+            s3_error!(NoSuchKey, "Object not found")
+        })?;
+        let mut content_length = location.raw_content_len;
 
-        trace!("calculating ranges");
-        let (query_ranges, edit_list, actual_size, actual_range) = match calculate_ranges(
-            req.input.range,
-            content_length as u64,
-            parts
-                .first()
-                .copied()
-                .unwrap_or(location.disk_content_len as u64),
-            footer,
-            &location,
-        ) {
-            Ok((query_ranges, edit_list, actual_size, actual_range)) => {
-                (query_ranges, edit_list, actual_size, actual_range)
+        let object = states.require_object()?;
+        object.fail_not_downloadable(&user_state)?;
+        let trailer_algorithm = get_trailer_algorithm(&req.uri)?;
+        // Resumed downloads fail before any data is served if the object changed
+        let now = chrono::Utc::now().timestamp();
+        if let Some(resume_tokens) = Self::resume_tokens() {
+            check_resume_token(
+                resume_tokens,
+                &req.headers,
+                object,
+                location.raw_content_len,
+                req.input.range.as_ref(),
+                now,
+            )?;
+        }
+
+        // Single-use links are only consumed by complete downloads through the proxy
+        let single_use = match get_single_use_link(&req.uri)? {
+            Some(link) => Some(self.cache.single_use.reserve(link)?),
+            None => None,
+        };
+
+        // Plain data is downloaded from the backend directly if it redirects downloads
+        if single_use.is_none() && is_redirectable(&location, &req.uri)? {
+            if let Some(url) = self
+                .backend
+                .presign_get_object(
+                    location.clone(),
+                    Some(get_content_disposition(&req.uri, object)),
+                    get_content_type(&req.uri, object).map(|mime| mime.to_string()),
+                )
+                .await
+                .map_err(|e| {
+                    error!(error = ?e, msg = "Unable to presign download");
+                    s3_error!(InternalError, "Unable to presign download")
+                })?
+            {
+                return redirect_response(&url);
             }
-            Err(err) => {
-                error!(error = ?err, "Unable to calculate ranges");
-                return Err(s3_error!(InternalError, "Unable to calculate ranges"));
+        }
+
+        // Row ranges are translated into byte ranges of the raw object
+        #[cfg(feature = "row-ranges")]
+        let range = match object_accessor::get_row_range(&req.uri)? {
+            Some(rows) => {
+                if req.input.range.is_some() {
+                    return Err(s3_error!(
+                        InvalidArgument,
+                        "Row ranges can not be combined with byte ranges"
+                    ));
+                }
+                let accessor =
+                    object_accessor::get_accessor(&self.cache, object, &location).await?;
+                Some(accessor.byte_range(&rows)?)
             }
+            None => req.input.range,
         };
+        #[cfg(not(feature = "row-ranges"))]
+        let range = req.input.range;
+        if single_use.is_some() && range.is_some() {
+            return Err(s3_error!(
+                InvalidArgument,
+                "Single-use links do not support range requests"
+            ));
+        }
+
+        let (final_rcv, actual_range) = self.read_location(&location, range).await?;
+        let offset = actual_range.as_ref().map_or(0, |range| range.from);
 
         let (accept_ranges, content_range) = if let Some(query_range) = actual_range {
             content_length = (query_range.to - query_range.from) as i64;
@@ -617,61 +1074,21 @@ impl S3 for ArunaS3Service {
             (None, None)
         };
 
-        trace!(?edit_list);
-
-        // Spawn get_object to fetch bytes from storage storage
-        let backend = self.backend.clone();
-        let loc_clone = location.clone();
-        trace!(?loc_clone, ?query_ranges, "spawning get_object");
-        tokio::spawn(
-            async move { backend.get_object(loc_clone, query_ranges, sender).await }
-                .instrument(info_span!("get_object")),
-        );
-        let (final_send, final_rcv) = async_channel::bounded(100);
-
-        let decryption_key = location.get_encryption_key();
-
-        trace!(parts = ?parts);
-        // Spawn final part
-        tokio::spawn(
-            async move {
-                pin!(receiver);
-                let mut asrw = GenericStreamReadWriter::new_with_sink(
-                    receiver,
-                    AsyncSenderSink::new(final_send),
-                );
-
-                if let Some(key) = decryption_key {
-                    asrw = asrw.add_transformer(ChaCha20DecParts::new_with_lengths(
-                        key,
-                        vec![actual_size],
-                    ));
-                }
-
-                if location.is_compressed() {
-                    asrw = asrw.add_transformer(ZstdDec::new());
-                }
-
-                if let Some(edit_list) = edit_list {
-                    asrw = asrw.add_transformer(Filter::new_with_edit_list(Some(edit_list)));
-                };
-
-                asrw.process().await.map_err(|e| {
-                    error!(error = ?e, msg = "Unable to process final part");
-                    s3_error!(InternalError, "Internal notifier error")
-                })?;
-
-                Ok::<_, anyhow::Error>(())
+        let body = Some(match (&self.download_throttle, single_use) {
+            (Some(throttle), Some(reservation)) => StreamingBlob::wrap(SingleUseBody::new(
+                throttle.throttle(&user_state, final_rcv),
+                reservation,
+            )),
+            (Some(throttle), None) => {
+                StreamingBlob::wrap(throttle.throttle(&user_state, final_rcv))
             }
-            .instrument(info_span!("query_data")),
-        );
-
-        let body = Some(StreamingBlob::wrap(final_rcv.map_err(|_| {
-            error!(error = "Unable to wrap final_rcv");
-            s3_error!(InternalError, "Internal processing error")
-        })));
+            (None, Some(reservation)) => {
+                StreamingBlob::wrap(SingleUseBody::new(final_rcv, reservation))
+            }
+            (None, None) => StreamingBlob::wrap(final_rcv),
+        });
 
-        let mime = mime_guess::from_path(object.name.as_str()).first();
+        let mime = get_content_type(&req.uri, object);
 
         let output = GetObjectOutput {
             body,
@@ -682,12 +1099,28 @@ impl S3 for ArunaS3Service {
             e_tag: Some(format!("-{}", object.id)),
             version_id: None,
             content_type: mime,
-            content_disposition: Some(format!(r#"attachment;filename="{}""#, object.name)),
+            content_disposition: Some(get_content_disposition(&req.uri, object)),
             ..Default::default()
         };
         debug!(?output);
 
         let mut resp = S3Response::new(output);
+        // Checksums of the whole object, also for range requests
+        for (k, v) in get_checksum_headers(object, &Self::checksum_algorithms()) {
+            resp.headers.insert(k, v);
+        }
+        if let Some(algorithm) = trailer_algorithm {
+            resp.headers.insert(
+                INTERNAL_CHECKSUM_TRAILER,
+                HeaderValue::from_static(algorithm.hash_key()),
+            );
+        }
+        if let Some(resume_tokens) = Self::resume_tokens() {
+            resp.headers.insert(
+                RESUME_TOKEN_HEADER,
+                issue_resume_token(resume_tokens, object, location.raw_content_len, offset, now)?,
+            );
+        }
         if let Some(headers) = headers {
             for (k, v) in headers {
                 resp.headers.insert(
@@ -755,7 +1188,7 @@ impl S3 for ArunaS3Service {
                     .into(),
             ),
             e_tag: Some(object.id.to_string()),
-            content_disposition: Some(format!(r#"attachment;filename="{}""#, object.name)),
+            content_disposition: Some(get_content_disposition(&req.uri, &object)),
             content_type: mime,
             ..Default::default()
         };
@@ -764,6 +1197,17 @@ impl S3 for ArunaS3Service {
         debug!(?headers);
 
         let mut resp = S3Response::new(output);
+        resp.headers.insert(
+            "x-aruna-materialized",
+            HeaderValue::from_static(if self.cache.on_access.is_materialized(&object.id) {
+                "true"
+            } else {
+                "false"
+            }),
+        );
+        for (k, v) in get_checksum_headers(&object, &Self::checksum_algorithms()) {
+            resp.headers.insert(k, v);
+        }
         if let Some(headers) = headers {
             for (k, v) in headers {
                 resp.headers.insert(
@@ -1123,8 +1567,14 @@ impl S3 for ArunaS3Service {
     #[allow(clippy::blocks_in_conditions)]
     async fn put_object(
         &self,
-        req: S3Request<PutObjectInput>,
+        mut req: S3Request<PutObjectInput>,
     ) -> S3Result<S3Response<PutObjectOutput>> {
+        let decode_result = Self::decode_streaming_body(
+            &req.headers,
+            req.credentials.as_ref(),
+            &mut req.input.body,
+            &mut req.input.content_length,
+        )?;
         match req.input.content_length {
             Some(0) | None => {
                 error!("Missing or invalid (0) content-length");
@@ -1135,6 +1585,9 @@ impl S3 for ArunaS3Service {
             }
             _ => {}
         };
+        if let Some(range) = get_content_length_range(&req.uri)? {
+            range.check(req.input.content_length.unwrap_or_default() as u64)?;
+        }
 
         let CheckAccessResult {
             objects_state,
@@ -1199,14 +1652,43 @@ impl S3 for ArunaS3Service {
 
         trace!(?new_object);
 
-        let mut location = self
-            .backend
-            .initialize_location(&new_object, req.input.content_length, location_state, false)
-            .await
-            .map_err(|_| {
-                error!(error = "Unable to create object_location");
-                s3_error!(InternalError, "Unable to create object_location")
-            })?;
+        // Encryption choice of the upload, projects can enforce encryption
+        let enforced = states.require_project()?.enforces_encryption();
+        let encryption_choice =
+            get_encryption_choice(&req.input.metadata)?.or(new_object.get_encryption_choice());
+        new_object.set_encryption_choice(encryption_choice, enforced);
+
+        // Uploads with a declared checksum share the data of an in-flight upload
+        let mut in_flight_leader = None;
+        let mut shared_upload = None;
+        if let Some(uploads) = &self.in_flight_uploads {
+            if let Some(sha256) = get_declared_sha256(req.input.checksum_sha256.as_deref())? {
+                let key = InFlightKey {
+                    project_id: states.require_project()?.id,
+                    sha256,
+                    encryption: new_object.get_encryption_choice(),
+                };
+                match uploads.join(key) {
+                    InFlightUpload::Leader(leader) => in_flight_leader = Some(leader),
+                    InFlightUpload::Follower(follower) => {
+                        trace!("Waiting for in-flight upload of the same data");
+                        shared_upload = follower.wait(IN_FLIGHT_WAIT_TIMEOUT).await;
+                    }
+                }
+            }
+        }
+
+        let mut location = match &shared_upload {
+            Some(shared) => shared.location.clone(),
+            None => self
+                .backend
+                .initialize_location(&new_object, req.input.content_length, location_state, false)
+                .await
+                .map_err(|_| {
+                    error!(error = "Unable to create object_location");
+                    s3_error!(InternalError, "Unable to create object_location")
+                })?,
+        };
         trace!(?location);
 
         trace!("Initialized data location");
@@ -1220,9 +1702,16 @@ impl S3 for ArunaS3Service {
         let (final_sha_trans, final_sha_recv) =
             HashingTransformer::new_with_backchannel(Sha256::new(), "sha256".to_string());
         let (final_size_trans, final_size_recv) = SizeProbe::new();
-
-        match req.input.body {
-            Some(data) => {
+        #[cfg(feature = "row-ranges")]
+        let line_indexer =
+            object_accessor::is_indexable(&new_object.name).then(|| LineIndexer::new(location.id));
+        #[cfg(feature = "row-ranges")]
+        let mut line_index_recv = None;
+
+        match (req.input.body, &shared_upload) {
+            // The data is already stored, the body is only verified
+            (Some(data), Some(shared)) => verify_shared_body(data, shared).await?,
+            (Some(data), None) => {
                 let (tx, rx) = async_channel::bounded(10);
 
                 let mut awr = GenericStreamReadWriter::new_with_sink(
@@ -1248,6 +1737,12 @@ impl S3 for ArunaS3Service {
                 awr = awr.add_transformer(initial_md5_trans);
                 awr = awr.add_transformer(initial_size_trans);
 
+                #[cfg(feature = "row-ranges")]
+                if let Some((indexer, recv)) = line_indexer {
+                    awr = awr.add_transformer(indexer);
+                    line_index_recv = Some(recv);
+                }
+
                 if location.is_compressed() && !location.is_pithos() {
                     trace!("adding zstd decompressor");
                     awr = awr.add_transformer(ZstdEnc::new());
@@ -1287,11 +1782,14 @@ impl S3 for ArunaS3Service {
                 awr = awr.add_transformer(final_size_trans);
 
                 awr.process().await.map_err(|_| {
-                    error!(error = "Internal data transformer processing error");
-                    s3_error!(InternalError, "Internal data transformer processing error")
+                    // Rejected chunks or checksums of streaming uploads end the body early
+                    decode_error(decode_result.as_ref()).unwrap_or_else(|| {
+                        error!(error = "Internal data transformer processing error");
+                        s3_error!(InternalError, "Internal data transformer processing error")
+                    })
                 })?;
             }
-            None => {
+            (None, _) => {
                 error!("Empty body is not allowed");
                 return Err(s3_error!(InvalidRequest, "Empty body is not allowed"));
             }
@@ -1339,28 +1837,60 @@ impl S3 for ArunaS3Service {
             )]));
         }
 
-        // Fetch calculated hashes
+        // Fetch calculated hashes, shared uploads get the hashes of the stored data
         trace!("fetching hashes");
-        let md5_initial = Some(initial_md5_recv.try_recv().map_err(|_| {
-            error!(error = "Unable to md5 hash initial data");
-            s3_error!(InternalError, "Unable to md5 hash initial data")
-        })?);
-        let sha_initial = Some(initial_sha_recv.try_recv().map_err(|_| {
-            error!(error = "Unable to sha hash initial data");
-            s3_error!(InternalError, "Unable to sha hash initial data")
-        })?);
-        let sha_final: String = final_sha_recv.try_recv().map_err(|_| {
-            error!(error = "Unable to sha hash final data");
-            s3_error!(InternalError, "Unable to sha hash final data")
-        })?;
-        let initial_size: u64 = initial_size_recv.try_recv().map_err(|_| {
-            error!(error = "Unable to get size");
-            s3_error!(InternalError, "Unable to get size")
-        })?;
-        let final_size: u64 = final_size_recv.try_recv().map_err(|_| {
-            error!(error = "Unable to get size");
-            s3_error!(InternalError, "Unable to get size")
-        })?;
+        let (md5_initial, sha_initial, sha_final, initial_size, final_size) = match &shared_upload {
+            Some(shared) => (
+                Some(shared.md5.clone()),
+                Some(shared.sha256.clone()),
+                shared.location.disk_hash.clone().unwrap_or_default(),
+                shared.location.raw_content_len as u64,
+                shared.location.disk_content_len as u64,
+            ),
+            None => {
+                let md5_initial = Some(initial_md5_recv.try_recv().map_err(|_| {
+                    error!(error = "Unable to md5 hash initial data");
+                    s3_error!(InternalError, "Unable to md5 hash initial data")
+                })?);
+                let sha_initial = Some(initial_sha_recv.try_recv().map_err(|_| {
+                    error!(error = "Unable to sha hash initial data");
+                    s3_error!(InternalError, "Unable to sha hash initial data")
+                })?);
+                let sha_final: String = final_sha_recv.try_recv().map_err(|_| {
+                    error!(error = "Unable to sha hash final data");
+                    s3_error!(InternalError, "Unable to sha hash final data")
+                })?;
+                let initial_size: u64 = initial_size_recv.try_recv().map_err(|_| {
+                    error!(error = "Unable to get size");
+                    s3_error!(InternalError, "Unable to get size")
+                })?;
+                let final_size: u64 = final_size_recv.try_recv().map_err(|_| {
+                    error!(error = "Unable to get size");
+                    s3_error!(InternalError, "Unable to get size")
+                })?;
+                (
+                    md5_initial,
+                    sha_initial,
+                    sha_final,
+                    initial_size,
+                    final_size,
+                )
+            }
+        };
+        // Uploads with a declared Content-MD5 must not be finished with different data
+        if let (Some(content_md5), Some(md5)) = (&req.input.content_md5, &md5_initial) {
+            verify_content_md5(content_md5, md5)?;
+        }
+        // Data of uploads with a declared checksum is shared with concurrent uploads
+        if let (Some(leader), Some(sha)) = (&in_flight_leader, &sha_initial) {
+            if leader.sha256() != sha {
+                debug!(declared = ?leader.sha256(), ?sha, "checksum mismatch");
+                return Err(s3_error!(
+                    BadDigest,
+                    "Checksum does not match the received data"
+                ));
+            }
+        }
 
         new_object.hashes = vec![
             (
@@ -1430,13 +1960,44 @@ impl S3 for ArunaS3Service {
             }
         }
 
-        self.cache
-            .add_location_with_binding(new_object.id, location)
-            .await
-            .map_err(|e| {
-                error!(error = ?e, msg = "Unable to add location with binding");
-                s3_error!(InternalError, "Unable to add location with binding")
+        match &shared_upload {
+            Some(shared) => {
+                self.cache
+                    .share_location(shared.object_id, new_object.id, location.id)
+                    .await
+                    .map_err(|e| {
+                        error!(error = ?e, msg = "Unable to share location");
+                        s3_error!(InternalError, "Unable to share location")
+                    })?;
+            }
+            None => {
+                self.cache
+                    .add_location_with_binding(new_object.id, location.clone())
+                    .await
+                    .map_err(|e| {
+                        error!(error = ?e, msg = "Unable to add location with binding");
+                        s3_error!(InternalError, "Unable to add location with binding")
+                    })?;
+            }
+        }
+        if let (Some(leader), Some(md5), Some(sha256)) =
+            (in_flight_leader, &md5_initial, &sha_initial)
+        {
+            leader.finish(FinishedUpload {
+                object_id: new_object.id,
+                location,
+                md5: md5.clone(),
+                sha256: sha256.clone(),
+            });
+        }
+
+        #[cfg(feature = "row-ranges")]
+        if let Some(index) = line_index_recv.and_then(|recv| recv.try_recv().ok()) {
+            self.cache.add_line_index(index).await.map_err(|e| {
+                error!(error = ?e, msg = "Unable to add line index");
+                s3_error!(InternalError, "Unable to add line index")
             })?;
+        }
 
         let output = PutObjectOutput {
             e_tag: md5_initial,
@@ -1465,8 +2026,14 @@ impl S3 for ArunaS3Service {
     #[allow(clippy::blocks_in_conditions)]
     async fn upload_part(
         &self,
-        req: S3Request<UploadPartInput>,
+        mut req: S3Request<UploadPartInput>,
     ) -> S3Result<S3Response<UploadPartOutput>> {
+        let decode_result = Self::decode_streaming_body(
+            &req.headers,
+            req.credentials.as_ref(),
+            &mut req.input.body,
+            &mut req.input.content_length,
+        )?;
         match req.input.content_length {
             Some(0) | None => {
                 error!("Missing or invalid (0) content-length");
@@ -1482,6 +2049,9 @@ impl S3 for ArunaS3Service {
                 }
             }
         };
+        if let Some(range) = get_content_length_range(&req.uri)? {
+            range.check(req.input.content_length.unwrap_or_default() as u64)?;
+        }
 
         let CheckAccessResult {
             objects_state,
@@ -1506,89 +2076,128 @@ impl S3 for ArunaS3Service {
             s3_error!(NoSuchKey, "Object not found")
         })?;
 
-        let etag = match req.input.body {
-            Some(data) => {
-                trace!("streaming data to backend");
-
-                let (sink, receiver) = BufferedS3Sink::new(
-                    self.backend.clone(),
-                    location.clone(),
-                    location.upload_id.clone(),
-                    Some(req.input.part_number),
-                    true,
-                    None,
-                    true,
-                );
-
-                let mut awr = GenericStreamReadWriter::new_with_sink(data, sink);
-
-                let (before_probe, before_receiver) = SizeProbe::new();
-                awr = awr.add_transformer(before_probe);
+        let Some(data) = req.input.body else {
+            error!("empty body is not allowed");
+            return Err(s3_error!(InvalidRequest, "Empty body is not allowed"));
+        };
+        let etag = self
+            .upload_part_data(object.id, location, req.input.part_number, data)
+            .await
+            .map_err(|err| decode_error(decode_result.as_ref()).unwrap_or(err))?;
 
-                let (after_probe, after_receiver) = SizeProbe::new();
+        let output = UploadPartOutput {
+            e_tag: Some(format!("-{}", etag)),
+            ..Default::default()
+        };
+        debug!(?output);
 
-                if let Some(enc_key) = &location.get_encryption_key() {
-                    trace!("adding chacha20 encryption");
-                    awr = awr.add_transformer(ChaCha20Enc::new_with_fixed(*enc_key).map_err(
-                        |_| {
-                            error!(error = "Unable to initialize ChaCha20Enc");
-                            s3_error!(InternalError, "Internal data transformer encryption error")
-                        },
-                    )?);
-                }
+        let mut resp = S3Response::new(output);
+        if let Some(headers) = headers {
+            for (k, v) in headers {
+                resp.headers.insert(
+                    HeaderName::from_bytes(k.as_bytes())
+                        .map_err(|_| s3_error!(InternalError, "Unable to parse header name"))?,
+                    HeaderValue::from_str(&v)
+                        .map_err(|_| s3_error!(InternalError, "Unable to parse header value"))?,
+                );
+            }
+        }
 
-                awr = awr.add_transformer(after_probe);
+        Ok(resp)
+    }
+    #[tracing::instrument(err)]
+    #[allow(clippy::blocks_in_conditions)]
+    async fn upload_part_copy(
+        &self,
+        req: S3Request<UploadPartCopyInput>,
+    ) -> S3Result<S3Response<UploadPartCopyOutput>> {
+        let CheckAccessResult {
+            objects_state,
+            headers,
+            ..
+        } = req
+            .extensions
+            .get::<CheckAccessResult>()
+            .cloned()
+            .ok_or_else(|| {
+                error!(error = "Missing data context");
+                s3_error!(UnexpectedContent, "Missing data context")
+            })?;
 
-                awr.process().await.map_err(|_| {
-                    error!(error = "Internal data transformer processing error");
-                    s3_error!(InternalError, "Internal data transformer processing error")
-                })?;
+        let (object, location) = objects_state.require_regular()?;
+        let object = object.require_object()?;
+        let location = location.ok_or_else(|| {
+            error!(error = "Unable to get resource");
+            s3_error!(NoSuchKey, "Object not found")
+        })?;
 
-                let before_size = before_receiver.try_recv().map_err(|_| {
-                    error!(error = "Unable to get size");
-                    s3_error!(InternalError, "Unable to get size")
-                })?;
+        let CopySource::Bucket { bucket, key, .. } = &req.input.copy_source else {
+            error!("Access points are not supported as copy source");
+            return Err(s3_error!(
+                NotImplemented,
+                "Access points are not supported as copy source"
+            ));
+        };
 
-                let after_size = after_receiver.try_recv().map_err(|_| {
-                    error!(error = "Unable to get size");
-                    s3_error!(InternalError, "Unable to get size")
-                })?;
+        // The source is read like a download of the requesting user
+        let CheckAccessResult {
+            objects_state: source_state,
+            user_state,
+            ..
+        } = self
+            .cache
+            .auth
+            .read()
+            .await
+            .as_ref()
+            .ok_or_else(|| {
+                error!(error = "Missing auth handler");
+                s3_error!(InternalError, "Missing auth handler")
+            })?
+            .check_access(
+                req.credentials.as_ref(),
+                &Method::GET,
+                &S3Path::Object {
+                    bucket: bucket.clone(),
+                    key: key.clone(),
+                },
+                &req.headers,
+            )
+            .await?;
+        let (source, source_location) = source_state.require_regular()?;
+        source
+            .require_object()?
+            .fail_not_downloadable(&user_state)?;
+        let source_location = source_location.ok_or_else(|| {
+            error!(error = "Unable to get copy source");
+            s3_error!(NoSuchKey, "Copy source not found")
+        })?;
 
-                self.cache
-                    .create_multipart_upload(
-                        location.upload_id.ok_or_else(|| {
-                            error!(error = "Unable to get upload_id");
-                            s3_error!(InternalError, "Unable to get upload_id")
-                        })?,
-                        object.id,
-                        req.input.part_number as u64,
-                        before_size,
-                        after_size,
-                    )
-                    .await
-                    .map_err(|_| {
-                        error!(error = "Unable to create multipart upload");
-                        s3_error!(InternalError, "Unable to create multipart upload")
-                    })?;
+        let range = copy_source_range(
+            req.input.copy_source_range.as_deref(),
+            source_location.raw_content_len as u64,
+        )
+        .map_err(|e| {
+            error!(error = ?e, "Invalid copy source range");
+            s3_error!(InvalidRange, "{}", e)
+        })?;
 
-                if let Some(r) = receiver {
-                    r.recv().await.map_err(|_| {
-                        error!(error = "Unable to query etag");
-                        s3_error!(InternalError, "Unable to query etag")
-                    })?
-                } else {
-                    error!("receiver is none");
-                    return Err(s3_error!(InternalError, "receiver is none"));
-                }
-            }
-            None => {
-                error!("empty body is not allowed");
-                return Err(s3_error!(InvalidRequest, "Empty body is not allowed"));
-            }
-        };
+        // Ranges are decrypted from the source and encrypted again with the key of the target
+        let (data, _) = self.read_location(&source_location, range).await?;
+        let etag = self
+            .upload_part_data(
+                object.id,
+                location,
+                req.input.part_number,
+                StreamingBlob::wrap(data),
+            )
+            .await?;
 
-        let output = UploadPartOutput {
-            e_tag: Some(format!("-{}", etag)),
+        let output = UploadPartCopyOutput {
+            copy_part_result: Some(CopyPartResult {
+                e_tag: Some(format!("-{}", etag)),
+                ..Default::default()
+            }),
             ..Default::default()
         };
         debug!(?output);
@@ -0,0 +1,640 @@
+use super::attribution::get_attribution_token;
+use crate::auth::crypto::verify_attribution_token;
+use crate::caching::cache::Cache;
+use crate::config::{AccessLog, AccessLogField, AccessLogTarget};
+use crate::data_backends::storage_backend::StorageBackend;
+use crate::structs::ObjectLocation;
+use async_channel::{Receiver, Sender, TrySendError};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use diesel_ulid::DieselUlid;
+use futures::Stream;
+use http::{HeaderMap, HeaderValue, Request, Response};
+use rand::Rng;
+use s3s::stream::{ByteStream, RemainingLength};
+use s3s::{Body, S3Error, StdError};
+use std::io::Write;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Single request in the S3 server access log format
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessLogRecord {
+    pub bucket_owner: Option<String>,
+    pub bucket: Option<String>,
+    pub time: DateTime<Utc>,
+    pub remote_ip: Option<IpAddr>,
+    pub requester: Option<String>,
+    pub request_id: String,
+    pub operation: String,
+    pub key: Option<String>,
+    pub request_uri: String,
+    pub http_status: u16,
+    pub error_code: Option<String>,
+    pub bytes_sent: u64,
+    pub object_size: Option<u64>,
+    pub total_time: Duration,
+    pub turn_around_time: Duration,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+    pub version_id: Option<String>,
+    // Identity which created the presigned url of the request
+    pub attribution: Option<String>,
+    // Unverified attribution token of the request url
+    pub attribution_token: Option<String>,
+}
+
+impl AccessLogRecord {
+    /// Formats the selected fields as space separated log line, missing values are written as '-'
+    pub fn format(&self, fields: &[AccessLogField]) -> String {
+        fn or_dash(value: Option<&str>) -> String {
+            value.unwrap_or("-").to_string()
+        }
+        fn quoted(value: Option<&str>) -> String {
+            value
+                .map(|value| format!("\"{}\"", value.replace('"', "\\\"")))
+                .unwrap_or_else(|| "-".to_string())
+        }
+
+        fields
+            .iter()
+            .map(|field| match field {
+                AccessLogField::BucketOwner => or_dash(self.bucket_owner.as_deref()),
+                AccessLogField::Bucket => or_dash(self.bucket.as_deref()),
+                AccessLogField::Time => {
+                    format!("[{}]", self.time.format("%d/%b/%Y:%H:%M:%S %z"))
+                }
+                AccessLogField::RemoteIp => self
+                    .remote_ip
+                    .map(|ip| ip.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                AccessLogField::Requester => or_dash(self.requester.as_deref()),
+                AccessLogField::RequestId => self.request_id.clone(),
+                AccessLogField::Operation => self.operation.clone(),
+                AccessLogField::Key => or_dash(self.key.as_deref()),
+                AccessLogField::RequestUri => quoted(Some(&self.request_uri)),
+                AccessLogField::HttpStatus => self.http_status.to_string(),
+                AccessLogField::ErrorCode => or_dash(self.error_code.as_deref()),
+                AccessLogField::BytesSent => match self.bytes_sent {
+                    0 => "-".to_string(),
+                    bytes => bytes.to_string(),
+                },
+                AccessLogField::ObjectSize => self
+                    .object_size
+                    .map(|size| size.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                AccessLogField::TotalTime => self.total_time.as_millis().to_string(),
+                AccessLogField::TurnAroundTime => self.turn_around_time.as_millis().to_string(),
+                AccessLogField::Referer => quoted(self.referer.as_deref()),
+                AccessLogField::UserAgent => quoted(self.user_agent.as_deref()),
+                AccessLogField::VersionId => or_dash(self.version_id.as_deref()),
+                AccessLogField::Attribution => or_dash(self.attribution.as_deref()),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Verifies the attribution token with the secret of the access key the
+    /// request was signed with, forged or expired tokens are not logged
+    fn resolve_attribution(&mut self, secret: &str) {
+        if let Some(token) = self.attribution_token.take() {
+            self.attribution = verify_attribution_token(secret, &token, self.time.timestamp())
+                .ok()
+                .map(|attribution| attribution.to_string());
+        }
+    }
+}
+
+/// Collects access log records of the S3 frontend and hands them to a background
+/// worker, so that writing the log never blocks a request
+#[derive(Debug, Clone)]
+pub struct AccessLogger {
+    sender: Sender<AccessLogRecord>,
+    sample_rate: f64,
+    hostname: Arc<String>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AccessLogger {
+    pub fn new(
+        config: &AccessLog,
+        hostname: String,
+        backend: Arc<Box<dyn StorageBackend>>,
+        cache: Arc<Cache>,
+    ) -> Self {
+        let (logger, receiver) = Self::channel(config, hostname);
+        let writer = AccessLogWriter::new(config, backend);
+        tokio::spawn(run_worker(
+            receiver,
+            writer,
+            config.get_fields(),
+            Some(cache),
+            Duration::from_secs(config.flush_interval),
+        ));
+        logger
+    }
+
+    pub fn channel(config: &AccessLog, hostname: String) -> (Self, Receiver<AccessLogRecord>) {
+        let (sender, receiver) = async_channel::bounded(config.buffer_size);
+        (
+            AccessLogger {
+                sender,
+                sample_rate: config.sample_rate,
+                hostname: Arc::new(hostname),
+                dropped: Arc::new(AtomicU64::new(0)),
+            },
+            receiver,
+        )
+    }
+
+    /// Starts the record of a request, returns None if the request is not sampled
+    pub fn start<B>(&self, req: &Request<B>, remote_ip: Option<IpAddr>) -> Option<PendingRecord> {
+        if self.sample_rate < 1.0 && rand::thread_rng().gen::<f64>() >= self.sample_rate {
+            return None;
+        }
+        let (bucket, key) = bucket_and_key(req, &self.hostname);
+        let resource = match (&bucket, &key) {
+            (Some(_), Some(_)) => "OBJECT",
+            (Some(_), None) => "BUCKET",
+            _ => "SERVICE",
+        };
+        let request_uri = format!(
+            "{} {} {:?}",
+            req.method(),
+            req.uri()
+                .path_and_query()
+                .map(|path| path.as_str())
+                .unwrap_or("/"),
+            req.version()
+        );
+        let record = AccessLogRecord {
+            bucket_owner: None,
+            bucket,
+            time: Utc::now(),
+            remote_ip,
+            requester: get_access_key(req),
+            request_id: DieselUlid::generate().to_string(),
+            operation: format!("REST.{}.{}", req.method(), resource),
+            key,
+            request_uri,
+            http_status: 0,
+            error_code: None,
+            bytes_sent: 0,
+            object_size: None,
+            total_time: Duration::ZERO,
+            turn_around_time: Duration::ZERO,
+            referer: header_value(req.headers(), "Referer"),
+            user_agent: header_value(req.headers(), "User-Agent"),
+            version_id: None,
+            attribution: None,
+            attribution_token: get_attribution_token(req.uri()),
+        };
+        Some(PendingRecord {
+            record,
+            started: Instant::now(),
+            logger: self.clone(),
+        })
+    }
+
+    fn log(&self, record: AccessLogRecord) {
+        match self.sender.try_send(record) {
+            Ok(_) => {}
+            Err(TrySendError::Full(_)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                // Avoid flooding the log if the buffer stays full
+                if dropped.is_power_of_two() {
+                    warn!(dropped, "Access log buffer full, dropping records");
+                }
+            }
+            Err(TrySendError::Closed(_)) => error!("Access log worker stopped"),
+        }
+    }
+}
+
+/// Record of a request whose response is still being sent
+pub struct PendingRecord {
+    record: AccessLogRecord,
+    started: Instant,
+    logger: AccessLogger,
+}
+
+impl PendingRecord {
+    /// Completes the record with the response. The record is logged once the body
+    /// was sent or the client disconnected.
+    pub fn finish(mut self, mut response: Response<Body>) -> Response<Body> {
+        self.record.http_status = response.status().as_u16();
+        self.record.turn_around_time = self.started.elapsed();
+        self.record.object_size =
+            header_value(response.headers(), "Content-Length").and_then(|len| len.parse().ok());
+        self.record.version_id = header_value(response.headers(), "x-amz-version-id");
+        if response.status().is_client_error() || response.status().is_server_error() {
+            self.record.error_code = response.body().bytes().and_then(|body| error_code(&body));
+        }
+        if let Ok(request_id) = HeaderValue::from_str(&self.record.request_id) {
+            response
+                .headers_mut()
+                .insert("x-amz-request-id", request_id);
+        }
+        response.map(|body| {
+            Body::from(Box::pin(LoggedBody {
+                inner: body,
+                pending: Some(self),
+            }) as s3s::stream::DynByteStream)
+        })
+    }
+
+    /// Logs requests which failed without a response
+    pub fn fail(mut self, err: &S3Error) {
+        self.record.http_status = err
+            .status_code()
+            .map(|status| status.as_u16())
+            .unwrap_or(500);
+        self.record.error_code = Some(err.code().as_str().to_string());
+        self.record.turn_around_time = self.started.elapsed();
+        self.record.total_time = self.record.turn_around_time;
+        self.logger.log(self.record);
+    }
+}
+
+/// Response body which counts the sent bytes and logs the record when it is dropped
+struct LoggedBody {
+    inner: Body,
+    pending: Option<PendingRecord>,
+}
+
+impl Stream for LoggedBody {
+    type Item = Result<Bytes, StdError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.inner).poll_next(cx);
+        if let Poll::Ready(Some(Ok(bytes))) = &poll {
+            if let Some(pending) = &mut self.pending {
+                pending.record.bytes_sent += bytes.len() as u64;
+            }
+        }
+        poll
+    }
+}
+
+impl ByteStream for LoggedBody {
+    fn remaining_length(&self) -> RemainingLength {
+        self.inner.remaining_length()
+    }
+}
+
+impl Drop for LoggedBody {
+    fn drop(&mut self) {
+        if let Some(PendingRecord {
+            mut record,
+            started,
+            logger,
+        }) = self.pending.take()
+        {
+            record.total_time = started.elapsed();
+            logger.log(record);
+        }
+    }
+}
+
+/// Extracts the code of S3 xml error responses
+fn error_code(body: &[u8]) -> Option<String> {
+    let body = std::str::from_utf8(body).ok()?;
+    let (_, code) = body.split_once("<Code>")?;
+    let (code, _) = code.split_once("</Code>")?;
+    Some(code.to_string())
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+/// Resolves bucket and key of virtual-hosted and path style requests
+fn bucket_and_key<B>(req: &Request<B>, hostname: &str) -> (Option<String>, Option<String>) {
+    let path = percent_decode(req.uri().path().trim_start_matches('/'));
+    let host = header_value(req.headers(), "Host")
+        .or_else(|| req.uri().authority().map(|a| a.to_string()));
+    let virtual_bucket = host.and_then(|host| {
+        host.strip_suffix(hostname)
+            .and_then(|prefix| prefix.strip_suffix('.'))
+            .map(|bucket| bucket.to_string())
+    });
+    let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_string());
+    match virtual_bucket {
+        Some(bucket) => (Some(bucket), non_empty(&path)),
+        None => match path.split_once('/') {
+            Some((bucket, key)) => (non_empty(bucket), non_empty(key)),
+            None => (non_empty(&path), None),
+        },
+    }
+}
+
+fn percent_decode(value: &str) -> String {
+    url::form_urlencoded::parse(format!("v={}", value.replace('+', "%2B")).as_bytes())
+        .next()
+        .map(|(_, value)| value.to_string())
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// Extracts the access key of signed requests and presigned urls
+fn get_access_key<B>(req: &Request<B>) -> Option<String> {
+    let credential = header_value(req.headers(), "Authorization")
+        .and_then(|auth| {
+            auth.split_once("Credential=")
+                .map(|(_, credential)| credential.to_string())
+        })
+        .or_else(|| {
+            url::form_urlencoded::parse(req.uri().query()?.as_bytes())
+                .find(|(key, _)| key == "X-Amz-Credential")
+                .map(|(_, credential)| credential.to_string())
+        })?;
+    credential
+        .split('/')
+        .next()
+        .filter(|key| !key.is_empty())
+        .map(|key| key.to_string())
+}
+
+enum AccessLogWriter {
+    Stdout,
+    Log,
+    Bucket {
+        backend: Arc<Box<dyn StorageBackend>>,
+        bucket: String,
+        prefix: String,
+        lines: Vec<String>,
+    },
+}
+
+impl AccessLogWriter {
+    fn new(config: &AccessLog, backend: Arc<Box<dyn StorageBackend>>) -> Self {
+        match config.target {
+            AccessLogTarget::Stdout => AccessLogWriter::Stdout,
+            AccessLogTarget::Log => AccessLogWriter::Log,
+            AccessLogTarget::Bucket => AccessLogWriter::Bucket {
+                backend,
+                bucket: config.bucket.clone().unwrap_or_default(),
+                prefix: config.prefix.clone(),
+                lines: Vec::new(),
+            },
+        }
+    }
+
+    fn write(&mut self, line: String) {
+        match self {
+            AccessLogWriter::Stdout => {
+                if let Err(err) = writeln!(std::io::stdout().lock(), "{line}") {
+                    error!(error = ?err, "Unable to write access log");
+                }
+            }
+            AccessLogWriter::Log => info!(target: "s3_access_log", "{line}"),
+            AccessLogWriter::Bucket { lines, .. } => lines.push(line),
+        }
+    }
+
+    /// Stores all buffered lines as one log object
+    async fn flush(&mut self) {
+        let AccessLogWriter::Bucket {
+            backend,
+            bucket,
+            prefix,
+            lines,
+        } = self
+        else {
+            return;
+        };
+        if lines.is_empty() {
+            return;
+        }
+        let mut data = lines.join("\n");
+        data.push('\n');
+        lines.clear();
+
+        let location = ObjectLocation {
+            id: DieselUlid::generate(),
+            bucket: bucket.clone(),
+            key: format!(
+                "{}{}-{}.log",
+                prefix,
+                Utc::now().format("%Y-%m-%d-%H-%M-%S"),
+                DieselUlid::generate()
+            ),
+            raw_content_len: data.len() as i64,
+            disk_content_len: data.len() as i64,
+            ..Default::default()
+        };
+        let content_len = data.len() as i64;
+        let (sender, receiver) = async_channel::bounded(1);
+        // Capacity is sufficient for the single chunk
+        let _ = sender.try_send(Ok(Bytes::from(data)));
+        drop(sender);
+        if let Err(err) = backend.put_object(receiver, location, content_len).await {
+            error!(error = ?err, "Unable to store access log");
+        }
+    }
+}
+
+async fn run_worker(
+    receiver: Receiver<AccessLogRecord>,
+    mut writer: AccessLogWriter,
+    fields: Vec<AccessLogField>,
+    cache: Option<Arc<Cache>>,
+    flush_interval: Duration,
+) {
+    let mut interval = tokio::time::interval(flush_interval);
+    loop {
+        tokio::select! {
+            record = receiver.recv() => {
+                let Ok(mut record) = record else {
+                    writer.flush().await;
+                    return;
+                };
+                if let Some(cache) = &cache {
+                    resolve_users(&mut record, cache).await;
+                }
+                writer.write(record.format(&fields));
+            }
+            _ = interval.tick() => writer.flush().await,
+        }
+    }
+}
+
+/// Replaces access keys with user ids, adds the owner of the bucket and the
+/// creator of presigned urls
+async fn resolve_users(record: &mut AccessLogRecord, cache: &Cache) {
+    if let Some(bucket) = &record.bucket {
+        record.bucket_owner = cache
+            .get_full_resource_by_path(bucket)
+            .await
+            .and_then(|project| project.created_by)
+            .map(|owner| owner.to_string());
+    }
+    if let Some(access_key) = &record.requester {
+        if let Some(perms) = cache.get_key_perms(access_key).await {
+            record.resolve_attribution(&perms.secret);
+            record.requester = Some(perms.user_id.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::crypto::{sign_attribution_token, Attribution};
+    use crate::s3_frontend::utils::attribution::ATTRIBUTION_KEY;
+
+    fn config(sample_rate: f64) -> AccessLog {
+        AccessLog {
+            target: AccessLogTarget::Stdout,
+            bucket: None,
+            prefix: String::new(),
+            sample_rate,
+            fields: None,
+            buffer_size: 10,
+            flush_interval: 60,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_object_log_line() {
+        let (logger, receiver) = AccessLogger::channel(&config(1.0), "localhost:1337".to_string());
+        let request = Request::get("http://localhost:1337/my-bucket/dir/file.txt?versionId=1")
+            .header("Host", "localhost:1337")
+            .header(
+                "Authorization",
+                "AWS4-HMAC-SHA256 Credential=ACCESSKEY/20240101/us-east-1/s3/aws4_request, SignedHeaders=host, Signature=abc",
+            )
+            .header("User-Agent", "aws-cli/2.0")
+            .body(())
+            .unwrap();
+        let pending = logger
+            .start(&request, Some("192.168.0.17".parse().unwrap()))
+            .unwrap();
+
+        let response = Response::builder()
+            .status(200)
+            .header("Content-Length", "11")
+            .body(Body::from("hello world".to_string()))
+            .unwrap();
+        let response = pending.finish(response);
+        let request_id = header_value(response.headers(), "x-amz-request-id").unwrap();
+        // Record is only logged after the body was sent
+        assert!(receiver.is_empty());
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body.len(), 11);
+
+        let record = receiver.recv().await.unwrap();
+        let line = record.format(&AccessLogField::ALL);
+        let time = format!("[{}]", record.time.format("%d/%b/%Y:%H:%M:%S %z"));
+        let expected = format!(
+            "- my-bucket {time} 192.168.0.17 ACCESSKEY {request_id} REST.GET.OBJECT dir/file.txt \
+             \"GET /my-bucket/dir/file.txt?versionId=1 HTTP/1.1\" 200 - 11 11 {} {} - \"aws-cli/2.0\" -",
+            record.total_time.as_millis(),
+            record.turn_around_time.as_millis(),
+        );
+        assert_eq!(line, expected);
+
+        // Field selection
+        assert_eq!(
+            record.format(&[
+                AccessLogField::Operation,
+                AccessLogField::HttpStatus,
+                AccessLogField::BytesSent
+            ]),
+            "REST.GET.OBJECT 200 11"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_virtual_host_and_sampling() {
+        let (logger, receiver) = AccessLogger::channel(&config(1.0), "localhost:1337".to_string());
+        let request = Request::head("http://my-bucket.localhost:1337/")
+            .header("Host", "my-bucket.localhost:1337")
+            .body(())
+            .unwrap();
+        let pending = logger.start(&request, None).unwrap();
+        let body =
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Error><Code>NoSuchBucket</Code></Error>";
+        drop(
+            pending.finish(
+                Response::builder()
+                    .status(404)
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            ),
+        );
+        let record = receiver.recv().await.unwrap();
+        assert_eq!(record.bucket.as_deref(), Some("my-bucket"));
+        assert_eq!(record.key, None);
+        assert_eq!(record.operation, "REST.HEAD.BUCKET");
+        assert_eq!(record.http_status, 404);
+        assert_eq!(record.error_code.as_deref(), Some("NoSuchBucket"));
+        // Body was never sent
+        assert_eq!(record.bytes_sent, 0);
+
+        let (logger, _) = AccessLogger::channel(&config(0.0), "localhost:1337".to_string());
+        assert!(logger.start(&request, None).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_presigned_url_attribution() {
+        let (logger, receiver) = AccessLogger::channel(&config(1.0), "localhost:1337".to_string());
+        let attribution = Attribution {
+            user_id: DieselUlid::generate(),
+            token_id: Some(DieselUlid::generate()),
+            resource_id: DieselUlid::generate(),
+            expires_at: Utc::now().timestamp() + 300,
+        };
+        let presigned = |token: &str| {
+            let query = url::form_urlencoded::Serializer::new(String::new())
+                .append_pair(
+                    "X-Amz-Credential",
+                    "ACCESSKEY/20240101/us-east-1/s3/aws4_request",
+                )
+                .append_pair(ATTRIBUTION_KEY, token)
+                .finish();
+            Request::get(format!("http://localhost:1337/my-bucket/file.txt?{query}"))
+                .body(())
+                .unwrap()
+        };
+
+        let token = sign_attribution_token("secret", &attribution).unwrap();
+        drop(
+            logger
+                .start(&presigned(&token), None)
+                .unwrap()
+                .finish(Response::new(Body::empty())),
+        );
+        let mut record = receiver.recv().await.unwrap();
+        assert_eq!(record.requester.as_deref(), Some("ACCESSKEY"));
+        record.resolve_attribution("secret");
+        assert_eq!(
+            record.format(&[AccessLogField::Attribution]),
+            attribution.to_string()
+        );
+        // The default format is not changed
+        assert!(!record
+            .format(&AccessLogField::ALL)
+            .contains(&attribution.to_string()));
+
+        // Tokens signed with another secret are not attributed
+        let forged = sign_attribution_token("other", &attribution).unwrap();
+        drop(
+            logger
+                .start(&presigned(&forged), None)
+                .unwrap()
+                .finish(Response::new(Body::empty())),
+        );
+        let mut record = receiver.recv().await.unwrap();
+        record.resolve_attribution("secret");
+        assert_eq!(record.attribution, None);
+        assert_eq!(record.format(&[AccessLogField::Attribution]), "-");
+    }
+}
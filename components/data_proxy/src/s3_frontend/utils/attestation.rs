@@ -0,0 +1,162 @@
+use base64::engine::general_purpose;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use md5::{Digest, Md5};
+use s3s::{s3_error, S3Result};
+use sha2::Sha256;
+use tracing::debug;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header of multipart completions with the checksum attestation of a trusted client
+pub const CHECKSUM_ATTESTATION_KEY: &str = "x-aruna-checksum-attestation";
+
+/// Multipart etag of the parts, the md5 of the concatenated part md5s with the number
+/// of parts as suffix. Only etags which are plain md5 hashes can be combined.
+pub fn multipart_etag(part_etags: &[String]) -> Option<String> {
+    let mut md5 = Md5::new();
+    for etag in part_etags {
+        let part = hex::decode(etag.trim_start_matches('-').trim_matches('"')).ok()?;
+        if part.len() != 16 {
+            return None;
+        }
+        md5.update(part);
+    }
+    Some(format!(
+        "{}-{}",
+        hex::encode(md5.finalize()),
+        part_etags.len()
+    ))
+}
+
+fn attestation_payload(sha256: &str, md5: &str, multipart_etag: &str) -> String {
+    format!(
+        "{}:{}:{}",
+        sha256.to_ascii_lowercase(),
+        md5.to_ascii_lowercase(),
+        multipart_etag
+    )
+}
+
+/// Verifies the attestation `<multipart_etag>.<signature>` of the hex encoded hashes
+/// a client computed during the upload. The signature is the url-safe base64 encoded
+/// HMAC-SHA256 of `<sha256>:<md5>:<multipart_etag>` with the secret key of the request,
+/// the multipart etag has to be corroborated by the etags of the stored parts.
+///
+/// The hashes themselves are not checked against the data: a client with valid
+/// credentials can attest any hashes for data whose parts it uploaded, so only
+/// projects which trust their clients accept attestations.
+pub fn verify_checksum_attestation(
+    attestation: &str,
+    secret_key: &str,
+    sha256: &str,
+    md5: &str,
+    part_etags: &[String],
+) -> S3Result<()> {
+    let (etag, signature) = attestation
+        .trim()
+        .split_once('.')
+        .ok_or_else(|| s3_error!(InvalidDigest, "Malformed checksum attestation"))?;
+    let signature = general_purpose::URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| s3_error!(InvalidDigest, "Malformed checksum attestation"))?;
+    let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes())
+        .map_err(|_| s3_error!(InternalError, "Invalid secret key"))?;
+    mac.update(attestation_payload(sha256, md5, etag).as_bytes());
+    mac.verify_slice(&signature)
+        .map_err(|_| s3_error!(AccessDenied, "Invalid checksum attestation"))?;
+
+    let stored = multipart_etag(part_etags).ok_or_else(|| {
+        s3_error!(
+            BadDigest,
+            "Stored parts can not corroborate the attestation"
+        )
+    })?;
+    if stored != etag {
+        debug!(attested = etag, stored, "checksum attestation mismatch");
+        return Err(s3_error!(
+            BadDigest,
+            "Checksum attestation does not match the stored parts"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign_checksum_attestation(
+        secret_key: &str,
+        sha256: &str,
+        md5: &str,
+        multipart_etag: &str,
+    ) -> S3Result<String> {
+        let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes())
+            .map_err(|_| s3_error!(InternalError, "Invalid secret key"))?;
+        mac.update(attestation_payload(sha256, md5, multipart_etag).as_bytes());
+        Ok(format!(
+            "{multipart_etag}.{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+        ))
+    }
+
+    fn part_etags(parts: &[&[u8]]) -> Vec<String> {
+        parts
+            .iter()
+            .map(|part| format!("-\"{}\"", hex::encode(Md5::digest(part))))
+            .collect()
+    }
+
+    fn hashes(data: &[u8]) -> (String, String) {
+        (
+            hex::encode(Sha256::digest(data)),
+            hex::encode(Md5::digest(data)),
+        )
+    }
+
+    #[test]
+    fn test_trusted_attestation_accepted() {
+        let stored = part_etags(&[b"aru", b"na"]);
+        let (sha256, md5) = hashes(b"aruna");
+        let etag = multipart_etag(&part_etags(&[b"aru", b"na"])).unwrap();
+        assert!(etag.ends_with("-2"));
+
+        let attestation = sign_checksum_attestation("secret", &sha256, &md5, &etag).unwrap();
+        assert!(
+            verify_checksum_attestation(&attestation, "secret", &sha256, &md5, &stored).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_attestation_mismatch_rejected() {
+        let stored = part_etags(&[b"aru", b"na"]);
+        let (sha256, md5) = hashes(b"aruna");
+
+        // Signed for other parts than the stored ones
+        let etag = multipart_etag(&part_etags(&[b"aru", b"NA"])).unwrap();
+        let attestation = sign_checksum_attestation("secret", &sha256, &md5, &etag).unwrap();
+        let err = verify_checksum_attestation(&attestation, "secret", &sha256, &md5, &stored)
+            .unwrap_err();
+        assert_eq!(err.code(), &s3s::S3ErrorCode::BadDigest);
+
+        // Signed with another secret or for other hashes
+        let etag = multipart_etag(&stored).unwrap();
+        let attestation = sign_checksum_attestation("other", &sha256, &md5, &etag).unwrap();
+        assert!(
+            verify_checksum_attestation(&attestation, "secret", &sha256, &md5, &stored).is_err()
+        );
+        let attestation = sign_checksum_attestation("secret", &sha256, &md5, &etag).unwrap();
+        let (other_sha256, _) = hashes(b"other");
+        assert!(
+            verify_checksum_attestation(&attestation, "secret", &other_sha256, &md5, &stored)
+                .is_err()
+        );
+
+        // Etags of encrypted parts are no plain md5 hashes
+        let encrypted = vec!["-\"not-a-md5\"".to_string()];
+        assert!(
+            verify_checksum_attestation(&attestation, "secret", &sha256, &md5, &encrypted).is_err()
+        );
+    }
+}
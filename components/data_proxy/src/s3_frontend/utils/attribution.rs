@@ -0,0 +1,78 @@
+use crate::auth::crypto::{verify_attribution_token, Attribution};
+use diesel_ulid::DieselUlid;
+use http::Uri;
+use s3s::{s3_error, S3Result};
+use tracing::debug;
+
+/// Signed query parameter of presigned urls with the identity that created them
+pub const ATTRIBUTION_KEY: &str = "x-aruna-attribution";
+
+/// Extracts the attribution token of a presigned url from its query
+pub fn get_attribution_token(uri: &Uri) -> Option<String> {
+    url::form_urlencoded::parse(uri.query()?.as_bytes())
+        .find(|(key, _)| key == ATTRIBUTION_KEY)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Verifies the attribution of a presigned url with the secret of its access key.
+/// Forged or expired tokens and tokens for another object are rejected.
+pub fn check_attribution(
+    uri: &Uri,
+    secret: &str,
+    object_id: Option<DieselUlid>,
+) -> S3Result<Option<Attribution>> {
+    let Some(token) = get_attribution_token(uri) else {
+        return Ok(None);
+    };
+    let attribution = verify_attribution_token(secret, &token, chrono::Utc::now().timestamp())
+        .map_err(|e| {
+            debug!(error = ?e, "invalid attribution token");
+            s3_error!(AccessDenied, "Invalid attribution")
+        })?;
+    if object_id.is_some_and(|id| id != attribution.resource_id) {
+        debug!(?attribution, ?object_id, "attribution for another object");
+        return Err(s3_error!(AccessDenied, "Invalid attribution"));
+    }
+    Ok(Some(attribution))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::crypto::sign_attribution_token;
+    use std::str::FromStr;
+
+    fn presigned_uri(token: &str) -> Uri {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair(
+                "X-Amz-Credential",
+                "ACCESSKEY/20240101/RegionOne/s3/aws4_request",
+            )
+            .append_pair(ATTRIBUTION_KEY, token)
+            .finish();
+        Uri::from_str(&format!("http://bucket.localhost/key?{query}")).unwrap()
+    }
+
+    #[test]
+    fn test_check_attribution() {
+        let attribution = Attribution {
+            user_id: DieselUlid::generate(),
+            token_id: Some(DieselUlid::generate()),
+            resource_id: DieselUlid::generate(),
+            expires_at: chrono::Utc::now().timestamp() + 300,
+        };
+        let uri = presigned_uri(&sign_attribution_token("secret", &attribution).unwrap());
+
+        assert_eq!(
+            check_attribution(&uri, "secret", Some(attribution.resource_id)).unwrap(),
+            Some(attribution.clone())
+        );
+        // Other objects, secrets and garbage are rejected
+        assert!(check_attribution(&uri, "secret", Some(DieselUlid::generate())).is_err());
+        assert!(check_attribution(&uri, "other", Some(attribution.resource_id)).is_err());
+        assert!(check_attribution(&presigned_uri("garbage"), "secret", None).is_err());
+        // Urls without attribution are not affected
+        let uri = Uri::from_str("http://bucket.localhost/key").unwrap();
+        assert_eq!(check_attribution(&uri, "secret", None).unwrap(), None);
+    }
+}
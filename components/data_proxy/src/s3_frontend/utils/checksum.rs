@@ -0,0 +1,218 @@
+use crate::config::ChecksumAlgorithm;
+use crate::structs::Object;
+use futures_util::StreamExt;
+use http::header::{CONTENT_LENGTH, TRAILER, TRANSFER_ENCODING};
+use http::{HeaderMap, HeaderName, HeaderValue, Uri};
+use hyper::Response;
+use md5::{Digest, Md5};
+use s3s::{s3_error, Body, S3Result};
+use sha2::Sha256;
+use std::str::FromStr;
+use tracing::{error, info_span, Instrument};
+
+/// Query parameter which requests the checksum of the sent bytes as response trailer
+pub const CHECKSUM_TRAILER_KEY: &str = "x-aruna-checksum-trailer";
+// Marks responses of get_object whose body is wrapped by the server with a trailer
+pub const INTERNAL_CHECKSUM_TRAILER: &str = "x-aruna-internal-checksum-trailer";
+
+impl ChecksumAlgorithm {
+    /// Key of the hash in the stored object hashes
+    pub fn hash_key(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Md5 => "MD5",
+            ChecksumAlgorithm::Sha256 => "SHA256",
+        }
+    }
+
+    /// Header and trailer name of the hex encoded checksum
+    pub fn header_name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Md5 => "x-aruna-checksum-md5",
+            ChecksumAlgorithm::Sha256 => "x-aruna-checksum-sha256",
+        }
+    }
+}
+
+impl FromStr for ChecksumAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "md5" => Ok(ChecksumAlgorithm::Md5),
+            "sha256" => Ok(ChecksumAlgorithm::Sha256),
+            _ => Err(anyhow::anyhow!("Unsupported checksum algorithm")),
+        }
+    }
+}
+
+/// Headers with the stored checksums of the whole object. Range requests get the
+/// same headers, they do not describe the returned range.
+pub fn get_checksum_headers(
+    object: &Object,
+    algorithms: &[ChecksumAlgorithm],
+) -> Vec<(HeaderName, HeaderValue)> {
+    algorithms
+        .iter()
+        .filter_map(|algorithm| {
+            let hash = object.hashes.get(algorithm.hash_key())?;
+            Some((
+                HeaderName::from_static(algorithm.header_name()),
+                HeaderValue::from_str(hash).ok()?,
+            ))
+        })
+        .collect()
+}
+
+/// Returns the algorithm of the checksum trailer requested via query parameter
+pub fn get_trailer_algorithm(uri: &Uri) -> S3Result<Option<ChecksumAlgorithm>> {
+    let Some(requested) = uri.query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(k, _)| k == CHECKSUM_TRAILER_KEY)
+            .map(|(_, value)| value.into_owned())
+    }) else {
+        return Ok(None);
+    };
+    ChecksumAlgorithm::from_str(&requested)
+        .map(Some)
+        .map_err(|_| s3_error!(InvalidArgument, "Unsupported checksum trailer algorithm"))
+}
+
+enum ChecksumHasher {
+    Md5(Md5),
+    Sha256(Sha256),
+}
+
+impl ChecksumHasher {
+    fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Md5 => ChecksumHasher::Md5(Md5::new()),
+            ChecksumAlgorithm::Sha256 => ChecksumHasher::Sha256(Sha256::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            ChecksumHasher::Md5(hasher) => hasher.update(data),
+            ChecksumHasher::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> String {
+        match self {
+            ChecksumHasher::Md5(hasher) => hex::encode(hasher.finalize()),
+            ChecksumHasher::Sha256(hasher) => hex::encode(hasher.finalize()),
+        }
+    }
+}
+
+/// Streams responses marked with the internal trailer header chunked and appends
+/// the checksum of the sent bytes as trailer. Other responses are returned unchanged.
+pub fn with_checksum_trailer(mut response: Response<Body>) -> Response<Body> {
+    let Some(algorithm) = response
+        .headers_mut()
+        .remove(INTERNAL_CHECKSUM_TRAILER)
+        .and_then(|value| ChecksumAlgorithm::from_str(value.to_str().ok()?).ok())
+    else {
+        return response;
+    };
+
+    let headers = response.headers_mut();
+    headers.remove(CONTENT_LENGTH);
+    headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+    headers.insert(TRAILER, HeaderValue::from_static(algorithm.header_name()));
+
+    let (parts, mut inner) = response.into_parts();
+    let (mut sender, body) = hyper::Body::channel();
+    tokio::spawn(
+        async move {
+            let mut hasher = ChecksumHasher::new(algorithm);
+            while let Some(chunk) = inner.next().await {
+                match chunk {
+                    Ok(bytes) => {
+                        hasher.update(&bytes);
+                        if sender.send_data(bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        error!(error = ?e, msg = "Unable to stream response body");
+                        sender.abort();
+                        return;
+                    }
+                }
+            }
+            let mut trailers = HeaderMap::new();
+            if let Ok(value) = HeaderValue::from_str(&hasher.finalize()) {
+                trailers.insert(HeaderName::from_static(algorithm.header_name()), value);
+            }
+            if let Err(e) = sender.send_trailers(trailers).await {
+                error!(error = ?e, msg = "Unable to send checksum trailer");
+            }
+        }
+        .instrument(info_span!("checksum_trailer")),
+    );
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::ObjectType;
+    use hyper::body::HttpBody;
+
+    #[test]
+    fn test_checksum_headers() {
+        let mut object = Object::initialize_now("data.csv".to_string(), ObjectType::Object, None);
+        let sha256 = hex::encode(Sha256::digest(b"aruna"));
+        object.hashes.insert("SHA256".to_string(), sha256.clone());
+
+        let headers = get_checksum_headers(
+            &object,
+            &[ChecksumAlgorithm::Md5, ChecksumAlgorithm::Sha256],
+        );
+        // Missing hashes are skipped
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].0, "x-aruna-checksum-sha256");
+        assert_eq!(headers[0].1, sha256.as_str());
+    }
+
+    #[test]
+    fn test_trailer_algorithm() {
+        let uri = Uri::from_str("http://bucket.localhost/data.csv").unwrap();
+        assert_eq!(get_trailer_algorithm(&uri).unwrap(), None);
+        let uri = Uri::from_str("http://bucket.localhost/data.csv?x-aruna-checksum-trailer=SHA256")
+            .unwrap();
+        assert_eq!(
+            get_trailer_algorithm(&uri).unwrap(),
+            Some(ChecksumAlgorithm::Sha256)
+        );
+        let uri = Uri::from_str("http://bucket.localhost/data.csv?x-aruna-checksum-trailer=crc32")
+            .unwrap();
+        assert!(get_trailer_algorithm(&uri).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_checksum_trailer() {
+        let response = Response::builder()
+            .header(CONTENT_LENGTH, "5")
+            .header(INTERNAL_CHECKSUM_TRAILER, "md5")
+            .body(Body::from(hyper::Body::from("aruna")))
+            .unwrap();
+        let mut response = with_checksum_trailer(response);
+        assert!(response.headers().get(INTERNAL_CHECKSUM_TRAILER).is_none());
+        assert!(response.headers().get(CONTENT_LENGTH).is_none());
+        assert_eq!(response.headers()[TRAILER], "x-aruna-checksum-md5");
+
+        let body = response.body_mut();
+        let mut data = Vec::new();
+        while let Some(chunk) = body.data().await {
+            data.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(data, b"aruna");
+        let trailers = body.trailers().await.unwrap().unwrap();
+        assert_eq!(
+            trailers["x-aruna-checksum-md5"],
+            hex::encode(Md5::digest(b"aruna")).as_str()
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use http::{HeaderMap, Uri};
+use ipnet::IpNet;
+use s3s::{s3_error, S3Result};
+use std::net::IpAddr;
+use std::str::FromStr;
+use tracing::debug;
+
+/// Signed query parameter of presigned urls which are restricted to a client network
+pub const RESTRICT_TO_CIDR_KEY: &str = "x-aruna-restrict-to-cidr";
+
+/// Remote address of the connection a request was received on
+#[derive(Debug, Clone, Copy)]
+pub struct ClientAddr(pub IpAddr);
+
+/// Extracts the network restriction of a presigned url from its query.
+pub fn get_cidr_restriction(uri: &Uri) -> S3Result<Option<IpNet>> {
+    let Some(query) = uri.query() else {
+        return Ok(None);
+    };
+    match url::form_urlencoded::parse(query.as_bytes()).find(|(key, _)| key == RESTRICT_TO_CIDR_KEY)
+    {
+        Some((_, value)) => Ok(Some(IpNet::from_str(&value).map_err(|_| {
+            s3_error!(InvalidArgument, "Invalid client network restriction")
+        })?)),
+        None => Ok(None),
+    }
+}
+
+/// Resolves the client ip of a request. The X-Forwarded-For header is only evaluated
+/// if the request was received from a trusted proxy. The header is traversed from right
+/// to left and the first address which is not a trusted proxy is the client.
+pub fn resolve_client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+    if !is_trusted(&peer) {
+        return peer;
+    }
+
+    let mut client = peer;
+    for value in headers.get_all("X-Forwarded-For").iter().rev() {
+        let Ok(value) = value.to_str() else {
+            return client;
+        };
+        for forwarded in value.rsplit(',') {
+            let Ok(forwarded) = IpAddr::from_str(forwarded.trim()) else {
+                return client;
+            };
+            client = forwarded;
+            if !is_trusted(&client) {
+                return client;
+            }
+        }
+    }
+    client
+}
+
+/// Rejects requests whose presigned url is restricted to a network the client is not part of.
+pub fn check_cidr_restriction(
+    uri: &Uri,
+    headers: &HeaderMap,
+    client: Option<&ClientAddr>,
+    trusted_proxies: &[IpNet],
+) -> S3Result<()> {
+    let Some(network) = get_cidr_restriction(uri)? else {
+        return Ok(());
+    };
+    let Some(ClientAddr(peer)) = client else {
+        return Err(s3_error!(AccessDenied, "Unknown client address"));
+    };
+
+    let client_ip = resolve_client_ip(*peer, headers, trusted_proxies);
+    if network.contains(&client_ip) {
+        Ok(())
+    } else {
+        debug!(?client_ip, ?network, "client not in restricted network");
+        Err(s3_error!(AccessDenied, "Client address not allowed"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    fn restricted_uri(network: &str) -> Uri {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair(RESTRICT_TO_CIDR_KEY, network)
+            .finish();
+        Uri::from_str(&format!("http://bucket.localhost/key?{query}")).unwrap()
+    }
+
+    #[test]
+    fn test_cidr_restriction() {
+        let uri = restricted_uri("192.168.0.0/24");
+        let headers = HeaderMap::new();
+        let in_range = ClientAddr(IpAddr::from_str("192.168.0.17").unwrap());
+        let out_of_range = ClientAddr(IpAddr::from_str("192.168.1.17").unwrap());
+
+        assert!(check_cidr_restriction(&uri, &headers, Some(&in_range), &[]).is_ok());
+        assert!(check_cidr_restriction(&uri, &headers, Some(&out_of_range), &[]).is_err());
+        assert!(check_cidr_restriction(&uri, &headers, None, &[]).is_err());
+
+        // Urls without restriction are not affected
+        let uri = Uri::from_str("http://bucket.localhost/key").unwrap();
+        assert!(check_cidr_restriction(&uri, &headers, Some(&out_of_range), &[]).is_ok());
+    }
+
+    #[test]
+    fn test_forwarded_client_ip() {
+        let uri = restricted_uri("192.168.0.0/24");
+        let proxy = ClientAddr(IpAddr::from_str("10.0.0.1").unwrap());
+        let trusted = vec![IpNet::from_str("10.0.0.0/8").unwrap()];
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Forwarded-For",
+            HeaderValue::from_static("192.168.1.5, 192.168.0.17, 10.0.0.2"),
+        );
+
+        // Header is only evaluated for trusted proxies
+        assert!(check_cidr_restriction(&uri, &headers, Some(&proxy), &trusted).is_ok());
+        assert!(check_cidr_restriction(&uri, &headers, Some(&proxy), &[]).is_err());
+
+        // Spoofed entries left of the first untrusted address are ignored
+        headers.insert(
+            "X-Forwarded-For",
+            HeaderValue::from_static("192.168.0.17, 192.168.1.5"),
+        );
+        assert!(check_cidr_restriction(&uri, &headers, Some(&proxy), &trusted).is_err());
+    }
+}
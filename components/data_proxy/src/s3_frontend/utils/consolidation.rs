@@ -0,0 +1,241 @@
+use crate::caching::cache::Cache;
+use crate::config::Consolidation;
+use crate::data_backends::storage_backend::StorageBackend;
+use crate::structs::ObjectLocation;
+use crate::CONFIG;
+use anyhow::{anyhow, bail, Result};
+use diesel_ulid::DieselUlid;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// Size of the parts the proxy writes to the backend, smaller objects are a single blob
+pub const BACKEND_PART_SIZE: i64 = 5_242_880;
+
+/// Returns true if the location is stored in multiple parts and small enough to be
+/// written as a single blob. Locations shared by deduplicated objects are skipped.
+pub fn needs_consolidation(location: &ObjectLocation, max_size: u64) -> bool {
+    !location.is_temporary
+        && location.ref_count <= 1
+        && location.disk_content_len > BACKEND_PART_SIZE
+        && location.disk_content_len as u64 <= max_size
+}
+
+/// Copies the stored bytes of a location unchanged into a single blob of a new
+/// location. The copy is verified against the stored size and disk hash and
+/// removed again if it does not match.
+pub async fn consolidate_location(
+    backend: Arc<Box<dyn StorageBackend>>,
+    location: &ObjectLocation,
+) -> Result<ObjectLocation> {
+    let new_location = ObjectLocation {
+        id: DieselUlid::generate(),
+        key: format!("{}.consolidated", location.key),
+        upload_id: None,
+        ..location.clone()
+    };
+
+    let (read_sender, read_receiver) = async_channel::bounded(10);
+    let (write_sender, write_receiver) = async_channel::bounded(10);
+    let read = backend.get_object(location.clone(), None, read_sender);
+    let write = backend.put_object(
+        write_receiver,
+        new_location.clone(),
+        location.disk_content_len,
+    );
+    let forward = async move {
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        while let Ok(chunk) = read_receiver.recv().await {
+            let chunk = chunk.map_err(|e| anyhow!(e))?;
+            hasher.update(&chunk);
+            size += chunk.len() as i64;
+            write_sender.send(Ok(chunk)).await?;
+        }
+        Ok::<(i64, String), anyhow::Error>((size, hex::encode(hasher.finalize())))
+    };
+    let (read, write, forwarded) = tokio::join!(read, write, forward);
+
+    let verified = read.and(write).and(forwarded).and_then(|(size, sha256)| {
+        if size != location.disk_content_len {
+            bail!(
+                "Consolidated {size} bytes instead of {}",
+                location.disk_content_len
+            );
+        }
+        if let Some(expected) = &location.disk_hash {
+            if !expected.eq_ignore_ascii_case(&sha256) {
+                bail!("Consolidated data has sha256 {sha256} instead of {expected}");
+            }
+        }
+        Ok(())
+    });
+    if let Err(e) = verified {
+        // The previous layout stays in use
+        if let Err(err) = backend.delete_object(new_location).await {
+            error!(error = ?err, "Unable to remove incomplete consolidation");
+        }
+        return Err(e);
+    }
+    Ok(new_location)
+}
+
+/// Consolidates the location of an object and swaps it in once the copy is verified.
+/// Downloads which already resolved the previous location keep reading it until it
+/// is deleted after the grace period.
+pub async fn consolidate_object(
+    cache: Arc<Cache>,
+    backend: Arc<Box<dyn StorageBackend>>,
+    object_id: DieselUlid,
+    config: Consolidation,
+) -> Result<()> {
+    let location = cache
+        .get_location(&object_id)
+        .await
+        .ok_or_else(|| anyhow!("Location not found"))?;
+    if !needs_consolidation(&location, config.max_size) {
+        debug!(?object_id, "Object does not need consolidation");
+        return Ok(());
+    }
+
+    let new_location = consolidate_location(backend.clone(), &location).await?;
+    if cache
+        .get_location(&object_id)
+        .await
+        .map(|current| current.id)
+        != Some(location.id)
+    {
+        // The object was changed while the copy was written
+        backend.delete_object(new_location).await?;
+        bail!("Location changed during consolidation");
+    }
+    cache.update_location(object_id, new_location).await?;
+    info!(
+        ?object_id,
+        size = location.disk_content_len,
+        "Consolidated multipart object"
+    );
+
+    tokio::time::sleep(Duration::from_secs(config.grace_period)).await;
+    backend.delete_object(location).await
+}
+
+/// Starts the consolidation of a finished object in the background if it is
+/// configured and the project of the object opted in
+pub async fn spawn_consolidation(
+    cache: Arc<Cache>,
+    backend: Arc<Box<dyn StorageBackend>>,
+    project_id: DieselUlid,
+    object_id: DieselUlid,
+) {
+    let Some(config) = CONFIG
+        .frontend
+        .as_ref()
+        .and_then(|frontend| frontend.consolidation.clone())
+    else {
+        return;
+    };
+    let opted_in = cache
+        .get_resource_cloned(&project_id, true)
+        .await
+        .is_ok_and(|(project, _)| project.consolidates_parts());
+    if !opted_in {
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(e) = consolidate_object(cache, backend, object_id, config).await {
+            error!(error = ?e, ?object_id, "Unable to consolidate object");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_backends::mock_backend::MockBackend;
+    use bytes::Bytes;
+
+    async fn download(backend: &MockBackend, location: &ObjectLocation) -> Vec<u8> {
+        let (sender, receiver) = async_channel::unbounded();
+        backend
+            .get_object(location.clone(), None, sender)
+            .await
+            .unwrap();
+        let mut data = Vec::new();
+        while let Ok(chunk) = receiver.recv().await {
+            data.extend_from_slice(&chunk.unwrap());
+        }
+        data
+    }
+
+    #[tokio::test]
+    async fn test_consolidate_location() {
+        let backend = MockBackend::default();
+        let data = (0..3 * BACKEND_PART_SIZE as usize + 1234)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let mut location = ObjectLocation {
+            id: DieselUlid::generate(),
+            bucket: "bucket".to_string(),
+            key: "project/object".to_string(),
+            disk_content_len: data.len() as i64,
+            raw_content_len: data.len() as i64,
+            disk_hash: Some(hex::encode(Sha256::digest(&data))),
+            ..Default::default()
+        };
+
+        // Stored in parts like the buffered sink writes them
+        let upload_id = backend
+            .init_multipart_upload(location.clone())
+            .await
+            .unwrap();
+        for (idx, part) in data.chunks(BACKEND_PART_SIZE as usize).enumerate() {
+            let (sender, receiver) = async_channel::bounded(1);
+            sender.send(Ok(Bytes::copy_from_slice(part))).await.unwrap();
+            drop(sender);
+            backend
+                .upload_multi_object(
+                    receiver,
+                    location.clone(),
+                    upload_id.clone(),
+                    part.len() as i64,
+                    idx as i32 + 1,
+                )
+                .await
+                .unwrap();
+        }
+        backend
+            .finish_multipart_upload(location.clone(), vec![], upload_id)
+            .await
+            .unwrap();
+        assert_eq!(backend.parts(&location).unwrap().len(), 4);
+        assert!(needs_consolidation(&location, 1024 * 1024 * 1024));
+        assert!(!needs_consolidation(&location, 10 * 1024 * 1024));
+
+        let dyn_backend: Arc<Box<dyn StorageBackend>> = Arc::new(Box::new(backend.clone()));
+        let consolidated = consolidate_location(dyn_backend.clone(), &location)
+            .await
+            .unwrap();
+
+        // Single blob with identical data, the previous layout is still readable
+        assert_ne!(consolidated.id, location.id);
+        assert_eq!(consolidated.disk_hash, location.disk_hash);
+        assert_eq!(backend.parts(&consolidated).unwrap().len(), 1);
+        assert!(!needs_consolidation(
+            &ObjectLocation {
+                disk_content_len: 1024,
+                ..consolidated.clone()
+            },
+            1024 * 1024 * 1024
+        ));
+        assert_eq!(download(&backend, &consolidated).await, data);
+        assert_eq!(download(&backend, &location).await, data);
+
+        // Copies which do not match the stored hash are discarded
+        location.disk_hash = Some(hex::encode(Sha256::digest(b"other")));
+        let blobs = backend.object_count();
+        assert!(consolidate_location(dyn_backend, &location).await.is_err());
+        assert_eq!(backend.object_count(), blobs);
+    }
+}
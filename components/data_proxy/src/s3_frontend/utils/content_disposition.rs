@@ -0,0 +1,157 @@
+use crate::structs::Object;
+use http::Uri;
+use mime_guess::Mime;
+use std::str::FromStr;
+
+/// Signed query parameter of presigned urls which overrides the Content-Disposition
+pub const RESPONSE_CONTENT_DISPOSITION_KEY: &str = "response-content-disposition";
+/// Signed query parameter of presigned urls which overrides the Content-Type
+pub const RESPONSE_CONTENT_TYPE_KEY: &str = "response-content-type";
+/// Object label with the default disposition (`inline` or `attachment`) of downloads
+pub const CONTENT_DISPOSITION_LABEL: &str = "app.aruna-storage.org/content-disposition";
+
+/// Resolves the Content-Disposition of a download. The disposition requested by the
+/// presigned url takes precedence, otherwise the object label (or inline) with the stored filename.
+pub fn get_content_disposition(uri: &Uri, object: &Object) -> String {
+    if let Some(requested) = get_query_value(uri, RESPONSE_CONTENT_DISPOSITION_KEY) {
+        return requested;
+    }
+
+    let disposition = match object
+        .key_values
+        .iter()
+        .find(|kv| kv.key == CONTENT_DISPOSITION_LABEL)
+        .map(|kv| kv.value.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("attachment") => "attachment",
+        _ => "inline",
+    };
+    content_disposition_header(disposition, &object.name)
+}
+
+/// Resolves the Content-Type of a download, the type requested by the presigned url
+/// takes precedence over the type guessed from the filename.
+pub fn get_content_type(uri: &Uri, object: &Object) -> Option<Mime> {
+    get_query_value(uri, RESPONSE_CONTENT_TYPE_KEY)
+        .and_then(|requested| Mime::from_str(&requested).ok())
+        .or_else(|| mime_guess::from_path(object.name.as_str()).first())
+}
+
+fn get_query_value(uri: &Uri, key: &str) -> Option<String> {
+    uri.query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.into_owned())
+    })
+}
+
+/// Builds a Content-Disposition header value with an ASCII fallback filename
+/// and the RFC 5987 encoded filename if it contains other characters
+pub fn content_disposition_header(disposition: &str, filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii() && !c.is_ascii_control() => c,
+            _ => '_',
+        })
+        .collect();
+    if fallback == filename {
+        return format!(r#"{disposition}; filename="{fallback}""#);
+    }
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'a'..=b'z'
+            | b'A'..=b'Z'
+            | b'0'..=b'9'
+            | b'!'
+            | b'#'
+            | b'$'
+            | b'&'
+            | b'+'
+            | b'-'
+            | b'.'
+            | b'^'
+            | b'_'
+            | b'`'
+            | b'|'
+            | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!(r#"{disposition}; filename="{fallback}"; filename*=UTF-8''{encoded}"#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::ObjectType;
+    use aruna_rust_api::api::storage::models::v2::{KeyValue, KeyValueVariant};
+
+    #[test]
+    fn test_download_content_disposition() {
+        let mut object = Object::initialize_now("data.csv".to_string(), ObjectType::Object, None);
+        let uri = Uri::from_str("http://bucket.localhost/data.csv").unwrap();
+
+        // Default is inline with the stored filename
+        assert_eq!(
+            get_content_disposition(&uri, &object),
+            r#"inline; filename="data.csv""#
+        );
+
+        // Object label changes the default
+        object.key_values.push(KeyValue {
+            key: CONTENT_DISPOSITION_LABEL.to_string(),
+            value: "attachment".to_string(),
+            variant: KeyValueVariant::Label as i32,
+        });
+        assert_eq!(
+            get_content_disposition(&uri, &object),
+            r#"attachment; filename="data.csv""#
+        );
+
+        // Disposition of the signed url takes precedence
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair(
+                RESPONSE_CONTENT_DISPOSITION_KEY,
+                r#"inline; filename="report.csv""#,
+            )
+            .finish();
+        let uri = Uri::from_str(&format!("http://bucket.localhost/data.csv?{query}")).unwrap();
+        assert_eq!(
+            get_content_disposition(&uri, &object),
+            r#"inline; filename="report.csv""#
+        );
+        assert_eq!(
+            get_content_type(&uri, &object).unwrap().essence_str(),
+            "text/csv"
+        );
+    }
+
+    #[test]
+    fn test_download_content_type() {
+        let object = Object::initialize_now("data.csv".to_string(), ObjectType::Object, None);
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair(RESPONSE_CONTENT_TYPE_KEY, "text/plain")
+            .finish();
+        let uri = Uri::from_str(&format!("http://bucket.localhost/data.csv?{query}")).unwrap();
+        assert_eq!(
+            get_content_type(&uri, &object).unwrap().essence_str(),
+            "text/plain"
+        );
+    }
+
+    #[test]
+    fn test_non_ascii_filename() {
+        assert_eq!(
+            content_disposition_header("attachment", "Größe €.txt"),
+            r#"attachment; filename="Gr__e _.txt"; filename*=UTF-8''Gr%C3%B6%C3%9Fe%20%E2%82%AC.txt"#
+        );
+        assert_eq!(
+            content_disposition_header("inline", r#"a"b.txt"#),
+            r#"inline; filename="a_b.txt"; filename*=UTF-8''a%22b.txt"#
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use http::Uri;
+use s3s::{s3_error, S3Result};
+
+/// Signed query parameter of presigned upload urls with the accepted upload size in
+/// bytes as `<min>-<max>`
+pub const CONTENT_LENGTH_RANGE_KEY: &str = "x-aruna-content-length-range";
+
+/// Accepted size of an upload, derived by the server from the declared content length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentLengthRange {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl ContentLengthRange {
+    /// Rejects uploads whose size is outside of the range
+    pub fn check(&self, actual: u64) -> S3Result<()> {
+        if actual < self.min {
+            return Err(s3_error!(
+                EntityTooSmall,
+                "Upload of {actual} bytes is smaller than the expected {} to {} bytes",
+                self.min,
+                self.max
+            ));
+        }
+        if actual > self.max {
+            return Err(s3_error!(
+                EntityTooLarge,
+                "Upload of {actual} bytes is larger than the expected {} to {} bytes",
+                self.min,
+                self.max
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Extracts the accepted upload size of a presigned url
+pub fn get_content_length_range(uri: &Uri) -> S3Result<Option<ContentLengthRange>> {
+    let Some(query) = uri.query() else {
+        return Ok(None);
+    };
+    let Some((_, range)) = url::form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == CONTENT_LENGTH_RANGE_KEY)
+    else {
+        return Ok(None);
+    };
+    range
+        .split_once('-')
+        .and_then(|(min, max)| Some((min.parse::<u64>().ok()?, max.parse::<u64>().ok()?)))
+        .filter(|(min, max)| min <= max)
+        .map(|(min, max)| Some(ContentLengthRange { min, max }))
+        .ok_or_else(|| s3_error!(InvalidArgument, "Invalid content length range: {range}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use s3s::S3ErrorCode;
+    use std::str::FromStr;
+
+    fn range_uri(range: &str) -> Uri {
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair(CONTENT_LENGTH_RANGE_KEY, range)
+            .finish();
+        Uri::from_str(&format!("http://bucket.localhost/key?{query}")).unwrap()
+    }
+
+    #[test]
+    fn test_get_content_length_range() {
+        assert_eq!(
+            get_content_length_range(&range_uri("90-110")).unwrap(),
+            Some(ContentLengthRange { min: 90, max: 110 })
+        );
+        let uri = Uri::from_str("http://bucket.localhost/key").unwrap();
+        assert_eq!(get_content_length_range(&uri).unwrap(), None);
+
+        for invalid in ["100", "110-90", "a-b"] {
+            let err = get_content_length_range(&range_uri(invalid)).unwrap_err();
+            assert_eq!(err.code(), &S3ErrorCode::InvalidArgument);
+        }
+    }
+
+    #[test]
+    fn test_content_length_range_check() {
+        let range = ContentLengthRange { min: 90, max: 110 };
+        assert!(range.check(90).is_ok());
+        assert!(range.check(110).is_ok());
+
+        let too_small = range.check(89).unwrap_err();
+        assert_eq!(too_small.code(), &S3ErrorCode::EntityTooSmall);
+        assert!(too_small.message().unwrap().contains("89 bytes"));
+
+        let too_large = range.check(111).unwrap_err();
+        assert_eq!(too_large.code(), &S3ErrorCode::EntityTooLarge);
+        assert!(too_large.message().unwrap().contains("111 bytes"));
+
+        // Exact ranges only accept the declared size
+        let exact = ContentLengthRange { min: 100, max: 100 };
+        assert!(exact.check(100).is_ok());
+        assert!(exact.check(99).is_err());
+        assert!(exact.check(101).is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use base64::engine::general_purpose;
+use base64::Engine;
+use s3s::{s3_error, S3Result};
+use tracing::debug;
+
+/// Compares the base64 encoded Content-MD5 header of an upload with the hex encoded
+/// md5 hash of the received data. Presigned upload urls of the server sign the header,
+/// so uploads without it are already rejected by the signature validation.
+pub fn verify_content_md5(content_md5: &str, md5_hex: &str) -> S3Result<()> {
+    let expected = general_purpose::STANDARD
+        .decode(content_md5.trim())
+        .map_err(|_| s3_error!(InvalidDigest, "Content-MD5 is not valid base64"))?;
+    if expected.len() != 16 {
+        return Err(s3_error!(
+            InvalidDigest,
+            "Content-MD5 has an invalid length"
+        ));
+    }
+    let received =
+        hex::decode(md5_hex).map_err(|_| s3_error!(InternalError, "Invalid md5 hash"))?;
+    if expected != received {
+        debug!(?content_md5, ?md5_hex, "content md5 mismatch");
+        return Err(s3_error!(
+            BadDigest,
+            "Content-MD5 does not match the received data"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use md5::{Digest, Md5};
+
+    fn md5_hex(data: &[u8]) -> String {
+        hex::encode(Md5::digest(data))
+    }
+
+    #[test]
+    fn test_matching_md5() {
+        let content_md5 = general_purpose::STANDARD.encode(Md5::digest(b"aruna"));
+        assert!(verify_content_md5(&content_md5, &md5_hex(b"aruna")).is_ok());
+    }
+
+    #[test]
+    fn test_mismatching_md5() {
+        let content_md5 = general_purpose::STANDARD.encode(Md5::digest(b"aruna"));
+        let err = verify_content_md5(&content_md5, &md5_hex(b"other")).unwrap_err();
+        assert_eq!(err.code(), &s3s::S3ErrorCode::BadDigest);
+        assert!(verify_content_md5("not base64!", &md5_hex(b"aruna")).is_err());
+        assert!(verify_content_md5("YXJ1bmE=", &md5_hex(b"aruna")).is_err());
+    }
+}
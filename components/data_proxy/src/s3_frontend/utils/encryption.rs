@@ -0,0 +1,24 @@
+use s3s::dto::Metadata;
+use s3s::{s3_error, S3Result};
+
+/// User metadata (`x-amz-meta-aruna-encryption`) to opt in or out of encryption
+/// at rest when an object is uploaded
+pub const ENCRYPTION_METADATA_KEY: &str = "aruna-encryption";
+
+/// Parses the encryption choice of an upload, None uses the backend default
+pub fn get_encryption_choice(metadata: &Option<Metadata>) -> S3Result<Option<bool>> {
+    let Some(value) = metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get(ENCRYPTION_METADATA_KEY))
+    else {
+        return Ok(None);
+    };
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" => Ok(Some(true)),
+        "false" => Ok(Some(false)),
+        _ => Err(s3_error!(
+            InvalidArgument,
+            "x-amz-meta-aruna-encryption must be true or false"
+        )),
+    }
+}
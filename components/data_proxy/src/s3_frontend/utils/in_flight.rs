@@ -0,0 +1,264 @@
+use crate::structs::ObjectLocation;
+use ahash::RandomState;
+use base64::engine::general_purpose;
+use base64::Engine;
+use bytes::Bytes;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use diesel_ulid::DieselUlid;
+use futures_util::{Stream, StreamExt};
+use s3s::{s3_error, S3Result};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::debug;
+
+// Longest wait for the upload of a leader before uploading the data independently
+pub const IN_FLIGHT_WAIT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Uploads with the same key store the same data and can share it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InFlightKey {
+    pub project_id: DieselUlid,
+    // Hex encoded sha256 of the plain data declared by the client
+    pub sha256: String,
+    pub encryption: Option<bool>,
+}
+
+/// Stored data of a finished upload which later uploads of the same data bind
+#[derive(Debug, Clone)]
+pub struct FinishedUpload {
+    pub object_id: DieselUlid,
+    pub location: ObjectLocation,
+    pub md5: String,
+    pub sha256: String,
+}
+
+type UploadResult = Option<Arc<FinishedUpload>>;
+
+/// Uploads which are currently written to the backend by their declared checksum
+#[derive(Default)]
+pub struct InFlightUploads {
+    uploads: DashMap<InFlightKey, watch::Receiver<UploadResult>, RandomState>,
+}
+
+pub enum InFlightUpload {
+    // First upload of the data, writes it to the backend
+    Leader(InFlightLeader),
+    // Later upload of the same data, shares the result of the leader
+    Follower(InFlightFollower),
+}
+
+impl InFlightUploads {
+    pub fn join(self: &Arc<Self>, key: InFlightKey) -> InFlightUpload {
+        match self.uploads.entry(key.clone()) {
+            Entry::Occupied(entry) => InFlightUpload::Follower(InFlightFollower {
+                receiver: entry.get().clone(),
+            }),
+            Entry::Vacant(entry) => {
+                let (sender, receiver) = watch::channel(None);
+                entry.insert(receiver);
+                InFlightUpload::Leader(InFlightLeader {
+                    uploads: self.clone(),
+                    key,
+                    sender,
+                })
+            }
+        }
+    }
+}
+
+/// Upload which writes the data, failed uploads drop it without a result
+pub struct InFlightLeader {
+    uploads: Arc<InFlightUploads>,
+    key: InFlightKey,
+    sender: watch::Sender<UploadResult>,
+}
+
+impl InFlightLeader {
+    pub fn sha256(&self) -> &str {
+        &self.key.sha256
+    }
+
+    pub fn finish(self, upload: FinishedUpload) {
+        self.sender.send_replace(Some(Arc::new(upload)));
+    }
+}
+
+impl Drop for InFlightLeader {
+    fn drop(&mut self) {
+        // Uploads which start afterwards write the data again
+        self.uploads.uploads.remove(&self.key);
+    }
+}
+
+pub struct InFlightFollower {
+    receiver: watch::Receiver<UploadResult>,
+}
+
+impl InFlightFollower {
+    /// Waits until the leader is done, `None` if it failed or did not finish within
+    /// `timeout` and the data has to be uploaded independently
+    pub async fn wait(mut self, timeout: Duration) -> UploadResult {
+        let result = tokio::time::timeout(timeout, self.receiver.wait_for(Option::is_some))
+            .await
+            .ok()?
+            .ok()?;
+        (*result).clone()
+    }
+}
+
+/// Converts the base64 encoded `x-amz-checksum-sha256` of an upload into a hex string
+pub fn get_declared_sha256(checksum_sha256: Option<&str>) -> S3Result<Option<String>> {
+    let Some(checksum) = checksum_sha256 else {
+        return Ok(None);
+    };
+    let sha256 = general_purpose::STANDARD
+        .decode(checksum.trim())
+        .map_err(|_| s3_error!(InvalidDigest, "Checksum is not valid base64"))?;
+    if sha256.len() != 32 {
+        return Err(s3_error!(InvalidDigest, "Checksum has an invalid length"));
+    }
+    Ok(Some(hex::encode(sha256)))
+}
+
+/// Reads the body of an upload which shares the data of another upload instead of
+/// writing it, the body has to match the shared data
+pub async fn verify_shared_body<E>(
+    mut body: impl Stream<Item = Result<Bytes, E>> + Unpin,
+    upload: &FinishedUpload,
+) -> S3Result<()> {
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|_| s3_error!(IncompleteBody, "Unable to read body"))?;
+        size += chunk.len() as i64;
+        hasher.update(&chunk);
+    }
+    let sha256 = hex::encode(hasher.finalize());
+    if sha256 != upload.sha256 || size != upload.location.raw_content_len {
+        debug!(?sha256, expected = ?upload.sha256, "shared upload mismatch");
+        return Err(s3_error!(
+            BadDigest,
+            "Checksum does not match the received data"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn key(data: &[u8]) -> InFlightKey {
+        InFlightKey {
+            project_id: DieselUlid::default(),
+            sha256: hex::encode(Sha256::digest(data)),
+            encryption: None,
+        }
+    }
+
+    fn body(data: &'static [u8]) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Unpin {
+        futures::stream::iter(vec![Ok(Bytes::from_static(data))])
+    }
+
+    // Writes the data as leader or shares the result of the leader
+    async fn upload(
+        upload: InFlightUpload,
+        data: &'static [u8],
+        writes: Arc<AtomicUsize>,
+    ) -> S3Result<FinishedUpload> {
+        match upload {
+            InFlightUpload::Leader(leader) => {
+                writes.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                let finished = FinishedUpload {
+                    object_id: DieselUlid::generate(),
+                    location: ObjectLocation {
+                        id: DieselUlid::generate(),
+                        raw_content_len: data.len() as i64,
+                        ..Default::default()
+                    },
+                    md5: String::new(),
+                    sha256: leader.sha256().to_string(),
+                };
+                leader.finish(finished.clone());
+                Ok(finished)
+            }
+            InFlightUpload::Follower(follower) => {
+                let finished = follower
+                    .wait(IN_FLIGHT_WAIT_TIMEOUT)
+                    .await
+                    .ok_or_else(|| s3_error!(InternalError, "Leader failed"))?;
+                verify_shared_body(body(data), &finished).await?;
+                Ok(finished.as_ref().clone())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_uploads_write_once() {
+        let uploads = Arc::new(InFlightUploads::default());
+        let writes = Arc::new(AtomicUsize::new(0));
+        let first = uploads.join(key(b"aruna"));
+        let second = uploads.join(key(b"aruna"));
+
+        let (first, second) = tokio::join!(
+            upload(first, b"aruna", writes.clone()),
+            upload(second, b"aruna", writes.clone()),
+        );
+        assert_eq!(writes.load(Ordering::SeqCst), 1);
+        assert_eq!(first.unwrap().location.id, second.unwrap().location.id);
+
+        // Finished uploads are not shared anymore
+        assert!(matches!(
+            uploads.join(key(b"aruna")),
+            InFlightUpload::Leader(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_and_mismatching_uploads() {
+        let uploads = Arc::new(InFlightUploads::default());
+
+        // Followers of a failed leader upload independently
+        let leader = uploads.join(key(b"aruna"));
+        let InFlightUpload::Follower(follower) = uploads.join(key(b"aruna")) else {
+            panic!("Expected follower");
+        };
+        drop(leader);
+        assert!(follower.wait(IN_FLIGHT_WAIT_TIMEOUT).await.is_none());
+
+        // Followers of a stalled leader upload independently after the timeout
+        let _leader = uploads.join(key(b"stalled"));
+        let InFlightUpload::Follower(follower) = uploads.join(key(b"stalled")) else {
+            panic!("Expected follower");
+        };
+        assert!(follower.wait(Duration::from_millis(10)).await.is_none());
+
+        // Bodies which do not match the shared data are rejected
+        let writes = Arc::new(AtomicUsize::new(0));
+        let first = uploads.join(key(b"aruna"));
+        let second = uploads.join(key(b"aruna"));
+        let (first, second) = tokio::join!(
+            upload(first, b"aruna", writes.clone()),
+            upload(second, b"other", writes.clone()),
+        );
+        assert!(first.is_ok());
+        assert_eq!(second.unwrap_err().code(), &s3s::S3ErrorCode::BadDigest);
+    }
+
+    #[test]
+    fn test_declared_sha256() {
+        let sha256 = Sha256::digest(b"aruna");
+        assert_eq!(
+            get_declared_sha256(Some(&general_purpose::STANDARD.encode(sha256))).unwrap(),
+            Some(hex::encode(sha256))
+        );
+        assert_eq!(get_declared_sha256(None).unwrap(), None);
+        assert!(get_declared_sha256(Some("not base64!")).is_err());
+        assert!(get_declared_sha256(Some("YXJ1bmE=")).is_err());
+    }
+}
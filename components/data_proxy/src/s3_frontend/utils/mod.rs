@@ -1,5 +1,26 @@
+pub mod access_log;
+pub mod attestation;
+pub mod attribution;
+pub mod aws_chunked;
 pub mod buffered_s3_sink;
+pub mod checksum;
+pub mod client_ip;
+pub mod consolidation;
+pub mod content_disposition;
+pub mod content_length_range;
+pub mod content_md5;
 pub mod debug_transformer;
+pub mod encryption;
+pub mod in_flight;
 pub mod list_objects;
+pub mod multipart;
+#[cfg(feature = "row-ranges")]
+pub mod object_accessor;
 pub mod ranges;
+pub mod redirect;
 pub mod replication_sink;
+pub mod resume_token;
+pub mod session_token;
+pub mod signed_cookie;
+pub mod single_use;
+pub mod throttle;
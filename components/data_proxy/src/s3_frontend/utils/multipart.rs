@@ -0,0 +1,305 @@
+use crate::data_backends::storage_backend::StorageBackend;
+use crate::structs::{ObjectLocation, PartETag, UploadPart};
+use anyhow::{bail, Result};
+use futures::{stream, StreamExt, TryStreamExt};
+use s3s::dto::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+// Verified parts between progress logs of long finalizations
+const PROGRESS_INTERVAL: usize = 1000;
+// Maximum size of a single part
+const MAX_PART_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Verifies the stored size of all parts in the backend and completes the
+/// multipart upload only if every part is valid. At most `concurrency` parts
+/// are verified at the same time to stay below the rate limits of the backend.
+pub async fn finish_verified_upload(
+    backend: Arc<Box<dyn StorageBackend>>,
+    location: ObjectLocation,
+    upload_id: String,
+    parts: Vec<(PartETag, UploadPart)>,
+    concurrency: usize,
+) -> Result<()> {
+    let total = parts.len();
+    let verified = AtomicUsize::new(0);
+
+    stream::iter(parts.iter())
+        .map(|(etag, part)| {
+            let (backend, location, upload_id) =
+                (backend.clone(), location.clone(), upload_id.clone());
+            let verified = &verified;
+            async move {
+                let size = backend
+                    .head_multipart_part(location, upload_id.clone(), etag.part_number)
+                    .await?;
+                if size != part.size as i64 {
+                    bail!(
+                        "Part {} has {} bytes stored instead of {}",
+                        etag.part_number,
+                        size,
+                        part.size
+                    );
+                }
+                let done = verified.fetch_add(1, Ordering::Relaxed) + 1;
+                if done % PROGRESS_INTERVAL == 0 {
+                    info!(upload_id, done, total, "Verifying multipart upload");
+                }
+                Ok(())
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let etags = parts.into_iter().map(|(etag, _)| etag).collect();
+    backend
+        .finish_multipart_upload(location, etags, upload_id)
+        .await
+}
+
+/// Outcome of the backend completion of a multipart upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadCompletion {
+    // The parts were combined by this request
+    Completed,
+    // An earlier attempt already combined the parts, e.g. if its response was lost
+    AlreadyCompleted,
+}
+
+/// Completes a multipart upload in the backend, retries of the same upload are safe.
+/// If the completion fails, the backend is checked for the complete object of an earlier
+/// attempt. Otherwise partially written data at the location is removed and the parts
+/// are kept, so the client can retry the completion.
+pub async fn complete_upload(
+    backend: Arc<Box<dyn StorageBackend>>,
+    location: ObjectLocation,
+    upload_id: String,
+    parts: Vec<(PartETag, UploadPart)>,
+    concurrency: usize,
+) -> Result<UploadCompletion> {
+    let expected_size = parts.iter().map(|(_, part)| part.size as i64).sum::<i64>();
+    let Err(err) = finish_verified_upload(
+        backend.clone(),
+        location.clone(),
+        upload_id.clone(),
+        parts,
+        concurrency,
+    )
+    .await
+    else {
+        return Ok(UploadCompletion::Completed);
+    };
+
+    match backend.head_object(location.clone()).await {
+        Ok(size) if size == expected_size => {
+            info!(upload_id, "Multipart upload was already completed");
+            Ok(UploadCompletion::AlreadyCompleted)
+        }
+        Ok(size) => {
+            warn!(
+                upload_id,
+                size, expected_size, "Removing partially completed upload"
+            );
+            if let Err(e) = backend.delete_object(location).await {
+                warn!(upload_id, error = ?e, "Unable to remove partially completed upload");
+            }
+            Err(err)
+        }
+        Err(_) => Err(err),
+    }
+}
+
+/// Parses the source range of a part copy (`bytes=first-last`), copies without a
+/// range use the whole source object. The range has to be within the source
+/// object and must not exceed the maximum part size.
+pub fn copy_source_range(range: Option<&str>, content_len: u64) -> Result<Option<Range>> {
+    let Some(range) = range else {
+        if content_len == 0 || content_len > MAX_PART_SIZE {
+            bail!("Source object of {content_len} bytes can not be copied as a single part");
+        }
+        return Ok(None);
+    };
+    let Some((first, last)) = range
+        .strip_prefix("bytes=")
+        .and_then(|range| range.split_once('-'))
+    else {
+        bail!("Invalid copy source range {range}");
+    };
+    let (first, last) = (first.trim().parse::<u64>()?, last.trim().parse::<u64>()?);
+    if first > last || last >= content_len {
+        bail!("Copy source range {range} is outside of the {content_len} bytes of the source");
+    }
+    if last - first + 1 > MAX_PART_SIZE {
+        bail!("Copy source range {range} is larger than 5GiB");
+    }
+    Ok(Some(Range::Int {
+        first,
+        last: Some(last),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_backends::mock_backend::MockBackend;
+    use diesel_ulid::DieselUlid;
+    use std::collections::HashMap;
+
+    /// Backend with the uploaded parts of the upload `upload`
+    fn uploaded(sizes: &HashMap<i32, i64>) -> MockBackend {
+        let backend = MockBackend::default();
+        for (part_number, size) in sizes {
+            backend.add_part("upload", *part_number, *size as usize);
+        }
+        backend
+    }
+
+    fn parts(count: i32) -> Vec<(PartETag, UploadPart)> {
+        let object_id = DieselUlid::generate();
+        (1..=count)
+            .map(|part_number| {
+                (
+                    PartETag {
+                        part_number,
+                        etag: format!("etag-{part_number}"),
+                    },
+                    UploadPart {
+                        id: DieselUlid::generate(),
+                        object_id,
+                        upload_id: "upload".to_string(),
+                        part_number: part_number as u64,
+                        raw_size: 100,
+                        size: 128,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Finishes an upload of `count` parts, returns the result and the number of
+    /// completions and the maximum of parallel verifications in the backend
+    async fn finish(sizes: HashMap<i32, i64>, count: i32) -> (Result<()>, usize, usize) {
+        let backend = uploaded(&sizes);
+        let (finished, max_running) = (backend.writes.clone(), backend.max_running.clone());
+        let result = finish_verified_upload(
+            Arc::new(Box::new(backend)),
+            ObjectLocation::default(),
+            "upload".to_string(),
+            parts(count),
+            8,
+        )
+        .await;
+        (
+            result,
+            finished.load(Ordering::SeqCst),
+            max_running.load(Ordering::SeqCst),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_finish_verified_upload() {
+        let count = 2500;
+        let sizes: HashMap<i32, i64> = (1..=count).map(|number| (number, 128)).collect();
+
+        // All parts valid, verified in parallel within the limit
+        let (result, finished, max_running) = finish(sizes.clone(), count).await;
+        assert!(result.is_ok());
+        assert_eq!(finished, 1);
+        assert!(max_running > 1 && max_running <= 8);
+
+        // A single truncated part fails the whole upload
+        let mut truncated = sizes.clone();
+        truncated.insert(1234, 64);
+        let (result, finished, _) = finish(truncated, count).await;
+        assert!(result.is_err());
+        assert_eq!(finished, 0);
+
+        // A missing part fails the whole upload
+        let mut missing = sizes;
+        missing.remove(&count);
+        let (result, finished, _) = finish(missing, count).await;
+        assert!(result.is_err());
+        assert_eq!(finished, 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_completion_can_be_retried() {
+        let sizes: HashMap<i32, i64> = (1..=3).map(|number| (number, 128)).collect();
+        let location = ObjectLocation::default();
+
+        // The backend fails midway, the partial object is removed and no completion is reported
+        let failing = uploaded(&sizes).with_failing_finish(Some(200));
+        let result = complete_upload(
+            Arc::new(Box::new(failing.clone())),
+            location.clone(),
+            "upload".to_string(),
+            parts(3),
+            8,
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(failing.head_object(location.clone()).await.is_err());
+
+        // Retry with the kept parts completes the upload
+        let backend = failing.with_failing_finish(None);
+        let completion = complete_upload(
+            Arc::new(Box::new(backend.clone())),
+            location.clone(),
+            "upload".to_string(),
+            parts(3),
+            8,
+        )
+        .await
+        .unwrap();
+        assert_eq!(completion, UploadCompletion::Completed);
+        assert_eq!(backend.head_object(location.clone()).await.unwrap(), 384);
+
+        // Retries after a lost response find the completed object, the parts are gone
+        let completion = complete_upload(
+            Arc::new(Box::new(backend.clone())),
+            location.clone(),
+            "upload".to_string(),
+            parts(3),
+            8,
+        )
+        .await
+        .unwrap();
+        assert_eq!(completion, UploadCompletion::AlreadyCompleted);
+        assert_eq!(backend.head_object(location).await.unwrap(), 384);
+    }
+
+    #[test]
+    fn test_copy_source_range() {
+        // New revision: the first 10 MiB of a 12 MiB object and a new tail part
+        let source_len = 12 * 1024 * 1024;
+        let head = copy_source_range(Some("bytes=0-5242879"), source_len).unwrap();
+        let middle = copy_source_range(Some("bytes=5242880-10485759"), source_len).unwrap();
+        let mut composed_len = 0;
+        for range in [head, middle] {
+            let Some(Range::Int {
+                first,
+                last: Some(last),
+            }) = range
+            else {
+                panic!("Expected a closed range");
+            };
+            composed_len += last - first + 1;
+        }
+        let tail_len = 3 * 1024 * 1024;
+        assert_eq!(composed_len + tail_len, 13 * 1024 * 1024);
+
+        // Whole objects are copied without a range
+        assert!(copy_source_range(None, source_len).unwrap().is_none());
+        assert!(copy_source_range(None, 6 * 1024 * 1024 * 1024).is_err());
+        assert!(copy_source_range(None, 0).is_err());
+
+        // Ranges outside of the source or in a wrong format are rejected
+        assert!(copy_source_range(Some("bytes=0-12582912"), source_len).is_err());
+        assert!(copy_source_range(Some("bytes=10-5"), source_len).is_err());
+        assert!(copy_source_range(Some("bytes=-100"), source_len).is_err());
+        assert!(copy_source_range(Some("0-100"), source_len).is_err());
+        assert!(copy_source_range(Some("bytes=0-6442450944"), 7 * 1024 * 1024 * 1024).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use crate::caching::cache::Cache;
+use crate::structs::{Object, ObjectLocation};
+use anyhow::{anyhow, Result};
+use async_channel::{Receiver, Sender, TryRecvError, TrySendError};
+use bytes::BytesMut;
+use diesel_ulid::DieselUlid;
+use http::Uri;
+use pithos_lib::helpers::notifications::{Message, Notifier};
+use pithos_lib::transformer::{Transformer, TransformerType};
+use s3s::dto::Range as S3Range;
+use s3s::{s3_error, S3Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, error};
+
+/// Query parameter to request a range of records instead of bytes
+pub const ROW_RANGE_KEY: &str = "x-aruna-row-range";
+
+/// Inclusive range of records, `last` is open if not specified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowRange {
+    pub first: u64,
+    pub last: Option<u64>,
+}
+
+impl RowRange {
+    /// Parses ranges of the form `first-last` or `first-`
+    pub fn parse(value: &str) -> Result<Self> {
+        let (first, last) = value
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Row range must be of the form first-last"))?;
+        let first = first.trim().parse::<u64>()?;
+        let last = match last.trim() {
+            "" => None,
+            last => Some(last.parse::<u64>()?),
+        };
+        if last.is_some_and(|last| last < first) {
+            return Err(anyhow!("Last row must not be smaller than first row"));
+        }
+        Ok(RowRange { first, last })
+    }
+}
+
+/// Translates logical records of an object into a byte range of its raw content
+pub trait ObjectAccessor: Send + Sync {
+    fn byte_range(&self, rows: &RowRange) -> S3Result<S3Range>;
+}
+
+/// Line offsets of a CSV object, collected while the object is uploaded
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LineIndex {
+    pub id: DieselUlid, // Id of the indexed location
+    pub offsets: Vec<u64>,
+    pub size: u64,
+}
+
+impl ObjectAccessor for LineIndex {
+    fn byte_range(&self, rows: &RowRange) -> S3Result<S3Range> {
+        let first = rows.first as usize;
+        let Some(start) = self.offsets.get(first) else {
+            return Err(s3_error!(
+                InvalidRange,
+                "Object only has {} rows",
+                self.offsets.len()
+            ));
+        };
+        let end = rows
+            .last
+            .and_then(|last| self.offsets.get(last as usize + 1))
+            .copied()
+            .unwrap_or(self.size);
+        Ok(S3Range::Int {
+            first: *start,
+            last: Some(end - 1),
+        })
+    }
+}
+
+/// Objects which get a line index on upload
+pub fn is_indexable(object_name: &str) -> bool {
+    object_name.to_lowercase().ends_with(".csv")
+}
+
+/// Extracts the requested row range from the query of a request
+pub fn get_row_range(uri: &Uri) -> S3Result<Option<RowRange>> {
+    let Some(query) = uri.query() else {
+        return Ok(None);
+    };
+    match url::form_urlencoded::parse(query.as_bytes()).find(|(key, _)| key == ROW_RANGE_KEY) {
+        Some((_, value)) => Ok(Some(RowRange::parse(&value).map_err(|e| {
+            debug!(error = ?e, "invalid row range");
+            s3_error!(InvalidArgument, "Invalid row range: {}", e)
+        })?)),
+        None => Ok(None),
+    }
+}
+
+/// Returns the accessor for the format of an object
+pub async fn get_accessor(
+    cache: &Cache,
+    object: &Object,
+    location: &ObjectLocation,
+) -> S3Result<Arc<dyn ObjectAccessor>> {
+    if is_indexable(&object.name) {
+        let index = cache.get_line_index(&location.id).await.map_err(|e| {
+            error!(error = ?e, msg = "Unable to get line index");
+            s3_error!(InternalError, "Unable to get line index")
+        })?;
+        if let Some(index) = index {
+            return Ok(index);
+        }
+    }
+    Err(s3_error!(
+        InvalidRequest,
+        "Format not indexed, row ranges are only supported for indexed CSV objects"
+    ))
+}
+
+/// Collects the start offsets of all lines of the raw data
+pub struct LineIndexer {
+    index: LineIndex,
+    index_sender: Sender<LineIndex>,
+    notifier: Option<Arc<Notifier>>,
+    msg_receiver: Option<Receiver<Message>>,
+    idx: Option<usize>,
+}
+
+impl LineIndexer {
+    #[tracing::instrument(level = "trace", skip())]
+    pub fn new(location_id: DieselUlid) -> (LineIndexer, Receiver<LineIndex>) {
+        let (index_sender, index_receiver) = async_channel::bounded(1);
+        (
+            LineIndexer {
+                index: LineIndex {
+                    id: location_id,
+                    offsets: vec![0],
+                    size: 0,
+                },
+                index_sender,
+                notifier: None,
+                msg_receiver: None,
+                idx: None,
+            },
+            index_receiver,
+        )
+    }
+
+    fn process_messages(&mut self) -> Result<bool> {
+        if let Some(rx) = &self.msg_receiver {
+            loop {
+                match rx.try_recv() {
+                    Ok(Message::Finished) => {
+                        return Ok(true);
+                    }
+                    Ok(_) => {}
+                    Err(TryRecvError::Empty) => {
+                        break;
+                    }
+                    Err(TryRecvError::Closed) => {
+                        error!("Message receiver closed");
+                        return Err(anyhow!("Message receiver closed"));
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn index_bytes(&mut self, buf: &[u8]) {
+        for (pos, _) in buf.iter().enumerate().filter(|(_, byte)| **byte == b'\n') {
+            self.index.offsets.push(self.index.size + pos as u64 + 1);
+        }
+        self.index.size += buf.len() as u64;
+    }
+
+    fn finish(&mut self) -> LineIndex {
+        // A trailing newline does not start another row
+        if self.index.offsets.last() == Some(&self.index.size) {
+            self.index.offsets.pop();
+        }
+        self.index.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl Transformer for LineIndexer {
+    #[tracing::instrument(level = "trace", skip(self))]
+    async fn initialize(&mut self, idx: usize) -> (TransformerType, Sender<Message>) {
+        self.idx = Some(idx);
+        let (sx, rx) = async_channel::bounded(10);
+        self.msg_receiver = Some(rx);
+        (TransformerType::Unspecified, sx)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, buf))]
+    async fn process_bytes(&mut self, buf: &mut BytesMut) -> Result<()> {
+        self.index_bytes(buf);
+
+        if buf.is_empty() && self.process_messages()? {
+            let index = self.finish();
+            match self.index_sender.try_send(index) {
+                Ok(_) | Err(TrySendError::Full(_)) => {}
+                Err(TrySendError::Closed(_)) => {
+                    error!("Line index sender closed");
+                    return Err(anyhow!("Line index sender closed"));
+                }
+            }
+            if let Some(notifier) = &self.notifier {
+                notifier.send_next(
+                    self.idx.ok_or_else(|| anyhow!("Missing idx"))?,
+                    Message::Finished,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, notifier))]
+    #[inline]
+    async fn set_notifier(&mut self, notifier: Arc<Notifier>) -> Result<()> {
+        self.notifier = Some(notifier);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_index(data: &[&[u8]]) -> LineIndex {
+        let (mut indexer, _) = LineIndexer::new(DieselUlid::generate());
+        for chunk in data {
+            indexer.index_bytes(chunk);
+        }
+        indexer.finish()
+    }
+
+    #[test]
+    fn test_line_index() {
+        // Lines are split across chunks
+        let index = build_index(&[b"a,b\n1,", b"2\n3,4\n"]);
+        assert_eq!(index.offsets, vec![0, 4, 8]);
+        assert_eq!(index.size, 12);
+
+        assert_eq!(
+            index.byte_range(&RowRange::parse("1-1").unwrap()).unwrap(),
+            S3Range::Int {
+                first: 4,
+                last: Some(7)
+            }
+        );
+        assert_eq!(
+            index.byte_range(&RowRange::parse("1-").unwrap()).unwrap(),
+            S3Range::Int {
+                first: 4,
+                last: Some(11)
+            }
+        );
+        // Ranges beyond the last row are truncated
+        assert_eq!(
+            index.byte_range(&RowRange::parse("0-10").unwrap()).unwrap(),
+            S3Range::Int {
+                first: 0,
+                last: Some(11)
+            }
+        );
+        assert!(index.byte_range(&RowRange::parse("3-").unwrap()).is_err());
+
+        // Last row without trailing newline
+        let index = build_index(&[b"a\nb"]);
+        assert_eq!(index.offsets, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_row_range_parsing() {
+        assert_eq!(
+            RowRange::parse("5-9").unwrap(),
+            RowRange {
+                first: 5,
+                last: Some(9)
+            }
+        );
+        assert_eq!(
+            RowRange::parse("5-").unwrap(),
+            RowRange {
+                first: 5,
+                last: None
+            }
+        );
+        assert!(RowRange::parse("9-5").is_err());
+        assert!(RowRange::parse("5").is_err());
+        assert!(RowRange::parse("-5").is_err());
+
+        let uri: Uri = format!("http://bucket.localhost/data.csv?{ROW_RANGE_KEY}=2-4")
+            .parse()
+            .unwrap();
+        assert_eq!(
+            get_row_range(&uri).unwrap(),
+            Some(RowRange {
+                first: 2,
+                last: Some(4)
+            })
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use crate::structs::{FileFormat, ObjectLocation};
+use http::header::LOCATION;
+use http::{HeaderValue, Response, StatusCode, Uri};
+use s3s::dto::GetObjectOutput;
+use s3s::{s3_error, S3Response, S3Result};
+
+// Marks responses of get_object which are turned into a redirect to the contained url
+pub const INTERNAL_REDIRECT: &str = "x-aruna-internal-redirect";
+
+/// Downloads which can be served by the storage system directly. Encrypted or compressed
+/// data has to be processed by the proxy, as have parts of unfinished multipart uploads
+/// and downloads with checksum trailers or row ranges.
+pub fn is_redirectable(location: &ObjectLocation, uri: &Uri) -> S3Result<bool> {
+    if !matches!(location.file_format, FileFormat::Raw) || location.is_temporary {
+        return Ok(false);
+    }
+    if super::checksum::get_trailer_algorithm(uri)?.is_some() {
+        return Ok(false);
+    }
+    #[cfg(feature = "row-ranges")]
+    if super::object_accessor::get_row_range(uri)?.is_some() {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Empty get_object response which is redirected to the presigned url by the server
+pub fn redirect_response(url: &str) -> S3Result<S3Response<GetObjectOutput>> {
+    let mut resp = S3Response::new(GetObjectOutput::default());
+    resp.headers.insert(
+        INTERNAL_REDIRECT,
+        HeaderValue::from_str(url)
+            .map_err(|_| s3_error!(InternalError, "Unable to parse redirect url"))?,
+    );
+    Ok(resp)
+}
+
+/// Turns marked responses into a 302 (Found) to their presigned url
+pub fn with_redirect<B>(mut response: Response<B>) -> Response<B> {
+    let Some(url) = response.headers_mut().remove(INTERNAL_REDIRECT) else {
+        return response;
+    };
+    *response.status_mut() = StatusCode::FOUND;
+    response.headers_mut().insert(LOCATION, url);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const PRESIGNED_URL: &str = "https://s3.example.com/bucket/key?X-Amz-Signature=abc";
+
+    fn into_http(resp: S3Response<GetObjectOutput>) -> Response<()> {
+        let mut response = Response::new(());
+        *response.headers_mut() = resp.headers;
+        with_redirect(response)
+    }
+
+    #[test]
+    fn test_redirect_plain_objects() {
+        let uri = Uri::from_str("http://bucket.localhost/key").unwrap();
+        let plain = ObjectLocation {
+            file_format: FileFormat::Raw,
+            ..Default::default()
+        };
+        assert!(is_redirectable(&plain, &uri).unwrap());
+        let resp = redirect_response(PRESIGNED_URL).unwrap();
+        assert!(resp.output.body.is_none());
+        let response = into_http(resp);
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(response.headers()[LOCATION], PRESIGNED_URL);
+        assert!(response.headers().get(INTERNAL_REDIRECT).is_none());
+
+        // Encrypted or compressed data is proxied
+        for file_format in [
+            FileFormat::RawEncrypted([0; 32]),
+            FileFormat::RawCompressed,
+            FileFormat::RawEncryptedCompressed([0; 32]),
+            FileFormat::Pithos([0; 32]),
+        ] {
+            let processed = ObjectLocation {
+                file_format,
+                ..Default::default()
+            };
+            assert!(!is_redirectable(&processed, &uri).unwrap());
+        }
+
+        // Unfinished multipart uploads are proxied
+        let temporary = ObjectLocation {
+            is_temporary: true,
+            ..plain
+        };
+        assert!(!is_redirectable(&temporary, &uri).unwrap());
+
+        // Other responses are not changed
+        let response = into_http(S3Response::new(GetObjectOutput::default()));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(LOCATION).is_none());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::auth::crypto::{sign_resume_token, verify_resume_token, ResumeToken};
+use crate::config::ResumeTokens;
+use crate::structs::Object;
+use http::{HeaderMap, HeaderValue};
+use s3s::dto::Range;
+use s3s::{s3_error, S3Result};
+use tracing::{debug, error};
+
+/// Header of downloads with the token to resume them, resumed downloads present the
+/// token with a range request
+pub const RESUME_TOKEN_HEADER: &str = "x-aruna-resume-token";
+
+/// Stored sha256 of the object which resumed downloads have to match
+fn object_sha256(object: &Object) -> String {
+    object
+        .hashes
+        .get("SHA256")
+        .map(|hash| hash.to_ascii_lowercase())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Creates the resume token of a download of `content_len` stored bytes starting at `offset`
+pub fn issue_resume_token(
+    config: &ResumeTokens,
+    object: &Object,
+    content_len: i64,
+    offset: u64,
+    now: i64,
+) -> S3Result<HeaderValue> {
+    let token = ResumeToken {
+        object_id: object.id,
+        content_len,
+        sha256: object_sha256(object),
+        offset,
+        expires_at: now + config.ttl,
+    };
+    let secret = config.get_secret().map_err(|e| {
+        error!(error = ?e, msg = "Resume token secret not set");
+        s3_error!(InternalError, "Unable to issue resume token")
+    })?;
+    let token = sign_resume_token(secret, &token).map_err(|e| {
+        error!(error = ?e, msg = "Unable to sign resume token");
+        s3_error!(InternalError, "Unable to issue resume token")
+    })?;
+    HeaderValue::from_str(&token).map_err(|_| s3_error!(InternalError, "Invalid resume token"))
+}
+
+/// Validates the resume token of a resumed download. Downloads of objects which changed
+/// since the token was issued fail with `PreconditionFailed` and have to be restarted.
+pub fn check_resume_token(
+    config: &ResumeTokens,
+    headers: &HeaderMap,
+    object: &Object,
+    content_len: i64,
+    range: Option<&Range>,
+    now: i64,
+) -> S3Result<()> {
+    let Some(token) = headers.get(RESUME_TOKEN_HEADER) else {
+        return Ok(());
+    };
+    let secret = config.get_secret().map_err(|e| {
+        error!(error = ?e, msg = "Resume token secret not set");
+        s3_error!(InternalError, "Unable to verify resume token")
+    })?;
+    let token = token
+        .to_str()
+        .map_err(anyhow::Error::from)
+        .and_then(|token| verify_resume_token(secret, token, now))
+        .map_err(|e| {
+            debug!(error = ?e, "invalid resume token");
+            s3_error!(AccessDenied, "Invalid resume token")
+        })?;
+
+    if token.object_id != object.id
+        || token.content_len != content_len
+        || token.sha256 != object_sha256(object)
+    {
+        debug!(?token, object_id = ?object.id, "object changed since the download started");
+        return Err(s3_error!(
+            PreconditionFailed,
+            "Object changed since the download started, restart the download"
+        ));
+    }
+
+    let start = match range {
+        Some(Range::Int { first, .. }) => *first,
+        Some(Range::Suffix { length }) => (content_len as u64).saturating_sub(*length),
+        None => {
+            return Err(s3_error!(
+                InvalidArgument,
+                "Resumed downloads require a range"
+            ))
+        }
+    };
+    if start < token.offset {
+        return Err(s3_error!(
+            InvalidRange,
+            "Resumed range starts before the download at byte {}",
+            token.offset
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::ObjectType;
+    use diesel_ulid::DieselUlid;
+    use s3s::S3ErrorCode;
+
+    fn config() -> ResumeTokens {
+        ResumeTokens {
+            secret: Some("a".repeat(32)),
+            ttl: 3600,
+        }
+    }
+
+    fn object() -> Object {
+        let mut object = Object::initialize_now("data.csv".to_string(), ObjectType::Object, None);
+        object.hashes.insert("SHA256".to_string(), "ab".repeat(32));
+        object
+    }
+
+    fn resume_headers(token: HeaderValue) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RESUME_TOKEN_HEADER, token);
+        headers
+    }
+
+    fn range_from(first: u64) -> Range {
+        Range::Int { first, last: None }
+    }
+
+    #[test]
+    fn test_resume_download() {
+        let (config, object) = (config(), object());
+        let now = chrono::Utc::now().timestamp();
+        let headers = resume_headers(issue_resume_token(&config, &object, 1000, 0, now).unwrap());
+
+        // The remainder of an unchanged object can be downloaded
+        assert!(check_resume_token(
+            &config,
+            &headers,
+            &object,
+            1000,
+            Some(&range_from(400)),
+            now
+        )
+        .is_ok());
+        let suffix = Range::Suffix { length: 600 };
+        assert!(check_resume_token(&config, &headers, &object, 1000, Some(&suffix), now).is_ok());
+
+        // Downloads without token are not affected
+        assert!(check_resume_token(&config, &HeaderMap::new(), &object, 1000, None, now).is_ok());
+
+        // Resumed downloads need a range after the start of the download
+        let err = check_resume_token(&config, &headers, &object, 1000, None, now).unwrap_err();
+        assert_eq!(err.code(), &S3ErrorCode::InvalidArgument);
+        let headers = resume_headers(issue_resume_token(&config, &object, 1000, 500, now).unwrap());
+        let err = check_resume_token(
+            &config,
+            &headers,
+            &object,
+            1000,
+            Some(&range_from(400)),
+            now,
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), &S3ErrorCode::InvalidRange);
+
+        // Forged and expired tokens are rejected
+        let forged = resume_headers(HeaderValue::from_static("garbage"));
+        let err = check_resume_token(&config, &forged, &object, 1000, Some(&range_from(400)), now)
+            .unwrap_err();
+        assert_eq!(err.code(), &S3ErrorCode::AccessDenied);
+        assert!(check_resume_token(
+            &config,
+            &headers,
+            &object,
+            1000,
+            Some(&range_from(600)),
+            now + 3601
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_resume_changed_object() {
+        let (config, object) = (config(), object());
+        let now = chrono::Utc::now().timestamp();
+        let headers = resume_headers(issue_resume_token(&config, &object, 1000, 0, now).unwrap());
+        let range = range_from(400);
+
+        // New data with the same size
+        let mut updated = object.clone();
+        updated.hashes.insert("SHA256".to_string(), "cd".repeat(32));
+        // New data with another size
+        let resized = object.clone();
+        // New revision of the object
+        let mut revision = object.clone();
+        revision.id = DieselUlid::generate();
+
+        for (changed, content_len) in [(&updated, 1000), (&resized, 1200), (&revision, 1000)] {
+            let err =
+                check_resume_token(&config, &headers, changed, content_len, Some(&range), now)
+                    .unwrap_err();
+            assert_eq!(err.code(), &S3ErrorCode::PreconditionFailed);
+            assert!(err.message().unwrap().contains("restart"));
+        }
+    }
+}
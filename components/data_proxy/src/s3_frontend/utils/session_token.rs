@@ -0,0 +1,85 @@
+use aruna_common::session_token::verify_session_token;
+use http::{HeaderMap, HeaderValue, Uri};
+use s3s::{s3_error, S3Result};
+use tracing::debug;
+
+/// Header with the session token of temporary credentials
+pub const SESSION_TOKEN_HEADER: &str = "x-amz-security-token";
+/// Query parameter with the session token of presigned urls
+pub const SESSION_TOKEN_QUERY: &str = "X-Amz-Security-Token";
+
+/// Extracts the session token of a request from its headers or its query
+pub fn get_session_token(uri: &Uri, headers: &HeaderMap<HeaderValue>) -> Option<String> {
+    if let Some(token) = headers.get(SESSION_TOKEN_HEADER) {
+        return token.to_str().ok().map(|token| token.to_string());
+    }
+    url::form_urlencoded::parse(uri.query()?.as_bytes())
+        .find(|(key, _)| key == SESSION_TOKEN_QUERY)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Verifies the session token of storage role credentials with the secret of the access key.
+/// Requests with forged or expired session tokens are rejected, requests with session-scoped
+/// credentials also without a session token.
+pub fn check_session_token(
+    uri: &Uri,
+    headers: &HeaderMap<HeaderValue>,
+    access_key: &str,
+    secret: &str,
+    session_scoped: bool,
+) -> S3Result<()> {
+    let Some(token) = get_session_token(uri, headers) else {
+        if session_scoped {
+            debug!(access_key, "missing session token");
+            return Err(s3_error!(
+                AccessDenied,
+                "Credentials require a session token"
+            ));
+        }
+        return Ok(());
+    };
+    verify_session_token(secret, access_key, &token, chrono::Utc::now().timestamp()).map_err(|e| {
+        debug!(error = ?e, access_key, "invalid session token");
+        s3_error!(ExpiredToken, "Invalid or expired session token")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aruna_common::session_token::sign_session_token;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_check_session_token() {
+        let now = chrono::Utc::now().timestamp();
+        let token = sign_session_token("secret", "ACCESSKEY", now + 300).unwrap();
+        let uri = Uri::from_str("http://bucket.localhost/key").unwrap();
+        let mut headers = HeaderMap::new();
+        // Regular credentials without session token
+        assert!(check_session_token(&uri, &headers, "ACCESSKEY", "secret", false).is_ok());
+        // Session-scoped credentials without session token
+        let err = check_session_token(&uri, &headers, "ACCESSKEY", "secret", true).unwrap_err();
+        assert_eq!(err.code(), &s3s::S3ErrorCode::AccessDenied);
+
+        headers.insert(SESSION_TOKEN_HEADER, HeaderValue::from_str(&token).unwrap());
+        assert!(check_session_token(&uri, &headers, "ACCESSKEY", "secret", true).is_ok());
+        assert!(check_session_token(&uri, &headers, "OTHERKEY", "secret", true).is_err());
+
+        // Presigned urls carry the token in the query
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair(SESSION_TOKEN_QUERY, &token)
+            .finish();
+        let uri = Uri::from_str(&format!("http://bucket.localhost/key?{query}")).unwrap();
+        assert!(check_session_token(&uri, &HeaderMap::new(), "ACCESSKEY", "secret", true).is_ok());
+
+        let expired = sign_session_token("secret", "ACCESSKEY", now - 1).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            SESSION_TOKEN_HEADER,
+            HeaderValue::from_str(&expired).unwrap(),
+        );
+        let err = check_session_token(&uri, &headers, "ACCESSKEY", "secret", true).unwrap_err();
+        assert_eq!(err.code(), &s3s::S3ErrorCode::ExpiredToken);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::auth::crypto::SessionCookie;
+use diesel_ulid::DieselUlid;
+use http::{header::COOKIE, HeaderMap, HeaderValue};
+use s3s::{s3_error, S3Result};
+use tracing::debug;
+
+/// Cookie of browser sessions issued by the server
+pub const SIGNED_COOKIE_NAME: &str = "aruna-session";
+
+/// Extracts the value of the signed cookie from the cookie headers of a request
+pub fn get_signed_cookie(headers: &HeaderMap<HeaderValue>) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SIGNED_COOKIE_NAME)
+        .map(|(_, value)| value.trim_matches('"').to_string())
+}
+
+/// Signed cookies only grant access to resources below the resource they are scoped to
+pub fn check_cookie_scope(
+    session: &SessionCookie,
+    path: &[Option<(DieselUlid, String)>; 4],
+) -> S3Result<()> {
+    if path
+        .iter()
+        .flatten()
+        .any(|(id, _)| *id == session.resource_id)
+    {
+        return Ok(());
+    }
+    debug!(?session, ?path, "signed cookie outside of its scope");
+    Err(s3_error!(AccessDenied, "Access Denied"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::crypto::{sign_session_cookie, verify_session_cookie};
+
+    fn path(ids: [DieselUlid; 3]) -> [Option<(DieselUlid, String)>; 4] {
+        let [project, collection, object] = ids;
+        [
+            Some((project, "project".to_string())),
+            Some((collection, "collection".to_string())),
+            None,
+            Some((object, "tile.png".to_string())),
+        ]
+    }
+
+    #[test]
+    fn test_cookie_scope() {
+        let (project, collection_a, collection_b) = (
+            DieselUlid::generate(),
+            DieselUlid::generate(),
+            DieselUlid::generate(),
+        );
+        let session = SessionCookie {
+            access_key: DieselUlid::generate().to_string(),
+            resource_id: collection_a,
+            expires_at: chrono::Utc::now().timestamp() + 300,
+        };
+        let value = sign_session_cookie("secret", &session).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            COOKIE,
+            HeaderValue::from_str(&format!("theme=dark; {SIGNED_COOKIE_NAME}={value}")).unwrap(),
+        );
+        let cookie = get_signed_cookie(&headers).unwrap();
+        let session =
+            verify_session_cookie("secret", &cookie, chrono::Utc::now().timestamp()).unwrap();
+
+        // Objects below the scoped collection
+        for _ in 0..3 {
+            let object = path([project, collection_a, DieselUlid::generate()]);
+            assert!(check_cookie_scope(&session, &object).is_ok());
+        }
+        // Objects of another collection or the project itself
+        let outside = path([project, collection_b, DieselUlid::generate()]);
+        assert!(check_cookie_scope(&session, &outside).is_err());
+        let project_only = [Some((project, "project".to_string())), None, None, None];
+        assert!(check_cookie_scope(&session, &project_only).is_err());
+
+        // Requests without the cookie
+        assert_eq!(get_signed_cookie(&HeaderMap::new()), None);
+    }
+}
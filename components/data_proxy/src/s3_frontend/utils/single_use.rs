@@ -0,0 +1,293 @@
+use ahash::RandomState;
+use bytes::Bytes;
+use chrono::NaiveDateTime;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use futures_core::Stream;
+use http::Uri;
+use s3s::{s3_error, S3Result};
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
+use tracing::{debug, warn};
+
+/// Signed query parameter of presigned urls which can only be downloaded once
+pub const SINGLE_USE_KEY: &str = "x-aruna-single-use";
+
+/// Single-use link of a presigned url, valid until the url expires
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SingleUseLink {
+    pub id: String,
+    pub expires_at: i64,
+}
+
+/// Extracts the single-use link of a presigned url. The expiry is derived from the
+/// signed `X-Amz-Date` and `X-Amz-Expires` parameters.
+pub fn get_single_use_link(uri: &Uri) -> S3Result<Option<SingleUseLink>> {
+    let Some(query) = uri.query() else {
+        return Ok(None);
+    };
+    let params = url::form_urlencoded::parse(query.as_bytes()).collect::<Vec<_>>();
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string())
+    };
+    let Some(id) = param(SINGLE_USE_KEY) else {
+        return Ok(None);
+    };
+    let signed_at = param("X-Amz-Date")
+        .and_then(|date| NaiveDateTime::parse_from_str(&date, "%Y%m%dT%H%M%SZ").ok())
+        .ok_or_else(|| s3_error!(AccessDenied, "Single-use links have to be presigned"))?;
+    let expires = param("X-Amz-Expires")
+        .and_then(|expires| expires.parse::<i64>().ok())
+        .ok_or_else(|| s3_error!(AccessDenied, "Single-use links have to be presigned"))?;
+    Ok(Some(SingleUseLink {
+        id,
+        expires_at: signed_at.and_utc().timestamp() + expires,
+    }))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LinkState {
+    InUse,
+    Consumed { expires_at: i64 },
+}
+
+/// Single-use links which are downloading or already consumed.
+///
+/// A link is reserved when its download starts, concurrent downloads of the same link
+/// are rejected. The link is consumed once the whole body was sent, interrupted
+/// downloads release the reservation and the link can be used again.
+#[derive(Default)]
+pub struct SingleUseLinks {
+    links: DashMap<String, LinkState, RandomState>,
+    // Receives consumed links to persist them
+    consumed: OnceLock<async_channel::Sender<SingleUseLink>>,
+}
+
+impl SingleUseLinks {
+    pub fn set_consumed_sender(&self, sender: async_channel::Sender<SingleUseLink>) {
+        if self.consumed.set(sender).is_err() {
+            warn!("Consumed link sender already set");
+        }
+    }
+
+    /// Restores consumed links, e.g. from the persistence
+    pub fn load(&self, links: Vec<SingleUseLink>) {
+        for link in links {
+            self.links.insert(
+                link.id,
+                LinkState::Consumed {
+                    expires_at: link.expires_at,
+                },
+            );
+        }
+    }
+
+    pub fn reserve(self: &Arc<Self>, link: SingleUseLink) -> S3Result<SingleUseReservation> {
+        match self.links.entry(link.id.clone()) {
+            Entry::Occupied(entry) => {
+                debug!(link = link.id, state = ?entry.get(), "single-use link rejected");
+                Err(s3_error!(AccessDenied, "Link was already used"))
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(LinkState::InUse);
+                Ok(SingleUseReservation {
+                    links: self.clone(),
+                    link: Some(link),
+                })
+            }
+        }
+    }
+
+    fn consume(&self, link: SingleUseLink) {
+        let now = chrono::Utc::now().timestamp();
+        self.links.retain(|_, state| match state {
+            LinkState::Consumed { expires_at } => *expires_at > now,
+            LinkState::InUse => true,
+        });
+        self.links.insert(
+            link.id.clone(),
+            LinkState::Consumed {
+                expires_at: link.expires_at,
+            },
+        );
+        if let Some(sender) = self.consumed.get() {
+            if let Err(e) = sender.try_send(link) {
+                warn!(error = ?e, "unable to persist consumed link");
+            }
+        }
+    }
+
+    fn release(&self, link: &SingleUseLink) {
+        self.links
+            .remove_if(&link.id, |_, state| *state == LinkState::InUse);
+    }
+}
+
+/// Reservation of a single-use link during its download, released if it is dropped
+/// without being consumed
+pub struct SingleUseReservation {
+    links: Arc<SingleUseLinks>,
+    link: Option<SingleUseLink>,
+}
+
+impl SingleUseReservation {
+    pub fn consume(mut self) {
+        if let Some(link) = self.link.take() {
+            self.links.consume(link);
+        }
+    }
+}
+
+impl Drop for SingleUseReservation {
+    fn drop(&mut self) {
+        if let Some(link) = self.link.take() {
+            debug!(link = link.id, "single-use download interrupted");
+            self.links.release(&link);
+        }
+    }
+}
+
+/// Response body which consumes its single-use link once the last chunk was sent.
+/// Errors and disconnects release the link.
+pub struct SingleUseBody<E> {
+    inner: Pin<Box<dyn Stream<Item = Result<Bytes, E>> + Send + Sync>>,
+    reservation: Option<SingleUseReservation>,
+}
+
+impl<E> SingleUseBody<E> {
+    pub fn new(
+        inner: impl Stream<Item = Result<Bytes, E>> + Send + Sync + 'static,
+        reservation: SingleUseReservation,
+    ) -> Self {
+        SingleUseBody {
+            inner: Box::pin(inner),
+            reservation: Some(reservation),
+        }
+    }
+}
+
+impl<E> Stream for SingleUseBody<E> {
+    type Item = Result<Bytes, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.inner.as_mut().poll_next(cx);
+        match &poll {
+            Poll::Ready(None) => {
+                if let Some(reservation) = self.reservation.take() {
+                    reservation.consume();
+                }
+            }
+            Poll::Ready(Some(Err(_))) => {
+                self.reservation.take();
+            }
+            _ => {}
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::str::FromStr;
+
+    fn link(id: &str) -> SingleUseLink {
+        SingleUseLink {
+            id: id.to_string(),
+            expires_at: chrono::Utc::now().timestamp() + 300,
+        }
+    }
+
+    fn body(
+        links: &Arc<SingleUseLinks>,
+        id: &str,
+        chunks: Vec<Result<Bytes, std::io::Error>>,
+    ) -> S3Result<SingleUseBody<std::io::Error>> {
+        let reservation = links.reserve(link(id))?;
+        Ok(SingleUseBody::new(
+            futures::stream::iter(chunks),
+            reservation,
+        ))
+    }
+
+    #[test]
+    fn test_get_single_use_link() {
+        let uri = Uri::from_str(&format!(
+            "http://bucket.localhost/key?X-Amz-Date=20240101T000000Z&X-Amz-Expires=300&{SINGLE_USE_KEY}=abc"
+        ))
+        .unwrap();
+        assert_eq!(
+            get_single_use_link(&uri).unwrap(),
+            Some(SingleUseLink {
+                id: "abc".to_string(),
+                expires_at: 1704067200 + 300,
+            })
+        );
+        let uri = Uri::from_str("http://bucket.localhost/key").unwrap();
+        assert_eq!(get_single_use_link(&uri).unwrap(), None);
+        let uri =
+            Uri::from_str(&format!("http://bucket.localhost/key?{SINGLE_USE_KEY}=abc")).unwrap();
+        assert!(get_single_use_link(&uri).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_first_download_consumes_link() {
+        let links = Arc::new(SingleUseLinks::default());
+        let (sender, receiver) = async_channel::bounded(1);
+        links.set_consumed_sender(sender);
+
+        let data = body(
+            &links,
+            "first",
+            vec![Ok(Bytes::from("a")), Ok(Bytes::from("b"))],
+        )
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(data.len(), 2);
+        assert_eq!(receiver.try_recv().unwrap().id, "first");
+
+        // Second download is rejected
+        assert!(body(&links, "first", vec![]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_download_keeps_link() {
+        let links = Arc::new(SingleUseLinks::default());
+
+        // Client disconnects after the first chunk
+        let mut interrupted = body(
+            &links,
+            "link",
+            vec![Ok(Bytes::from("a")), Ok(Bytes::from("b"))],
+        )
+        .unwrap();
+        assert!(interrupted.next().await.is_some());
+        // Concurrent downloads of a link in use are rejected
+        assert!(body(&links, "link", vec![]).is_err());
+        drop(interrupted);
+
+        // Failed transfer
+        let failed = body(
+            &links,
+            "link",
+            vec![Ok(Bytes::from("a")), Err(std::io::Error::other("backend"))],
+        )
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+        assert!(failed[1].is_err());
+
+        // Link is still usable and consumed by the complete download
+        body(&links, "link", vec![Ok(Bytes::from("a"))])
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert!(body(&links, "link", vec![]).is_err());
+    }
+}
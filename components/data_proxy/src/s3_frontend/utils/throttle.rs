@@ -0,0 +1,240 @@
+use crate::config::DownloadLimits;
+use crate::replication::limits::BandwidthLimiter;
+use crate::structs::UserState;
+use ahash::RandomState;
+use async_channel::Receiver;
+use bytes::Bytes;
+use dashmap::DashMap;
+use futures_util::{Stream, StreamExt};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::pin;
+use tracing::{info, info_span, Instrument};
+
+/// Limits the bandwidth of downloads per token and for all downloads together.
+/// Limited downloads are slowed down, they never fail because of the limits.
+#[derive(Debug)]
+pub struct DownloadThrottle {
+    global: Option<Arc<BandwidthLimiter>>,
+    per_token: Option<u64>,
+    token_limiters: DashMap<String, Arc<BandwidthLimiter>, RandomState>,
+    exempt_access_keys: HashSet<String>,
+    metrics: Arc<DownloadMetrics>,
+}
+
+impl DownloadThrottle {
+    pub fn new(config: &DownloadLimits) -> Self {
+        DownloadThrottle {
+            global: config
+                .global
+                .map(|limit| Arc::new(BandwidthLimiter::new(limit))),
+            per_token: config.per_token,
+            token_limiters: DashMap::default(),
+            exempt_access_keys: config.exempt_access_keys.iter().cloned().collect(),
+            metrics: Arc::new(DownloadMetrics::new()),
+        }
+    }
+
+    /// Starts the throttle with a background task which logs the download throughput
+    pub fn start(config: &DownloadLimits) -> Arc<Self> {
+        let throttle = Arc::new(DownloadThrottle::new(config));
+        let interval = Duration::from_secs(config.metrics_interval);
+        let weak = Arc::downgrade(&throttle);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(throttle) = weak.upgrade() else {
+                    return;
+                };
+                // Limiters of tokens without running downloads are dropped
+                throttle
+                    .token_limiters
+                    .retain(|_, limiter| Arc::strong_count(limiter) > 1);
+                throttle.metrics.log();
+            }
+        });
+        throttle
+    }
+
+    /// Forwards the body stream of a download, chunks are passed on as soon as they fit into the limits
+    pub fn throttle<S, E>(&self, user_state: &UserState, stream: S) -> Receiver<Result<Bytes, E>>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: Send + 'static,
+    {
+        let limiters = self.get_limiters(user_state);
+        let metrics = self.metrics.clone();
+        let (sender, receiver) = async_channel::bounded(10);
+        tokio::spawn(
+            async move {
+                let _active = ActiveDownload::new(metrics.clone());
+                pin!(stream);
+                while let Some(chunk) = stream.next().await {
+                    if let Ok(bytes) = &chunk {
+                        for limiter in &limiters {
+                            limiter.consume(bytes.len() as u64).await;
+                        }
+                        metrics
+                            .transferred_bytes
+                            .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                    }
+                    if sender.send(chunk).await.is_err() {
+                        // Download was aborted by the client
+                        return;
+                    }
+                }
+            }
+            .instrument(info_span!("download_throttle")),
+        );
+        receiver
+    }
+
+    fn get_limiters(&self, user_state: &UserState) -> Vec<Arc<BandwidthLimiter>> {
+        let token = match user_state {
+            UserState::Token { access_key, .. } => {
+                if self.exempt_access_keys.contains(access_key) {
+                    return vec![];
+                }
+                Some(access_key.clone())
+            }
+            UserState::Personal { user_id } => Some(user_id.to_string()),
+            UserState::Anonymous => None,
+        };
+        let mut limiters = Vec::with_capacity(2);
+        if let (Some(token), Some(limit)) = (token, self.per_token) {
+            limiters.push(
+                self.token_limiters
+                    .entry(token)
+                    .or_insert_with(|| Arc::new(BandwidthLimiter::new(limit)))
+                    .clone(),
+            );
+        }
+        if let Some(global) = &self.global {
+            limiters.push(global.clone());
+        }
+        limiters
+    }
+
+    pub fn metrics(&self) -> &DownloadMetrics {
+        &self.metrics
+    }
+}
+
+#[derive(Debug)]
+pub struct DownloadMetrics {
+    active: AtomicUsize,
+    transferred_bytes: AtomicU64,
+    // Transferred bytes at the last throughput sample
+    last_sample: Mutex<(Instant, u64)>,
+}
+
+impl DownloadMetrics {
+    fn new() -> Self {
+        DownloadMetrics {
+            active: AtomicUsize::new(0),
+            transferred_bytes: AtomicU64::new(0),
+            last_sample: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn transferred_bytes(&self) -> u64 {
+        self.transferred_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Aggregated download throughput in bytes per second since the last sample
+    pub fn sample_throughput(&self) -> f64 {
+        let transferred = self.transferred_bytes();
+        let Ok(mut last_sample) = self.last_sample.lock() else {
+            return 0.0;
+        };
+        let (last_time, last_transferred) = *last_sample;
+        let elapsed = last_time.elapsed().as_secs_f64();
+        *last_sample = (Instant::now(), transferred);
+        if elapsed > 0.0 {
+            transferred.saturating_sub(last_transferred) as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    pub fn log(&self) {
+        info!(
+            active = self.active(),
+            transferred_bytes = self.transferred_bytes(),
+            throughput = self.sample_throughput(),
+            "download metrics"
+        );
+    }
+}
+
+/// Counts a download as active until it is dropped
+struct ActiveDownload(Arc<DownloadMetrics>);
+
+impl ActiveDownload {
+    fn new(metrics: Arc<DownloadMetrics>) -> Self {
+        metrics.active.fetch_add(1, Ordering::Relaxed);
+        ActiveDownload(metrics)
+    }
+}
+
+impl Drop for ActiveDownload {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel_ulid::DieselUlid;
+
+    fn chunks(count: usize, size: usize) -> impl Stream<Item = Result<Bytes, ()>> + Send {
+        futures_util::stream::iter((0..count).map(move |_| Ok(Bytes::from(vec![0u8; size]))))
+    }
+
+    #[tokio::test]
+    async fn test_throttled_download() {
+        let throttle = DownloadThrottle::new(&DownloadLimits {
+            per_token: Some(1000),
+            global: None,
+            exempt_access_keys: vec!["trusted".to_string()],
+            metrics_interval: 60,
+        });
+        let user = UserState::Token {
+            access_key: "token".to_string(),
+            user_id: DieselUlid::generate(),
+        };
+
+        let start = Instant::now();
+        // The first 1000 bytes are covered by the initial bucket, the rest takes 0.5 seconds
+        let received = throttle
+            .throttle(&user, chunks(15, 100))
+            .collect::<Vec<_>>()
+            .await;
+        assert!(start.elapsed() >= Duration::from_millis(500));
+        assert_eq!(received.len(), 15);
+        assert!(received.iter().all(|chunk| chunk.is_ok()));
+        assert_eq!(throttle.metrics().transferred_bytes(), 1500);
+        assert_eq!(throttle.metrics().active(), 0);
+
+        // Trusted tokens are not limited
+        let trusted = UserState::Token {
+            access_key: "trusted".to_string(),
+            user_id: DieselUlid::generate(),
+        };
+        let start = Instant::now();
+        throttle
+            .throttle(&trusted, chunks(15, 100))
+            .collect::<Vec<_>>()
+            .await;
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+}
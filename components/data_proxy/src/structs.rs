@@ -1,11 +1,12 @@
 use anyhow::Result;
 use anyhow::{anyhow, bail};
+use aruna_common::session_token::SESSION_SCOPED_TOKEN_ATTRIBUTE;
 use aruna_rust_api::api::storage::models::v2::generic_resource::Resource;
 use aruna_rust_api::api::storage::models::v2::permission::ResourceId;
 use aruna_rust_api::api::storage::models::v2::Pubkey;
 use aruna_rust_api::api::storage::models::v2::{
-    relation::Relation, DataClass, InternalRelationVariant, KeyValue, Object as GrpcObject,
-    PermissionLevel, Project, RelationDirection, Status, User as GrpcUser,
+    relation::Relation, DataClass, InternalRelationVariant, KeyValue, KeyValueVariant,
+    Object as GrpcObject, PermissionLevel, Project, RelationDirection, Status, User as GrpcUser,
 };
 use aruna_rust_api::api::storage::models::v2::{Collection, DataEndpoint};
 use aruna_rust_api::api::storage::models::v2::{Dataset, ResourceVariant};
@@ -40,6 +41,19 @@ use crate::CONFIG;
 
 /* ----- Constants ----- */
 pub const ALL_RIGHTS_RESERVED: &str = "AllRightsReserved";
+/// Hook status key-value with the access key of the scanner of a scanning object
+pub const SCAN_TOKEN_KEY: &str = "app.aruna-storage.org/scan-token";
+/// Object label to opt in or out of encryption at rest, overrides the backend default
+pub const ENCRYPTION_KEY: &str = "app.aruna-storage.org/encryption";
+/// Project label which forces encryption of all objects, overrides object opt-outs
+pub const ENFORCE_ENCRYPTION_KEY: &str = "app.aruna-storage.org/enforce-encryption";
+/// Project label to opt in to the consolidation of multipart objects into a single blob
+pub const CONSOLIDATE_PARTS_KEY: &str = "app.aruna-storage.org/consolidate-parts";
+/// Project label to accept checksum attestations of clients instead of waiting for the
+/// verification of multipart uploads, see `verify_checksum_attestation`
+pub const TRUSTED_CLIENT_CHECKSUMS_KEY: &str = "app.aruna-storage.org/trusted-client-checksums";
+/// Object label with the RFC3339 timestamp after which the object can not be downloaded anymore
+pub const EXPIRES_AT_KEY: &str = "app.aruna-storage.org/expires-at";
 
 #[tracing::instrument(level = "trace", skip())]
 pub fn type_name_of<T>(_: T) -> &'static str {
@@ -180,6 +194,22 @@ impl FileFormat {
         }
     }
 
+    /// File format of a new location, the encryption choice of an object
+    /// overrides the backend default. Every location gets its own key.
+    pub fn from_choice(
+        allow_pithos: bool,
+        allow_encryption: bool,
+        allow_compression: bool,
+        encryption_choice: Option<bool>,
+    ) -> Self {
+        match encryption_choice {
+            None => FileFormat::from_bools(allow_pithos, allow_encryption, allow_compression),
+            Some(true) => FileFormat::from_bools(allow_pithos, true, allow_compression),
+            // Pithos files are always encrypted
+            Some(false) => FileFormat::from_bools(false, false, allow_compression),
+        }
+    }
+
     pub fn is_encrypted(&self) -> bool {
         matches!(
             self,
@@ -229,6 +259,9 @@ pub struct ObjectLocation {
     pub disk_hash: Option<String>,
     pub is_temporary: bool,
     pub ref_count: u32, // Number of objects that reference this location
+    // Storage tier of the data, stored in the default backend if not set
+    #[serde(default)]
+    pub tier: Option<String>,
 }
 
 impl ObjectLocation {
@@ -349,6 +382,50 @@ impl Object {
         }
     }
 
+    /// Returns the encryption choice of the object, None uses the backend default
+    pub fn get_encryption_choice(&self) -> Option<bool> {
+        self.key_values
+            .iter()
+            .find(|kv| kv.key == ENCRYPTION_KEY)
+            .and_then(|kv| kv.value.parse::<bool>().ok())
+    }
+
+    pub fn enforces_encryption(&self) -> bool {
+        self.key_values
+            .iter()
+            .any(|kv| kv.key == ENFORCE_ENCRYPTION_KEY && kv.value.eq_ignore_ascii_case("true"))
+    }
+
+    pub fn consolidates_parts(&self) -> bool {
+        self.key_values
+            .iter()
+            .any(|kv| kv.key == CONSOLIDATE_PARTS_KEY && kv.value.eq_ignore_ascii_case("true"))
+    }
+
+    pub fn trusts_client_checksums(&self) -> bool {
+        self.key_values.iter().any(|kv| {
+            kv.key == TRUSTED_CLIENT_CHECKSUMS_KEY && kv.value.eq_ignore_ascii_case("true")
+        })
+    }
+
+    /// Records the encryption choice as object label,
+    /// projects which enforce encryption override opt-outs
+    pub fn set_encryption_choice(&mut self, encryption_choice: Option<bool>, enforced: bool) {
+        let encryption_choice = if enforced {
+            Some(true)
+        } else {
+            encryption_choice
+        };
+        self.key_values.retain(|kv| kv.key != ENCRYPTION_KEY);
+        if let Some(encryption) = encryption_choice {
+            self.key_values.push(KeyValue {
+                key: ENCRYPTION_KEY.to_string(),
+                value: encryption.to_string(),
+                variant: KeyValueVariant::Label as i32,
+            });
+        }
+    }
+
     pub fn get_file_context(
         &self,
         location: Option<ObjectLocation>,
@@ -392,6 +469,9 @@ pub struct User {
     pub user_id: DieselUlid,
     pub personal_permissions: HashMap<DieselUlid, DbPermissionLevel>,
     pub tokens: HashMap<DieselUlid, HashMap<DieselUlid, DbPermissionLevel>>,
+    // Tokens whose credentials need a session token, e.g. storage roles
+    #[serde(default)]
+    pub session_tokens: HashSet<DieselUlid>,
     pub is_service_account: bool,
     pub attributes: HashMap<String, String>,
 }
@@ -521,6 +601,18 @@ impl TryFrom<GrpcUser> for User {
                     ))
                 })
                 .collect::<Result<HashMap<DieselUlid, HashMap<DieselUlid, DbPermissionLevel>>>>()?,
+            session_tokens: value
+                .attributes
+                .as_ref()
+                .map(|attr| {
+                    attr.custom_attributes
+                        .iter()
+                        .filter(|a| a.attribute_name == SESSION_SCOPED_TOKEN_ATTRIBUTE)
+                        .map(|a| DieselUlid::from_str(&a.attribute_value))
+                        .collect::<Result<HashSet<_>, _>>()
+                })
+                .transpose()?
+                .unwrap_or_default(),
             attributes,
             is_service_account,
         })
@@ -534,6 +626,9 @@ pub struct AccessKeyPermissions {
     pub secret: String,
     pub is_service_account: bool,
     pub permissions: HashMap<DieselUlid, DbPermissionLevel>,
+    // Requests with these credentials have to present a valid session token
+    #[serde(default)]
+    pub session_scoped: bool,
 }
 
 // TODO! ENDPOINTS
@@ -1112,7 +1207,12 @@ impl From<Object> for CreateObjectRequest {
             name: value.name,
             title: value.title,
             description: "".to_string(),
-            key_values: vec![],
+            // Stores the encryption choice of the upload with the object
+            key_values: value
+                .key_values
+                .into_iter()
+                .filter(|kv| kv.key == ENCRYPTION_KEY)
+                .collect(),
             relations: vec![],
             data_class: value.data_class.into(),
             parent: value
@@ -1186,6 +1286,53 @@ impl Object {
         Ok(())
     }
 
+    /// Expired objects are unavailable before the server deletes them,
+    /// objects with an invalid expiry are treated as expired
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn is_expired(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.key_values
+            .iter()
+            .find(|kv| kv.key == EXPIRES_AT_KEY)
+            .map(|kv| {
+                chrono::DateTime::parse_from_rfc3339(&kv.value)
+                    .map(|expires_at| expires_at <= now)
+                    .unwrap_or(true)
+            })
+            .unwrap_or(false)
+    }
+
+    /// Scanning objects can only be downloaded by the scanner, quarantined and expired objects not at all
+    #[tracing::instrument(level = "trace", skip(self, user_state))]
+    pub fn fail_not_downloadable(&self, user_state: &UserState) -> Result<(), S3Error> {
+        if self.is_expired(chrono::Utc::now()) {
+            error!("Rejecting request: Object expired");
+            return Err(s3_error!(InvalidObjectState, "Object expired"));
+        }
+        match self.object_status {
+            Status::Unavailable => {
+                error!("Rejecting request: Object quarantined");
+                Err(s3_error!(InvalidObjectState, "Object is quarantined"))
+            }
+            Status::Validating => {
+                let UserState::Token { access_key, .. } = user_state else {
+                    error!("Rejecting request: Object is being scanned");
+                    return Err(s3_error!(InvalidObjectState, "Object is being scanned"));
+                };
+                let is_scanner = self.key_values.iter().any(|kv| {
+                    kv.key == SCAN_TOKEN_KEY
+                        && kv.variant == KeyValueVariant::HookStatus as i32
+                        && &kv.value == access_key
+                });
+                if !is_scanner {
+                    error!("Rejecting request: Object is being scanned");
+                    return Err(s3_error!(InvalidObjectState, "Object is being scanned"));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
     #[tracing::instrument(level = "trace", skip(self))]
     pub fn project_get_headers(
         &self,
@@ -1965,6 +2112,98 @@ pub struct UploadPart {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn test_resource_strings_cmp() {}
+
+    #[test]
+    fn test_encryption_choice() {
+        let mut project = Object::initialize_now("project".to_string(), ObjectType::Project, None);
+        let mut plain = Object::initialize_now("plain".to_string(), ObjectType::Object, None);
+        let mut encrypted =
+            Object::initialize_now("encrypted".to_string(), ObjectType::Object, None);
+        plain.set_encryption_choice(Some(false), project.enforces_encryption());
+        encrypted.set_encryption_choice(Some(true), project.enforces_encryption());
+
+        // Mixed formats with the same backend defaults, each with its own key
+        let plain_format = FileFormat::from_choice(true, true, true, plain.get_encryption_choice());
+        let encrypted_format =
+            FileFormat::from_choice(true, true, true, encrypted.get_encryption_choice());
+        let other_format =
+            FileFormat::from_choice(true, true, true, encrypted.get_encryption_choice());
+        assert_eq!(plain_format, FileFormat::RawCompressed);
+        assert!(encrypted_format.is_encrypted());
+        assert_ne!(
+            encrypted_format.get_encryption_key(),
+            other_format.get_encryption_key()
+        );
+        assert_eq!(
+            FileFormat::from_choice(false, false, false, None),
+            FileFormat::Raw
+        );
+
+        // Enforcing projects override opt-outs
+        project.key_values.push(KeyValue {
+            key: ENFORCE_ENCRYPTION_KEY.to_string(),
+            value: "true".to_string(),
+            variant: KeyValueVariant::Label as i32,
+        });
+        plain.set_encryption_choice(Some(false), project.enforces_encryption());
+        assert_eq!(plain.get_encryption_choice(), Some(true));
+        assert_eq!(
+            plain
+                .key_values
+                .iter()
+                .filter(|kv| kv.key == ENCRYPTION_KEY)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_expired_not_downloadable() {
+        let mut object = Object::initialize_now("expiring".to_string(), ObjectType::Object, None);
+        let now = chrono::Utc::now();
+        object.key_values.push(KeyValue {
+            key: EXPIRES_AT_KEY.to_string(),
+            value: (now + chrono::Duration::seconds(1)).to_rfc3339(),
+            variant: KeyValueVariant::Label as i32,
+        });
+        assert!(object.fail_not_downloadable(&UserState::Anonymous).is_ok());
+        assert!(object.is_expired(now + chrono::Duration::seconds(2)));
+
+        object.key_values.last_mut().unwrap().value =
+            (now - chrono::Duration::seconds(1)).to_rfc3339();
+        assert!(object.fail_not_downloadable(&UserState::Anonymous).is_err());
+    }
+
+    #[test]
+    fn test_session_scoped_tokens() {
+        use aruna_rust_api::api::storage::models::v2::{CustomAttribute, Token, UserAttributes};
+
+        let token = |id: DieselUlid, name: &str| Token {
+            id: id.to_string(),
+            name: name.to_string(),
+            permission: Some(Permission::default()),
+            ..Default::default()
+        };
+        let (role, named) = (DieselUlid::generate(), DieselUlid::generate());
+        let user = GrpcUser {
+            id: DieselUlid::generate().to_string(),
+            attributes: Some(UserAttributes {
+                tokens: vec![token(role, "role"), token(named, "storage-role:named")],
+                custom_attributes: vec![CustomAttribute {
+                    attribute_name: SESSION_SCOPED_TOKEN_ATTRIBUTE.to_string(),
+                    attribute_value: role.to_string(),
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Only tokens marked by the server are session-scoped, independent of their name
+        let user = User::try_from(user).unwrap();
+        assert_eq!(user.session_tokens, HashSet::from([role]));
+    }
 }
@@ -0,0 +1,223 @@
+use super::token_handler::{ProcessedToken, TokenHandler};
+use crate::caching::cache::Cache;
+use anyhow::{anyhow, bail, Result};
+use diesel_ulid::DieselUlid;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Identity of an authenticated caller
+pub type Identity = ProcessedToken;
+
+/// Validates credentials and resolves them to an identity.
+/// Authenticators return `Ok(None)` for credentials they are not responsible for,
+/// so that the next authenticator can be tried.
+#[async_trait::async_trait]
+pub trait Authenticator: Send + Sync {
+    async fn authenticate(&self, credentials: &str) -> Result<Option<Identity>>;
+}
+
+/// Default authentication of OIDC, ArunaServer and Dataproxy JWTs
+#[async_trait::async_trait]
+impl Authenticator for TokenHandler {
+    async fn authenticate(&self, credentials: &str) -> Result<Option<Identity>> {
+        self.process_token(credentials).await.map(Some)
+    }
+}
+
+/// Tries multiple authenticators in order, the first resolved identity wins
+#[derive(Clone, Default)]
+pub struct AuthenticatorChain(Vec<Arc<dyn Authenticator>>);
+
+impl AuthenticatorChain {
+    pub fn new(authenticators: Vec<Arc<dyn Authenticator>>) -> Self {
+        AuthenticatorChain(authenticators)
+    }
+
+    pub fn push(&mut self, authenticator: Arc<dyn Authenticator>) {
+        self.0.push(authenticator);
+    }
+}
+
+#[async_trait::async_trait]
+impl Authenticator for AuthenticatorChain {
+    /// Returns the error of the first failed authenticator if no identity could be resolved
+    async fn authenticate(&self, credentials: &str) -> Result<Option<Identity>> {
+        let mut first_error = None;
+        for authenticator in &self.0 {
+            match authenticator.authenticate(credentials).await {
+                Ok(Some(identity)) => return Ok(Some(identity)),
+                Ok(None) => {}
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Authenticates static API keys which are mapped to existing users
+pub struct StaticKeyAuthenticator {
+    cache: Arc<Cache>,
+    keys: HashMap<String, DieselUlid>,
+}
+
+impl StaticKeyAuthenticator {
+    pub fn new(cache: Arc<Cache>, keys: HashMap<String, DieselUlid>) -> Self {
+        StaticKeyAuthenticator { cache, keys }
+    }
+
+    /// Parses keys of the form `<key>=<user_id>,<key>=<user_id>`
+    pub fn from_config(cache: Arc<Cache>, config: &str) -> Result<Self> {
+        let mut keys = HashMap::new();
+        for entry in config.split(',').filter(|entry| !entry.trim().is_empty()) {
+            let (key, user_id) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Static API keys must be of the form <key>=<user_id>"))?;
+            let key = key.trim();
+            if key.len() < 32 {
+                bail!("Static API keys must be at least 32 characters long");
+            }
+            keys.insert(key.to_string(), DieselUlid::from_str(user_id.trim())?);
+        }
+        Ok(StaticKeyAuthenticator::new(cache, keys))
+    }
+}
+
+#[async_trait::async_trait]
+impl Authenticator for StaticKeyAuthenticator {
+    async fn authenticate(&self, credentials: &str) -> Result<Option<Identity>> {
+        let Some(user_id) = self.keys.get(credentials) else {
+            return Ok(None);
+        };
+        let user = self
+            .cache
+            .get_user(user_id)
+            .ok_or_else(|| anyhow!("Invalid user"))?;
+        if !user.active {
+            bail!("User is deactivated")
+        }
+        let (user_permissions, is_personal) = user.get_permissions(None)?;
+        Ok(Some(Identity {
+            main_id: user.id,
+            token: None,
+            is_personal,
+            user_permissions,
+            is_proxy: false,
+            proxy_intent: None,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dsls::user_dsl::{User, UserAttributes};
+    use crate::database::enums::{DbPermissionLevel, ObjectMapping};
+    use dashmap::DashMap;
+    use postgres_types::Json;
+
+    const KEY: &str = "static-test-key-0123456789abcdefghij";
+
+    fn user(active: bool, project_id: DieselUlid) -> User {
+        User {
+            id: DieselUlid::generate(),
+            display_name: "static".to_string(),
+            first_name: "".to_string(),
+            last_name: "".to_string(),
+            email: "static@test".to_string(),
+            attributes: Json(UserAttributes {
+                global_admin: false,
+                service_account: false,
+                tokens: DashMap::default(),
+                trusted_endpoints: DashMap::default(),
+                custom_attributes: vec![],
+                permissions: DashMap::from_iter([(
+                    project_id,
+                    ObjectMapping::PROJECT(DbPermissionLevel::READ),
+                )]),
+                external_ids: vec![],
+                pubkey: "".to_string(),
+                data_proxy_attribute: vec![],
+            }),
+            active,
+        }
+    }
+
+    struct Failing;
+
+    #[async_trait::async_trait]
+    impl Authenticator for Failing {
+        async fn authenticate(&self, _credentials: &str) -> Result<Option<Identity>> {
+            bail!("Invalid token")
+        }
+    }
+
+    #[tokio::test]
+    async fn static_key_authentication() {
+        let cache = Cache::new();
+        let project_id = DieselUlid::generate();
+        let active = user(true, project_id);
+        let inactive = user(false, project_id);
+        cache.add_user(active.id, active.clone());
+        cache.add_user(inactive.id, inactive.clone());
+
+        let config = format!("{KEY}={},{KEY}-inactive={}", active.id, inactive.id);
+        let authenticator = StaticKeyAuthenticator::from_config(cache, &config).unwrap();
+
+        let identity = authenticator.authenticate(KEY).await.unwrap().unwrap();
+        assert_eq!(identity.main_id, active.id);
+        assert!(identity.is_personal);
+        assert_eq!(
+            identity.user_permissions,
+            vec![(project_id, DbPermissionLevel::READ)]
+        );
+
+        // Unknown keys are left to other authenticators
+        assert!(authenticator
+            .authenticate("unknown")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(authenticator
+            .authenticate(&format!("{KEY}-inactive"))
+            .await
+            .is_err());
+
+        // Short keys and malformed entries are rejected
+        assert!(StaticKeyAuthenticator::from_config(
+            Cache::new(),
+            "short=01H819G3ZMK5DC9Q5PD18N9SXB"
+        )
+        .is_err());
+        assert!(StaticKeyAuthenticator::from_config(Cache::new(), KEY).is_err());
+    }
+
+    #[tokio::test]
+    async fn authenticator_chain() {
+        let cache = Cache::new();
+        let user = user(true, DieselUlid::generate());
+        cache.add_user(user.id, user.clone());
+        let static_keys = Arc::new(StaticKeyAuthenticator::new(
+            cache,
+            HashMap::from_iter([(KEY.to_string(), user.id)]),
+        ));
+
+        // Errors of previous authenticators are skipped if a later one succeeds
+        let chain = AuthenticatorChain::new(vec![Arc::new(Failing), static_keys.clone()]);
+        let identity = chain.authenticate(KEY).await.unwrap().unwrap();
+        assert_eq!(identity.main_id, user.id);
+
+        // The first error is returned if no authenticator succeeds
+        let err = chain.authenticate("unknown").await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid token");
+
+        // Without errors, unknown credentials are not resolved
+        let chain = AuthenticatorChain::new(vec![static_keys]);
+        assert!(chain.authenticate("unknown").await.unwrap().is_none());
+    }
+}
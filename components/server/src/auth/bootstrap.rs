@@ -0,0 +1,90 @@
+use anyhow::{bail, Result};
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+lazy_static! {
+    pub static ref ADMIN_BOOTSTRAP: Option<AdminBootstrap> = AdminBootstrap::from_env();
+}
+
+/// One-time token which promotes the first registered user of a fresh instance to global admin.
+/// The token is unusable as soon as any global admin exists.
+pub struct AdminBootstrap {
+    token_hash: [u8; 32],
+    used: AtomicBool,
+}
+
+impl AdminBootstrap {
+    pub fn new(token: &str) -> Self {
+        AdminBootstrap {
+            token_hash: Sha256::digest(token.as_bytes()).into(),
+            used: AtomicBool::new(false),
+        }
+    }
+
+    pub fn from_env() -> Option<Self> {
+        let token = dotenvy::var("BOOTSTRAP_ADMIN_TOKEN").ok()?;
+        if token.len() < 32 {
+            log::error!(
+                "BOOTSTRAP_ADMIN_TOKEN has to be at least 32 characters, bootstrap disabled"
+            );
+            return None;
+        }
+        Some(AdminBootstrap::new(&token))
+    }
+
+    /// Uses up the bootstrap if the token matches and no global admin exists yet
+    pub fn consume(&self, token: &str, admin_exists: bool) -> Result<()> {
+        if admin_exists {
+            self.used.store(true, Ordering::SeqCst);
+            bail!("Bootstrap is disabled");
+        }
+        // Hashes of equal length are compared to not leak the token length
+        let token_hash: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        if token_hash != self.token_hash {
+            bail!("Invalid bootstrap token");
+        }
+        if self
+            .used
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            bail!("Bootstrap is disabled");
+        }
+        Ok(())
+    }
+
+    /// Makes the bootstrap usable again if the promotion failed
+    pub fn release(&self) {
+        self.used.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bootstrap_once() {
+        let bootstrap = AdminBootstrap::new("a-one-time-token-for-the-first-admin");
+        assert!(bootstrap.consume("wrong-token", false).is_err());
+        assert!(bootstrap
+            .consume("a-one-time-token-for-the-first-admin", false)
+            .is_ok());
+        assert!(bootstrap
+            .consume("a-one-time-token-for-the-first-admin", false)
+            .is_err());
+    }
+
+    #[test]
+    fn test_bootstrap_disabled_by_admin() {
+        let bootstrap = AdminBootstrap::new("a-one-time-token-for-the-first-admin");
+        assert!(bootstrap
+            .consume("a-one-time-token-for-the-first-admin", true)
+            .is_err());
+        // Also after the admin is gone
+        assert!(bootstrap
+            .consume("a-one-time-token-for-the-first-admin", false)
+            .is_err());
+    }
+}
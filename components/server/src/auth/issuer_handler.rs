@@ -3,7 +3,8 @@ use anyhow::{anyhow, bail, Result};
 use chrono::{NaiveDateTime, Utc};
 use jsonwebtoken::{decode_header, jwk::JwkSet, DecodingKey};
 
-use super::token_handler::ArunaTokenClaims;
+use super::token_handler::{ArunaTokenClaims, TokenError, TOKEN_CONFIG};
+use jsonwebtoken::errors::ErrorKind;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum IssuerType {
@@ -126,7 +127,11 @@ impl Issuer {
         if let Some(aud) = audiences {
             validation.set_audience(aud)
         };
-        let tokendata = jsonwebtoken::decode::<ArunaTokenClaims>(token, decoding_key, &validation)?;
+        let tokendata = jsonwebtoken::decode::<ArunaTokenClaims>(token, decoding_key, &validation)
+            .map_err(|e| match e.kind() {
+                ErrorKind::InvalidAudience => anyhow!(TokenError::InvalidAudience),
+                _ => e.into(),
+            })?;
         Ok(tokendata.claims)
     }
 }
@@ -141,7 +146,7 @@ pub async fn convert_to_pubkeys_issuers(pubkeys: &Vec<(i16, PubKeyEnum)>) -> Res
                 let issuer = Issuer::new_with_keys(
                     key.to_string(),
                     vec![(id.to_string(), dec_key.clone())],
-                    Some(vec!["aruna".to_string()]),
+                    Some(TOKEN_CONFIG.audiences.clone()),
                     IssuerType::DATAPROXY,
                 )
                 .await?;
@@ -154,9 +159,9 @@ pub async fn convert_to_pubkeys_issuers(pubkeys: &Vec<(i16, PubKeyEnum)>) -> Res
     }
     issuers.push(
         Issuer::new_with_keys(
-            "aruna".to_string(),
+            TOKEN_CONFIG.issuer.clone(),
             server_encoding_keys,
-            Some(vec!["aruna".to_string()]),
+            Some(TOKEN_CONFIG.audiences.clone()),
             IssuerType::ARUNA,
         )
         .await?,
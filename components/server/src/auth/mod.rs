@@ -1,3 +1,5 @@
+pub mod authenticator;
+pub mod bootstrap;
 pub mod issuer_handler;
 pub mod permission_handler;
 pub mod structs;
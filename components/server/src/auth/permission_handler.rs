@@ -1,26 +1,38 @@
 use super::{
+    authenticator::{Authenticator, AuthenticatorChain},
     structs::{Context, ContextVariant},
-    token_handler::{Action, ArunaTokenClaims, OIDCError, ProcessedToken, TokenHandler},
+    token_handler::{
+        Action, ArunaTokenClaims, OIDCError, ProcessedToken, TokenError, TokenHandler,
+        TokenIntrospection,
+    },
 };
 use crate::{
     caching::cache::Cache,
-    database::{dsls::user_dsl::OIDCMapping, enums::DbPermissionLevel},
+    database::{
+        dsls::access_policy_dsl::PolicyDecision,
+        dsls::user_dsl::{APIToken, OIDCMapping},
+        enums::{DbPermissionLevel, ObjectMapping},
+    },
+    middlelayer::token_request_types::{AssumeStorageRole, DownscopeToken},
 };
 use anyhow::anyhow;
 use anyhow::Result;
 use base64::{engine::general_purpose, Engine};
 use diesel_ulid::DieselUlid;
 use log::error;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 pub struct PermissionHandler {
     cache: Arc<Cache>,
     pub token_handler: Arc<TokenHandler>,
+    authenticators: AuthenticatorChain,
 }
 
 pub struct PermissionCheck {
     pub user_id: DieselUlid,
     pub token: Option<DieselUlid>,
+    pub is_personal: bool,
     pub is_proxy: bool,
     pub proxy_id: Option<DieselUlid>,
 }
@@ -29,10 +41,24 @@ impl PermissionHandler {
     pub fn new(cache: Arc<Cache>, token_handler: Arc<TokenHandler>) -> Self {
         Self {
             cache: cache.clone(),
+            authenticators: AuthenticatorChain::new(vec![token_handler.clone()]),
             token_handler, //Arc::new(TokenHandler::new(cache, realm_info.to_string())),
         }
     }
 
+    /// Adds an authenticator which is tried after all previously added ones
+    pub fn add_authenticator(&mut self, authenticator: Arc<dyn Authenticator>) {
+        self.authenticators.push(authenticator);
+    }
+
+    /// Resolves the identity of a token with the configured authenticators
+    pub async fn authenticate(&self, token: &str) -> Result<ProcessedToken> {
+        self.authenticators
+            .authenticate(token)
+            .await?
+            .ok_or_else(|| anyhow!("Invalid token"))
+    }
+
     pub async fn check_permissions_verbose(
         &self,
         token: &str,
@@ -54,10 +80,13 @@ impl PermissionHandler {
             user_permissions: ref permissions,
             is_proxy,
             ref proxy_intent,
-        } = match self.token_handler.process_token(token).await {
+        } = match self.authenticate(token).await {
             Ok(results) => results,
             Err(err) => {
                 error!("Error in auth: {:?}", err);
+                if let Some(err) = err.downcast_ref::<TokenError>() {
+                    return Err(tonic::Status::unauthenticated(err.to_string()));
+                }
                 return match err.downcast_ref::<OIDCError>() {
                     Some(_) => Err(tonic::Status::unauthenticated("Not registered")),
                     None => Err(tonic::Status::unauthenticated("Unauthorized")),
@@ -244,6 +273,7 @@ impl PermissionHandler {
                     Ok(PermissionCheck {
                         user_id: main_id,
                         token,
+                        is_personal: personal,
                         is_proxy: true,
                         proxy_id: Some(intent.target),
                     })
@@ -257,18 +287,34 @@ impl PermissionHandler {
             };
         }
 
-        // Check permissions for standard ArunaServer user token
-        if self
+        // Access policies are evaluated in addition to the role-based permissions,
+        // a deny overrides every role of the user
+        let policy_decision = self
             .cache
-            .check_permissions_with_contexts(&ctxs, permissions, personal, &main_id)
+            .evaluate_access_policies(&ctxs, personal, &main_id);
+        if policy_decision == PolicyDecision::Deny {
+            return Err(tonic::Status::permission_denied("Denied by access policy"));
+        }
+
+        // Check permissions for standard ArunaServer user token
+        if policy_decision == PolicyDecision::Allow
+            || self
+                .cache
+                .check_permissions_with_contexts(&ctxs, permissions, personal, &main_id)
         {
             //Ok((main_id, token, false, None))
             Ok(PermissionCheck {
                 user_id: main_id,
                 token,
+                is_personal: personal,
                 is_proxy,
                 proxy_id: None,
             })
+        } else if self.cache.is_warming() {
+            // Permissions of resources which are not loaded yet can not be evaluated
+            Err(tonic::Status::unavailable(
+                "Cache is warming up, permissions can not be checked yet",
+            ))
         } else {
             Err(tonic::Status::unauthenticated("Invalid permissions"))
         }
@@ -284,6 +330,173 @@ impl PermissionHandler {
         Ok(user_id)
     }
 
+    /// Introspects `token` on behalf of the authenticated caller.
+    /// Details are only returned for tokens of the caller itself or if the caller is a global admin,
+    /// all other tokens are reported as inactive.
+    pub async fn introspect_token(
+        &self,
+        caller_token: &str,
+        token: &str,
+    ) -> Result<TokenIntrospection, tonic::Status> {
+        let caller_id = self
+            .check_permissions(caller_token, vec![Context::registered()])
+            .await?;
+
+        let introspection = self.token_handler.introspect_token(token).await;
+        if !introspection.active || introspection.sub == Some(caller_id) {
+            return Ok(introspection);
+        }
+        match self
+            .check_permissions(caller_token, vec![Context::admin()])
+            .await
+        {
+            Ok(_) => Ok(introspection),
+            Err(_) => Ok(TokenIntrospection::inactive()),
+        }
+    }
+
+    /// Authenticates the parent token of `request` and builds its downscoped child.
+    /// The child scope is the intersection of the requested scope and the permissions of the
+    /// parent, requests for resources outside of the parent scope are rejected.
+    pub async fn downscope_token(
+        &self,
+        request: &DownscopeToken,
+        pubkey_serial: i32,
+    ) -> Result<(DieselUlid, APIToken), tonic::Status> {
+        let PermissionCheck {
+            user_id,
+            token,
+            is_proxy,
+            ..
+        } = self
+            .check_permissions_verbose(&request.parent_token, vec![Context::registered()])
+            .await?;
+        let parent_id = match token {
+            Some(token) if !is_proxy => token,
+            _ => {
+                return Err(tonic::Status::invalid_argument(
+                    "Only API tokens can be downscoped",
+                ))
+            }
+        };
+        let user = self
+            .cache
+            .get_user(&user_id)
+            .ok_or_else(|| tonic::Status::not_found("User not found"))?;
+        let parent = user
+            .attributes
+            .0
+            .tokens
+            .get(&parent_id)
+            .map(|token| token.clone())
+            .ok_or_else(|| tonic::Status::not_found("Parent token not found"))?;
+        let (permissions, _) = user
+            .get_permissions(Some(parent_id))
+            .map_err(|_| tonic::Status::unauthenticated("Invalid parent token"))?;
+
+        let scope = request
+            .get_requested_scope()
+            .map_err(|err| tonic::Status::invalid_argument(err.to_string()))?;
+        let scope = match scope {
+            Some((resource, requested)) => {
+                let granted = self.get_granted_permission(&resource, &permissions)?;
+                let level = requested.min(granted);
+                if level <= DbPermissionLevel::NONE {
+                    return Err(tonic::Status::permission_denied(
+                        "Requested scope exceeds parent token",
+                    ));
+                }
+                Some((resource, level))
+            }
+            None => None,
+        };
+
+        let child = request
+            .build_token(pubkey_serial, parent_id, &parent, scope)
+            .map_err(|err| tonic::Status::invalid_argument(err.to_string()))?;
+        Ok((user_id, child))
+    }
+
+    /// Authenticates the caller of `request` and builds the token behind its storage role
+    /// credentials. The caller needs the requested permission level on the scope, roles
+    /// assumed with an API token become children of that token.
+    pub async fn assume_storage_role(
+        &self,
+        token: &str,
+        request: &AssumeStorageRole,
+        pubkey_serial: i32,
+    ) -> Result<(DieselUlid, APIToken), tonic::Status> {
+        let (resource, level) = request
+            .get_scope()
+            .map_err(|err| tonic::Status::invalid_argument(err.to_string()))?;
+        let PermissionCheck {
+            user_id,
+            token,
+            is_proxy,
+            ..
+        } = self
+            .check_permissions_verbose(
+                token,
+                vec![Context::res_ctx(resource.into_inner(), level, true)],
+            )
+            .await?;
+        if is_proxy {
+            return Err(tonic::Status::permission_denied(
+                "Proxies can not assume storage roles",
+            ));
+        }
+        let parent = match token {
+            Some(token_id) => {
+                let user = self
+                    .cache
+                    .get_user(&user_id)
+                    .ok_or_else(|| tonic::Status::not_found("User not found"))?;
+                let parent = user
+                    .attributes
+                    .0
+                    .tokens
+                    .get(&token_id)
+                    .map(|token| token.clone())
+                    .ok_or_else(|| tonic::Status::not_found("Token not found"))?;
+                Some((token_id, parent))
+            }
+            None => None,
+        };
+
+        let role = request
+            .build_token(
+                pubkey_serial,
+                parent.as_ref().map(|(id, parent)| (*id, parent)),
+            )
+            .map_err(|err| tonic::Status::invalid_argument(err.to_string()))?;
+        Ok((user_id, role))
+    }
+
+    /// Highest permission level on `resource` granted directly or through its ancestors
+    fn get_granted_permission(
+        &self,
+        resource: &ObjectMapping<DieselUlid>,
+        permissions: &[(DieselUlid, DbPermissionLevel)],
+    ) -> Result<DbPermissionLevel, tonic::Status> {
+        let resource_id = resource.into_inner();
+        let hierarchies = self
+            .cache
+            .upstream_dfs_iterative(&resource_id)
+            .map_err(|_| tonic::Status::not_found("Resource not found"))?;
+        // Hierarchies start with the resource itself
+        let ancestors = hierarchies
+            .into_iter()
+            .flatten()
+            .map(|mapping| mapping.into_inner())
+            .collect::<HashSet<_>>();
+        permissions
+            .iter()
+            .filter(|(id, _)| ancestors.contains(id))
+            .map(|(_, level)| *level)
+            .max()
+            .ok_or_else(|| tonic::Status::permission_denied("Requested scope exceeds parent token"))
+    }
+
     pub async fn check_unregistered_oidc(&self, token: &str) -> Result<OIDCMapping> {
         let split = token
             .split('.')
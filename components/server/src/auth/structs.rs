@@ -91,6 +91,14 @@ impl User {
     ) -> Result<(Vec<(DieselUlid, DbPermissionLevel)>, bool)> {
         if let Some(token) = token {
             if let Some(token) = self.attributes.0.tokens.get(&token) {
+                // Downscoped tokens are only valid as long as all their ancestors exist
+                let mut parent = token.parent;
+                while let Some(parent_id) = parent {
+                    parent = match self.attributes.0.tokens.get(&parent_id) {
+                        Some(parent_token) => parent_token.parent,
+                        None => bail!("Parent token revoked"),
+                    };
+                }
                 // Check if token is mapped to an object
                 let object_id = if let Some(mapping) = token.object_id {
                     match mapping {
@@ -2,21 +2,24 @@ use super::structs::CachedRule;
 use super::structs::ObjectWrapper;
 use super::structs::ProxyCacheIterator;
 use super::structs::PubKeyEnum;
+use super::sync::{CacheSync, SyncProgress, SyncSegment};
 use crate::auth::issuer_handler::convert_to_pubkeys_issuers;
 use crate::auth::issuer_handler::Issuer;
 use crate::auth::structs::Context;
 use crate::auth::structs::ContextVariant;
 use crate::database::connection::Database;
-use crate::database::crud::CrudDb;
+use crate::database::dsls::access_policy_dsl::{AccessPolicy, PolicyDecision};
 use crate::database::dsls::identity_provider_dsl::IdentityProvider;
 use crate::database::dsls::internal_relation_dsl::InternalRelation;
 use crate::database::dsls::internal_relation_dsl::INTERNAL_RELATION_VARIANT_BELONGS_TO;
-use crate::database::dsls::object_dsl::get_all_objects_with_relations;
+use crate::database::dsls::object_dsl::KeyValue;
+use crate::database::dsls::object_dsl::KeyValueVariant;
 use crate::database::dsls::object_dsl::ObjectWithRelations;
 use crate::database::dsls::pub_key_dsl::PubKey as DbPubkey;
 use crate::database::dsls::rule_dsl::Rule;
 use crate::database::dsls::rule_dsl::RuleBinding;
 use crate::database::dsls::stats_dsl::ObjectStats;
+use crate::database::dsls::user_dsl::APIToken;
 use crate::database::dsls::user_dsl::OIDCMapping;
 use crate::database::dsls::user_dsl::User;
 use crate::database::enums::DbPermissionLevel;
@@ -45,19 +48,23 @@ use async_channel::Sender;
 use chrono::NaiveDateTime;
 use dashmap::mapref::one::Ref;
 use dashmap::DashMap;
+use dashmap::DashSet;
 use diesel_ulid::DieselUlid;
 use evmap::shallow_copy::CopyValue;
 use evmap::ReadHandleFactory;
 use evmap::WriteHandle;
 use itertools::Itertools;
 use std::collections::VecDeque;
-use std::ops::Deref;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::sync::RwLock;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 
 pub struct Cache {
     object_cache: DashMap<DieselUlid, ObjectWithRelations, RandomState>,
+    // Objects deleted while warming before their batch was loaded
+    removed_while_warming: DashSet<DieselUlid, RandomState>,
     stats_reader: ReadHandleFactory<DieselUlid, CopyValue<ObjectStats>>, //RwLock<ReadHandle<DieselUlid, ObjectStats>>,
     stats_writer: Arc<Mutex<WriteHandle<DieselUlid, CopyValue<ObjectStats>>>>,
     user_cache: DashMap<DieselUlid, User, RandomState>,
@@ -65,10 +72,17 @@ pub struct Cache {
     issuer_info: DashMap<String, Issuer>,
     pub issuer_sender: Sender<String>,
     lock: AtomicBool,
+    warming: AtomicBool,
+    sync_progress: RwLock<SyncProgress>,
     object_rules: DashMap<DieselUlid, Arc<CachedRule>>,
     object_rule_bindings: DashMap<DieselUlid, Arc<Vec<RuleBinding>>, RandomState>,
+    effective_permissions: DashMap<DieselUlid, Arc<Vec<EffectivePermission>>, RandomState>,
+    access_policies: DashMap<DieselUlid, Arc<AccessPolicy>, RandomState>,
 }
 
+/// Resource with the effective permission level of a user
+pub type EffectivePermission = (DieselUlid, ObjectMapping<DbPermissionLevel>);
+
 impl Cache {
     pub fn new() -> Arc<Self> {
         let (issuer_sender, issuer_recv) = async_channel::bounded(50);
@@ -76,6 +90,7 @@ impl Cache {
 
         let cache = Arc::new(Self {
             object_cache: DashMap::default(),
+            removed_while_warming: DashSet::default(),
             stats_reader: stats_reader.factory(),
             stats_writer: Arc::new(Mutex::new(stats_writer)),
             user_cache: DashMap::default(),
@@ -83,8 +98,12 @@ impl Cache {
             issuer_info: DashMap::default(),
             issuer_sender,
             lock: AtomicBool::new(false),
+            warming: AtomicBool::new(false),
+            sync_progress: RwLock::new(SyncProgress::default()),
             object_rules: DashMap::default(),
             object_rule_bindings: DashMap::default(),
+            effective_permissions: DashMap::default(),
+            access_policies: DashMap::default(),
         });
 
         let cache_clone = cache.clone();
@@ -98,34 +117,74 @@ impl Cache {
         cache
     }
 
+    /// Fully resyncs the cache, reads are blocked until the sync is finished
     pub async fn sync_cache(&self, db: Arc<Database>) -> Result<()> {
         self.lock.store(true, std::sync::atomic::Ordering::Relaxed);
+        self.start_sync();
+        let result = match CacheSync::from_env() {
+            Ok(mut sync) => sync.run(self, db.as_ref()).await,
+            Err(err) => Err(err),
+        };
+        self.lock.store(false, std::sync::atomic::Ordering::Relaxed);
+        result
+    }
+
+    /// Syncs everything needed for authentication and loads all resources in the background.
+    /// Reads are served while loading, resources which are not yet loaded are reported as warming.
+    pub async fn warm_up(self: &Arc<Self>, db: Arc<Database>) -> Result<JoinHandle<Result<()>>> {
+        self.start_sync();
+        let mut sync = CacheSync::from_env()?;
+        sync.run_until(self, db.as_ref(), Some(SyncSegment::Objects))
+            .await?;
+        let cache = self.clone();
+        Ok(tokio::spawn(
+            async move { sync.run(&cache, db.as_ref()).await },
+        ))
+    }
+
+    /// Clears all synced data and marks the cache as warming
+    pub fn start_sync(&self) {
+        self.warming
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+        self.update_sync_progress(|progress| *progress = SyncProgress::default());
         self.object_cache.clear();
+        self.removed_while_warming.clear();
         self.user_cache.clear();
         self.pubkeys.clear();
-        let client = db.get_client().await?;
+        self.effective_permissions.clear();
+        self.object_rules.clear();
+        self.object_rule_bindings.clear();
+    }
 
-        let all_objects = get_all_objects_with_relations(&client).await?;
-        for obj in all_objects {
-            self.object_cache.insert(obj.object.id, obj);
-        }
+    pub fn finish_sync(&self) {
+        self.update_sync_progress(|progress| progress.finished = true);
+        self.warming
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+    }
 
-        // Object stats update
-        let mut stats_writer = self.stats_writer.lock().await;
-        stats_writer.purge(); // Clear object stats map
-        for stats in ObjectStats::get_all_stats(&client).await? {
-            stats_writer.insert(stats.origin_pid, stats.into());
-        }
-        stats_writer.refresh();
-        drop(stats_writer);
+    /// Returns true while the cache is not fully synced
+    pub fn is_warming(&self) -> bool {
+        self.warming.load(std::sync::atomic::Ordering::Relaxed)
+    }
 
-        let users = User::all(&client).await?;
-        for user in users {
-            self.user_cache.insert(user.id, user);
-        }
+    pub fn sync_progress(&self) -> SyncProgress {
+        self.sync_progress
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub fn update_sync_progress(&self, update: impl FnOnce(&mut SyncProgress)) {
+        update(
+            &mut self
+                .sync_progress
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
 
-        let pubkeys: Vec<(i16, PubKeyEnum)> = DbPubkey::all(&client)
-            .await?
+    pub(super) async fn sync_pubkeys(&self, db_pubkeys: Vec<DbPubkey>) -> Result<()> {
+        let pubkeys: Vec<(i16, PubKeyEnum)> = db_pubkeys
             .into_iter()
             .map(|x| {
                 let id = x.id;
@@ -142,8 +201,13 @@ impl Cache {
         for (id, pubkey) in pubkeys {
             self.pubkeys.insert(id, pubkey);
         }
+        Ok(())
+    }
 
-        let issuers = IdentityProvider::all(&client).await?;
+    pub(super) async fn sync_identity_providers(
+        &self,
+        issuers: Vec<IdentityProvider>,
+    ) -> Result<()> {
         for IdentityProvider {
             issuer_name,
             jwks_endpoint,
@@ -161,21 +225,16 @@ impl Cache {
                 Issuer::new_with_endpoint(issuer_name.clone(), jwks_endpoint, audiences).await?,
             );
         }
+        Ok(())
+    }
 
-        let bindings = RuleBinding::all(&client).await?;
-        for b in bindings {
-            let object_id = b.object_id;
-            if let Some(bindings) = self.object_rule_bindings.get(&object_id).map(|x| x.clone()) {
-                let mut bindings = bindings.deref().clone();
-                bindings.push(b.clone());
-                self.object_rule_bindings
-                    .insert(object_id, Arc::new(bindings));
-            } else {
-                self.object_rule_bindings
-                    .insert(object_id, Arc::new(vec![b.clone()]));
-            }
+    pub(super) fn sync_users(&self, users: Vec<User>) {
+        for user in users {
+            self.user_cache.insert(user.id, user);
         }
-        let rules = Rule::all(&client).await?;
+    }
+
+    pub(super) fn sync_rules(&self, rules: Vec<Rule>) -> Result<()> {
         for r in rules {
             self.object_rules.insert(
                 r.id,
@@ -186,11 +245,44 @@ impl Cache {
                 }),
             );
         }
-
-        self.lock.store(false, std::sync::atomic::Ordering::Relaxed);
         Ok(())
     }
 
+    pub(super) fn sync_rule_bindings(&self, bindings: Vec<RuleBinding>) {
+        let grouped = bindings.into_iter().into_group_map_by(|b| b.object_id);
+        for (object_id, bindings) in grouped {
+            self.object_rule_bindings
+                .insert(object_id, Arc::new(bindings));
+        }
+    }
+
+    pub(super) fn sync_access_policies(&self, policies: Vec<AccessPolicy>) {
+        for policy in policies {
+            self.access_policies
+                .insert(policy.project_id, Arc::new(policy));
+        }
+    }
+
+    /// Loads a batch read from the database, objects that were already written
+    /// or deleted while warming are newer than the batch and are kept
+    pub(super) fn sync_objects(&self, objects: Vec<ObjectWithRelations>) {
+        for obj in objects {
+            if self.removed_while_warming.contains(&obj.object.id) {
+                continue;
+            }
+            self.object_cache.entry(obj.object.id).or_insert(obj);
+        }
+    }
+
+    pub(super) async fn sync_stats(&self, stats: Vec<ObjectStats>) {
+        let mut stats_writer = self.stats_writer.lock().await;
+        stats_writer.purge(); // Clear object stats map
+        for stats in stats {
+            stats_writer.insert(stats.origin_pid, stats.into());
+        }
+        stats_writer.refresh();
+    }
+
     pub fn check_lock(&self) {
         while self.lock.load(std::sync::atomic::Ordering::Relaxed) {
             std::hint::spin_loop()
@@ -264,6 +356,100 @@ impl Cache {
         });
     }
 
+    pub fn get_access_policy(&self, project_id: &DieselUlid) -> Option<Arc<AccessPolicy>> {
+        self.check_lock();
+        self.access_policies.get(project_id).map(|x| x.clone())
+    }
+
+    pub fn upsert_access_policy(&self, policy: AccessPolicy) {
+        self.check_lock();
+        self.access_policies
+            .insert(policy.project_id, Arc::new(policy));
+    }
+
+    pub fn remove_access_policy(&self, project_id: &DieselUlid) {
+        self.check_lock();
+        self.access_policies.remove(project_id);
+    }
+
+    /// Evaluates the access policies of the projects of all requested resources.
+    /// Denied if any resource is denied, allowed only if all contexts are resources
+    /// allowed by a policy. Policies can only allow requests of personal tokens,
+    /// global admins are not affected by policies.
+    pub fn evaluate_access_policies(
+        &self,
+        ctxs: &[Context],
+        personal: bool,
+        user_id: &DieselUlid,
+    ) -> PolicyDecision {
+        self.check_lock();
+        if self.access_policies.is_empty() {
+            return PolicyDecision::NotApplicable;
+        }
+        let user = match self.get_user(user_id) {
+            Some(user) if user.active => user,
+            _ => return PolicyDecision::NotApplicable,
+        };
+        if user.attributes.0.global_admin && personal {
+            return PolicyDecision::NotApplicable;
+        }
+
+        let mut all_allowed = personal && !ctxs.is_empty();
+        for ctx in ctxs {
+            let ContextVariant::Resource((id, requested)) = &ctx.variant else {
+                all_allowed = false;
+                continue;
+            };
+            let Some(resource) = self.get_object(id) else {
+                all_allowed = false;
+                continue;
+            };
+            let policies = match self.upstream_dfs_iterative(id) {
+                Ok(hierarchies) => hierarchies
+                    .into_iter()
+                    .filter_map(|hierarchy| hierarchy.last().map(|project| project.into_inner()))
+                    .unique()
+                    .filter_map(|project_id| self.get_access_policy(&project_id))
+                    .collect_vec(),
+                Err(_) => vec![],
+            };
+            let mut labels = resource
+                .object
+                .key_values
+                .0
+                 .0
+                .iter()
+                .filter(|kv| {
+                    matches!(
+                        kv.variant,
+                        KeyValueVariant::LABEL | KeyValueVariant::STATIC_LABEL
+                    )
+                })
+                .cloned()
+                .collect_vec();
+            labels.extend(self.get_inherited_labels(id));
+
+            let mut allowed = false;
+            for policy in policies {
+                match policy.rules.0.evaluate(
+                    &user.attributes.0.custom_attributes,
+                    &labels,
+                    *requested,
+                ) {
+                    PolicyDecision::Deny => return PolicyDecision::Deny,
+                    PolicyDecision::Allow => allowed = true,
+                    PolicyDecision::NotApplicable => {}
+                }
+            }
+            all_allowed &= allowed;
+        }
+        if all_allowed {
+            PolicyDecision::Allow
+        } else {
+            PolicyDecision::NotApplicable
+        }
+    }
+
     pub fn list_rules(&self) -> Vec<APIRule> {
         self.check_lock();
         self.object_rules
@@ -318,16 +504,18 @@ impl Cache {
 
     pub fn get_object_document(&self, id: &DieselUlid) -> Option<ObjectDocument> {
         if let Some(object) = self.get_object(id) {
-            // Fast return if Object which already has stats
-            if object.object.object_type == ObjectType::OBJECT {
-                return Some(object.object.into());
-            }
-
-            // Try to extend hierarchical objects with stats
+            let is_object = object.object.object_type == ObjectType::OBJECT;
             let mut object_document: ObjectDocument = object.object.into();
-            if let Some(object_stats) = self.get_object_stats(id) {
-                object_document.count = object_stats.count;
-                object_document.size = object_stats.size;
+            object_document
+                .effective_labels
+                .extend(self.get_inherited_labels(id));
+
+            // Objects already have stats, try to extend hierarchical objects with stats
+            if !is_object {
+                if let Some(object_stats) = self.get_object_stats(id) {
+                    object_document.count = object_stats.count;
+                    object_document.size = object_stats.size;
+                }
             }
 
             Some(object_document)
@@ -378,7 +566,9 @@ impl Cache {
 
     pub fn insert_object(&self, object: ObjectWithRelations) {
         self.check_lock();
-        self.object_cache.insert(object.object.id, object);
+        let id = object.object.id;
+        self.object_cache.insert(id, object);
+        self.invalidate_effective_permissions(&[id]);
     }
 
     pub fn get_user(&self, id: &DieselUlid) -> Option<User> {
@@ -438,6 +628,7 @@ impl Cache {
         } else {
             self.object_cache.insert(object.object.id, object);
         }
+        self.invalidate_effective_permissions(&[*id]);
     }
 
     pub async fn upsert_object_stats(&self, object_stats: Vec<ObjectStats>) -> Result<()> {
@@ -485,6 +676,11 @@ impl Cache {
                 *target.value_mut() = clone;
             }
         }
+        let affected = relations
+            .iter()
+            .flat_map(|ir| [ir.origin_pid, ir.target_pid])
+            .collect_vec();
+        self.invalidate_effective_permissions(&affected);
     }
 
     pub fn update_user(&self, id: &DieselUlid, user: User) {
@@ -492,23 +688,30 @@ impl Cache {
         if let Some(mut x) = self.user_cache.get_mut(id) {
             *x.value_mut() = user;
         }
+        self.effective_permissions.remove(id);
     }
 
     pub fn add_object(&self, rel: ObjectWithRelations) {
         self.check_lock();
-        self.object_cache.insert(rel.object.id, rel);
+        let id = rel.object.id;
+        self.object_cache.insert(id, rel);
+        self.invalidate_effective_permissions(&[id]);
     }
 
     pub fn remove_object(&self, id: &DieselUlid) {
         self.check_lock();
         if let Some(mut x) = self.object_cache.get_mut(id) {
             x.value_mut().object.object_status = ObjectStatus::DELETED;
+        } else if self.is_warming() {
+            self.removed_while_warming.insert(*id);
         }
+        self.invalidate_effective_permissions(&[*id]);
     }
 
     pub fn add_user(&self, id: DieselUlid, user: User) {
         self.check_lock();
         self.user_cache.insert(id, user);
+        self.effective_permissions.remove(&id);
     }
 
     pub fn add_pubkey(&self, id: i16, key: PubKeyEnum) {
@@ -529,6 +732,7 @@ impl Cache {
     pub fn remove_user(&self, id: &DieselUlid) {
         self.check_lock();
         self.user_cache.remove(id);
+        self.effective_permissions.remove(id);
     }
 
     pub fn get_user_by_oidc(&self, external: &OIDCMapping) -> Option<User> {
@@ -539,6 +743,44 @@ impl Cache {
             .map(|x| x.value().clone())
     }
 
+    /// Ids of the tokens matching `filter`, grouped by their user
+    pub fn find_user_tokens(
+        &self,
+        filter: impl Fn(&APIToken) -> bool,
+    ) -> Vec<(DieselUlid, Vec<DieselUlid>)> {
+        self.check_lock();
+        self.user_cache
+            .iter()
+            .filter_map(|user| {
+                let tokens = user
+                    .value()
+                    .attributes
+                    .0
+                    .tokens
+                    .iter()
+                    .filter(|token| filter(token.value()))
+                    .map(|token| *token.key())
+                    .collect::<Vec<_>>();
+                (!tokens.is_empty()).then_some((*user.key(), tokens))
+            })
+            .collect()
+    }
+
+    pub fn get_resource_users(&self, resource_id: &DieselUlid) -> Vec<(User, DbPermissionLevel)> {
+        self.check_lock();
+        self.user_cache
+            .iter()
+            .filter_map(|u| {
+                u.value()
+                    .attributes
+                    .0
+                    .permissions
+                    .get(resource_id)
+                    .map(|perm| (u.value().clone(), perm.into_inner()))
+            })
+            .collect()
+    }
+
     pub async fn get_all_users(&self) -> Vec<APIUser> {
         self.check_lock();
         Vec::from_iter(self.user_cache.iter().map(|u| u.clone().into()))
@@ -625,6 +867,94 @@ impl Cache {
         Ok(resource_perms)
     }
 
+    /// Resolves the effective permissions of a user on projects and collections.
+    ///
+    /// Permissions are inherited downwards the same way the authorizer grants
+    /// them (see `check_permissions_with_contexts`) and merged with direct grants
+    /// by choosing the highest level. Direct grants on datasets and objects are
+    /// included as is. The result is sorted by resource id and cached until the
+    /// users permissions or the hierarchy above one of the resources change.
+    pub fn get_effective_permissions(
+        &self,
+        user_id: &DieselUlid,
+    ) -> Result<Vec<EffectivePermission>> {
+        self.check_lock();
+        if let Some(cached) = self.effective_permissions.get(user_id) {
+            return Ok(cached.value().to_vec());
+        }
+        let computed = self.resolve_effective_permissions(user_id)?;
+        self.effective_permissions
+            .insert(*user_id, Arc::new(computed.clone()));
+        Ok(computed)
+    }
+
+    /// Drops the cached effective permissions of all users whose view can change with
+    /// the resources: Users with the resources or one of their ancestors in their view.
+    fn invalidate_effective_permissions(&self, resource_ids: &[DieselUlid]) {
+        if self.effective_permissions.is_empty() {
+            return;
+        }
+        let mut affected = HashSet::default();
+        for id in resource_ids {
+            affected.insert(*id);
+            for path in self.upstream_dfs_iterative(id).unwrap_or_default() {
+                affected.extend(path.iter().map(|ancestor| ancestor.into_inner()));
+            }
+        }
+        self.effective_permissions
+            .retain(|_, view| !view.iter().any(|(id, _)| affected.contains(id)));
+    }
+
+    fn resolve_effective_permissions(
+        &self,
+        user_id: &DieselUlid,
+    ) -> Result<Vec<EffectivePermission>> {
+        let user = self
+            .get_user(user_id)
+            .ok_or_else(|| anyhow!("User not found"))?;
+
+        // Inactive users are not granted any resource permissions
+        if !user.active {
+            return Ok(vec![]);
+        }
+
+        let mut effective: HashMap<DieselUlid, ObjectMapping<DbPermissionLevel>> =
+            HashMap::default();
+        let mut merge = |mapping: ObjectMapping<DbPermissionLevel>, id: DieselUlid| {
+            effective
+                .entry(id)
+                .and_modify(|existing| {
+                    if mapping.into_inner() > existing.into_inner() {
+                        *existing = mapping
+                    }
+                })
+                .or_insert(mapping);
+        };
+
+        for entry in user.attributes.0.permissions.iter() {
+            let (id, mapping) = (*entry.key(), *entry.value());
+            merge(mapping, id);
+
+            // Inherit permission for all projects/collections below the granted resource
+            let level = mapping.into_inner();
+            let mut queue = VecDeque::from([id]);
+            while let Some(current) = queue.pop_front() {
+                if let Some(object) = self.get_object(&current) {
+                    for child in object.get_permission_children() {
+                        if let Some(child_object) = self.get_object(&child) {
+                            if child_object.object.object_type == ObjectType::COLLECTION {
+                                merge(ObjectMapping::COLLECTION(level), child);
+                                queue.push_back(child);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(effective.into_iter().sorted_by_key(|(id, _)| *id).collect())
+    }
+
     pub fn check_proxy_ctxs(&self, endpoint_id: &DieselUlid, ctxs: &[Context]) -> bool {
         self.check_lock();
         ctxs.iter().all(|x| match &x.variant {
@@ -771,6 +1101,68 @@ impl Cache {
         Ok(subresources.into_iter().collect_vec())
     }
 
+    /// Collects the labels of all ancestors of a resource, i.e. the labels an object
+    /// inherits from its datasets, collections and projects. Internal and private
+    /// labels are skipped like for the resource itself.
+    pub fn get_inherited_labels(&self, id: &DieselUlid) -> Vec<KeyValue> {
+        self.check_lock();
+
+        let mut visited: HashSet<DieselUlid> = HashSet::default();
+        let mut inherited = Vec::new();
+        let mut queue = VecDeque::new();
+        if let Some(resource) = self.get_object(id) {
+            queue.extend(resource.get_parents());
+        }
+
+        while let Some(parent_id) = queue.pop_front() {
+            if !visited.insert(parent_id) {
+                continue;
+            }
+            if let Some(parent) = self.get_object(&parent_id) {
+                inherited.extend(
+                    parent
+                        .object
+                        .key_values
+                        .0
+                         .0
+                        .iter()
+                        .filter(|kv| {
+                            matches!(
+                                kv.variant,
+                                KeyValueVariant::LABEL | KeyValueVariant::STATIC_LABEL
+                            )
+                        })
+                        .filter(|kv| !kv.key.starts_with("app.aruna-storage"))
+                        .filter(|kv| !kv.key.starts_with("private"))
+                        .cloned(),
+                );
+                queue.extend(parent.get_parents());
+            }
+        }
+        inherited
+    }
+
+    /// Returns the default data license of the nearest resource in the hierarchy
+    /// (starting with the resource itself) and the id of the resource which defines it
+    pub fn get_default_license(&self, id: &DieselUlid) -> Option<(DieselUlid, String)> {
+        self.check_lock();
+
+        let mut visited: HashSet<DieselUlid> = HashSet::default();
+        let mut queue = VecDeque::from([*id]);
+        while let Some(current_id) = queue.pop_front() {
+            if !visited.insert(current_id) {
+                continue;
+            }
+            if let Some(current) = self.get_object(&current_id) {
+                if let Some(license) = current.object.get_default_license() {
+                    return Some((current_id, license));
+                }
+                queue.extend(current.get_parents());
+            }
+        }
+        None
+    }
+
     ///ToDo: Rust Doc
     pub fn upstream_dfs_iterative(
         &self,
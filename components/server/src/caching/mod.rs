@@ -1,3 +1,4 @@
 pub mod cache;
 pub mod notifications_handler;
 pub mod structs;
+pub mod sync;
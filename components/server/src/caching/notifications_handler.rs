@@ -20,6 +20,7 @@ use time::OffsetDateTime;
 
 use crate::database::dsls::pub_key_dsl::PubKey;
 use crate::database::dsls::rule_dsl::{Rule, RuleBinding};
+use crate::notification::dedup::DeliveryDeduplicator;
 use crate::notification::natsio_handler::{Action, Created, Deleted, ServerEvents, Updated};
 use crate::notification::utils::build_rule;
 use crate::search::meilisearch_client::{MeilisearchClient, ObjectDocument};
@@ -78,12 +79,21 @@ impl NotificationHandler {
         let cache_clone = cache.clone();
         let database_clone = database.clone();
         let sender_arc = Arc::new(refresh_sender);
+        let mut deduplicator = DeliveryDeduplicator::from_env();
         tokio::spawn(async move {
             loop {
                 if let Some(Ok(nats_message)) = messages.next().await {
                     log_received!(&nats_message);
 
-                    if nats_message.subject.starts_with("AOS.SERVER") {
+                    // Events are delivered at least once, redeliveries are only acknowledged
+                    let duplicate = nats_message
+                        .info()
+                        .map(|info| deduplicator.is_duplicate(info.stream_sequence))
+                        .unwrap_or(false);
+
+                    if duplicate {
+                        debug!("NotificationHandler skipped duplicate delivery");
+                    } else if nats_message.subject.starts_with("AOS.SERVER") {
                         let msg_variant = match serde_json::from_slice(
                             nats_message.message.payload.to_vec().as_slice(),
                         ) {
@@ -148,8 +158,9 @@ impl NotificationHandler {
         Ok(NotificationHandler {})
     }
 
-    ///ToDo: Rust Doc
-    async fn update_server_cache(
+    /// Updates cache and search index with the state of the event resource.
+    /// Applying the same event multiple times results in the same state.
+    pub async fn update_server_cache(
         message: MessageVariant,
         cache: Arc<Cache>,
         database: Arc<Database>,
@@ -0,0 +1,390 @@
+use super::cache::Cache;
+use crate::database::connection::Database;
+use crate::database::crud::CrudDb;
+use crate::database::dsls::access_policy_dsl::AccessPolicy;
+use crate::database::dsls::identity_provider_dsl::IdentityProvider;
+use crate::database::dsls::object_dsl::{get_objects_with_relations_page, ObjectWithRelations};
+use crate::database::dsls::pub_key_dsl::PubKey as DbPubkey;
+use crate::database::dsls::rule_dsl::{Rule, RuleBinding};
+use crate::database::dsls::stats_dsl::ObjectStats;
+use crate::database::dsls::user_dsl::User;
+use anyhow::Result;
+use diesel_ulid::DieselUlid;
+use log::{info, warn};
+use std::time::Duration;
+
+pub const DEFAULT_SYNC_BATCH_SIZE: i64 = 10000;
+pub const DEFAULT_SYNC_MAX_RETRIES: u64 = 5;
+pub const DEFAULT_SYNC_RETRY_TIMEOUT: u64 = 1000; // Milliseconds
+
+/// Parts of the cache which are synced one after another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncSegment {
+    PubKeys,
+    IdentityProviders,
+    Users,
+    Rules,
+    RuleBindings,
+    AccessPolicies,
+    Objects,
+    Stats,
+}
+
+impl SyncSegment {
+    /// Segments required for authentication are synced first,
+    /// so that requests can be served while resources are still loading
+    pub const ALL: [SyncSegment; 8] = [
+        SyncSegment::PubKeys,
+        SyncSegment::IdentityProviders,
+        SyncSegment::Users,
+        SyncSegment::Rules,
+        SyncSegment::RuleBindings,
+        SyncSegment::AccessPolicies,
+        SyncSegment::Objects,
+        SyncSegment::Stats,
+    ];
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncProgress {
+    pub completed: Vec<SyncSegment>,
+    pub synced_objects: usize,
+    pub retries: u64,
+    pub finished: bool,
+}
+
+/// Source of all data loaded into the cache
+#[async_trait::async_trait]
+pub trait SyncSource: Send + Sync {
+    async fn pubkeys(&self) -> Result<Vec<DbPubkey>>;
+    async fn identity_providers(&self) -> Result<Vec<IdentityProvider>>;
+    async fn users(&self) -> Result<Vec<User>>;
+    async fn rules(&self) -> Result<Vec<Rule>>;
+    async fn rule_bindings(&self) -> Result<Vec<RuleBinding>>;
+    async fn access_policies(&self) -> Result<Vec<AccessPolicy>>;
+    async fn objects(
+        &self,
+        after: Option<DieselUlid>,
+        limit: i64,
+    ) -> Result<Vec<ObjectWithRelations>>;
+    async fn stats(&self) -> Result<Vec<ObjectStats>>;
+}
+
+#[async_trait::async_trait]
+impl SyncSource for Database {
+    async fn pubkeys(&self) -> Result<Vec<DbPubkey>> {
+        let client = self.get_client().await?;
+        DbPubkey::all_trusted(&client).await
+    }
+    async fn identity_providers(&self) -> Result<Vec<IdentityProvider>> {
+        let client = self.get_client().await?;
+        IdentityProvider::all(&client).await
+    }
+    async fn users(&self) -> Result<Vec<User>> {
+        let client = self.get_client().await?;
+        User::all(&client).await
+    }
+    async fn rules(&self) -> Result<Vec<Rule>> {
+        let client = self.get_client().await?;
+        Rule::all(&client).await
+    }
+    async fn rule_bindings(&self) -> Result<Vec<RuleBinding>> {
+        let client = self.get_client().await?;
+        RuleBinding::all(&client).await
+    }
+    async fn access_policies(&self) -> Result<Vec<AccessPolicy>> {
+        let client = self.get_client().await?;
+        AccessPolicy::all(&client).await
+    }
+    async fn objects(
+        &self,
+        after: Option<DieselUlid>,
+        limit: i64,
+    ) -> Result<Vec<ObjectWithRelations>> {
+        let client = self.get_client().await?;
+        get_objects_with_relations_page(&client, after, limit).await
+    }
+    async fn stats(&self) -> Result<Vec<ObjectStats>> {
+        let client = self.get_client().await?;
+        ObjectStats::get_all_stats(&client).await
+    }
+}
+
+/// Syncs the cache segment by segment and objects in batches.
+/// Failed segments and batches are retried with exponential backoff
+/// without discarding already synced parts.
+pub struct CacheSync {
+    batch_size: i64,
+    max_retries: u64,
+    retry_timeout: u64,
+    next_segment: usize,
+    cursor: Option<DieselUlid>,
+}
+
+impl CacheSync {
+    pub fn new(batch_size: i64, max_retries: u64, retry_timeout: u64) -> Self {
+        CacheSync {
+            batch_size: batch_size.max(1),
+            max_retries,
+            retry_timeout,
+            next_segment: 0,
+            cursor: None,
+        }
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let batch_size = match dotenvy::var("CACHE_SYNC_BATCH_SIZE") {
+            Ok(size) => size.parse()?,
+            Err(_) => DEFAULT_SYNC_BATCH_SIZE,
+        };
+        let max_retries = match dotenvy::var("CACHE_SYNC_MAX_RETRIES") {
+            Ok(retries) => retries.parse()?,
+            Err(_) => DEFAULT_SYNC_MAX_RETRIES,
+        };
+        let retry_timeout = match dotenvy::var("CACHE_SYNC_RETRY_TIMEOUT") {
+            Ok(timeout) => timeout.parse()?,
+            Err(_) => DEFAULT_SYNC_RETRY_TIMEOUT,
+        };
+        Ok(CacheSync::new(batch_size, max_retries, retry_timeout))
+    }
+
+    /// Syncs all remaining segments up to, but not including, `until`
+    pub async fn run_until(
+        &mut self,
+        cache: &Cache,
+        source: &dyn SyncSource,
+        until: Option<SyncSegment>,
+    ) -> Result<()> {
+        while let Some(segment) = SyncSegment::ALL.get(self.next_segment).copied() {
+            if Some(segment) == until {
+                return Ok(());
+            }
+            self.sync_segment(cache, source, segment).await?;
+            self.next_segment += 1;
+            cache.update_sync_progress(|progress| progress.completed.push(segment));
+            info!("Cache sync: {segment:?} completed");
+        }
+        cache.finish_sync();
+        info!("Cache sync finished: {:?}", cache.sync_progress());
+        Ok(())
+    }
+
+    /// Syncs all remaining segments
+    pub async fn run(&mut self, cache: &Cache, source: &dyn SyncSource) -> Result<()> {
+        self.run_until(cache, source, None).await
+    }
+
+    async fn sync_segment(
+        &mut self,
+        cache: &Cache,
+        source: &dyn SyncSource,
+        segment: SyncSegment,
+    ) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            match self.sync_step(cache, source, segment).await {
+                Ok(true) => return Ok(()),
+                // Only the failed batch is retried, all further batches get a fresh retry budget
+                Ok(false) => attempt = 0,
+                Err(err) => {
+                    attempt += 1;
+                    if attempt > self.max_retries {
+                        return Err(err);
+                    }
+                    cache.update_sync_progress(|progress| progress.retries += 1);
+                    let timeout = self.retry_timeout * 2u64.pow(attempt as u32 - 1);
+                    warn!("Cache sync: {segment:?} failed, retrying in {timeout}ms: {err}");
+                    tokio::time::sleep(Duration::from_millis(timeout)).await;
+                }
+            }
+        }
+    }
+
+    /// Returns true if the segment is complete
+    async fn sync_step(
+        &mut self,
+        cache: &Cache,
+        source: &dyn SyncSource,
+        segment: SyncSegment,
+    ) -> Result<bool> {
+        match segment {
+            SyncSegment::PubKeys => cache.sync_pubkeys(source.pubkeys().await?).await?,
+            SyncSegment::IdentityProviders => {
+                cache
+                    .sync_identity_providers(source.identity_providers().await?)
+                    .await?
+            }
+            SyncSegment::Users => cache.sync_users(source.users().await?),
+            SyncSegment::Rules => cache.sync_rules(source.rules().await?)?,
+            SyncSegment::RuleBindings => cache.sync_rule_bindings(source.rule_bindings().await?),
+            SyncSegment::AccessPolicies => {
+                cache.sync_access_policies(source.access_policies().await?)
+            }
+            SyncSegment::Objects => {
+                let batch = source.objects(self.cursor, self.batch_size).await?;
+                let done = (batch.len() as i64) < self.batch_size;
+                if let Some(last) = batch.last() {
+                    self.cursor = Some(last.object.id);
+                }
+                let synced = batch.len();
+                cache.sync_objects(batch);
+                cache.update_sync_progress(|progress| progress.synced_objects += synced);
+                info!(
+                    "Cache sync: {} objects loaded",
+                    cache.sync_progress().synced_objects
+                );
+                return Ok(done);
+            }
+            SyncSegment::Stats => cache.sync_stats(source.stats().await?).await,
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+
+    struct FlakySource {
+        objects: Vec<ObjectWithRelations>,
+        fail_after: Option<DieselUlid>,
+        failed: AtomicBool,
+        requests: Mutex<Vec<Option<DieselUlid>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl SyncSource for FlakySource {
+        async fn pubkeys(&self) -> Result<Vec<DbPubkey>> {
+            Ok(vec![])
+        }
+        async fn identity_providers(&self) -> Result<Vec<IdentityProvider>> {
+            Ok(vec![])
+        }
+        async fn users(&self) -> Result<Vec<User>> {
+            Ok(vec![])
+        }
+        async fn rules(&self) -> Result<Vec<Rule>> {
+            Ok(vec![])
+        }
+        async fn rule_bindings(&self) -> Result<Vec<RuleBinding>> {
+            Ok(vec![])
+        }
+        async fn access_policies(&self) -> Result<Vec<AccessPolicy>> {
+            Ok(vec![])
+        }
+        async fn objects(
+            &self,
+            after: Option<DieselUlid>,
+            limit: i64,
+        ) -> Result<Vec<ObjectWithRelations>> {
+            self.requests.lock().unwrap().push(after);
+            // Fails once in the middle of the object segment
+            if after.is_some()
+                && after == self.fail_after
+                && !self.failed.swap(true, Ordering::SeqCst)
+            {
+                anyhow::bail!("connection reset")
+            }
+            Ok(self
+                .objects
+                .iter()
+                .filter(|o| after.is_none_or(|after| o.object.id > after))
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+        async fn stats(&self) -> Result<Vec<ObjectStats>> {
+            Ok(vec![])
+        }
+    }
+
+    fn source(count: usize, fail_after: Option<usize>) -> FlakySource {
+        let parent = DieselUlid::generate();
+        let mut objects: Vec<_> = (0..count)
+            .map(|_| ObjectWithRelations::random_object_to(&DieselUlid::generate(), &parent))
+            .collect();
+        objects.sort_by_key(|o| o.object.id);
+        FlakySource {
+            fail_after: fail_after.map(|idx| objects[idx].object.id),
+            objects,
+            failed: AtomicBool::new(false),
+            requests: Mutex::new(vec![]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_failure() {
+        let cache = Cache::new();
+        // Second batch fails once
+        let source = source(10, Some(3));
+        cache.start_sync();
+
+        let mut sync = CacheSync::new(4, 2, 1);
+        sync.run_until(&cache, &source, Some(SyncSegment::Objects))
+            .await
+            .unwrap();
+        // Authentication data is available while objects are still loading
+        assert!(cache.is_warming());
+        assert_eq!(cache.sync_progress().completed.len(), 6);
+        assert!(cache.get_object(&source.objects[0].object.id).is_none());
+
+        sync.run(&cache, &source).await.unwrap();
+        assert!(!cache.is_warming());
+        let progress = cache.sync_progress();
+        assert!(progress.finished);
+        assert_eq!(progress.completed, SyncSegment::ALL.to_vec());
+        assert_eq!(progress.synced_objects, 10);
+        assert_eq!(progress.retries, 1);
+        for object in &source.objects {
+            assert!(cache.get_object(&object.object.id).is_some());
+        }
+
+        // Only the failed batch was requested again
+        let cursor = Some(source.objects[3].object.id);
+        assert_eq!(
+            *source.requests.lock().unwrap(),
+            vec![None, cursor, cursor, Some(source.objects[7].object.id)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync_gives_up() {
+        let cache = Cache::new();
+        let source = source(10, Some(3));
+        cache.start_sync();
+
+        // No retries allowed
+        let mut sync = CacheSync::new(4, 0, 1);
+        assert!(sync.run(&cache, &source).await.is_err());
+        assert!(cache.is_warming());
+        assert_eq!(cache.sync_progress().synced_objects, 4);
+    }
+
+    #[tokio::test]
+    async fn test_writes_while_warming() {
+        let cache = Cache::new();
+        let source = source(10, None);
+        cache.start_sync();
+
+        let mut sync = CacheSync::new(4, 0, 1);
+        sync.run_until(&cache, &source, Some(SyncSegment::Objects))
+            .await
+            .unwrap();
+        // Written while the objects are loading
+        let mut updated = source.objects[0].clone();
+        updated.object.name = "updated".to_string();
+        cache.upsert_object(&updated.object.id, updated.clone());
+        let removed = source.objects[9].object.id;
+        cache.remove_object(&removed);
+
+        sync.run(&cache, &source).await.unwrap();
+        assert_eq!(
+            cache.get_object(&updated.object.id).unwrap().object.name,
+            "updated"
+        );
+        assert!(cache.get_object(&removed).is_none());
+        assert!(cache.get_object(&source.objects[5].object.id).is_some());
+    }
+}
@@ -1,7 +1,79 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use deadpool_postgres::{Config, ManagerConfig, Object, Pool, RecyclingMethod, Runtime};
 use tokio_postgres::NoTls;
 
+/// Version of `schema.sql` this server expects. Has to be increased with every schema
+/// change, together with a migration step for the new version.
+pub const SCHEMA_VERSION: i32 = 6;
+
+/// Migration steps keyed by the schema version they migrate to. Databases of an older
+/// version get all steps after their version applied in order, each step in its own
+/// transaction together with the update of the stored version.
+pub const MIGRATIONS: &[(i32, &str)] = &[
+    // Dead letters of external notification sinks
+    (
+        2,
+        "CREATE TABLE IF NOT EXISTS dead_letter_notifications (
+    id UUID PRIMARY KEY NOT NULL,
+    sink TEXT NOT NULL,
+    project_id UUID NOT NULL,
+    payload JSONB NOT NULL,
+    reason TEXT NOT NULL,
+    attempts INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS dead_letter_notifications_project_idx ON dead_letter_notifications (project_id);",
+    ),
+    // Signed object manifests
+    (
+        3,
+        "CREATE TABLE IF NOT EXISTS object_manifests (
+    object_id UUID NOT NULL,
+    revision INT NOT NULL,
+    manifest JSONB NOT NULL,
+    signature TEXT NOT NULL,
+    pubkey_serial SMALLINT NOT NULL,
+    signed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY(object_id, revision)
+);",
+    ),
+    // Retention watermarks of persistent notifications
+    (
+        4,
+        "CREATE TABLE IF NOT EXISTS notification_watermarks (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    pruned_until UUID NOT NULL
+);",
+    ),
+    // Download accounting
+    (
+        5,
+        "CREATE TABLE IF NOT EXISTS download_stats (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    project_id UUID NOT NULL,
+    requester_pays BOOL NOT NULL,
+    count BIGINT NOT NULL DEFAULT 0,
+    size BIGINT NOT NULL DEFAULT 0,
+    last_download TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY(user_id, project_id, requester_pays)
+);",
+    ),
+    // Sequence counters
+    (
+        6,
+        "CREATE TABLE IF NOT EXISTS sequences (
+    namespace VARCHAR(511) PRIMARY KEY NOT NULL,
+    value BIGINT NOT NULL
+);",
+    ),
+];
+
+const SCHEMA_VERSION_TABLE: &str = "CREATE TABLE IF NOT EXISTS schema_version (
+    id BOOL PRIMARY KEY NOT NULL DEFAULT TRUE CHECK (id),
+    version INT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);";
+
 pub struct Database {
     connection_pool: Pool,
 }
@@ -40,8 +112,73 @@ impl Database {
         let client = self.connection_pool.get().await?;
 
         dotenvy::from_filename(".env")?;
+        let migrate = dotenvy::var("DATABASE_MIGRATE")
+            .map(|migrate| migrate.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if let Some(version) = self.check_schema_version(SCHEMA_VERSION, migrate).await? {
+            self.migrate_schema(version, SCHEMA_VERSION).await?;
+        }
+
         let initial = tokio::fs::read_to_string(dotenvy::var("DATABASE_SCHEMA")?).await?;
         client.batch_execute(&initial).await?;
+        client
+            .execute(
+                "INSERT INTO schema_version (version) VALUES ($1)
+                ON CONFLICT (id) DO UPDATE SET version = $1, updated_at = NOW();",
+                &[&SCHEMA_VERSION],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Refuses databases with another schema version than `expected`. Older schemas are
+    /// only accepted with `migrate`, newer schemas are never downgraded. Databases without
+    /// a version are accepted and get the version of the applied schema.
+    ///
+    /// Returns the version of databases which have to be migrated.
+    pub async fn check_schema_version(&self, expected: i32, migrate: bool) -> Result<Option<i32>> {
+        let client = self.connection_pool.get().await?;
+        client.batch_execute(SCHEMA_VERSION_TABLE).await?;
+        let version: Option<i32> = client
+            .query_opt("SELECT version FROM schema_version;", &[])
+            .await?
+            .map(|row| row.get(0));
+        match version {
+            None => Ok(None),
+            Some(version) if version == expected => Ok(None),
+            Some(version) if version > expected => bail!(
+                "Database schema version {version} is newer than version {expected} of this \
+                server, update the server before connecting it to this database"
+            ),
+            Some(version) if migrate => {
+                log::warn!("Migrating database schema from version {version} to {expected}");
+                Ok(Some(version))
+            }
+            Some(version) => bail!(
+                "Database schema version {version} is older than version {expected} of this \
+                server, set DATABASE_MIGRATE=true to migrate the database"
+            ),
+        }
+    }
+
+    /// Applies the migration steps after version `from` up to version `to`
+    pub async fn migrate_schema(&self, from: i32, to: i32) -> Result<()> {
+        let mut client = self.connection_pool.get().await?;
+        for (version, step) in MIGRATIONS
+            .iter()
+            .filter(|(version, _)| *version > from && *version <= to)
+        {
+            log::info!("Applying database schema migration to version {version}");
+            let transaction = client.transaction().await?;
+            transaction.batch_execute(step).await?;
+            transaction
+                .execute(
+                    "UPDATE schema_version SET version = $1, updated_at = NOW();",
+                    &[version],
+                )
+                .await?;
+            transaction.commit().await?;
+        }
         Ok(())
     }
 
@@ -0,0 +1,277 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use crate::database::dsls::object_dsl::KeyValue;
+use crate::database::dsls::user_dsl::CustomAttributes;
+use crate::database::enums::DbPermissionLevel;
+use anyhow::{bail, Result};
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use postgres_types::Json;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+#[allow(non_camel_case_types)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyEffect {
+    ALLOW,
+    DENY,
+}
+
+/// Custom attribute of the requesting user, e.g. `group` = `G`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SubjectMatch {
+    pub attribute_name: String,
+    pub attribute_value: String,
+}
+
+/// Label of the requested resource, any value matches if `value` is not set
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LabelMatch {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+/// Rule of an access policy, applies if the user has all subject attributes
+/// and the resource (or one of its ancestors) carries all labels.
+/// Allow rules grant requests up to `permission`, deny rules reject
+/// requests from `permission` on. Requests which need ADMIN are never denied,
+/// so that project admins can always change the policy.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PolicyRule {
+    pub effect: PolicyEffect,
+    pub subject: Vec<SubjectMatch>,
+    pub labels: Vec<LabelMatch>,
+    pub permission: DbPermissionLevel,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct PolicyRules(pub Vec<PolicyRule>);
+
+/// Result of the rules of all policies which apply to a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyDecision {
+    Allow,
+    Deny,
+    // No rule applies, only role-based permissions decide
+    NotApplicable,
+}
+
+/// Attribute-based access policy of a project, evaluated in addition to the
+/// role-based permissions of users for all resources of the project
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct AccessPolicy {
+    pub project_id: DieselUlid,
+    pub rules: Json<PolicyRules>,
+    pub updated_by: DieselUlid,
+    pub updated_at: NaiveDateTime,
+}
+
+impl PolicyRule {
+    fn matches(&self, subject: &[CustomAttributes], labels: &[KeyValue]) -> bool {
+        self.subject.iter().all(|required| {
+            subject.iter().any(|attribute| {
+                attribute.attribute_name == required.attribute_name
+                    && attribute.attribute_value == required.attribute_value
+            })
+        }) && self.labels.iter().all(|required| {
+            labels.iter().any(|label| {
+                label.key == required.key
+                    && required
+                        .value
+                        .as_ref()
+                        .map_or(true, |value| &label.value == value)
+            })
+        })
+    }
+}
+
+impl PolicyRules {
+    pub fn validate(&self) -> Result<()> {
+        for rule in &self.0 {
+            if rule
+                .subject
+                .iter()
+                .any(|s| s.attribute_name.is_empty() || s.attribute_value.is_empty())
+            {
+                bail!("Subject attributes need a name and a value");
+            }
+            if rule.labels.iter().any(|label| label.key.is_empty()) {
+                bail!("Label matches need a key");
+            }
+            if rule.permission < DbPermissionLevel::READ
+                || rule.permission > DbPermissionLevel::WRITE
+            {
+                bail!("Policy rules can only apply to READ, APPEND or WRITE");
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluates the request of `requested` on a resource with `labels` (including
+    /// inherited labels) by a user with `subject` attributes. Deny overrides allow.
+    pub fn evaluate(
+        &self,
+        subject: &[CustomAttributes],
+        labels: &[KeyValue],
+        requested: DbPermissionLevel,
+    ) -> PolicyDecision {
+        let mut decision = PolicyDecision::NotApplicable;
+        for rule in self.0.iter().filter(|rule| rule.matches(subject, labels)) {
+            match rule.effect {
+                PolicyEffect::DENY
+                    if requested >= rule.permission && requested < DbPermissionLevel::ADMIN =>
+                {
+                    return PolicyDecision::Deny
+                }
+                PolicyEffect::ALLOW if requested <= rule.permission => {
+                    decision = PolicyDecision::Allow
+                }
+                _ => {}
+            }
+        }
+        decision
+    }
+}
+
+#[async_trait::async_trait]
+impl CrudDb for AccessPolicy {
+    // Replaces the previous policy of the project
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO access_policies
+          (project_id, rules, updated_by, updated_at)
+        VALUES
+          ($1, $2, $3, $4)
+        ON CONFLICT (project_id) DO UPDATE SET
+          rules = $2, updated_by = $3, updated_at = $4;";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.project_id,
+                    &self.rules,
+                    &self.updated_by,
+                    &self.updated_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(project_id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM access_policies WHERE project_id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&project_id])
+            .await?
+            .map(|e| AccessPolicy::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM access_policies;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows.iter().map(AccessPolicy::from_row).collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM access_policies WHERE project_id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.project_id]).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::dsls::object_dsl::KeyValueVariant;
+
+    fn label(key: &str, value: &str) -> KeyValue {
+        KeyValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            variant: KeyValueVariant::LABEL,
+            value_type: None,
+        }
+    }
+
+    fn group(value: &str) -> CustomAttributes {
+        CustomAttributes {
+            attribute_name: "group".to_string(),
+            attribute_value: value.to_string(),
+        }
+    }
+
+    fn rule(effect: PolicyEffect, value: &str, permission: DbPermissionLevel) -> PolicyRule {
+        PolicyRule {
+            effect,
+            subject: vec![SubjectMatch {
+                attribute_name: "group".to_string(),
+                attribute_value: "G".to_string(),
+            }],
+            labels: vec![LabelMatch {
+                key: "classification".to_string(),
+                value: Some(value.to_string()),
+            }],
+            permission,
+        }
+    }
+
+    #[test]
+    fn test_policy_evaluation() {
+        let rules = PolicyRules(vec![
+            rule(PolicyEffect::ALLOW, "public", DbPermissionLevel::READ),
+            rule(PolicyEffect::DENY, "secret", DbPermissionLevel::READ),
+        ]);
+        rules.validate().unwrap();
+        let public = [label("classification", "public")];
+        let secret = [label("classification", "secret")];
+
+        // Allowed by label for group members up to the granted level
+        assert_eq!(
+            rules.evaluate(&[group("G")], &public, DbPermissionLevel::READ),
+            PolicyDecision::Allow
+        );
+        assert_eq!(
+            rules.evaluate(&[group("G")], &public, DbPermissionLevel::WRITE),
+            PolicyDecision::NotApplicable
+        );
+        assert_eq!(
+            rules.evaluate(&[group("H")], &public, DbPermissionLevel::READ),
+            PolicyDecision::NotApplicable
+        );
+
+        // Denied by label, also if another rule allows
+        let both = [
+            label("classification", "public"),
+            label("classification", "secret"),
+        ];
+        assert_eq!(
+            rules.evaluate(&[group("G")], &both, DbPermissionLevel::READ),
+            PolicyDecision::Deny
+        );
+        assert_eq!(
+            rules.evaluate(&[group("G")], &secret, DbPermissionLevel::WRITE),
+            PolicyDecision::Deny
+        );
+        // Admin requests are never denied
+        assert_eq!(
+            rules.evaluate(&[group("G")], &secret, DbPermissionLevel::ADMIN),
+            PolicyDecision::NotApplicable
+        );
+
+        assert!(PolicyRules(vec![rule(
+            PolicyEffect::ALLOW,
+            "public",
+            DbPermissionLevel::ADMIN
+        )])
+        .validate()
+        .is_err());
+    }
+}
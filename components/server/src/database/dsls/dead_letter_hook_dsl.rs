@@ -0,0 +1,96 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use postgres_types::Json;
+use tokio_postgres::Client;
+
+/// External hook call which still failed after all retries.
+/// The payload is the triggering resource without the credentials of the call.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetterHook {
+    pub id: DieselUlid,
+    pub hook_id: DieselUlid,
+    pub project_id: DieselUlid,
+    pub object_id: DieselUlid,
+    pub user_id: DieselUlid,
+    pub request_id: Option<String>,
+    pub payload: Json<serde_json::Value>,
+    pub reason: String,
+    pub attempts: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for DeadLetterHook {
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO dead_letter_hooks
+          (id, hook_id, project_id, object_id, user_id, request_id, payload, reason, attempts, created_at)
+        VALUES
+          ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10);";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.id,
+                    &self.hook_id,
+                    &self.project_id,
+                    &self.object_id,
+                    &self.user_id,
+                    &self.request_id,
+                    &self.payload,
+                    &self.reason,
+                    &self.attempts,
+                    &self.created_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM dead_letter_hooks WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&id])
+            .await?
+            .map(|e| DeadLetterHook::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM dead_letter_hooks ORDER BY created_at, id;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(DeadLetterHook::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM dead_letter_hooks WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.id]).await?;
+        Ok(())
+    }
+}
+
+impl DeadLetterHook {
+    pub async fn get_by_hook(hook_id: &DieselUlid, client: &Client) -> Result<Vec<DeadLetterHook>> {
+        let query = "SELECT * FROM dead_letter_hooks WHERE hook_id = $1 ORDER BY created_at, id;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[hook_id]).await?;
+        Ok(rows
+            .iter()
+            .map(DeadLetterHook::from_row)
+            .collect::<Vec<_>>())
+    }
+}
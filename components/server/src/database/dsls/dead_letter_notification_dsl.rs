@@ -0,0 +1,94 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use postgres_types::Json;
+use tokio_postgres::Client;
+
+/// Batch of events which could not be delivered to an external notification sink
+/// after all retries. The payload contains the undelivered events.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetterNotification {
+    pub id: DieselUlid,
+    pub sink: String,
+    pub project_id: DieselUlid,
+    pub payload: Json<serde_json::Value>,
+    pub reason: String,
+    pub attempts: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for DeadLetterNotification {
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO dead_letter_notifications
+          (id, sink, project_id, payload, reason, attempts, created_at)
+        VALUES
+          ($1, $2, $3, $4, $5, $6, $7);";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.id,
+                    &self.sink,
+                    &self.project_id,
+                    &self.payload,
+                    &self.reason,
+                    &self.attempts,
+                    &self.created_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM dead_letter_notifications WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&id])
+            .await?
+            .map(|e| DeadLetterNotification::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM dead_letter_notifications ORDER BY created_at, id;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(DeadLetterNotification::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM dead_letter_notifications WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.id]).await?;
+        Ok(())
+    }
+}
+
+impl DeadLetterNotification {
+    pub async fn get_by_project(
+        project_id: &DieselUlid,
+        client: &Client,
+    ) -> Result<Vec<DeadLetterNotification>> {
+        let query = "SELECT * FROM dead_letter_notifications
+            WHERE project_id = $1 ORDER BY created_at, id;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[project_id]).await?;
+        Ok(rows
+            .iter()
+            .map(DeadLetterNotification::from_row)
+            .collect::<Vec<_>>())
+    }
+}
@@ -0,0 +1,90 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use tokio_postgres::Client;
+
+/// Hook message which was persisted because the hook queue was full
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct DeferredHook {
+    pub id: DieselUlid,
+    pub hook_id: DieselUlid,
+    pub project_id: DieselUlid,
+    pub object_id: DieselUlid,
+    pub user_id: DieselUlid,
+    pub request_id: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for DeferredHook {
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO deferred_hooks
+          (id, hook_id, project_id, object_id, user_id, request_id, created_at)
+        VALUES
+          ($1, $2, $3, $4, $5, $6, $7);";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.id,
+                    &self.hook_id,
+                    &self.project_id,
+                    &self.object_id,
+                    &self.user_id,
+                    &self.request_id,
+                    &self.created_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM deferred_hooks WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&id])
+            .await?
+            .map(|e| DeferredHook::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM deferred_hooks;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows.iter().map(DeferredHook::from_row).collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM deferred_hooks WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.id]).await?;
+        Ok(())
+    }
+}
+
+impl DeferredHook {
+    /// Fetches the oldest deferred hook messages
+    pub async fn get_oldest(limit: i64, client: &Client) -> Result<Vec<DeferredHook>> {
+        let query = "SELECT * FROM deferred_hooks ORDER BY created_at, id LIMIT $1;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[&limit]).await?;
+        Ok(rows.iter().map(DeferredHook::from_row).collect::<Vec<_>>())
+    }
+
+    pub async fn count(client: &Client) -> Result<i64> {
+        let query = "SELECT COUNT(*) FROM deferred_hooks;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client.query_one(&prepared, &[]).await?.get(0))
+    }
+}
@@ -89,6 +89,16 @@ impl Endpoint {
             .await?
             .map(|e| Endpoint::from_row(&e)))
     }
+    pub async fn set_status(
+        id: &DieselUlid,
+        status: EndpointStatus,
+        client: &Client,
+    ) -> Result<()> {
+        let query = "UPDATE endpoints SET status = $2 WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+        client.execute(&prepared, &[id, &status]).await?;
+        Ok(())
+    }
     pub async fn delete_by_id(id: &DieselUlid, client: &Client) -> Result<()> {
         let query = "DELETE FROM endpoints WHERE id = $1;";
         let prepared = client.prepare(query).await?;
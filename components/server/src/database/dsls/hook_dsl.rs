@@ -69,9 +69,23 @@ pub enum TriggerVariant {
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum InternalHook {
-    AddLabel { key: String, value: String },
-    AddHook { key: String, value: String },
-    CreateRelation { relation: Relation },
+    AddLabel {
+        key: String,
+        value: String,
+    },
+    AddHook {
+        key: String,
+        value: String,
+    },
+    CreateRelation {
+        relation: Relation,
+    },
+    /// Built-in malware scan of finished objects, never stored as a user defined hook
+    Scan,
+    /// Built-in preview generation of finished objects, never stored as a user defined hook
+    Preview,
+    /// Built-in content validation of finished objects, never stored as a user defined hook
+    Validate,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -94,6 +108,8 @@ pub struct BasicTemplate {
     pub pubkey_serial: i32,
     pub access_key: Option<String>,
     pub secret_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -40,6 +40,8 @@ pub const INTERNAL_RELATION_VARIANT_VERSION: &str = "VERSION";
 pub const INTERNAL_RELATION_VARIANT_METADATA: &str = "METADATA";
 pub const INTERNAL_RELATION_VARIANT_POLICY: &str = "POLICY";
 pub const INTERNAL_RELATION_VARIANT_DELETED: &str = "DELETED";
+pub const INTERNAL_RELATION_VARIANT_SYMLINK: &str = "SYMLINK";
+pub const INTERNAL_RELATION_VARIANT_PREVIEW: &str = "PREVIEW";
 
 #[async_trait::async_trait]
 impl CrudDb for InternalRelation {
@@ -189,6 +191,75 @@ impl InternalRelation {
         Ok(())
     }
 
+    /// Counts the outbound relations (e.g. children) of a resource
+    pub async fn count_outbound(origin: &DieselUlid, client: &Client) -> Result<i64> {
+        let query = "SELECT COUNT(*) FROM internal_relations
+            WHERE origin_pid = $1;";
+        let prepared = client.prepare(query).await?;
+        let row = client.query_one(&prepared, &[origin]).await?;
+        Ok(row.get(0))
+    }
+
+    /// All current and deleted hierarchy relations below a resource, at most `max_depth`
+    /// levels deep and at most `limit` relations
+    pub async fn get_hierarchy_relations(
+        root: &DieselUlid,
+        max_depth: usize,
+        limit: usize,
+        client: &Client,
+    ) -> Result<Vec<InternalRelation>> {
+        let query = "/*+ indexscan(ir) set(yb_bnl_batch_size 1024) */
+        WITH RECURSIVE paths AS (
+            SELECT ir.*, 1 AS depth
+              FROM internal_relations ir
+              WHERE ir.origin_pid = $1 AND ir.relation_name IN ('BELONGS_TO', 'DELETED')
+            UNION
+            SELECT ir2.*, paths.depth + 1
+              FROM paths, internal_relations ir2
+              WHERE ir2.origin_pid = paths.target_pid AND ir2.relation_name IN ('BELONGS_TO', 'DELETED')
+                AND paths.depth < $2
+        ) SELECT * FROM paths LIMIT $3;";
+
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query(&prepared, &[root, &(max_depth as i32), &(limit as i64)])
+            .await?
+            .iter()
+            .map(InternalRelation::from_row)
+            .collect())
+    }
+
+    /// Symlink of a collection by its (normalized) name
+    pub async fn get_symlink(
+        collection_id: &DieselUlid,
+        name: &str,
+        client: &Client,
+    ) -> Result<Option<InternalRelation>> {
+        let query = "SELECT * FROM internal_relations
+            WHERE origin_pid = $1 AND relation_name = 'SYMLINK' AND target_name = $2;";
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query_opt(&prepared, &[collection_id, &name])
+            .await?
+            .map(|row| InternalRelation::from_row(&row)))
+    }
+
+    /// All symlinks which resolve to one of the objects
+    pub async fn get_symlinks_to(
+        ids: &Vec<DieselUlid>,
+        client: &Client,
+    ) -> Result<Vec<InternalRelation>> {
+        let query = "SELECT * FROM internal_relations
+            WHERE relation_name = 'SYMLINK' AND target_pid = ANY($1::uuid[]);";
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query(&prepared, &[ids])
+            .await?
+            .iter()
+            .map(InternalRelation::from_row)
+            .collect())
+    }
+
     // Gets all outbound relations for pid
     pub async fn get_all_by_id(id: &DieselUlid, client: &Client) -> Result<Vec<InternalRelation>> {
         let query = "SELECT * FROM internal_relations 
@@ -1,6 +1,7 @@
 use crate::database::crud::{CrudDb, PrimaryKey};
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::NaiveDateTime;
 use postgres_from_row::FromRow;
 use serde::{Deserialize, Serialize};
 use tokio_postgres::Client;
@@ -11,6 +12,10 @@ pub struct License {
     pub name: String,
     pub text: String,
     pub url: String,
+    // Data of resources with this license can not be accessed before this date
+    pub embargo_until: Option<NaiveDateTime>,
+    // Data of resources with this license can only be accessed for this many days after creation
+    pub access_days: Option<i32>,
 }
 
 pub const ALL_RIGHTS_RESERVED: &str = "AllRightsReserved";
@@ -18,14 +23,24 @@ pub const ALL_RIGHTS_RESERVED: &str = "AllRightsReserved";
 #[async_trait]
 impl CrudDb for License {
     async fn create(&mut self, client: &Client) -> Result<()> {
-        let query = "INSERT INTO licenses (tag, name, text, url) 
-        VALUES ( $1, $2, $3, $4) 
+        let query = "INSERT INTO licenses (tag, name, text, url, embargo_until, access_days) 
+        VALUES ( $1, $2, $3, $4, $5, $6) 
         RETURNING *;";
 
         let prepared = client.prepare(query).await?;
 
         let row = client
-            .query_one(&prepared, &[&self.tag, &self.name, &self.text, &self.url])
+            .query_one(
+                &prepared,
+                &[
+                    &self.tag,
+                    &self.name,
+                    &self.text,
+                    &self.url,
+                    &self.embargo_until,
+                    &self.access_days,
+                ],
+            )
             .await?;
 
         *self = License::from_row(&row);
@@ -52,3 +67,21 @@ impl CrudDb for License {
         Ok(())
     }
 }
+
+impl License {
+    /// Replaces the access terms of a license, existing resources are affected immediately
+    pub async fn set_terms(
+        tag: &str,
+        embargo_until: Option<NaiveDateTime>,
+        access_days: Option<i32>,
+        client: &Client,
+    ) -> Result<Option<License>> {
+        let query = "UPDATE licenses SET embargo_until = $2, access_days = $3
+        WHERE tag = $1 RETURNING *;";
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query_opt(&prepared, &[&tag, &embargo_until, &access_days])
+            .await?
+            .map(|e| License::from_row(&e)))
+    }
+}
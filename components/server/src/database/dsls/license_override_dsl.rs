@@ -0,0 +1,94 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use tokio_postgres::Client;
+
+/// Grant of an admin which exempts a user from the access terms of the licenses
+/// of a resource and all of its descendants
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct LicenseOverride {
+    pub id: DieselUlid,
+    pub user_id: DieselUlid,
+    pub resource_id: DieselUlid,
+    pub granted_by: DieselUlid,
+    pub created_at: NaiveDateTime,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for LicenseOverride {
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO license_overrides
+          (id, user_id, resource_id, granted_by, created_at)
+        VALUES
+          ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id, resource_id) DO UPDATE SET granted_by = $4
+        RETURNING *;";
+        let prepared = client.prepare(query).await?;
+
+        let row = client
+            .query_one(
+                &prepared,
+                &[
+                    &self.id,
+                    &self.user_id,
+                    &self.resource_id,
+                    &self.granted_by,
+                    &self.created_at,
+                ],
+            )
+            .await?;
+
+        *self = LicenseOverride::from_row(&row);
+        Ok(())
+    }
+
+    async fn get(id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM license_overrides WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&id])
+            .await?
+            .map(|e| LicenseOverride::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM license_overrides;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(LicenseOverride::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM license_overrides WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.id]).await?;
+        Ok(())
+    }
+}
+
+impl LicenseOverride {
+    /// Checks if the user has a grant for any of the resources
+    pub async fn exists(
+        user_id: &DieselUlid,
+        resource_ids: &Vec<DieselUlid>,
+        client: &Client,
+    ) -> Result<bool> {
+        let query = "SELECT EXISTS (
+            SELECT 1 FROM license_overrides WHERE user_id = $1 AND resource_id = ANY($2::uuid[])
+        );";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_one(&prepared, &[user_id, resource_ids])
+            .await?
+            .get(0))
+    }
+}
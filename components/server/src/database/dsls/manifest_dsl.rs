@@ -0,0 +1,159 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use crate::database::dsls::object_dsl::{Algorithm, KeyValueVariant, Object};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use postgres_types::Json;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+/// Key-values with this prefix are managed by the server and not signed
+const SERVER_KEY_PREFIX: &str = "app.aruna-storage.org/";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ManifestLabel {
+    pub key: String,
+    pub value: String,
+}
+
+/// Content and metadata of an object at the time it was signed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ObjectManifest {
+    pub object_id: DieselUlid,
+    // Incremented each time the object is signed again after a change
+    pub revision: i32,
+    pub name: String,
+    pub title: String,
+    pub description: String,
+    pub content_len: i64,
+    pub sha256: Option<String>,
+    pub md5: Option<String>,
+    pub labels: Vec<ManifestLabel>,
+    pub data_license: String,
+    pub metadata_license: String,
+    pub signed_at: i64,
+}
+
+impl ObjectManifest {
+    pub fn new(object: &Object, revision: i32, signed_at: i64) -> Self {
+        let hash = |alg: Algorithm| {
+            object
+                .hashes
+                .0
+                 .0
+                .iter()
+                .find(|hash| hash.alg == alg)
+                .map(|hash| hash.hash.to_ascii_lowercase())
+        };
+        let mut labels = object
+            .key_values
+            .0
+             .0
+            .iter()
+            .filter(|kv| {
+                matches!(
+                    kv.variant,
+                    KeyValueVariant::LABEL | KeyValueVariant::STATIC_LABEL
+                ) && !kv.key.starts_with(SERVER_KEY_PREFIX)
+            })
+            .map(|kv| ManifestLabel {
+                key: kv.key.clone(),
+                value: kv.value.clone(),
+            })
+            .collect::<Vec<_>>();
+        labels.sort();
+        ObjectManifest {
+            object_id: object.id,
+            revision,
+            name: object.name.clone(),
+            title: object.title.clone(),
+            description: object.description.clone(),
+            content_len: object.content_len,
+            sha256: hash(Algorithm::SHA256),
+            md5: hash(Algorithm::MD5),
+            labels,
+            data_license: object.data_license.clone(),
+            metadata_license: object.metadata_license.clone(),
+            signed_at,
+        }
+    }
+
+    /// Whether the signed content and metadata still describe the object
+    pub fn describes(&self, object: &Object) -> bool {
+        *self == ObjectManifest::new(object, self.revision, self.signed_at)
+    }
+}
+
+/// Manifest signed with a server key. The signature is a compact EdDSA JWS with the
+/// manifest as payload and the serial of the server key as `kid`. Manifests are not
+/// referenced by foreign keys and survive the deletion of their objects.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct SignedManifest {
+    pub object_id: DieselUlid,
+    pub revision: i32,
+    pub manifest: Json<ObjectManifest>,
+    pub signature: String,
+    pub pubkey_serial: i16,
+    pub signed_at: NaiveDateTime,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for SignedManifest {
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO object_manifests
+          (object_id, revision, manifest, signature, pubkey_serial, signed_at)
+        VALUES
+          ($1, $2, $3, $4, $5, $6);";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.object_id,
+                    &self.revision,
+                    &self.manifest,
+                    &self.signature,
+                    &self.pubkey_serial,
+                    &self.signed_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    // Latest revision of the manifest of an object
+    async fn get(object_id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM object_manifests WHERE object_id = $1
+        ORDER BY revision DESC LIMIT 1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&object_id])
+            .await?
+            .map(|e| SignedManifest::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM object_manifests;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(SignedManifest::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM object_manifests WHERE object_id = $1 AND revision = $2;";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(&prepared, &[&self.object_id, &self.revision])
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use crate::database::dsls::object_dsl::{KeyValue, KeyValueVariant};
+use anyhow::{bail, Result};
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use postgres_types::Json;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use tokio_postgres::Client;
+
+/// Rules for the label `key` of objects
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MetadataField {
+    pub key: String,
+    pub required: bool,
+    // Anchored regex every value has to match
+    pub pattern: Option<String>,
+    // Allowed values, empty if every value is allowed
+    pub allowed_values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct MetadataFields(pub Vec<MetadataField>);
+
+/// Metadata schema of a project, applied to labels of objects on create, finish and update.
+/// Existing objects are only validated again if they are written.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct MetadataSchema {
+    pub project_id: DieselUlid,
+    pub fields: Json<MetadataFields>,
+    pub updated_by: DieselUlid,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Label of an object which does not match a field of the metadata schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaViolation {
+    Missing { key: String },
+    PatternMismatch { key: String, value: String },
+    NotAllowed { key: String, value: String },
+}
+
+impl Display for SchemaViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaViolation::Missing { key } => write!(f, "{key}: required label is missing"),
+            SchemaViolation::PatternMismatch { key, value } => {
+                write!(f, "{key}: value '{value}' does not match the pattern")
+            }
+            SchemaViolation::NotAllowed { key, value } => {
+                write!(f, "{key}: value '{value}' is not allowed")
+            }
+        }
+    }
+}
+
+/// All violations of the metadata schema of a project by the labels of an object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolations {
+    pub project_id: DieselUlid,
+    pub violations: Vec<SchemaViolation>,
+}
+
+impl Display for SchemaViolations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Labels do not match the metadata schema of project {}: ",
+            self.project_id
+        )?;
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for SchemaViolations {}
+
+impl MetadataField {
+    fn regex(&self) -> Result<Option<Regex>> {
+        self.pattern
+            .as_ref()
+            .map(|pattern| Ok(Regex::new(&format!("^(?:{pattern})$"))?))
+            .transpose()
+    }
+}
+
+impl MetadataFields {
+    /// Checks that keys are unique and patterns are valid regexes
+    pub fn validate(&self) -> Result<()> {
+        let mut keys = HashSet::new();
+        for field in &self.0 {
+            if field.key.trim().is_empty() {
+                bail!("Metadata schema keys must not be empty");
+            }
+            if !keys.insert(field.key.as_str()) {
+                bail!("Duplicate metadata schema key {}", field.key);
+            }
+            if field.regex().is_err() {
+                bail!("Invalid pattern for metadata schema key {}", field.key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns all violations by the (static) labels of an object, other key-values are ignored
+    pub fn violations(&self, key_values: &[KeyValue]) -> Result<Vec<SchemaViolation>> {
+        let labels = key_values
+            .iter()
+            .filter(|kv| {
+                matches!(
+                    kv.variant,
+                    KeyValueVariant::LABEL | KeyValueVariant::STATIC_LABEL
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut violations = Vec::new();
+        for field in &self.0 {
+            let values = labels
+                .iter()
+                .filter(|kv| kv.key == field.key)
+                .map(|kv| kv.value.as_str())
+                .collect::<Vec<_>>();
+            if values.is_empty() && field.required {
+                violations.push(SchemaViolation::Missing {
+                    key: field.key.clone(),
+                });
+            }
+            let regex = field.regex()?;
+            for value in values {
+                if regex.as_ref().is_some_and(|regex| !regex.is_match(value)) {
+                    violations.push(SchemaViolation::PatternMismatch {
+                        key: field.key.clone(),
+                        value: value.to_string(),
+                    });
+                } else if !field.allowed_values.is_empty()
+                    && !field.allowed_values.iter().any(|allowed| allowed == value)
+                {
+                    violations.push(SchemaViolation::NotAllowed {
+                        key: field.key.clone(),
+                        value: value.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(violations)
+    }
+}
+
+#[async_trait::async_trait]
+impl CrudDb for MetadataSchema {
+    // Replaces the current schema of the project
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO metadata_schemas
+          (project_id, fields, updated_by, updated_at)
+        VALUES
+          ($1, $2, $3, $4)
+        ON CONFLICT (project_id) DO UPDATE SET
+          fields = $2, updated_by = $3, updated_at = $4;";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.project_id,
+                    &self.fields,
+                    &self.updated_by,
+                    &self.updated_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(project_id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM metadata_schemas WHERE project_id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&project_id])
+            .await?
+            .map(|e| MetadataSchema::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM metadata_schemas;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(MetadataSchema::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM metadata_schemas WHERE project_id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.project_id]).await?;
+        Ok(())
+    }
+}
+
+impl MetadataSchema {
+    pub async fn get_for_projects(
+        project_ids: &[DieselUlid],
+        client: &Client,
+    ) -> Result<Vec<MetadataSchema>> {
+        let query = "SELECT * FROM metadata_schemas WHERE project_id = ANY($1::uuid[]);";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[&project_ids]).await?;
+        Ok(rows
+            .iter()
+            .map(MetadataSchema::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    /// Validates the labels of an object against the schema
+    pub fn check(&self, key_values: &[KeyValue]) -> Result<()> {
+        let violations = self.fields.0.violations(key_values)?;
+        if !violations.is_empty() {
+            return Err(SchemaViolations {
+                project_id: self.project_id,
+                violations,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(key: &str, value: &str) -> KeyValue {
+        KeyValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            variant: KeyValueVariant::LABEL,
+            value_type: None,
+        }
+    }
+
+    #[test]
+    fn test_metadata_fields() {
+        let fields = MetadataFields(vec![
+            MetadataField {
+                key: "sample_id".to_string(),
+                required: true,
+                pattern: Some("S-[0-9]{4}".to_string()),
+                allowed_values: vec![],
+            },
+            MetadataField {
+                key: "condition".to_string(),
+                required: false,
+                pattern: None,
+                allowed_values: vec!["control".to_string(), "treated".to_string()],
+            },
+        ]);
+        assert!(fields.validate().is_ok());
+        assert!(fields
+            .violations(&[label("sample_id", "S-0001"), label("condition", "treated")])
+            .unwrap()
+            .is_empty());
+
+        // Patterns have to match the whole value
+        assert_eq!(
+            fields
+                .violations(&[label("sample_id", "S-00012"), label("condition", "other")])
+                .unwrap(),
+            vec![
+                SchemaViolation::PatternMismatch {
+                    key: "sample_id".to_string(),
+                    value: "S-00012".to_string()
+                },
+                SchemaViolation::NotAllowed {
+                    key: "condition".to_string(),
+                    value: "other".to_string()
+                },
+            ]
+        );
+
+        // Hook key-values do not satisfy required labels
+        let mut hook = label("sample_id", "S-0001");
+        hook.variant = KeyValueVariant::HOOK;
+        assert_eq!(
+            fields.violations(&[hook]).unwrap(),
+            vec![SchemaViolation::Missing {
+                key: "sample_id".to_string()
+            }]
+        );
+
+        let mut invalid = fields.clone();
+        invalid.0[1].pattern = Some("(".to_string());
+        assert!(invalid.validate().is_err());
+        invalid.0[1] = fields.0[0].clone();
+        assert!(invalid.validate().is_err());
+    }
+}
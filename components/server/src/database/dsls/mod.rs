@@ -1,15 +1,32 @@
+pub mod access_policy_dsl;
+pub mod dead_letter_hook_dsl;
+pub mod dead_letter_notification_dsl;
+pub mod deferred_hook_dsl;
 pub mod endpoint_dsl;
 pub mod external_user_id_dsl;
 pub mod hook_dsl;
 pub mod identity_provider_dsl;
 pub mod internal_relation_dsl;
 pub mod license_dsl;
+pub mod license_override_dsl;
+pub mod manifest_dsl;
+pub mod metadata_schema_dsl;
+pub mod name_reservation_dsl;
 pub mod notification_dsl;
+pub mod notification_watermark_dsl;
 pub mod object_dsl;
 pub mod persistent_notification_dsl;
+pub mod pinned_view_dsl;
+pub mod provenance_dsl;
 pub mod pub_key_dsl;
+pub mod publication_request_dsl;
 pub mod relation_type_dsl;
 pub mod rule_dsl;
+pub mod scan_attestation_dsl;
+pub mod search_index_queue_dsl;
+pub mod search_reindex_dsl;
+pub mod sequence_dsl;
+pub mod staging_dsl;
 pub mod stats_dsl;
 pub mod user_dsl;
 pub mod workspaces_dsl;
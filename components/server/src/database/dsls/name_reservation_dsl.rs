@@ -0,0 +1,98 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use anyhow::{bail, Result};
+use chrono::{NaiveDateTime, Utc};
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use tokio_postgres::Client;
+
+/// Reservation of an object name in a parent. The id is the token which has to be
+/// presented when the object is created, `name` is stored in its normalized form.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct NameReservation {
+    pub id: DieselUlid,
+    pub parent_id: DieselUlid,
+    pub name: String,
+    pub created_by: DieselUlid,
+    pub expires_at: NaiveDateTime,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for NameReservation {
+    // Expired reservations of the same name are replaced, active ones let the creation fail
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO name_reservations
+          (id, parent_id, name, created_by, expires_at)
+        VALUES
+          ($1, $2, $3, $4, $5)
+        ON CONFLICT (parent_id, name) DO UPDATE SET
+          id = $1, created_by = $4, expires_at = $5
+          WHERE name_reservations.expires_at <= $6
+        RETURNING id;";
+        let prepared = client.prepare(query).await?;
+
+        let reserved = client
+            .query_opt(
+                &prepared,
+                &[
+                    &self.id,
+                    &self.parent_id,
+                    &self.name,
+                    &self.created_by,
+                    &self.expires_at,
+                    &Utc::now().naive_utc(),
+                ],
+            )
+            .await?;
+        if reserved.is_none() {
+            bail!("Name {} is already reserved", self.name);
+        }
+        Ok(())
+    }
+
+    async fn get(id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM name_reservations WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&id])
+            .await?
+            .map(|e| NameReservation::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM name_reservations;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(NameReservation::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM name_reservations WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.id]).await?;
+        Ok(())
+    }
+}
+
+impl NameReservation {
+    /// Reservation of a normalized name in a parent which has not expired yet
+    pub async fn get_active(
+        parent_id: &DieselUlid,
+        name: &str,
+        client: &Client,
+    ) -> Result<Option<NameReservation>> {
+        let query = "SELECT * FROM name_reservations
+        WHERE parent_id = $1 AND name = $2 AND expires_at > $3;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[parent_id, &name, &Utc::now().naive_utc()])
+            .await?
+            .map(|e| NameReservation::from_row(&e)))
+    }
+}
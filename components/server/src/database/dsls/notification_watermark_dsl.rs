@@ -0,0 +1,62 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use anyhow::Result;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use tokio_postgres::Client;
+
+/// Latest persistent notification of a user which was removed by the retention pruner.
+/// Notification ids are ULIDs and ordered by their creation time.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct NotificationWatermark {
+    pub user_id: DieselUlid,
+    pub pruned_until: DieselUlid,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for NotificationWatermark {
+    // Watermarks only move forward
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO notification_watermarks (user_id, pruned_until)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+          SET pruned_until = GREATEST(notification_watermarks.pruned_until, EXCLUDED.pruned_until)
+        RETURNING *;";
+        let prepared = client.prepare(query).await?;
+
+        let row = client
+            .query_one(&prepared, &[&self.user_id, &self.pruned_until])
+            .await?;
+        *self = NotificationWatermark::from_row(&row);
+
+        Ok(())
+    }
+
+    async fn get(user_id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM notification_watermarks WHERE user_id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&user_id])
+            .await?
+            .map(|e| NotificationWatermark::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM notification_watermarks;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(NotificationWatermark::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM notification_watermarks WHERE user_id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.user_id]).await?;
+        Ok(())
+    }
+}
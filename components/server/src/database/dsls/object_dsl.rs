@@ -17,9 +17,17 @@ use postgres_from_row::FromRow;
 use postgres_types::{FromSql, Json, ToSql};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
 use std::time::Duration;
 use tokio_postgres::Client;
 
+/// Project key-value with the endpoint new objects of the project are uploaded to
+pub const DEFAULT_ENDPOINT_KEY: &str = "app.aruna-storage.org/default-endpoint";
+/// Project key-value which forces encryption of all objects uploaded to the project
+pub const ENFORCE_ENCRYPTION_KEY: &str = "app.aruna-storage.org/enforce-encryption";
+/// Key-value of projects and collections with the data license new child resources inherit
+pub const DEFAULT_LICENSE_KEY: &str = "app.aruna-storage.org/default-license";
+
 lazy_static! {
     pub static ref MAX_RETRIES: u64 = dotenvy::var("MAX_RETRIES")
         .map(|var| var.parse::<u64>().unwrap_or(10))
@@ -38,11 +46,23 @@ pub enum KeyValueVariant {
     HOOK_STATUS,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum KeyValueType {
+    STRING,
+    INTEGER,
+    FLOAT,
+    BOOL,
+    TIMESTAMP,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd)]
 pub struct KeyValue {
     pub key: String,
     pub value: String,
     pub variant: KeyValueVariant,
+    // Untyped key values are plain strings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<KeyValueType>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd)]
@@ -342,6 +362,16 @@ impl Object {
         Ok(users)
     }
 
+    /// Fetches an object and locks its row until the surrounding transaction ends
+    pub async fn get_for_update(id: &DieselUlid, client: &Client) -> Result<Option<Object>> {
+        let query = "SELECT * FROM objects WHERE id = $1 FOR UPDATE;";
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query_opt(&prepared, &[id])
+            .await?
+            .map(|row| Object::from_row(&row)))
+    }
+
     //ToDo: Docs
     pub async fn finish_object_staging(
         id: &DieselUlid,
@@ -457,6 +487,49 @@ impl Object {
         Ok(subresource_ids)
     }
 
+    /// Ids of the non-deleted objects below a collection, optionally restricted to
+    /// objects whose name starts with `name_prefix`. Follows at most `max_depth`
+    /// relations and returns at most `limit` ids.
+    pub async fn list_collection_objects(
+        collection_id: &DieselUlid,
+        name_prefix: Option<&str>,
+        max_depth: usize,
+        limit: usize,
+        client: &Client,
+    ) -> Result<Vec<DieselUlid>> {
+        let query = "WITH RECURSIVE paths AS (
+            SELECT ir.target_pid, 1 AS depth
+              FROM internal_relations ir
+              WHERE ir.origin_pid = $1 AND ir.relation_name = 'BELONGS_TO'
+            UNION
+            SELECT ir2.target_pid, paths.depth + 1
+              FROM paths, internal_relations ir2
+              WHERE ir2.origin_pid = paths.target_pid AND ir2.relation_name = 'BELONGS_TO'
+                AND paths.depth < $3
+        ) SELECT DISTINCT o.id FROM paths
+          JOIN objects o ON o.id = paths.target_pid
+          WHERE o.object_type = 'OBJECT' AND o.object_status != 'DELETED'
+            AND ($2::VARCHAR IS NULL OR LEFT(o.name, LENGTH($2::VARCHAR)) = $2::VARCHAR)
+          ORDER BY o.id
+          LIMIT $4;";
+
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query(
+                &prepared,
+                &[
+                    collection_id,
+                    &name_prefix,
+                    &(max_depth as i32),
+                    &(limit as i64),
+                ],
+            )
+            .await?
+            .iter()
+            .map(|row| row.get::<usize, DieselUlid>(0))
+            .collect())
+    }
+
     // ToDo: Rust Doc
     pub async fn fetch_parents_by_id(
         resource_id: &DieselUlid,
@@ -654,6 +727,20 @@ impl Object {
         client.query(&prepared, &[id, &title]).await?;
         Ok(())
     }
+    pub async fn update_status(
+        id: &DieselUlid,
+        object_status: ObjectStatus,
+        client: &Client,
+    ) -> Result<()> {
+        let query = "UPDATE objects
+        SET object_status = $2
+        WHERE id = $1 ;";
+
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[id, &object_status]).await?;
+        Ok(())
+    }
     pub async fn update(&self, client: &Client) -> Result<()> {
         let query = "UPDATE objects 
         SET description = $2, key_values = $3, data_class = $4
@@ -835,6 +922,58 @@ impl Object {
         Ok(result)
     }
 
+    /// Returns all available objects with a stored hash equal to `hash`
+    pub async fn get_objects_by_hash(hash: &Hash, client: &Client) -> Result<Vec<Object>> {
+        // Containment queries are served by the GIN index on hashes
+        let query = "SELECT * FROM objects 
+            WHERE hashes @> $1 AND object_type = 'OBJECT' AND object_status = 'AVAILABLE';";
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query(&prepared, &[&Json(Hashes(vec![hash.clone()]))])
+            .await?
+            .iter()
+            .map(Object::from_row)
+            .collect())
+    }
+
+    /// Returns all objects which are not deleted and have a key-value with `key`
+    pub async fn get_objects_with_key(key: &str, client: &Client) -> Result<Vec<Object>> {
+        let query = "SELECT * FROM objects
+            WHERE key_values @> $1 AND object_type = 'OBJECT' AND object_status <> 'DELETED';";
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query(&prepared, &[&Json(serde_json::json!([{ "key": key }]))])
+            .await?
+            .iter()
+            .map(Object::from_row)
+            .collect())
+    }
+
+    /// Fetches up to `limit` objects ordered by id, starting after `after`
+    pub async fn get_page(
+        after: Option<DieselUlid>,
+        limit: i64,
+        client: &Client,
+    ) -> Result<Vec<Object>> {
+        let query = "SELECT * FROM objects
+            WHERE $1::UUID IS NULL OR id > $1::UUID
+            ORDER BY id
+            LIMIT $2;";
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query(&prepared, &[&after, &limit])
+            .await?
+            .iter()
+            .map(Object::from_row)
+            .collect())
+    }
+
+    pub async fn count_all(client: &Client) -> Result<i64> {
+        let query = "SELECT COUNT(*) FROM objects;";
+        let prepared = client.prepare(query).await?;
+        Ok(client.query_one(&prepared, &[]).await?.get::<_, i64>(0))
+    }
+
     //ToDo: Docs
     pub async fn get_objects(ids: &Vec<DieselUlid>, client: &Client) -> Result<Vec<Object>> {
         // Fast return if no ids are provided
@@ -994,6 +1133,23 @@ impl Object {
         Ok(result)
     }
 
+    /// Returns the child with the given name, names of children are unique per parent
+    pub async fn get_child_by_name(
+        parent_id: &DieselUlid,
+        name: &str,
+        client: &Client,
+    ) -> Result<Option<ObjectWithRelations>> {
+        let query = "SELECT target_pid FROM internal_relations
+            WHERE origin_pid = $1 AND relation_name = 'BELONGS_TO' AND target_name = $2;";
+        let prepared = client.prepare(query).await?;
+        match client.query_opt(&prepared, &[parent_id, &name]).await? {
+            Some(row) => Ok(Some(
+                Object::get_object_with_relations(&row.get::<usize, DieselUlid>(0), client).await?,
+            )),
+            None => Ok(None),
+        }
+    }
+
     pub async fn update_endpoints(
         endpoint_id: DieselUlid,
         ep_status: EndpointInfo,
@@ -1008,6 +1164,80 @@ impl Object {
         client.execute(&prepared, &[&endpoint, &object_ids]).await?;
         Ok(())
     }
+
+    /// Returns the default endpoint of a project, if set
+    pub fn get_default_endpoint(&self) -> Option<DieselUlid> {
+        self.key_values
+            .0
+             .0
+            .iter()
+            .find(|kv| kv.key == DEFAULT_ENDPOINT_KEY)
+            .and_then(|kv| DieselUlid::from_str(&kv.value).ok())
+    }
+
+    /// Replaces the default endpoint of a project, unsets it if None
+    pub async fn set_default_endpoint(
+        id: &DieselUlid,
+        endpoint_id: Option<DieselUlid>,
+        client: &Client,
+    ) -> Result<()> {
+        Object::replace_reserved_key_value(
+            id,
+            DEFAULT_ENDPOINT_KEY,
+            endpoint_id.map(|endpoint_id| endpoint_id.to_string()),
+            client,
+        )
+        .await
+    }
+
+    /// Returns the default data license of the children of a project or collection, if set
+    pub fn get_default_license(&self) -> Option<String> {
+        self.key_values
+            .0
+             .0
+            .iter()
+            .find(|kv| kv.key == DEFAULT_LICENSE_KEY)
+            .map(|kv| kv.value.clone())
+    }
+
+    /// Replaces the default data license of a project or collection, unsets it if None
+    pub async fn set_default_license(
+        id: &DieselUlid,
+        license_tag: Option<String>,
+        client: &Client,
+    ) -> Result<()> {
+        Object::replace_reserved_key_value(id, DEFAULT_LICENSE_KEY, license_tag, client).await
+    }
+
+    // Replaces all key-values with `key` by a single label with `value`
+    async fn replace_reserved_key_value(
+        id: &DieselUlid,
+        key: &str,
+        value: Option<String>,
+        client: &Client,
+    ) -> Result<()> {
+        let query = "UPDATE objects
+        SET key_values = COALESCE(
+            (SELECT jsonb_agg(kv) FROM jsonb_array_elements(key_values) kv WHERE kv->>'key' <> $1),
+            '[]'::jsonb
+        ) || $2::jsonb
+        WHERE id = $3;";
+        let key_values = Json(KeyValues(
+            value
+                .map(|value| KeyValue {
+                    key: key.to_string(),
+                    value,
+                    variant: KeyValueVariant::LABEL,
+                    value_type: None,
+                })
+                .into_iter()
+                .collect(),
+        ));
+
+        let prepared = client.prepare(query).await?;
+        client.execute(&prepared, &[&key, &key_values, id]).await?;
+        Ok(())
+    }
 }
 impl Eq for Hashes {}
 impl PartialEq for Hashes {
@@ -1144,6 +1374,29 @@ pub async fn get_all_objects_with_relations(client: &Client) -> Result<Vec<Objec
     Ok(row.iter().map(ObjectWithRelations::from_row).collect())
 }
 
+/// Fetches up to `limit` objects with relations ordered by id, starting after `after`
+pub async fn get_objects_with_relations_page(
+    client: &Client,
+    after: Option<DieselUlid>,
+    limit: i64,
+) -> Result<Vec<ObjectWithRelations>> {
+    let query = "SELECT o.*,
+        COALESCE(JSON_OBJECT_AGG(ir1.id, ir1.*) FILTER (WHERE ir1.target_pid = o.id AND NOT ir1.relation_name = 'BELONGS_TO'), '{}') inbound,
+        COALESCE(JSON_OBJECT_AGG(ir1.origin_pid, ir1.*) FILTER (WHERE ir1.target_pid = o.id AND ir1.relation_name = 'BELONGS_TO'), '{}') inbound_belongs_to,
+        COALESCE(JSON_OBJECT_AGG(ir1.id, ir1.*) FILTER (WHERE ir1.origin_pid = o.id AND NOT ir1.relation_name = 'BELONGS_TO'), '{}') outbound,
+        COALESCE(JSON_OBJECT_AGG(ir1.target_pid, ir1.*) FILTER (WHERE ir1.origin_pid = o.id AND ir1.relation_name = 'BELONGS_TO'), '{}') outbound_belongs_to
+        FROM objects o
+        LEFT OUTER JOIN internal_relations ir1 ON o.id IN (ir1.target_pid, ir1.origin_pid)
+        WHERE $1::UUID IS NULL OR o.id > $1::UUID
+        GROUP BY o.id
+        ORDER BY o.id
+        LIMIT $2;";
+    let prepared = client.prepare(query).await?;
+    let row = client.query(&prepared, &[&after, &limit]).await?;
+
+    Ok(row.iter().map(ObjectWithRelations::from_row).collect())
+}
+
 impl ObjectWithRelations {
     //ToDo: Docs
     pub fn as_object_mapping<T>(&self, mapping: T) -> ObjectMapping<T> {
@@ -1,8 +1,10 @@
 use anyhow::Result;
+use chrono::Utc;
 use diesel_ulid::DieselUlid;
 use postgres_from_row::FromRow;
 use postgres_types::{Json, ToSql};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use tokio_postgres::Client;
 
 use crate::{
@@ -110,6 +112,25 @@ impl PersistentNotification {
             .collect::<Vec<_>>())
     }
 
+    /// Notifications of the user created after the provided notification, oldest first
+    pub async fn get_user_notifications_after(
+        user_id: &DieselUlid,
+        after: Option<&DieselUlid>,
+        client: &Client,
+    ) -> Result<Vec<PersistentNotification>> {
+        let query = "SELECT * FROM persistent_notifications
+          WHERE user_id = $1 AND ($2::UUID IS NULL OR id > $2::UUID)
+          ORDER BY id;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[&user_id, &after]).await?;
+
+        Ok(rows
+            .iter()
+            .map(PersistentNotification::from_row)
+            .collect::<Vec<_>>())
+    }
+
     //ToDo: Rust Doc
     pub async fn acknowledge_user_notifications(
         notification_ids: &Vec<DieselUlid>,
@@ -130,4 +151,52 @@ impl PersistentNotification {
 
         Ok(())
     }
+
+    /// Checks if the user already received a notification of the same variant
+    /// referencing the same resource/user within the provided time window.
+    /// The creation time is derived from the notification ULID.
+    pub async fn exists_within_window(
+        user_id: &DieselUlid,
+        variant: &PersistentNotificationVariant,
+        reference_value: &str,
+        window: Duration,
+        client: &Client,
+    ) -> Result<bool> {
+        let query = "SELECT EXISTS(SELECT 1 FROM persistent_notifications 
+          WHERE user_id = $1 AND notification_variant = $2 AND refs @> $3 AND id >= $4);";
+        let prepared = client.prepare(query).await?;
+
+        // Smallest ULID of the window start, ULIDs sort by their timestamp prefix
+        let window_start = (Utc::now().timestamp_millis() as u64)
+            .saturating_sub(window.as_millis().try_into().unwrap_or(u64::MAX));
+        let lower_bound = DieselUlid::from(((window_start as u128) << 80).to_be_bytes());
+
+        let refs_filter = Json(serde_json::json!([{ "reference_value": reference_value }]));
+        let row = client
+            .query_one(&prepared, &[&user_id, &variant, &refs_filter, &lower_bound])
+            .await?;
+        Ok(row.get(0))
+    }
+
+    /// Creates the notification only if no equal notification (same user, variant
+    /// and first reference) was created within the provided time window.
+    /// Returns true if the notification was created.
+    pub async fn create_deduplicated(&mut self, window: Duration, client: &Client) -> Result<bool> {
+        if let Some(reference) = self.refs.0 .0.first() {
+            if PersistentNotification::exists_within_window(
+                &self.user_id,
+                &self.notification_variant,
+                &reference.reference_value,
+                window,
+                client,
+            )
+            .await?
+            {
+                return Ok(false);
+            }
+        }
+
+        self.create(client).await?;
+        Ok(true)
+    }
 }
@@ -0,0 +1,107 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use tokio_postgres::Client;
+
+/// Named view of a collection which pins the object revisions the collection
+/// contained when the view was created
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct PinnedView {
+    pub id: DieselUlid,
+    pub collection_id: DieselUlid,
+    pub name: String,
+    pub created_by: DieselUlid,
+    pub created_at: NaiveDateTime,
+    pub revision_ids: Vec<DieselUlid>,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for PinnedView {
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO pinned_views
+          (id, collection_id, name, created_by, created_at, revision_ids)
+        VALUES
+          ($1, $2, $3, $4, $5, $6);";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.id,
+                    &self.collection_id,
+                    &self.name,
+                    &self.created_by,
+                    &self.created_at,
+                    &self.revision_ids,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM pinned_views WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&id])
+            .await?
+            .map(|e| PinnedView::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM pinned_views;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows.iter().map(PinnedView::from_row).collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM pinned_views WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.id]).await?;
+        Ok(())
+    }
+}
+
+impl PinnedView {
+    pub async fn get_by_collection(
+        collection_id: &DieselUlid,
+        client: &Client,
+    ) -> Result<Vec<PinnedView>> {
+        let query = "SELECT * FROM pinned_views WHERE collection_id = $1 ORDER BY created_at, id;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[collection_id]).await?;
+        Ok(rows.iter().map(PinnedView::from_row).collect::<Vec<_>>())
+    }
+
+    /// Fetches all views which pin at least one of the revisions
+    pub async fn get_pinning(
+        revision_ids: &Vec<DieselUlid>,
+        client: &Client,
+    ) -> Result<Vec<PinnedView>> {
+        let query = "SELECT * FROM pinned_views WHERE revision_ids && $1::uuid[];";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[revision_ids]).await?;
+        Ok(rows.iter().map(PinnedView::from_row).collect::<Vec<_>>())
+    }
+
+    pub async fn delete_by_collections(
+        collection_ids: &Vec<DieselUlid>,
+        client: &Client,
+    ) -> Result<()> {
+        let query = "DELETE FROM pinned_views WHERE collection_id = ANY($1::uuid[]);";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[collection_ids]).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use tokio_postgres::Client;
+
+/// Derivation of an object from its source objects by a compute task. Objects are
+/// not referenced by foreign keys, the lineage survives the deletion of its objects.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    pub id: DieselUlid,
+    pub derived_id: DieselUlid,
+    pub source_ids: Vec<DieselUlid>,
+    pub task_id: String,
+    pub tool: String,
+    pub tool_version: Option<String>,
+    pub created_by: DieselUlid,
+    pub created_at: NaiveDateTime,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for Provenance {
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO provenance
+          (id, derived_id, source_ids, task_id, tool, tool_version, created_by, created_at)
+        VALUES
+          ($1, $2, $3, $4, $5, $6, $7, $8);";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.id,
+                    &self.derived_id,
+                    &self.source_ids,
+                    &self.task_id,
+                    &self.tool,
+                    &self.tool_version,
+                    &self.created_by,
+                    &self.created_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM provenance WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&id])
+            .await?
+            .map(|e| Provenance::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM provenance;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows.iter().map(Provenance::from_row).collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM provenance WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.id]).await?;
+        Ok(())
+    }
+}
+
+impl Provenance {
+    /// Derivations of the given objects, objects without provenance are skipped
+    pub async fn get_by_derived(ids: &[DieselUlid], client: &Client) -> Result<Vec<Provenance>> {
+        let query = "SELECT * FROM provenance WHERE derived_id = ANY($1::uuid[]);";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[&ids]).await?;
+        Ok(rows.iter().map(Provenance::from_row).collect::<Vec<_>>())
+    }
+}
@@ -53,6 +53,25 @@ impl PubKey {
         Ok(res.unwrap_or(0))
     }
 
+    /// All pubkeys except the ones of self-registered endpoints which are pending approval
+    pub async fn all_trusted(client: &Client) -> Result<Vec<PubKey>> {
+        let query = "SELECT p.* FROM pub_keys p
+            LEFT JOIN endpoints e ON p.proxy = e.id
+            WHERE e.status IS DISTINCT FROM 'PENDING'";
+        let prepared = client.prepare(query).await?;
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows.iter().map(PubKey::from_row).collect::<Vec<_>>())
+    }
+
+    pub async fn get_by_proxy(proxy: &DieselUlid, client: &Client) -> Result<Option<PubKey>> {
+        let query = "SELECT * FROM pub_keys WHERE proxy = $1";
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query_opt(&prepared, &[proxy])
+            .await?
+            .map(|e| PubKey::from_row(&e)))
+    }
+
     /// Queries the pub_keys table for a specific pubkey.
     /// As the pubkeys are unique on the database level it returns an Option which includes the
     /// pubkey if it exists.
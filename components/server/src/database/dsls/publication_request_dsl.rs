@@ -0,0 +1,133 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use crate::database::enums::PublicationStatus;
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use tokio_postgres::Client;
+
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct PublicationRequest {
+    pub id: DieselUlid,
+    pub resource_id: DieselUlid,
+    pub requested_by: DieselUlid,
+    pub requested_at: NaiveDateTime,
+    pub status: PublicationStatus,
+    pub decided_by: Option<DieselUlid>,
+    pub decided_at: Option<NaiveDateTime>,
+    pub reason: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for PublicationRequest {
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO publication_requests
+          (id, resource_id, requested_by, requested_at, status, decided_by, decided_at, reason)
+        VALUES
+          ($1, $2, $3, $4, $5, $6, $7, $8);";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.id,
+                    &self.resource_id,
+                    &self.requested_by,
+                    &self.requested_at,
+                    &self.status,
+                    &self.decided_by,
+                    &self.decided_at,
+                    &self.reason,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get(id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM publication_requests WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&id])
+            .await?
+            .map(|e| PublicationRequest::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM publication_requests;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(PublicationRequest::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM publication_requests WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.id]).await?;
+        Ok(())
+    }
+}
+
+impl PublicationRequest {
+    /// Locks the request until the end of the transaction
+    pub async fn get_for_update(id: &DieselUlid, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM publication_requests WHERE id = $1 FOR UPDATE;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[id])
+            .await?
+            .map(|e| PublicationRequest::from_row(&e)))
+    }
+
+    /// All requests of a resource, the most recent request first
+    pub async fn get_by_resource(
+        resource_id: &DieselUlid,
+        client: &Client,
+    ) -> Result<Vec<PublicationRequest>> {
+        let query = "SELECT * FROM publication_requests
+        WHERE resource_id = $1
+        ORDER BY requested_at DESC;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[resource_id]).await?;
+        Ok(rows
+            .iter()
+            .map(PublicationRequest::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    /// Stores the decision of a pending request
+    pub async fn decide(
+        &mut self,
+        status: PublicationStatus,
+        decided_by: DieselUlid,
+        decided_at: NaiveDateTime,
+        reason: Option<String>,
+        client: &Client,
+    ) -> Result<()> {
+        let query = "UPDATE publication_requests
+        SET status = $2, decided_by = $3, decided_at = $4, reason = $5
+        WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[&self.id, &status, &decided_by, &decided_at, &reason],
+            )
+            .await?;
+        self.status = status;
+        self.decided_by = Some(decided_by);
+        self.decided_at = Some(decided_at);
+        self.reason = reason;
+        Ok(())
+    }
+}
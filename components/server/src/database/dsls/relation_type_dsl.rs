@@ -1,20 +1,61 @@
 use crate::database::crud::{CrudDb, PrimaryKey};
-use anyhow::Result;
+use crate::database::dsls::internal_relation_dsl::{
+    InternalRelation, INTERNAL_RELATION_VARIANT_BELONGS_TO, INTERNAL_RELATION_VARIANT_DELETED,
+    INTERNAL_RELATION_VARIANT_METADATA, INTERNAL_RELATION_VARIANT_ORIGIN,
+    INTERNAL_RELATION_VARIANT_POLICY, INTERNAL_RELATION_VARIANT_PREVIEW,
+    INTERNAL_RELATION_VARIANT_SYMLINK, INTERNAL_RELATION_VARIANT_VERSION,
+};
+use anyhow::{bail, Result};
+use diesel_ulid::DieselUlid;
 use postgres_from_row::FromRow;
 use serde::{Deserialize, Serialize};
 use tokio_postgres::Client;
 
-#[derive(FromRow, Debug, Serialize, Deserialize, Clone)]
+/// Names of the built-in relation types which can not be defined by projects
+pub const BUILT_IN_RELATION_TYPES: [&str; 8] = [
+    INTERNAL_RELATION_VARIANT_BELONGS_TO,
+    INTERNAL_RELATION_VARIANT_ORIGIN,
+    INTERNAL_RELATION_VARIANT_VERSION,
+    INTERNAL_RELATION_VARIANT_METADATA,
+    INTERNAL_RELATION_VARIANT_POLICY,
+    INTERNAL_RELATION_VARIANT_DELETED,
+    INTERNAL_RELATION_VARIANT_SYMLINK,
+    INTERNAL_RELATION_VARIANT_PREVIEW,
+];
+
+/// Type of internal relations. Custom types are defined by a project and can only be
+/// used by relations from resources of the project. Undirected types are symmetric,
+/// a relation and its reverse are the same edge and `max_outbound` limits the
+/// relations of a resource in both directions.
+#[derive(FromRow, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct RelationType {
     pub relation_name: String,
+    pub project_id: Option<DieselUlid>,
+    pub directed: bool,
+    pub max_outbound: Option<i32>,
+    pub max_inbound: Option<i32>,
 }
 
 #[async_trait::async_trait]
 impl CrudDb for RelationType {
     async fn create(&mut self, client: &Client) -> Result<()> {
-        let query = "INSERT INTO relation_types (relation_name) VALUES ($1);";
+        let query = "INSERT INTO relation_types
+          (relation_name, project_id, directed, max_outbound, max_inbound)
+        VALUES
+          ($1, $2, $3, $4, $5);";
         let prepared = client.prepare(query).await?;
-        client.query(&prepared, &[&self.relation_name]).await?;
+        client
+            .query(
+                &prepared,
+                &[
+                    &self.relation_name,
+                    &self.project_id,
+                    &self.directed,
+                    &self.max_outbound,
+                    &self.max_inbound,
+                ],
+            )
+            .await?;
         Ok(())
     }
     async fn get(id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
@@ -48,4 +89,132 @@ impl RelationType {
             .await?
             .map(|e| RelationType::from_row(&e)))
     }
+
+    /// Custom relation types with one of the names
+    pub async fn get_custom(names: &[String], client: &Client) -> Result<Vec<RelationType>> {
+        let query = "SELECT * FROM relation_types
+            WHERE relation_name = ANY($1::VARCHAR[]) AND project_id IS NOT NULL;";
+        let prepared = client.prepare(query).await?;
+        let rows = client.query(&prepared, &[&names]).await?;
+        Ok(rows.iter().map(RelationType::from_row).collect::<Vec<_>>())
+    }
+
+    /// Built-in relation types and the custom types of the project
+    pub async fn get_for_project(
+        project_id: &DieselUlid,
+        client: &Client,
+    ) -> Result<Vec<RelationType>> {
+        let query = "SELECT * FROM relation_types
+            WHERE project_id IS NULL OR project_id = $1
+            ORDER BY relation_name;";
+        let prepared = client.prepare(query).await?;
+        let rows = client.query(&prepared, &[project_id]).await?;
+        Ok(rows.iter().map(RelationType::from_row).collect::<Vec<_>>())
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let name = self.relation_name.trim();
+        if name.is_empty() || name != self.relation_name {
+            bail!("Relation type names must not be empty or padded");
+        }
+        if BUILT_IN_RELATION_TYPES
+            .iter()
+            .any(|built_in| built_in.eq_ignore_ascii_case(name))
+        {
+            bail!("Relation type {name} is built-in");
+        }
+        if self.max_outbound.is_some_and(|max| max < 1)
+            || self.max_inbound.is_some_and(|max| max < 1)
+        {
+            bail!("Relation type limits must be at least 1");
+        }
+        if !self.directed && self.max_inbound.is_some() {
+            bail!("Undirected relation types are only limited by max_outbound");
+        }
+        Ok(())
+    }
+
+    /// Checks the limits of the type after relations were added to `resources`
+    pub async fn check_cardinality(&self, resources: &[DieselUlid], client: &Client) -> Result<()> {
+        let outbound = "SELECT COUNT(*) FROM internal_relations
+            WHERE relation_name = $1 AND origin_pid = $2;";
+        let inbound = "SELECT COUNT(*) FROM internal_relations
+            WHERE relation_name = $1 AND target_pid = $2;";
+        let both = "SELECT COUNT(*) FROM internal_relations
+            WHERE relation_name = $1 AND (origin_pid = $2 OR target_pid = $2);";
+        let checks = if self.directed {
+            vec![(outbound, self.max_outbound), (inbound, self.max_inbound)]
+        } else {
+            vec![(both, self.max_outbound)]
+        };
+        for (query, max) in checks {
+            let Some(max) = max else {
+                continue;
+            };
+            let prepared = client.prepare(query).await?;
+            for resource in resources {
+                let row = client
+                    .query_one(&prepared, &[&self.relation_name, resource])
+                    .await?;
+                let count: i64 = row.get(0);
+                if count > max as i64 {
+                    bail!(
+                        "Resource {resource} exceeds the maximum of {max} {} relations",
+                        self.relation_name
+                    );
+                }
+            }
+        }
+        if !self.directed {
+            // The reverse of an undirected relation is the same edge
+            let query = "SELECT COUNT(*) FROM internal_relations a
+                JOIN internal_relations b
+                  ON a.origin_pid = b.target_pid AND a.target_pid = b.origin_pid
+                WHERE a.relation_name = $1 AND b.relation_name = $1 AND a.id <> b.id
+                  AND a.origin_pid = ANY($2::UUID[]);";
+            let prepared = client.prepare(query).await?;
+            let row = client
+                .query_one(&prepared, &[&self.relation_name, &resources])
+                .await?;
+            if row.get::<_, i64>(0) > 0 {
+                bail!("Undirected {} relation exists already", self.relation_name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes a custom type. Types which are still used by relations are only
+    /// deleted with `cascade`, which also deletes the relations. Returns the
+    /// deleted relations.
+    pub async fn delete_custom(
+        &self,
+        cascade: bool,
+        client: &Client,
+    ) -> Result<Vec<InternalRelation>> {
+        if self.project_id.is_none() {
+            bail!("Built-in relation types can not be deleted");
+        }
+        let query = "SELECT * FROM internal_relations WHERE relation_name = $1;";
+        let prepared = client.prepare(query).await?;
+        let relations = client
+            .query(&prepared, &[&self.relation_name])
+            .await?
+            .iter()
+            .map(InternalRelation::from_row)
+            .collect::<Vec<_>>();
+        if !relations.is_empty() {
+            if !cascade {
+                bail!(
+                    "Relation type {} is used by {} relations",
+                    self.relation_name,
+                    relations.len()
+                );
+            }
+            let query = "DELETE FROM internal_relations WHERE relation_name = $1;";
+            let prepared = client.prepare(query).await?;
+            client.execute(&prepared, &[&self.relation_name]).await?;
+        }
+        self.delete(client).await?;
+        Ok(relations)
+    }
 }
@@ -0,0 +1,87 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use crate::hooks::scan_hook::ScanVerdict;
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use postgres_from_row::FromRow;
+use postgres_types::Json;
+use tokio_postgres::Client;
+
+/// Verdict of the last scan of content with the sha256 `hash`
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct ScanAttestation {
+    pub hash: String,
+    pub verdict: Json<ScanVerdict>,
+    pub scanner_version: Option<String>,
+    pub scanned_at: NaiveDateTime,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for ScanAttestation {
+    // Replaces the attestation of an earlier scan of the same content
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO scan_attestations
+          (hash, verdict, scanner_version, scanned_at)
+        VALUES
+          ($1, $2, $3, $4)
+        ON CONFLICT (hash) DO UPDATE SET
+          verdict = $2, scanner_version = $3, scanned_at = $4;";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.hash,
+                    &self.verdict,
+                    &self.scanner_version,
+                    &self.scanned_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(hash: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM scan_attestations WHERE hash = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&hash])
+            .await?
+            .map(|e| ScanAttestation::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM scan_attestations ORDER BY scanned_at;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(ScanAttestation::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM scan_attestations WHERE hash = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.hash]).await?;
+        Ok(())
+    }
+}
+
+impl ScanAttestation {
+    /// Signature version reported with the most recent scan
+    pub async fn latest_version(client: &Client) -> Result<Option<String>> {
+        let query = "SELECT scanner_version FROM scan_attestations
+        ORDER BY scanned_at DESC LIMIT 1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[])
+            .await?
+            .and_then(|row| row.get::<_, Option<String>>(0)))
+    }
+}
@@ -0,0 +1,107 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+use postgres_from_row::FromRow;
+use tokio_postgres::Client;
+
+/// Search index update of a resource which could not be delivered while Meilisearch was unavailable
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct QueuedIndexUpdate {
+    pub id: DieselUlid,
+    pub deletion: bool, // Removes the resource from the index instead of updating it
+    pub queued_at: NaiveDateTime,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for QueuedIndexUpdate {
+    // Replaces a queued update of the same resource
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO search_index_queue
+          (id, deletion, queued_at)
+        VALUES
+          ($1, $2, $3)
+        ON CONFLICT (id) DO UPDATE SET
+          deletion = $2, queued_at = $3;";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(&prepared, &[&self.id, &self.deletion, &self.queued_at])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM search_index_queue WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&id])
+            .await?
+            .map(|e| QueuedIndexUpdate::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM search_index_queue;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(QueuedIndexUpdate::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM search_index_queue WHERE id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.id]).await?;
+        Ok(())
+    }
+}
+
+impl QueuedIndexUpdate {
+    /// Queues updates or deletions of the resources, replacing queued updates of the same resources
+    pub async fn queue(ids: &[DieselUlid], deletion: bool, client: &Client) -> Result<()> {
+        // A resource can only be inserted once per statement
+        let ids = ids.iter().unique().cloned().collect_vec();
+        let query = "INSERT INTO search_index_queue (id, deletion, queued_at)
+          SELECT id, $2, NOW() FROM UNNEST($1::uuid[]) AS id
+        ON CONFLICT (id) DO UPDATE SET
+          deletion = $2, queued_at = NOW();";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&ids, &deletion]).await?;
+        Ok(())
+    }
+
+    /// Fetches the oldest queued updates
+    pub async fn get_oldest(limit: i64, client: &Client) -> Result<Vec<QueuedIndexUpdate>> {
+        let query = "SELECT * FROM search_index_queue ORDER BY queued_at, id LIMIT $1;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[&limit]).await?;
+        Ok(rows
+            .iter()
+            .map(QueuedIndexUpdate::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    /// Removes delivered updates, updates queued again in the meantime are kept
+    pub async fn dequeue(delivered: &[QueuedIndexUpdate], client: &Client) -> Result<()> {
+        let (ids, queued_at): (Vec<_>, Vec<_>) = delivered
+            .iter()
+            .map(|update| (update.id, update.queued_at))
+            .unzip();
+        let query = "DELETE FROM search_index_queue q
+          USING UNNEST($1::uuid[], $2::timestamp[]) AS d(id, queued_at)
+        WHERE q.id = d.id AND q.queued_at = d.queued_at;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&ids, &queued_at]).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use tokio_postgres::Client;
+
+/// Checkpoint of an interrupted or running full reindex of the search index `index_name`
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct SearchReindexCheckpoint {
+    pub index_name: String,
+    pub last_id: Option<DieselUlid>, // Resources are indexed ordered by id
+    pub processed: i64, // Resources processed so far, including those which are not searchable
+    pub started_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for SearchReindexCheckpoint {
+    // Replaces the previous checkpoint of the same index
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO search_reindex_checkpoints
+          (index_name, last_id, processed, started_at, updated_at)
+        VALUES
+          ($1, $2, $3, $4, $5)
+        ON CONFLICT (index_name) DO UPDATE SET
+          last_id = $2, processed = $3, started_at = $4, updated_at = $5;";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.index_name,
+                    &self.last_id,
+                    &self.processed,
+                    &self.started_at,
+                    &self.updated_at,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(index_name: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM search_reindex_checkpoints WHERE index_name = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&index_name])
+            .await?
+            .map(|e| SearchReindexCheckpoint::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM search_reindex_checkpoints;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(SearchReindexCheckpoint::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM search_reindex_checkpoints WHERE index_name = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.index_name]).await?;
+        Ok(())
+    }
+}
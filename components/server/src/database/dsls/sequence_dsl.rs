@@ -0,0 +1,40 @@
+use anyhow::{bail, Result};
+use postgres_from_row::FromRow;
+use tokio_postgres::Client;
+
+/// Counter of a sequence namespace with the last allocated value. Allocations lock the
+/// row of the namespace, concurrent allocations of all server instances never overlap.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct Sequence {
+    pub namespace: String,
+    pub value: i64,
+}
+
+impl Sequence {
+    /// Allocates the next `count` values of the namespace and returns the last of them.
+    /// Sequences start at 1 and are created by their first allocation.
+    pub async fn allocate(namespace: &str, count: i64, client: &Client) -> Result<i64> {
+        if count < 1 {
+            bail!("At least one value has to be allocated");
+        }
+        let query = "INSERT INTO sequences (namespace, value)
+        VALUES ($1, $2)
+        ON CONFLICT (namespace) DO UPDATE
+          SET value = sequences.value + EXCLUDED.value
+        RETURNING *;";
+        let prepared = client.prepare(query).await?;
+
+        let row = client.query_one(&prepared, &[&namespace, &count]).await?;
+        Ok(Sequence::from_row(&row).value)
+    }
+
+    pub async fn get(namespace: &str, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM sequences WHERE namespace = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&namespace])
+            .await?
+            .map(|e| Sequence::from_row(&e)))
+    }
+}
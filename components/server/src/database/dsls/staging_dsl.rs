@@ -0,0 +1,140 @@
+use crate::database::crud::{CrudDb, PrimaryKey};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use postgres_from_row::FromRow;
+use postgres_types::Json;
+use tokio_postgres::Client;
+
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct StagingDeadline {
+    pub object_id: DieselUlid,
+    pub user_id: DieselUlid,
+    pub deadline: NaiveDateTime,
+    pub upload_ids: Json<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl CrudDb for StagingDeadline {
+    /// Creates the staging deadline or resets an existing one
+    async fn create(&mut self, client: &Client) -> Result<()> {
+        let query = "INSERT INTO staging_deadlines
+          (object_id, user_id, deadline, upload_ids)
+        VALUES
+          ($1, $2, $3, $4)
+        ON CONFLICT (object_id) DO UPDATE
+        SET user_id = $2, deadline = $3, upload_ids = $4;";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(
+                &prepared,
+                &[
+                    &self.object_id,
+                    &self.user_id,
+                    &self.deadline,
+                    &self.upload_ids,
+                ],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(id: impl PrimaryKey, client: &Client) -> Result<Option<Self>> {
+        let query = "SELECT * FROM staging_deadlines WHERE object_id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        Ok(client
+            .query_opt(&prepared, &[&id])
+            .await?
+            .map(|e| StagingDeadline::from_row(&e)))
+    }
+
+    async fn all(client: &Client) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM staging_deadlines;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[]).await?;
+        Ok(rows
+            .iter()
+            .map(StagingDeadline::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    async fn delete(&self, client: &Client) -> Result<()> {
+        let query = "DELETE FROM staging_deadlines WHERE object_id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[&self.object_id]).await?;
+        Ok(())
+    }
+}
+
+impl StagingDeadline {
+    /// Moves the deadline of a staging object and optionally registers a new multipart upload id
+    pub async fn renew(
+        object_id: &DieselUlid,
+        deadline: NaiveDateTime,
+        upload_id: Option<String>,
+        client: &Client,
+    ) -> Result<()> {
+        match upload_id {
+            Some(upload_id) => {
+                let query = "UPDATE staging_deadlines
+                SET deadline = $2, upload_ids = upload_ids || to_jsonb($3::TEXT)
+                WHERE object_id = $1;";
+                let prepared = client.prepare(query).await?;
+                client
+                    .execute(&prepared, &[object_id, &deadline, &upload_id])
+                    .await?;
+            }
+            None => {
+                let query = "UPDATE staging_deadlines
+                SET deadline = $2
+                WHERE object_id = $1;";
+                let prepared = client.prepare(query).await?;
+                client.execute(&prepared, &[object_id, &deadline]).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetches all deadlines which expired before `now` and whose objects are still staging
+    pub async fn get_expired(now: NaiveDateTime, client: &Client) -> Result<Vec<StagingDeadline>> {
+        let query = "SELECT s.* FROM staging_deadlines s
+        JOIN objects o ON s.object_id = o.id
+        WHERE s.deadline < $1 AND o.object_status = 'INITIALIZING';";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[&now]).await?;
+        Ok(rows
+            .iter()
+            .map(StagingDeadline::from_row)
+            .collect::<Vec<_>>())
+    }
+
+    /// Removes the deadline of a staging object, e.g. when the upload was finished
+    pub async fn remove(object_id: &DieselUlid, client: &Client) -> Result<()> {
+        let query = "DELETE FROM staging_deadlines WHERE object_id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        client.execute(&prepared, &[object_id]).await?;
+        Ok(())
+    }
+
+    /// Counts the staging objects of a user which are still uploading
+    pub async fn count_active(user_id: &DieselUlid, client: &Client) -> Result<i64> {
+        let query = "SELECT COUNT(*) FROM staging_deadlines s
+        JOIN objects o ON s.object_id = o.id
+        WHERE s.user_id = $1 AND o.object_status = 'INITIALIZING';";
+        let prepared = client.prepare(query).await?;
+
+        let row = client.query_one(&prepared, &[user_id]).await?;
+        Ok(row.get(0))
+    }
+
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        self.deadline < now
+    }
+}
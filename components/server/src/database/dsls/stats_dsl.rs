@@ -14,7 +14,12 @@ use crate::{
     database::connection::Database,
     notification::natsio_handler::{NatsIoHandler, ServerEvents},
     search::meilisearch_client::{MeilisearchClient, ObjectDocument},
-    utils::search_utils,
+    utils::{
+        search_utils,
+        user_notification_utils::{
+            notify_expiring_tokens, notify_quota_thresholds, USER_NOTIFICATION_CONFIG,
+        },
+    },
 };
 
 #[derive(Copy, Clone, Debug, FromRow, Eq)]
@@ -84,6 +89,55 @@ impl ObjectStats {
     }
 }
 
+/// Accounted downloads of a user from a project. Downloads of requester pays resources by
+/// users outside the project are billed to the user, all others to the project.
+#[derive(Clone, Debug, FromRow, PartialEq, Eq)]
+pub struct DownloadStats {
+    pub user_id: DieselUlid,
+    pub project_id: DieselUlid,
+    pub requester_pays: bool,
+    pub count: i64,
+    pub size: i64,
+    pub last_download: NaiveDateTime,
+}
+
+impl DownloadStats {
+    /// Adds a download of `size` bytes to the stats of the user and project
+    pub async fn add_download(
+        user_id: &DieselUlid,
+        project_id: &DieselUlid,
+        requester_pays: bool,
+        size: i64,
+        client: &Client,
+    ) -> Result<()> {
+        let query = "INSERT INTO download_stats
+          (user_id, project_id, requester_pays, count, size, last_download)
+        VALUES
+          ($1, $2, $3, 1, $4, NOW())
+        ON CONFLICT (user_id, project_id, requester_pays) DO UPDATE
+        SET count = download_stats.count + 1,
+            size = download_stats.size + EXCLUDED.size,
+            last_download = EXCLUDED.last_download;";
+        let prepared = client.prepare(query).await?;
+
+        client
+            .execute(&prepared, &[user_id, project_id, &requester_pays, &size])
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_user_download_stats(
+        user_id: &DieselUlid,
+        client: &Client,
+    ) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM download_stats WHERE user_id = $1;";
+        let prepared = client.prepare(query).await?;
+
+        let rows = client.query(&prepared, &[user_id]).await?;
+        Ok(rows.iter().map(DownloadStats::from_row).collect_vec())
+    }
+}
+
 pub async fn refresh_stats_view(client: &Client) -> Result<()> {
     let query = "REFRESH MATERIALIZED VIEW object_stats;";
     let prepared = client.prepare(query).await?;
@@ -200,6 +254,28 @@ pub async fn start_refresh_loop(
 
                     // Update changed stats in cache only if stats are available and anything has changed
                     if !diff.is_empty() {
+                        // Collect previous sizes for quota threshold notifications
+                        let quota_stats = diff
+                            .iter()
+                            .map(|os| {
+                                let previous_size = cache
+                                    .get_object_stats(&os.origin_pid)
+                                    .map(|prev| prev.size)
+                                    .unwrap_or_default();
+                                (*os, previous_size)
+                            })
+                            .collect_vec();
+                        if let Err(err) = notify_quota_thresholds(
+                            &client,
+                            &USER_NOTIFICATION_CONFIG,
+                            &cache,
+                            &quota_stats,
+                        )
+                        .await
+                        {
+                            error!("Quota threshold notification failed: {}", err)
+                        }
+
                         if let Err(err) = cache.upsert_object_stats(diff.clone()).await {
                             error!("Object stats cache update failed: {}", err)
                         } else {
@@ -238,6 +314,13 @@ pub async fn start_refresh_loop(
                 }
             }
 
+            // Notify users about their expiring tokens
+            if let Err(err) =
+                notify_expiring_tokens(&client, &USER_NOTIFICATION_CONFIG, &cache).await
+            {
+                error!("Token expiry notification failed: {}", err)
+            }
+
             // Sleep for refresh interval
             tokio::time::sleep(Duration::from_millis(
                 refresh_interval.try_into().unwrap_or(30000),
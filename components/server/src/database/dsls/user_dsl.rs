@@ -34,6 +34,12 @@ pub struct APIToken {
     pub expires_at: NaiveDateTime,
     pub object_id: Option<ObjectMapping<DieselUlid>>,
     pub user_rights: DbPermissionLevel,
+    // Token this token was downscoped from, revoking the parent revokes this token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<DieselUlid>,
+    // Credentials of the token require a session token, e.g. storage roles
+    #[serde(default)]
+    pub session_scoped: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, FromRow, Debug, Eq, PartialEq, PartialOrd)]
@@ -183,6 +189,28 @@ impl User {
         Ok(User::from_row(&row))
     }
 
+    pub async fn global_admin_exists(client: &Client) -> Result<bool> {
+        let query = "SELECT EXISTS (
+            SELECT 1 FROM users WHERE (attributes->>'global_admin')::bool
+        );";
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query_one(&prepared, &[])
+            .await?
+            .get::<usize, bool>(0))
+    }
+
+    pub async fn get_global_admin_ids(client: &Client) -> Result<Vec<DieselUlid>> {
+        let query = "SELECT id FROM users WHERE (attributes->>'global_admin')::bool;";
+        let prepared = client.prepare(query).await?;
+        Ok(client
+            .query(&prepared, &[])
+            .await?
+            .iter()
+            .map(|row| row.get::<usize, DieselUlid>(0))
+            .collect())
+    }
+
     //ToDo: Rust Doc
     pub async fn set_user_global_admin(
         client: &Client,
@@ -330,6 +358,45 @@ impl User {
         Ok(User::from_row(&row))
     }
 
+    pub async fn remove_user_tokens(
+        client: &Client,
+        user_id: &DieselUlid,
+        token_ids: &[DieselUlid],
+    ) -> Result<User> {
+        let query = "UPDATE users 
+            SET attributes = jsonb_set(attributes, '{tokens}', (attributes->'tokens') - $1::TEXT[]) 
+            WHERE id = $2
+            RETURNING *;";
+
+        let token_ids = token_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>();
+        let prepared = client.prepare(query).await?;
+        let row = client.query_one(&prepared, &[&token_ids, user_id]).await?;
+
+        Ok(User::from_row(&row))
+    }
+
+    /// Returns the token and all tokens downscoped from it, directly or transitively
+    pub fn get_token_with_children(&self, token_id: &DieselUlid) -> Vec<DieselUlid> {
+        let mut token_ids = vec![*token_id];
+        let mut idx = 0;
+        while idx < token_ids.len() {
+            let current = token_ids[idx];
+            token_ids.extend(
+                self.attributes
+                    .0
+                    .tokens
+                    .iter()
+                    .filter(|token| token.value().parent == Some(current))
+                    .map(|token| *token.key()),
+            );
+            idx += 1;
+        }
+        token_ids
+    }
+
     pub async fn remove_all_tokens(client: &Client, user_id: &DieselUlid) -> Result<User> {
         let query = "UPDATE users 
             SET attributes = jsonb_set(attributes, '{tokens}', '{}') 
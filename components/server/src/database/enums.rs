@@ -161,6 +161,7 @@ pub enum EndpointStatus {
     DEGRADED,
     UNAVAILABLE,
     MAINTENANCE,
+    PENDING, // Self-registered and not yet approved
 }
 
 impl TryFrom<i32> for DbPermissionLevel {
@@ -202,6 +203,22 @@ pub enum PersistentNotificationVariant {
     PERMISSION_GRANTED,
     PERMISSION_UPDATED,
     ANNOUNCEMENT,
+    TOKEN_EXPIRING,
+    QUOTA_THRESHOLD_REACHED,
+    PROJECT_DELETED,
+    OBJECT_QUARANTINED,
+    RELATION_LIMIT_APPROACHING,
+    PUBLICATION_REQUESTED,
+    PUBLICATION_DECIDED,
+    OBJECT_EXPIRED,
+    QUOTA_EXCEEDED,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, ToSql, FromSql)]
+pub enum PublicationStatus {
+    PENDING,
+    APPROVED,
+    REJECTED,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, ToSql, FromSql)]
@@ -69,6 +69,8 @@ BEGIN
 END
 $$;
 
+ALTER TYPE "EndpointStatus" ADD VALUE IF NOT EXISTS 'PENDING';
+
 DO $$
     BEGIN
         IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'EndpointVariant') THEN
@@ -111,6 +113,24 @@ DO $$
     END
 $$;
 
+ALTER TYPE "PersistentNotificationVariant" ADD VALUE IF NOT EXISTS 'TOKEN_EXPIRING';
+ALTER TYPE "PersistentNotificationVariant" ADD VALUE IF NOT EXISTS 'QUOTA_THRESHOLD_REACHED';
+ALTER TYPE "PersistentNotificationVariant" ADD VALUE IF NOT EXISTS 'PROJECT_DELETED';
+ALTER TYPE "PersistentNotificationVariant" ADD VALUE IF NOT EXISTS 'OBJECT_QUARANTINED';
+ALTER TYPE "PersistentNotificationVariant" ADD VALUE IF NOT EXISTS 'RELATION_LIMIT_APPROACHING';
+ALTER TYPE "PersistentNotificationVariant" ADD VALUE IF NOT EXISTS 'PUBLICATION_REQUESTED';
+ALTER TYPE "PersistentNotificationVariant" ADD VALUE IF NOT EXISTS 'PUBLICATION_DECIDED';
+ALTER TYPE "PersistentNotificationVariant" ADD VALUE IF NOT EXISTS 'OBJECT_EXPIRED';
+ALTER TYPE "PersistentNotificationVariant" ADD VALUE IF NOT EXISTS 'QUOTA_EXCEEDED';
+
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'PublicationStatus') THEN
+        CREATE TYPE "PublicationStatus" AS ENUM ('PENDING', 'APPROVED', 'REJECTED');
+    END IF;
+END
+$$;
+
 /* ----- Authorization --------------------------------------------- */
 -- Table with users imported from some aai
 -- Join table to map users to multiple identity providers
@@ -139,6 +159,18 @@ CREATE TABLE IF NOT EXISTS licenses (
     text TEXT NOT NULL,                    -- Full license text
     url VARCHAR(2047) NOT NULL             -- URL to full license text
 );
+ALTER TABLE licenses ADD COLUMN IF NOT EXISTS embargo_until TIMESTAMP; -- Data is not accessible before this date
+ALTER TABLE licenses ADD COLUMN IF NOT EXISTS access_days INT;         -- Data is accessible for this many days after creation
+
+-- Table for grants which exempt users from the access terms of licenses
+CREATE TABLE IF NOT EXISTS license_overrides (
+    id UUID PRIMARY KEY NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    resource_id UUID NOT NULL,  -- Grant applies to the resource and all of its descendants
+    granted_by UUID NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE(user_id, resource_id)
+);
 
 /* ----- Object Service -------------------------------------------- */
 -- Table with objects which represent individual data blobs
@@ -166,6 +198,7 @@ CREATE TABLE IF NOT EXISTS objects (
     UNIQUE(id, object_type)
 );
 CREATE INDEX IF NOT EXISTS objects_pk_idx ON objects (id);
+CREATE INDEX IF NOT EXISTS objects_hashes_idx ON objects USING GIN (hashes);
 
 -- Table with endpoints
 CREATE TABLE IF NOT EXISTS endpoints (
@@ -186,6 +219,10 @@ CREATE TABLE IF NOT EXISTS relation_types (
     --id SMALLSERIAL PRIMARY KEY NOT NULL,
     relation_name VARCHAR(511) PRIMARY KEY NOT NULL 
 );
+ALTER TABLE relation_types ADD COLUMN IF NOT EXISTS project_id UUID; -- Project of custom relation types, NULL for built-in types
+ALTER TABLE relation_types ADD COLUMN IF NOT EXISTS directed BOOL NOT NULL DEFAULT TRUE;
+ALTER TABLE relation_types ADD COLUMN IF NOT EXISTS max_outbound INT; -- Maximum relations of the type per origin
+ALTER TABLE relation_types ADD COLUMN IF NOT EXISTS max_inbound INT;  -- Maximum relations of the type per target
 
 -- Table to store all internal relations between objects
 CREATE TABLE IF NOT EXISTS internal_relations (
@@ -233,6 +270,17 @@ GROUP BY origin_pid;
 -- Create unique index for concurrent refreshs
 CREATE UNIQUE INDEX IF NOT EXISTS object_stats_id_idx ON object_stats (origin_pid);
 
+-- Table for accounted downloads, requester pays downloads are billed to the user
+CREATE TABLE IF NOT EXISTS download_stats (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    project_id UUID NOT NULL,
+    requester_pays BOOL NOT NULL,
+    count BIGINT NOT NULL DEFAULT 0,
+    size BIGINT NOT NULL DEFAULT 0,
+    last_download TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY(user_id, project_id, requester_pays)
+);
+
 
 /* ----- Notification Service -------------------------------------- */
 -- Table for the notification service to persist consumer
@@ -251,6 +299,36 @@ CREATE TABLE IF NOT EXISTS persistent_notifications (
     refs JSONB NOT NULL
 );
 
+-- Table for the latest persistent notification of each user removed by the retention pruner
+CREATE TABLE IF NOT EXISTS notification_watermarks (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    pruned_until UUID NOT NULL
+);
+
+/* ----- Object staging ----------------------------- */
+-- Table for upload deadlines of staging objects
+CREATE TABLE IF NOT EXISTS staging_deadlines (
+    object_id UUID PRIMARY KEY REFERENCES objects(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    deadline TIMESTAMP NOT NULL,
+    upload_ids JSONB NOT NULL DEFAULT '[]'
+);
+
+/* ----- Publication requests ----------------------- */
+-- Table for requests to make resources public and their decisions
+CREATE TABLE IF NOT EXISTS publication_requests (
+    id UUID PRIMARY KEY NOT NULL,
+    resource_id UUID NOT NULL REFERENCES objects(id) ON DELETE CASCADE,
+    requested_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    requested_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    status "PublicationStatus" NOT NULL DEFAULT 'PENDING',
+    decided_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    decided_at TIMESTAMP,
+    reason VARCHAR(1023)
+);
+-- Only one pending request per resource
+CREATE UNIQUE INDEX IF NOT EXISTS pending_publication_idx ON publication_requests (resource_id) WHERE status = 'PENDING';
+
 /* ----- Hooks -------------------------------------- */
 -- Table for persisting hooks 
 CREATE TABLE IF NOT EXISTS hooks (
@@ -268,6 +346,142 @@ CREATE TABLE IF NOT EXISTS hooks (
     hook JSONB NOT NULL
 );
 
+-- Table for hook messages which did not fit into the full hook queue
+CREATE TABLE IF NOT EXISTS deferred_hooks (
+    id UUID PRIMARY KEY NOT NULL,
+    hook_id UUID NOT NULL,
+    project_id UUID NOT NULL,
+    object_id UUID NOT NULL REFERENCES objects(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL,
+    request_id VARCHAR(511),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Table for external hook calls which failed after all retries
+CREATE TABLE IF NOT EXISTS dead_letter_hooks (
+    id UUID PRIMARY KEY NOT NULL,
+    hook_id UUID NOT NULL,
+    project_id UUID NOT NULL,
+    object_id UUID NOT NULL REFERENCES objects(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL,
+    request_id VARCHAR(511),
+    payload JSONB NOT NULL,
+    reason TEXT NOT NULL,
+    attempts INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS dead_letter_hooks_hook_idx ON dead_letter_hooks (hook_id);
+
+-- Table for event batches which could not be delivered to external notification sinks
+CREATE TABLE IF NOT EXISTS dead_letter_notifications (
+    id UUID PRIMARY KEY NOT NULL,
+    sink TEXT NOT NULL,
+    project_id UUID NOT NULL,
+    payload JSONB NOT NULL,
+    reason TEXT NOT NULL,
+    attempts INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS dead_letter_notifications_project_idx ON dead_letter_notifications (project_id);
+
+-- Table with the verdicts of malware scans by content hash
+CREATE TABLE IF NOT EXISTS scan_attestations (
+    hash VARCHAR(64) PRIMARY KEY NOT NULL, -- Hex encoded sha256 of the content
+    verdict JSONB NOT NULL,
+    scanner_version VARCHAR(511),
+    scanned_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+/* ----- Metadata schemas ------------------------------- */
+-- Required labels and allowed label values of new and updated objects of a project
+CREATE TABLE IF NOT EXISTS metadata_schemas (
+    project_id UUID PRIMARY KEY NOT NULL REFERENCES objects(id) ON DELETE CASCADE,
+    fields JSONB NOT NULL,
+    updated_by UUID NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+/* ----- Access policies -------------------------------- */
+-- Attribute-based rules of a project, evaluated in addition to the role-based permissions
+CREATE TABLE IF NOT EXISTS access_policies (
+    project_id UUID PRIMARY KEY NOT NULL REFERENCES objects(id) ON DELETE CASCADE,
+    rules JSONB NOT NULL,
+    updated_by UUID NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+/* ----- Name reservations ------------------------------- */
+-- Object names reserved in a parent until an upload creates the object
+CREATE TABLE IF NOT EXISTS name_reservations (
+    id UUID PRIMARY KEY NOT NULL, -- Token of the reservation
+    parent_id UUID NOT NULL REFERENCES objects(id) ON DELETE CASCADE,
+    name VARCHAR(511) NOT NULL, -- Normalized name
+    created_by UUID NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    UNIQUE(parent_id, name)
+);
+
+/* ----- Pinned views ------------------------------------ */
+-- Named views of collections with fixed object revisions
+CREATE TABLE IF NOT EXISTS pinned_views (
+    id UUID PRIMARY KEY NOT NULL,
+    collection_id UUID NOT NULL REFERENCES objects(id) ON DELETE CASCADE,
+    name VARCHAR(511) NOT NULL,
+    created_by UUID NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    revision_ids UUID[] NOT NULL,
+    UNIQUE(collection_id, name)
+);
+
+/* ----- Provenance -------------------------------------- */
+-- Derivations of objects by compute tasks, objects are referenced by plain ids
+-- to preserve the lineage after their deletion
+CREATE TABLE IF NOT EXISTS provenance (
+    id UUID PRIMARY KEY NOT NULL,
+    derived_id UUID NOT NULL UNIQUE,
+    source_ids UUID[] NOT NULL,
+    task_id VARCHAR(511) NOT NULL,
+    tool VARCHAR(511) NOT NULL,
+    tool_version VARCHAR(511),
+    created_by UUID NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+/* ----- Signed manifests -------------------------------- */
+-- Signed content and metadata of objects, each change of a signed object adds a revision
+CREATE TABLE IF NOT EXISTS object_manifests (
+    object_id UUID NOT NULL,
+    revision INT NOT NULL,
+    manifest JSONB NOT NULL,
+    signature TEXT NOT NULL, -- Compact EdDSA JWS of the manifest
+    pubkey_serial SMALLINT NOT NULL,
+    signed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY(object_id, revision)
+);
+
+/* ----- Search index ------------------------------------ */
+-- Checkpoints of full search reindexes, removed once the rebuilt index is live
+CREATE TABLE IF NOT EXISTS search_reindex_checkpoints (
+    index_name VARCHAR(511) PRIMARY KEY NOT NULL,
+    last_id UUID,
+    processed BIGINT NOT NULL DEFAULT 0,
+    started_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+-- Index updates which could not be delivered while the search index was unavailable
+CREATE TABLE IF NOT EXISTS search_index_queue (
+    id UUID PRIMARY KEY NOT NULL,
+    deletion BOOL NOT NULL DEFAULT FALSE,
+    queued_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+/* ----- Sequences --------------------------------------- */
+-- Table for monotonic counters, each namespace stores its last allocated value
+CREATE TABLE IF NOT EXISTS sequences (
+    namespace VARCHAR(511) PRIMARY KEY NOT NULL,
+    value BIGINT NOT NULL
+);
+
 /* ----- Workspaces -------------------------------------- */
 -- Table for workspace templates
 CREATE TABLE IF NOT EXISTS workspaces (
@@ -300,6 +514,8 @@ CREATE TABLE IF NOT EXISTS rule_bindings (
 );
 
 -- Insert predefined relation types
-INSERT INTO relation_types (relation_name) VALUES ('BELONGS_TO'), ('VERSION'), ('METADATA'), ('ORIGIN'), ('POLICY'), ('DELETED') ON CONFLICT (relation_name) DO NOTHING;
+INSERT INTO relation_types (relation_name) VALUES ('BELONGS_TO'), ('VERSION'), ('METADATA'), ('ORIGIN'), ('POLICY'), ('DELETED'), ('SYMLINK'), ('PREVIEW') ON CONFLICT (relation_name) DO NOTHING;
 -- Create partial unique index for BELONGS_TO relations only
-CREATE UNIQUE INDEX IF NOT EXISTS belongs_to_idx ON internal_relations (origin_pid, relation_name, target_name) WHERE relation_name = ('BELONGS_TO')
+CREATE UNIQUE INDEX IF NOT EXISTS belongs_to_idx ON internal_relations (origin_pid, relation_name, target_name) WHERE relation_name = ('BELONGS_TO');
+-- Symlink names are unique per collection
+CREATE UNIQUE INDEX IF NOT EXISTS symlink_idx ON internal_relations (origin_pid, relation_name, target_name) WHERE relation_name = ('SYMLINK')
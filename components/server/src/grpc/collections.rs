@@ -2,20 +2,34 @@ use crate::auth::permission_handler::{PermissionCheck, PermissionHandler};
 use crate::auth::structs::Context;
 use crate::caching::cache::Cache;
 use crate::caching::structs::ObjectWrapper;
+use crate::database::dsls::access_policy_dsl::PolicyDecision;
 use crate::database::dsls::object_dsl::ObjectWithRelations;
+use crate::database::dsls::pinned_view_dsl::PinnedView;
 use crate::database::enums::DbPermissionLevel;
 use crate::middlelayer::create_request_types::CreateRequest;
 use crate::middlelayer::db_handler::DatabaseHandler;
 use crate::middlelayer::delete_request_types::DeleteRequest;
+use crate::middlelayer::listing_request_types::ListCollectionObjects;
+use crate::middlelayer::pinned_view_request_types::{
+    CreatePinnedView, ListPinnedViews, ResolvePinnedView, ResolvedPinnedView,
+};
+use crate::middlelayer::publication_request_types::{
+    publication_needs_admin, PUBLICATION_STATE_KEY,
+};
 use crate::middlelayer::snapshot_request_types::SnapshotRequest;
 use crate::middlelayer::update_request_types::{
     DataClassUpdate, DescriptionUpdate, KeyValueUpdate, LicenseUpdate, NameUpdate, UpdateAuthor,
     UpdateTitle,
 };
 use crate::search::meilisearch_client::{MeilisearchClient, ObjectDocument};
-use crate::utils::grpc_utils::{get_id_and_ctx, get_token_from_md, query, IntoGenericInner};
+use crate::utils::grpc_utils::{
+    delete_status, get_id_and_ctx, get_page_request_from_md, get_token_from_md, page_info_to_md,
+    query, write_error_status, IntoGenericInner,
+};
+use crate::utils::pagination_utils::paginate;
 use crate::utils::search_utils;
-use aruna_rust_api::api::storage::models::v2::{generic_resource, Collection};
+use crate::utils::traversal_utils::TRUNCATED_KEY;
+use aruna_rust_api::api::storage::models::v2::{generic_resource, Collection, Object};
 use aruna_rust_api::api::storage::services::v2::collection_service_server::CollectionService;
 use aruna_rust_api::api::storage::services::v2::{
     CreateCollectionRequest, CreateCollectionResponse, DeleteCollectionRequest,
@@ -32,6 +46,7 @@ use diesel_ulid::DieselUlid;
 use itertools::Itertools;
 use std::str::FromStr;
 use std::sync::Arc;
+use tonic::metadata::MetadataValue;
 use tonic::{Request, Response, Result};
 
 crate::impl_grpc_server!(CollectionServiceImpl, search_client: Arc<MeilisearchClient>);
@@ -50,6 +65,7 @@ impl CollectionService for CollectionServiceImpl {
         );
 
         let request = CreateRequest::Collection(request.into_inner());
+        tonic_invalid!(request.validate(), "Invalid request");
         let mut ctxs = request.get_relation_contexts()?;
         let parent_ctx = tonic_invalid!(
             request
@@ -59,6 +75,9 @@ impl CollectionService for CollectionServiceImpl {
             "invalid parent"
         );
         ctxs.push(parent_ctx);
+        if publication_needs_admin(request.get_data_class()) {
+            ctxs.push(Context::admin());
+        }
 
         let PermissionCheck {
             user_id, is_proxy, ..
@@ -82,12 +101,11 @@ impl CollectionService for CollectionServiceImpl {
             ));
         }
 
-        let (collection, _) = tonic_internal!(
-            self.database_handler
-                .create_resource(request, user_id, is_proxy,)
-                .await,
-            "Internal database error"
-        );
+        let (collection, _) = self
+            .database_handler
+            .create_resource(request, user_id, is_proxy)
+            .await
+            .map_err(|err| write_error_status(err, "Internal database error"))?;
 
         // Already done in create_resource
         // self.cache.add_object(collection.clone());
@@ -160,6 +178,11 @@ impl CollectionService for CollectionServiceImpl {
             "Token authentication error"
         );
 
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let request = request.into_inner();
 
         let (ids, ctxs): (Vec<DieselUlid>, Vec<Context>) = get_id_and_ctx(request.collection_ids)?;
@@ -174,9 +197,10 @@ impl CollectionService for CollectionServiceImpl {
             .map(|id| -> Result<Collection> { query(&self.cache, id)?.into_inner() })
             .collect();
 
-        let response = GetCollectionsResponse { collections: res? };
+        let (collections, page_info) = paginate(res?, &page, |resource| resource.id.clone());
+        let response = GetCollectionsResponse { collections };
 
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
 
     async fn delete_collection(
@@ -199,11 +223,18 @@ impl CollectionService for CollectionServiceImpl {
             self.authorizer.check_permissions(&token, vec![ctx]).await,
             "Unauthorized."
         );
+        // Admins can delete objects younger than the minimum delete age of their project
+        let is_admin = self
+            .authorizer
+            .check_permissions(&token, vec![Context::admin()])
+            .await
+            .is_ok();
 
-        let updates: Vec<ObjectWithRelations> = tonic_internal!(
-            self.database_handler.delete_resource(request).await,
-            "Internal database error"
-        );
+        let updates: Vec<ObjectWithRelations> = self
+            .database_handler
+            .delete_resource_checked(request, is_admin)
+            .await
+            .map_err(|err| delete_status(err, "Internal database error"))?;
 
         // Remove deleted resources from search index
         search_utils::remove_from_search_index(
@@ -229,6 +260,7 @@ impl CollectionService for CollectionServiceImpl {
         );
 
         let request = NameUpdate::Collection(request.into_inner());
+        tonic_invalid!(request.get_name(), "Invalid name");
         let collection_id = tonic_invalid!(request.get_id(), "Invalid collection id.");
         let ctx = Context::res_ctx(collection_id, DbPermissionLevel::WRITE, true);
 
@@ -282,6 +314,7 @@ impl CollectionService for CollectionServiceImpl {
         );
 
         let request = DescriptionUpdate::Collection(request.into_inner());
+        tonic_invalid!(request.validate(), "Invalid description");
         let collection_id = tonic_invalid!(request.get_id(), "Invalid collection id.");
         let ctx = Context::res_ctx(collection_id, DbPermissionLevel::WRITE, true);
 
@@ -336,10 +369,18 @@ impl CollectionService for CollectionServiceImpl {
 
         let request = KeyValueUpdate::Collection(request.into_inner());
         let collection_id = tonic_invalid!(request.get_id(), "Invalid collection id.");
-        let ctx = Context::res_ctx(collection_id, DbPermissionLevel::WRITE, true);
+        let mut ctxs = vec![Context::res_ctx(
+            collection_id,
+            DbPermissionLevel::WRITE,
+            true,
+        )];
+        // Publication states can only be changed by global admins
+        if request.contains_key(PUBLICATION_STATE_KEY) {
+            ctxs.push(Context::admin());
+        }
 
         tonic_auth!(
-            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            self.authorizer.check_permissions(&token, ctxs).await,
             "Unauthorized"
         );
 
@@ -358,6 +399,13 @@ impl CollectionService for CollectionServiceImpl {
             vec![ObjectDocument::from(collection.object.clone())],
         )
         .await;
+        // Propagate changed labels to the effective labels of all subresources
+        search_utils::update_inherited_labels(
+            &self.search_client,
+            &self.cache,
+            &collection.object.id,
+        )
+        .await;
 
         let rules = self
             .cache
@@ -388,10 +436,17 @@ impl CollectionService for CollectionServiceImpl {
         let request = DataClassUpdate::Collection(request.into_inner());
         let collection_id = tonic_invalid!(request.get_id(), "Invalid collection id.");
         // Dataclass can only be changed by non-servcieaccounts
-        let ctx = Context::res_ctx(collection_id, DbPermissionLevel::WRITE, false);
+        let mut ctxs = vec![Context::res_ctx(
+            collection_id,
+            DbPermissionLevel::WRITE,
+            false,
+        )];
+        if publication_needs_admin(request.get_raw_dataclass()) {
+            ctxs.push(Context::admin());
+        }
 
         tonic_auth!(
-            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            self.authorizer.check_permissions(&token, ctxs).await,
             "Unauthorized"
         );
 
@@ -620,3 +675,172 @@ impl CollectionService for CollectionServiceImpl {
         return_with_log!(response);
     }
 }
+
+impl CollectionServiceImpl {
+    /// Pins the current revisions of all objects in a collection under a name. Updates of
+    /// the objects do not change the view and pinned revisions can not be deleted.
+    pub async fn create_pinned_view(
+        &self,
+        request: Request<CreatePinnedView>,
+    ) -> Result<Response<PinnedView>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let request = request.into_inner();
+        let collection_id = tonic_invalid!(request.get_id(), "Invalid collection id");
+        let ctx = Context::res_ctx(collection_id, DbPermissionLevel::WRITE, true);
+        let user_id = tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let view = tonic_invalid!(
+            self.database_handler
+                .create_pinned_view(request, user_id)
+                .await,
+            "Invalid pinned view request"
+        );
+        return_with_log!(view);
+    }
+
+    pub async fn list_pinned_views(
+        &self,
+        request: Request<ListPinnedViews>,
+    ) -> Result<Response<Vec<PinnedView>>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let request = request.into_inner();
+        let collection_id = tonic_invalid!(request.get_id(), "Invalid collection id");
+        let ctx = Context::res_ctx(collection_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let views = tonic_internal!(
+            self.database_handler.list_pinned_views(request).await,
+            "Error while listing pinned views"
+        );
+        return_with_log!(views);
+    }
+
+    /// Returns the object revisions which were pinned when the view was created.
+    pub async fn resolve_pinned_view(
+        &self,
+        request: Request<ResolvePinnedView>,
+    ) -> Result<Response<ResolvedPinnedView>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let request = request.into_inner();
+        let view = tonic_invalid!(
+            self.database_handler.get_pinned_view(&request).await,
+            "Invalid pinned view"
+        );
+        let ctx = Context::res_ctx(view.collection_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let objects = tonic_internal!(
+            self.database_handler.resolve_pinned_view(&view).await,
+            "Error while resolving pinned view"
+        )
+        .into_iter()
+        .map(|object| {
+            let rules = self
+                .cache
+                .get_rule_bindings(&object.object.id)
+                .unwrap_or_default();
+            let generic_resource: generic_resource::Resource = ObjectWrapper {
+                object_with_relations: object,
+                rules,
+            }
+            .into();
+            generic_resource.into_inner()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+        let response = ResolvedPinnedView { view, objects };
+        return_with_log!(response);
+    }
+
+    /// Lists the objects below a collection which are readable by the caller.
+    pub async fn list_collection_objects(
+        &self,
+        request: Request<ListCollectionObjects>,
+    ) -> Result<Response<Vec<Object>>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
+        let request = request.into_inner();
+        let collection_id = tonic_invalid!(request.get_id(), "Invalid collection id");
+        let ctx = Context::res_ctx(collection_id, DbPermissionLevel::READ, true);
+        let PermissionCheck {
+            user_id,
+            is_personal,
+            ..
+        } = tonic_auth!(
+            self.authorizer
+                .check_permissions_verbose(&token, vec![ctx])
+                .await,
+            "Unauthorized"
+        );
+
+        let (ids, truncated) = tonic_internal!(
+            self.database_handler.list_collection_objects(request).await,
+            "Error while listing collection objects"
+        );
+
+        // Read permissions on the collection are inherited by all objects below it,
+        // only access policies can still deny single objects
+        let readable = ids
+            .into_iter()
+            .filter(|id| {
+                let ctx = Context::res_ctx(*id, DbPermissionLevel::READ, true);
+                self.cache
+                    .evaluate_access_policies(&[ctx], is_personal, &user_id)
+                    != PolicyDecision::Deny
+            })
+            .collect_vec();
+
+        let (ids, page_info) = paginate(readable, &page, |id| id.to_string());
+        let objects = ids
+            .iter()
+            .filter_map(|id| self.cache.get_wrapped_object(id))
+            .map(|object| {
+                let generic_resource: generic_resource::Resource = object.into();
+                generic_resource.into_inner()
+            })
+            .collect::<Result<Vec<Object>>>()?;
+
+        let mut md = page_info_to_md(&page_info);
+        if truncated {
+            md.insert(TRUNCATED_KEY, MetadataValue::from_static("true"));
+        }
+        return_with_log!(objects, md);
+    }
+}
@@ -26,14 +26,20 @@ use crate::database::enums::DbPermissionLevel;
 use crate::middlelayer::create_request_types::CreateRequest;
 use crate::middlelayer::db_handler::DatabaseHandler;
 use crate::middlelayer::delete_request_types::DeleteRequest;
+use crate::middlelayer::publication_request_types::{
+    publication_needs_admin, PUBLICATION_STATE_KEY,
+};
 use crate::middlelayer::snapshot_request_types::SnapshotRequest;
 use crate::middlelayer::update_request_types::{
     DataClassUpdate, DescriptionUpdate, KeyValueUpdate, LicenseUpdate, NameUpdate, UpdateAuthor,
     UpdateTitle,
 };
 use crate::search::meilisearch_client::{MeilisearchClient, ObjectDocument};
-use crate::utils::grpc_utils::get_token_from_md;
-use crate::utils::grpc_utils::{get_id_and_ctx, query, IntoGenericInner};
+use crate::utils::grpc_utils::{
+    delete_status, get_id_and_ctx, query, write_error_status, IntoGenericInner,
+};
+use crate::utils::grpc_utils::{get_page_request_from_md, get_token_from_md, page_info_to_md};
+use crate::utils::pagination_utils::paginate;
 use crate::utils::search_utils;
 
 crate::impl_grpc_server!(DatasetServiceImpl, search_client: Arc<MeilisearchClient>);
@@ -52,6 +58,7 @@ impl DatasetService for DatasetServiceImpl {
         );
 
         let request = CreateRequest::Dataset(request.into_inner());
+        tonic_invalid!(request.validate(), "Invalid request");
         let mut ctxs = request.get_relation_contexts()?;
         let parent_ctx = tonic_invalid!(
             request
@@ -61,6 +68,9 @@ impl DatasetService for DatasetServiceImpl {
             "invalid parent"
         );
         ctxs.push(parent_ctx);
+        if publication_needs_admin(request.get_data_class()) {
+            ctxs.push(Context::admin());
+        }
 
         let PermissionCheck {
             user_id, is_proxy, ..
@@ -84,12 +94,11 @@ impl DatasetService for DatasetServiceImpl {
             ));
         }
 
-        let (dataset, _) = tonic_internal!(
-            self.database_handler
-                .create_resource(request, user_id, is_proxy)
-                .await,
-            "Internal database error"
-        );
+        let (dataset, _) = self
+            .database_handler
+            .create_resource(request, user_id, is_proxy)
+            .await
+            .map_err(|err| write_error_status(err, "Internal database error"))?;
 
         self.cache.add_object(dataset.clone());
 
@@ -163,6 +172,11 @@ impl DatasetService for DatasetServiceImpl {
             "Token authentication error"
         );
 
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let request = request.into_inner();
 
         let (ids, ctxs): (Vec<DieselUlid>, Vec<Context>) = get_id_and_ctx(request.dataset_ids)?;
@@ -177,9 +191,10 @@ impl DatasetService for DatasetServiceImpl {
             .map(|id| -> Result<Dataset> { query(&self.cache, id)?.into_inner() })
             .collect();
 
-        let response = GetDatasetsResponse { datasets: res? };
+        let (datasets, page_info) = paginate(res?, &page, |resource| resource.id.clone());
+        let response = GetDatasetsResponse { datasets };
 
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
 
     async fn delete_dataset(
@@ -202,11 +217,18 @@ impl DatasetService for DatasetServiceImpl {
             self.authorizer.check_permissions(&token, vec![ctx]).await,
             "Unauthorized."
         );
+        // Admins can delete objects younger than the minimum delete age of their project
+        let is_admin = self
+            .authorizer
+            .check_permissions(&token, vec![Context::admin()])
+            .await
+            .is_ok();
 
-        let updates: Vec<ObjectWithRelations> = tonic_internal!(
-            self.database_handler.delete_resource(request).await,
-            "Internal database error"
-        );
+        let updates: Vec<ObjectWithRelations> = self
+            .database_handler
+            .delete_resource_checked(request, is_admin)
+            .await
+            .map_err(|err| delete_status(err, "Internal database error"))?;
 
         // Remove deleted resources from search index
         search_utils::remove_from_search_index(
@@ -232,6 +254,7 @@ impl DatasetService for DatasetServiceImpl {
         );
 
         let request = NameUpdate::Dataset(request.into_inner());
+        tonic_invalid!(request.get_name(), "Invalid name");
         let dataset_id = tonic_invalid!(request.get_id(), "Invalid dataset id.");
 
         let ctx = Context::res_ctx(dataset_id, DbPermissionLevel::WRITE, true);
@@ -285,6 +308,7 @@ impl DatasetService for DatasetServiceImpl {
         );
 
         let request = DescriptionUpdate::Dataset(request.into_inner());
+        tonic_invalid!(request.validate(), "Invalid description");
         let dataset_id = tonic_invalid!(request.get_id(), "Invalid dataset id.");
         let ctx = Context::res_ctx(dataset_id, DbPermissionLevel::WRITE, true);
 
@@ -338,10 +362,14 @@ impl DatasetService for DatasetServiceImpl {
 
         let request = KeyValueUpdate::Dataset(request.into_inner());
         let dataset_id = tonic_invalid!(request.get_id(), "Invalid dataset id.");
-        let ctx = Context::res_ctx(dataset_id, DbPermissionLevel::WRITE, true);
+        let mut ctxs = vec![Context::res_ctx(dataset_id, DbPermissionLevel::WRITE, true)];
+        // Publication states can only be changed by global admins
+        if request.contains_key(PUBLICATION_STATE_KEY) {
+            ctxs.push(Context::admin());
+        }
 
         tonic_auth!(
-            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            self.authorizer.check_permissions(&token, ctxs).await,
             "Unauthorized"
         );
 
@@ -360,6 +388,9 @@ impl DatasetService for DatasetServiceImpl {
             vec![ObjectDocument::from(dataset.object.clone())],
         )
         .await;
+        // Propagate changed labels to the effective labels of all subresources
+        search_utils::update_inherited_labels(&self.search_client, &self.cache, &dataset.object.id)
+            .await;
 
         let rules = self
             .cache
@@ -391,10 +422,17 @@ impl DatasetService for DatasetServiceImpl {
         let request = DataClassUpdate::Dataset(request.into_inner());
         let dataset_id = tonic_invalid!(request.get_id(), "Invalid dataset id.");
         // Dataclass can only be set by non-serivceaccounts
-        let ctx = Context::res_ctx(dataset_id, DbPermissionLevel::WRITE, false);
+        let mut ctxs = vec![Context::res_ctx(
+            dataset_id,
+            DbPermissionLevel::WRITE,
+            false,
+        )];
+        if publication_needs_admin(request.get_raw_dataclass()) {
+            ctxs.push(Context::admin());
+        }
 
         tonic_auth!(
-            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            self.authorizer.check_permissions(&token, ctxs).await,
             "Unauthorized"
         );
 
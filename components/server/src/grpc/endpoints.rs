@@ -3,8 +3,11 @@ use crate::auth::structs::Context;
 use crate::caching::cache::Cache;
 use crate::caching::structs::PubKeyEnum;
 use crate::middlelayer::db_handler::DatabaseHandler;
-use crate::middlelayer::endpoints_request_types::{CreateEP, DeleteEP, GetEP};
-use crate::utils::grpc_utils::get_token_from_md;
+use crate::middlelayer::endpoints_request_types::{
+    decoding_key_from_pubkey, ApproveEP, CreateEP, DeleteEP, GetEP, RegisterEP,
+};
+use crate::utils::grpc_utils::{get_page_request_from_md, get_token_from_md, page_info_to_md};
+use crate::utils::pagination_utils::paginate;
 use anyhow::bail;
 use aruna_rust_api::api::storage::models::v2::Endpoint;
 use aruna_rust_api::api::storage::services::v2::endpoint_service_server::EndpointService;
@@ -14,7 +17,6 @@ use aruna_rust_api::api::storage::services::v2::{
     GetDefaultEndpointRequest, GetDefaultEndpointResponse, GetEndpointRequest, GetEndpointResponse,
     GetEndpointsRequest, GetEndpointsResponse, SetEndpointStatusRequest, SetEndpointStatusResponse,
 };
-use jsonwebtoken::DecodingKey;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
@@ -44,21 +46,10 @@ impl EndpointService for EndpointServiceImpl {
             "Unauthorized"
         );
 
-        let key = if request.0.pubkey.starts_with("-----BEGIN PUBLIC KEY-----") {
-            tonic_invalid!(
-                DecodingKey::from_ed_pem(request.0.pubkey.as_bytes()),
-                "Invalid pubkey"
-            )
-        } else {
-            let public_pem = format!(
-                "-----BEGIN PUBLIC KEY-----{}-----END PUBLIC KEY-----",
-                &request.0.pubkey
-            );
-            tonic_invalid!(
-                DecodingKey::from_ed_pem(public_pem.as_bytes()),
-                "Invalid pubkey"
-            )
-        };
+        let key = tonic_invalid!(
+            decoding_key_from_pubkey(&request.0.pubkey),
+            "Invalid pubkey"
+        );
 
         let (ep, pk) = tonic_invalid!(
             self.database_handler.create_endpoint(request).await,
@@ -178,6 +169,11 @@ impl EndpointService for EndpointServiceImpl {
         // Consumer gRPC request into its parts
         let (request_metadata, _, _) = request.into_parts();
 
+        let page = tonic_invalid!(
+            get_page_request_from_md(&request_metadata),
+            "Invalid pagination"
+        );
+
         let is_admin = if request_metadata.get("Authorization").is_some() {
             // Extract token and check permissions with empty context
             let token = tonic_auth!(
@@ -212,13 +208,15 @@ impl EndpointService for EndpointServiceImpl {
                 .collect::<Vec<_>>()
         };
 
-        let response = GetEndpointsResponse {
-            endpoints: eps
-                .into_iter()
+        let (endpoints, page_info) = paginate(
+            eps.into_iter()
                 .map(|ep| -> Endpoint { ep.into() })
                 .collect::<Vec<Endpoint>>(),
-        };
-        return_with_log!(response);
+            &page,
+            |endpoint| endpoint.id.clone(),
+        );
+        let response = GetEndpointsResponse { endpoints };
+        return_with_log!(response, page_info_to_md(&page_info));
     }
 
     async fn delete_endpoint(
@@ -283,3 +281,64 @@ impl EndpointService for EndpointServiceImpl {
         ))
     }
 }
+
+impl EndpointServiceImpl {
+    /// Registers a data proxy without an admin token. The proxy proves the possession of
+    /// the private key by signing a registration challenge for its name; the endpoint stays
+    /// pending until an administrator approves it.
+    pub async fn register_endpoint(
+        &self,
+        request: Request<RegisterEP>,
+    ) -> Result<Response<CreateEndpointResponse>> {
+        log_received!(&request);
+
+        let (ep, _) = tonic_invalid!(
+            self.database_handler
+                .register_endpoint(request.into_inner())
+                .await,
+            "Invalid endpoint registration"
+        );
+
+        let result = CreateEndpointResponse {
+            endpoint: Some(ep.into()),
+        };
+
+        return_with_log!(result);
+    }
+
+    /// Approves a pending self-registered endpoint and trusts its pubkey.
+    pub async fn approve_endpoint(
+        &self,
+        request: Request<ApproveEP>,
+    ) -> Result<Response<CreateEndpointResponse>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let ctx = Context::admin();
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let (ep, pk) = tonic_invalid!(
+            self.database_handler
+                .approve_endpoint(request.into_inner())
+                .await,
+            "Invalid approve endpoint request"
+        );
+        let key = tonic_internal!(decoding_key_from_pubkey(&pk.pubkey), "Invalid pubkey");
+
+        self.cache
+            .add_pubkey(pk.id, PubKeyEnum::DataProxy((pk.pubkey, key, ep.id)));
+
+        let result = CreateEndpointResponse {
+            endpoint: Some(ep.into()),
+        };
+
+        return_with_log!(result);
+    }
+}
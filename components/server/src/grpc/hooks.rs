@@ -1,14 +1,20 @@
 use crate::auth::permission_handler::PermissionHandler;
 use crate::auth::structs::Context;
 use crate::caching::cache::Cache;
+use crate::database::dsls::dead_letter_hook_dsl::DeadLetterHook;
 use crate::database::enums::DbPermissionLevel;
 use crate::middlelayer::db_handler::DatabaseHandler;
 use crate::middlelayer::hooks_request_types::CreateHook;
 use crate::middlelayer::hooks_request_types::ListBy;
-use crate::utils::grpc_utils::get_token_from_md;
+use crate::middlelayer::hooks_request_types::{
+    DiscardDeadLetterHook, ListDeadLetterHooks, RetryDeadLetterHook,
+};
+use crate::utils::grpc_utils::{get_page_request_from_md, get_token_from_md, page_info_to_md};
+use crate::utils::pagination_utils::paginate;
 use aruna_rust_api::api::hooks::services::v2::hooks_service_server::HooksService;
 use aruna_rust_api::api::hooks::services::v2::AddProjectsToHookRequest;
 use aruna_rust_api::api::hooks::services::v2::AddProjectsToHookResponse;
+use aruna_rust_api::api::hooks::services::v2::HookInfo;
 use aruna_rust_api::api::hooks::services::v2::{
     CreateHookRequest, CreateHookResponse, DeleteHookRequest, DeleteHookResponse,
     HookCallbackRequest, HookCallbackResponse, ListOwnedHooksRequest, ListOwnedHooksResponse,
@@ -70,6 +76,11 @@ impl HooksService for HookServiceImpl {
             "Token authentication error"
         );
 
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let request = ListBy::PROJECT(request.into_inner());
         let project_id = tonic_invalid!(request.get_id(), "invalid parent");
         let ctx = Context::res_ctx(project_id, DbPermissionLevel::ADMIN, true);
@@ -83,11 +94,14 @@ impl HooksService for HookServiceImpl {
             "Internal error while listing hooks"
         );
 
-        let response = ListProjectHooksResponse {
-            infos: hooks.into_iter().map(|h| h.into()).collect(),
-        };
+        let (infos, page_info) = paginate(
+            hooks.into_iter().map(|h| h.into()).collect(),
+            &page,
+            |info: &HookInfo| info.hook_id.clone(),
+        );
+        let response = ListProjectHooksResponse { infos };
 
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
 
     async fn list_owned_hooks(
@@ -101,6 +115,11 @@ impl HooksService for HookServiceImpl {
             "Token authentication error"
         );
 
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let request = request.into_inner();
         let request = if request.user_id.is_empty() {
             let ctx = Context::self_ctx();
@@ -123,11 +142,14 @@ impl HooksService for HookServiceImpl {
             "Internal error while listing hooks"
         );
 
-        let response = ListOwnedHooksResponse {
-            infos: hooks.into_iter().map(|h| h.into()).collect(),
-        };
+        let (infos, page_info) = paginate(
+            hooks.into_iter().map(|h| h.into()).collect(),
+            &page,
+            |info: &HookInfo| info.hook_id.clone(),
+        );
+        let response = ListOwnedHooksResponse { infos };
 
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
     async fn delete_hook(
         &self,
@@ -215,3 +237,85 @@ impl HooksService for HookServiceImpl {
         return_with_log!(AddProjectsToHookResponse {});
     }
 }
+
+impl HookServiceImpl {
+    /// Lists external hook calls which failed after all retries.
+    pub async fn list_dead_letter_hooks(
+        &self,
+        request: Request<ListDeadLetterHooks>,
+    ) -> Result<Response<Vec<DeadLetterHook>>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let ctx = Context::admin();
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let request = request.into_inner();
+        tonic_invalid!(request.get_hook_id(), "Invalid hook id");
+        let dead_letters = tonic_internal!(
+            self.database_handler.list_dead_letter_hooks(request).await,
+            "Error while listing dead-lettered hooks"
+        );
+        return_with_log!(dead_letters);
+    }
+
+    /// Runs a dead-lettered hook call again through the hook handler.
+    pub async fn retry_dead_letter_hook(
+        &self,
+        request: Request<RetryDeadLetterHook>,
+    ) -> Result<Response<()>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let ctx = Context::admin();
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        tonic_invalid!(
+            self.database_handler
+                .retry_dead_letter_hook(request.into_inner())
+                .await,
+            "Invalid dead-lettered hook"
+        );
+        return_with_log!(());
+    }
+
+    pub async fn discard_dead_letter_hook(
+        &self,
+        request: Request<DiscardDeadLetterHook>,
+    ) -> Result<Response<()>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let ctx = Context::admin();
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        tonic_invalid!(
+            self.database_handler
+                .discard_dead_letter_hook(request.into_inner())
+                .await,
+            "Invalid dead-lettered hook"
+        );
+        return_with_log!(());
+    }
+}
@@ -1,11 +1,13 @@
 use crate::auth::permission_handler::PermissionHandler;
 use crate::caching::cache::Cache;
 use crate::middlelayer::db_handler::DatabaseHandler;
+use aruna_rust_api::api::storage::models::v2::ComponentStatus as Status;
 use aruna_rust_api::api::storage::services::v2::storage_status_service_server::StorageStatusService;
 use aruna_rust_api::api::storage::services::v2::{
-    GetAnnouncementsRequest, GetAnnouncementsResponse, GetPubkeysRequest, GetPubkeysResponse,
-    GetStorageStatusRequest, GetStorageStatusResponse, GetStorageVersionRequest,
-    GetStorageVersionResponse, SetAnnouncementsRequest, SetAnnouncementsResponse,
+    ComponentStatus, GetAnnouncementsRequest, GetAnnouncementsResponse, GetPubkeysRequest,
+    GetPubkeysResponse, GetStorageStatusRequest, GetStorageStatusResponse,
+    GetStorageVersionRequest, GetStorageVersionResponse, LocationStatus, SetAnnouncementsRequest,
+    SetAnnouncementsResponse,
 };
 use std::sync::Arc;
 use tonic::Response;
@@ -35,7 +37,24 @@ impl StorageStatusService for StorageStatusServiceImpl {
         &self,
         _request: tonic::Request<GetStorageStatusRequest>,
     ) -> Result<Response<GetStorageStatusResponse>, tonic::Status> {
-        Err(tonic::Status::unimplemented("Nothing to see here!"))
+        // Writes succeed while Nats.io is unavailable, notifications are delayed
+        let nats_status = if self.database_handler.natsio_handler.is_available() {
+            Status::Available
+        } else {
+            Status::Degraded
+        };
+
+        let response = GetStorageStatusResponse {
+            location_status: vec![LocationStatus {
+                location: "server".to_string(),
+                component_status: vec![ComponentStatus {
+                    name: "nats".to_string(),
+                    status: nats_status as i32,
+                }],
+            }],
+        };
+
+        Ok(Response::new(response))
     }
 
     async fn get_pubkeys(
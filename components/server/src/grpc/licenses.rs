@@ -1,8 +1,17 @@
 use crate::auth::permission_handler::PermissionHandler;
 use crate::auth::structs::Context;
 use crate::caching::cache::Cache;
+use crate::database::dsls::license_dsl::License;
+use crate::database::dsls::license_override_dsl::LicenseOverride;
+use crate::database::enums::DbPermissionLevel;
 use crate::middlelayer::db_handler::DatabaseHandler;
-use crate::utils::grpc_utils::get_token_from_md;
+use crate::middlelayer::license_request_types::{
+    EffectiveLicense, GetEffectiveLicense, GetObjectLicenseTerms, GrantLicenseOverride,
+    ObjectLicenseTerms, RevokeLicenseOverride, SetDefaultLicense, SetLicenseTerms,
+};
+use crate::utils::grpc_utils::{get_page_request_from_md, get_token_from_md, page_info_to_md};
+use crate::utils::pagination_utils::paginate;
+use aruna_rust_api::api::storage::models::v2::License as APILicense;
 use aruna_rust_api::api::storage::services::v2::license_service_server::LicenseService;
 use aruna_rust_api::api::storage::services::v2::{
     CreateLicenseRequest, CreateLicenseResponse, GetLicenseRequest, GetLicenseResponse,
@@ -86,14 +95,192 @@ impl LicenseService for LicensesServiceImpl {
         //     "Unauthorized"
         // );
 
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let licenses = tonic_internal!(
             self.database_handler.list_licenses().await,
             "License fetching error"
         );
 
-        let response = ListLicensesResponse {
-            licenses: licenses.into_iter().map(|l| l.into()).collect(),
-        };
-        return_with_log!(response);
+        let (licenses, page_info) = paginate(
+            licenses.into_iter().map(|l| l.into()).collect(),
+            &page,
+            |license: &APILicense| license.tag.clone(),
+        );
+        let response = ListLicensesResponse { licenses };
+        return_with_log!(response, page_info_to_md(&page_info));
+    }
+}
+
+impl LicensesServiceImpl {
+    /// Sets the embargo and access expiry of a license and returns the license with its terms.
+    pub async fn set_license_terms(
+        &self,
+        request: Request<SetLicenseTerms>,
+    ) -> Result<Response<License>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let ctx = Context::admin();
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let license = tonic_invalid!(
+            self.database_handler
+                .set_license_terms(request.into_inner())
+                .await,
+            "Invalid license terms"
+        );
+        return_with_log!(license);
+    }
+
+    /// Returns when the data of an object is accessible according to its data license.
+    pub async fn get_object_license_terms(
+        &self,
+        request: Request<GetObjectLicenseTerms>,
+    ) -> Result<Response<ObjectLicenseTerms>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let object_id = tonic_invalid!(request.get_ref().get_id(), "Invalid object id");
+        let ctx = Context::res_ctx(object_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let terms = tonic_internal!(
+            self.database_handler
+                .get_object_license_terms(&object_id)
+                .await,
+            "Error while fetching license terms"
+        );
+        return_with_log!(terms);
+    }
+
+    /// Sets or unsets the data license new children of a project or collection inherit.
+    pub async fn set_default_license(
+        &self,
+        request: Request<SetDefaultLicense>,
+    ) -> Result<Response<EffectiveLicense>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let resource_id = tonic_invalid!(request.get_ref().get_id(), "Invalid resource id");
+        let ctx = Context::res_ctx(resource_id, DbPermissionLevel::ADMIN, false);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let resource = tonic_invalid!(
+            self.database_handler
+                .set_default_license(request.into_inner())
+                .await,
+            "Invalid default license"
+        );
+        self.cache.upsert_object(&resource_id, resource);
+
+        let license = tonic_internal!(
+            self.database_handler.get_effective_license(&resource_id),
+            "Error while fetching effective license"
+        );
+        return_with_log!(license);
+    }
+
+    /// Returns the licenses of a resource and the default data license its new children inherit.
+    pub async fn get_effective_license(
+        &self,
+        request: Request<GetEffectiveLicense>,
+    ) -> Result<Response<EffectiveLicense>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let resource_id = tonic_invalid!(request.get_ref().get_id(), "Invalid resource id");
+        let ctx = Context::res_ctx(resource_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let license = tonic_internal!(
+            self.database_handler.get_effective_license(&resource_id),
+            "Error while fetching effective license"
+        );
+        return_with_log!(license);
+    }
+
+    /// Exempts a user from the license terms of a resource and its descendants.
+    pub async fn grant_license_override(
+        &self,
+        request: Request<GrantLicenseOverride>,
+    ) -> Result<Response<LicenseOverride>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let ctx = Context::admin();
+        let admin_id = tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let grant = tonic_invalid!(
+            self.database_handler
+                .grant_license_override(request.into_inner(), admin_id)
+                .await,
+            "Invalid license override"
+        );
+        return_with_log!(grant);
+    }
+
+    pub async fn revoke_license_override(
+        &self,
+        request: Request<RevokeLicenseOverride>,
+    ) -> Result<Response<()>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let ctx = Context::admin();
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        tonic_invalid!(
+            self.database_handler
+                .revoke_license_override(request.into_inner())
+                .await,
+            "Invalid license override"
+        );
+        return_with_log!(());
     }
 }
@@ -1,7 +1,7 @@
 use std::str::FromStr;
 use std::sync::Arc;
 
-use aruna_rust_api::api::storage::models::v2::{generic_resource, Object};
+use aruna_rust_api::api::storage::models::v2::{generic_resource, Collection, Object};
 use aruna_rust_api::api::storage::services::v2::object_service_server::ObjectService;
 use aruna_rust_api::api::storage::services::v2::{
     CloneObjectRequest, CloneObjectResponse, CreateObjectRequest, CreateObjectResponse,
@@ -12,28 +12,66 @@ use aruna_rust_api::api::storage::services::v2::{
     UpdateObjectAuthorsRequest, UpdateObjectAuthorsResponse, UpdateObjectRequest,
     UpdateObjectResponse, UpdateObjectTitleRequest, UpdateObjectTitleResponse,
 };
+use chrono::Utc;
 use diesel_ulid::DieselUlid;
+use futures::StreamExt;
 use itertools::Itertools;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Result, Status};
 
 use crate::auth::permission_handler::{PermissionCheck, PermissionHandler};
 use crate::auth::structs::Context;
 use crate::caching::cache::Cache;
 use crate::caching::structs::ObjectWrapper;
+use crate::database::dsls::manifest_dsl::SignedManifest;
+use crate::database::dsls::metadata_schema_dsl::SchemaViolations;
+use crate::database::dsls::name_reservation_dsl::NameReservation;
 use crate::database::dsls::object_dsl::ObjectWithRelations;
-use crate::database::enums::DbPermissionLevel;
+use crate::database::enums::{DbPermissionLevel, ObjectStatus};
 use crate::middlelayer::clone_request_types::CloneObject;
-use crate::middlelayer::create_request_types::CreateRequest;
+use crate::middlelayer::create_request_types::{CreateRequest, ExistingObjectMode};
 use crate::middlelayer::db_handler::DatabaseHandler;
-use crate::middlelayer::delete_request_types::DeleteRequest;
-use crate::middlelayer::presigned_url_handler::{PresignedDownload, PresignedUpload};
+use crate::middlelayer::delete_request_types::{
+    BatchDeleteResult, BatchDeleteStatus, DeleteBatchProgress, DeleteBatchStreamMessage,
+    DeleteObjectsBatch, DeleteRequest, DELETE_BATCH_CONFIG,
+};
+use crate::middlelayer::expiry_request_types::{
+    is_expired, validate_expiry_label, SetObjectExpiry, EXPIRES_AT_KEY,
+};
+use crate::middlelayer::hash_db_handler::FindObjectsByHash;
+use crate::middlelayer::license_request_types::LicenseViolation;
+use crate::middlelayer::lifecycle_request_types::{
+    GetObjectLifecycleState, ObjectLifecycleState, TransitionObjectState, LIFECYCLE_STATE_KEY,
+};
+use crate::middlelayer::manifest_request_types::GetSignedManifest;
+use crate::middlelayer::name_reservation_request_types::{ReleaseObjectName, ReserveObjectName};
+use crate::middlelayer::presigned_url_handler::{
+    BatchDownloadUrl, BatchDownloadUrlEntry, CreateDownloadLinksStream, DownloadLinksStreamMessage,
+    DownloadUrlOptions, GetDownloadUrlsBatch, PartPlan, PresignedDownload, PresignedUpload,
+    MAX_DOWNLOAD_URL_BATCH_SIZE, UPLOAD_SIZE_POLICY,
+};
+use crate::middlelayer::preview_request_types::GetPreview;
+use crate::middlelayer::requester_pays_request_types::RequesterPaysNotAcknowledged;
+use crate::middlelayer::symlink_request_types::{CreateSymlink, GetSymlink};
+use crate::middlelayer::update_db_handler::FinishConflict;
 use crate::middlelayer::update_request_types::{
     SetHashes, UpdateAuthor, UpdateObject, UpdateTitle,
 };
 use crate::search::meilisearch_client::{MeilisearchClient, ObjectDocument};
-use crate::utils::grpc_utils::get_token_from_md;
-use crate::utils::grpc_utils::{get_id_and_ctx, IntoGenericInner};
+use crate::utils::grpc_utils::{
+    delete_status, get_cidr_restriction_from_md, get_content_disposition_from_md,
+    get_content_length_from_md, get_content_md5_from_md, get_if_exists_from_md,
+    get_name_reservation_from_md, get_request_payer_from_md, get_single_use_from_md,
+    get_token_from_md, part_plan_to_md,
+};
+use crate::utils::grpc_utils::{
+    get_id_and_ctx, get_page_request_from_md, metadata_schema_status, not_found, page_info_to_md,
+    write_error_status, IntoGenericInner,
+};
+use crate::utils::pagination_utils::paginate;
 use crate::utils::search_utils;
+use crate::utils::user_notification_utils::QuotaExceeded;
 
 crate::impl_grpc_server!(ObjectServiceImpl, search_client: Arc<MeilisearchClient>);
 
@@ -50,15 +88,41 @@ impl ObjectService for ObjectServiceImpl {
             "Token authentication error"
         );
 
-        let request = CreateRequest::Object(request.into_inner());
-        let mut ctxs = request.get_relation_contexts()?;
-        let parent_ctx = tonic_invalid!(
-            request
-                .get_parent()
-                .ok_or(Status::invalid_argument("Parent missing."))?
-                .get_context(),
-            "invalid parent"
+        let mode = tonic_invalid!(
+            get_if_exists_from_md(request.metadata()),
+            "Invalid if-exists mode"
         );
+        let reservation = tonic_invalid!(
+            get_name_reservation_from_md(request.metadata()),
+            "Invalid name reservation"
+        );
+        let inner = request.into_inner();
+        let request = CreateRequest::Object(inner.clone());
+        tonic_invalid!(request.validate(), "Invalid request");
+        // New objects start in the initial lifecycle state of their project
+        if request
+            .get_key_values()
+            .iter()
+            .any(|kv| kv.key == LIFECYCLE_STATE_KEY)
+        {
+            return Err(Status::failed_precondition(
+                "Lifecycle states can only be changed by transitions",
+            ));
+        }
+        tonic_invalid!(
+            validate_expiry_label(request.get_key_values(), Utc::now().naive_utc()),
+            "Invalid expiry"
+        );
+        let mut ctxs = request.get_relation_contexts()?;
+        let parent = request
+            .get_parent()
+            .ok_or(Status::invalid_argument("Parent missing."))?;
+        let parent_ctx = tonic_invalid!(parent.get_context(), "invalid parent");
+        // Overwriting creates a new revision of the existing object in the parent
+        if mode == ExistingObjectMode::Overwrite {
+            let parent_id = tonic_invalid!(parent.get_id(), "invalid parent");
+            ctxs.push(Context::res_ctx(parent_id, DbPermissionLevel::WRITE, true));
+        }
         ctxs.push(parent_ctx);
         let PermissionCheck {
             user_id, is_proxy, ..
@@ -80,14 +144,25 @@ impl ObjectService for ObjectServiceImpl {
                 "Workspaces have to be claimed for dataclass changes",
             ));
         }
-        let (object_plus, _) = tonic_internal!(
-            self.database_handler
-                .create_resource(request, user_id, is_proxy)
-                .await,
-            "Internal database error"
-        );
-
-        self.cache.add_object(object_plus.clone());
+        let (object_plus, created) = self
+            .database_handler
+            .create_or_get_object(
+                inner,
+                user_id,
+                is_proxy,
+                is_service_account,
+                mode,
+                reservation,
+            )
+            .await
+            .map_err(|err| write_error_status(err, "Internal database error"))?;
+
+        if created {
+            self.cache.add_object(object_plus.clone());
+        } else {
+            self.cache
+                .upsert_object(&object_plus.object.id, object_plus.clone());
+        }
 
         // Add or update object in search index
         search_utils::update_search_index(
@@ -124,7 +199,28 @@ impl ObjectService for ObjectServiceImpl {
             "Token authentication error"
         );
 
+        let content_md5 = tonic_invalid!(
+            get_content_md5_from_md(request.metadata()),
+            "Invalid content md5"
+        );
+        let content_len = tonic_invalid!(
+            get_content_length_from_md(request.metadata()),
+            "Invalid content length"
+        );
         let request = PresignedUpload(request.into_inner());
+        let part_plan = if request.get_multipart() {
+            let plan = tonic_invalid!(PartPlan::recommend(content_len), "Invalid content length");
+            tonic_invalid!(
+                plan.check_part_number(request.0.part_number),
+                "Invalid part number"
+            );
+            Some(plan)
+        } else {
+            None
+        };
+        // The data proxy rejects uploads outside the declared size
+        let size_range =
+            UPLOAD_SIZE_POLICY.upload_range(content_len, part_plan.as_ref(), request.0.part_number);
 
         let object_id = tonic_invalid!(request.get_id(), "Invalid id");
         let PermissionCheck { user_id, token, .. } = tonic_auth!(
@@ -137,6 +233,18 @@ impl ObjectService for ObjectServiceImpl {
             "Unauthorized"
         );
 
+        // Renew upload deadline, expired staging objects have to be re-initialized
+        if !tonic_internal!(
+            self.database_handler
+                .heartbeat_upload(&object_id, None)
+                .await,
+            "Error while renewing upload deadline"
+        ) {
+            return Err(Status::failed_precondition(
+                "Upload deadline expired, object has to be re-initialized",
+            ));
+        }
+
         let signed_url = tonic_internal!(
             self.database_handler
                 .get_presigend_upload(
@@ -145,6 +253,8 @@ impl ObjectService for ObjectServiceImpl {
                     self.authorizer.clone(),
                     user_id,
                     token,
+                    content_md5,
+                    size_range,
                 )
                 .await,
             "Error while building presigned url"
@@ -152,7 +262,14 @@ impl ObjectService for ObjectServiceImpl {
 
         let result = GetUploadUrlResponse { url: signed_url };
 
-        return_with_log!(result);
+        match part_plan {
+            Some(plan) => {
+                return_with_log!(result, part_plan_to_md(&plan));
+            }
+            None => {
+                return_with_log!(result);
+            }
+        }
     }
 
     async fn get_download_url(
@@ -166,6 +283,16 @@ impl ObjectService for ObjectServiceImpl {
             "Token authentication error"
         );
 
+        let restrict_to_cidr = tonic_invalid!(
+            get_cidr_restriction_from_md(request.metadata()),
+            "Invalid CIDR restriction"
+        );
+        let content_disposition = tonic_invalid!(
+            get_content_disposition_from_md(request.metadata()),
+            "Invalid content disposition"
+        );
+        let single_use = get_single_use_from_md(request.metadata());
+        let request_payer = get_request_payer_from_md(request.metadata());
         let request = PresignedDownload(request.into_inner());
 
         let object_id = tonic_invalid!(request.get_id(), "Invalid id");
@@ -179,6 +306,9 @@ impl ObjectService for ObjectServiceImpl {
             "Unauthorized"
         );
 
+        self.check_downloadable(&object_id)?;
+        self.check_license_terms(&object_id, &user_id).await?;
+
         let signed_url = tonic_internal!(
             self.database_handler
                 .get_presigned_download(
@@ -187,10 +317,18 @@ impl ObjectService for ObjectServiceImpl {
                     request,
                     user_id,
                     token,
+                    DownloadUrlOptions {
+                        restrict_to_cidr,
+                        content_disposition,
+                        single_use,
+                        ..Default::default()
+                    },
                 )
                 .await,
             "Error while building presigned url"
         );
+        self.account_download(&object_id, &user_id, request_payer)
+            .await?;
 
         let result = GetDownloadUrlResponse { url: signed_url };
 
@@ -245,23 +383,50 @@ impl ObjectService for ObjectServiceImpl {
             return_with_log!(response);
         }
 
-        let object = tonic_internal!(
+        let (object, finalized) = match self
+            .database_handler
+            .finish_object(request, dataproxy_id)
+            .await
+        {
+            Ok(result) => result,
+            Err(err) => {
+                if err.is::<SchemaViolations>() {
+                    return Err(metadata_schema_status(err, "Internal database error."));
+                }
+                if err.is::<QuotaExceeded>() {
+                    return Err(write_error_status(err, "Internal database error."));
+                }
+                log::error!("{}", err);
+                return match err.downcast_ref::<FinishConflict>() {
+                    Some(conflict) => Err(Status::already_exists(conflict.to_string())),
+                    None => Err(Status::internal(format!(
+                        "Internal database error. : {}",
+                        err
+                    ))),
+                };
+            }
+        };
+
+        // Objects finished by a concurrent request are already cached and indexed
+        if finalized {
+            self.cache.upsert_object(&object.object.id, object.clone());
+
+            // Add or update object in search index
+            search_utils::update_search_index(
+                &self.search_client,
+                &self.cache,
+                vec![ObjectDocument::from(object.object.clone())],
+            )
+            .await;
+        }
+        // Repeated finishes sign objects whose signing failed before
+        tonic_internal!(
             self.database_handler
-                .finish_object(request, dataproxy_id)
+                .sign_manifest(&object.object.id, &self.authorizer.token_handler)
                 .await,
-            "Internal database error."
+            "Manifest signing failed"
         );
 
-        self.cache.upsert_object(&object.object.id, object.clone());
-
-        // Add or update object in search index
-        search_utils::update_search_index(
-            &self.search_client,
-            &self.cache,
-            vec![ObjectDocument::from(object.object.clone())],
-        )
-        .await;
-
         let object: generic_resource::Resource = ObjectWrapper {
             object_with_relations: object.clone(),
             rules: self
@@ -285,9 +450,10 @@ impl ObjectService for ObjectServiceImpl {
             get_token_from_md(request.metadata()),
             "Token authentication error."
         );
-        let inner = request.into_inner();
+        let mut inner = request.into_inner();
         let req = UpdateObject(inner.clone());
         let object_id = tonic_invalid!(req.get_id(), "Invalid object id.");
+        tonic_invalid!(req.validate(), "Invalid request");
 
         let ctx = Context::res_ctx(object_id, DbPermissionLevel::WRITE, true);
 
@@ -296,6 +462,44 @@ impl ObjectService for ObjectServiceImpl {
             "Unauthorized"
         );
 
+        if inner
+            .add_key_values
+            .iter()
+            .chain(inner.remove_key_values.iter())
+            .any(|kv| kv.key == EXPIRES_AT_KEY)
+        {
+            return Err(Status::failed_precondition(
+                "Expiry can only be changed with set_object_expiry",
+            ));
+        }
+        // Lifecycle states are only changed by valid transitions, which are applied after the update
+        if inner
+            .remove_key_values
+            .iter()
+            .any(|kv| kv.key == LIFECYCLE_STATE_KEY)
+        {
+            return Err(Status::failed_precondition(
+                "Lifecycle states can only be changed by transitions",
+            ));
+        }
+        let transition = inner
+            .add_key_values
+            .iter()
+            .rev()
+            .find(|kv| kv.key == LIFECYCLE_STATE_KEY)
+            .map(|kv| kv.value.clone());
+        if let Some(state) = &transition {
+            if let Err(err) = self
+                .database_handler
+                .check_lifecycle_transition(&object_id, state)
+            {
+                return Err(Status::failed_precondition(err.to_string()));
+            }
+            inner
+                .add_key_values
+                .retain(|kv| kv.key != LIFECYCLE_STATE_KEY);
+        }
+
         // Check if service account changes dataclass
         let is_service_account = self
             .cache
@@ -305,15 +509,32 @@ impl ObjectService for ObjectServiceImpl {
             .0
             .service_account;
 
-        let (object, new_revision) = tonic_internal!(
+        let (mut object, new_revision) = self
+            .database_handler
+            .update_grpc_object(inner, user_id, is_service_account)
+            .await
+            .map_err(|err| metadata_schema_status(err, "Internal database error."))?;
+        if let Some(state) = transition {
+            object = tonic_internal!(
+                self.database_handler
+                    .transition_object_state(TransitionObjectState {
+                        object_id: object.object.id.to_string(),
+                        state,
+                    })
+                    .await,
+                "Lifecycle transition failed"
+            );
+        }
+
+        self.cache.upsert_object(&object.object.id, object.clone());
+        // Changed objects below parents with a manifest policy are signed again
+        tonic_internal!(
             self.database_handler
-                .update_grpc_object(inner, user_id, is_service_account)
+                .sign_manifest(&object.object.id, &self.authorizer.token_handler)
                 .await,
-            "Internal database error."
+            "Manifest signing failed"
         );
 
-        self.cache.upsert_object(&object.object.id, object.clone());
-
         // Add or update object in search index
         search_utils::update_search_index(
             &self.search_client,
@@ -409,11 +630,18 @@ impl ObjectService for ObjectServiceImpl {
             self.authorizer.check_permissions(&token, vec![ctx]).await,
             "Unauthorized."
         );
-
-        let updates: Vec<ObjectWithRelations> = tonic_internal!(
-            self.database_handler.delete_resource(request).await,
-            "Internal database error"
-        );
+        // Admins can delete objects younger than the minimum delete age of their project
+        let is_admin = self
+            .authorizer
+            .check_permissions(&token, vec![Context::admin()])
+            .await
+            .is_ok();
+
+        let updates: Vec<ObjectWithRelations> = self
+            .database_handler
+            .delete_resource_checked(request, is_admin)
+            .await
+            .map_err(|err| delete_status(err, "Internal database error"))?;
 
         // Remove deleted resources from search index
         search_utils::remove_from_search_index(
@@ -455,7 +683,7 @@ impl ObjectService for ObjectServiceImpl {
         let res = self
             .cache
             .get_wrapped_object(&object_id)
-            .ok_or_else(|| Status::not_found("Object not found"))?;
+            .ok_or_else(|| not_found(&self.cache, "Object not found"))?;
 
         let generic_object: generic_resource::Resource = res.into();
 
@@ -477,6 +705,11 @@ impl ObjectService for ObjectServiceImpl {
             "Token authentication error"
         );
 
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let request = request.into_inner();
 
         let (ids, ctxs): (Vec<DieselUlid>, Vec<Context>) = get_id_and_ctx(request.object_ids)?;
@@ -498,9 +731,10 @@ impl ObjectService for ObjectServiceImpl {
             })
             .collect();
 
-        let response = GetObjectsResponse { objects: res? };
+        let (objects, page_info) = paginate(res?, &page, |object| object.id.clone());
+        let response = GetObjectsResponse { objects };
 
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
     async fn update_object_authors(
         &self,
@@ -572,6 +806,12 @@ impl ObjectService for ObjectServiceImpl {
             "Invalid update license request"
         );
         self.cache.add_stats_to_object(&mut object);
+        tonic_internal!(
+            self.database_handler
+                .sign_manifest(&object_id, &self.authorizer.token_handler)
+                .await,
+            "Manifest signing failed"
+        );
 
         // Add or update collection in search index
         search_utils::update_search_index(
@@ -639,3 +879,822 @@ impl ObjectService for ObjectServiceImpl {
         return_with_log!(response);
     }
 }
+
+impl ObjectServiceImpl {
+    /// Objects are blocked while they are scanned, after they were quarantined
+    /// and after they expired, even if the sweeper has not deleted them yet
+    fn check_downloadable(&self, object_id: &DieselUlid) -> Result<()> {
+        let Some(object) = self.cache.get_object(object_id) else {
+            return Ok(());
+        };
+        match object.object.object_status {
+            ObjectStatus::VALIDATING => Err(Status::failed_precondition("Object is being scanned")),
+            ObjectStatus::UNAVAILABLE => Err(Status::failed_precondition("Object is quarantined")),
+            _ if is_expired(&object.object, Utc::now().naive_utc()) => {
+                Err(Status::failed_precondition("Object expired"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Embargoed data and data with expired access terms can not be downloaded
+    /// without a license override
+    async fn check_license_terms(
+        &self,
+        object_id: &DieselUlid,
+        user_id: &DieselUlid,
+    ) -> Result<()> {
+        match self
+            .database_handler
+            .check_license_terms(object_id, user_id)
+            .await
+        {
+            Ok(()) => Ok(()),
+            Err(err) => match err.downcast_ref::<LicenseViolation>() {
+                Some(violation) => Err(Status::permission_denied(violation.to_string())),
+                None => {
+                    log::error!("{}", err);
+                    Err(Status::internal("Error while checking license terms"))
+                }
+            },
+        }
+    }
+
+    /// Requester pays objects can only be downloaded by users outside their projects
+    /// if the request acknowledged the charges
+    async fn account_download(
+        &self,
+        object_id: &DieselUlid,
+        user_id: &DieselUlid,
+        request_payer: bool,
+    ) -> Result<()> {
+        match self
+            .database_handler
+            .account_download(object_id, user_id, request_payer)
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(err) => match err.downcast_ref::<RequesterPaysNotAcknowledged>() {
+                Some(not_acknowledged) => {
+                    Err(Status::permission_denied(not_acknowledged.to_string()))
+                }
+                None => {
+                    log::error!("{}", err);
+                    Err(Status::internal("Error while accounting download"))
+                }
+            },
+        }
+    }
+
+    /// Result of a single batch entry with the url or the reason it was not created
+    async fn batch_download_result(
+        &self,
+        token: &str,
+        user_id: DieselUlid,
+        token_id: Option<DieselUlid>,
+        request_payer: bool,
+        entry: BatchDownloadUrlEntry,
+    ) -> BatchDownloadUrl {
+        let object_id = entry.object_id.clone();
+        match self
+            .batch_download_url(token, user_id, token_id, request_payer, entry)
+            .await
+        {
+            Ok(url) => BatchDownloadUrl {
+                object_id,
+                url: Some(url),
+                error: None,
+            },
+            Err(status) => BatchDownloadUrl {
+                object_id,
+                url: None,
+                error: Some(status.message().to_string()),
+            },
+        }
+    }
+
+    /// Presigned url of a single batch entry
+    async fn batch_download_url(
+        &self,
+        token: &str,
+        user_id: DieselUlid,
+        token_id: Option<DieselUlid>,
+        request_payer: bool,
+        entry: BatchDownloadUrlEntry,
+    ) -> Result<String> {
+        let request = PresignedDownload(GetDownloadUrlRequest {
+            object_id: entry.object_id,
+        });
+        let object_id = tonic_invalid!(request.get_id(), "Invalid id");
+        tonic_auth!(
+            self.authorizer
+                .check_permissions(
+                    token,
+                    vec![Context::res_ctx(object_id, DbPermissionLevel::READ, true)]
+                )
+                .await,
+            "Unauthorized"
+        );
+        self.check_downloadable(&object_id)?;
+        self.check_license_terms(&object_id, &user_id).await?;
+        let signed_url = tonic_internal!(
+            self.database_handler
+                .get_presigned_download(
+                    self.cache.clone(),
+                    self.authorizer.clone(),
+                    request,
+                    user_id,
+                    token_id,
+                    entry.options,
+                )
+                .await,
+            "Error while building presigned url"
+        );
+        self.account_download(&object_id, &user_id, request_payer)
+            .await?;
+        Ok(signed_url)
+    }
+
+    /// Creates presigned download urls for multiple objects, each with its own options.
+    /// Objects which can not be downloaded are reported in their result instead of
+    /// failing the whole batch.
+    pub async fn get_download_urls_batch(
+        &self,
+        request: Request<GetDownloadUrlsBatch>,
+    ) -> Result<Response<Vec<BatchDownloadUrl>>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request_payer = get_request_payer_from_md(request.metadata());
+        let PermissionCheck {
+            user_id,
+            token: token_id,
+            ..
+        } = tonic_auth!(
+            self.authorizer
+                .check_permissions_verbose(&token, vec![Context::registered()])
+                .await,
+            "Unauthorized"
+        );
+
+        let request = request.into_inner();
+        if request.entries.len() > MAX_DOWNLOAD_URL_BATCH_SIZE {
+            return Err(Status::invalid_argument(format!(
+                "Batch exceeds the maximum of {MAX_DOWNLOAD_URL_BATCH_SIZE} objects"
+            )));
+        }
+
+        let mut results = Vec::with_capacity(request.entries.len());
+        for entry in request.entries {
+            results.push(
+                self.batch_download_result(&token, user_id, token_id, request_payer, entry)
+                    .await,
+            );
+        }
+        return_with_log!(results);
+    }
+
+    /// Streams presigned download urls for any number of objects. Objects which can not be
+    /// downloaded are reported inline and the stream continues with the remaining objects,
+    /// the last message summarizes the succeeded and failed objects.
+    pub async fn create_download_links_stream(
+        &self,
+        request: Request<CreateDownloadLinksStream>,
+    ) -> Result<Response<ReceiverStream<Result<DownloadLinksStreamMessage>>>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request_payer = get_request_payer_from_md(request.metadata());
+        let PermissionCheck {
+            user_id,
+            token: token_id,
+            ..
+        } = tonic_auth!(
+            self.authorizer
+                .check_permissions_verbose(&token, vec![Context::registered()])
+                .await,
+            "Unauthorized"
+        );
+
+        let (tx, rx) = mpsc::channel(100);
+        let service = ObjectServiceImpl {
+            database_handler: self.database_handler.clone(),
+            authorizer: self.authorizer.clone(),
+            cache: self.cache.clone(),
+            search_client: self.search_client.clone(),
+        };
+        let entries = request.into_inner().entries;
+        tokio::spawn(async move {
+            let (mut succeeded, mut failed) = (0, 0);
+            for entry in entries {
+                let result = service
+                    .batch_download_result(&token, user_id, token_id, request_payer, entry)
+                    .await;
+                match result.url {
+                    Some(_) => succeeded += 1,
+                    None => failed += 1,
+                }
+                if tx
+                    .send(Ok(DownloadLinksStreamMessage::Link(result)))
+                    .await
+                    .is_err()
+                {
+                    log::debug!("Download links stream closed by client");
+                    return;
+                }
+            }
+            if tx
+                .send(Ok(DownloadLinksStreamMessage::Summary {
+                    succeeded,
+                    failed,
+                }))
+                .await
+                .is_err()
+            {
+                log::debug!("Download links stream closed by client");
+            }
+        });
+
+        let stream = ReceiverStream::new(rx);
+        return_with_log!(stream);
+    }
+
+    /// Deletes the listed objects or all objects below a collection and streams the result
+    /// of each object, periodic progress and a final summary. Objects under legal hold or
+    /// younger than their minimum delete age are reported as skipped.
+    pub async fn delete_objects_batch(
+        &self,
+        request: Request<DeleteObjectsBatch>,
+    ) -> Result<Response<ReceiverStream<Result<DeleteBatchStreamMessage>>>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request = request.into_inner();
+        tonic_invalid!(request.validate(), "Invalid request");
+
+        let (ids, truncated) = match &request.filter {
+            Some(filter) => {
+                let collection_id = tonic_invalid!(filter.get_id(), "Invalid collection id");
+                let ctx = Context::res_ctx(collection_id, DbPermissionLevel::ADMIN, true);
+                tonic_auth!(
+                    self.authorizer.check_permissions(&token, vec![ctx]).await,
+                    "Unauthorized"
+                );
+                let (ids, truncated) = tonic_internal!(
+                    self.database_handler
+                        .list_collection_objects(filter.clone())
+                        .await,
+                    "Listing collection objects failed"
+                );
+                (ids, truncated)
+            }
+            None => (
+                tonic_invalid!(request.get_ids(), "Invalid object id"),
+                false,
+            ),
+        };
+        // Admins can delete objects younger than the minimum delete age of their project
+        let is_admin = self
+            .authorizer
+            .check_permissions(&token, vec![Context::admin()])
+            .await
+            .is_ok();
+
+        let (tx, rx) = mpsc::channel(100);
+        let service = ObjectServiceImpl {
+            database_handler: self.database_handler.clone(),
+            authorizer: self.authorizer.clone(),
+            cache: self.cache.clone(),
+            search_client: self.search_client.clone(),
+        };
+        tokio::spawn(async move {
+            let mut progress =
+                DeleteBatchProgress::new(ids.len() as u64, DELETE_BATCH_CONFIG.progress_interval);
+            let mut permitted = Vec::with_capacity(ids.len());
+            for id in ids {
+                let ctx = Context::res_ctx(id, DbPermissionLevel::ADMIN, true);
+                if service
+                    .authorizer
+                    .check_permissions(&token, vec![ctx])
+                    .await
+                    .is_ok()
+                {
+                    permitted.push(id);
+                    continue;
+                }
+                let result = BatchDeleteResult {
+                    object_id: id.to_string(),
+                    status: BatchDeleteStatus::Failed("Unauthorized".to_string()),
+                };
+                if !send_delete_batch_result(&tx, &mut progress, result).await {
+                    return;
+                }
+            }
+
+            let mut results = service.database_handler.delete_objects_batch(
+                permitted,
+                request.with_revisions,
+                is_admin,
+            );
+            while let Some((result, deleted)) = results.next().await {
+                // Remove deleted resources from search index
+                search_utils::remove_from_search_index(&service.search_client, deleted).await;
+                if !send_delete_batch_result(&tx, &mut progress, result).await {
+                    return;
+                }
+            }
+            if tx.send(Ok(progress.summary(truncated))).await.is_err() {
+                log::debug!("Batch deletion stream closed by client");
+            }
+        });
+
+        let stream = ReceiverStream::new(rx);
+        return_with_log!(stream);
+    }
+
+    /// Returns the ids of all readable objects with the requested content.
+    pub async fn find_objects_by_hash(
+        &self,
+        request: Request<FindObjectsByHash>,
+    ) -> Result<Response<Vec<String>>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        tonic_auth!(
+            self.authorizer
+                .check_permissions(&token, vec![Context::registered()])
+                .await,
+            "Unauthorized"
+        );
+
+        let ids = tonic_invalid!(
+            self.database_handler
+                .find_objects_by_hash(request.into_inner())
+                .await,
+            "Invalid hash lookup request"
+        );
+
+        // Matches without read permissions are not disclosed
+        let mut object_ids = Vec::new();
+        for id in ids {
+            let ctx = Context::res_ctx(id, DbPermissionLevel::READ, true);
+            if self
+                .authorizer
+                .check_permissions(&token, vec![ctx])
+                .await
+                .is_ok()
+            {
+                object_ids.push(id.to_string());
+            }
+        }
+        return_with_log!(object_ids);
+    }
+
+    /// Moves an object into another state of the lifecycle of its project.
+    pub async fn transition_object_state(
+        &self,
+        request: Request<TransitionObjectState>,
+    ) -> Result<Response<Object>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request = request.into_inner();
+        let object_id = tonic_invalid!(request.get_id(), "Invalid object id");
+        let ctx = Context::res_ctx(object_id, DbPermissionLevel::WRITE, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        if let Err(err) = self
+            .database_handler
+            .check_lifecycle_transition(&object_id, &request.state)
+        {
+            return Err(Status::failed_precondition(err.to_string()));
+        }
+        let object = tonic_internal!(
+            self.database_handler.transition_object_state(request).await,
+            "Lifecycle transition failed"
+        );
+
+        search_utils::update_search_index(
+            &self.search_client,
+            &self.cache,
+            vec![ObjectDocument::from(object.object.clone())],
+        )
+        .await;
+
+        let rules = self
+            .cache
+            .get_rule_bindings(&object.object.id)
+            .unwrap_or_default();
+        let generic_resource: generic_resource::Resource = ObjectWrapper {
+            object_with_relations: object,
+            rules,
+        }
+        .into();
+        let object: Object = generic_resource.into_inner()?;
+        return_with_log!(object);
+    }
+
+    /// Returns the current lifecycle state of an object and the states it can move into.
+    pub async fn get_object_lifecycle_state(
+        &self,
+        request: Request<GetObjectLifecycleState>,
+    ) -> Result<Response<ObjectLifecycleState>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let object_id = tonic_invalid!(request.get_ref().get_id(), "Invalid object id");
+        let ctx = Context::res_ctx(object_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let state = tonic_invalid!(
+            self.database_handler.get_lifecycle_state(&object_id),
+            "No lifecycle state"
+        );
+        return_with_log!(state);
+    }
+
+    /// Sets the expiry of an object. Extending an existing expiry needs write permissions,
+    /// setting a new or earlier expiry schedules a deletion and needs admin permissions.
+    pub async fn set_object_expiry(
+        &self,
+        request: Request<SetObjectExpiry>,
+    ) -> Result<Response<Object>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request = request.into_inner();
+        let object_id = tonic_invalid!(request.get_id(), "Invalid object id");
+        let now = Utc::now().naive_utc();
+        let expires_at = tonic_invalid!(request.get_expires_at(now), "Invalid expiry");
+        let level = if tonic_invalid!(
+            self.database_handler.extends_expiry(&object_id, expires_at),
+            "Invalid object"
+        ) {
+            DbPermissionLevel::WRITE
+        } else {
+            DbPermissionLevel::ADMIN
+        };
+        let ctx = Context::res_ctx(object_id, level, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let object = match self.database_handler.set_object_expiry(request, now).await {
+            Ok(object) => object,
+            Err(err) => return Err(Status::failed_precondition(err.to_string())),
+        };
+
+        let rules = self
+            .cache
+            .get_rule_bindings(&object.object.id)
+            .unwrap_or_default();
+        let generic_resource: generic_resource::Resource = ObjectWrapper {
+            object_with_relations: object,
+            rules,
+        }
+        .into();
+        let object: Object = generic_resource.into_inner()?;
+        return_with_log!(object);
+    }
+
+    /// Creates a symlink in a collection which resolves to the latest revision of an
+    /// object in another collection, needs write permissions on the collection and read
+    /// permissions on the source.
+    pub async fn create_symlink(
+        &self,
+        request: Request<CreateSymlink>,
+    ) -> Result<Response<Collection>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request = request.into_inner();
+        let source_id = tonic_invalid!(request.get_source_id(), "Invalid source id");
+        let collection_id = tonic_invalid!(request.get_collection_id(), "Invalid collection id");
+        tonic_auth!(
+            self.authorizer
+                .check_permissions(
+                    &token,
+                    vec![
+                        Context::res_ctx(collection_id, DbPermissionLevel::WRITE, true),
+                        Context::res_ctx(source_id, DbPermissionLevel::READ, true),
+                    ]
+                )
+                .await,
+            "Unauthorized"
+        );
+
+        let collection = tonic_invalid!(
+            self.database_handler.create_symlink(request).await,
+            "Invalid symlink request"
+        );
+        return_with_log!(self.symlink_collection_response(collection)?);
+    }
+
+    /// Resolves a symlink to the latest revision of its source object
+    pub async fn resolve_symlink(&self, request: Request<GetSymlink>) -> Result<Response<Object>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let (source, _) = self
+            .resolve_symlink_source(&token, &request.into_inner())
+            .await?;
+
+        let rules = self
+            .cache
+            .get_rule_bindings(&source.object.id)
+            .unwrap_or_default();
+        let generic_resource: generic_resource::Resource = ObjectWrapper {
+            object_with_relations: source,
+            rules,
+        }
+        .into();
+        let object: Object = generic_resource.into_inner()?;
+        return_with_log!(object);
+    }
+
+    /// Creates a presigned download url of the latest revision of the symlink source
+    pub async fn get_symlink_download_url(
+        &self,
+        request: Request<GetSymlink>,
+    ) -> Result<Response<GetDownloadUrlResponse>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request_payer = get_request_payer_from_md(request.metadata());
+        let (source, PermissionCheck { user_id, token, .. }) = self
+            .resolve_symlink_source(&token, &request.into_inner())
+            .await?;
+        self.check_downloadable(&source.object.id)?;
+        self.check_license_terms(&source.object.id, &user_id)
+            .await?;
+
+        let signed_url = tonic_internal!(
+            self.database_handler
+                .get_presigned_download(
+                    self.cache.clone(),
+                    self.authorizer.clone(),
+                    PresignedDownload(GetDownloadUrlRequest {
+                        object_id: source.object.id.to_string(),
+                    }),
+                    user_id,
+                    token,
+                    DownloadUrlOptions::default(),
+                )
+                .await,
+            "Error while building presigned url"
+        );
+        self.account_download(&source.object.id, &user_id, request_payer)
+            .await?;
+
+        let result = GetDownloadUrlResponse { url: signed_url };
+        return_with_log!(result);
+    }
+
+    /// Removes a symlink from a collection, the source object is not changed
+    pub async fn delete_symlink(
+        &self,
+        request: Request<GetSymlink>,
+    ) -> Result<Response<Collection>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request = request.into_inner();
+        let collection_id = tonic_invalid!(request.get_collection_id(), "Invalid collection id");
+        let ctx = Context::res_ctx(collection_id, DbPermissionLevel::WRITE, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let collection = tonic_invalid!(
+            self.database_handler.delete_symlink(&request).await,
+            "Invalid symlink"
+        );
+        return_with_log!(self.symlink_collection_response(collection)?);
+    }
+
+    /// Reserves an object name in a collection. The returned token has to be sent with
+    /// the creation of the object, other creations of the name fail until it expires.
+    pub async fn reserve_object_name(
+        &self,
+        request: Request<ReserveObjectName>,
+    ) -> Result<Response<NameReservation>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request = request.into_inner();
+        let collection_id = tonic_invalid!(request.get_id(), "Invalid collection id");
+        let ctx = Context::res_ctx(collection_id, DbPermissionLevel::APPEND, true);
+        let user_id = tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let reservation = match self
+            .database_handler
+            .reserve_object_name(request, user_id)
+            .await
+        {
+            Ok(reservation) => reservation,
+            Err(err) => return Err(Status::already_exists(err.to_string())),
+        };
+        return_with_log!(reservation);
+    }
+
+    /// Releases a name reservation before it expires
+    pub async fn release_object_name(
+        &self,
+        request: Request<ReleaseObjectName>,
+    ) -> Result<Response<()>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request = request.into_inner();
+        let reservation = match self.database_handler.get_name_reservation(&request).await {
+            Ok(reservation) => reservation,
+            Err(err) => return Err(Status::not_found(err.to_string())),
+        };
+        let ctx = Context::res_ctx(reservation.parent_id, DbPermissionLevel::APPEND, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        tonic_internal!(
+            self.database_handler
+                .release_object_name(&reservation)
+                .await,
+            "Error while releasing name reservation"
+        );
+        return_with_log!(());
+    }
+
+    /// Returns the preview object of an object, previews are generated by the built-in
+    /// preview hook after the object is finished
+    pub async fn get_preview(&self, request: Request<GetPreview>) -> Result<Response<Object>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request = request.into_inner();
+        let object_id = tonic_invalid!(request.get_id(), "Invalid object id");
+        let ctx = Context::res_ctx(object_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let preview = match self.database_handler.get_preview(request).await {
+            Ok(preview) => preview,
+            Err(err) => return Err(Status::not_found(err.to_string())),
+        };
+        let rules = self
+            .cache
+            .get_rule_bindings(&preview.object.id)
+            .unwrap_or_default();
+        let generic_resource: generic_resource::Resource = ObjectWrapper {
+            object_with_relations: preview,
+            rules,
+        }
+        .into();
+        let object: Object = generic_resource.into_inner()?;
+        return_with_log!(object);
+    }
+
+    /// Returns the latest signed manifest of an object. The signature can be verified
+    /// with the server public key of the manifest's key serial.
+    pub async fn get_signed_manifest(
+        &self,
+        request: Request<GetSignedManifest>,
+    ) -> Result<Response<SignedManifest>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request = request.into_inner();
+        let object_id = tonic_invalid!(request.get_id(), "Invalid object id");
+        let ctx = Context::res_ctx(object_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let manifest = match self.database_handler.get_signed_manifest(request).await {
+            Ok(manifest) => manifest,
+            Err(err) => return Err(Status::not_found(err.to_string())),
+        };
+        return_with_log!(manifest);
+    }
+
+    /// Resolves a symlink visible to the token, the token also needs read permissions
+    /// on the source itself
+    async fn resolve_symlink_source(
+        &self,
+        token: &str,
+        request: &GetSymlink,
+    ) -> Result<(ObjectWithRelations, PermissionCheck)> {
+        let collection_id = tonic_invalid!(request.get_collection_id(), "Invalid collection id");
+        let ctx = Context::res_ctx(collection_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let (_, source) = match self.database_handler.resolve_symlink(request).await {
+            Ok(resolved) => resolved,
+            Err(err) => return Err(Status::not_found(err.to_string())),
+        };
+        let ctx = Context::res_ctx(source.object.id, DbPermissionLevel::READ, true);
+        let check = tonic_auth!(
+            self.authorizer
+                .check_permissions_verbose(token, vec![ctx])
+                .await,
+            "Unauthorized"
+        );
+        Ok((source, check))
+    }
+
+    fn symlink_collection_response(
+        &self,
+        mut collection: ObjectWithRelations,
+    ) -> Result<Collection> {
+        self.cache.add_stats_to_object(&mut collection);
+        let rules = self
+            .cache
+            .get_rule_bindings(&collection.object.id)
+            .unwrap_or_default();
+        let generic_resource: generic_resource::Resource = ObjectWrapper {
+            object_with_relations: collection,
+            rules,
+        }
+        .into();
+        generic_resource.into_inner()
+    }
+}
+
+/// Sends the result of a batch deletion and the progress it completes,
+/// returns false if the client closed the stream
+async fn send_delete_batch_result(
+    tx: &mpsc::Sender<Result<DeleteBatchStreamMessage>>,
+    progress: &mut DeleteBatchProgress,
+    result: BatchDeleteResult,
+) -> bool {
+    let update = progress.record(&result);
+    for message in std::iter::once(DeleteBatchStreamMessage::Result(result)).chain(update) {
+        if tx.send(Ok(message)).await.is_err() {
+            log::debug!("Batch deletion stream closed by client");
+            return false;
+        }
+    }
+    true
+}
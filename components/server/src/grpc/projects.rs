@@ -2,19 +2,36 @@ use crate::auth::permission_handler::{PermissionCheck, PermissionHandler};
 use crate::auth::structs::Context;
 use crate::caching::cache::Cache;
 use crate::caching::structs::ObjectWrapper;
-use crate::database::enums::DbPermissionLevel;
+use crate::database::enums::{DataClass, DbPermissionLevel};
+use crate::middlelayer::access_policy_request_types::{GetAccessPolicy, SetAccessPolicy};
 use crate::middlelayer::create_request_types::CreateRequest;
 use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::integrity_request_types::{CheckIntegrity, IntegrityReport};
+use crate::middlelayer::lifecycle_request_types::{Lifecycle, LIFECYCLE_KEY};
+use crate::middlelayer::metadata_schema_request_types::{GetMetadataSchema, SetMetadataSchema};
+use crate::middlelayer::notification_retention_request_types::{
+    GetNotificationRetention, NotificationRetentionSettings,
+};
+use crate::middlelayer::publication_request_types::{
+    publication_needs_admin, DecidePublication, RequestPublication, PUBLICATION_REQUIRES_APPROVAL,
+    PUBLICATION_STATE_KEY,
+};
+use crate::middlelayer::relations_db_handler::MAX_RELATIONS_KEY;
 use crate::middlelayer::snapshot_request_types::SnapshotRequest;
 use crate::middlelayer::update_request_types::{
     DataClassUpdate, DescriptionUpdate, KeyValueUpdate, LicenseUpdate, NameUpdate, UpdateAuthor,
-    UpdateTitle,
+    UpdateProjectDefaultEndpoint, UpdateTitle,
 };
 use crate::search::meilisearch_client::{MeilisearchClient, ObjectDocument};
-use crate::utils::grpc_utils::get_token_from_md;
-use crate::utils::grpc_utils::{get_id_and_ctx, query, IntoGenericInner};
-
-use crate::database::dsls::object_dsl::ObjectWithRelations;
+use crate::utils::grpc_utils::{delete_status, get_id_and_ctx, query, IntoGenericInner};
+use crate::utils::grpc_utils::{get_page_request_from_md, get_token_from_md, page_info_to_md};
+use crate::utils::name_utils::{NameNormalization, NAME_NORMALIZATION_KEY};
+use crate::utils::pagination_utils::paginate;
+
+use crate::database::dsls::access_policy_dsl::AccessPolicy;
+use crate::database::dsls::metadata_schema_dsl::MetadataSchema;
+use crate::database::dsls::object_dsl::{ObjectWithRelations, ENFORCE_ENCRYPTION_KEY};
+use crate::database::dsls::publication_request_dsl::PublicationRequest;
 use crate::middlelayer::delete_request_types::DeleteRequest;
 use crate::utils::search_utils;
 use aruna_rust_api::api::storage::models::v2::{generic_resource, Project};
@@ -33,7 +50,7 @@ use diesel_ulid::DieselUlid;
 use itertools::Itertools;
 use std::str::FromStr;
 use std::sync::Arc;
-use tonic::{Request, Response, Result};
+use tonic::{Request, Response, Result, Status};
 
 crate::impl_grpc_server!(ProjectServiceImpl, search_client: Arc<MeilisearchClient>, default_endpoint: String);
 
@@ -48,6 +65,7 @@ impl ProjectService for ProjectServiceImpl {
         // Consume gRPC request into its parts
         let (request_metadata, _, inner_request) = request.into_parts();
         let request = CreateRequest::Project(inner_request, self.default_endpoint.clone());
+        tonic_invalid!(request.validate(), "Invalid request");
 
         // Extract token from request and check permissions
         let token = tonic_auth!(
@@ -60,6 +78,34 @@ impl ProjectService for ProjectServiceImpl {
         let mut ctx = Context::registered();
         ctx.allow_service_account = false;
         ctxs.push(ctx);
+        if publication_needs_admin(request.get_data_class()) {
+            ctxs.push(Context::admin());
+        }
+        // Relation maximums can only be raised by global admins
+        if request
+            .get_key_values()
+            .iter()
+            .any(|kv| kv.key == MAX_RELATIONS_KEY)
+        {
+            ctxs.push(Context::admin());
+        }
+        for kv in request
+            .get_key_values()
+            .iter()
+            .filter(|kv| kv.key == LIFECYCLE_KEY)
+        {
+            tonic_invalid!(Lifecycle::from_str(&kv.value), "Invalid lifecycle");
+        }
+        for kv in request
+            .get_key_values()
+            .iter()
+            .filter(|kv| kv.key == NAME_NORMALIZATION_KEY)
+        {
+            tonic_invalid!(
+                NameNormalization::from_str(&kv.value),
+                "Invalid name normalization"
+            );
+        }
 
         let PermissionCheck {
             user_id,
@@ -152,6 +198,11 @@ impl ProjectService for ProjectServiceImpl {
             "Token authentication error"
         );
 
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let request = request.into_inner();
 
         let (ids, ctxs): (Vec<DieselUlid>, Vec<Context>) = get_id_and_ctx(request.project_ids)?;
@@ -166,9 +217,10 @@ impl ProjectService for ProjectServiceImpl {
             .map(|id| -> Result<Project> { query(&self.cache, id)?.into_inner() })
             .collect();
 
-        let response = GetProjectsResponse { projects: res? };
+        let (projects, page_info) = paginate(res?, &page, |resource| resource.id.clone());
+        let response = GetProjectsResponse { projects };
 
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
 
     async fn delete_project(
@@ -191,11 +243,18 @@ impl ProjectService for ProjectServiceImpl {
             self.authorizer.check_permissions(&token, vec![ctx]).await,
             "Unauthorized."
         );
-
-        let updates: Vec<ObjectWithRelations> = tonic_internal!(
-            self.database_handler.delete_resource(request).await,
-            "Internal database error"
-        );
+        // Admins can delete objects younger than the minimum delete age of their project
+        let is_admin = self
+            .authorizer
+            .check_permissions(&token, vec![Context::admin()])
+            .await
+            .is_ok();
+
+        let updates: Vec<ObjectWithRelations> = self
+            .database_handler
+            .delete_resource_checked(request, is_admin)
+            .await
+            .map_err(|err| delete_status(err, "Internal database error"))?;
 
         // Remove deleted resources from search index
         search_utils::remove_from_search_index(
@@ -219,6 +278,7 @@ impl ProjectService for ProjectServiceImpl {
         );
 
         let request = NameUpdate::Project(request.into_inner());
+        tonic_invalid!(request.get_name(), "Invalid name");
         let project_id = tonic_invalid!(request.get_id(), "Invalid project id");
         let ctx = Context::res_ctx(project_id, DbPermissionLevel::ADMIN, false);
 
@@ -270,6 +330,7 @@ impl ProjectService for ProjectServiceImpl {
         );
 
         let request = DescriptionUpdate::Project(request.into_inner());
+        tonic_invalid!(request.validate(), "Invalid description");
         let project_id = tonic_invalid!(request.get_id(), "Invalid project id");
         let ctx = Context::res_ctx(project_id, DbPermissionLevel::WRITE, true);
 
@@ -322,10 +383,43 @@ impl ProjectService for ProjectServiceImpl {
 
         let request = KeyValueUpdate::Project(request.into_inner());
         let project_id = tonic_invalid!(request.get_id(), "Invalid project id");
-        let ctx = Context::res_ctx(project_id, DbPermissionLevel::WRITE, true);
+        // Encryption policy, lifecycle and name normalization of the project can only be
+        // changed by project admins
+        let level = if request.contains_key(ENFORCE_ENCRYPTION_KEY)
+            || request.contains_key(LIFECYCLE_KEY)
+            || request.contains_key(NAME_NORMALIZATION_KEY)
+        {
+            DbPermissionLevel::ADMIN
+        } else {
+            DbPermissionLevel::WRITE
+        };
+        let mut ctxs = vec![Context::res_ctx(project_id, level, true)];
+        // Relation maximums can only be raised and publication states only be changed by global admins
+        if request.contains_key(MAX_RELATIONS_KEY) || request.contains_key(PUBLICATION_STATE_KEY) {
+            ctxs.push(Context::admin());
+        }
+        if let KeyValueUpdate::Project(req) = &request {
+            for kv in req
+                .add_key_values
+                .iter()
+                .filter(|kv| kv.key == LIFECYCLE_KEY)
+            {
+                tonic_invalid!(Lifecycle::from_str(&kv.value), "Invalid lifecycle");
+            }
+            for kv in req
+                .add_key_values
+                .iter()
+                .filter(|kv| kv.key == NAME_NORMALIZATION_KEY)
+            {
+                tonic_invalid!(
+                    NameNormalization::from_str(&kv.value),
+                    "Invalid name normalization"
+                );
+            }
+        }
 
         tonic_auth!(
-            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            self.authorizer.check_permissions(&token, ctxs).await,
             "Unauthorized"
         );
 
@@ -344,6 +438,9 @@ impl ProjectService for ProjectServiceImpl {
             vec![ObjectDocument::from(project.object.clone())],
         )
         .await;
+        // Propagate changed labels to the effective labels of all subresources
+        search_utils::update_inherited_labels(&self.search_client, &self.cache, &project.object.id)
+            .await;
 
         let project: generic_resource::Resource = ObjectWrapper {
             object_with_relations: project.clone(),
@@ -374,10 +471,17 @@ impl ProjectService for ProjectServiceImpl {
         let request = DataClassUpdate::Project(request.into_inner());
         let project_id = tonic_invalid!(request.get_id(), "Invalid project id");
         // Project dataclass cannot be changed by service accounts/ non-admins
-        let ctx = Context::res_ctx(project_id, DbPermissionLevel::ADMIN, false);
+        let mut ctxs = vec![Context::res_ctx(
+            project_id,
+            DbPermissionLevel::ADMIN,
+            false,
+        )];
+        if publication_needs_admin(request.get_raw_dataclass()) {
+            ctxs.push(Context::admin());
+        }
 
         tonic_auth!(
-            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            self.authorizer.check_permissions(&token, ctxs).await,
             "Unauthorized"
         );
 
@@ -604,3 +708,365 @@ impl ProjectService for ProjectServiceImpl {
         return_with_log!(response);
     }
 }
+
+impl ProjectServiceImpl {
+    /// Sets or unsets the endpoint new objects of the project are uploaded to.
+    pub async fn update_project_default_endpoint(
+        &self,
+        request: Request<UpdateProjectDefaultEndpoint>,
+    ) -> Result<Response<Project>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error."
+        );
+
+        let request = request.into_inner();
+        let project_id = tonic_invalid!(request.get_id(), "Invalid project id.");
+        let ctx = Context::res_ctx(project_id, DbPermissionLevel::ADMIN, false);
+
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let mut project = tonic_invalid!(
+            self.database_handler.update_default_endpoint(request).await,
+            "Invalid update default endpoint request"
+        );
+        self.cache
+            .upsert_object(&project.object.id, project.clone());
+        self.cache.add_stats_to_object(&mut project);
+
+        let generic_resource: generic_resource::Resource = ObjectWrapper {
+            object_with_relations: project.clone(),
+            rules: self
+                .cache
+                .get_rule_bindings(&project.object.id)
+                .unwrap_or_default(),
+        }
+        .into();
+        let response: Project = generic_resource.into_inner()?;
+        return_with_log!(response);
+    }
+
+    /// Requests to make a project, collection or dataset public. If the instance requires
+    /// approval the resource stays private and is labeled as pending until a global admin decides.
+    pub async fn request_publication(
+        &self,
+        request: Request<RequestPublication>,
+    ) -> Result<Response<PublicationRequest>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error."
+        );
+
+        let request = request.into_inner();
+        let resource_id = tonic_invalid!(request.get_id(), "Invalid resource id.");
+        let ctx = Context::res_ctx(resource_id, DbPermissionLevel::ADMIN, false);
+        let user_id = tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let (publication, resource) = tonic_invalid!(
+            self.database_handler
+                .request_publication(request, user_id, *PUBLICATION_REQUIRES_APPROVAL)
+                .await,
+            "Invalid publication request"
+        );
+        if resource.object.data_class == DataClass::PUBLIC {
+            tonic_internal!(
+                self.database_handler
+                    .sign_published_manifests(&resource.object.id, &self.authorizer.token_handler)
+                    .await,
+                "Manifest signing failed"
+            );
+        }
+
+        search_utils::update_search_index(
+            &self.search_client,
+            &self.cache,
+            vec![ObjectDocument::from(resource.object)],
+        )
+        .await;
+
+        return_with_log!(publication);
+    }
+
+    /// Approves or rejects a pending publication request, rejections need a reason.
+    pub async fn decide_publication(
+        &self,
+        request: Request<DecidePublication>,
+    ) -> Result<Response<PublicationRequest>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error."
+        );
+
+        let user_id = tonic_auth!(
+            self.authorizer
+                .check_permissions(&token, vec![Context::admin()])
+                .await,
+            "Unauthorized"
+        );
+
+        let (publication, resource) = tonic_invalid!(
+            self.database_handler
+                .decide_publication(request.into_inner(), user_id)
+                .await,
+            "Invalid publication decision"
+        );
+        if resource.object.data_class == DataClass::PUBLIC {
+            tonic_internal!(
+                self.database_handler
+                    .sign_published_manifests(&resource.object.id, &self.authorizer.token_handler)
+                    .await,
+                "Manifest signing failed"
+            );
+        }
+
+        search_utils::update_search_index(
+            &self.search_client,
+            &self.cache,
+            vec![ObjectDocument::from(resource.object)],
+        )
+        .await;
+
+        return_with_log!(publication);
+    }
+
+    /// Scans a project for orphans, dangling relations and objects without stored data.
+    /// The scan is read-only unless repair is requested, which moves orphans into a
+    /// lost-and-found collection and removes dangling relations.
+    pub async fn check_integrity(
+        &self,
+        request: Request<CheckIntegrity>,
+    ) -> Result<Response<IntegrityReport>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error."
+        );
+
+        let user_id = tonic_auth!(
+            self.authorizer
+                .check_permissions(&token, vec![Context::admin()])
+                .await,
+            "Unauthorized"
+        );
+
+        let report = tonic_invalid!(
+            self.database_handler
+                .check_integrity(request.into_inner(), user_id)
+                .await,
+            "Integrity check failed"
+        );
+
+        if let Some(lost_and_found) = report
+            .lost_and_found
+            .and_then(|id| self.cache.get_object(&id))
+        {
+            search_utils::update_search_index(
+                &self.search_client,
+                &self.cache,
+                vec![ObjectDocument::from(lost_and_found.object)],
+            )
+            .await;
+        }
+
+        return_with_log!(report);
+    }
+
+    /// Replaces the metadata schema of a project. New, finished and updated objects
+    /// have to carry the required labels with allowed values, existing objects stay valid.
+    pub async fn set_metadata_schema(
+        &self,
+        request: Request<SetMetadataSchema>,
+    ) -> Result<Response<MetadataSchema>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error."
+        );
+        let request = request.into_inner();
+        let project_id = tonic_invalid!(request.get_id(), "Invalid project id");
+        let ctx = Context::res_ctx(project_id, DbPermissionLevel::ADMIN, true);
+        let user_id = tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let schema = tonic_invalid!(
+            self.database_handler
+                .set_metadata_schema(request, user_id)
+                .await,
+            "Invalid metadata schema"
+        );
+        return_with_log!(schema);
+    }
+
+    /// Returns the metadata schema of a project
+    pub async fn get_metadata_schema(
+        &self,
+        request: Request<GetMetadataSchema>,
+    ) -> Result<Response<MetadataSchema>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error."
+        );
+        let request = request.into_inner();
+        let project_id = tonic_invalid!(request.get_id(), "Invalid project id");
+        let ctx = Context::res_ctx(project_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let schema = match self.database_handler.get_metadata_schema(&request).await {
+            Ok(schema) => schema,
+            Err(err) => return Err(Status::not_found(err.to_string())),
+        };
+        return_with_log!(schema);
+    }
+
+    /// Removes the metadata schema of a project
+    pub async fn delete_metadata_schema(
+        &self,
+        request: Request<GetMetadataSchema>,
+    ) -> Result<Response<()>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error."
+        );
+        let request = request.into_inner();
+        let project_id = tonic_invalid!(request.get_id(), "Invalid project id");
+        let ctx = Context::res_ctx(project_id, DbPermissionLevel::ADMIN, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        if let Err(err) = self.database_handler.delete_metadata_schema(&request).await {
+            return Err(Status::not_found(err.to_string()));
+        }
+        return_with_log!(());
+    }
+
+    /// Replaces the access policy of a project. Its rules allow or deny requests based on
+    /// attributes of users and labels of resources in addition to the role-based permissions.
+    pub async fn set_access_policy(
+        &self,
+        request: Request<SetAccessPolicy>,
+    ) -> Result<Response<AccessPolicy>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error."
+        );
+        let request = request.into_inner();
+        let project_id = tonic_invalid!(request.get_id(), "Invalid project id");
+        let ctx = Context::res_ctx(project_id, DbPermissionLevel::ADMIN, true);
+        let user_id = tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let policy = tonic_invalid!(
+            self.database_handler
+                .set_access_policy(request, user_id)
+                .await,
+            "Invalid access policy"
+        );
+        return_with_log!(policy);
+    }
+
+    /// Returns the access policy of a project
+    pub async fn get_access_policy(
+        &self,
+        request: Request<GetAccessPolicy>,
+    ) -> Result<Response<AccessPolicy>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error."
+        );
+        let request = request.into_inner();
+        let project_id = tonic_invalid!(request.get_id(), "Invalid project id");
+        let ctx = Context::res_ctx(project_id, DbPermissionLevel::ADMIN, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let policy = match self.database_handler.get_access_policy(&request).await {
+            Ok(policy) => policy,
+            Err(err) => return Err(Status::not_found(err.to_string())),
+        };
+        return_with_log!(policy);
+    }
+
+    /// Removes the access policy of a project
+    pub async fn delete_access_policy(
+        &self,
+        request: Request<GetAccessPolicy>,
+    ) -> Result<Response<()>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error."
+        );
+        let request = request.into_inner();
+        let project_id = tonic_invalid!(request.get_id(), "Invalid project id");
+        let ctx = Context::res_ctx(project_id, DbPermissionLevel::ADMIN, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        if let Err(err) = self.database_handler.delete_access_policy(&request).await {
+            return Err(Status::not_found(err.to_string()));
+        }
+        return_with_log!(());
+    }
+
+    /// Returns the retention of persistent notifications referencing resources of a project
+    pub async fn get_notification_retention(
+        &self,
+        request: Request<GetNotificationRetention>,
+    ) -> Result<Response<NotificationRetentionSettings>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error."
+        );
+        let request = request.into_inner();
+        let project_id = tonic_invalid!(request.get_id(), "Invalid project id");
+        let ctx = Context::res_ctx(project_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let settings = match self.database_handler.get_notification_retention(request) {
+            Ok(settings) => settings,
+            Err(err) => return Err(Status::not_found(err.to_string())),
+        };
+        return_with_log!(settings);
+    }
+}
@@ -1,12 +1,19 @@
 use crate::auth::permission_handler::PermissionHandler;
 use crate::auth::structs::Context;
 use crate::caching::cache::Cache;
+use crate::database::dsls::internal_relation_dsl::InternalRelation;
+use crate::database::dsls::provenance_dsl::Provenance;
+use crate::database::dsls::relation_type_dsl::RelationType;
 use crate::database::enums::DbPermissionLevel;
 use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::provenance_request_types::{GetLineage, Lineage, RecordProvenance};
+use crate::middlelayer::relation_type_request_types::{
+    CreateRelationType, DeleteRelationType, GetRelationTypes, GetRelationsByType,
+};
 use crate::middlelayer::relations_request_types::ModifyRelations;
 use crate::search::meilisearch_client::MeilisearchClient;
 use crate::search::meilisearch_client::ObjectDocument;
-use crate::utils::grpc_utils::get_token_from_md;
+use crate::utils::grpc_utils::{get_token_from_md, write_error_status};
 use crate::utils::search_utils;
 use aruna_rust_api::api::storage::services::v2::relations_service_server::RelationsService;
 use aruna_rust_api::api::storage::services::v2::GetHierarchyRequest;
@@ -51,16 +58,15 @@ impl RelationsService for RelationsServiceImpl {
             "Unauthorized"
         );
 
-        let object = tonic_internal!(
-            self.database_handler
-                .modify_relations(
-                    resource,
-                    labels_info.relations_to_add,
-                    labels_info.relations_to_remove
-                )
-                .await,
-            "Database error"
-        );
+        let object = self
+            .database_handler
+            .modify_relations(
+                resource,
+                labels_info.relations_to_add,
+                labels_info.relations_to_remove,
+            )
+            .await
+            .map_err(|err| write_error_status(err, "Database error"))?;
 
         self.cache.upsert_object(&object.object.id, object.clone());
 
@@ -104,3 +110,186 @@ impl RelationsService for RelationsServiceImpl {
         return_with_log!(result);
     }
 }
+
+impl RelationsServiceImpl {
+    /// Records the source objects and the compute task an object was derived from.
+    /// Compute tasks report their results with it after the derived object was created.
+    pub async fn record_provenance(
+        &self,
+        request: tonic::Request<RecordProvenance>,
+    ) -> Result<tonic::Response<Provenance>, tonic::Status> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let request = request.into_inner();
+        let derived_id = tonic_invalid!(request.get_derived_id(), "Invalid derived object id");
+        let source_ids = tonic_invalid!(request.get_source_ids(), "Invalid source object ids");
+        let mut ctxs = vec![Context::res_ctx(derived_id, DbPermissionLevel::WRITE, true)];
+        ctxs.extend(
+            source_ids
+                .into_iter()
+                .map(|id| Context::res_ctx(id, DbPermissionLevel::READ, true)),
+        );
+        let user_id = tonic_auth!(
+            self.authorizer.check_permissions(&token, ctxs).await,
+            "Unauthorized"
+        );
+
+        let provenance = tonic_invalid!(
+            self.database_handler
+                .record_provenance(request, user_id)
+                .await,
+            "Invalid provenance"
+        );
+        return_with_log!(provenance);
+    }
+
+    /// Returns the upstream derivation graph of an object. Deleted objects of the
+    /// graph are returned as tombstones.
+    pub async fn get_lineage(
+        &self,
+        request: tonic::Request<GetLineage>,
+    ) -> Result<tonic::Response<Lineage>, tonic::Status> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let request = request.into_inner();
+        let object_id = tonic_invalid!(request.get_id(), "Invalid object id");
+        let ctx = Context::res_ctx(object_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let lineage = tonic_invalid!(
+            self.database_handler.get_lineage(request).await,
+            "Invalid lineage request"
+        );
+        return_with_log!(lineage);
+    }
+
+    /// Defines a custom relation type for the resources of a project.
+    pub async fn create_relation_type(
+        &self,
+        request: tonic::Request<CreateRelationType>,
+    ) -> Result<tonic::Response<RelationType>, tonic::Status> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let request = request.into_inner();
+        let project_id = tonic_invalid!(request.get_project_id(), "Invalid project id");
+        let ctx = Context::res_ctx(project_id, DbPermissionLevel::ADMIN, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let relation_type = tonic_invalid!(
+            self.database_handler.create_relation_type(request).await,
+            "Invalid relation type"
+        );
+        return_with_log!(relation_type);
+    }
+
+    /// Deletes a custom relation type. Types which are still used are rejected unless
+    /// the request cascades to their relations.
+    pub async fn delete_relation_type(
+        &self,
+        request: tonic::Request<DeleteRelationType>,
+    ) -> Result<tonic::Response<RelationType>, tonic::Status> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let request = request.into_inner();
+        let relation_type = tonic_invalid!(
+            self.database_handler.get_relation_type(&request.name).await,
+            "Invalid relation type"
+        );
+        let Some(project_id) = relation_type.project_id else {
+            return Err(tonic::Status::invalid_argument(
+                "Built-in relation types can not be deleted",
+            ));
+        };
+        let ctx = Context::res_ctx(project_id, DbPermissionLevel::ADMIN, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let relation_type = tonic_invalid!(
+            self.database_handler.delete_relation_type(request).await,
+            "Relation type not deleted"
+        );
+        return_with_log!(relation_type);
+    }
+
+    /// Lists the built-in and the custom relation types of a project.
+    pub async fn get_relation_types(
+        &self,
+        request: tonic::Request<GetRelationTypes>,
+    ) -> Result<tonic::Response<Vec<RelationType>>, tonic::Status> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let request = request.into_inner();
+        let project_id = tonic_invalid!(request.get_project_id(), "Invalid project id");
+        let ctx = Context::res_ctx(project_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let relation_types = tonic_internal!(
+            self.database_handler.get_relation_types(request).await,
+            "Database error"
+        );
+        return_with_log!(relation_types);
+    }
+
+    /// Returns the inbound and outbound relations of a type of a resource.
+    pub async fn get_relations_by_type(
+        &self,
+        request: tonic::Request<GetRelationsByType>,
+    ) -> Result<tonic::Response<Vec<InternalRelation>>, tonic::Status> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let request = request.into_inner();
+        let resource_id = tonic_invalid!(request.get_resource_id(), "Invalid resource id");
+        let ctx = Context::res_ctx(resource_id, DbPermissionLevel::READ, true);
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let relations = tonic_invalid!(
+            self.database_handler.get_relations_by_type(request),
+            "Invalid resource"
+        );
+        return_with_log!(relations);
+    }
+}
@@ -6,7 +6,8 @@ use crate::middlelayer::db_handler::DatabaseHandler;
 use crate::middlelayer::rule_request_types::{
     CreateRule, CreateRuleBinding, DeleteRule, DeleteRuleBinding, UpdateRule,
 };
-use crate::utils::grpc_utils::get_token_from_md;
+use crate::utils::grpc_utils::{get_page_request_from_md, get_token_from_md, page_info_to_md};
+use crate::utils::pagination_utils::paginate;
 use aruna_rust_api::api::storage::services::v2::{
     rules_service_server::RulesService, CreateRuleResponse, Rule,
 };
@@ -109,11 +110,17 @@ impl RulesService for RuleServiceImpl {
     }
     async fn list_rule(
         &self,
-        _request: Request<ListRuleRequest>,
+        request: Request<ListRuleRequest>,
     ) -> Result<Response<ListRuleResponse>> {
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let rules = self.cache.list_rules();
+        let (rules, page_info) = paginate(rules, &page, |rule| rule.id.clone());
         let response = ListRuleResponse { rules };
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
     async fn update_rule(
         &self,
@@ -28,8 +28,11 @@ use crate::database::dsls::rule_dsl::RuleBinding;
 use crate::{
     auth::structs::Context,
     middlelayer::db_handler::DatabaseHandler,
-    search::meilisearch_client::{MeilisearchClient, MeilisearchIndexes, ObjectDocument},
-    utils::grpc_utils::get_token_from_md,
+    search::meilisearch_client::{
+        include_inherited_labels, MeilisearchClient, MeilisearchIndexes, ObjectDocument,
+        ReindexProgress,
+    },
+    utils::grpc_utils::{get_inherited_labels_from_md, get_token_from_md},
 };
 
 crate::impl_grpc_server!(SearchServiceImpl, search_client: Arc<MeilisearchClient>);
@@ -44,7 +47,7 @@ impl SearchService for SearchServiceImpl {
         log_received!(&request);
 
         // Consumer gRPC request into its parts
-        let (_, _, inner_request) = request.into_parts();
+        let (metadata, _, inner_request) = request.into_parts();
 
         // NO AUTHORIZATION:
         // Search results are always redacted for PRIVATE
@@ -56,19 +59,45 @@ impl SearchService for SearchServiceImpl {
             return Err(Status::invalid_argument("Limit must be between 1 and 100"));
         }
 
+        // Fail fast while the search index is unavailable
+        if !self.search_client.is_available() {
+            return Err(Status::unavailable(
+                "Search is temporarily unavailable, please try again later",
+            ));
+        }
+
+        // Label filters optionally match labels inherited from ancestors
+        let filter = if get_inherited_labels_from_md(&metadata) {
+            include_inherited_labels(&inner_request.filter)
+        } else {
+            inner_request.filter.clone()
+        };
+
         // Search meilisearch index
-        let (objects, estimated_total) = tonic_internal!(
-            self.search_client
-                .query_generic_stuff::<ObjectDocument>(
-                    &MeilisearchIndexes::OBJECT.to_string(), // Currently only one index is used for all resources
-                    &inner_request.query,
-                    &inner_request.filter,
-                    inner_request.limit as usize,
-                    inner_request.offset as usize,
-                )
-                .await,
-            "Query search failed"
-        );
+        let (objects, estimated_total) = match self
+            .search_client
+            .query_generic_stuff::<ObjectDocument>(
+                &MeilisearchIndexes::OBJECT.to_string(), // Currently only one index is used for all resources
+                &inner_request.query,
+                &filter,
+                inner_request.limit as usize,
+                inner_request.offset as usize,
+            )
+            .await
+        {
+            Ok(result) => result,
+            Err(err) if MeilisearchClient::is_connection_error(&err) => {
+                log::error!("{}", err);
+                self.search_client.set_available(false);
+                return Err(Status::unavailable(
+                    "Search is temporarily unavailable, please try again later",
+                ));
+            }
+            Err(err) => {
+                log::error!("{}", err);
+                return Err(Status::internal(format!("Query search failed : {}", err)));
+            }
+        };
 
         // Convert search to proto resources
         let mut proto_resources = vec![];
@@ -422,3 +451,29 @@ impl SearchService for SearchServiceImpl {
         return_with_log!(response);
     }
 }
+
+impl SearchServiceImpl {
+    /// Returns the progress of the running full reindex of the search index, if any.
+    pub async fn get_reindex_progress(
+        &self,
+        request: tonic::Request<()>,
+    ) -> tonic::Result<tonic::Response<Option<ReindexProgress>>> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+
+        let ctx = Context::admin();
+        tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let progress = self
+            .search_client
+            .reindex_progress(&MeilisearchIndexes::OBJECT.to_string());
+        return_with_log!(progress);
+    }
+}
@@ -17,8 +17,11 @@ use crate::middlelayer::service_account_request_types::{
 };
 use crate::middlelayer::user_request_types::DeleteProxyAttributeSource;
 use crate::utils::conversions::users::convert_token_to_proto;
+use crate::utils::grpc_utils::{get_page_request_from_md, page_info_to_md};
+use crate::utils::pagination_utils::paginate;
 use crate::{auth::permission_handler::PermissionHandler, utils::grpc_utils::get_token_from_md};
 use aruna_rust_api::api::storage::models::v2::context::Context as ProtoContext;
+use aruna_rust_api::api::storage::models::v2::Token;
 use aruna_rust_api::api::storage::services::v2::{
     service_account_service_server::ServiceAccountService, AddDataProxyAttributeUserRequest,
     CreateDataproxyTokenSvcAccountRequest, CreateDataproxyTokenSvcAccountResponse,
@@ -172,6 +175,11 @@ impl ServiceAccountService for ServiceAccountServiceImpl {
             get_token_from_md(request.metadata()),
             "Token authentication error"
         );
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let client = tonic_internal!(
             self.database_handler.database.get_client().await,
             "Could not create database client"
@@ -199,8 +207,9 @@ impl ServiceAccountService for ServiceAccountServiceImpl {
                 convert_token_to_proto(id, token.clone())
             })
             .collect();
+        let (tokens, page_info) = paginate(tokens, &page, |token: &Token| token.id.clone());
         let response = GetServiceAccountTokensResponse { tokens };
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
 
     async fn delete_service_account_token(
@@ -567,10 +576,7 @@ impl ServiceAccountService for ServiceAccountServiceImpl {
         );
         let ProcessedToken {
             main_id, is_proxy, ..
-        } = tonic_auth!(
-            self.authorizer.token_handler.process_token(&token).await,
-            "Unauthorized"
-        );
+        } = tonic_auth!(self.authorizer.authenticate(&token).await, "Unauthorized");
 
         let id = if is_proxy {
             let ctx = Context::proxy();
@@ -676,10 +682,8 @@ impl ServiceAccountServiceImpl {
         token: String,
         request: T,
     ) -> Result<(User, DieselUlid, Option<DieselUlid>)> {
-        let ProcessedToken { main_id, .. } = tonic_auth!(
-            self.authorizer.token_handler.process_token(&token).await,
-            "Unauthorized"
-        );
+        let ProcessedToken { main_id, .. } =
+            tonic_auth!(self.authorizer.authenticate(&token).await, "Unauthorized");
         let is_self = self
             .cache
             .get_user(&main_id)
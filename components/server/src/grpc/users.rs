@@ -1,19 +1,33 @@
+use crate::auth::bootstrap::ADMIN_BOOTSTRAP;
 use crate::auth::permission_handler::{PermissionCheck, PermissionHandler};
-use crate::auth::structs::Context;
+use crate::auth::structs::{Context, ContextVariant};
 use crate::auth::token_handler::{Action, Intent, ProcessedToken, TokenHandler};
 use crate::caching::cache::Cache;
 use crate::database::enums::DbPermissionLevel;
 use crate::middlelayer::db_handler::DatabaseHandler;
-use crate::middlelayer::token_request_types::{CreateToken, DeleteToken, GetToken};
+use crate::middlelayer::notification_retention_request_types::{
+    NotificationsPruned, ReplayPersistentNotifications,
+};
+use crate::middlelayer::token_request_types::{
+    sign_cookie, AssumeStorageRole, CreateSignedCookie, CreateToken, DeleteToken, DownscopeToken,
+    GetToken, SignedCookie, StorageRoleCredentials, SIGNED_COOKIE_NAME,
+};
 use crate::middlelayer::user_request_types::{
-    ActivateUser, DeactivateUser, DeleteProxyAttributeSource, GetUser, RegisterUser,
-    UpdateUserEmail, UpdateUserName,
+    ActivateUser, BootstrapAdmin, DeactivateUser, DeleteProxyAttributeSource, GetUser,
+    RegisterUser, UpdateUserEmail, UpdateUserName,
+};
+use crate::notification::handler::EventHandler;
+use crate::utils::conversions::users::{
+    as_api_token, convert_permission_to_proto, convert_token_to_proto,
 };
-use crate::utils::conversions::users::{as_api_token, convert_token_to_proto};
-use crate::utils::grpc_utils::get_token_from_md;
+use crate::utils::grpc_utils::{get_page_request_from_md, get_token_from_md, page_info_to_md};
 use crate::utils::mailclient::MailClient;
+use crate::utils::pagination_utils::paginate;
 use anyhow::anyhow;
+use aruna_common::session_token::sign_session_token;
 use aruna_rust_api::api::storage::models::v2::context::Context as ProtoContext;
+use aruna_rust_api::api::storage::models::v2::Permission;
+use aruna_rust_api::api::storage::models::v2::User as APIUser;
 use aruna_rust_api::api::storage::services::v2::user_service_server::UserService;
 use aruna_rust_api::api::storage::services::v2::{
     AcknowledgePersonalNotificationsRequest, AcknowledgePersonalNotificationsResponse,
@@ -30,11 +44,12 @@ use aruna_rust_api::api::storage::services::v2::{
     GetNotActivatedUsersResponse, GetPersonalNotificationsRequest,
     GetPersonalNotificationsResponse, GetS3CredentialsUserTokenRequest,
     GetS3CredentialsUserTokenResponse, GetUserRedactedRequest, GetUserRedactedResponse,
-    GetUserRequest, GetUserResponse, RegisterUserRequest, RegisterUserResponse,
-    RemoveDataProxyAttributeUserRequest, RemoveDataProxyAttributeUserResponse,
-    RemoveOidcProviderRequest, RemoveOidcProviderResponse, RemoveTrustedEndpointsUserRequest,
-    RemoveTrustedEndpointsUserResponse, UpdateUserDisplayNameRequest,
-    UpdateUserDisplayNameResponse, UpdateUserEmailRequest, UpdateUserEmailResponse,
+    GetUserRequest, GetUserResponse, PersonalNotification, RegisterUserRequest,
+    RegisterUserResponse, RemoveDataProxyAttributeUserRequest,
+    RemoveDataProxyAttributeUserResponse, RemoveOidcProviderRequest, RemoveOidcProviderResponse,
+    RemoveTrustedEndpointsUserRequest, RemoveTrustedEndpointsUserResponse,
+    UpdateUserDisplayNameRequest, UpdateUserDisplayNameResponse, UpdateUserEmailRequest,
+    UpdateUserEmailResponse,
 };
 use diesel_ulid::DieselUlid;
 use std::str::FromStr;
@@ -251,6 +266,11 @@ the Aruna team", user.display_name),
             get_token_from_md(request.metadata()),
             "Token authentication error"
         );
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let ctx = Context::self_ctx();
         let user_id = tonic_auth!(
             self.authorizer.check_permissions(&token, vec![ctx]).await,
@@ -269,9 +289,10 @@ the Aruna team", user.display_name),
                 .into_iter()
                 .map(|t| as_api_token(t.0, t.1)),
         );
+        let (tokens, page_info) = paginate(tokens, &page, |token| token.id.clone());
         let response = GetApiTokensResponse { tokens };
 
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
 
     //ToDo: Docs
@@ -455,6 +476,11 @@ the Aruna team", user.display_name),
             get_token_from_md(request.metadata()),
             "Token authentication error"
         );
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let ctx = Context::admin();
         tonic_auth!(
             self.authorizer.check_permissions(&token, vec![ctx]).await,
@@ -463,8 +489,9 @@ the Aruna team", user.display_name),
 
         let users = self.cache.get_all_deactivated().await;
 
+        let (users, page_info) = paginate(users, &page, |user| user.id.clone());
         let response = GetNotActivatedUsersResponse { users };
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
 
     async fn get_all_users(
@@ -476,6 +503,11 @@ the Aruna team", user.display_name),
             get_token_from_md(request.metadata()),
             "Token authentication error"
         );
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+
         let ctx = Context::admin();
         tonic_auth!(
             self.authorizer.check_permissions(&token, vec![ctx]).await,
@@ -483,8 +515,9 @@ the Aruna team", user.display_name),
         );
         let user = self.cache.get_all_users_proto().await;
 
+        let (user, page_info) = paginate(user, &page, |user| user.id.clone());
         let response = GetAllUsersResponse { user };
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
 
     //ToDo: Docs
@@ -639,6 +672,11 @@ the Aruna team", user.display_name),
             "Token authentication error"
         );
 
+        let page = tonic_invalid!(
+            get_page_request_from_md(&request_metadata),
+            "Invalid pagination"
+        );
+
         let ctx = Context::self_ctx();
         let token_user_ulid = tonic_auth!(
             self.authorizer.check_permissions(&token, vec![ctx]).await,
@@ -654,8 +692,10 @@ the Aruna team", user.display_name),
         );
 
         // Return personal notifications
+        let (notifications, page_info) =
+            paginate(notifications, &page, |notification| notification.id.clone());
         let response = GetPersonalNotificationsResponse { notifications };
-        return_with_log!(response);
+        return_with_log!(response, page_info_to_md(&page_info));
     }
 
     //ToDo: Docs
@@ -932,10 +972,7 @@ the Aruna team", user.display_name),
         );
         let ProcessedToken {
             main_id, is_proxy, ..
-        } = tonic_auth!(
-            self.token_handler.process_token(&token).await,
-            "Unauthorized"
-        );
+        } = tonic_auth!(self.authorizer.authenticate(&token).await, "Unauthorized");
 
         let id = if is_proxy {
             let ctx = Context::proxy();
@@ -1055,3 +1092,309 @@ the Aruna team", user.display_name),
         return_with_log!(DeleteS3CredentialsUserResponse {});
     }
 }
+
+impl UserServiceImpl {
+    /// Promotes the calling registered user of a fresh instance to an active global admin
+    /// with the one-time token configured as BOOTSTRAP_ADMIN_TOKEN. The bootstrap is
+    /// disabled after its first use and as soon as any global admin exists.
+    pub async fn bootstrap_admin(
+        &self,
+        request: Request<BootstrapAdmin>,
+    ) -> Result<Response<APIUser>, Status> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        // Registered users are not activated yet
+        let ctx = Context {
+            variant: ContextVariant::NotActivated,
+            allow_service_account: false,
+            is_self: true,
+        };
+        let user_id = tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let Some(bootstrap) = ADMIN_BOOTSTRAP.as_ref() else {
+            return Err(Status::failed_precondition(
+                "Admin bootstrap is not configured",
+            ));
+        };
+        let user = tonic_auth!(
+            self.database_handler
+                .bootstrap_admin(bootstrap, user_id, &request.into_inner().bootstrap_token)
+                .await,
+            "Admin bootstrap failed"
+        );
+        let user: APIUser = user.into();
+        return_with_log!(user);
+    }
+
+    /// Issues a child token of the parent token in the request, which is restricted to the
+    /// intersection of the requested scope and the parent permissions and expires no later
+    /// than its parent. Deleting the parent also deletes the child.
+    pub async fn downscope_token(
+        &self,
+        request: Request<DownscopeToken>,
+    ) -> Result<Response<CreateApiTokenResponse>, Status> {
+        log_received!(&request);
+
+        // The parent token authenticates the request
+        let request = request.into_inner();
+        let (user_id, token) = self
+            .authorizer
+            .downscope_token(
+                &request,
+                self.token_handler.get_current_pubkey_serial() as i32,
+            )
+            .await?;
+
+        let token_ulid = tonic_internal!(
+            self.database_handler
+                .create_downscoped_token(&user_id, token.clone())
+                .await,
+            "Token creation failed"
+        );
+        let token_secret = tonic_internal!(
+            self.token_handler.sign_user_token(
+                &user_id,
+                &token_ulid,
+                Some(token.expires_at.into()),
+            ),
+            "Token signing failed"
+        );
+
+        let response = CreateApiTokenResponse {
+            token: Some(convert_token_to_proto(&token_ulid, token)),
+            token_secret,
+        };
+        return_with_log!(response);
+    }
+
+    /// Mints temporary S3 credentials with a session token which are restricted to the
+    /// requested scope and expire after the ttl. The access key is the id of the token
+    /// behind the credentials, deleting the token with DeleteApiToken revokes them.
+    pub async fn assume_storage_role(
+        &self,
+        request: Request<AssumeStorageRole>,
+    ) -> Result<Response<StorageRoleCredentials>, Status> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let response = self
+            .issue_storage_role(&token, request.into_inner())
+            .await?;
+        return_with_log!(response);
+    }
+
+    /// Issues a signed cookie for browsers which grants read access to all objects below the
+    /// requested resource until the ttl expires. The cookie session is a storage role, its
+    /// session id is the id of the token behind it and deleting the token revokes the cookie.
+    pub async fn create_signed_cookie(
+        &self,
+        request: Request<CreateSignedCookie>,
+    ) -> Result<Response<SignedCookie>, Status> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let request = request.into_inner();
+        let resource_id = tonic_invalid!(request.get_resource_id(), "Invalid resource id");
+        let credentials = self
+            .issue_storage_role(&token, request.as_storage_role())
+            .await?;
+        let value = tonic_internal!(
+            sign_cookie(
+                &credentials.secret_key,
+                &credentials.access_key,
+                &resource_id,
+                credentials.expires_at,
+            ),
+            "Cookie signing failed"
+        );
+
+        let response = SignedCookie {
+            name: SIGNED_COOKIE_NAME.to_string(),
+            value,
+            session_id: credentials.access_key,
+            s3_endpoint_url: credentials.s3_endpoint_url,
+            expires_at: credentials.expires_at,
+        };
+        return_with_log!(response);
+    }
+
+    /// Stores the token of a storage role and requests its S3 credentials at the endpoint,
+    /// the token is removed again if the credentials can not be created
+    async fn issue_storage_role(
+        &self,
+        token: &str,
+        request: AssumeStorageRole,
+    ) -> Result<StorageRoleCredentials, Status> {
+        let (user_id, role) = self
+            .authorizer
+            .assume_storage_role(
+                token,
+                &request,
+                self.token_handler.get_current_pubkey_serial() as i32,
+            )
+            .await?;
+        let resource_id = tonic_invalid!(request.get_scope(), "Invalid scope")
+            .0
+            .into_inner();
+        let expires_at = role.expires_at.and_utc().timestamp();
+
+        let token_id = tonic_internal!(
+            self.database_handler
+                .create_hook_token(&user_id, role)
+                .await,
+            "Token creation failed"
+        );
+        let credentials = self
+            .get_storage_role_credentials(user_id, token_id, resource_id, request.endpoint_id)
+            .await;
+        let (access_key, secret_key, s3_endpoint_url) = match credentials {
+            Ok(credentials) => credentials,
+            Err(err) => {
+                log::error!("Storage role credentials failed: {}", err);
+                if let Err(err) = self
+                    .database_handler
+                    .delete_token(
+                        user_id,
+                        DeleteToken(DeleteApiTokenRequest {
+                            token_id: token_id.to_string(),
+                        }),
+                    )
+                    .await
+                {
+                    log::error!("Removing storage role token failed: {}", err);
+                }
+                return Err(Status::internal("Storage role credentials failed"));
+            }
+        };
+        let session_token = tonic_internal!(
+            sign_session_token(&secret_key, &access_key, expires_at),
+            "Session token signing failed"
+        );
+
+        Ok(StorageRoleCredentials {
+            access_key,
+            secret_key,
+            session_token,
+            s3_endpoint_url,
+            expires_at,
+        })
+    }
+
+    async fn get_storage_role_credentials(
+        &self,
+        user_id: DieselUlid,
+        token_id: DieselUlid,
+        resource_id: DieselUlid,
+        endpoint_id: Option<String>,
+    ) -> anyhow::Result<(String, String, String)> {
+        let endpoint_id = match endpoint_id {
+            Some(endpoint_id) => endpoint_id,
+            None => self
+                .database_handler
+                .get_fullsync_endpoint(resource_id)
+                .await?
+                .id
+                .to_string(),
+        };
+        // The proxy has to know the token before it creates the credentials
+        self.database_handler
+            .natsio_handler
+            .wait_for_acknowledgement(&endpoint_id)
+            .await?;
+        self.database_handler
+            .get_s3_credentials(
+                user_id,
+                Some(token_id),
+                endpoint_id,
+                &self.authorizer.token_handler,
+            )
+            .await
+    }
+
+    /// Returns the effective permissions of the requesting user, resolved through the
+    /// resource hierarchy the same way the authorizer grants them. The personal permissions
+    /// of GetUser only contain the direct grants of the user. Large memberships are paged
+    /// with the pagination metadata, ordered by resource id.
+    pub async fn get_effective_permissions(
+        &self,
+        request: Request<()>,
+    ) -> Result<Response<Vec<Permission>>, Status> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let page = tonic_invalid!(
+            get_page_request_from_md(request.metadata()),
+            "Invalid pagination"
+        );
+        let ctx = Context::self_ctx();
+        let user_id = tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let permissions = tonic_internal!(
+            self.cache.get_effective_permissions(&user_id),
+            "Effective permission resolution failed"
+        );
+        let (permissions, page_info) = paginate(permissions, &page, |(id, _)| id.to_string());
+        let permissions: Vec<Permission> = permissions
+            .into_iter()
+            .map(|(id, perm)| convert_permission_to_proto(id, perm))
+            .collect();
+        return_with_log!(permissions, page_info_to_md(&page_info));
+    }
+
+    /// Returns the personal notifications created after the requested notification, oldest
+    /// first. Replays from notifications which were already pruned by the retention fail
+    /// with FailedPrecondition and have to resync without a starting notification.
+    pub async fn replay_personal_notifications(
+        &self,
+        request: Request<ReplayPersistentNotifications>,
+    ) -> Result<Response<Vec<PersonalNotification>>, Status> {
+        log_received!(&request);
+
+        let token = tonic_auth!(
+            get_token_from_md(request.metadata()),
+            "Token authentication error"
+        );
+        let ctx = Context::self_ctx();
+        let user_id = tonic_auth!(
+            self.authorizer.check_permissions(&token, vec![ctx]).await,
+            "Unauthorized"
+        );
+
+        let request = request.into_inner();
+        tonic_invalid!(request.get_after(), "Invalid notification id");
+        let result = self
+            .database_handler
+            .replay_persistent_notifications(user_id, request)
+            .await;
+        if let Some(pruned) = result
+            .as_ref()
+            .err()
+            .and_then(|err| err.downcast_ref::<NotificationsPruned>())
+        {
+            return Err(Status::failed_precondition(pruned.to_string()));
+        }
+        let notifications = tonic_internal!(result, "Internal replay notifications error");
+        let notifications: Vec<PersonalNotification> =
+            notifications.into_iter().map(|n| n.into()).collect();
+        return_with_log!(notifications);
+    }
+}
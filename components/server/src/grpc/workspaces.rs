@@ -2,6 +2,8 @@ use crate::auth::structs::Context;
 use crate::caching::cache::Cache;
 use crate::middlelayer::db_handler::DatabaseHandler;
 use crate::middlelayer::workspace_request_types::{CreateTemplate, CreateWorkspace};
+use crate::utils::grpc_utils::{get_page_request_from_md, page_info_to_md};
+use crate::utils::pagination_utils::paginate;
 use crate::{auth::permission_handler::PermissionHandler, utils::grpc_utils::get_token_from_md};
 use aruna_rust_api::api::storage::services::v2::{
     workspace_service_server::WorkspaceService, ClaimWorkspaceRequest, ClaimWorkspaceResponse,
@@ -11,7 +13,7 @@ use aruna_rust_api::api::storage::services::v2::{
 use aruna_rust_api::api::storage::services::v2::{
     DeleteWorkspaceTemplateRequest, DeleteWorkspaceTemplateResponse, GetWorkspaceTemplateRequest,
     GetWorkspaceTemplateResponse, ListOwnedWorkspaceTemplatesRequest,
-    ListOwnedWorkspaceTemplatesResponse,
+    ListOwnedWorkspaceTemplatesResponse, WorkspaceInfo,
 };
 
 use std::str::FromStr;
@@ -198,6 +200,8 @@ impl WorkspaceService for WorkspaceServiceImpl {
         log_received!(&request);
         let metadata = request.metadata();
 
+        let page = tonic_invalid!(get_page_request_from_md(metadata), "Invalid pagination");
+
         // Authorization
         let token = tonic_auth!(get_token_from_md(metadata), "Token authentication error");
         let ctx = Context::self_ctx();
@@ -209,10 +213,13 @@ impl WorkspaceService for WorkspaceServiceImpl {
             self.database_handler.get_owned_ws(&user_id).await,
             "No workspaces found"
         );
-        let response = ListOwnedWorkspaceTemplatesResponse {
-            workspaces: workspaces.into_iter().map(|ws| ws.into()).collect(),
-        };
-        return_with_log!(response);
+        let (workspaces, page_info) = paginate(
+            workspaces.into_iter().map(|ws| ws.into()).collect(),
+            &page,
+            |workspace: &WorkspaceInfo| workspace.workspace_id.clone(),
+        );
+        let response = ListOwnedWorkspaceTemplatesResponse { workspaces };
+        return_with_log!(response, page_info_to_md(&page_info));
     }
     async fn delete_workspace_template(
         &self,
@@ -5,10 +5,19 @@ use crate::database::dsls::hook_dsl::{
 use crate::database::dsls::object_dsl::KeyValueVariant::HOOK_STATUS;
 use crate::database::dsls::user_dsl::APIToken;
 use crate::database::enums::{ObjectMapping, ObjectStatus, ObjectType};
+use crate::hooks::hook_queue::{send_with_retries, HookLimiter, HOOK_QUEUE_CONFIG};
+use crate::hooks::preview_hook::{content_type, request_preview, PreviewRequest, PREVIEW_CONFIG};
+use crate::hooks::scan_hook::{request_scan, ScanRequest, ScanVerdict, SCAN_CONFIG};
+use crate::hooks::validation_hook::{
+    validate_content, validation_rules, ValidationOutcome, VALIDATION_CONFIG,
+};
 use crate::middlelayer::hooks_request_types::CustomTemplate;
-use crate::middlelayer::presigned_url_handler::PresignedDownload;
+use crate::middlelayer::presigned_url_handler::{
+    PresignedDownload, PresignedUpload, UploadSizePolicy,
+};
 use crate::middlelayer::relations_request_types::ModifyRelations;
 use crate::notification::handler::EventHandler;
+use crate::utils::request_id_utils::{with_request_id, REQUEST_ID_KEY};
 use crate::{
     auth::permission_handler::PermissionHandler,
     database::dsls::{
@@ -24,8 +33,8 @@ use aruna_rust_api::api::storage::models::v2::{
     KeyValue as APIKeyVals, KeyValueVariant as APIKeyValVariant,
 };
 use aruna_rust_api::api::storage::services::v2::{
-    GetDownloadUrlRequest, UpdateCollectionKeyValuesRequest, UpdateObjectRequest,
-    UpdateProjectKeyValuesRequest,
+    GetDownloadUrlRequest, GetUploadUrlRequest, UpdateCollectionKeyValuesRequest,
+    UpdateObjectRequest, UpdateProjectKeyValuesRequest,
 };
 use async_channel::Receiver;
 use diesel_ulid::DieselUlid;
@@ -44,6 +53,29 @@ pub struct HookMessage {
     pub hook: HookWithAssociatedProject,
     pub object: ObjectWithRelations,
     pub user_id: DieselUlid,
+    pub request_id: Option<String>, // Correlation id of the triggering request
+}
+
+impl HookMessage {
+    /// Host which is called by the hook, concurrent calls are limited per host
+    pub fn target_host(&self) -> Option<String> {
+        let url = match &self.hook.hook.0 {
+            crate::database::dsls::hook_dsl::HookVariant::External(ExternalHook {
+                url, ..
+            }) => url.as_str(),
+            crate::database::dsls::hook_dsl::HookVariant::Internal(
+                crate::database::dsls::hook_dsl::InternalHook::Scan,
+            ) => SCAN_CONFIG.as_ref()?.url.as_str(),
+            crate::database::dsls::hook_dsl::HookVariant::Internal(
+                crate::database::dsls::hook_dsl::InternalHook::Preview,
+            ) => PREVIEW_CONFIG.as_ref()?.url.as_str(),
+            crate::database::dsls::hook_dsl::HookVariant::Internal(_) => return None,
+        };
+        reqwest::Url::parse(url)
+            .ok()?
+            .host_str()
+            .map(|host| host.to_ascii_lowercase())
+    }
 }
 
 impl HookHandler {
@@ -61,15 +93,34 @@ impl HookHandler {
     pub async fn run(&self) -> Result<()> {
         let handler = self.clone();
         let client = reqwest::Client::new();
+        let limiter = HookLimiter::new(&HOOK_QUEUE_CONFIG);
+        limiter.start_metrics_loop(self.reciever.clone(), HOOK_QUEUE_CONFIG.metrics_interval);
         tokio::spawn(async move {
-            while let Ok(message) = handler.reciever.recv().await {
+            loop {
+                // Messages stay in the queue while too many hooks are waiting
+                let Ok(reservation) = limiter.reserve().await else {
+                    break;
+                };
+                let Ok(message) = handler.reciever.recv().await else {
+                    break;
+                };
                 // TODO:
-                // - queue logic
                 // - deduplication
-                // - retries
-                if let Err(action) = handler.hook_action(message, client.clone()).await {
-                    log::error!("[HookHandler] ERROR: {:?}", action);
-                };
+                let host = message.target_host();
+                let (handler, client, limiter) = (handler.clone(), client.clone(), limiter.clone());
+                tokio::spawn(async move {
+                    let request_id = message.request_id.clone();
+                    let hook = async {
+                        if let Err(action) =
+                            with_request_id(request_id, handler.hook_action(message, client)).await
+                        {
+                            log::error!("[HookHandler] ERROR: {:?}", action);
+                        };
+                    };
+                    if let Err(err) = limiter.run(reservation, host, hook).await {
+                        log::error!("[HookHandler] ERROR: {:?}", err);
+                    }
+                });
             }
         });
         Ok(())
@@ -79,6 +130,7 @@ impl HookHandler {
             hook,
             object,
             user_id,
+            request_id,
         } = message;
         let object_id = object.object.id;
 
@@ -168,6 +220,69 @@ impl HookHandler {
                                 .await?;
                         }
                     }
+                    crate::database::dsls::hook_dsl::InternalHook::Scan => {
+                        let status = match self.scan_object(&object, &hook, user_id, &client).await
+                        {
+                            Ok(_) => {
+                                self.sign_manifest(object_id).await;
+                                HookStatusVariant::FINISHED
+                            }
+                            Err(e) => {
+                                // Objects stay blocked until a scan succeeds
+                                log::error!("Scan of object {object_id} failed: {e}");
+                                HookStatusVariant::ERROR(e.to_string())
+                            }
+                        };
+                        // Status and key-values changed while scanning
+                        let object = self
+                            .database_handler
+                            .cache
+                            .get_object(&object_id)
+                            .unwrap_or(object);
+                        self.add_or_replace_status(&hook, &object, status).await?;
+                    }
+                    crate::database::dsls::hook_dsl::InternalHook::Validate => {
+                        let status =
+                            match self.validate_object(&object, &hook, user_id, &client).await {
+                                Ok(_) => {
+                                    self.sign_manifest(object_id).await;
+                                    HookStatusVariant::FINISHED
+                                }
+                                Err(e) => {
+                                    // Objects stay blocked until a validation succeeds
+                                    log::error!("Validation of object {object_id} failed: {e}");
+                                    HookStatusVariant::ERROR(e.to_string())
+                                }
+                            };
+                        let object = self
+                            .database_handler
+                            .cache
+                            .get_object(&object_id)
+                            .unwrap_or(object);
+                        self.add_or_replace_status(&hook, &object, status).await?;
+                    }
+                    crate::database::dsls::hook_dsl::InternalHook::Preview => {
+                        let status = match self
+                            .generate_preview(&object, &hook, user_id, &client)
+                            .await
+                        {
+                            Ok(preview_id) => {
+                                log::info!("Created preview {preview_id} of object {object_id}");
+                                HookStatusVariant::FINISHED
+                            }
+                            Err(e) => {
+                                // Previews are best-effort, the original object is not affected
+                                log::warn!("Preview of object {object_id} failed: {e}");
+                                HookStatusVariant::ERROR(e.to_string())
+                            }
+                        };
+                        let object = self
+                            .database_handler
+                            .cache
+                            .get_object(&object_id)
+                            .unwrap_or(object);
+                        self.add_or_replace_status(&hook, &object, status).await?;
+                    }
                 }
             }
             crate::database::dsls::hook_dsl::HookVariant::External(ExternalHook {
@@ -196,6 +311,10 @@ impl HookHandler {
                         None => client.post(url),
                     },
                 };
+                let base_request = match &request_id {
+                    Some(request_id) => base_request.header(REQUEST_ID_KEY, request_id),
+                    None => base_request,
+                };
                 // Query rule bindings from cache and build generic object
                 let object_wrapper = ObjectWrapper {
                     object_with_relations: object.clone(),
@@ -217,6 +336,7 @@ impl HookHandler {
                             pubkey_serial: pubkey_serial.into(),
                             access_key: Some(upload_credentials.access_key),
                             secret_key: Some(upload_credentials.secret_key),
+                            request_id: request_id.clone(),
                         };
                         base_request.json(&input)
                     }
@@ -235,14 +355,23 @@ impl HookHandler {
                             .body(template)
                     }
                 };
-                if let Err(e) = data_request.send().await {
-                    log::error!("External hook error: {e}");
-                    self.add_or_replace_status(
-                        &hook,
-                        &object,
-                        HookStatusVariant::ERROR(e.to_string()),
-                    )
-                    .await?;
+                if let Err(failure) = send_with_retries(data_request, &HOOK_QUEUE_CONFIG).await {
+                    log::error!(
+                        "External hook error after {} attempts: {}",
+                        failure.attempts,
+                        failure.reason
+                    );
+                    let status = HookStatusVariant::ERROR(failure.reason.clone());
+                    let message = HookMessage {
+                        hook: hook.clone(),
+                        object: object.clone(),
+                        user_id,
+                        request_id,
+                    };
+                    self.database_handler
+                        .dead_letter_hook(&message, failure)
+                        .await?;
+                    self.add_or_replace_status(&hook, &object, status).await?;
                 };
             }
         };
@@ -351,6 +480,7 @@ impl HookHandler {
             key: hook.id.to_string(),
             value: serde_json::to_string(&status_value)?,
             variant: HOOK_STATUS,
+            value_type: None,
         };
         if object
             .object
@@ -400,6 +530,291 @@ impl HookHandler {
         Ok(())
     }
 
+    /// Signs the manifest of objects which became available after a scan or validation.
+    /// Failures are only logged, the manifest is signed with the next change of the object.
+    async fn sign_manifest(&self, object_id: DieselUlid) {
+        if let Err(e) = self
+            .database_handler
+            .sign_manifest(&object_id, &self.authorizer.token_handler)
+            .await
+        {
+            log::error!("Signing the manifest of object {object_id} failed: {e}");
+        }
+    }
+
+    /// Sends the object to the configured scanner and applies its verdict
+    async fn scan_object(
+        &self,
+        object: &ObjectWithRelations,
+        hook: &HookWithAssociatedProject,
+        user_id: DieselUlid,
+        client: &reqwest::Client,
+    ) -> Result<ScanVerdict> {
+        let config = SCAN_CONFIG
+            .as_ref()
+            .ok_or_else(|| anyhow!("No scanner configured"))?;
+        let object_id = object.object.id;
+
+        // Identical content is not scanned again
+        if let Some(verdict) = self
+            .database_handler
+            .cached_scan_verdict(object_id, config.attestation_ttl)
+            .await?
+        {
+            log::info!(
+                "Reusing {} verdict of identical content for {object_id}",
+                verdict.label()
+            );
+            self.database_handler
+                .finish_scan(object_id, &verdict)
+                .await?;
+            return Ok(verdict);
+        }
+
+        // Read only credentials restricted to the scanned object
+        let scan_token = APIToken {
+            pub_key: self
+                .authorizer
+                .token_handler
+                .get_current_pubkey_serial()
+                .into(),
+            name: format!("{}-scan", object_id),
+            created_at: chrono::Utc::now().naive_utc(),
+            expires_at: hook.timeout,
+            object_id: Some(ObjectMapping::OBJECT(object_id)),
+            user_rights: crate::database::enums::DbPermissionLevel::READ,
+            parent: None,
+            session_scoped: false,
+        };
+        let token_id = self
+            .database_handler
+            .create_hook_token(&user_id, scan_token)
+            .await?;
+        let endpoint = self
+            .database_handler
+            .get_fullsync_endpoint(hook.project_id)
+            .await?;
+        self.database_handler
+            .natsio_handler
+            .wait_for_acknowledgement(&endpoint.id.to_string())
+            .await?;
+
+        let request = PresignedDownload(GetDownloadUrlRequest {
+            object_id: object_id.to_string(),
+        });
+        let (download_url, credentials) = self
+            .database_handler
+            .get_presigned_download_with_credentials(
+                self.authorizer.clone(),
+                request,
+                user_id,
+                Some(token_id),
+                hook.project_id,
+                endpoint,
+            )
+            .await?;
+        self.database_handler
+            .start_scan(object_id, &credentials.access_key)
+            .await?;
+
+        let response = request_scan(
+            client,
+            config,
+            &ScanRequest {
+                object_id,
+                name: object.object.name.clone(),
+                content_len: object.object.content_len,
+                download_url,
+            },
+        )
+        .await?;
+        self.database_handler
+            .attest_scan(object_id, &response)
+            .await?;
+        self.database_handler
+            .finish_scan(object_id, &response.verdict)
+            .await?;
+        Ok(response.verdict)
+    }
+
+    /// Streams the object through the validators of its collections
+    async fn validate_object(
+        &self,
+        object: &ObjectWithRelations,
+        hook: &HookWithAssociatedProject,
+        user_id: DieselUlid,
+        client: &reqwest::Client,
+    ) -> Result<ValidationOutcome> {
+        let object_id = object.object.id;
+        let rules = validation_rules(&self.database_handler.cache, &object.object)?;
+
+        // Read only credentials restricted to the validated object
+        let validation_token = APIToken {
+            pub_key: self
+                .authorizer
+                .token_handler
+                .get_current_pubkey_serial()
+                .into(),
+            name: format!("{}-validation", object_id),
+            created_at: chrono::Utc::now().naive_utc(),
+            expires_at: hook.timeout,
+            object_id: Some(ObjectMapping::OBJECT(object_id)),
+            user_rights: crate::database::enums::DbPermissionLevel::READ,
+            parent: None,
+            session_scoped: false,
+        };
+        let token_id = self
+            .database_handler
+            .create_hook_token(&user_id, validation_token)
+            .await?;
+        let endpoint = self
+            .database_handler
+            .get_fullsync_endpoint(hook.project_id)
+            .await?;
+        self.database_handler
+            .natsio_handler
+            .wait_for_acknowledgement(&endpoint.id.to_string())
+            .await?;
+
+        let request = PresignedDownload(GetDownloadUrlRequest {
+            object_id: object_id.to_string(),
+        });
+        let (download_url, credentials) = self
+            .database_handler
+            .get_presigned_download_with_credentials(
+                self.authorizer.clone(),
+                request,
+                user_id,
+                Some(token_id),
+                hook.project_id,
+                endpoint,
+            )
+            .await?;
+        // Validating objects can only be downloaded with the scan token
+        self.database_handler
+            .start_scan(object_id, &credentials.access_key)
+            .await?;
+
+        let response = client
+            .get(download_url)
+            .timeout(VALIDATION_CONFIG.timeout)
+            .send()
+            .await?
+            .error_for_status()?;
+        let outcome = validate_content(
+            &rules,
+            Box::pin(response.bytes_stream()),
+            VALIDATION_CONFIG.max_size,
+        )
+        .await?;
+        self.database_handler
+            .finish_validation(object_id, &outcome)
+            .await?;
+        Ok(outcome)
+    }
+
+    /// Sends the object to the configured generator and stores the
+    /// returned thumbnail as preview object linked to the original
+    async fn generate_preview(
+        &self,
+        object: &ObjectWithRelations,
+        hook: &HookWithAssociatedProject,
+        user_id: DieselUlid,
+        client: &reqwest::Client,
+    ) -> Result<DieselUlid> {
+        let config = PREVIEW_CONFIG
+            .as_ref()
+            .ok_or_else(|| anyhow!("No preview generator configured"))?;
+        let object_id = object.object.id;
+        let content_type =
+            content_type(&object.object).ok_or_else(|| anyhow!("Unknown content type"))?;
+
+        // Read access to the original and append access for the upload of the preview
+        let preview_token = APIToken {
+            pub_key: self
+                .authorizer
+                .token_handler
+                .get_current_pubkey_serial()
+                .into(),
+            name: format!("{}-preview", object_id),
+            created_at: chrono::Utc::now().naive_utc(),
+            expires_at: hook.timeout,
+            object_id: Some(ObjectMapping::PROJECT(hook.project_id)),
+            user_rights: crate::database::enums::DbPermissionLevel::APPEND,
+            parent: None,
+            session_scoped: false,
+        };
+        let token_id = self
+            .database_handler
+            .create_hook_token(&user_id, preview_token)
+            .await?;
+        let endpoint = self
+            .database_handler
+            .get_fullsync_endpoint(hook.project_id)
+            .await?;
+        self.database_handler
+            .natsio_handler
+            .wait_for_acknowledgement(&endpoint.id.to_string())
+            .await?;
+
+        let request = PresignedDownload(GetDownloadUrlRequest {
+            object_id: object_id.to_string(),
+        });
+        let (download_url, _) = self
+            .database_handler
+            .get_presigned_download_with_credentials(
+                self.authorizer.clone(),
+                request,
+                user_id,
+                Some(token_id),
+                hook.project_id,
+                endpoint,
+            )
+            .await?;
+        let thumbnail = request_preview(
+            client,
+            config,
+            &PreviewRequest {
+                object_id,
+                name: object.object.name.clone(),
+                content_type,
+                download_url,
+            },
+        )
+        .await?;
+
+        let preview = self
+            .database_handler
+            .create_preview(object_id, &thumbnail, user_id)
+            .await?;
+        let upload_url = self
+            .database_handler
+            .get_presigend_upload(
+                self.database_handler.cache.clone(),
+                PresignedUpload(GetUploadUrlRequest {
+                    object_id: preview.id.to_string(),
+                    multipart: false,
+                    part_number: 1,
+                }),
+                self.authorizer.clone(),
+                user_id,
+                Some(token_id),
+                None,
+                // Thumbnails are uploaded by the server with their exact size
+                Some(UploadSizePolicy::Exact.range(thumbnail.data.len() as u64)),
+            )
+            .await?;
+        client
+            .put(upload_url)
+            .timeout(config.timeout)
+            .header(CONTENT_TYPE, &thumbnail.content_type)
+            .body(thumbnail.data)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(preview.id)
+    }
+
     async fn get_template_input(
         &self,
         object: ObjectWithRelations,
@@ -426,6 +841,8 @@ impl HookHandler {
                 // TODO: Custom resource permissions for hooks
                 object_id: Some(ObjectMapping::PROJECT(hook.project_id)),
                 user_rights: crate::database::enums::DbPermissionLevel::APPEND,
+                parent: None,
+                session_scoped: false,
             };
             let token_id = self
                 .database_handler
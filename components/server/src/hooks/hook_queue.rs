@@ -0,0 +1,281 @@
+use anyhow::Result;
+use async_channel::Receiver;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+lazy_static! {
+    pub static ref HOOK_QUEUE_CONFIG: HookQueueConfig = HookQueueConfig::from_env();
+}
+
+/// What happens to hook messages if the hook queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    // Producers wait until the queue has free capacity
+    Block,
+    // Messages are persisted and queued again once the queue has free capacity
+    Persist,
+}
+
+/// Size of the hook queue and concurrency limits of hook executions
+#[derive(Debug, Clone)]
+pub struct HookQueueConfig {
+    pub capacity: usize,
+    pub workers: usize,
+    pub workers_per_host: usize,
+    pub overflow: OverflowPolicy,
+    pub replay_interval: Duration,
+    pub metrics_interval: Duration,
+    pub max_retries: u32,
+    pub retry_backoff: Duration,
+}
+
+fn var(key: &str, default: u64) -> u64 {
+    dotenvy::var(key)
+        .map(|var| var.parse::<u64>().unwrap_or(default))
+        .unwrap_or(default)
+        .max(1)
+}
+
+impl HookQueueConfig {
+    pub fn from_env() -> Self {
+        let workers = var("HOOK_WORKERS", 16) as usize;
+        HookQueueConfig {
+            capacity: var("HOOK_QUEUE_SIZE", 1000) as usize,
+            workers,
+            workers_per_host: (var("HOOK_WORKERS_PER_HOST", 4) as usize).min(workers),
+            overflow: match dotenvy::var("HOOK_QUEUE_OVERFLOW").as_deref() {
+                Ok("persist") => OverflowPolicy::Persist,
+                _ => OverflowPolicy::Block,
+            },
+            replay_interval: Duration::from_secs(var("HOOK_REPLAY_INTERVAL", 30)),
+            metrics_interval: Duration::from_secs(var("HOOK_QUEUE_METRICS_INTERVAL", 60)),
+            max_retries: dotenvy::var("HOOK_MAX_RETRIES")
+                .ok()
+                .and_then(|var| var.parse::<u32>().ok())
+                .unwrap_or(3),
+            retry_backoff: Duration::from_secs(var("HOOK_RETRY_BACKOFF", 1)),
+        }
+    }
+}
+
+/// External hook call which failed after all retries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryFailure {
+    pub reason: String,
+    pub attempts: u32,
+}
+
+/// Sends the request of an external hook and retries failed calls with an
+/// exponential backoff. Error status codes of the receiver count as failures.
+pub async fn send_with_retries(
+    request: reqwest::RequestBuilder,
+    config: &HookQueueConfig,
+) -> std::result::Result<(), DeliveryFailure> {
+    let mut backoff = config.retry_backoff;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let Some(attempt) = request.try_clone() else {
+            return Err(DeliveryFailure {
+                reason: "Hook request can not be retried".to_string(),
+                attempts,
+            });
+        };
+        let reason = match attempt.send().await.and_then(|r| r.error_for_status()) {
+            Ok(_) => return Ok(()),
+            Err(err) => err.to_string(),
+        };
+        if attempts > config.max_retries {
+            return Err(DeliveryFailure { reason, attempts });
+        }
+        log::warn!("External hook call failed (attempt {attempts}): {reason}");
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    }
+}
+
+/// Limits the concurrent hook executions overall and per target host.
+/// Hooks waiting for a busy host do not occupy a worker, so one slow
+/// host can only use its share of the workers.
+#[derive(Debug, Clone)]
+pub struct HookLimiter {
+    workers: Arc<Semaphore>,
+    worker_count: usize,
+    // Messages taken from the queue which are not finished yet
+    pending: Arc<Semaphore>,
+    pending_count: usize,
+    workers_per_host: usize,
+    hosts: Arc<DashMap<String, Arc<Semaphore>>>,
+}
+
+impl HookLimiter {
+    pub fn new(config: &HookQueueConfig) -> Self {
+        // Waiting hooks are limited like the queue itself
+        let pending_count = config.workers + config.capacity;
+        HookLimiter {
+            workers: Arc::new(Semaphore::new(config.workers)),
+            worker_count: config.workers,
+            pending: Arc::new(Semaphore::new(pending_count)),
+            pending_count,
+            workers_per_host: config.workers_per_host,
+            hosts: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Waits until another message can be taken from the queue
+    pub async fn reserve(&self) -> Result<OwnedSemaphorePermit> {
+        Ok(self.pending.clone().acquire_owned().await?)
+    }
+
+    /// Runs a hook once a worker and a slot of its target host are free
+    pub async fn run<F: Future<Output = ()>>(
+        &self,
+        reservation: OwnedSemaphorePermit,
+        host: Option<String>,
+        hook: F,
+    ) -> Result<()> {
+        let _host_permit = match host {
+            Some(host) => {
+                let semaphore = self
+                    .hosts
+                    .entry(host)
+                    .or_insert_with(|| Arc::new(Semaphore::new(self.workers_per_host)))
+                    .clone();
+                Some(semaphore.acquire_owned().await?)
+            }
+            None => None,
+        };
+        let _worker_permit = self.workers.acquire().await?;
+        hook.await;
+        drop(reservation);
+        Ok(())
+    }
+
+    pub fn running(&self) -> usize {
+        self.worker_count - self.workers.available_permits()
+    }
+
+    pub fn waiting(&self) -> usize {
+        (self.pending_count - self.pending.available_permits()).saturating_sub(self.running())
+    }
+
+    /// Removes the limits of hosts without running or waiting hooks
+    fn cleanup_hosts(&self) {
+        self.hosts.retain(|_, semaphore| {
+            Arc::strong_count(semaphore) > 1
+                || semaphore.available_permits() < self.workers_per_host
+        });
+    }
+
+    /// Periodically logs the depth of the hook queue
+    pub fn start_metrics_loop<T: Send + 'static>(&self, queue: Receiver<T>, interval: Duration) {
+        let limiter = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                limiter.cleanup_hosts();
+                let queued = queue.len();
+                let message = format!(
+                    "Hook queue: queued={} waiting={} running={} hosts={}",
+                    queued,
+                    limiter.waiting(),
+                    limiter.running(),
+                    limiter.hosts.len()
+                );
+                if queue.capacity().is_some_and(|capacity| queued >= capacity) {
+                    log::warn!("{message} (full)");
+                } else {
+                    log::info!("{message}");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Gauge {
+        current: AtomicUsize,
+        max: AtomicUsize,
+    }
+
+    impl Gauge {
+        fn enter(&self) {
+            let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(current, Ordering::SeqCst);
+        }
+
+        fn exit(&self) {
+            self.current.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hook_concurrency_limits() {
+        let config = HookQueueConfig {
+            capacity: 10,
+            workers: 4,
+            workers_per_host: 2,
+            overflow: OverflowPolicy::Block,
+            replay_interval: Duration::from_secs(30),
+            metrics_interval: Duration::from_secs(60),
+            max_retries: 3,
+            retry_backoff: Duration::from_secs(1),
+        };
+        let limiter = HookLimiter::new(&config);
+        let total = Arc::new(Gauge::default());
+        // Host 0 is slow, hosts 1 and 2 are fast
+        let hosts: Vec<Arc<Gauge>> = (0..3).map(|_| Arc::new(Gauge::default())).collect();
+
+        // Burst of hooks, mostly for one slow host
+        let (sender, receiver) = async_channel::bounded(config.capacity);
+        tokio::spawn(async move {
+            for i in 0..60usize {
+                let host = if i % 3 == 0 { i % 2 + 1 } else { 0 };
+                sender.send(host).await.unwrap();
+            }
+        });
+        let mut handles = Vec::new();
+        loop {
+            let reservation = limiter.reserve().await.unwrap();
+            let Ok(host) = receiver.recv().await else {
+                break;
+            };
+            assert!(limiter.waiting() + limiter.running() <= config.workers + config.capacity);
+            let (limiter, total, gauge) = (limiter.clone(), total.clone(), hosts[host].clone());
+            handles.push(tokio::spawn(async move {
+                limiter
+                    .run(reservation, Some(format!("host-{host}")), async {
+                        total.enter();
+                        gauge.enter();
+                        let millis = if host == 0 { 20 } else { 5 };
+                        tokio::time::sleep(Duration::from_millis(millis)).await;
+                        gauge.exit();
+                        total.exit();
+                    })
+                    .await
+                    .unwrap();
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert!(total.max.load(Ordering::SeqCst) <= config.workers);
+        for gauge in &hosts {
+            assert!(gauge.max.load(Ordering::SeqCst) <= config.workers_per_host);
+        }
+        // The slow host did not block the other hosts
+        assert_eq!(hosts[0].max.load(Ordering::SeqCst), config.workers_per_host);
+        assert_eq!(limiter.running(), 0);
+        assert_eq!(limiter.waiting(), 0);
+    }
+}
@@ -1 +1,5 @@
 pub mod hook_handler;
+pub mod hook_queue;
+pub mod preview_hook;
+pub mod scan_hook;
+pub mod validation_hook;
@@ -0,0 +1,302 @@
+use crate::database::dsls::hook_dsl::{
+    HookVariant, HookWithAssociatedProject, InternalHook, Trigger, TriggerVariant,
+};
+use crate::database::dsls::internal_relation_dsl::INTERNAL_RELATION_VARIANT_PREVIEW;
+use crate::database::dsls::object_dsl::{Object, ObjectWithRelations};
+use crate::database::enums::ObjectType;
+use anyhow::{anyhow, bail, Result};
+use diesel_ulid::DieselUlid;
+use lazy_static::lazy_static;
+use postgres_types::Json;
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Label which overrides the content type of an object guessed from its name
+pub const CONTENT_TYPE_KEY: &str = "app.aruna-storage.org/content-type";
+/// Label of objects with a preview, the value is the id of the preview object
+pub const PREVIEW_KEY: &str = "app.aruna-storage.org/preview";
+/// Reserved id of the built-in preview hook
+pub const PREVIEW_HOOK_ID: &str = "00000000000000000000000001";
+
+lazy_static! {
+    pub static ref PREVIEW_CONFIG: Option<PreviewConfig> = PreviewConfig::from_env();
+}
+
+/// Generator endpoint which is called for every finished object of an eligible content type
+#[derive(Debug, Clone)]
+pub struct PreviewConfig {
+    pub url: String,
+    pub token: Option<String>,
+    pub timeout: Duration,
+    // Content types like `image/png` or `image/*`
+    pub content_types: Vec<String>,
+}
+
+impl PreviewConfig {
+    pub fn from_env() -> Option<Self> {
+        let url = dotenvy::var("PREVIEW_HOOK_URL").ok()?;
+        Some(PreviewConfig {
+            url,
+            token: dotenvy::var("PREVIEW_HOOK_TOKEN").ok(),
+            timeout: Duration::from_secs(
+                dotenvy::var("PREVIEW_HOOK_TIMEOUT")
+                    .map(|var| var.parse::<u64>().unwrap_or(60))
+                    .unwrap_or(60),
+            ),
+            content_types: dotenvy::var("PREVIEW_CONTENT_TYPES")
+                .unwrap_or_else(|_| "image/*,application/pdf".to_string())
+                .split(',')
+                .map(|content_type| content_type.trim().to_ascii_lowercase())
+                .filter(|content_type| !content_type.is_empty())
+                .collect(),
+        })
+    }
+
+    pub fn is_eligible(&self, content_type: &str) -> bool {
+        matches_content_type(&self.content_types, content_type)
+    }
+}
+
+/// Matches a content type against patterns like `image/png` or `image/*`
+pub fn matches_content_type(patterns: &[String], content_type: &str) -> bool {
+    let content_type = content_type.to_ascii_lowercase();
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_suffix("/*") {
+            Some(prefix) => content_type
+                .split_once('/')
+                .is_some_and(|(main, _)| main == prefix),
+            None => pattern == content_type,
+        }
+    })
+}
+
+/// Content type of an object from its content type label or the extension of its name
+pub fn content_type(object: &Object) -> Option<String> {
+    if let Some(kv) = object
+        .key_values
+        .0
+         .0
+        .iter()
+        .find(|kv| kv.key == CONTENT_TYPE_KEY)
+    {
+        return Some(kv.value.trim().to_ascii_lowercase());
+    }
+    guess_content_type(&object.name).map(|content_type| content_type.to_string())
+}
+
+fn guess_content_type(name: &str) -> Option<&'static str> {
+    let (_, extension) = name.rsplit_once('.')?;
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "tif" | "tiff" => Some("image/tiff"),
+        "bmp" => Some("image/bmp"),
+        "svg" => Some("image/svg+xml"),
+        "pdf" => Some("application/pdf"),
+        "csv" => Some("text/csv"),
+        "json" => Some("application/json"),
+        _ => None,
+    }
+}
+
+/// Returns whether a preview is generated for the finished object.
+/// Previews are never generated for previews themselves.
+pub fn needs_preview(object: &ObjectWithRelations) -> bool {
+    let Some(config) = PREVIEW_CONFIG.as_ref() else {
+        return false;
+    };
+    object.object.object_type == ObjectType::OBJECT
+        && !object
+            .inbound
+            .0
+            .iter()
+            .any(|relation| relation.relation_name == INTERNAL_RELATION_VARIANT_PREVIEW)
+        && content_type(&object.object)
+            .is_some_and(|content_type| config.is_eligible(&content_type))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PreviewRequest {
+    pub object_id: DieselUlid,
+    pub name: String,
+    pub content_type: String,
+    pub download_url: String,
+}
+
+/// Thumbnail returned by the generator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Thumbnail {
+    /// Name of the preview object, unique for every revision of the original
+    pub fn object_name(&self, original_id: &DieselUlid) -> String {
+        let extension = match self.content_type.as_str() {
+            "image/jpeg" => "jpg",
+            "image/svg+xml" => "svg",
+            content_type => content_type
+                .split_once('/')
+                .map_or("bin", |(_, subtype)| subtype),
+        };
+        format!("preview-{original_id}.{extension}")
+    }
+}
+
+/// Sends the object to the generator and waits for the thumbnail image in the response body
+pub async fn request_preview(
+    client: &reqwest::Client,
+    config: &PreviewConfig,
+    request: &PreviewRequest,
+) -> Result<Thumbnail> {
+    let mut builder = client.post(&config.url).timeout(config.timeout);
+    if let Some(token) = &config.token {
+        builder = builder.bearer_auth(token);
+    }
+    let response = builder.json(request).send().await.map_err(|e| {
+        if e.is_timeout() {
+            anyhow!("Preview generator timed out")
+        } else {
+            anyhow!("Preview generator request failed: {e}")
+        }
+    })?;
+    let response = response.error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    if !content_type.starts_with("image/") {
+        bail!("Preview generator returned no image");
+    }
+    let data = response.bytes().await?.to_vec();
+    if data.is_empty() {
+        bail!("Preview generator returned an empty image");
+    }
+    Ok(Thumbnail { content_type, data })
+}
+
+/// Built-in hook which is sent to the hook handler for objects that get a preview
+pub fn preview_hook(
+    project_id: DieselUlid,
+    owner: DieselUlid,
+) -> Result<HookWithAssociatedProject> {
+    let timeout = PREVIEW_CONFIG
+        .as_ref()
+        .map(|config| config.timeout)
+        .unwrap_or_default();
+    Ok(HookWithAssociatedProject {
+        id: DieselUlid::from_str(PREVIEW_HOOK_ID)?,
+        name: "preview".to_string(),
+        description: "Built-in preview generation".to_string(),
+        project_ids: vec![project_id],
+        owner,
+        trigger: Json(Trigger {
+            variant: TriggerVariant::OBJECT_FINISHED,
+            filter: vec![],
+        }),
+        timeout: chrono::Utc::now().naive_utc() + timeout,
+        hook: Json(HookVariant::Internal(InternalHook::Preview)),
+        project_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Minimal generator which answers every request with a `content_type` body
+    async fn generator(content_type: &'static str, body: &'static [u8]) -> PreviewConfig {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/preview", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0; 4096];
+                let _ = stream.read(&mut buf).await;
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: {}\r\ncontent-length: {}\r\n\r\n",
+                    content_type,
+                    body.len()
+                );
+                let _ = stream.write_all(header.as_bytes()).await;
+                let _ = stream.write_all(body).await;
+            }
+        });
+        PreviewConfig {
+            url,
+            token: None,
+            timeout: Duration::from_secs(5),
+            content_types: vec!["image/*".to_string(), "application/pdf".to_string()],
+        }
+    }
+
+    fn request() -> PreviewRequest {
+        PreviewRequest {
+            object_id: DieselUlid::generate(),
+            name: "scan.tiff".to_string(),
+            content_type: "image/tiff".to_string(),
+            download_url: "http://localhost/bucket/scan.tiff".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_preview() {
+        let config = generator("image/png", b"\x89PNG thumbnail").await;
+        let request = request();
+        let thumbnail = request_preview(&reqwest::Client::new(), &config, &request)
+            .await
+            .unwrap();
+        assert_eq!(thumbnail.content_type, "image/png");
+        assert_eq!(thumbnail.data, b"\x89PNG thumbnail");
+        assert_eq!(
+            thumbnail.object_name(&request.object_id),
+            format!("preview-{}.png", request.object_id)
+        );
+
+        // Generators have to answer with an image
+        let config = generator("application/json", b"{}").await;
+        assert!(request_preview(&reqwest::Client::new(), &config, &request)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_preview_content_types() {
+        let config = PreviewConfig {
+            url: "http://localhost/preview".to_string(),
+            token: None,
+            timeout: Duration::from_secs(5),
+            content_types: vec!["image/*".to_string(), "application/pdf".to_string()],
+        };
+        assert!(config.is_eligible("image/png"));
+        assert!(config.is_eligible("IMAGE/TIFF"));
+        assert!(config.is_eligible("application/pdf"));
+        assert!(!config.is_eligible("application/json"));
+        assert!(!config.is_eligible("imagefoo"));
+
+        assert_eq!(
+            guess_content_type("photos/IMG_0001.JPG"),
+            Some("image/jpeg")
+        );
+        assert_eq!(guess_content_type("paper.pdf"), Some("application/pdf"));
+        assert_eq!(guess_content_type("reads.fastq"), None);
+        assert_eq!(guess_content_type("README"), None);
+    }
+
+    #[test]
+    fn test_preview_hook() {
+        let project_id = DieselUlid::generate();
+        let hook = preview_hook(project_id, DieselUlid::generate()).unwrap();
+        assert_eq!(hook.id.to_string(), PREVIEW_HOOK_ID);
+        assert_eq!(hook.hook.0, HookVariant::Internal(InternalHook::Preview));
+    }
+}
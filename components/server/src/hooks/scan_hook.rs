@@ -0,0 +1,313 @@
+use crate::caching::cache::Cache;
+use crate::database::dsls::hook_dsl::{
+    HookVariant, HookWithAssociatedProject, InternalHook, Trigger, TriggerVariant,
+};
+use crate::database::dsls::scan_attestation_dsl::ScanAttestation;
+use crate::database::enums::{ObjectMapping, ObjectStatus};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use lazy_static::lazy_static;
+use postgres_types::Json;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Project key-value which enables scanning of all finished objects in the project
+pub const SCAN_ENABLED_KEY: &str = "app.aruna-storage.org/scan";
+/// Hook status key-value with the access key the scanner uses to download a scanning object
+pub const SCAN_TOKEN_KEY: &str = "app.aruna-storage.org/scan-token";
+/// Label with the verdict of the last scan
+pub const SCAN_VERDICT_KEY: &str = "app.aruna-storage.org/scan-verdict";
+/// Reserved id of the built-in scan hook
+pub const SCAN_HOOK_ID: &str = "00000000000000000000000000";
+
+lazy_static! {
+    pub static ref SCAN_CONFIG: Option<ScanConfig> = ScanConfig::from_env();
+}
+
+/// Scanner endpoint which is called for every finished object of projects with enabled scanning
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    pub url: String,
+    pub token: Option<String>,
+    pub timeout: Duration,
+    // Clean verdicts of content are reused for this long, zero disables reuse
+    pub attestation_ttl: Duration,
+}
+
+impl ScanConfig {
+    pub fn from_env() -> Option<Self> {
+        let url = dotenvy::var("SCAN_HOOK_URL").ok()?;
+        Some(ScanConfig {
+            url,
+            token: dotenvy::var("SCAN_HOOK_TOKEN").ok(),
+            timeout: Duration::from_secs(
+                dotenvy::var("SCAN_HOOK_TIMEOUT")
+                    .map(|var| var.parse::<u64>().unwrap_or(300))
+                    .unwrap_or(300),
+            ),
+            attestation_ttl: Duration::from_secs(
+                dotenvy::var("SCAN_ATTESTATION_TTL")
+                    .map(|var| var.parse::<u64>().unwrap_or(2592000))
+                    .unwrap_or(2592000),
+            ),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScanRequest {
+    pub object_id: DieselUlid,
+    pub name: String,
+    pub content_len: i64,
+    pub download_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "verdict", rename_all = "UPPERCASE")]
+pub enum ScanVerdict {
+    Clean,
+    Infected { details: Option<String> },
+}
+
+impl ScanVerdict {
+    /// Only clean objects become available, infected objects are quarantined
+    pub fn object_status(&self) -> ObjectStatus {
+        match self {
+            ScanVerdict::Clean => ObjectStatus::AVAILABLE,
+            ScanVerdict::Infected { .. } => ObjectStatus::UNAVAILABLE,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            ScanVerdict::Clean => "clean".to_string(),
+            ScanVerdict::Infected { details: None } => "infected".to_string(),
+            ScanVerdict::Infected {
+                details: Some(details),
+            } => format!("infected: {details}"),
+        }
+    }
+}
+
+/// Answer of the scanner with the version of the signatures used for the scan
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScanResponse {
+    #[serde(flatten)]
+    pub verdict: ScanVerdict,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// Verdict of an earlier scan of the same content which replaces a new scan.
+/// Infected content stays rejected, clean verdicts expire after the ttl and are
+/// only valid for the current signature version of the scanner.
+pub fn cached_verdict(
+    attestation: &ScanAttestation,
+    current_version: Option<&str>,
+    ttl: Duration,
+    now: NaiveDateTime,
+) -> Option<ScanVerdict> {
+    match &attestation.verdict.0 {
+        infected @ ScanVerdict::Infected { .. } => Some(infected.clone()),
+        ScanVerdict::Clean => {
+            let expires_at = attestation.scanned_at + chrono::Duration::from_std(ttl).ok()?;
+            (expires_at > now && attestation.scanner_version.as_deref() == current_version)
+                .then_some(ScanVerdict::Clean)
+        }
+    }
+}
+
+/// Sends the object to the scanner and waits for its verdict.
+/// Scanners that do not answer within the configured timeout produce an error.
+pub async fn request_scan(
+    client: &reqwest::Client,
+    config: &ScanConfig,
+    request: &ScanRequest,
+) -> Result<ScanResponse> {
+    let mut builder = client.post(&config.url).timeout(config.timeout);
+    if let Some(token) = &config.token {
+        builder = builder.bearer_auth(token);
+    }
+    let response = builder.json(request).send().await.map_err(|e| {
+        if e.is_timeout() {
+            anyhow!("Scanner timed out")
+        } else {
+            anyhow!("Scanner request failed: {e}")
+        }
+    })?;
+    let response = response.error_for_status()?;
+    Ok(response.json::<ScanResponse>().await?)
+}
+
+/// Returns the project which requires a scan of the object, if any
+pub fn scan_project(cache: &Cache, object_id: &DieselUlid) -> Result<Option<DieselUlid>> {
+    for branch in cache.upstream_dfs_iterative(object_id)? {
+        for parent in branch {
+            let ObjectMapping::PROJECT(project_id) = parent else {
+                continue;
+            };
+            let enabled =
+                cache.get_object(&project_id).is_some_and(|project| {
+                    project.object.key_values.0 .0.iter().any(|kv| {
+                        kv.key == SCAN_ENABLED_KEY && kv.value.eq_ignore_ascii_case("true")
+                    })
+                });
+            if enabled {
+                return Ok(Some(project_id));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Built-in hook which is sent to the hook handler for objects that need to be scanned
+pub fn scan_hook(project_id: DieselUlid, owner: DieselUlid) -> Result<HookWithAssociatedProject> {
+    let timeout = SCAN_CONFIG
+        .as_ref()
+        .map(|config| config.timeout)
+        .unwrap_or_default();
+    Ok(HookWithAssociatedProject {
+        id: DieselUlid::from_str(SCAN_HOOK_ID)?,
+        name: "scan".to_string(),
+        description: "Built-in malware scan".to_string(),
+        project_ids: vec![project_id],
+        owner,
+        trigger: Json(Trigger {
+            variant: TriggerVariant::OBJECT_FINISHED,
+            filter: vec![],
+        }),
+        timeout: chrono::Utc::now().naive_utc() + timeout,
+        hook: Json(HookVariant::Internal(InternalHook::Scan)),
+        project_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Minimal scanner which answers every request with `body` after `delay`
+    async fn scanner(body: &'static str, delay: Duration) -> ScanConfig {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/scan", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0; 4096];
+                let _ = stream.read(&mut buf).await;
+                tokio::time::sleep(delay).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        ScanConfig {
+            url,
+            token: Some("secret".to_string()),
+            timeout: Duration::from_millis(200),
+            attestation_ttl: Duration::from_secs(60),
+        }
+    }
+
+    fn request() -> ScanRequest {
+        ScanRequest {
+            object_id: DieselUlid::generate(),
+            name: "upload.bin".to_string(),
+            content_len: 42,
+            download_url: "http://localhost/bucket/upload.bin".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_clean() {
+        let config = scanner(r#"{"verdict":"CLEAN","version":"27001"}"#, Duration::ZERO).await;
+        let response = request_scan(&reqwest::Client::new(), &config, &request())
+            .await
+            .unwrap();
+        assert_eq!(response.version.as_deref(), Some("27001"));
+        let verdict = response.verdict;
+        assert_eq!(verdict, ScanVerdict::Clean);
+        assert_eq!(verdict.object_status(), ObjectStatus::AVAILABLE);
+        assert_eq!(verdict.label(), "clean");
+    }
+
+    #[tokio::test]
+    async fn test_scan_infected() {
+        let config = scanner(
+            r#"{"verdict":"INFECTED","details":"Eicar-Test-Signature"}"#,
+            Duration::ZERO,
+        )
+        .await;
+        let response = request_scan(&reqwest::Client::new(), &config, &request())
+            .await
+            .unwrap();
+        assert_eq!(response.version, None);
+        let verdict = response.verdict;
+        assert_eq!(
+            verdict,
+            ScanVerdict::Infected {
+                details: Some("Eicar-Test-Signature".to_string())
+            }
+        );
+        assert_eq!(verdict.object_status(), ObjectStatus::UNAVAILABLE);
+        assert_eq!(verdict.label(), "infected: Eicar-Test-Signature");
+    }
+
+    #[tokio::test]
+    async fn test_scan_timeout() {
+        let config = scanner(r#"{"verdict":"CLEAN"}"#, Duration::from_secs(5)).await;
+        let err = request_scan(&reqwest::Client::new(), &config, &request())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Scanner timed out");
+    }
+
+    #[test]
+    fn test_cached_verdict() {
+        let now = chrono::Utc::now().naive_utc();
+        let ttl = Duration::from_secs(3600);
+        let attestation = |verdict, version: &str, age| ScanAttestation {
+            hash: "a".repeat(64),
+            verdict: Json(verdict),
+            scanner_version: Some(version.to_string()),
+            scanned_at: now - chrono::Duration::seconds(age),
+        };
+
+        // Clean content of the current signatures is not scanned again
+        let clean = attestation(ScanVerdict::Clean, "v1", 60);
+        assert_eq!(
+            cached_verdict(&clean, Some("v1"), ttl, now),
+            Some(ScanVerdict::Clean)
+        );
+        // New signatures and expired attestations require a new scan
+        assert_eq!(cached_verdict(&clean, Some("v2"), ttl, now), None);
+        let expired = attestation(ScanVerdict::Clean, "v1", 7200);
+        assert_eq!(cached_verdict(&expired, Some("v1"), ttl, now), None);
+        assert_eq!(
+            cached_verdict(&clean, Some("v1"), Duration::ZERO, now),
+            None
+        );
+
+        // Infected content is always rejected
+        let infected = attestation(ScanVerdict::Infected { details: None }, "v1", 7200);
+        assert_eq!(
+            cached_verdict(&infected, Some("v2"), ttl, now),
+            Some(ScanVerdict::Infected { details: None })
+        );
+    }
+
+    #[test]
+    fn test_scan_hook() {
+        let project_id = DieselUlid::generate();
+        let hook = scan_hook(project_id, DieselUlid::generate()).unwrap();
+        assert_eq!(hook.id.to_string(), SCAN_HOOK_ID);
+        assert_eq!(hook.project_id, project_id);
+        assert_eq!(hook.hook.0, HookVariant::Internal(InternalHook::Scan));
+    }
+}
@@ -0,0 +1,373 @@
+use crate::caching::cache::Cache;
+use crate::database::dsls::hook_dsl::{
+    HookVariant, HookWithAssociatedProject, InternalHook, Trigger, TriggerVariant,
+};
+use crate::database::dsls::object_dsl::Object;
+use crate::database::enums::{ObjectMapping, ObjectType};
+use crate::hooks::preview_hook::{content_type, matches_content_type};
+use anyhow::{anyhow, Result};
+use diesel_ulid::DieselUlid;
+use futures::{Stream, StreamExt};
+use lazy_static::lazy_static;
+use postgres_types::Json;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Collection key-value with a JSON validation rule for the content of its objects, e.g.
+/// `{"content_types": ["text/csv"], "validator": {"type": "CSV", "required_columns": ["id"]}, "policy": "REJECT"}`.
+/// Collections can have multiple rules, all matching rules of all collections apply.
+pub const VALIDATION_KEY: &str = "app.aruna-storage.org/content-validation";
+/// Label with the details of a failed validation
+pub const VALIDATION_FAILED_KEY: &str = "app.aruna-storage.org/validation-failed";
+/// Reserved id of the built-in validation hook
+pub const VALIDATION_HOOK_ID: &str = "00000000000000000000000002";
+
+lazy_static! {
+    pub static ref VALIDATION_CONFIG: ValidationConfig = ValidationConfig::from_env();
+}
+
+/// Limits of content validations
+#[derive(Debug, Clone)]
+pub struct ValidationConfig {
+    // Validators which need the whole content fail for larger objects
+    pub max_size: usize,
+    pub timeout: Duration,
+}
+
+impl ValidationConfig {
+    pub fn from_env() -> Self {
+        ValidationConfig {
+            max_size: dotenvy::var("VALIDATION_MAX_SIZE")
+                .map(|var| var.parse::<usize>().unwrap_or(16777216))
+                .unwrap_or(16777216),
+            timeout: Duration::from_secs(
+                dotenvy::var("VALIDATION_TIMEOUT")
+                    .map(|var| var.parse::<u64>().unwrap_or(300))
+                    .unwrap_or(300),
+            ),
+        }
+    }
+}
+
+/// What happens to objects whose content does not conform
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ValidationPolicy {
+    // The finish is rejected, the object becomes unavailable
+    Reject,
+    // The object becomes available with a validation-failed label
+    Flag,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "UPPERCASE")]
+pub enum Validator {
+    /// The header row has to contain all required columns
+    Csv {
+        required_columns: Vec<String>,
+        #[serde(default = "default_delimiter")]
+        delimiter: char,
+    },
+    /// The content has to be a JSON object, or an array of objects, with all required fields
+    Json { required_fields: Vec<String> },
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+impl Validator {
+    /// Csv validators only read the header row
+    fn needs_whole_content(&self) -> bool {
+        matches!(self, Validator::Json { .. })
+    }
+
+    fn validate(&self, content: &[u8]) -> std::result::Result<(), String> {
+        match self {
+            Validator::Csv {
+                required_columns,
+                delimiter,
+            } => {
+                let header = content
+                    .split(|byte| *byte == b'\n')
+                    .next()
+                    .unwrap_or_default();
+                let header = std::str::from_utf8(header)
+                    .map_err(|_| "CSV header is not valid UTF-8".to_string())?;
+                let columns = header
+                    .trim_start_matches('\u{feff}')
+                    .trim_end_matches('\r')
+                    .split(*delimiter)
+                    .map(|column| column.trim().trim_matches('"'))
+                    .collect::<HashSet<_>>();
+                let missing = required_columns
+                    .iter()
+                    .filter(|column| !columns.contains(column.as_str()))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if missing.is_empty() {
+                    Ok(())
+                } else {
+                    Err(format!("Missing CSV columns: {}", missing.join(", ")))
+                }
+            }
+            Validator::Json { required_fields } => {
+                let value = serde_json::from_slice::<serde_json::Value>(content)
+                    .map_err(|e| format!("Invalid JSON: {e}"))?;
+                let documents = match &value {
+                    serde_json::Value::Array(documents) => documents.iter().collect::<Vec<_>>(),
+                    document => vec![document],
+                };
+                for (index, document) in documents.into_iter().enumerate() {
+                    let Some(document) = document.as_object() else {
+                        return Err(format!("JSON document {index} is not an object"));
+                    };
+                    if let Some(field) = required_fields
+                        .iter()
+                        .find(|field| !document.contains_key(field.as_str()))
+                    {
+                        return Err(format!("JSON document {index} misses field {field}"));
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ValidationRule {
+    // Content types like `text/csv` or `application/*`
+    pub content_types: Vec<String>,
+    pub validator: Validator,
+    pub policy: ValidationPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationOutcome {
+    Passed,
+    Flagged { details: String },
+    Rejected { details: String },
+}
+
+/// Validation rules of all collections of the object which match its content type
+pub fn validation_rules(cache: &Cache, object: &Object) -> Result<Vec<ValidationRule>> {
+    if object.object_type != ObjectType::OBJECT {
+        return Ok(vec![]);
+    }
+    let Some(content_type) = content_type(object) else {
+        return Ok(vec![]);
+    };
+    let mut collections = cache
+        .upstream_dfs_iterative(&object.id)?
+        .into_iter()
+        .flatten()
+        .filter_map(|parent| match parent {
+            ObjectMapping::COLLECTION(id) => Some(id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    collections.sort();
+    collections.dedup();
+
+    let mut rules = Vec::new();
+    for collection in collections.iter().filter_map(|id| cache.get_object(id)) {
+        for kv in collection
+            .object
+            .key_values
+            .0
+             .0
+            .iter()
+            .filter(|kv| kv.key == VALIDATION_KEY)
+        {
+            match serde_json::from_str::<ValidationRule>(&kv.value) {
+                Ok(rule) if matches_content_type(&rule.content_types, &content_type) => {
+                    rules.push(rule)
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!(
+                    "Invalid validation rule of collection {}: {e}",
+                    collection.object.id
+                ),
+            }
+        }
+    }
+    Ok(rules)
+}
+
+/// Streams the content through the validators of the rules. Only the header row is
+/// read for CSV validators, JSON validators read the whole content up to `max_size`.
+pub async fn validate_content<B: AsRef<[u8]>, E: Display>(
+    rules: &[ValidationRule],
+    mut content: impl Stream<Item = std::result::Result<B, E>> + Unpin,
+    max_size: usize,
+) -> Result<ValidationOutcome> {
+    let needs_whole_content = rules
+        .iter()
+        .any(|rule| rule.validator.needs_whole_content());
+    let mut buffer = Vec::new();
+    let mut too_large = false;
+    while let Some(chunk) = content.next().await {
+        let chunk = chunk.map_err(|e| anyhow!("Reading content failed: {e}"))?;
+        buffer.extend_from_slice(chunk.as_ref());
+        if buffer.len() > max_size {
+            too_large = true;
+            break;
+        }
+        if !needs_whole_content && buffer.contains(&b'\n') {
+            break;
+        }
+    }
+
+    let mut flagged = Vec::new();
+    let mut rejected = Vec::new();
+    for rule in rules {
+        let result = if too_large && rule.validator.needs_whole_content() {
+            Err(format!(
+                "Content exceeds the validation limit of {max_size} bytes"
+            ))
+        } else {
+            rule.validator.validate(&buffer)
+        };
+        if let Err(details) = result {
+            match rule.policy {
+                ValidationPolicy::Reject => rejected.push(details),
+                ValidationPolicy::Flag => flagged.push(details),
+            }
+        }
+    }
+    Ok(if !rejected.is_empty() {
+        ValidationOutcome::Rejected {
+            details: rejected.join("; "),
+        }
+    } else if !flagged.is_empty() {
+        ValidationOutcome::Flagged {
+            details: flagged.join("; "),
+        }
+    } else {
+        ValidationOutcome::Passed
+    })
+}
+
+/// Built-in hook which is sent to the hook handler for objects that need to be validated
+pub fn validation_hook(
+    project_id: DieselUlid,
+    owner: DieselUlid,
+) -> Result<HookWithAssociatedProject> {
+    Ok(HookWithAssociatedProject {
+        id: DieselUlid::from_str(VALIDATION_HOOK_ID)?,
+        name: "validation".to_string(),
+        description: "Built-in content validation".to_string(),
+        project_ids: vec![project_id],
+        owner,
+        trigger: Json(Trigger {
+            variant: TriggerVariant::OBJECT_FINISHED,
+            filter: vec![],
+        }),
+        timeout: chrono::Utc::now().naive_utc() + VALIDATION_CONFIG.timeout,
+        hook: Json(HookVariant::Internal(InternalHook::Validate)),
+        project_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(
+        chunks: Vec<&'static str>,
+    ) -> impl Stream<Item = std::result::Result<&'static [u8], std::io::Error>> + Unpin {
+        futures::stream::iter(chunks.into_iter().map(|chunk| Ok(chunk.as_bytes())))
+    }
+
+    fn csv_rule(policy: ValidationPolicy) -> ValidationRule {
+        serde_json::from_value(serde_json::json!({
+            "content_types": ["text/csv"],
+            "validator": {"type": "CSV", "required_columns": ["sample_id", "organism"]},
+            "policy": policy,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_csv_missing_column_rejected() {
+        let rules = vec![csv_rule(ValidationPolicy::Reject)];
+        let outcome = validate_content(
+            &rules,
+            content(vec!["sample_id,concentration\n", "s1,0.5\n"]),
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            outcome,
+            ValidationOutcome::Rejected {
+                details: "Missing CSV columns: organism".to_string()
+            }
+        );
+
+        // Header split across chunks with all columns
+        let outcome = validate_content(
+            &rules,
+            content(vec![
+                "\"sample_id\",conc",
+                "entration,organism\r\n",
+                "s1,0.5,E. coli\n",
+            ]),
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, ValidationOutcome::Passed);
+
+        // Flagging rules do not reject the finish
+        let outcome = validate_content(
+            &[csv_rule(ValidationPolicy::Flag)],
+            content(vec!["sample_id\n"]),
+            1024,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(outcome, ValidationOutcome::Flagged { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_json_required_fields() {
+        let rules = vec![ValidationRule {
+            content_types: vec!["application/json".to_string()],
+            validator: Validator::Json {
+                required_fields: vec!["id".to_string()],
+            },
+            policy: ValidationPolicy::Reject,
+        }];
+        let outcome = validate_content(
+            &rules,
+            content(vec![r#"[{"id": 1}, "#, r#"{"id": 2}]"#]),
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, ValidationOutcome::Passed);
+
+        let outcome = validate_content(&rules, content(vec![r#"[{"id": 1}, {"name": 2}]"#]), 1024)
+            .await
+            .unwrap();
+        assert!(matches!(outcome, ValidationOutcome::Rejected { .. }));
+
+        // Content above the limit can not be validated
+        let outcome = validate_content(&rules, content(vec![r#"{"id": 1}"#]), 4)
+            .await
+            .unwrap();
+        assert!(matches!(outcome, ValidationOutcome::Rejected { .. }));
+    }
+
+    #[test]
+    fn test_validation_hook() {
+        let hook = validation_hook(DieselUlid::generate(), DieselUlid::generate()).unwrap();
+        assert_eq!(hook.id.to_string(), VALIDATION_HOOK_ID);
+        assert_eq!(hook.hook.0, HookVariant::Internal(InternalHook::Validate));
+    }
+}
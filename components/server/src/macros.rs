@@ -88,7 +88,11 @@ macro_rules! impl_grpc_server {
 macro_rules! tonic_internal {
     ($result:expr, $message:expr) => {
         $result.map_err(|e| {
-            log::error!("{}", e);
+            log::error!(
+                "[{}] {}",
+                $crate::utils::request_id_utils::current_request_id().unwrap_or_default(),
+                e
+            );
             let msg = format!("{} : {}", $message, e);
             tonic::Status::internal(msg)
         })?
@@ -99,7 +103,11 @@ macro_rules! tonic_internal {
 macro_rules! tonic_invalid {
     ($result:expr, $message:expr) => {
         $result.map_err(|e| {
-            log::error!("{}", e);
+            log::error!(
+                "[{}] {}",
+                $crate::utils::request_id_utils::current_request_id().unwrap_or_default(),
+                e
+            );
             let msg = format!("{} : {}", $message, e);
             tonic::Status::invalid_argument(msg)
         })?
@@ -110,7 +118,11 @@ macro_rules! tonic_invalid {
 macro_rules! tonic_auth {
     ($result:expr, $message:expr) => {
         $result.map_err(|e| {
-            log::error!("{}", e);
+            log::error!(
+                "[{}] {}",
+                $crate::utils::request_id_utils::current_request_id().unwrap_or_default(),
+                e
+            );
             let msg = format!("{} : {}", $message, e);
             tonic::Status::unauthenticated(msg)
         })?
@@ -121,7 +133,8 @@ macro_rules! tonic_auth {
 macro_rules! log_received {
     ($request:expr) => {
         log::info!(
-            "Received {}",
+            "[{}] Received {}",
+            $crate::utils::request_id_utils::current_request_id().unwrap_or_default(),
             $crate::utils::grpc_utils::type_name_of($request)
         );
         log::debug!("{:?}", $request);
@@ -132,10 +145,47 @@ macro_rules! log_received {
 macro_rules! return_with_log {
     ($response:expr) => {
         log::info!(
-            "Returned {}",
+            "[{}] Returned {}",
+            $crate::utils::request_id_utils::current_request_id().unwrap_or_default(),
             $crate::utils::grpc_utils::type_name_of(&$response)
         );
         log::debug!("{:?}", &$response);
-        return Ok(tonic::Response::new($response));
+        let encoded_len = {
+            use $crate::utils::compression_utils::{EncodedLen, MessageLen, OtherLen};
+            (&EncodedLen(&$response)).message_len()
+        };
+        let mut response = tonic::Response::new($response);
+        $crate::utils::compression_utils::COMPRESSION_CONFIG.apply(&mut response, encoded_len);
+        return Ok(response);
     };
+    ($response:expr, $metadata:expr) => {
+        log::info!(
+            "[{}] Returned {}",
+            $crate::utils::request_id_utils::current_request_id().unwrap_or_default(),
+            $crate::utils::grpc_utils::type_name_of(&$response)
+        );
+        log::debug!("{:?}", &$response);
+        let encoded_len = {
+            use $crate::utils::compression_utils::{EncodedLen, MessageLen, OtherLen};
+            (&EncodedLen(&$response)).message_len()
+        };
+        let mut response = tonic::Response::new($response);
+        *response.metadata_mut() = $metadata;
+        $crate::utils::compression_utils::COMPRESSION_CONFIG.apply(&mut response, encoded_len);
+        return Ok(response);
+    };
+}
+
+/// Enables the configured compression encodings on a generated gRPC server
+#[macro_export]
+macro_rules! with_compression {
+    ($server:expr) => {{
+        let mut server = $server;
+        for encoding in &$crate::utils::compression_utils::COMPRESSION_CONFIG.encodings {
+            server = server
+                .accept_compressed(*encoding)
+                .send_compressed(*encoding);
+        }
+        server
+    }};
 }
@@ -19,7 +19,10 @@ use aruna_rust_api::api::{
     },
 };
 use aruna_server::{
-    auth::{permission_handler::PermissionHandler, token_handler::TokenHandler},
+    auth::{
+        authenticator::StaticKeyAuthenticator, permission_handler::PermissionHandler,
+        token_handler::TokenHandler,
+    },
     caching::{cache::Cache, notifications_handler::NotificationHandler},
     database::{
         self,
@@ -34,17 +37,35 @@ use aruna_server::{
         object::ObjectServiceImpl, projects::ProjectServiceImpl, relations::RelationsServiceImpl,
         search::SearchServiceImpl, users::UserServiceImpl,
     },
-    hooks,
-    middlelayer::db_handler::DatabaseHandler,
-    notification::natsio_handler::NatsIoHandler,
-    search::meilisearch_client::{MeilisearchClient, MeilisearchIndexes},
+    hooks::{
+        self,
+        hook_queue::{OverflowPolicy, HOOK_QUEUE_CONFIG},
+    },
+    middlelayer::{
+        db_handler::DatabaseHandler, expiry_db_handler::start_expiry_cleanup_loop,
+        expiry_request_types::EXPIRY_CLEANUP_INTERVAL, hooks_db_handler::start_hook_replay_loop,
+        notification_retention_db_handler::start_notification_prune_loop,
+        staging_db_handler::start_staging_cleanup_loop,
+        token_db_handler::start_storage_role_cleanup_loop,
+        token_request_types::STORAGE_ROLE_CLEANUP_INTERVAL,
+    },
+    notification::{
+        external_sink::{ProjectSinks, SinkFanout, SinkQueueConfig},
+        natsio_handler::NatsIoHandler,
+    },
+    search::meilisearch_client::MeilisearchClient,
     utils::mailclient::MailClient,
     utils::search_utils,
+    utils::{
+        admission_utils::{AdmissionLayer, ADMISSION_CONFIG},
+        grpc_settings::GrpcSettings,
+        request_id_utils::RequestIdLayer,
+    },
+    with_compression,
 };
 use diesel_ulid::DieselUlid;
 use log::{error, info, warn};
 use simple_logger::SimpleLogger;
-use tonic::transport::Server;
 
 //noinspection RsTypeCheck
 #[tokio::main]
@@ -63,6 +84,10 @@ pub async fn main() -> Result<()> {
     // Load env
     dotenvy::from_filename(".env")?;
 
+    // Validate gRPC settings before anything is initialized
+    let grpc_settings = GrpcSettings::from_env()?;
+    grpc_settings.log();
+
     // Init database connection
     let db = database::connection::Database::new(
         dotenvy::var("DATABASE_HOST")?,
@@ -86,10 +111,31 @@ pub async fn main() -> Result<()> {
     )
     .await?;
     let token_handler_arc = Arc::new(token_handler);
-    cache_arc.sync_cache(db_arc.clone()).await?;
+
+    // Resources are loaded in the background, the server is unavailable if loading fails
+    let cache_warm_up = cache_arc.warm_up(db_arc.clone()).await?;
+    tokio::spawn(async move {
+        match cache_warm_up.await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => {
+                error!("Cache sync failed: {}", err);
+                std::process::exit(1);
+            }
+            Err(err) => {
+                error!("Cache sync panicked: {}", err);
+                std::process::exit(1);
+            }
+        }
+    });
 
     // Init PermissionHandler
-    let authorizer = PermissionHandler::new(cache_arc.clone(), token_handler_arc.clone());
+    let mut authorizer = PermissionHandler::new(cache_arc.clone(), token_handler_arc.clone());
+    if let Ok(keys) = dotenvy::var("STATIC_API_KEYS") {
+        authorizer.add_authenticator(Arc::new(StaticKeyAuthenticator::from_config(
+            cache_arc.clone(),
+            &keys,
+        )?));
+    }
     let auth_arc = Arc::new(authorizer);
 
     // Init NatsIoHandler
@@ -99,8 +145,34 @@ pub async fn main() -> Result<()> {
         .map_err(|_| anyhow::anyhow!("NatsIoHandler init failed"))?;
     let natsio_arc = Arc::new(natsio_handler);
 
+    // Init delivery of notifications buffered during Nats.io outages
+    let nats_recovery_interval = dotenvy::var("NATS_RECOVERY_INTERVAL")
+        .map(|var| var.parse::<u64>().unwrap_or(10))
+        .unwrap_or(10);
+    natsio_arc
+        .clone()
+        .start_recovery_loop(std::time::Duration::from_secs(nats_recovery_interval));
+
+    // Init fan-out of resource events to the external sinks configured by projects
+    if dotenvy::var("NOTIFICATION_SINKS_ENABLED").is_ok_and(|var| var == "true") {
+        let (dead_letter_sender, dead_letter_receiver) = async_channel::unbounded();
+        natsio_arc.set_external_sinks(SinkFanout::start(
+            SinkQueueConfig::from_env(),
+            Arc::new(ProjectSinks::new(cache_arc.clone())),
+            dead_letter_sender,
+        ));
+        let db_clone = db_arc.clone();
+        tokio::spawn(async move {
+            while let Ok(failure) = dead_letter_receiver.recv().await {
+                if let Err(err) = failure.store(&db_clone).await {
+                    error!("Storing dead-lettered notifications failed: {}", err);
+                }
+            }
+        });
+    }
+
     // Create channel for HookHandler
-    let (hook_sender, hook_reciever) = async_channel::unbounded();
+    let (hook_sender, hook_reciever) = async_channel::bounded(HOOK_QUEUE_CONFIG.capacity);
 
     // Init DatabaseHandler
     let database_handler = DatabaseHandler {
@@ -117,41 +189,57 @@ pub async fn main() -> Result<()> {
     let hook_handler =
         hooks::hook_handler::HookHandler::new(hook_reciever, auth_clone, db_clone).await;
     hook_handler.run().await?;
+    if HOOK_QUEUE_CONFIG.overflow == OverflowPolicy::Persist {
+        start_hook_replay_loop(db_handler_arc.clone(), HOOK_QUEUE_CONFIG.replay_interval);
+    }
 
     // MeilisearchClient
     let meilisearch_client = MeilisearchClient::new(
         &dotenvy::var("MEILISEARCH_HOST")?,
         Some(&dotenvy::var("MEILISEARCH_API_KEY")?),
-    )?;
+    )?
+    .with_index_queue(db_arc.clone());
     let meilisearch_arc = Arc::new(meilisearch_client);
 
     let db_clone = db_arc.clone();
     let cache_clone = cache_arc.clone();
     let search_clone = meilisearch_arc.clone();
     tokio::spawn(async move {
-        // Delete existing index
-        if let Err(err) = search_clone.delete_index(MeilisearchIndexes::OBJECT).await {
-            warn!("Search index deletion failed: {}", err)
-        }
-
-        // Re-create index with current config
-        if let Err(err) = search_clone
-            .get_or_create_index(&MeilisearchIndexes::OBJECT.to_string(), Some("id"))
+        // Deliver updates queued before the restart, then rebuild search index with database
+        // content and current config, the existing index keeps serving queries until the
+        // rebuild is finished
+        let sync = async {
+            search_utils::deliver_queued_index_updates(&cache_clone, &search_clone).await?;
+            search_utils::full_sync_search_index(
+                db_clone,
+                cache_clone.clone(),
+                search_clone.clone(),
+            )
             .await
-        {
-            warn!("Search index creation failed: {}", err)
         };
-
-        // Full sync search index with database content
-        if let Err(err) =
-            search_utils::full_sync_search_index(db_clone, cache_clone, search_clone).await
-        {
-            warn!("Search index full sync failed: {}", err)
+        if let Err(err) = sync.await {
+            warn!("Search index full sync failed: {}", err);
+            if MeilisearchClient::is_connection_error(&err) {
+                // Reindex gets resumed by the recovery loop
+                search_clone.set_available(false);
+            }
         };
 
         Ok::<(), anyhow::Error>(())
     });
 
+    // Init search index recovery loop
+    let search_recovery_interval = dotenvy::var("SEARCH_RECOVERY_INTERVAL")
+        .map(|var| var.parse::<u64>().unwrap_or(30))
+        .unwrap_or(30);
+    search_utils::start_search_recovery_loop(
+        db_arc.clone(),
+        cache_arc.clone(),
+        meilisearch_arc.clone(),
+        std::time::Duration::from_secs(search_recovery_interval),
+    )
+    .await;
+
     // Create channel for MV refresh notifications
     let (n_send, n_recv) = async_channel::unbounded();
     // NotificationHandler
@@ -182,6 +270,31 @@ pub async fn main() -> Result<()> {
     )
     .await;
 
+    // Init staging cleanup loop
+    let staging_cleanup_interval = dotenvy::var("STAGING_CLEANUP_INTERVAL")
+        .map(|var| var.parse::<u64>().unwrap_or(300))
+        .unwrap_or(300);
+    start_staging_cleanup_loop(
+        db_handler_arc.clone(),
+        auth_arc.clone(),
+        std::time::Duration::from_secs(staging_cleanup_interval),
+    )
+    .await;
+
+    // Init cleanup loop for expired objects
+    start_expiry_cleanup_loop(
+        db_handler_arc.clone(),
+        meilisearch_arc.clone(),
+        *EXPIRY_CLEANUP_INTERVAL,
+    )
+    .await;
+
+    // Init cleanup loop for expired storage role credentials
+    start_storage_role_cleanup_loop(db_handler_arc.clone(), *STORAGE_ROLE_CLEANUP_INTERVAL).await;
+
+    // Init pruning of persistent notifications beyond their retention
+    start_notification_prune_loop(db_handler_arc.clone());
+
     // init MailClient
     let mailclient: Arc<Option<MailClient>> = if !dotenvy::var("ARUNA_DEV_ENV")?.parse::<bool>()? {
         Arc::new(Some(MailClient::new()?))
@@ -192,9 +305,11 @@ pub async fn main() -> Result<()> {
     let default_endpoint = dotenvy::var("DEFAULT_DATAPROXY_ULID")?;
 
     // Init server builder
-    let mut builder = Server::builder()
-        .http2_keepalive_interval(Some(std::time::Duration::from_secs(15)))
-        .add_service(EndpointServiceServer::new(
+    let mut builder = grpc_settings
+        .builder()
+        .layer(RequestIdLayer)
+        .layer(AdmissionLayer::new(ADMISSION_CONFIG.clone()))
+        .add_service(with_compression!(EndpointServiceServer::new(
             EndpointServiceImpl::new(
                 db_handler_arc.clone(),
                 auth_arc.clone(),
@@ -202,7 +317,7 @@ pub async fn main() -> Result<()> {
                 default_endpoint.to_string(),
             )
             .await,
-        ));
+        )));
 
     // Check default endpoint -> Only endpoint service available
     let client = db_arc.get_client().await?;
@@ -214,15 +329,15 @@ pub async fn main() -> Result<()> {
     {
         // Add other services
         builder = builder
-            .add_service(AuthorizationServiceServer::new(
+            .add_service(with_compression!(AuthorizationServiceServer::new(
                 AuthorizationServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
                     cache_arc.clone(),
                 )
                 .await,
-            ))
-            .add_service(UserServiceServer::new(
+            )))
+            .add_service(with_compression!(UserServiceServer::new(
                 UserServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
@@ -231,8 +346,8 @@ pub async fn main() -> Result<()> {
                     mailclient.clone(),
                 )
                 .await,
-            ))
-            .add_service(ProjectServiceServer::new(
+            )))
+            .add_service(with_compression!(ProjectServiceServer::new(
                 ProjectServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
@@ -241,8 +356,8 @@ pub async fn main() -> Result<()> {
                     default_endpoint.clone(),
                 )
                 .await,
-            ))
-            .add_service(CollectionServiceServer::new(
+            )))
+            .add_service(with_compression!(CollectionServiceServer::new(
                 CollectionServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
@@ -250,8 +365,8 @@ pub async fn main() -> Result<()> {
                     meilisearch_arc.clone(),
                 )
                 .await,
-            ))
-            .add_service(DatasetServiceServer::new(
+            )))
+            .add_service(with_compression!(DatasetServiceServer::new(
                 DatasetServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
@@ -259,8 +374,8 @@ pub async fn main() -> Result<()> {
                     meilisearch_arc.clone(),
                 )
                 .await,
-            ))
-            .add_service(ObjectServiceServer::new(
+            )))
+            .add_service(with_compression!(ObjectServiceServer::new(
                 ObjectServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
@@ -268,8 +383,8 @@ pub async fn main() -> Result<()> {
                     meilisearch_arc.clone(),
                 )
                 .await,
-            ))
-            .add_service(RelationsServiceServer::new(
+            )))
+            .add_service(with_compression!(RelationsServiceServer::new(
                 RelationsServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
@@ -277,8 +392,8 @@ pub async fn main() -> Result<()> {
                     meilisearch_arc.clone(),
                 )
                 .await,
-            ))
-            .add_service(EventNotificationServiceServer::new(
+            )))
+            .add_service(with_compression!(EventNotificationServiceServer::new(
                 NotificationServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
@@ -286,8 +401,8 @@ pub async fn main() -> Result<()> {
                     natsio_arc.clone(),
                 )
                 .await,
-            ))
-            .add_service(SearchServiceServer::new(
+            )))
+            .add_service(with_compression!(SearchServiceServer::new(
                 SearchServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
@@ -295,42 +410,44 @@ pub async fn main() -> Result<()> {
                     meilisearch_arc.clone(),
                 )
                 .await,
-            ))
-            .add_service(StorageStatusServiceServer::new(
+            )))
+            .add_service(with_compression!(StorageStatusServiceServer::new(
                 StorageStatusServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
                     cache_arc.clone(),
                 )
                 .await,
-            ))
-            .add_service(HooksServiceServer::new(
+            )))
+            .add_service(with_compression!(HooksServiceServer::new(
                 HookServiceImpl::new(db_handler_arc.clone(), auth_arc.clone(), cache_arc.clone())
                     .await,
-            ))
-            .add_service(LicenseServiceServer::new(
+            )))
+            .add_service(with_compression!(LicenseServiceServer::new(
                 LicensesServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
                     cache_arc.clone(),
                 )
                 .await,
-            ))
-            .add_service(DataReplicationServiceServer::new(
+            )))
+            .add_service(with_compression!(DataReplicationServiceServer::new(
                 DataReplicationServiceImpl::new(
                     db_handler_arc.clone(),
                     auth_arc.clone(),
                     cache_arc.clone(),
                 )
                 .await,
-            ));
+            )));
     }
 
     // Do it.
     //let addr: std::net::SocketAddr = "0.0.0.0:50051".parse()?;
     let addr: std::net::SocketAddr = dotenvy::var("ARUNA_SOCKET_ADDRESS")?.parse()?;
     info!("ArunaServer listening on {}", addr);
-    builder.serve(addr).await?;
+    builder
+        .serve_with_incoming(grpc_settings.incoming(addr)?)
+        .await?;
 
     // Cron scheduler?
 
@@ -0,0 +1,57 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::access_policy_dsl::AccessPolicy;
+use crate::database::dsls::object_dsl::Object;
+use crate::database::enums::{ObjectStatus, ObjectType};
+use crate::middlelayer::access_policy_request_types::{GetAccessPolicy, SetAccessPolicy};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+use postgres_types::Json;
+
+impl DatabaseHandler {
+    /// Replaces the access policy of a project, the rules apply immediately
+    /// to all resources of the project
+    pub async fn set_access_policy(
+        &self,
+        request: SetAccessPolicy,
+        user_id: DieselUlid,
+    ) -> Result<AccessPolicy> {
+        let project_id = request.get_id()?;
+        let rules = request.get_rules()?;
+
+        let client = self.database.get_client().await?;
+        let project = Object::get(project_id, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Project not found"))?;
+        if project.object_type != ObjectType::PROJECT
+            || project.object_status == ObjectStatus::DELETED
+        {
+            bail!("Access policies can only be set for existing projects");
+        }
+        let mut policy = AccessPolicy {
+            project_id,
+            rules: Json(rules),
+            updated_by: user_id,
+            updated_at: Utc::now().naive_utc(),
+        };
+        policy.create(&client).await?;
+        self.cache.upsert_access_policy(policy.clone());
+        Ok(policy)
+    }
+
+    pub async fn get_access_policy(&self, request: &GetAccessPolicy) -> Result<AccessPolicy> {
+        let client = self.database.get_client().await?;
+        AccessPolicy::get(request.get_id()?, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Project has no access policy"))
+    }
+
+    pub async fn delete_access_policy(&self, request: &GetAccessPolicy) -> Result<()> {
+        let client = self.database.get_client().await?;
+        let policy = self.get_access_policy(request).await?;
+        policy.delete(&client).await?;
+        self.cache.remove_access_policy(&policy.project_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use crate::database::dsls::access_policy_dsl::{PolicyRule, PolicyRules};
+use anyhow::Result;
+use diesel_ulid::DieselUlid;
+use std::str::FromStr;
+
+/// Replaces the access policy of a project, evaluated in addition to role-based permissions.
+#[derive(Debug, Clone)]
+pub struct SetAccessPolicy {
+    pub project_id: String,
+    pub rules: Vec<PolicyRule>,
+}
+
+impl SetAccessPolicy {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.project_id)?)
+    }
+
+    pub fn get_rules(&self) -> Result<PolicyRules> {
+        let rules = PolicyRules(self.rules.clone());
+        rules.validate()?;
+        Ok(rules)
+    }
+}
+
+/// Access policy of a project, used to get and delete policies.
+#[derive(Debug, Clone)]
+pub struct GetAccessPolicy {
+    pub project_id: String,
+}
+
+impl GetAccessPolicy {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.project_id)?)
+    }
+}
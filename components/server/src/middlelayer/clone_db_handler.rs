@@ -45,7 +45,10 @@ impl DatabaseHandler {
             relation_name: INTERNAL_RELATION_VARIANT_BELONGS_TO.to_string(),
             target_pid: new_id,
             target_type: ObjectType::OBJECT,
-            target_name: clone.name.to_string(),
+            target_name: self
+                .get_name_normalization(&origin_pid, &client)
+                .await?
+                .normalize(&clone.name),
         };
 
         // Create object and relation in transaction
@@ -5,15 +5,20 @@ use crate::database::dsls::internal_relation_dsl::{
 };
 use crate::database::dsls::object_dsl::{KeyValue, KeyValueVariant, Object, ObjectWithRelations};
 use crate::database::dsls::user_dsl::User;
-use crate::database::enums::{DbPermissionLevel, ObjectMapping, ObjectType};
-use crate::middlelayer::create_request_types::CreateRequest;
+use crate::database::enums::{DbPermissionLevel, ObjectMapping, ObjectStatus, ObjectType};
+use crate::middlelayer::create_request_types::{CreateRequest, ExistingObjectMode};
 use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::utils::cache_utils::check_key_value_types;
+use crate::utils::name_utils::NameNormalization;
+use crate::utils::user_notification_utils::{check_project_quota, USER_NOTIFICATION_CONFIG};
 use ahash::RandomState;
 use anyhow::{anyhow, Result};
 use aruna_rust_api::api::notification::services::v2::EventVariant;
+use aruna_rust_api::api::storage::services::v2::{CreateObjectRequest, UpdateObjectRequest};
 use dashmap::DashMap;
 use diesel_ulid::DieselUlid;
 use itertools::Itertools;
+use std::str::FromStr;
 use tokio_postgres::Client;
 
 impl DatabaseHandler {
@@ -48,6 +53,12 @@ impl DatabaseHandler {
                 self.check_object(&request).await?;
             }
         }
+        // Check relation maximum of the parent
+        let parent_id = request.get_parent().map(|p| p.get_id()).transpose()?;
+        let relation_warnings = self
+            .check_relation_limits(parent_id.as_slice(), &[], &client)
+            .await?;
+
         // Transaction setup
         let transaction = client.transaction().await?;
         let transaction_client = transaction.client();
@@ -57,7 +68,17 @@ impl DatabaseHandler {
         let mut object = request
             .as_new_db_object(user_id, transaction_client, self.cache.clone())
             .await?;
+        check_key_value_types(&self.cache, parent_id.as_ref(), &object.key_values.0 .0)?;
+        if let (ObjectType::OBJECT, Some(parent_id)) = (object.object_type, &parent_id) {
+            check_project_quota(&USER_NOTIFICATION_CONFIG, &self.cache, parent_id, 0)?;
+            self.check_metadata_schemas(parent_id, &object.key_values.0 .0, transaction_client)
+                .await?;
+        }
         object.create(transaction_client).await?;
+        if object.object_status == ObjectStatus::INITIALIZING {
+            self.check_upload_limit(&user_id, transaction_client).await?;
+            DatabaseHandler::init_staging_deadline(object.id, user_id, transaction_client).await?;
+        }
 
         // Create internal relation for parent and add user permissions for resource
         let (parent, _internal_relation): (
@@ -83,6 +104,11 @@ impl DatabaseHandler {
                     .get_parent()
                     .ok_or_else(|| anyhow!("No parent provided"))?;
 
+                // Names are unique in their normalized form
+                let target_name = self
+                    .get_name_normalization(&parent.get_id()?, transaction_client)
+                    .await?
+                    .normalize(&object.name);
                 let mut ir = InternalRelation {
                     id: DieselUlid::generate(),
                     origin_pid: parent.get_id()?,
@@ -90,7 +116,7 @@ impl DatabaseHandler {
                     target_pid: object.id,
                     target_type: object.object_type,
                     relation_name: INTERNAL_RELATION_VARIANT_BELONGS_TO.to_string(),
-                    target_name: object.name.to_string(),
+                    target_name,
                 };
                 let result = ir.create(transaction_client).await;
 
@@ -102,7 +128,7 @@ impl DatabaseHandler {
                         // ... iterate over outbound relations ...
                         for (id, irel) in parent.outbound_belongs_to.0 {
                             // ... if object name exists in outbound relations ...
-                            if irel.target_name == object.name {
+                            if irel.target_name == ir.target_name {
                                 // ... return existing object
                                 return Ok((
                                     self.cache
@@ -118,6 +144,8 @@ impl DatabaseHandler {
                         "Either cache not synced or other database error while creating object"
                     ));
                 }
+                // Names are unique per parent, concurrent creations fail here
+                result?;
 
                 let parent =
                     Object::get_object_with_relations(&parent.get_id()?, transaction_client)
@@ -143,6 +171,8 @@ impl DatabaseHandler {
         }
 
         transaction.commit().await?;
+        self.notify_relation_limits(relation_warnings, &client)
+            .await;
 
         // Update cache with affected objects
         let affected_owrs = Object::get_objects_with_relations(&affected, &client).await?;
@@ -239,6 +269,83 @@ impl DatabaseHandler {
         }
     }
 
+    /// Creates an object or handles an existing object with the same name in the parent
+    /// according to `mode`. Concurrent creations are decided by the unique index on the
+    /// names of children: the losing requests handle the created object as existing one.
+    /// Reserved names can only be used with the `reservation` token, which is consumed
+    /// by the creation. Returns the object and whether it was newly created.
+    pub async fn create_or_get_object(
+        &self,
+        request: CreateObjectRequest,
+        user_id: DieselUlid,
+        is_dataproxy: bool,
+        is_service_account: bool,
+        mode: ExistingObjectMode,
+        reservation: Option<DieselUlid>,
+    ) -> Result<(ObjectWithRelations, bool)> {
+        let create = CreateRequest::Object(request.clone());
+        let parent_id = create
+            .get_parent()
+            .ok_or_else(|| anyhow!("No parent found"))?
+            .get_id()?;
+        let client = self.database.get_client().await?;
+        let name = self
+            .get_name_normalization(&parent_id, &client)
+            .await?
+            .normalize(&request.name);
+        let reservation =
+            Self::check_name_reservation(&parent_id, &name, reservation, &client).await?;
+        if mode == ExistingObjectMode::Fail {
+            let (object, _) = self.create_resource(create, user_id, is_dataproxy).await?;
+            if let Some(reservation) = reservation {
+                reservation.delete(&client).await?;
+            }
+            return Ok((object, true));
+        }
+        let existing = match Object::get_child_by_name(&parent_id, &name, &client).await? {
+            Some(existing) => existing,
+            None => match self.create_resource(create, user_id, is_dataproxy).await {
+                Ok((object, _)) => {
+                    if let Some(reservation) = reservation {
+                        reservation.delete(&client).await?;
+                    }
+                    return Ok((object, true));
+                }
+                Err(err) => Object::get_child_by_name(&parent_id, &name, &client)
+                    .await?
+                    .ok_or(err)?,
+            },
+        };
+        if existing.object.object_type != ObjectType::OBJECT {
+            return Err(anyhow!("Name is already used by a non object resource"));
+        }
+        match mode {
+            ExistingObjectMode::Overwrite => {
+                let (object, _) = self
+                    .update_grpc_object(
+                        UpdateObjectRequest {
+                            object_id: existing.object.id.to_string(),
+                            name: None,
+                            description: Some(request.description),
+                            add_key_values: request.key_values,
+                            remove_key_values: vec![],
+                            data_class: request.data_class,
+                            hashes: vec![],
+                            parent: None,
+                            force_revision: true,
+                            data_license_tag: None,
+                            metadata_license_tag: None,
+                        },
+                        user_id,
+                        is_service_account,
+                    )
+                    .await?;
+                Ok((object, false))
+            }
+            _ => Ok((existing, false)),
+        }
+    }
+
     async fn check_hierarchy(&self, request: &CreateRequest) -> Result<()> {
         let client = self.database.get_client().await?;
         let parent_id = request
@@ -246,7 +353,8 @@ impl DatabaseHandler {
             .ok_or_else(|| anyhow!("No parent found"))?
             .get_id()?;
         let parent = Object::get_object_with_relations(&parent_id, &client).await?;
-        let name = request.get_name()?;
+        let normalization = self.get_name_normalization(&parent_id, &client).await?;
+        let name = normalization.normalize(&request.get_name()?);
         if parent
             .outbound_belongs_to
             .0
@@ -264,6 +372,8 @@ impl DatabaseHandler {
                 // return name of other resources
                 _ => rel.target_name.to_string(),
             })
+            // Relations created before the normalization of names
+            .map(|name| normalization.normalize(&name))
             // Check if names contain request name
             .contains(&name)
         {
@@ -281,7 +391,8 @@ impl DatabaseHandler {
             .ok_or_else(|| anyhow!("No parent found"))?
             .get_id()?;
         let parent = Object::get_object_with_relations(&parent_id, &client).await?;
-        let name = request.get_name()?;
+        let normalization = self.get_name_normalization(&parent_id, &client).await?;
+        let name = normalization.normalize(&request.get_name()?);
         let query = match name.split('/').next() {
             Some(name) => name.to_string(),
             None => name,
@@ -303,6 +414,8 @@ impl DatabaseHandler {
                 // return name of other resources
                 _ => rel.target_name.to_string(),
             })
+            // Relations created before the normalization of names
+            .map(|name| normalization.normalize(&name))
             .contains(&query)
         {
             return Err(anyhow!(
@@ -312,6 +425,41 @@ impl DatabaseHandler {
         Ok(())
     }
 
+    /// Name normalization of the project a resource belongs to. Resources shared between
+    /// projects use the policy of their first project.
+    pub async fn get_name_normalization(
+        &self,
+        resource_id: &DieselUlid,
+        client: &Client,
+    ) -> Result<NameNormalization> {
+        let project_id = match self.cache.upstream_dfs_iterative(resource_id) {
+            // Hierarchies end with their project
+            Ok(hierarchies) => hierarchies
+                .first()
+                .and_then(|hierarchy| hierarchy.last())
+                .map(|project| project.into_inner()),
+            // Resources which are not cached yet
+            Err(_) => Object::get(*resource_id, client)
+                .await?
+                .ok_or_else(|| anyhow!("Resource not found"))?
+                .fetch_object_hierarchies(client)
+                .await?
+                .first()
+                .map(|hierarchy| DieselUlid::from_str(&hierarchy.project_id))
+                .transpose()?,
+        };
+        let Some(project_id) = project_id else {
+            return Ok(NameNormalization::default());
+        };
+        let project = match self.cache.get_object(&project_id) {
+            Some(project) => project.object,
+            None => Object::get(project_id, client)
+                .await?
+                .ok_or_else(|| anyhow!("Project not found"))?,
+        };
+        NameNormalization::from_project(&project)
+    }
+
     async fn collect_and_create_affected(
         &self,
         request: CreateRequest,
@@ -324,6 +472,7 @@ impl DatabaseHandler {
             .await?;
         if !internal_relations.is_empty() {
             InternalRelation::batch_create(&internal_relations, transaction_client).await?;
+            self.check_custom_relations(&internal_relations, transaction_client).await?;
         }
         // Collect affected objects
         let mut affected: Vec<DieselUlid> = Vec::new();
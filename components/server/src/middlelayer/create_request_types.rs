@@ -11,6 +11,7 @@ use crate::database::enums::{
     DbPermissionLevel, ObjectStatus, ObjectType, ReplicationStatus, ReplicationType,
 };
 use crate::utils::conversions::relations::ContextContainer;
+use crate::utils::validation_utils::VALIDATION_RULES;
 use ahash::RandomState;
 use anyhow::{anyhow, Result};
 use aruna_rust_api::api::storage::models::v2::Hash;
@@ -23,13 +24,41 @@ use aruna_rust_api::api::storage::{
 };
 use dashmap::DashMap;
 use diesel_ulid::DieselUlid;
-use lazy_static::lazy_static;
 use postgres_types::Json;
-use regex::Regex;
 use std::str::FromStr;
 use std::sync::Arc;
 use tokio_postgres::Client;
 
+/// Metadata key which selects how object creation handles an existing object with the same name
+pub const IF_EXISTS_KEY: &str = "x-aruna-if-exists";
+
+/// Metadata key with the token of a name reservation which object creation consumes
+pub const NAME_RESERVATION_KEY: &str = "x-aruna-name-reservation";
+
+/// Handling of an existing object with the same name in the parent during object creation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExistingObjectMode {
+    /// Creation fails
+    #[default]
+    Fail,
+    /// The existing object is returned unchanged
+    Get,
+    /// A new revision of the existing object is created
+    Overwrite,
+}
+
+impl FromStr for ExistingObjectMode {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "fail" => Ok(ExistingObjectMode::Fail),
+            "get" => Ok(ExistingObjectMode::Get),
+            "overwrite" => Ok(ExistingObjectMode::Overwrite),
+            _ => Err(anyhow!("If-exists mode must be fail, get or overwrite")),
+        }
+    }
+}
+
 pub enum CreateRequest {
     Project(CreateProjectRequest, String),
     Collection(CreateCollectionRequest),
@@ -44,15 +73,6 @@ pub enum Parent {
     Dataset(String),
 }
 
-lazy_static! {
-    pub static ref PROJECT_SCHEMA: Regex =
-        Regex::new(r"^[a-z0-9\-]+$").expect("Regex must be valid");
-    pub static ref S3_KEY_SCHEMA: Regex =
-        Regex::new(r"^[a-zA-Z0-9\-\!\_\.\*\_\'\(\)]+$").expect("Regex must be valid");
-    pub static ref OBJECT_SCHEMA: Regex =
-        Regex::new(r"^[a-zA-Z0-9\-\!\_\.\*\_\'\(\)\/]+$").expect("Regex must be valid");
-}
-
 impl Parent {
     pub fn get_id(&self) -> Result<DieselUlid> {
         match self {
@@ -81,40 +101,21 @@ impl Parent {
 
 impl CreateRequest {
     pub fn get_name(&self) -> Result<String> {
-        match self {
-            CreateRequest::Project(request, _) => {
-                let name = request.name.to_string();
-                if !PROJECT_SCHEMA.is_match(&name) {
-                    Err(anyhow!("Invalid project name"))
-                } else {
-                    Ok(name)
-                }
-            }
-            CreateRequest::Collection(request) => {
-                let name = request.name.to_string();
-                if !S3_KEY_SCHEMA.is_match(&name) {
-                    Err(anyhow!("Invalid collection name"))
-                } else {
-                    Ok(name)
-                }
-            }
-            CreateRequest::Dataset(request) => {
-                let name = request.name.to_string();
-                if !S3_KEY_SCHEMA.is_match(&name) {
-                    Err(anyhow!("Invalid dataset name"))
-                } else {
-                    Ok(name)
-                }
-            }
-            CreateRequest::Object(request) => {
-                let name = request.name.to_string();
-                if !OBJECT_SCHEMA.is_match(&name) {
-                    Err(anyhow!("Invalid object name"))
-                } else {
-                    Ok(name)
-                }
-            }
-        }
+        let name = match self {
+            CreateRequest::Project(request, _) => request.name.to_string(),
+            CreateRequest::Collection(request) => request.name.to_string(),
+            CreateRequest::Dataset(request) => request.name.to_string(),
+            CreateRequest::Object(request) => request.name.to_string(),
+        };
+        VALIDATION_RULES.validate_name(self.get_type(), &name)?;
+        Ok(name)
+    }
+
+    /// Checks name and description against the configured validation rules
+    pub fn validate(&self) -> Result<()> {
+        self.get_name()?;
+        VALIDATION_RULES.validate_description(&self.get_description())?;
+        Ok(())
     }
 
     pub fn get_description(&self) -> String {
@@ -427,7 +428,7 @@ impl CreateRequest {
             Some(h) => h.try_into()?,
             None => Hashes(Vec::new()),
         };
-        let (metadata_license, data_license) = self.get_licenses(client).await?;
+        let (metadata_license, data_license) = self.get_licenses(&cache, client).await?;
         let endpoints = self.get_endpoint(cache, client).await?;
         let name = self.get_name()?;
 
@@ -455,8 +456,9 @@ impl CreateRequest {
         })
     }
 
-    pub async fn get_licenses(&self, client: &Client) -> Result<(String, String)> {
-        // Either retrieve license from request or parent
+    pub async fn get_licenses(&self, cache: &Cache, client: &Client) -> Result<(String, String)> {
+        // Either retrieve license from request, the default license of the
+        // nearest parent or the parent itself
         match &self {
             // Projects must specify licenses
             CreateRequest::Project(req, _) => {
@@ -483,7 +485,10 @@ impl CreateRequest {
                     .get_parent()
                     .ok_or_else(|| anyhow!("No parent specified"))?
                     .get_id()?;
-                let data_tag = req.default_data_license_tag.clone();
+                let data_tag = req
+                    .default_data_license_tag
+                    .clone()
+                    .or_else(|| CreateRequest::default_license(&parent, cache));
                 let meta_tag = req.metadata_license_tag.clone();
                 CreateRequest::check_license(data_tag, meta_tag, parent, client).await
             }
@@ -492,7 +497,10 @@ impl CreateRequest {
                     .get_parent()
                     .ok_or_else(|| anyhow!("No parent specified"))?
                     .get_id()?;
-                let data_tag = req.default_data_license_tag.clone();
+                let data_tag = req
+                    .default_data_license_tag
+                    .clone()
+                    .or_else(|| CreateRequest::default_license(&parent, cache));
                 let meta_tag = req.metadata_license_tag.clone();
                 CreateRequest::check_license(data_tag, meta_tag, parent, client).await
             }
@@ -502,7 +510,7 @@ impl CreateRequest {
                     .ok_or_else(|| anyhow!("No parent specified"))?
                     .get_id()?;
                 let data_tag = if req.data_license_tag.is_empty() {
-                    None
+                    CreateRequest::default_license(&parent, cache)
                 } else {
                     Some(req.data_license_tag.clone())
                 };
@@ -516,6 +524,13 @@ impl CreateRequest {
         }
     }
 
+    // Data license new children of the parent inherit, if a default is set
+    fn default_license(parent: &DieselUlid, cache: &Cache) -> Option<String> {
+        cache
+            .get_default_license(parent)
+            .map(|(_, license_tag)| license_tag)
+    }
+
     // Checks if licenses are specified
     // and if not tries to retrieve parent licenses
     async fn check_license(
@@ -4,18 +4,41 @@ use crate::database::dsls::internal_relation_dsl::{
     InternalRelation, INTERNAL_RELATION_VARIANT_VERSION,
 };
 use crate::database::dsls::object_dsl::ObjectWithRelations;
-use crate::database::enums::{ObjectStatus, ObjectType};
+use crate::database::dsls::pinned_view_dsl::PinnedView;
+use crate::database::enums::{ObjectMapping, ObjectStatus, ObjectType};
 use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::delete_request_types::{
+    min_delete_age, BatchDeleteResult, BatchDeleteStatus, DeleteOnHold, DeleteTooEarly,
+    DELETE_BATCH_CONFIG, LEGAL_HOLD_KEY,
+};
+use crate::middlelayer::symlink_request_types::{SymlinkSourceDeletion, SYMLINK_SOURCE_DELETION};
+use crate::utils::user_notification_utils::{notify_project_deleted, USER_NOTIFICATION_CONFIG};
 use crate::{database::dsls::object_dsl::Object, middlelayer::delete_request_types::DeleteRequest};
 use anyhow::{bail, Result};
 use aruna_rust_api::api::notification::services::v2::EventVariant;
+use aruna_rust_api::api::storage::services::v2::DeleteObjectRequest;
+use chrono::Utc;
 use diesel_ulid::DieselUlid;
+use futures::{Stream, StreamExt};
 use itertools::Itertools;
+use tokio_postgres::Client;
 
 impl DatabaseHandler {
+    /// Deletes a resource without the minimum delete age of projects,
+    /// e.g. for expired objects and workspaces
     pub async fn delete_resource(
         &self,
         delete_request: DeleteRequest,
+    ) -> Result<Vec<ObjectWithRelations>> {
+        self.delete_resource_checked(delete_request, true).await
+    }
+
+    /// Deletes a resource, objects younger than the minimum delete age of their
+    /// project are only deleted if `bypass_min_age` is set, e.g. for admins.
+    pub async fn delete_resource_checked(
+        &self,
+        delete_request: DeleteRequest,
+        bypass_min_age: bool,
     ) -> Result<Vec<ObjectWithRelations>> {
         let mut client = self.database.get_client().await?;
         let transaction = client.transaction().await?;
@@ -204,6 +227,49 @@ impl DatabaseHandler {
                 }
             };
 
+        // Legal holds apply to admins and expiries too
+        self.check_legal_holds(&object_ids_to_delete)?;
+        if !bypass_min_age {
+            self.check_min_delete_age(&object_ids_to_delete, transaction_client)
+                .await?;
+        }
+
+        // Pinned revisions can only be deleted together with the collections of their views
+        let pinning_views = PinnedView::get_pinning(&object_ids_to_delete, transaction_client)
+            .await?
+            .into_iter()
+            .filter(|view| !object_ids_to_delete.contains(&view.collection_id))
+            .collect_vec();
+        if !pinning_views.is_empty() {
+            bail!(
+                "Resource is pinned by views: {}",
+                pinning_views
+                    .iter()
+                    .map(|view| format!("{} ({})", view.name, view.id))
+                    .join(", ")
+            );
+        }
+        PinnedView::delete_by_collections(&object_ids_to_delete, transaction_client).await?;
+
+        // Symlinks to deleted objects either block the deletion or stay as tombstones
+        if *SYMLINK_SOURCE_DELETION == SymlinkSourceDeletion::Error {
+            let symlinks =
+                InternalRelation::get_symlinks_to(&object_ids_to_delete, transaction_client)
+                    .await?
+                    .into_iter()
+                    .filter(|symlink| !object_ids_to_delete.contains(&symlink.origin_pid))
+                    .collect_vec();
+            if !symlinks.is_empty() {
+                bail!(
+                    "Object is the source of symlinks: {}",
+                    symlinks
+                        .iter()
+                        .map(|symlink| format!("{}/{}", symlink.origin_pid, symlink.target_name))
+                        .join(", ")
+                );
+            }
+        }
+
         // "Delete" relations
         InternalRelation::set_deleted(&relation_ids_to_delete, transaction_client).await?;
 
@@ -217,6 +283,21 @@ impl DatabaseHandler {
         // Commit transaction
         transaction.commit().await?;
 
+        // Notify project members about deleted project
+        if root_object.object.object_type == ObjectType::PROJECT {
+            if let Err(err) = notify_project_deleted(
+                &client,
+                &USER_NOTIFICATION_CONFIG,
+                &self.cache,
+                &root_object.object.id,
+                &root_object.object.name,
+            )
+            .await
+            {
+                log::error!("Project deletion notification failed: {}", err);
+            }
+        }
+
         // Fetch hierarchies and object relations for notifications
         let deleted_objects =
             Object::get_objects_with_relations(&object_ids_to_delete, &client).await?;
@@ -289,4 +370,142 @@ impl DatabaseHandler {
 
         Ok(deleted_objects)
     }
+
+    /// Deletes objects with up to `DELETE_BATCH_CONFIG.concurrency` deletions at the same
+    /// time and yields the result and deleted ids of each object as it completes.
+    /// Objects under legal hold or younger than their minimum delete age are skipped,
+    /// failed deletions do not abort the batch.
+    pub fn delete_objects_batch(
+        &self,
+        object_ids: Vec<DieselUlid>,
+        with_revisions: bool,
+        bypass_min_age: bool,
+    ) -> impl Stream<Item = (BatchDeleteResult, Vec<DieselUlid>)> + Send + '_ {
+        futures::stream::iter(object_ids)
+            .map(move |id| self.delete_batch_object(id, with_revisions, bypass_min_age))
+            .buffer_unordered(DELETE_BATCH_CONFIG.concurrency)
+    }
+
+    async fn delete_batch_object(
+        &self,
+        object_id: DieselUlid,
+        with_revisions: bool,
+        bypass_min_age: bool,
+    ) -> (BatchDeleteResult, Vec<DieselUlid>) {
+        let result = |status| BatchDeleteResult {
+            object_id: object_id.to_string(),
+            status,
+        };
+        match self.cache.get_object(&object_id) {
+            Some(object) if object.object.object_type == ObjectType::OBJECT => {}
+            _ => {
+                return (
+                    result(BatchDeleteStatus::Failed("Object not found".to_string())),
+                    vec![],
+                )
+            }
+        }
+        let request = DeleteRequest::Object(DeleteObjectRequest {
+            object_id: object_id.to_string(),
+            with_revisions,
+        });
+        match self.delete_resource_checked(request, bypass_min_age).await {
+            Ok(deleted) => (
+                result(BatchDeleteStatus::Deleted),
+                deleted.iter().map(|o| o.object.id).collect(),
+            ),
+            Err(err) if err.is::<DeleteOnHold>() || err.is::<DeleteTooEarly>() => {
+                (result(BatchDeleteStatus::Skipped(err.to_string())), vec![])
+            }
+            Err(err) => {
+                log::error!("Batch deletion of {object_id} failed: {err}");
+                (result(BatchDeleteStatus::Failed(err.to_string())), vec![])
+            }
+        }
+    }
+
+    /// Fails if a resource or one of its parents is under legal hold
+    fn check_legal_holds(&self, ids: &[DieselUlid]) -> Result<()> {
+        for id in ids {
+            // Resources missing in the cache have no known holds
+            let held_by = self
+                .cache
+                .upstream_dfs_iterative(id)
+                .unwrap_or_default()
+                .into_iter()
+                .flatten()
+                .map(|resource| resource.into_inner())
+                .chain(std::iter::once(*id))
+                .unique()
+                .find(|resource_id| {
+                    self.cache.get_object(resource_id).is_some_and(|resource| {
+                        resource
+                            .object
+                            .key_values
+                            .0
+                             .0
+                            .iter()
+                            .any(|kv| kv.key == LEGAL_HOLD_KEY)
+                    })
+                });
+            if let Some(held_by) = held_by {
+                return Err(DeleteOnHold {
+                    object_id: *id,
+                    held_by,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails with the latest earliest-deletable time if any object is younger
+    /// than the minimum delete age of one of its projects
+    async fn check_min_delete_age(&self, ids: &[DieselUlid], client: &Client) -> Result<()> {
+        let now = Utc::now().naive_utc();
+        let mut too_early: Option<DeleteTooEarly> = None;
+        for object in Object::get_objects(&ids.to_vec(), client).await? {
+            let (ObjectType::OBJECT, Some(created_at)) = (object.object_type, object.created_at)
+            else {
+                continue;
+            };
+            let project_ids = self
+                .cache
+                .upstream_dfs_iterative(&object.id)?
+                .into_iter()
+                .flatten()
+                .filter_map(|parent| match parent {
+                    ObjectMapping::PROJECT(id) => Some(id),
+                    _ => None,
+                })
+                .unique()
+                .collect_vec();
+            for project_id in project_ids {
+                let Some(age) = self
+                    .cache
+                    .get_object(&project_id)
+                    .map(|project| min_delete_age(&project.object))
+                    .transpose()?
+                    .flatten()
+                else {
+                    continue;
+                };
+                let deletable_at = created_at + age;
+                if deletable_at > now
+                    && too_early
+                        .as_ref()
+                        .map_or(true, |early| deletable_at > early.deletable_at)
+                {
+                    too_early = Some(DeleteTooEarly {
+                        object_id: object.id,
+                        deletable_at,
+                    });
+                }
+            }
+        }
+        match too_early {
+            Some(too_early) => Err(too_early.into()),
+            None => Ok(()),
+        }
+    }
 }
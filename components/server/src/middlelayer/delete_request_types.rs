@@ -1,10 +1,49 @@
-use anyhow::Result;
+use crate::database::dsls::object_dsl::Object;
+use crate::middlelayer::listing_request_types::ListCollectionObjects;
+use anyhow::{bail, Result};
 use aruna_rust_api::api::storage::services::v2::{
     DeleteCollectionRequest, DeleteDatasetRequest, DeleteObjectRequest, DeleteProjectRequest,
 };
+use chrono::NaiveDateTime;
 use diesel_ulid::DieselUlid;
+use lazy_static::lazy_static;
+use std::error::Error;
+use std::fmt::Display;
 use std::str::FromStr;
 
+/// Project key-value with the minimum age in seconds before objects of the project can be deleted
+pub const MIN_DELETE_AGE_KEY: &str = "app.aruna-storage.org/min-delete-age";
+/// Key-value of resources which blocks deleting them and everything below them until it is removed
+pub const LEGAL_HOLD_KEY: &str = "app.aruna-storage.org/legal-hold";
+
+lazy_static! {
+    pub static ref DELETE_BATCH_CONFIG: DeleteBatchConfig = DeleteBatchConfig::from_env();
+}
+
+/// Limits of batch deletions
+#[derive(Debug, Clone, Copy)]
+pub struct DeleteBatchConfig {
+    // Objects of a batch which are deleted at the same time
+    pub concurrency: usize,
+    // Results between two progress messages
+    pub progress_interval: u64,
+}
+
+impl DeleteBatchConfig {
+    pub fn from_env() -> Self {
+        DeleteBatchConfig {
+            concurrency: dotenvy::var("DELETE_BATCH_CONCURRENCY")
+                .map(|var| var.parse::<usize>().unwrap_or(8))
+                .unwrap_or(8)
+                .max(1),
+            progress_interval: dotenvy::var("DELETE_BATCH_PROGRESS_INTERVAL")
+                .map(|var| var.parse::<u64>().unwrap_or(100))
+                .unwrap_or(100)
+                .max(1),
+        }
+    }
+}
+
 pub enum DeleteRequest {
     Project(DeleteProjectRequest),
     Collection(DeleteCollectionRequest),
@@ -23,3 +62,225 @@ impl DeleteRequest {
         Ok(id)
     }
 }
+
+/// Deletes either the listed objects or all objects matching the filter.
+#[derive(Debug, Clone)]
+pub struct DeleteObjectsBatch {
+    pub object_ids: Vec<String>,
+    pub filter: Option<ListCollectionObjects>,
+    pub with_revisions: bool,
+}
+
+impl DeleteObjectsBatch {
+    pub fn validate(&self) -> Result<()> {
+        match (self.object_ids.is_empty(), &self.filter) {
+            (true, None) => bail!("No objects or filter provided"),
+            (false, Some(_)) => bail!("Objects and filter are mutually exclusive"),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn get_ids(&self) -> Result<Vec<DieselUlid>> {
+        let mut ids = self
+            .object_ids
+            .iter()
+            .map(|id| Ok(DieselUlid::from_str(id)?))
+            .collect::<Result<Vec<_>>>()?;
+        ids.sort();
+        ids.dedup();
+        Ok(ids)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchDeleteStatus {
+    Deleted,
+    // Objects under legal hold or younger than their minimum delete age
+    Skipped(String),
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchDeleteResult {
+    pub object_id: String,
+    pub status: BatchDeleteStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeleteBatchStreamMessage {
+    Result(BatchDeleteResult),
+    Progress {
+        processed: u64,
+        total: u64,
+    },
+    // Filters matching more objects than a traversal visits are truncated
+    Summary {
+        deleted: u64,
+        skipped: u64,
+        failed: u64,
+        truncated: bool,
+    },
+}
+
+/// Counts the results of a batch deletion
+#[derive(Debug, Clone, Default)]
+pub struct DeleteBatchProgress {
+    pub total: u64,
+    pub interval: u64,
+    pub deleted: u64,
+    pub skipped: u64,
+    pub failed: u64,
+}
+
+impl DeleteBatchProgress {
+    pub fn new(total: u64, interval: u64) -> Self {
+        DeleteBatchProgress {
+            total,
+            interval: interval.max(1),
+            ..Default::default()
+        }
+    }
+
+    pub fn processed(&self) -> u64 {
+        self.deleted + self.skipped + self.failed
+    }
+
+    /// Counts the result, returns a progress message every `interval` results
+    /// before the last one
+    pub fn record(&mut self, result: &BatchDeleteResult) -> Option<DeleteBatchStreamMessage> {
+        match result.status {
+            BatchDeleteStatus::Deleted => self.deleted += 1,
+            BatchDeleteStatus::Skipped(_) => self.skipped += 1,
+            BatchDeleteStatus::Failed(_) => self.failed += 1,
+        }
+        let processed = self.processed();
+        (processed % self.interval == 0 && processed < self.total).then_some(
+            DeleteBatchStreamMessage::Progress {
+                processed,
+                total: self.total,
+            },
+        )
+    }
+
+    pub fn summary(&self, truncated: bool) -> DeleteBatchStreamMessage {
+        DeleteBatchStreamMessage::Summary {
+            deleted: self.deleted,
+            skipped: self.skipped,
+            failed: self.failed,
+            truncated,
+        }
+    }
+}
+
+/// Parses the minimum delete age of a project, projects without it allow immediate deletes
+pub fn min_delete_age(project: &Object) -> Result<Option<chrono::Duration>> {
+    project
+        .key_values
+        .0
+         .0
+        .iter()
+        .find(|kv| kv.key == MIN_DELETE_AGE_KEY)
+        .map(|kv| Ok(chrono::Duration::seconds(kv.value.trim().parse::<i64>()?)))
+        .transpose()
+}
+
+/// Deleting would remove an object younger than the minimum delete age of its project
+#[derive(Debug)]
+pub struct DeleteTooEarly {
+    pub object_id: DieselUlid,
+    pub deletable_at: NaiveDateTime,
+}
+impl Display for DeleteTooEarly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Object {} can not be deleted before {}",
+            self.object_id,
+            self.deletable_at.and_utc().to_rfc3339()
+        )
+    }
+}
+impl Error for DeleteTooEarly {}
+
+/// Deleting would remove a resource under legal hold of itself or one of its parents
+#[derive(Debug)]
+pub struct DeleteOnHold {
+    pub object_id: DieselUlid,
+    pub held_by: DieselUlid,
+}
+impl Display for DeleteOnHold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.object_id == self.held_by {
+            write!(f, "Object {} is under legal hold", self.object_id)
+        } else {
+            write!(
+                f,
+                "Object {} is under legal hold of {}",
+                self.object_id, self.held_by
+            )
+        }
+    }
+}
+impl Error for DeleteOnHold {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(status: BatchDeleteStatus) -> BatchDeleteResult {
+        BatchDeleteResult {
+            object_id: DieselUlid::generate().to_string(),
+            status,
+        }
+    }
+
+    #[test]
+    fn test_delete_batch_progress() {
+        let mut progress = DeleteBatchProgress::new(5, 2);
+        assert_eq!(progress.record(&result(BatchDeleteStatus::Deleted)), None);
+        assert_eq!(
+            progress.record(&result(BatchDeleteStatus::Skipped("hold".to_string()))),
+            Some(DeleteBatchStreamMessage::Progress {
+                processed: 2,
+                total: 5
+            })
+        );
+        assert_eq!(
+            progress.record(&result(BatchDeleteStatus::Failed("error".to_string()))),
+            None
+        );
+        assert!(progress
+            .record(&result(BatchDeleteStatus::Deleted))
+            .is_some());
+        // The summary follows the last result
+        assert_eq!(progress.record(&result(BatchDeleteStatus::Deleted)), None);
+        assert_eq!(
+            progress.summary(false),
+            DeleteBatchStreamMessage::Summary {
+                deleted: 3,
+                skipped: 1,
+                failed: 1,
+                truncated: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_delete_objects_batch_validation() {
+        let mut request = DeleteObjectsBatch {
+            object_ids: vec![],
+            filter: None,
+            with_revisions: false,
+        };
+        assert!(request.validate().is_err());
+        let id = DieselUlid::generate().to_string();
+        request.object_ids = vec![id.clone(), id.clone()];
+        assert!(request.validate().is_ok());
+        assert_eq!(request.get_ids().unwrap().len(), 1);
+        request.filter = Some(ListCollectionObjects {
+            collection_id: DieselUlid::generate().to_string(),
+            name_prefix: None,
+        });
+        assert!(request.validate().is_err());
+    }
+}
@@ -3,8 +3,11 @@ use crate::database::dsls::endpoint_dsl::Endpoint;
 use crate::database::dsls::object_dsl::Object;
 use crate::database::dsls::pub_key_dsl::PubKey;
 use crate::database::dsls::user_dsl::User;
+use crate::database::enums::EndpointStatus;
 use crate::middlelayer::db_handler::DatabaseHandler;
-use crate::middlelayer::endpoints_request_types::{CreateEP, DeleteEP, GetBy, GetEP};
+use crate::middlelayer::endpoints_request_types::{
+    ApproveEP, CreateEP, DeleteEP, GetBy, GetEP, RegisterEP,
+};
 
 use anyhow::{anyhow, Result};
 use aruna_rust_api::api::notification::services::v2::announcement_event::EventVariant as AnnouncementVariant;
@@ -40,6 +43,59 @@ impl DatabaseHandler {
         Ok((endpoint, pubkey))
     }
 
+    /// Registers a data proxy which proved the possession of its private key.
+    /// The endpoint stays pending and its pubkey untrusted until an admin approves it.
+    pub async fn register_endpoint(&self, request: RegisterEP) -> Result<(Endpoint, PubKey)> {
+        request.verify()?;
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+        let (mut endpoint, pubkey) = request.build_endpoint()?;
+        if PubKey::get_by_key(&pubkey.pubkey, transaction_client)
+            .await?
+            .is_some()
+        {
+            return Err(anyhow!("Pubkey is already registered"));
+        }
+        endpoint.create(transaction_client).await?;
+        let pubkey =
+            PubKey::create_or_get_without_id(Some(endpoint.id), &pubkey.pubkey, transaction_client)
+                .await?;
+        transaction.commit().await?;
+        Ok((endpoint, pubkey))
+    }
+
+    /// Activates a pending self-registered endpoint and announces it like a created one
+    pub async fn approve_endpoint(&self, request: ApproveEP) -> Result<(Endpoint, PubKey)> {
+        let id = request.get_id()?;
+        let client = self.database.get_client().await?;
+        let mut endpoint = Endpoint::get(id, &client)
+            .await?
+            .ok_or_else(|| anyhow!("No endpoint found"))?;
+        if endpoint.status != EndpointStatus::PENDING {
+            return Err(anyhow!("Endpoint is not pending approval"));
+        }
+        let pubkey = PubKey::get_by_proxy(&id, &client)
+            .await?
+            .ok_or_else(|| anyhow!("No pubkey found for endpoint"))?;
+        Endpoint::set_status(&id, EndpointStatus::AVAILABLE, &client).await?;
+        endpoint.status = EndpointStatus::AVAILABLE;
+
+        // Emit announcement notifications
+        let announcements = vec![
+            AnnouncementVariant::NewDataProxyId(endpoint.id.to_string()),
+            AnnouncementVariant::NewPubkey(pubkey.id as i32),
+        ];
+        for ann in announcements {
+            if let Err(err) = self.natsio_handler.register_announcement_event(ann).await {
+                log::error!("{}", err);
+                return Err(anyhow::anyhow!("Notification emission failed"));
+            }
+        }
+
+        Ok((endpoint, pubkey))
+    }
+
     pub async fn get_endpoint(&self, request: GetEP) -> Result<Endpoint> {
         let client = self.database.get_client().await?;
         let endpoint = match request.get_query()? {
@@ -6,14 +6,58 @@ use aruna_rust_api::api::storage::services::v2::{
     get_endpoint_request::Endpoint as GetEndpoint, CreateEndpointRequest, DeleteEndpointRequest,
     GetEndpointRequest,
 };
+use chrono::Utc;
 use diesel_ulid::DieselUlid;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
 use postgres_types::Json;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
+/// Audience of the signed challenge of endpoint self-registrations
+pub const REGISTRATION_AUDIENCE: &str = "aruna-endpoint-registration";
+// Challenges are short-lived to limit replays
+const MAX_CHALLENGE_VALIDITY: usize = 300;
+
 pub struct CreateEP(pub CreateEndpointRequest);
 pub struct DeleteEP(pub DeleteEndpointRequest);
 pub struct GetEP(pub GetEndpointRequest);
 
+/// Self-registration of a data proxy. The challenge is an EdDSA JWT signed with the
+/// private key of the registered pubkey, which proves the possession of the key.
+#[derive(Debug, Clone)]
+pub struct RegisterEP {
+    pub request: CreateEndpointRequest,
+    pub challenge: String,
+}
+
+/// Approval of a self-registered endpoint by a global admin.
+#[derive(Debug, Clone)]
+pub struct ApproveEP {
+    pub endpoint_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistrationChallenge {
+    pub iss: String, // Endpoint name
+    pub aud: String,
+    pub iat: usize,
+    pub exp: usize,
+}
+
+/// Parses raw or PEM encoded ed25519 pubkeys of endpoints
+pub fn decoding_key_from_pubkey(pubkey: &str) -> Result<DecodingKey> {
+    let key = if pubkey.starts_with("-----BEGIN PUBLIC KEY-----") {
+        DecodingKey::from_ed_pem(pubkey.as_bytes())?
+    } else {
+        let public_pem = format!(
+            "-----BEGIN PUBLIC KEY-----{}-----END PUBLIC KEY-----",
+            pubkey
+        );
+        DecodingKey::from_ed_pem(public_pem.as_bytes())?
+    };
+    Ok(key)
+}
+
 impl CreateEP {
     pub fn build_endpoint(&self) -> Result<(Endpoint, PubKey)> {
         let id = DieselUlid::generate();
@@ -35,6 +79,41 @@ impl CreateEP {
     }
 }
 
+impl RegisterEP {
+    /// Verifies the challenge signature with the presented pubkey
+    pub fn verify(&self) -> Result<DecodingKey> {
+        let key = decoding_key_from_pubkey(&self.request.pubkey)?;
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.set_audience(&[REGISTRATION_AUDIENCE]);
+        validation.set_required_spec_claims(&["exp", "aud", "iss"]);
+        validation.set_issuer(&[&self.request.name]);
+        let claims = decode::<RegistrationChallenge>(&self.challenge, &key, &validation)
+            .map_err(|e| anyhow!("Invalid registration challenge: {e}"))?
+            .claims;
+
+        let now = Utc::now().timestamp() as usize;
+        if claims.exp.saturating_sub(claims.iat) > MAX_CHALLENGE_VALIDITY
+            || claims.iat > now + validation.leeway as usize
+        {
+            return Err(anyhow!("Registration challenge validity too long"));
+        }
+        Ok(key)
+    }
+
+    /// Self-registered endpoints are pending until approved by an admin
+    pub fn build_endpoint(&self) -> Result<(Endpoint, PubKey)> {
+        let (mut endpoint, pubkey) = CreateEP(self.request.clone()).build_endpoint()?;
+        endpoint.status = EndpointStatus::PENDING;
+        Ok((endpoint, pubkey))
+    }
+}
+
+impl ApproveEP {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.endpoint_id)?)
+    }
+}
+
 pub enum GetBy {
     NAME(String),
     ID(DieselUlid),
@@ -0,0 +1,163 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::object_dsl::{Object, ObjectWithRelations};
+use crate::database::dsls::persistent_notification_dsl::NotificationReference;
+use crate::database::enums::{
+    NotificationReferenceType, ObjectStatus, PersistentNotificationVariant,
+};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::delete_request_types::DeleteRequest;
+use crate::middlelayer::expiry_request_types::{
+    expiry_label, get_expiry, is_expired, SetObjectExpiry, EXPIRES_AT_KEY,
+};
+use crate::search::meilisearch_client::MeilisearchClient;
+use crate::utils::search_utils;
+use crate::utils::user_notification_utils::{notify_user, USER_NOTIFICATION_CONFIG};
+use anyhow::{anyhow, bail, Result};
+use aruna_rust_api::api::notification::services::v2::EventVariant;
+use aruna_rust_api::api::storage::services::v2::DeleteObjectRequest;
+use chrono::{NaiveDateTime, Utc};
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+use std::sync::Arc;
+use std::time::Duration;
+
+impl DatabaseHandler {
+    /// Checks if `expires_at` extends the current expiry of the object
+    pub fn extends_expiry(
+        &self,
+        object_id: &DieselUlid,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool> {
+        let object = self
+            .cache
+            .get_object(object_id)
+            .ok_or_else(|| anyhow!("Object not found"))?;
+        Ok(get_expiry(&object.object)?.is_some_and(|current| expires_at > current))
+    }
+
+    /// Replaces the expiry label of an object which is not expired yet
+    pub async fn set_object_expiry(
+        &self,
+        request: SetObjectExpiry,
+        now: NaiveDateTime,
+    ) -> Result<ObjectWithRelations> {
+        let object_id = request.get_id()?;
+        let expires_at = request.get_expires_at(now)?;
+
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+        let mut object = Object::get_for_update(&object_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Object not found"))?;
+        if object.object_status == ObjectStatus::DELETED {
+            bail!("Object is deleted");
+        }
+        if is_expired(&object, now) {
+            bail!("Object expired");
+        }
+        object.key_values.0 .0.retain(|kv| kv.key != EXPIRES_AT_KEY);
+        object.key_values.0 .0.push(expiry_label(expires_at));
+        object.update(transaction_client).await?;
+        transaction.commit().await?;
+
+        let object = Object::get_object_with_relations(&object_id, &client).await?;
+        self.cache.upsert_object(&object_id, object.clone());
+
+        let hierarchies = object.object.fetch_object_hierarchies(&client).await?;
+        if let Err(err) = self
+            .natsio_handler
+            .register_resource_event(
+                &object,
+                hierarchies,
+                EventVariant::Updated,
+                Some(&DieselUlid::generate()), // block_id for deduplication
+            )
+            .await
+        {
+            log::error!("{}", err);
+            return Err(anyhow!("Notification emission failed"));
+        }
+        Ok(object)
+    }
+
+    /// Deletes all objects which expired before `now` and notifies their owners.
+    ///
+    /// Returns the ids of all deleted objects.
+    pub async fn expire_objects(&self, now: NaiveDateTime) -> Result<Vec<DieselUlid>> {
+        let client = self.database.get_client().await?;
+        let expired = Object::get_objects_with_key(EXPIRES_AT_KEY, &client)
+            .await?
+            .into_iter()
+            .filter(|object| is_expired(object, now))
+            .collect_vec();
+
+        let mut deleted = Vec::with_capacity(expired.len());
+        for object in expired {
+            // Revisions share the expiry and are deleted together with the latest revision
+            if deleted.contains(&object.id) {
+                continue;
+            }
+            match self
+                .delete_resource(DeleteRequest::Object(DeleteObjectRequest {
+                    object_id: object.id.to_string(),
+                    with_revisions: true,
+                }))
+                .await
+            {
+                Ok(objects) => deleted.extend(objects.into_iter().map(|o| o.object.id)),
+                Err(err) => {
+                    log::warn!("Deleting expired object {} failed: {}", object.id, err);
+                    continue;
+                }
+            }
+
+            if let Err(err) = notify_user(
+                &client,
+                &USER_NOTIFICATION_CONFIG,
+                object.created_by,
+                PersistentNotificationVariant::OBJECT_EXPIRED,
+                format!(
+                    "Object {} {} expired and was deleted",
+                    object.name, object.id
+                ),
+                vec![NotificationReference {
+                    reference_type: NotificationReferenceType::Resource,
+                    reference_name: object.name.clone(),
+                    reference_value: object.id.to_string(),
+                }],
+            )
+            .await
+            {
+                log::error!("Expiry notification failed: {}", err);
+            }
+        }
+
+        Ok(deleted)
+    }
+}
+
+/// Periodically deletes expired objects
+pub async fn start_expiry_cleanup_loop(
+    database_handler: Arc<DatabaseHandler>,
+    search_client: Arc<MeilisearchClient>,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+
+            match database_handler
+                .expire_objects(Utc::now().naive_utc())
+                .await
+            {
+                Ok(deleted) if !deleted.is_empty() => {
+                    log::info!("Deleted {} expired objects", deleted.len());
+                    search_utils::remove_from_search_index(&search_client, deleted).await;
+                }
+                Ok(_) => {}
+                Err(err) => log::error!("Expiry cleanup failed: {}", err),
+            }
+        }
+    });
+}
@@ -0,0 +1,93 @@
+use crate::database::dsls::object_dsl::{KeyValue, KeyValueVariant, Object};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, NaiveDateTime};
+use diesel_ulid::DieselUlid;
+use lazy_static::lazy_static;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Label with the RFC3339 timestamp after which an object expires
+pub const EXPIRES_AT_KEY: &str = "app.aruna-storage.org/expires-at";
+
+lazy_static! {
+    /// Time between runs of the sweeper which deletes expired objects
+    pub static ref EXPIRY_CLEANUP_INTERVAL: Duration = Duration::from_secs(
+        dotenvy::var("EXPIRY_CLEANUP_INTERVAL")
+            .map(|var| var.parse::<u64>().unwrap_or(300))
+            .unwrap_or(300)
+    );
+}
+
+pub fn parse_expiry(value: &str) -> Result<NaiveDateTime> {
+    Ok(DateTime::parse_from_rfc3339(value)
+        .map_err(|_| anyhow!("Expiry has to be a RFC3339 timestamp"))?
+        .naive_utc())
+}
+
+/// Expiry of an object, objects without expiry label never expire
+pub fn get_expiry(object: &Object) -> Result<Option<NaiveDateTime>> {
+    object
+        .key_values
+        .0
+         .0
+        .iter()
+        .find(|kv| kv.key == EXPIRES_AT_KEY)
+        .map(|kv| parse_expiry(&kv.value))
+        .transpose()
+}
+
+/// Objects with an invalid expiry label are treated as expired
+pub fn is_expired(object: &Object, now: NaiveDateTime) -> bool {
+    match get_expiry(object) {
+        Ok(Some(expires_at)) => expires_at <= now,
+        Ok(None) => false,
+        Err(_) => true,
+    }
+}
+
+/// Checks the expiry label of a new object, it has to be a valid timestamp in the future
+pub fn validate_expiry_label(
+    key_values: &[aruna_rust_api::api::storage::models::v2::KeyValue],
+    now: NaiveDateTime,
+) -> Result<()> {
+    let mut labels = key_values.iter().filter(|kv| kv.key == EXPIRES_AT_KEY);
+    if let Some(label) = labels.next() {
+        if labels.next().is_some() {
+            bail!("Only one expiry can be set");
+        }
+        if parse_expiry(&label.value)? <= now {
+            bail!("Expiry has to be in the future");
+        }
+    }
+    Ok(())
+}
+
+pub fn expiry_label(expires_at: NaiveDateTime) -> KeyValue {
+    KeyValue {
+        key: EXPIRES_AT_KEY.to_string(),
+        value: expires_at.and_utc().to_rfc3339(),
+        variant: KeyValueVariant::LABEL,
+        value_type: None,
+    }
+}
+
+/// Sets or extends the expiry of an object.
+#[derive(Debug, Clone)]
+pub struct SetObjectExpiry {
+    pub object_id: String,
+    pub expires_at: String,
+}
+
+impl SetObjectExpiry {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.object_id)?)
+    }
+
+    pub fn get_expires_at(&self, now: NaiveDateTime) -> Result<NaiveDateTime> {
+        let expires_at = parse_expiry(&self.expires_at)?;
+        if expires_at <= now {
+            bail!("Expiry has to be in the future");
+        }
+        Ok(expires_at)
+    }
+}
@@ -0,0 +1,46 @@
+use crate::database::dsls::object_dsl::{Algorithm, Hash, Object};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use anyhow::{bail, Result};
+use diesel_ulid::DieselUlid;
+
+/// Content lookup of objects by one of their stored hashes
+pub struct FindObjectsByHash {
+    pub algorithm: i32,
+    pub hex_digest: String,
+}
+
+impl FindObjectsByHash {
+    pub fn get_hash(&self) -> Result<Hash> {
+        let alg = Algorithm::try_from(self.algorithm)?;
+        let expected_len = match alg {
+            Algorithm::MD5 => 32,
+            Algorithm::SHA256 => 64,
+        };
+        if self.hex_digest.len() != expected_len
+            || !self.hex_digest.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            bail!("Invalid hex digest for hash algorithm")
+        }
+        Ok(Hash {
+            alg,
+            hash: self.hex_digest.to_ascii_lowercase(),
+        })
+    }
+}
+
+impl DatabaseHandler {
+    /// Returns the ids of all available objects with matching content,
+    /// permissions of the caller have to be checked for each returned id.
+    pub async fn find_objects_by_hash(
+        &self,
+        request: FindObjectsByHash,
+    ) -> Result<Vec<DieselUlid>> {
+        let hash = request.get_hash()?;
+        let client = self.database.get_client().await?;
+        Ok(Object::get_objects_by_hash(&hash, &client)
+            .await?
+            .into_iter()
+            .map(|object| object.id)
+            .collect())
+    }
+}
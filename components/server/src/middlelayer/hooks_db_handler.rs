@@ -1,4 +1,7 @@
+use crate::caching::structs::ObjectWrapper;
 use crate::database::crud::CrudDb;
+use crate::database::dsls::dead_letter_hook_dsl::DeadLetterHook;
+use crate::database::dsls::deferred_hook_dsl::DeferredHook;
 use crate::database::dsls::hook_dsl::{
     Filter, Hook, HookStatusValues, HookStatusVariant, HookWithAssociatedProject, TriggerVariant,
 };
@@ -6,16 +9,28 @@ use crate::database::dsls::object_dsl::{KeyValue, KeyValueVariant};
 use crate::database::dsls::object_dsl::{Object, ObjectWithRelations};
 use crate::database::enums::ObjectMapping;
 use crate::hooks::hook_handler::HookMessage;
+use crate::hooks::hook_queue::{DeliveryFailure, OverflowPolicy, HOOK_QUEUE_CONFIG};
+use crate::hooks::preview_hook::{needs_preview, preview_hook, PREVIEW_HOOK_ID};
+use crate::hooks::scan_hook::{scan_hook, SCAN_HOOK_ID};
+use crate::hooks::validation_hook::{validation_hook, VALIDATION_HOOK_ID};
 use crate::middlelayer::db_handler::DatabaseHandler;
-use crate::middlelayer::hooks_request_types::{Callback, CreateHook};
-use anyhow::{anyhow, Result};
+use crate::middlelayer::hooks_request_types::{
+    Callback, CreateHook, DiscardDeadLetterHook, ListDeadLetterHooks, RetryDeadLetterHook,
+};
+use crate::utils::request_id_utils::current_request_id;
+use anyhow::{anyhow, bail, Result};
+use async_channel::TrySendError;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::middlelayer::hooks_request_types::ListBy;
 use aruna_rust_api::api::hooks::services::v2::AddProjectsToHookRequest;
+use aruna_rust_api::api::storage::models::v2::generic_resource;
 use diesel_ulid::DieselUlid;
 use postgres_types::Json;
 use regex::Regex;
 use std::str::FromStr;
+use tokio_postgres::Client;
 
 impl DatabaseHandler {
     pub async fn create_hook(&self, request: CreateHook, user_id: &DieselUlid) -> Result<Hook> {
@@ -143,6 +158,7 @@ impl DatabaseHandler {
                     key: request.0.hook_id,
                     value: serde_json::to_string(&value)?,
                     variant: KeyValueVariant::HOOK_STATUS,
+                    value_type: None,
                 };
                 // Update status
                 Object::add_key_value(&object_id, &client, kv.clone()).await?;
@@ -166,6 +182,7 @@ impl DatabaseHandler {
                             key: kv.key.clone(),
                             value,
                             variant: KeyValueVariant::HOOK_STATUS,
+                            value_type: None,
                         })
                     } else {
                         Ok(kv.clone())
@@ -236,7 +253,7 @@ impl DatabaseHandler {
         };
 
         // Get hooks that are associated with triggered-object parent-projects
-        let hooks: Vec<HookWithAssociatedProject> = {
+        let mut hooks: Vec<HookWithAssociatedProject> = {
             let mut hooks = Vec::new();
             // Filter through hooks
             for h in Hook::get_hooks_for_projects(&projects, &client).await? {
@@ -262,6 +279,7 @@ impl DatabaseHandler {
                                 key,
                                 value,
                                 variant,
+                                ..
                             }) => {
                                 let key_regex = if let Ok(regex) = Regex::new(&key) {
                                     regex
@@ -293,6 +311,12 @@ impl DatabaseHandler {
             }
             hooks
         };
+        // Previews are generated once per finished object, not once per project
+        if triggers.contains(&TriggerVariant::OBJECT_FINISHED) && needs_preview(&object) {
+            if let Some(project) = projects.first().and_then(|id| self.cache.get_object(id)) {
+                hooks.push(preview_hook(project.object.id, project.object.created_by)?);
+            }
+        }
         if hooks.is_empty() {
             Ok(())
         } else {
@@ -305,10 +329,213 @@ impl DatabaseHandler {
                     hook,
                     object: object.clone(),
                     user_id,
+                    request_id: current_request_id(),
                 };
-                self.hook_sender.send(message).await?;
+                self.enqueue_hook(message).await?;
             }
             Ok(())
         }
     }
+
+    /// Queues a hook message. If the queue is full the caller either waits
+    /// for free capacity or the message is deferred to the database.
+    pub async fn enqueue_hook(&self, message: HookMessage) -> Result<()> {
+        match HOOK_QUEUE_CONFIG.overflow {
+            OverflowPolicy::Block => {
+                if self.hook_sender.is_full() {
+                    log::warn!("Hook queue is full, waiting for free capacity");
+                }
+                self.hook_sender.send(message).await?;
+            }
+            OverflowPolicy::Persist => match self.hook_sender.try_send(message) {
+                Ok(()) => {}
+                Err(TrySendError::Full(message)) => {
+                    log::warn!(
+                        "Hook queue is full, deferring hook {} for object {}",
+                        message.hook.id,
+                        message.object.object.id
+                    );
+                    let client = self.database.get_client().await?;
+                    DeferredHook {
+                        id: DieselUlid::generate(),
+                        hook_id: message.hook.id,
+                        project_id: message.hook.project_id,
+                        object_id: message.object.object.id,
+                        user_id: message.user_id,
+                        request_id: message.request_id,
+                        created_at: chrono::Utc::now().naive_utc(),
+                    }
+                    .create(&client)
+                    .await?;
+                }
+                Err(TrySendError::Closed(_)) => bail!("Hook queue is closed"),
+            },
+        }
+        Ok(())
+    }
+
+    /// Moves deferred hook messages back into the queue while it has free capacity.
+    /// Messages of deleted hooks or objects are dropped.
+    pub async fn replay_deferred_hooks(&self) -> Result<usize> {
+        let client = self.database.get_client().await?;
+        let free = HOOK_QUEUE_CONFIG
+            .capacity
+            .saturating_sub(self.hook_sender.len());
+        if free == 0 {
+            return Ok(0);
+        }
+        let mut replayed = 0;
+        for deferred in DeferredHook::get_oldest(free as i64, &client).await? {
+            let message = self
+                .restore_hook_message(
+                    deferred.hook_id,
+                    deferred.project_id,
+                    deferred.object_id,
+                    deferred.user_id,
+                    deferred.request_id.clone(),
+                    &client,
+                )
+                .await?;
+            if let Some(message) = message {
+                if self.hook_sender.try_send(message).is_err() {
+                    // Queue was filled in the meantime
+                    break;
+                }
+                replayed += 1;
+            }
+            deferred.delete(&client).await?;
+        }
+        Ok(replayed)
+    }
+
+    /// Rebuilds a hook message of a persisted hook call.
+    /// Returns None if the hook or the object do not exist anymore.
+    async fn restore_hook_message(
+        &self,
+        hook_id: DieselUlid,
+        project_id: DieselUlid,
+        object_id: DieselUlid,
+        user_id: DieselUlid,
+        request_id: Option<String>,
+        client: &Client,
+    ) -> Result<Option<HookMessage>> {
+        let hook = if hook_id == DieselUlid::from_str(SCAN_HOOK_ID)? {
+            Some(scan_hook(project_id, user_id)?)
+        } else if hook_id == DieselUlid::from_str(PREVIEW_HOOK_ID)? {
+            Some(preview_hook(project_id, user_id)?)
+        } else if hook_id == DieselUlid::from_str(VALIDATION_HOOK_ID)? {
+            Some(validation_hook(project_id, user_id)?)
+        } else {
+            Hook::get(hook_id, client)
+                .await?
+                .map(|hook| HookWithAssociatedProject {
+                    id: hook.id,
+                    name: hook.name,
+                    description: hook.description,
+                    project_ids: hook.project_ids,
+                    owner: hook.owner,
+                    trigger: hook.trigger,
+                    timeout: hook.timeout,
+                    hook: hook.hook,
+                    project_id,
+                })
+        };
+        let object = self.cache.get_object(&object_id);
+        Ok(match (hook, object) {
+            (Some(hook), Some(object)) => Some(HookMessage {
+                hook,
+                object,
+                user_id,
+                request_id,
+            }),
+            _ => None,
+        })
+    }
+
+    /// Stores a hook call which failed after all retries for manual inspection
+    pub async fn dead_letter_hook(
+        &self,
+        message: &HookMessage,
+        failure: DeliveryFailure,
+    ) -> Result<DeadLetterHook> {
+        let client = self.database.get_client().await?;
+        let resource: generic_resource::Resource = ObjectWrapper {
+            object_with_relations: message.object.clone(),
+            rules: self
+                .cache
+                .get_rule_bindings(&message.object.object.id)
+                .unwrap_or_default(),
+        }
+        .into();
+        let mut dead_letter = DeadLetterHook {
+            id: DieselUlid::generate(),
+            hook_id: message.hook.id,
+            project_id: message.hook.project_id,
+            object_id: message.object.object.id,
+            user_id: message.user_id,
+            request_id: message.request_id.clone(),
+            payload: Json(serde_json::to_value(resource)?),
+            reason: failure.reason,
+            attempts: failure.attempts as i32,
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+        dead_letter.create(&client).await?;
+        Ok(dead_letter)
+    }
+
+    pub async fn list_dead_letter_hooks(
+        &self,
+        request: ListDeadLetterHooks,
+    ) -> Result<Vec<DeadLetterHook>> {
+        let client = self.database.get_client().await?;
+        match request.get_hook_id()? {
+            Some(hook_id) => DeadLetterHook::get_by_hook(&hook_id, &client).await,
+            None => DeadLetterHook::all(&client).await,
+        }
+    }
+
+    /// Queues a dead-lettered hook call again, it is removed from the
+    /// dead-letter store and runs through the hook handler like a new call.
+    pub async fn retry_dead_letter_hook(&self, request: RetryDeadLetterHook) -> Result<()> {
+        let client = self.database.get_client().await?;
+        let dead_letter = DeadLetterHook::get(request.get_id()?, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Dead-lettered hook not found"))?;
+        let message = self
+            .restore_hook_message(
+                dead_letter.hook_id,
+                dead_letter.project_id,
+                dead_letter.object_id,
+                dead_letter.user_id,
+                dead_letter.request_id.clone(),
+                &client,
+            )
+            .await?
+            .ok_or_else(|| anyhow!("Hook or object of the dead-lettered hook not found"))?;
+        self.enqueue_hook(message).await?;
+        dead_letter.delete(&client).await?;
+        Ok(())
+    }
+
+    pub async fn discard_dead_letter_hook(&self, request: DiscardDeadLetterHook) -> Result<()> {
+        let client = self.database.get_client().await?;
+        let dead_letter = DeadLetterHook::get(request.get_id()?, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Dead-lettered hook not found"))?;
+        dead_letter.delete(&client).await
+    }
+}
+
+/// Periodically queues hook messages which were deferred because the queue was full
+pub fn start_hook_replay_loop(db_handler: Arc<DatabaseHandler>, interval: Duration) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            match db_handler.replay_deferred_hooks().await {
+                Ok(0) => {}
+                Ok(replayed) => log::info!("Queued {replayed} deferred hooks"),
+                Err(err) => log::error!("Queueing deferred hooks failed: {}", err),
+            }
+        }
+    });
 }
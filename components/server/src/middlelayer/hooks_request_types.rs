@@ -5,6 +5,7 @@ use crate::database::dsls::hook_dsl::{
 };
 use crate::database::dsls::object_dsl::{KeyValue, KeyValueVariant, KeyValues, Object};
 use crate::database::enums::{DataClass, ObjectStatus};
+use crate::utils::conversions::objects::parse_typed_value;
 use anyhow::{anyhow, Result};
 use aruna_rust_api::api::dataproxy::services::v2::GetCredentialsResponse;
 use aruna_rust_api::api::hooks::services::v2::{
@@ -41,6 +42,45 @@ impl ListBy {
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Callback(pub HookCallbackRequest);
 
+/// Lists dead-lettered hook calls, optionally only those of one hook.
+#[derive(Debug, Clone)]
+pub struct ListDeadLetterHooks {
+    pub hook_id: Option<String>,
+}
+
+impl ListDeadLetterHooks {
+    pub fn get_hook_id(&self) -> Result<Option<DieselUlid>> {
+        Ok(self
+            .hook_id
+            .as_deref()
+            .map(DieselUlid::from_str)
+            .transpose()?)
+    }
+}
+
+/// Queues a dead-lettered hook call again.
+#[derive(Debug, Clone)]
+pub struct RetryDeadLetterHook {
+    pub id: String,
+}
+
+impl RetryDeadLetterHook {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.id)?)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscardDeadLetterHook {
+    pub id: String,
+}
+
+impl DiscardDeadLetterHook {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.id)?)
+    }
+}
+
 impl CreateHook {
     fn get_trigger(&self) -> Result<Trigger> {
         match self.0.trigger.clone() {
@@ -248,10 +288,12 @@ impl Callback {
                         KeyValueVariant::HOOK_STATUS
                     }
                 };
+                let (value, value_type) = parse_typed_value(&kv.value)?;
                 Ok(KeyValue {
                     key: kv.key,
-                    value: kv.value,
+                    value,
                     variant,
+                    value_type,
                 })
             })
             .collect::<Result<Vec<KeyValue>>>()?;
@@ -276,10 +318,12 @@ impl Callback {
                         KeyValueVariant::HOOK_STATUS
                     }
                 };
+                let (value, value_type) = parse_typed_value(&kv.value)?;
                 Ok(KeyValue {
                     key: kv.key,
-                    value: kv.value,
+                    value,
                     variant,
+                    value_type,
                 })
             })
             .collect::<Result<Vec<KeyValue>>>()?;
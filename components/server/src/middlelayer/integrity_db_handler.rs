@@ -0,0 +1,313 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::internal_relation_dsl::{
+    InternalRelation, INTERNAL_RELATION_VARIANT_BELONGS_TO,
+};
+use crate::database::dsls::object_dsl::{
+    ExternalRelations, Hashes, KeyValues, Object, ObjectWithRelations,
+};
+use crate::database::enums::{ObjectStatus, ObjectType};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::integrity_request_types::{
+    CheckIntegrity, IntegrityReport, LOST_AND_FOUND_NAME,
+};
+use crate::utils::traversal_utils::{TraversalLimits, TRAVERSAL_LIMITS};
+use anyhow::{anyhow, Result};
+use aruna_rust_api::api::notification::services::v2::EventVariant;
+use dashmap::DashMap;
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+use postgres_types::Json;
+use std::collections::HashSet;
+use tokio_postgres::{Client, IsolationLevel};
+
+impl DatabaseHandler {
+    /// Scans the hierarchy of a project for orphans, dangling relations and missing data.
+    /// The scan runs in a read-only snapshot, changes are only made in repair mode.
+    /// Truncated scans of hierarchies beyond the traversal limits are never repaired.
+    pub async fn check_integrity(
+        &self,
+        request: CheckIntegrity,
+        user_id: DieselUlid,
+    ) -> Result<IntegrityReport> {
+        let project_id = request.get_id()?;
+
+        let mut client = self.database.get_client().await?;
+        let transaction = client
+            .build_transaction()
+            .isolation_level(IsolationLevel::RepeatableRead)
+            .read_only(true)
+            .start()
+            .await?;
+        let limits = *TRAVERSAL_LIMITS;
+        let report = tokio::time::timeout(
+            limits.budget,
+            Self::scan_integrity(project_id, limits, transaction.client()),
+        )
+        .await
+        .map_err(|_| anyhow!("Integrity scan exceeds the traversal budget"))??;
+        transaction.commit().await?;
+
+        if !request.repair || report.truncated || report.is_consistent() {
+            return Ok(report);
+        }
+        self.repair_integrity(report, user_id).await
+    }
+
+    async fn scan_integrity(
+        project_id: DieselUlid,
+        limits: TraversalLimits,
+        client: &Client,
+    ) -> Result<IntegrityReport> {
+        let project = Object::get(project_id, client)
+            .await?
+            .ok_or_else(|| anyhow!("Project not found"))?;
+        if project.object_type != ObjectType::PROJECT {
+            return Err(anyhow!("Integrity checks need a project"));
+        }
+
+        let mut relations = InternalRelation::get_hierarchy_relations(
+            &project_id,
+            limits.max_depth,
+            limits.max_nodes + 1,
+            client,
+        )
+        .await?;
+        let truncated = relations.len() > limits.max_nodes;
+        relations.truncate(limits.max_nodes);
+        let mut ids = relations
+            .into_iter()
+            .map(|relation| relation.target_pid)
+            .collect::<HashSet<_>>();
+        ids.insert(project_id);
+        let resources =
+            Object::get_objects_with_relations(&ids.iter().cloned().collect_vec(), client).await?;
+
+        // Parents outside of the hierarchy, e.g. of resources shared with other projects
+        let external_ids = resources
+            .iter()
+            .flat_map(|r| {
+                r.inbound_belongs_to
+                    .0
+                    .iter()
+                    .map(|relation| relation.origin_pid)
+                    .collect_vec()
+            })
+            .filter(|id| !ids.contains(id))
+            .unique()
+            .collect_vec();
+        let external = Object::get_objects(&external_ids, client).await?;
+
+        Ok(IntegrityReport {
+            truncated,
+            ..IntegrityReport::analyze(project_id, &resources, &external)
+        })
+    }
+
+    /// Removes dangling relations and moves the topmost orphans into the lost-and-found
+    /// collection of the project. Orphaned collections are moved directly into the project.
+    async fn repair_integrity(
+        &self,
+        mut report: IntegrityReport,
+        user_id: DieselUlid,
+    ) -> Result<IntegrityReport> {
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+
+        let dangling = report
+            .dangling_relations
+            .iter()
+            .map(|relation| relation.relation_id)
+            .collect_vec();
+        InternalRelation::set_deleted(&dangling, transaction_client).await?;
+
+        let orphan_ids = report.orphans.iter().cloned().collect::<HashSet<_>>();
+        let orphans = Object::get_objects_with_relations(&report.orphans, transaction_client)
+            .await?
+            .into_iter()
+            .filter(|orphan| orphan.object.object_status != ObjectStatus::DELETED)
+            .collect_vec();
+        // Descendants of orphans are moved together with their topmost orphan
+        let roots = orphans
+            .into_iter()
+            .filter(|orphan| {
+                !orphan.inbound_belongs_to.0.iter().any(|relation| {
+                    !dangling.contains(&relation.id) && orphan_ids.contains(&relation.origin_pid)
+                })
+            })
+            .collect_vec();
+
+        let project = Object::get(report.project_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Project not found"))?;
+        let mut lost_and_found = None;
+        let mut created_lost_and_found = false;
+        let mut reparented = Vec::new();
+        for root in roots {
+            let parent = match root.object.object_type {
+                ObjectType::COLLECTION => project.clone(),
+                ObjectType::DATASET | ObjectType::OBJECT => {
+                    if lost_and_found.is_none() {
+                        let (collection, created) = Self::get_or_create_lost_and_found(
+                            &project,
+                            user_id,
+                            transaction_client,
+                        )
+                        .await?;
+                        created_lost_and_found = created;
+                        lost_and_found = Some(collection);
+                    }
+                    lost_and_found
+                        .clone()
+                        .ok_or_else(|| anyhow!("Lost and found missing"))?
+                }
+                ObjectType::PROJECT => continue,
+            };
+            let target_name = self
+                .get_name_normalization(&project.id, transaction_client)
+                .await?
+                .normalize(&root.object.name);
+            if Object::get_child_by_name(&parent.id, &target_name, transaction_client)
+                .await?
+                .is_some()
+            {
+                log::warn!(
+                    "Orphan {} not repaired, {} already has a child named {}",
+                    root.object.id,
+                    parent.id,
+                    root.object.name
+                );
+                continue;
+            }
+            InternalRelation {
+                id: DieselUlid::generate(),
+                origin_pid: parent.id,
+                origin_type: parent.object_type,
+                relation_name: INTERNAL_RELATION_VARIANT_BELONGS_TO.to_string(),
+                target_pid: root.object.id,
+                target_type: root.object.object_type,
+                target_name,
+            }
+            .create(transaction_client)
+            .await?;
+            reparented.push(root.object.id);
+        }
+        transaction.commit().await?;
+
+        // Reload everything touched by the repair
+        let mut updated = report
+            .dangling_relations
+            .iter()
+            .flat_map(|relation| [relation.origin_pid, relation.target_pid])
+            .chain(reparented.iter().cloned())
+            .chain([project.id])
+            .collect::<HashSet<_>>();
+        if let Some(collection) = &lost_and_found {
+            updated.insert(collection.id);
+        }
+        let updated =
+            Object::get_objects_with_relations(&updated.into_iter().collect_vec(), &client).await?;
+        for resource in &updated {
+            self.cache
+                .upsert_object(&resource.object.id, resource.clone());
+        }
+        for resource in updated
+            .iter()
+            .filter(|r| r.object.object_status != ObjectStatus::DELETED)
+        {
+            let variant = match &lost_and_found {
+                Some(collection)
+                    if created_lost_and_found && collection.id == resource.object.id =>
+                {
+                    EventVariant::Created
+                }
+                _ => EventVariant::Updated,
+            };
+            self.emit_integrity_event(resource, variant, &client)
+                .await?;
+        }
+
+        report.repaired = true;
+        report.lost_and_found = lost_and_found.map(|collection| collection.id);
+        report.reparented = reparented;
+        Ok(report)
+    }
+
+    async fn get_or_create_lost_and_found(
+        project: &Object,
+        user_id: DieselUlid,
+        client: &Client,
+    ) -> Result<(Object, bool)> {
+        if let Some(existing) =
+            Object::get_child_by_name(&project.id, LOST_AND_FOUND_NAME, client).await?
+        {
+            if existing.object.object_type != ObjectType::COLLECTION
+                || existing.object.object_status == ObjectStatus::DELETED
+            {
+                return Err(anyhow!(
+                    "{LOST_AND_FOUND_NAME} of the project is not a collection"
+                ));
+            }
+            return Ok((existing.object, false));
+        }
+
+        let mut collection = Object {
+            id: DieselUlid::generate(),
+            revision_number: 0,
+            name: LOST_AND_FOUND_NAME.to_string(),
+            title: "Lost and found".to_string(),
+            description: "Resources recovered by an integrity check".to_string(),
+            created_at: None,
+            created_by: user_id,
+            authors: Json(vec![]),
+            content_len: 0,
+            count: 0,
+            key_values: Json(KeyValues(vec![])),
+            object_status: ObjectStatus::AVAILABLE,
+            data_class: project.data_class.clone(),
+            object_type: ObjectType::COLLECTION,
+            external_relations: Json(ExternalRelations(DashMap::default())),
+            hashes: Json(Hashes(vec![])),
+            dynamic: true,
+            endpoints: project.endpoints.clone(),
+            metadata_license: project.metadata_license.clone(),
+            data_license: project.data_license.clone(),
+        };
+        collection.create(client).await?;
+        InternalRelation {
+            id: DieselUlid::generate(),
+            origin_pid: project.id,
+            origin_type: ObjectType::PROJECT,
+            relation_name: INTERNAL_RELATION_VARIANT_BELONGS_TO.to_string(),
+            target_pid: collection.id,
+            target_type: ObjectType::COLLECTION,
+            target_name: collection.name.clone(),
+        }
+        .create(client)
+        .await?;
+        Ok((collection, true))
+    }
+
+    async fn emit_integrity_event(
+        &self,
+        resource: &ObjectWithRelations,
+        variant: EventVariant,
+        client: &Client,
+    ) -> Result<()> {
+        let hierarchies = resource.object.fetch_object_hierarchies(client).await?;
+        if let Err(err) = self
+            .natsio_handler
+            .register_resource_event(
+                resource,
+                hierarchies,
+                variant,
+                Some(&DieselUlid::generate()), // block_id for deduplication
+            )
+            .await
+        {
+            log::error!("{}", err);
+            return Err(anyhow!("Notification emission failed"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use crate::database::dsls::object_dsl::{Object, ObjectWithRelations};
+use crate::database::enums::{ObjectStatus, ObjectType, ReplicationStatus};
+use anyhow::Result;
+use diesel_ulid::DieselUlid;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+
+/// Name of the collection orphans are moved into on repair
+pub const LOST_AND_FOUND_NAME: &str = "lost-and-found";
+
+/// Scan of the relation graph of a project, read-only unless `repair` is set.
+#[derive(Debug, Clone)]
+pub struct CheckIntegrity {
+    pub project_id: String,
+    pub repair: bool,
+}
+
+impl CheckIntegrity {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.project_id)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingRelation {
+    pub relation_id: DieselUlid,
+    pub origin_pid: DieselUlid,
+    pub target_pid: DieselUlid,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntegrityReport {
+    pub project_id: DieselUlid,
+    pub scanned_resources: usize,
+    // Resources which are not deleted but have no valid parent path to the project
+    pub orphans: Vec<DieselUlid>,
+    // Hierarchy relations from or to deleted or missing resources
+    pub dangling_relations: Vec<DanglingRelation>,
+    // Available objects without finished data on any endpoint
+    pub missing_blobs: Vec<DieselUlid>,
+    pub repaired: bool,
+    pub lost_and_found: Option<DieselUlid>,
+    pub reparented: Vec<DieselUlid>,
+    // The hierarchy exceeds the traversal limits, only a part of it was scanned
+    pub truncated: bool,
+}
+
+impl IntegrityReport {
+    /// Analyzes all resources below a project (including the project). `external` are the
+    /// parents outside of the scanned hierarchy which resources still belong to.
+    pub fn analyze(
+        project_id: DieselUlid,
+        resources: &[ObjectWithRelations],
+        external: &[Object],
+    ) -> Self {
+        let statuses: HashMap<DieselUlid, ObjectStatus> = resources
+            .iter()
+            .map(|r| (r.object.id, r.object.object_status.clone()))
+            .chain(external.iter().map(|o| (o.id, o.object_status.clone())))
+            .collect();
+        let is_alive = |id: &DieselUlid| {
+            statuses
+                .get(id)
+                .is_some_and(|status| *status != ObjectStatus::DELETED)
+        };
+
+        let mut dangling = BTreeMap::new();
+        for resource in resources {
+            for relation in resource
+                .inbound_belongs_to
+                .0
+                .iter()
+                .chain(resource.outbound_belongs_to.0.iter())
+            {
+                let relation = relation.value();
+                let reason = match (
+                    statuses.get(&relation.origin_pid),
+                    statuses.get(&relation.target_pid),
+                ) {
+                    (None, _) | (_, None) => "Missing resource",
+                    (Some(ObjectStatus::DELETED), _) | (_, Some(ObjectStatus::DELETED)) => {
+                        "Deleted resource"
+                    }
+                    _ => continue,
+                };
+                dangling
+                    .entry(relation.id)
+                    .or_insert_with(|| DanglingRelation {
+                        relation_id: relation.id,
+                        origin_pid: relation.origin_pid,
+                        target_pid: relation.target_pid,
+                        reason: reason.to_string(),
+                    });
+            }
+        }
+
+        // Valid paths start at the project and at parents in other hierarchies
+        let by_id: HashMap<DieselUlid, &ObjectWithRelations> =
+            resources.iter().map(|r| (r.object.id, r)).collect();
+        let mut valid = HashSet::new();
+        let mut queue = VecDeque::new();
+        if is_alive(&project_id) {
+            queue.push_back(project_id);
+        }
+        for resource in resources {
+            if is_alive(&resource.object.id)
+                && resource.inbound_belongs_to.0.iter().any(|relation| {
+                    !by_id.contains_key(&relation.origin_pid) && is_alive(&relation.origin_pid)
+                })
+            {
+                queue.push_back(resource.object.id);
+            }
+        }
+        while let Some(id) = queue.pop_front() {
+            if !valid.insert(id) {
+                continue;
+            }
+            if let Some(resource) = by_id.get(&id) {
+                for relation in resource.outbound_belongs_to.0.iter() {
+                    if is_alive(&relation.target_pid) && !valid.contains(&relation.target_pid) {
+                        queue.push_back(relation.target_pid);
+                    }
+                }
+            }
+        }
+
+        let alive = resources
+            .iter()
+            .filter(|r| r.object.object_status != ObjectStatus::DELETED)
+            .map(|r| &r.object);
+        let mut orphans = alive
+            .clone()
+            .filter(|o| o.id != project_id && !valid.contains(&o.id))
+            .map(|o| o.id)
+            .collect::<Vec<_>>();
+        orphans.sort();
+        let mut missing_blobs = alive
+            .filter(|o| {
+                o.object_type == ObjectType::OBJECT
+                    && o.object_status == ObjectStatus::AVAILABLE
+                    && o.content_len > 0
+                    && !o
+                        .endpoints
+                        .0
+                        .iter()
+                        .any(|ep| ep.status == Some(ReplicationStatus::Finished))
+            })
+            .map(|o| o.id)
+            .collect::<Vec<_>>();
+        missing_blobs.sort();
+
+        IntegrityReport {
+            project_id,
+            scanned_resources: resources.len(),
+            orphans,
+            dangling_relations: dangling.into_values().collect(),
+            missing_blobs,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.orphans.is_empty() && self.dangling_relations.is_empty()
+    }
+}
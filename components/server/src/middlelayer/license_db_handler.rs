@@ -1,8 +1,17 @@
 use crate::database::crud::CrudDb;
 use crate::database::dsls::license_dsl::License;
+use crate::database::dsls::license_override_dsl::LicenseOverride;
+use crate::database::dsls::object_dsl::{Object, ObjectWithRelations};
+use crate::database::enums::ObjectType;
 use crate::middlelayer::db_handler::DatabaseHandler;
-use anyhow::{anyhow, Result};
+use crate::middlelayer::license_request_types::{
+    EffectiveLicense, GrantLicenseOverride, ObjectLicenseTerms, RevokeLicenseOverride,
+    SetDefaultLicense, SetLicenseTerms,
+};
+use anyhow::{anyhow, bail, Result};
+use aruna_rust_api::api::notification::services::v2::EventVariant;
 use aruna_rust_api::api::storage::services::v2::CreateLicenseRequest;
+use diesel_ulid::DieselUlid;
 
 impl DatabaseHandler {
     pub async fn create_license(&self, request: CreateLicenseRequest) -> Result<String> {
@@ -23,4 +32,157 @@ impl DatabaseHandler {
         let licenses = License::all(&client).await?;
         Ok(licenses)
     }
+    pub async fn set_license_terms(&self, request: SetLicenseTerms) -> Result<License> {
+        let client = self.database.get_client().await?;
+        let license = License::set_terms(
+            &request.tag,
+            request.get_embargo_until()?,
+            request.get_access_days()?,
+            &client,
+        )
+        .await?
+        .ok_or_else(|| anyhow!("No license found"))?;
+        Ok(license)
+    }
+
+    /// Access terms of the data license of an object
+    pub async fn get_object_license_terms(
+        &self,
+        object_id: &DieselUlid,
+    ) -> Result<ObjectLicenseTerms> {
+        let object = self
+            .cache
+            .get_object(object_id)
+            .ok_or_else(|| anyhow!("Object not found"))?
+            .object;
+        let license = self.get_license(object.data_license.clone()).await?;
+        ObjectLicenseTerms::new(object.id, &license, object.created_at)
+    }
+
+    /// Checks if the license terms allow the user to access the data of an object.
+    /// Denied access fails with a `LicenseViolation`.
+    pub async fn check_license_terms(
+        &self,
+        object_id: &DieselUlid,
+        user_id: &DieselUlid,
+    ) -> Result<()> {
+        let terms = self.get_object_license_terms(object_id).await?;
+        let Err(violation) = terms.check(chrono::Utc::now().naive_utc()) else {
+            return Ok(());
+        };
+        // Grants on the object or any of its parents
+        let mut resource_ids = self
+            .cache
+            .upstream_dfs_iterative(object_id)?
+            .into_iter()
+            .flatten()
+            .map(|mapping| mapping.into_inner())
+            .collect::<Vec<_>>();
+        resource_ids.push(*object_id);
+        let client = self.database.get_client().await?;
+        if LicenseOverride::exists(user_id, &resource_ids, &client).await? {
+            return Ok(());
+        }
+        Err(violation.into())
+    }
+
+    pub async fn grant_license_override(
+        &self,
+        request: GrantLicenseOverride,
+        granted_by: DieselUlid,
+    ) -> Result<LicenseOverride> {
+        let (user_id, resource_id) = request.get_ids()?;
+        self.cache
+            .get_user(&user_id)
+            .ok_or_else(|| anyhow!("User not found"))?;
+        self.cache
+            .get_object(&resource_id)
+            .ok_or_else(|| anyhow!("Resource not found"))?;
+        let client = self.database.get_client().await?;
+        let mut grant = LicenseOverride {
+            id: DieselUlid::generate(),
+            user_id,
+            resource_id,
+            granted_by,
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+        grant.create(&client).await?;
+        Ok(grant)
+    }
+
+    pub async fn revoke_license_override(&self, request: RevokeLicenseOverride) -> Result<()> {
+        let client = self.database.get_client().await?;
+        LicenseOverride::get(request.get_id()?, &client)
+            .await?
+            .ok_or_else(|| anyhow!("License override not found"))?
+            .delete(&client)
+            .await
+    }
+
+    /// Sets the default data license of a project or collection. Only resources
+    /// created afterwards inherit it, existing children keep their licenses.
+    pub async fn set_default_license(
+        &self,
+        request: SetDefaultLicense,
+    ) -> Result<ObjectWithRelations> {
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+
+        let id = request.get_id()?;
+        let license_tag = request.get_license_tag();
+        let resource = Object::get_for_update(&id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Resource not found"))?;
+        if !matches!(
+            resource.object_type,
+            ObjectType::PROJECT | ObjectType::COLLECTION
+        ) {
+            bail!("Default licenses can only be set for projects and collections");
+        }
+        if let Some(tag) = &license_tag {
+            License::get(tag.clone(), transaction_client)
+                .await?
+                .ok_or_else(|| anyhow!("Invalid license: License not found"))?;
+        }
+        Object::set_default_license(&id, license_tag, transaction_client).await?;
+        transaction.commit().await?;
+
+        let resource = Object::get_object_with_relations(&id, &client).await?;
+        let hierarchies = resource.object.fetch_object_hierarchies(&client).await?;
+        if let Err(err) = self
+            .natsio_handler
+            .register_resource_event(
+                &resource,
+                hierarchies,
+                EventVariant::Updated,
+                Some(&DieselUlid::generate()), // block_id for deduplication
+            )
+            .await
+        {
+            log::error!("{}", err);
+            return Err(anyhow::anyhow!("Notification emission failed"));
+        }
+        Ok(resource)
+    }
+
+    /// Licenses of a resource and the default data license of its new children
+    pub fn get_effective_license(&self, resource_id: &DieselUlid) -> Result<EffectiveLicense> {
+        let object = self
+            .cache
+            .get_object(resource_id)
+            .ok_or_else(|| anyhow!("Resource not found"))?
+            .object;
+        let (inherited_from, default_license) = self
+            .cache
+            .get_default_license(resource_id)
+            .unwrap_or((object.id, object.data_license.clone()));
+        Ok(EffectiveLicense {
+            resource_id: object.id,
+            metadata_license: object.metadata_license,
+            data_license: object.data_license,
+            default_license,
+            inherited_from,
+        })
+    }
 }
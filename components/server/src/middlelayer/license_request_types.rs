@@ -0,0 +1,249 @@
+use crate::database::dsls::license_dsl::License;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Days, NaiveDateTime};
+use diesel_ulid::DieselUlid;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Access terms of the data license of a specific object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectLicenseTerms {
+    pub object_id: DieselUlid,
+    pub license_tag: String,
+    pub embargo_until: Option<NaiveDateTime>,
+    pub access_expires_at: Option<NaiveDateTime>,
+}
+
+/// Reason why the terms of a license deny access to the data of an object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LicenseViolation {
+    Embargoed { tag: String, until: NaiveDateTime },
+    AccessExpired { tag: String, since: NaiveDateTime },
+}
+
+impl Display for LicenseViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LicenseViolation::Embargoed { tag, until } => write!(
+                f,
+                "License {tag} embargoes the data until {}",
+                until.and_utc().to_rfc3339()
+            ),
+            LicenseViolation::AccessExpired { tag, since } => write!(
+                f,
+                "License {tag} does not allow access since {}",
+                since.and_utc().to_rfc3339()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LicenseViolation {}
+
+impl ObjectLicenseTerms {
+    pub fn new(
+        object_id: DieselUlid,
+        license: &License,
+        created_at: Option<NaiveDateTime>,
+    ) -> Result<Self> {
+        let access_expires_at = match (license.access_days, created_at) {
+            (Some(days), Some(created_at)) => Some(
+                created_at
+                    .checked_add_days(Days::new(days.max(0) as u64))
+                    .ok_or_else(|| anyhow!("Invalid access expiry"))?,
+            ),
+            _ => None,
+        };
+        Ok(ObjectLicenseTerms {
+            object_id,
+            license_tag: license.tag.clone(),
+            embargo_until: license.embargo_until,
+            access_expires_at,
+        })
+    }
+
+    pub fn check(&self, now: NaiveDateTime) -> std::result::Result<(), LicenseViolation> {
+        if let Some(until) = self.embargo_until {
+            if now < until {
+                return Err(LicenseViolation::Embargoed {
+                    tag: self.license_tag.clone(),
+                    until,
+                });
+            }
+        }
+        if let Some(since) = self.access_expires_at {
+            if now >= since {
+                return Err(LicenseViolation::AccessExpired {
+                    tag: self.license_tag.clone(),
+                    since,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sets the access terms of a license, terms which are not set are removed.
+#[derive(Debug, Clone)]
+pub struct SetLicenseTerms {
+    pub tag: String,
+    pub embargo_until: Option<String>, // RFC3339 timestamp
+    pub access_days: Option<i32>,
+}
+
+impl SetLicenseTerms {
+    pub fn get_embargo_until(&self) -> Result<Option<NaiveDateTime>> {
+        self.embargo_until
+            .as_deref()
+            .map(|value| {
+                Ok(DateTime::parse_from_rfc3339(value)
+                    .map_err(|_| anyhow!("Embargo has to be a RFC3339 timestamp"))?
+                    .naive_utc())
+            })
+            .transpose()
+    }
+
+    pub fn get_access_days(&self) -> Result<Option<i32>> {
+        match self.access_days {
+            Some(days) if days < 1 => bail!("Access has to be granted for at least one day"),
+            days => Ok(days),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GetObjectLicenseTerms {
+    pub object_id: String,
+}
+
+impl GetObjectLicenseTerms {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.object_id)?)
+    }
+}
+
+/// Exempts a user from the license terms of a resource and its descendants.
+#[derive(Debug, Clone)]
+pub struct GrantLicenseOverride {
+    pub user_id: String,
+    pub resource_id: String,
+}
+
+impl GrantLicenseOverride {
+    pub fn get_ids(&self) -> Result<(DieselUlid, DieselUlid)> {
+        Ok((
+            DieselUlid::from_str(&self.user_id)?,
+            DieselUlid::from_str(&self.resource_id)?,
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RevokeLicenseOverride {
+    pub override_id: String,
+}
+
+impl RevokeLicenseOverride {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.override_id)?)
+    }
+}
+
+/// Sets or unsets the data license new children of a project or collection inherit.
+#[derive(Debug, Clone)]
+pub struct SetDefaultLicense {
+    pub resource_id: String,
+    pub license_tag: Option<String>, // Unsets the default license if None
+}
+
+impl SetDefaultLicense {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.resource_id)?)
+    }
+    pub fn get_license_tag(&self) -> Option<String> {
+        self.license_tag.clone().filter(|tag| !tag.is_empty())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GetEffectiveLicense {
+    pub resource_id: String,
+}
+
+impl GetEffectiveLicense {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.resource_id)?)
+    }
+}
+
+/// Licenses of a resource and the default data license its new children inherit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectiveLicense {
+    pub resource_id: DieselUlid,
+    pub metadata_license: String,
+    pub data_license: String,
+    pub default_license: String,
+    // Resource which defines the default license, the resource itself if no
+    // default license is set in its hierarchy
+    pub inherited_from: DieselUlid,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license(embargo_until: Option<NaiveDateTime>, access_days: Option<i32>) -> License {
+        License {
+            tag: "Embargoed".to_string(),
+            name: "Embargoed license".to_string(),
+            text: "".to_string(),
+            url: "".to_string(),
+            embargo_until,
+            access_days,
+        }
+    }
+
+    #[test]
+    fn test_license_terms() {
+        let now = chrono::Utc::now().naive_utc();
+        let created_at = now - chrono::Duration::days(10);
+        let object_id = DieselUlid::generate();
+
+        // Licenses without terms allow access
+        let terms =
+            ObjectLicenseTerms::new(object_id, &license(None, None), Some(created_at)).unwrap();
+        assert!(terms.check(now).is_ok());
+
+        // Access is denied before the embargo lifts
+        let until = now + chrono::Duration::days(1);
+        let terms =
+            ObjectLicenseTerms::new(object_id, &license(Some(until), None), Some(created_at))
+                .unwrap();
+        assert!(matches!(
+            terms.check(now),
+            Err(LicenseViolation::Embargoed { until: u, .. }) if u == until
+        ));
+        assert!(terms.check(until).is_ok());
+
+        // Access is denied after it expired
+        let terms =
+            ObjectLicenseTerms::new(object_id, &license(None, Some(5)), Some(created_at)).unwrap();
+        assert_eq!(
+            terms.access_expires_at,
+            Some(created_at + chrono::Duration::days(5))
+        );
+        assert!(matches!(
+            terms.check(now),
+            Err(LicenseViolation::AccessExpired { .. })
+        ));
+        assert!(terms.check(created_at).is_ok());
+
+        let request = SetLicenseTerms {
+            tag: "Embargoed".to_string(),
+            embargo_until: Some("not a date".to_string()),
+            access_days: Some(0),
+        };
+        assert!(request.get_embargo_until().is_err());
+        assert!(request.get_access_days().is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::hook_dsl::TriggerVariant;
+use crate::database::dsls::object_dsl::{Object, ObjectWithRelations};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::lifecycle_request_types::{
+    state_label, Lifecycle, ObjectLifecycleState, TransitionObjectState, LIFECYCLE_STATE_KEY,
+};
+use anyhow::{anyhow, bail, Result};
+use aruna_rust_api::api::notification::services::v2::EventVariant;
+use diesel_ulid::DieselUlid;
+
+impl DatabaseHandler {
+    /// Lifecycle of the projects of a resource. Projects which share a resource
+    /// have to define the same lifecycle.
+    pub fn get_lifecycle(&self, resource_id: &DieselUlid) -> Result<Option<Lifecycle>> {
+        let mut lifecycle: Option<Lifecycle> = None;
+        for project in self
+            .get_project_ids(resource_id)
+            .iter()
+            .filter_map(|id| self.cache.get_object(id))
+        {
+            if let Some(project_lifecycle) = Lifecycle::from_project(&project.object)? {
+                match &lifecycle {
+                    Some(existing) if *existing != project_lifecycle => {
+                        bail!("Projects of the resource define conflicting lifecycles")
+                    }
+                    _ => lifecycle = Some(project_lifecycle),
+                }
+            }
+        }
+        Ok(lifecycle)
+    }
+
+    pub fn get_lifecycle_state(&self, object_id: &DieselUlid) -> Result<ObjectLifecycleState> {
+        let object = self
+            .cache
+            .get_object(object_id)
+            .ok_or_else(|| anyhow!("Object not found"))?;
+        let lifecycle = self
+            .get_lifecycle(object_id)?
+            .ok_or_else(|| anyhow!("Project defines no object lifecycle"))?;
+        let state = lifecycle.current_state(&object.object);
+        Ok(ObjectLifecycleState {
+            object_id: object_id.to_string(),
+            allowed_next_states: lifecycle.allowed_next_states(&state),
+            state,
+        })
+    }
+
+    /// Checks if the object can move from its current state into `state`
+    pub fn check_lifecycle_transition(&self, object_id: &DieselUlid, state: &str) -> Result<()> {
+        let current = self.get_lifecycle_state(object_id)?;
+        if !current.allowed_next_states.iter().any(|next| next == state) {
+            bail!(
+                "Transition from {} to {} is not allowed",
+                current.state,
+                state
+            );
+        }
+        Ok(())
+    }
+
+    /// Replaces the state label of the object. The added label triggers the
+    /// label hooks of the project, so transitions can be used as hook triggers.
+    pub async fn transition_object_state(
+        &self,
+        request: TransitionObjectState,
+    ) -> Result<ObjectWithRelations> {
+        let object_id = request.get_id()?;
+        let lifecycle = self
+            .get_lifecycle(&object_id)?
+            .ok_or_else(|| anyhow!("Project defines no object lifecycle"))?;
+
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+        let mut object = Object::get_for_update(&object_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Object not found"))?;
+        // Re-checked on the locked object to prevent concurrent transitions
+        lifecycle.check_transition(&lifecycle.current_state(&object), &request.state)?;
+        let label = state_label(&request.state);
+        object
+            .key_values
+            .0
+             .0
+            .retain(|kv| kv.key != LIFECYCLE_STATE_KEY);
+        object.key_values.0 .0.push(label.clone());
+        object.update(transaction_client).await?;
+        transaction.commit().await?;
+
+        let object = Object::get_object_with_relations(&object_id, &client).await?;
+        self.cache.upsert_object(&object_id, object.clone());
+
+        let db_handler = DatabaseHandler {
+            database: self.database.clone(),
+            natsio_handler: self.natsio_handler.clone(),
+            cache: self.cache.clone(),
+            hook_sender: self.hook_sender.clone(),
+        };
+        let owr = object.clone();
+        tokio::spawn(async move {
+            let call = db_handler
+                .trigger_hooks(owr, vec![TriggerVariant::LABEL_ADDED], Some(vec![label]))
+                .await;
+            if call.is_err() {
+                log::error!("{:?}", call);
+            }
+        });
+
+        let hierarchies = object.object.fetch_object_hierarchies(&client).await?;
+        if let Err(err) = self
+            .natsio_handler
+            .register_resource_event(
+                &object,
+                hierarchies,
+                EventVariant::Updated,
+                Some(&DieselUlid::generate()), // block_id for deduplication
+            )
+            .await
+        {
+            log::error!("{}", err);
+            return Err(anyhow!("Notification emission failed"));
+        }
+        Ok(object)
+    }
+}
@@ -0,0 +1,163 @@
+use crate::database::dsls::object_dsl::{KeyValue, KeyValueVariant, Object};
+use anyhow::{anyhow, bail, Result};
+use diesel_ulid::DieselUlid;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Project key-value with the object lifecycle of the project as JSON
+pub const LIFECYCLE_KEY: &str = "app.aruna-storage.org/lifecycle";
+/// Label with the current lifecycle state of an object
+pub const LIFECYCLE_STATE_KEY: &str = "app.aruna-storage.org/lifecycle-state";
+/// Names of the built-in object states which can not be used for custom states
+pub const RESERVED_STATES: [&str; 7] = [
+    "staging",
+    "initializing",
+    "validating",
+    "available",
+    "unavailable",
+    "error",
+    "deleted",
+];
+
+/// Custom object states and the allowed transitions between them, defined per project.
+/// Objects without a state label are in the initial state.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Lifecycle {
+    pub initial: String,
+    pub states: Vec<String>,
+    pub transitions: Vec<Transition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Transition {
+    pub from: String,
+    pub to: String,
+}
+
+impl Lifecycle {
+    pub fn validate(&self) -> Result<()> {
+        if self.states.is_empty() {
+            bail!("Lifecycle needs at least one state");
+        }
+        let mut states = HashSet::new();
+        for state in &self.states {
+            if state.trim().is_empty() {
+                bail!("Lifecycle states can not be empty");
+            }
+            if RESERVED_STATES.contains(&state.to_ascii_lowercase().as_str()) {
+                bail!("Lifecycle state {state} is reserved");
+            }
+            if !states.insert(state) {
+                bail!("Duplicate lifecycle state {state}");
+            }
+        }
+        if !states.contains(&self.initial) {
+            bail!("Initial state {} is not defined", self.initial);
+        }
+        for Transition { from, to } in &self.transitions {
+            if !states.contains(from) || !states.contains(to) {
+                bail!("Transition {from} -> {to} uses undefined states");
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses the lifecycle key-value of a project, if the project defines a lifecycle
+    pub fn from_project(project: &Object) -> Result<Option<Lifecycle>> {
+        project
+            .key_values
+            .0
+             .0
+            .iter()
+            .find(|kv| kv.key == LIFECYCLE_KEY)
+            .map(|kv| Lifecycle::from_str(&kv.value))
+            .transpose()
+    }
+
+    /// Current state of the object, objects without a state label are in the initial state
+    pub fn current_state(&self, object: &Object) -> String {
+        get_state_label(object).unwrap_or_else(|| self.initial.clone())
+    }
+
+    pub fn allowed_next_states(&self, state: &str) -> Vec<String> {
+        self.transitions
+            .iter()
+            .filter(|transition| transition.from == state)
+            .map(|transition| transition.to.clone())
+            .collect()
+    }
+
+    pub fn check_transition(&self, from: &str, to: &str) -> Result<()> {
+        if !self
+            .transitions
+            .iter()
+            .any(|transition| transition.from == from && transition.to == to)
+        {
+            bail!("Transition from {from} to {to} is not allowed");
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Lifecycle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let lifecycle: Lifecycle =
+            serde_json::from_str(s).map_err(|e| anyhow!("Invalid lifecycle: {e}"))?;
+        lifecycle.validate()?;
+        Ok(lifecycle)
+    }
+}
+
+pub fn get_state_label(object: &Object) -> Option<String> {
+    object
+        .key_values
+        .0
+         .0
+        .iter()
+        .find(|kv| kv.key == LIFECYCLE_STATE_KEY)
+        .map(|kv| kv.value.clone())
+}
+
+pub fn state_label(state: &str) -> KeyValue {
+    KeyValue {
+        key: LIFECYCLE_STATE_KEY.to_string(),
+        value: state.to_string(),
+        variant: KeyValueVariant::LABEL,
+        value_type: None,
+    }
+}
+
+/// Moves an object into another lifecycle state of its project.
+#[derive(Debug, Clone)]
+pub struct TransitionObjectState {
+    pub object_id: String,
+    pub state: String,
+}
+
+impl TransitionObjectState {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.object_id)?)
+    }
+}
+
+/// Request for the lifecycle overview of an object.
+#[derive(Debug, Clone)]
+pub struct GetObjectLifecycleState {
+    pub object_id: String,
+}
+
+impl GetObjectLifecycleState {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.object_id)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectLifecycleState {
+    pub object_id: String,
+    pub state: String,
+    pub allowed_next_states: Vec<String>,
+}
@@ -0,0 +1,36 @@
+use crate::database::dsls::object_dsl::Object;
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::listing_request_types::ListCollectionObjects;
+use crate::utils::traversal_utils::TRAVERSAL_LIMITS;
+use anyhow::{anyhow, Result};
+use diesel_ulid::DieselUlid;
+
+impl DatabaseHandler {
+    /// Ids of all objects below the collection. The ids are not filtered by the
+    /// permissions of the caller. Listings which exceed the traversal limits are truncated.
+    pub async fn list_collection_objects(
+        &self,
+        request: ListCollectionObjects,
+    ) -> Result<(Vec<DieselUlid>, bool)> {
+        let collection_id = request.get_id()?;
+        let name_prefix = request.get_name_prefix();
+
+        let limits = *TRAVERSAL_LIMITS;
+        let client = self.database.get_client().await?;
+        let mut ids = tokio::time::timeout(
+            limits.budget,
+            Object::list_collection_objects(
+                &collection_id,
+                name_prefix.as_deref(),
+                limits.max_depth,
+                limits.max_nodes + 1,
+                &client,
+            ),
+        )
+        .await
+        .map_err(|_| anyhow!("Listing exceeds the traversal budget"))??;
+        let truncated = ids.len() > limits.max_nodes;
+        ids.truncate(limits.max_nodes);
+        Ok((ids, truncated))
+    }
+}
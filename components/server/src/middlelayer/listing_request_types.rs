@@ -0,0 +1,21 @@
+use anyhow::Result;
+use diesel_ulid::DieselUlid;
+use std::str::FromStr;
+
+/// Lists the objects below a collection, paginated with the request metadata.
+#[derive(Debug, Clone)]
+pub struct ListCollectionObjects {
+    pub collection_id: String,
+    // Only objects whose name starts with the prefix are listed
+    pub name_prefix: Option<String>,
+}
+
+impl ListCollectionObjects {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.collection_id)?)
+    }
+
+    pub fn get_name_prefix(&self) -> Option<String> {
+        self.name_prefix.clone().filter(|prefix| !prefix.is_empty())
+    }
+}
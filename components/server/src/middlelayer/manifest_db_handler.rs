@@ -0,0 +1,153 @@
+use crate::auth::token_handler::TokenHandler;
+use crate::database::crud::CrudDb;
+use crate::database::dsls::manifest_dsl::{ObjectManifest, SignedManifest};
+use crate::database::dsls::object_dsl::Object;
+use crate::database::enums::{ObjectMapping, ObjectStatus, ObjectType};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::manifest_request_types::{
+    GetSignedManifest, ManifestImmutable, ManifestPolicy, MANIFEST_POLICY_KEY,
+};
+use crate::utils::traversal_utils::TRAVERSAL_LIMITS;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+use postgres_types::Json;
+use std::str::FromStr;
+use tokio_postgres::Client;
+
+impl DatabaseHandler {
+    /// Strictest manifest policy of the projects, collections and datasets above a resource
+    pub fn get_manifest_policy(&self, resource_id: &DieselUlid) -> Result<Option<ManifestPolicy>> {
+        let mut policy = None;
+        for parent_id in self
+            .cache
+            .upstream_dfs_iterative(resource_id)?
+            .into_iter()
+            .flatten()
+            .filter_map(|parent| match parent {
+                ObjectMapping::OBJECT(_) => None,
+                parent => Some(parent.into_inner()),
+            })
+            .unique()
+        {
+            let Some(parent) = self.cache.get_object(&parent_id) else {
+                continue;
+            };
+            for kv in parent
+                .object
+                .key_values
+                .0
+                 .0
+                .iter()
+                .filter(|kv| kv.key == MANIFEST_POLICY_KEY)
+            {
+                match ManifestPolicy::from_str(&kv.value) {
+                    Ok(parent_policy) => policy = policy.max(Some(parent_policy)),
+                    Err(e) => log::warn!("Invalid manifest policy of {parent_id}: {e}"),
+                }
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Signs the manifest of an available object whose parents enable signed manifests.
+    /// Unchanged objects keep their latest manifest, changed objects are signed as a new
+    /// manifest revision.
+    pub async fn sign_manifest(
+        &self,
+        object_id: &DieselUlid,
+        token_handler: &TokenHandler,
+    ) -> Result<Option<SignedManifest>> {
+        if self.get_manifest_policy(object_id)?.is_none() {
+            return Ok(None);
+        }
+        let client = self.database.get_client().await?;
+        let object = Object::get(*object_id, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Object not found"))?;
+        if object.object_type != ObjectType::OBJECT
+            || object.object_status != ObjectStatus::AVAILABLE
+        {
+            return Ok(None);
+        }
+
+        let latest = SignedManifest::get(*object_id, &client).await?;
+        if let Some(latest) = latest.as_ref() {
+            if latest.manifest.0.describes(&object) {
+                return Ok(Some(latest.clone()));
+            }
+        }
+        let now = Utc::now();
+        let revision = latest.map_or(0, |latest| latest.revision + 1);
+        let manifest = ObjectManifest::new(&object, revision, now.timestamp());
+        let (pubkey_serial, signature) = token_handler.sign_manifest(&manifest)?;
+        let mut signed = SignedManifest {
+            object_id: *object_id,
+            revision,
+            manifest: Json(manifest),
+            signature,
+            pubkey_serial,
+            signed_at: now.naive_utc(),
+        };
+        signed.create(&client).await?;
+        Ok(Some(signed))
+    }
+
+    /// Signs the manifests of the objects below a published resource, returns the
+    /// number of signed objects
+    pub async fn sign_published_manifests(
+        &self,
+        resource_id: &DieselUlid,
+        token_handler: &TokenHandler,
+    ) -> Result<usize> {
+        if self.get_manifest_policy(resource_id)?.is_none() {
+            return Ok(0);
+        }
+        let object_ids = {
+            let client = self.database.get_client().await?;
+            Object::list_collection_objects(
+                resource_id,
+                None,
+                TRAVERSAL_LIMITS.max_depth,
+                TRAVERSAL_LIMITS.max_nodes,
+                &client,
+            )
+            .await?
+        };
+        let mut signed = 0;
+        for object_id in object_ids {
+            if self
+                .sign_manifest(&object_id, token_handler)
+                .await?
+                .is_some()
+            {
+                signed += 1;
+            }
+        }
+        Ok(signed)
+    }
+
+    /// Latest signed manifest of an object
+    pub async fn get_signed_manifest(&self, request: GetSignedManifest) -> Result<SignedManifest> {
+        let object_id = request.get_id()?;
+        let client = self.database.get_client().await?;
+        SignedManifest::get(object_id, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Object has no signed manifest"))
+    }
+
+    /// Rejects changes of signed objects below parents with an immutable manifest policy
+    pub async fn check_manifest_mutation(
+        &self,
+        object_id: &DieselUlid,
+        client: &Client,
+    ) -> Result<()> {
+        if self.get_manifest_policy(object_id)? == Some(ManifestPolicy::Immutable)
+            && SignedManifest::get(*object_id, client).await?.is_some()
+        {
+            return Err(anyhow!(ManifestImmutable));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::metadata_schema_dsl::MetadataSchema;
+use crate::database::dsls::object_dsl::{KeyValue, Object};
+use crate::database::enums::{ObjectStatus, ObjectType};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::metadata_schema_request_types::{GetMetadataSchema, SetMetadataSchema};
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+use postgres_types::Json;
+use tokio_postgres::Client;
+
+impl DatabaseHandler {
+    /// Replaces the metadata schema of a project. Existing objects are not validated again.
+    pub async fn set_metadata_schema(
+        &self,
+        request: SetMetadataSchema,
+        user_id: DieselUlid,
+    ) -> Result<MetadataSchema> {
+        let project_id = request.get_id()?;
+        let fields = request.get_fields()?;
+
+        let client = self.database.get_client().await?;
+        let project = Object::get(project_id, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Project not found"))?;
+        if project.object_type != ObjectType::PROJECT
+            || project.object_status == ObjectStatus::DELETED
+        {
+            bail!("Metadata schemas can only be set for existing projects");
+        }
+        let mut schema = MetadataSchema {
+            project_id,
+            fields: Json(fields),
+            updated_by: user_id,
+            updated_at: Utc::now().naive_utc(),
+        };
+        schema.create(&client).await?;
+        Ok(schema)
+    }
+
+    pub async fn get_metadata_schema(&self, request: &GetMetadataSchema) -> Result<MetadataSchema> {
+        let client = self.database.get_client().await?;
+        MetadataSchema::get(request.get_id()?, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Project has no metadata schema"))
+    }
+
+    pub async fn delete_metadata_schema(&self, request: &GetMetadataSchema) -> Result<()> {
+        let client = self.database.get_client().await?;
+        self.get_metadata_schema(request)
+            .await?
+            .delete(&client)
+            .await
+    }
+
+    /// Validates the labels of an object against the metadata schemas of all projects
+    /// of `resource_id` (the object itself or the parent of a new object).
+    /// Violations are returned as `SchemaViolations` error.
+    pub async fn check_metadata_schemas(
+        &self,
+        resource_id: &DieselUlid,
+        key_values: &[KeyValue],
+        client: &Client,
+    ) -> Result<()> {
+        // Last element of each upstream hierarchy is the project
+        let projects = self
+            .cache
+            .upstream_dfs_iterative(resource_id)?
+            .into_iter()
+            .filter_map(|hierarchy| hierarchy.last().map(|project| project.into_inner()))
+            .unique()
+            .collect_vec();
+        for schema in MetadataSchema::get_for_projects(&projects, client).await? {
+            schema.check(key_values)?;
+        }
+        Ok(())
+    }
+}
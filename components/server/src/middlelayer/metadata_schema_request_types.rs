@@ -0,0 +1,35 @@
+use crate::database::dsls::metadata_schema_dsl::{MetadataField, MetadataFields};
+use anyhow::Result;
+use diesel_ulid::DieselUlid;
+use std::str::FromStr;
+
+/// Replaces the metadata schema of a project, new and updated objects have to match it.
+#[derive(Debug, Clone)]
+pub struct SetMetadataSchema {
+    pub project_id: String,
+    pub fields: Vec<MetadataField>,
+}
+
+impl SetMetadataSchema {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.project_id)?)
+    }
+
+    pub fn get_fields(&self) -> Result<MetadataFields> {
+        let fields = MetadataFields(self.fields.clone());
+        fields.validate()?;
+        Ok(fields)
+    }
+}
+
+/// Metadata schema of a project, used to get and delete schemas.
+#[derive(Debug, Clone)]
+pub struct GetMetadataSchema {
+    pub project_id: String,
+}
+
+impl GetMetadataSchema {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.project_id)?)
+    }
+}
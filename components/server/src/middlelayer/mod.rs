@@ -1,3 +1,5 @@
+pub mod access_policy_db_handler;
+pub mod access_policy_request_types;
 pub mod clone_db_handler;
 pub mod clone_request_types;
 pub mod create_db_handler;
@@ -7,25 +9,60 @@ pub mod delete_db_handler;
 pub mod delete_request_types;
 pub mod endpoints_db_handler;
 pub mod endpoints_request_types;
+pub mod expiry_db_handler;
+pub mod expiry_request_types;
+pub mod hash_db_handler;
 pub mod hooks_db_handler;
 pub mod hooks_request_types;
+pub mod integrity_db_handler;
+pub mod integrity_request_types;
 pub mod license_db_handler;
+pub mod license_request_types;
+pub mod lifecycle_db_handler;
+pub mod lifecycle_request_types;
+pub mod listing_db_handler;
+pub mod listing_request_types;
+pub mod manifest_db_handler;
+pub mod manifest_request_types;
+pub mod metadata_schema_db_handler;
+pub mod metadata_schema_request_types;
+pub mod name_reservation_db_handler;
+pub mod name_reservation_request_types;
+pub mod notification_retention_db_handler;
+pub mod notification_retention_request_types;
+pub mod pinned_view_db_handler;
+pub mod pinned_view_request_types;
 pub mod presigned_url_handler;
+pub mod preview_db_handler;
+pub mod preview_request_types;
+pub mod provenance_db_handler;
+pub mod provenance_request_types;
+pub mod publication_db_handler;
+pub mod publication_request_types;
+pub mod relation_type_db_handler;
+pub mod relation_type_request_types;
 pub mod relations_db_handler;
 pub mod relations_request_types;
 pub mod replication_db_handler;
 pub mod replication_request_types;
+pub mod requester_pays_db_handler;
+pub mod requester_pays_request_types;
 pub mod rule_db_handler;
 pub mod rule_request_types;
+pub mod scan_db_handler;
 pub mod service_account_request_types;
 pub mod service_accounts_db_handler;
 pub mod snapshot_db_handler;
 pub mod snapshot_request_types;
+pub mod staging_db_handler;
+pub mod symlink_db_handler;
+pub mod symlink_request_types;
 pub mod token_db_handler;
 pub mod token_request_types;
 pub mod update_db_handler;
 pub mod update_request_types;
 pub mod user_db_handler;
 pub mod user_request_types;
+pub mod validation_db_handler;
 pub mod workspace_db_handler;
 pub mod workspace_request_types;
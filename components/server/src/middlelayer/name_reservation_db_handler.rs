@@ -0,0 +1,86 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::name_reservation_dsl::NameReservation;
+use crate::database::dsls::object_dsl::Object;
+use crate::database::enums::{ObjectStatus, ObjectType};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::name_reservation_request_types::{ReleaseObjectName, ReserveObjectName};
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+use tokio_postgres::Client;
+
+impl DatabaseHandler {
+    /// Reserves an object name in a collection. Concurrent reservations of the same
+    /// name are decided by the unique index, only one of them succeeds.
+    pub async fn reserve_object_name(
+        &self,
+        request: ReserveObjectName,
+        user_id: DieselUlid,
+    ) -> Result<NameReservation> {
+        let parent_id = request.get_id()?;
+        let name = request.get_name()?;
+        let ttl = request.get_ttl()?;
+
+        let client = self.database.get_client().await?;
+        let parent = Object::get(parent_id, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Collection not found"))?;
+        if parent.object_type == ObjectType::OBJECT {
+            bail!("Names can only be reserved in collections, datasets or projects");
+        }
+        if parent.object_status == ObjectStatus::DELETED {
+            bail!("Collection is deleted");
+        }
+        let name = self
+            .get_name_normalization(&parent_id, &client)
+            .await?
+            .normalize(&name);
+        if Object::get_child_by_name(&parent_id, &name, &client)
+            .await?
+            .is_some()
+        {
+            bail!("Name {name} is already used");
+        }
+
+        let mut reservation = NameReservation {
+            id: DieselUlid::generate(),
+            parent_id,
+            name,
+            created_by: user_id,
+            expires_at: Utc::now().naive_utc() + ttl,
+        };
+        reservation.create(&client).await?;
+        Ok(reservation)
+    }
+
+    pub async fn get_name_reservation(
+        &self,
+        request: &ReleaseObjectName,
+    ) -> Result<NameReservation> {
+        let client = self.database.get_client().await?;
+        NameReservation::get(request.get_token()?, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Reservation not found"))
+    }
+
+    pub async fn release_object_name(&self, reservation: &NameReservation) -> Result<()> {
+        let client = self.database.get_client().await?;
+        reservation.delete(&client).await
+    }
+
+    /// Checks that an active reservation of the normalized name in the parent is
+    /// presented with its token. Returns the reservation which the creation consumes.
+    pub async fn check_name_reservation(
+        parent_id: &DieselUlid,
+        name: &str,
+        token: Option<DieselUlid>,
+        client: &Client,
+    ) -> Result<Option<NameReservation>> {
+        match NameReservation::get_active(parent_id, name, client).await? {
+            Some(reservation) if Some(reservation.id) == token => Ok(Some(reservation)),
+            Some(_) => bail!("Name {name} is reserved"),
+            None if token.is_some() => bail!("Reservation not found or expired"),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::database::enums::ObjectType;
+use crate::utils::validation_utils::VALIDATION_RULES;
+use anyhow::{bail, Result};
+use chrono::Duration;
+use diesel_ulid::DieselUlid;
+use std::str::FromStr;
+
+/// Longest time a name can be reserved in seconds
+pub const MAX_RESERVATION_TTL: u64 = 86400;
+
+/// Reserves the name of an object in a collection until the object is created or the
+/// reservation expires after `ttl` seconds.
+#[derive(Debug, Clone)]
+pub struct ReserveObjectName {
+    pub collection_id: String,
+    pub name: String,
+    pub ttl: u64,
+}
+
+impl ReserveObjectName {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.collection_id)?)
+    }
+
+    pub fn get_name(&self) -> Result<String> {
+        VALIDATION_RULES.validate_name(ObjectType::OBJECT, &self.name)?;
+        Ok(self.name.clone())
+    }
+
+    pub fn get_ttl(&self) -> Result<Duration> {
+        if self.ttl == 0 || self.ttl > MAX_RESERVATION_TTL {
+            bail!("Reservations must last between 1 and {MAX_RESERVATION_TTL} seconds");
+        }
+        Ok(Duration::seconds(self.ttl as i64))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReleaseObjectName {
+    pub token: String,
+}
+
+impl ReleaseObjectName {
+    pub fn get_token(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.token)?)
+    }
+}
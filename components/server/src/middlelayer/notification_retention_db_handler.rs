@@ -0,0 +1,156 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::notification_watermark_dsl::NotificationWatermark;
+use crate::database::dsls::persistent_notification_dsl::PersistentNotification;
+use crate::database::enums::{NotificationReferenceType, ObjectMapping, ObjectType};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::notification_retention_request_types::{
+    GetNotificationRetention, NotificationRetention, NotificationRetentionConfig,
+    NotificationRetentionSettings, NotificationsPruned, ReplayPersistentNotifications,
+    NOTIFICATION_RETENTION_CONFIG,
+};
+use ahash::HashMap;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+use std::str::FromStr;
+use std::sync::Arc;
+
+// Notifications deleted per statement
+const PRUNE_CHUNK_SIZE: usize = 1000;
+
+impl DatabaseHandler {
+    /// Retention of the project, the server default applies to projects without one
+    pub fn get_notification_retention(
+        &self,
+        request: GetNotificationRetention,
+    ) -> Result<NotificationRetentionSettings> {
+        let project_id = request.get_id()?;
+        let project = self
+            .cache
+            .get_object(&project_id)
+            .filter(|project| project.object.object_type == ObjectType::PROJECT)
+            .ok_or_else(|| anyhow!("Project not found"))?;
+        let configured = NotificationRetention::from_project(&project.object);
+        Ok(NotificationRetentionSettings {
+            project_id: project_id.to_string(),
+            retention: configured.unwrap_or(NOTIFICATION_RETENTION_CONFIG.default),
+            project_configured: configured.is_some(),
+            min_age: NOTIFICATION_RETENTION_CONFIG.min_age.as_secs(),
+        })
+    }
+
+    /// Project of the first referenced resource which belongs to a project
+    fn notification_project(&self, notification: &PersistentNotification) -> Option<DieselUlid> {
+        notification
+            .refs
+            .0
+             .0
+            .iter()
+            .filter(|reference| reference.reference_type == NotificationReferenceType::Resource)
+            .filter_map(|reference| DieselUlid::from_str(&reference.reference_value).ok())
+            .find_map(|resource_id| {
+                self.cache
+                    .upstream_dfs_iterative(&resource_id)
+                    .ok()?
+                    .into_iter()
+                    .flatten()
+                    .find_map(|parent| match parent {
+                        ObjectMapping::PROJECT(project_id) => Some(project_id),
+                        _ => None,
+                    })
+            })
+    }
+
+    /// Deletes the persistent notifications which exceed the retention of their project
+    /// at `now` (milliseconds since the epoch) and raises the watermarks of their users.
+    /// Returns the number of deleted notifications.
+    pub async fn prune_persistent_notifications(
+        &self,
+        config: &NotificationRetentionConfig,
+        now: u64,
+    ) -> Result<usize> {
+        let client = self.database.get_client().await?;
+
+        // Notifications of each user and project
+        let mut groups: HashMap<(DieselUlid, Option<DieselUlid>), Vec<DieselUlid>> =
+            HashMap::default();
+        for notification in PersistentNotification::all(&client).await? {
+            let project_id = self.notification_project(&notification);
+            groups
+                .entry((notification.user_id, project_id))
+                .or_default()
+                .push(notification.id);
+        }
+
+        let mut pruned = Vec::new();
+        let mut watermarks: HashMap<DieselUlid, DieselUlid> = HashMap::default();
+        for ((user_id, project_id), mut ids) in groups {
+            let retention = project_id
+                .and_then(|id| self.cache.get_object(&id))
+                .and_then(|project| NotificationRetention::from_project(&project.object))
+                .unwrap_or(config.default);
+            ids.sort_by(|a, b| b.cmp(a));
+            for id in retention.exceeding(&ids, now, config.min_age) {
+                let watermark = watermarks.entry(user_id).or_insert(id);
+                *watermark = (*watermark).max(id);
+                pruned.push(id);
+            }
+        }
+
+        // Watermarks are raised first, failed deletions only make replays stricter
+        for (user_id, pruned_until) in watermarks {
+            NotificationWatermark {
+                user_id,
+                pruned_until,
+            }
+            .create(&client)
+            .await?;
+        }
+        for chunk in pruned.chunks(PRUNE_CHUNK_SIZE) {
+            PersistentNotification::acknowledge_user_notifications(&chunk.to_vec(), &client)
+                .await?;
+        }
+        Ok(pruned.len())
+    }
+
+    /// Persistent notifications of the user after the requested notification, fails with
+    /// `NotificationsPruned` if notifications after it were already pruned
+    pub async fn replay_persistent_notifications(
+        &self,
+        user_id: DieselUlid,
+        request: ReplayPersistentNotifications,
+    ) -> Result<Vec<PersistentNotification>> {
+        let after = request.get_after()?;
+        let client = self.database.get_client().await?;
+        if let Some(after) = after {
+            if let Some(watermark) = NotificationWatermark::get(user_id, &client).await? {
+                if after < watermark.pruned_until {
+                    return Err(NotificationsPruned {
+                        watermark: watermark.pruned_until,
+                    }
+                    .into());
+                }
+            }
+        }
+        PersistentNotification::get_user_notifications_after(&user_id, after.as_ref(), &client)
+            .await
+    }
+}
+
+/// Periodically deletes persistent notifications which exceed their retention
+pub fn start_notification_prune_loop(db_handler: Arc<DatabaseHandler>) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(NOTIFICATION_RETENTION_CONFIG.prune_interval).await;
+            let now = Utc::now().timestamp_millis() as u64;
+            match db_handler
+                .prune_persistent_notifications(&NOTIFICATION_RETENTION_CONFIG, now)
+                .await
+            {
+                Ok(0) => {}
+                Ok(pruned) => log::info!("Pruned {pruned} persistent notifications"),
+                Err(err) => log::error!("Pruning persistent notifications failed: {}", err),
+            }
+        }
+    });
+}
@@ -0,0 +1,230 @@
+use crate::database::dsls::object_dsl::Object;
+use anyhow::Result;
+use diesel_ulid::DieselUlid;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Project key-value with the JSON retention of the persistent notifications referencing
+/// resources of the project, e.g. `{"max_age": 2592000, "max_count": 1000}`.
+/// Ages are in seconds, counts apply to the notifications of each user.
+pub const NOTIFICATION_RETENTION_KEY: &str = "app.aruna-storage.org/notification-retention";
+
+lazy_static! {
+    pub static ref NOTIFICATION_RETENTION_CONFIG: NotificationRetentionConfig =
+        NotificationRetentionConfig::from_env();
+}
+
+/// Server wide retention of persistent notifications
+#[derive(Debug, Clone)]
+pub struct NotificationRetentionConfig {
+    // Applies to notifications without project retention
+    pub default: NotificationRetention,
+    // Notifications younger than this are never pruned, e.g. for compliance
+    pub min_age: Duration,
+    // Time between runs of the pruner
+    pub prune_interval: Duration,
+}
+
+impl NotificationRetentionConfig {
+    pub fn from_env() -> Self {
+        NotificationRetentionConfig {
+            default: NotificationRetention {
+                max_age: dotenvy::var("NOTIFICATION_RETENTION_MAX_AGE")
+                    .ok()
+                    .and_then(|var| var.parse::<u64>().ok()),
+                max_count: dotenvy::var("NOTIFICATION_RETENTION_MAX_COUNT")
+                    .ok()
+                    .and_then(|var| var.parse::<usize>().ok()),
+            },
+            min_age: Duration::from_secs(
+                dotenvy::var("NOTIFICATION_RETENTION_MIN_AGE")
+                    .map(|var| var.parse::<u64>().unwrap_or(0))
+                    .unwrap_or(0),
+            ),
+            prune_interval: Duration::from_secs(
+                dotenvy::var("NOTIFICATION_PRUNE_INTERVAL")
+                    .map(|var| var.parse::<u64>().unwrap_or(3600))
+                    .unwrap_or(3600),
+            ),
+        }
+    }
+}
+
+/// Notifications are kept forever without limits
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NotificationRetention {
+    #[serde(default)]
+    pub max_age: Option<u64>,
+    #[serde(default)]
+    pub max_count: Option<usize>,
+}
+
+impl NotificationRetention {
+    /// Retention configured by the project, the last valid key-value applies
+    pub fn from_project(project: &Object) -> Option<Self> {
+        project
+            .key_values
+            .0
+             .0
+            .iter()
+            .filter(|kv| kv.key == NOTIFICATION_RETENTION_KEY)
+            .filter_map(
+                |kv| match serde_json::from_str::<NotificationRetention>(&kv.value) {
+                    Ok(retention) => Some(retention),
+                    Err(e) => {
+                        log::warn!("Invalid notification retention of {}: {e}", project.id);
+                        None
+                    }
+                },
+            )
+            .last()
+    }
+
+    /// Notifications of one user, newest first, which exceed the retention at `now`
+    /// (milliseconds since the epoch). Notifications younger than `min_age` are kept.
+    pub fn exceeding(
+        &self,
+        newest_first: &[DieselUlid],
+        now: u64,
+        min_age: Duration,
+    ) -> Vec<DieselUlid> {
+        let min_age = min_age.as_millis() as u64;
+        newest_first
+            .iter()
+            .enumerate()
+            .filter(|(index, id)| {
+                let age = now.saturating_sub(id.timestamp());
+                age >= min_age
+                    && (self.max_count.is_some_and(|max_count| *index >= max_count)
+                        || self
+                            .max_age
+                            .is_some_and(|max_age| age > max_age.saturating_mul(1000)))
+            })
+            .map(|(_, id)| *id)
+            .collect()
+    }
+}
+
+/// Effective notification retention of a project
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct NotificationRetentionSettings {
+    pub project_id: String,
+    pub retention: NotificationRetention,
+    // Projects without own retention use the server default
+    pub project_configured: bool,
+    // Seconds
+    pub min_age: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetNotificationRetention {
+    pub project_id: String,
+}
+
+impl GetNotificationRetention {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.project_id)?)
+    }
+}
+
+/// Persistent notifications of the requesting user created after the notification with
+/// id `after`, all current notifications without it.
+#[derive(Debug, Clone)]
+pub struct ReplayPersistentNotifications {
+    pub after: Option<String>,
+}
+
+impl ReplayPersistentNotifications {
+    pub fn get_after(&self) -> Result<Option<DieselUlid>> {
+        self.after
+            .as_deref()
+            .filter(|after| !after.is_empty())
+            .map(|after| Ok(DieselUlid::from_str(after)?))
+            .transpose()
+    }
+}
+
+/// Replays starting before the latest pruned notification would miss notifications
+#[derive(Debug)]
+pub struct NotificationsPruned {
+    pub watermark: DieselUlid,
+}
+impl Display for NotificationsPruned {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Notifications up to {} were pruned, resync required",
+            self.watermark
+        )
+    }
+}
+impl Error for NotificationsPruned {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ulids created `age` milliseconds before `now`, newest first
+    fn ids(now: u64, ages: &[u64]) -> Vec<DieselUlid> {
+        const CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+        ages.iter()
+            .map(|age| {
+                let ulid = (((now - age) as u128) << 80) | (rand::random::<u128>() >> 48);
+                let encoded = (0..26)
+                    .rev()
+                    .map(|i| CROCKFORD[((ulid >> (i * 5)) & 31) as usize] as char)
+                    .collect::<String>();
+                DieselUlid::from_str(&encoded).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_retention_exceeding() {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let notifications = ids(now, &[1_000, 60_000, 120_000, 7_200_000]);
+
+        // Older than an hour
+        let by_age = NotificationRetention {
+            max_age: Some(3600),
+            max_count: None,
+        };
+        assert_eq!(
+            by_age.exceeding(&notifications, now, Duration::ZERO),
+            notifications[3..]
+        );
+
+        // More than two
+        let by_count = NotificationRetention {
+            max_age: None,
+            max_count: Some(2),
+        };
+        assert_eq!(
+            by_count.exceeding(&notifications, now, Duration::ZERO),
+            notifications[2..]
+        );
+
+        // The minimum age keeps younger notifications beyond the count
+        assert_eq!(
+            by_count.exceeding(&notifications, now, Duration::from_secs(600)),
+            notifications[3..]
+        );
+
+        // Without limits everything is kept
+        assert!(NotificationRetention::default()
+            .exceeding(&notifications, now, Duration::ZERO)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_retention_from_project_json() {
+        let retention: NotificationRetention =
+            serde_json::from_str(r#"{"max_count": 10}"#).unwrap();
+        assert_eq!(retention.max_age, None);
+        assert_eq!(retention.max_count, Some(10));
+    }
+}
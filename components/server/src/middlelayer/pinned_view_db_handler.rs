@@ -0,0 +1,97 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::object_dsl::{Object, ObjectWithRelations};
+use crate::database::dsls::pinned_view_dsl::PinnedView;
+use crate::database::enums::{ObjectStatus, ObjectType};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::pinned_view_request_types::{
+    CreatePinnedView, ListPinnedViews, ResolvePinnedView,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+use std::collections::{HashSet, VecDeque};
+
+impl DatabaseHandler {
+    /// Pins the current revisions of all objects below a collection
+    pub async fn create_pinned_view(
+        &self,
+        request: CreatePinnedView,
+        user_id: DieselUlid,
+    ) -> Result<PinnedView> {
+        let collection_id = request.get_id()?;
+        let name = request.get_name()?;
+
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+
+        let collection = Object::get(collection_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Collection not found"))?;
+        if collection.object_type != ObjectType::COLLECTION {
+            bail!("Pinned views can only be created for collections");
+        }
+        if collection.object_status == ObjectStatus::DELETED {
+            bail!("Collection is deleted");
+        }
+
+        // Only follow BELONGS_TO relations, deleted children are not part of the view
+        let mut revision_ids = HashSet::new();
+        let mut visited = HashSet::from([collection_id]);
+        let mut queue = VecDeque::from([collection_id]);
+        while let Some(id) = queue.pop_front() {
+            let children = Object::get_object_with_relations(&id, transaction_client)
+                .await?
+                .get_children()
+                .into_iter()
+                .filter(|child| visited.insert(*child))
+                .collect_vec();
+            for child in Object::get_objects(&children, transaction_client).await? {
+                if child.object_status == ObjectStatus::DELETED {
+                    continue;
+                }
+                match child.object_type {
+                    ObjectType::OBJECT => {
+                        revision_ids.insert(child.id);
+                    }
+                    ObjectType::DATASET => queue.push_back(child.id),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut view = PinnedView {
+            id: DieselUlid::generate(),
+            collection_id,
+            name,
+            created_by: user_id,
+            created_at: Utc::now().naive_utc(),
+            revision_ids: revision_ids.into_iter().sorted().collect(),
+        };
+        view.create(transaction_client).await?;
+        transaction.commit().await?;
+
+        Ok(view)
+    }
+
+    pub async fn list_pinned_views(&self, request: ListPinnedViews) -> Result<Vec<PinnedView>> {
+        let client = self.database.get_client().await?;
+        PinnedView::get_by_collection(&request.get_id()?, &client).await
+    }
+
+    pub async fn get_pinned_view(&self, request: &ResolvePinnedView) -> Result<PinnedView> {
+        let client = self.database.get_client().await?;
+        PinnedView::get(request.get_id()?, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Pinned view not found"))
+    }
+
+    /// Fetches the pinned revisions of a view, independent of later updates of the objects
+    pub async fn resolve_pinned_view(&self, view: &PinnedView) -> Result<Vec<ObjectWithRelations>> {
+        let client = self.database.get_client().await?;
+        let mut objects = Object::get_objects_with_relations(&view.revision_ids, &client).await?;
+        objects.sort_by_key(|object| object.object.id);
+        Ok(objects)
+    }
+}
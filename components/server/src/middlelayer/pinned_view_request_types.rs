@@ -0,0 +1,54 @@
+use crate::database::dsls::pinned_view_dsl::PinnedView;
+use crate::database::enums::ObjectType;
+use crate::utils::validation_utils::VALIDATION_RULES;
+use anyhow::Result;
+use aruna_rust_api::api::storage::models::v2::Object;
+use diesel_ulid::DieselUlid;
+use std::str::FromStr;
+
+/// Pins the current object revisions of a collection under a name.
+#[derive(Debug, Clone)]
+pub struct CreatePinnedView {
+    pub collection_id: String,
+    pub name: String,
+}
+
+impl CreatePinnedView {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.collection_id)?)
+    }
+
+    pub fn get_name(&self) -> Result<String> {
+        VALIDATION_RULES.validate_name(ObjectType::COLLECTION, &self.name)?;
+        Ok(self.name.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ListPinnedViews {
+    pub collection_id: String,
+}
+
+impl ListPinnedViews {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.collection_id)?)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvePinnedView {
+    pub view_id: String,
+}
+
+impl ResolvePinnedView {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.view_id)?)
+    }
+}
+
+/// A pinned view with the object revisions it references
+#[derive(Debug, Clone)]
+pub struct ResolvedPinnedView {
+    pub view: PinnedView,
+    pub objects: Vec<Object>,
+}
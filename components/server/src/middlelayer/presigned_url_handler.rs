@@ -2,7 +2,8 @@ use crate::auth::permission_handler::PermissionHandler;
 use crate::auth::token_handler::{Action, Intent};
 use crate::caching::cache::Cache;
 use crate::database::dsls::endpoint_dsl::{Endpoint, HostConfig};
-use crate::database::enums::{DataProxyFeature, ObjectMapping, ReplicationType};
+use crate::database::dsls::object_dsl::{Algorithm, Hash, Object};
+use crate::database::enums::{DataProxyFeature, ObjectMapping, ObjectStatus, ReplicationType};
 use crate::middlelayer::db_handler::DatabaseHandler;
 use crate::middlelayer::endpoints_request_types::GetEP;
 use anyhow::{anyhow, Result};
@@ -18,11 +19,18 @@ use aws_config::BehaviorVersion;
 use aws_sdk_s3::config::Credentials;
 use aws_sdk_s3::Client;
 use aws_types::region::Region;
+use base64::engine::general_purpose;
+use base64::Engine;
 use diesel_ulid::DieselUlid;
+use hmac::{Hmac, Mac};
+use ipnet::IpNet;
 use itertools::Itertools;
+use lazy_static::lazy_static;
 use log::debug;
 use reqsign::{AwsCredential, AwsV4Signer};
 use reqwest::Method;
+use sha2::Sha256;
+use std::fmt::Display;
 use std::str::FromStr;
 use std::sync::Arc;
 use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue};
@@ -30,6 +38,129 @@ use tonic::transport::{Channel, ClientTlsConfig};
 use tonic::Request;
 use url::Url;
 
+/// Metadata key and signed query parameter to bind presigned download urls to client networks
+pub const RESTRICT_TO_CIDR_KEY: &str = "x-aruna-restrict-to-cidr";
+/// Metadata key of the hex encoded md5 hash a presigned upload has to match
+pub const CONTENT_MD5_KEY: &str = "x-aruna-content-md5";
+/// Metadata key to request an `inline` or `attachment` download
+pub const CONTENT_DISPOSITION_KEY: &str = "x-aruna-content-disposition";
+/// Binary metadata key of the UTF-8 encoded filename of a download
+pub const DOWNLOAD_FILENAME_KEY: &str = "x-aruna-download-filename-bin";
+/// Object label with the default disposition of downloads
+pub const CONTENT_DISPOSITION_LABEL: &str = "app.aruna-storage.org/content-disposition";
+/// Signed query parameter which overrides the Content-Disposition of a download
+const RESPONSE_CONTENT_DISPOSITION: &str = "response-content-disposition";
+/// Signed query parameter which overrides the Content-Type of a download
+const RESPONSE_CONTENT_TYPE: &str = "response-content-type";
+/// Signed query parameter which attributes downloads of a presigned url to the identity that created it
+pub const ATTRIBUTION_KEY: &str = "x-aruna-attribution";
+/// Metadata key and signed query parameter of download urls which can only be used once
+pub const SINGLE_USE_KEY: &str = "x-aruna-single-use";
+/// Default and maximum validity of presigned download urls in seconds (one week)
+pub const MAX_DOWNLOAD_URL_TTL: i64 = 604800;
+/// Maximum number of objects of a batch download url request
+pub const MAX_DOWNLOAD_URL_BATCH_SIZE: usize = 1000;
+/// Metadata key of the declared size in bytes of an upload
+pub const CONTENT_LENGTH_KEY: &str = "x-aruna-content-length";
+/// Signed query parameter with the inclusive `<min>-<max>` size in bytes of a presigned upload
+pub const CONTENT_LENGTH_RANGE_KEY: &str = "x-aruna-content-length-range";
+/// Response metadata key of the recommended part size in bytes
+pub const PART_SIZE_KEY: &str = "x-aruna-part-size";
+/// Response metadata key of the number of parts of the recommended plan
+pub const PART_COUNT_KEY: &str = "x-aruna-part-count";
+/// Smallest part size accepted by S3 for all but the last part (5 MiB)
+pub const MIN_PART_SIZE: u64 = 5 * MIB;
+/// Largest part size accepted by S3 (5 GiB)
+pub const MAX_PART_SIZE: u64 = 5 * 1024 * MIB;
+/// Maximum number of parts of a S3 multipart upload
+pub const MAX_PARTS: u64 = 10000;
+const MIB: u64 = 1024 * 1024;
+
+type HmacSha256 = Hmac<Sha256>;
+
+lazy_static! {
+    /// Part size recommended for multipart uploads without declared size
+    pub static ref DEFAULT_PART_SIZE: u64 = dotenvy::var("MULTIPART_DEFAULT_PART_SIZE")
+        .ok()
+        .and_then(|var| var.parse::<u64>().ok())
+        .unwrap_or(64 * MIB)
+        .clamp(MIN_PART_SIZE, MAX_PART_SIZE);
+    /// Sizes presigned uploads with a declared size accept
+    pub static ref UPLOAD_SIZE_POLICY: UploadSizePolicy = UploadSizePolicy::from_env();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DispositionType {
+    Inline,
+    Attachment,
+}
+
+impl FromStr for DispositionType {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "inline" => Ok(DispositionType::Inline),
+            "attachment" => Ok(DispositionType::Attachment),
+            _ => Err(anyhow!("Content disposition must be inline or attachment")),
+        }
+    }
+}
+
+/// Requested Content-Disposition of a download, unset fields default
+/// to the label of the object (or inline) and the stored filename
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentDisposition {
+    pub disposition: Option<DispositionType>,
+    pub filename: Option<String>,
+}
+
+/// Optional parameters of presigned download urls, all of them are part of the signature
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DownloadUrlOptions {
+    pub restrict_to_cidr: Option<IpNet>,
+    pub content_disposition: Option<ContentDisposition>,
+    pub content_type: Option<String>,
+    // Validity in seconds, defaults to MAX_DOWNLOAD_URL_TTL
+    pub ttl: Option<i64>,
+    // Url expires after its first complete download
+    pub single_use: bool,
+}
+
+/// Download url request of a single object of a batch
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchDownloadUrlEntry {
+    pub object_id: String,
+    pub options: DownloadUrlOptions,
+}
+
+/// Presigned download urls for multiple objects with individual options.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GetDownloadUrlsBatch {
+    pub entries: Vec<BatchDownloadUrlEntry>,
+}
+
+/// Result for a single object of a batch, either the url or the reason it was not created
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchDownloadUrl {
+    pub object_id: String,
+    pub url: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Streamed presigned download urls for any number of objects.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CreateDownloadLinksStream {
+    pub entries: Vec<BatchDownloadUrlEntry>,
+}
+
+/// Message of a download links stream. Every object gets its own result, failed objects
+/// do not end the stream. The summary is always the last message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadLinksStreamMessage {
+    Link(BatchDownloadUrl),
+    Summary { succeeded: u64, failed: u64 },
+}
+
 pub struct PresignedUpload(pub GetUploadUrlRequest);
 pub struct PresignedDownload(pub GetDownloadUrlRequest);
 impl DatabaseHandler {
@@ -59,6 +190,13 @@ impl DatabaseHandler {
             true,
         )
         .await?;
+        let attribution = sign_attribution_token(
+            &credentials.secret_key,
+            &user_id,
+            token_id.as_ref(),
+            &object_id,
+            chrono::Utc::now().timestamp() + MAX_DOWNLOAD_URL_TTL,
+        )?;
         let url = sign_download_url(
             &credentials.access_key,
             &credentials.secret_key,
@@ -66,6 +204,12 @@ impl DatabaseHandler {
             &bucket_name,
             &key,
             &endpoint_s3_url,
+            MAX_DOWNLOAD_URL_TTL,
+            None,
+            None,
+            None,
+            None,
+            attribution,
         )?;
         Ok((url, credentials))
     }
@@ -76,11 +220,24 @@ impl DatabaseHandler {
         request: PresignedDownload,
         user_id: DieselUlid,
         token: Option<DieselUlid>,
+        options: DownloadUrlOptions,
     ) -> Result<String> {
         let object_id = request.get_id()?;
+        let ttl = options.get_ttl()?;
+        let content_type = options.get_content_type()?;
         let (project_id, bucket_name, key) =
             DatabaseHandler::get_path(object_id, cache.clone()).await?;
         let endpoint = self.get_fullsync_endpoint(project_id).await?;
+        let content_disposition = match options.content_disposition {
+            Some(content_disposition) => {
+                let object = cache
+                    .get_object(&object_id)
+                    .ok_or_else(|| anyhow!("Object not found"))?
+                    .object;
+                Some(content_disposition.to_header(&object))
+            }
+            None => None,
+        };
 
         // Not sure if this is needed
         // Check if user trusts endpoint
@@ -99,6 +256,13 @@ impl DatabaseHandler {
         let (_, endpoint_s3_url, ssl, credentials) =
             DatabaseHandler::get_or_create_credentials(authorizer, user_id, token, endpoint, true)
                 .await?;
+        let attribution = sign_attribution_token(
+            &credentials.secret_key,
+            &user_id,
+            token.as_ref(),
+            &object_id,
+            chrono::Utc::now().timestamp() + ttl,
+        )?;
         let url = sign_download_url(
             &credentials.access_key,
             &credentials.secret_key,
@@ -106,6 +270,14 @@ impl DatabaseHandler {
             &bucket_name,
             &key,
             &endpoint_s3_url,
+            ttl,
+            options.restrict_to_cidr,
+            content_disposition,
+            content_type,
+            options
+                .single_use
+                .then(|| DieselUlid::generate().to_string()),
+            attribution,
         )?;
         Ok(url)
     }
@@ -116,10 +288,26 @@ impl DatabaseHandler {
         authorizer: Arc<PermissionHandler>,
         user_id: DieselUlid,
         token: Option<DieselUlid>,
+        content_md5: Option<String>,
+        size_range: Option<ContentLengthRange>,
     ) -> Result<String> {
         let object_id = request.get_id()?;
         let multipart = request.get_multipart();
         let parts = request.get_parts()?;
+        let content_md5 = match content_md5 {
+            // Parts of multipart uploads have their own md5 hashes
+            Some(_) if multipart => {
+                return Err(anyhow!(
+                    "Content-MD5 is only supported for single part uploads"
+                ))
+            }
+            Some(md5) => {
+                let header = content_md5_header(&md5)?;
+                self.set_expected_md5(object_id, &md5).await?;
+                Some(header)
+            }
+            None => None,
+        };
 
         let (project_id, bucket_name, key) =
             DatabaseHandler::get_path(object_id, cache.clone()).await?;
@@ -140,6 +328,11 @@ impl DatabaseHandler {
         } else {
             None
         };
+        if let Some(upload_id) = &upload_id {
+            // Register upload id to abort the multipart upload on staging expiry
+            self.heartbeat_upload(&object_id, Some(upload_id.to_string()))
+                .await?;
+        }
 
         let signed_url = sign_url(
             Method::PUT,
@@ -153,9 +346,33 @@ impl DatabaseHandler {
             &key,
             &endpoint_s3_url,
             604800,
+            None,
+            content_md5,
+            size_range
+                .map(|range| (CONTENT_LENGTH_RANGE_KEY, range.to_string()))
+                .into_iter()
+                .collect(),
         )?;
         Ok(signed_url)
     }
+
+    /// Records the md5 hash the data of a staging object has to match when it is finished
+    pub async fn set_expected_md5(&self, object_id: DieselUlid, md5: &str) -> Result<()> {
+        let client = self.database.get_client().await?;
+        let mut object = Object::get_object_with_relations(&object_id, &client).await?;
+        if object.object.object_status != ObjectStatus::INITIALIZING {
+            return Err(anyhow!("Object is not staging"));
+        }
+        let hashes = &mut object.object.hashes.0 .0;
+        hashes.retain(|hash| hash.alg != Algorithm::MD5);
+        hashes.push(Hash {
+            alg: Algorithm::MD5,
+            hash: md5.to_ascii_lowercase(),
+        });
+        object.object.update(&client).await?;
+        self.cache.upsert_object(&object_id, object);
+        Ok(())
+    }
     async fn get_path(
         object_id: DieselUlid,
         cache: Arc<Cache>,
@@ -287,19 +504,28 @@ impl DatabaseHandler {
         Ok((project_id, project_name, key))
     }
     pub async fn get_fullsync_endpoint(&self, object_id: DieselUlid) -> Result<Endpoint> {
-        // Only gets first endpoint
-        let endpoint = *Vec::from_iter(
-            self.cache
-                .get_object(&object_id)
-                .ok_or_else(|| anyhow!("Object not found"))?
-                .object
-                .endpoints
-                .0,
-        )
-        .iter()
-        .find(|(_, ep)| matches!(ep.replication, ReplicationType::FullSync))
-        .ok_or_else(|| anyhow!("No full sync endpoint found"))?
-        .0;
+        let object = self
+            .cache
+            .get_object(&object_id)
+            .ok_or_else(|| anyhow!("Object not found"))?
+            .object;
+        let endpoints = Vec::from_iter(object.endpoints.0);
+        let is_full_sync = |id: &DieselUlid| {
+            endpoints.iter().any(|(ep_id, ep)| {
+                ep_id == id && matches!(ep.replication, ReplicationType::FullSync)
+            })
+        };
+        // Prefer the default endpoint of a project, otherwise only gets first endpoint
+        let endpoint = match object.get_default_endpoint() {
+            Some(default_endpoint) if is_full_sync(&default_endpoint) => default_endpoint,
+            _ => {
+                endpoints
+                    .iter()
+                    .find(|(_, ep)| matches!(ep.replication, ReplicationType::FullSync))
+                    .ok_or_else(|| anyhow!("No full sync endpoint found"))?
+                    .0
+            }
+        };
         // Fetch endpoint from cache/database
         self.get_endpoint(GetEP(GetEndpointRequest {
             endpoint: Some(APIEndpointEnum::EndpointId(endpoint.to_string())),
@@ -415,23 +641,7 @@ impl DatabaseHandler {
         key: &str,
     ) -> Result<Option<String>> {
         // Impersonate User and InitMultiPartUpload via S3 and endpoint_host_url for multipart uploads
-        let creds = Credentials::new(
-            access_key,
-            secret_key,
-            None,
-            None,
-            "ARUNA_SERVER", // Endpoint name?
-        );
-        let config = aws_config::defaults(BehaviorVersion::v2023_11_09())
-            .credentials_provider(creds)
-            .load()
-            .await;
-        let s3_config = aws_sdk_s3::config::Builder::from(&config)
-            .region(Region::new("RegionOne"))
-            .endpoint_url(endpoint_host_url)
-            .build();
-
-        let s3_client = Client::from_conf(s3_config);
+        let s3_client = impersonated_s3_client(access_key, secret_key, endpoint_host_url).await;
 
         let upload_id = s3_client
             .create_multipart_upload()
@@ -443,13 +653,248 @@ impl DatabaseHandler {
             .map(|id| id.to_string());
         Ok(upload_id)
     }
+
+    /// Aborts the multipart uploads of a staging object on its endpoint
+    /// in the name of the user who initialized the uploads.
+    pub async fn abort_multipart_uploads(
+        &self,
+        authorizer: Arc<PermissionHandler>,
+        object_id: DieselUlid,
+        user_id: DieselUlid,
+        upload_ids: &[String],
+    ) -> Result<()> {
+        if upload_ids.is_empty() {
+            return Ok(());
+        }
+
+        let (project_id, bucket_name, key) =
+            DatabaseHandler::get_path(object_id, self.cache.clone()).await?;
+        let endpoint = self.get_fullsync_endpoint(project_id).await?;
+        let (endpoint_host_url, _, _, credentials) =
+            DatabaseHandler::get_or_create_credentials(authorizer, user_id, None, endpoint, false)
+                .await?;
+
+        // Impersonate User and AbortMultipartUpload via S3 and endpoint_host_url
+        let s3_client = impersonated_s3_client(
+            &credentials.access_key,
+            &credentials.secret_key,
+            &endpoint_host_url,
+        )
+        .await;
+        for upload_id in upload_ids {
+            s3_client
+                .abort_multipart_upload()
+                .bucket(&bucket_name)
+                .key(&key)
+                .upload_id(upload_id)
+                .send()
+                .await?;
+        }
+        Ok(())
+    }
 }
+
+async fn impersonated_s3_client(
+    access_key: &str,
+    secret_key: &str,
+    endpoint_host_url: &str,
+) -> Client {
+    let creds = Credentials::new(
+        access_key,
+        secret_key,
+        None,
+        None,
+        "ARUNA_SERVER", // Endpoint name?
+    );
+    let config = aws_config::defaults(BehaviorVersion::v2023_11_09())
+        .credentials_provider(creds)
+        .load()
+        .await;
+    let s3_config = aws_sdk_s3::config::Builder::from(&config)
+        .region(Region::new("RegionOne"))
+        .endpoint_url(endpoint_host_url)
+        .build();
+
+    Client::from_conf(s3_config)
+}
+
 impl PresignedDownload {
     pub fn get_id(&self) -> Result<DieselUlid> {
         Ok(DieselUlid::from_str(&self.0.object_id)?)
     }
 }
 
+impl DownloadUrlOptions {
+    pub fn get_content_type(&self) -> Result<Option<String>> {
+        match &self.content_type {
+            Some(content_type)
+                if content_type.is_empty()
+                    || !content_type
+                        .chars()
+                        .all(|c| c.is_ascii() && !c.is_ascii_control()) =>
+            {
+                Err(anyhow!("Invalid response content type"))
+            }
+            content_type => Ok(content_type.clone()),
+        }
+    }
+    pub fn get_ttl(&self) -> Result<i64> {
+        match self.ttl {
+            None => Ok(MAX_DOWNLOAD_URL_TTL),
+            Some(ttl) if ttl > 0 && ttl <= MAX_DOWNLOAD_URL_TTL => Ok(ttl),
+            Some(_) => Err(anyhow!(
+                "Download url ttl must be between 1 and {MAX_DOWNLOAD_URL_TTL} seconds"
+            )),
+        }
+    }
+}
+
+impl ContentDisposition {
+    /// Builds the Content-Disposition header value, non ASCII filenames
+    /// are encoded according to RFC 5987 with an ASCII fallback
+    pub fn to_header(&self, object: &Object) -> String {
+        let disposition = self.disposition.unwrap_or_else(|| {
+            object
+                .key_values
+                .0
+                 .0
+                .iter()
+                .find(|kv| kv.key == CONTENT_DISPOSITION_LABEL)
+                .and_then(|kv| DispositionType::from_str(&kv.value).ok())
+                .unwrap_or(DispositionType::Inline)
+        });
+        let disposition = match disposition {
+            DispositionType::Inline => "inline",
+            DispositionType::Attachment => "attachment",
+        };
+        let filename = self.filename.as_deref().unwrap_or(&object.name);
+        content_disposition_header(disposition, filename)
+    }
+}
+
+/// Sizes the data proxy accepts for presigned uploads with a declared size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UploadSizePolicy {
+    // Uploads have exactly the declared size
+    Exact,
+    // Uploads are at most the factor smaller or larger than declared
+    Tolerance(f64),
+}
+
+impl UploadSizePolicy {
+    pub fn from_env() -> Self {
+        match dotenvy::var("UPLOAD_SIZE_MODE").as_deref() {
+            Ok("tolerance") => UploadSizePolicy::Tolerance(
+                dotenvy::var("UPLOAD_SIZE_TOLERANCE")
+                    .ok()
+                    .and_then(|var| var.parse::<f64>().ok())
+                    .filter(|tolerance| tolerance.is_finite() && *tolerance >= 1.0)
+                    .unwrap_or(1.1),
+            ),
+            _ => UploadSizePolicy::Exact,
+        }
+    }
+
+    pub fn range(&self, declared: u64) -> ContentLengthRange {
+        match self {
+            UploadSizePolicy::Exact => ContentLengthRange {
+                min: declared,
+                max: declared,
+            },
+            UploadSizePolicy::Tolerance(tolerance) => ContentLengthRange {
+                min: (declared as f64 / tolerance).floor() as u64,
+                max: (declared as f64 * tolerance).ceil() as u64,
+            },
+        }
+    }
+
+    /// Accepted sizes of a single part upload, or of a part of a multipart upload,
+    /// with the declared size of the whole object
+    pub fn upload_range(
+        &self,
+        content_len: Option<u64>,
+        part_plan: Option<&PartPlan>,
+        part_number: i32,
+    ) -> Option<ContentLengthRange> {
+        content_len.map(|content_len| {
+            self.range(
+                part_plan.map_or(content_len, |plan| plan.part_len(content_len, part_number)),
+            )
+        })
+    }
+}
+
+/// Inclusive size range in bytes of a presigned upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentLengthRange {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl Display for ContentLengthRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.min, self.max)
+    }
+}
+
+/// Recommended part layout of a multipart upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartPlan {
+    pub part_size: u64,
+    pub part_count: u64,
+}
+
+impl PartPlan {
+    /// Computes the part layout for the declared size of an upload:
+    ///
+    /// * The part size is the declared size divided by the maximum of 10,000 parts,
+    ///   but at least 5 MiB, rounded up to a whole MiB
+    /// * The part count is the declared size divided by the part size, rounded up
+    ///
+    /// Uploads without declared size get the configured default part size and
+    /// may use all 10,000 parts. Sizes which need parts above 5 GiB are rejected.
+    pub fn recommend(content_len: Option<u64>) -> Result<PartPlan> {
+        let Some(content_len) = content_len else {
+            return Ok(PartPlan {
+                part_size: *DEFAULT_PART_SIZE,
+                part_count: MAX_PARTS,
+            });
+        };
+        let part_size = content_len
+            .div_ceil(MAX_PARTS)
+            .max(MIN_PART_SIZE)
+            .div_ceil(MIB)
+            * MIB;
+        if part_size > MAX_PART_SIZE {
+            return Err(anyhow!("Declared size exceeds the maximum multipart size"));
+        }
+        Ok(PartPlan {
+            part_size,
+            part_count: content_len.div_ceil(part_size).max(1),
+        })
+    }
+
+    /// Size of a part of an upload with the declared size, all parts but the last
+    /// one have the part size
+    pub fn part_len(&self, content_len: u64, part_number: i32) -> u64 {
+        if (part_number as u64) < self.part_count {
+            self.part_size
+        } else {
+            content_len.saturating_sub(self.part_size * (self.part_count - 1))
+        }
+    }
+
+    pub fn check_part_number(&self, part_number: i32) -> Result<()> {
+        if part_number < 1 || part_number as u64 > self.part_count {
+            return Err(anyhow!(
+                "Part number has to be between 1 and {}",
+                self.part_count
+            ));
+        }
+        Ok(())
+    }
+}
+
 impl PresignedUpload {
     pub fn get_id(&self) -> Result<DieselUlid> {
         Ok(DieselUlid::from_str(&self.0.object_id)?)
@@ -462,7 +907,7 @@ impl PresignedUpload {
         let part_number = self.0.part_number;
         let parts = match (part_number, multipart) {
             (0, true) => return Err(anyhow!("No part number provided for multipart upload",)),
-            (n, true) if n < 1 => n,
+            (n, true) if n > 0 && n as u64 <= MAX_PARTS => n,
             (_, false) => 1,
             _ => return Err(anyhow!("Invalid part number")),
         };
@@ -485,6 +930,9 @@ impl PresignedUpload {
 /// * `key: &String` - Full path of object in bucket
 /// * `endpoint: &String` - Full path of object in bucket
 /// * `duration: i64` - Full path of object in bucket
+/// * `restrict_to_cidr: Option<IpNet>` - Client network the url is restricted to, part of the signed query
+/// * `content_md5: Option<String>` - Base64 encoded Content-MD5 header the request has to be sent with
+/// * `signed_params: Vec<(&str, String)>` - Response header overrides, attribution and upload sizes, part of the signed query
 /// *
 ///
 /// ## Returns:
@@ -504,6 +952,9 @@ fn sign_url(
     key: &str,
     endpoint: &str,
     duration: i64,
+    restrict_to_cidr: Option<IpNet>,
+    content_md5: Option<String>,
+    signed_params: Vec<(&str, String)>,
 ) -> Result<String> {
     let signer = AwsV4Signer::new("s3", "RegionOne");
 
@@ -520,7 +971,7 @@ fn sign_url(
     };
 
     // Construct request
-    let url = if multipart {
+    let mut url = if multipart {
         let upload_id = upload_id
             .ok_or_else(|| anyhow!("No upload id provided for multipart presigned url"))?;
         Url::parse(&format!(
@@ -533,8 +984,22 @@ fn sign_url(
             protocol, bucket, endpoint_sanitized, key
         ))?
     };
+    if let Some(network) = restrict_to_cidr {
+        url.query_pairs_mut()
+            .append_pair(RESTRICT_TO_CIDR_KEY, &network.to_string());
+    }
+    for (key, value) in signed_params {
+        url.query_pairs_mut().append_pair(key, &value);
+    }
 
     let mut req = reqwest::Request::new(method, url);
+    if let Some(content_md5) = content_md5 {
+        // Signed headers have to be sent with the exact same value
+        req.headers_mut().insert(
+            "content-md5",
+            reqwest::header::HeaderValue::from_str(&content_md5)?,
+        );
+    }
 
     // Signing request with Signer
     signer.sign_query(
@@ -551,6 +1016,7 @@ fn sign_url(
 }
 
 /// Convenience wrapper function for sign_url(...) to reduce unused parameters for download url.
+#[allow(clippy::too_many_arguments)]
 fn sign_download_url(
     access_key: &str,
     secret_key: &str,
@@ -558,7 +1024,22 @@ fn sign_download_url(
     bucket: &str,
     key: &str,
     endpoint: &str,
+    duration: i64,
+    restrict_to_cidr: Option<IpNet>,
+    content_disposition: Option<String>,
+    content_type: Option<String>,
+    single_use: Option<String>,
+    attribution: String,
 ) -> Result<String> {
+    let signed_params = [
+        (RESPONSE_CONTENT_DISPOSITION, content_disposition),
+        (RESPONSE_CONTENT_TYPE, content_type),
+        (SINGLE_USE_KEY, single_use),
+        (ATTRIBUTION_KEY, Some(attribution)),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| value.map(|value| (key, value)))
+    .collect();
     sign_url(
         Method::GET,
         access_key,
@@ -570,6 +1051,80 @@ fn sign_download_url(
         bucket,
         key,
         endpoint,
-        604800, //Note: Default 1 week until requests allow custom duration
+        duration,
+        restrict_to_cidr,
+        None,
+        signed_params,
     )
 }
+
+/// Creates the attribution token of a presigned download in the format `<payload>.<signature>`.
+///
+/// The payload is the url-safe base64 encoded `<user_id>:<token_id>:<resource_id>:<expires_at>`
+/// (`-` for personal sessions without token). The signature is the url-safe base64 encoded
+/// HMAC-SHA256 of the decoded payload with the secret key the url is signed with, which is
+/// only known to the server and the data proxy that issued it.
+fn sign_attribution_token(
+    secret_key: &str,
+    user_id: &DieselUlid,
+    token_id: Option<&DieselUlid>,
+    resource_id: &DieselUlid,
+    expires_at: i64,
+) -> Result<String> {
+    let token_id = token_id.map_or_else(|| "-".to_string(), |id| id.to_string());
+    let payload = format!("{user_id}:{token_id}:{resource_id}:{expires_at}");
+    let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes())?;
+    mac.update(payload.as_bytes());
+    Ok(format!(
+        "{}.{}",
+        general_purpose::URL_SAFE_NO_PAD.encode(payload),
+        general_purpose::URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+    ))
+}
+
+/// Converts a hex encoded md5 hash into the base64 encoded Content-MD5 header value
+fn content_md5_header(md5: &str) -> Result<String> {
+    let digest = hex::decode(md5).map_err(|_| anyhow!("Content-MD5 is not a hex encoded hash"))?;
+    if digest.len() != 16 {
+        return Err(anyhow!("Content-MD5 has an invalid length"));
+    }
+    Ok(general_purpose::STANDARD.encode(digest))
+}
+
+/// Builds a Content-Disposition header value with an ASCII fallback filename
+/// and the RFC 5987 encoded filename if it contains other characters
+fn content_disposition_header(disposition: &str, filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii() && !c.is_ascii_control() => c,
+            _ => '_',
+        })
+        .collect();
+    if fallback == filename {
+        return format!(r#"{disposition}; filename="{fallback}""#);
+    }
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'a'..=b'z'
+            | b'A'..=b'Z'
+            | b'0'..=b'9'
+            | b'!'
+            | b'#'
+            | b'$'
+            | b'&'
+            | b'+'
+            | b'-'
+            | b'.'
+            | b'^'
+            | b'_'
+            | b'`'
+            | b'|'
+            | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!(r#"{disposition}; filename="{fallback}"; filename*=UTF-8''{encoded}"#)
+}
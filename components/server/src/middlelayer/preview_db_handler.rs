@@ -0,0 +1,134 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::internal_relation_dsl::{
+    InternalRelation, INTERNAL_RELATION_VARIANT_BELONGS_TO, INTERNAL_RELATION_VARIANT_PREVIEW,
+};
+use crate::database::dsls::object_dsl::{
+    ExternalRelations, Hashes, KeyValue, KeyValueVariant, KeyValues, Object, ObjectWithRelations,
+};
+use crate::database::enums::{ObjectStatus, ObjectType};
+use crate::hooks::preview_hook::{Thumbnail, PREVIEW_KEY};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::preview_request_types::GetPreview;
+use anyhow::{anyhow, bail, Result};
+use dashmap::DashMap;
+use diesel_ulid::DieselUlid;
+use postgres_types::Json;
+
+impl DatabaseHandler {
+    /// Creates the staging object of a preview next to the original and links it with
+    /// a preview relation. The thumbnail is uploaded into the returned object afterwards.
+    pub async fn create_preview(
+        &self,
+        original_id: DieselUlid,
+        thumbnail: &Thumbnail,
+        user_id: DieselUlid,
+    ) -> Result<Object> {
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+
+        let mut original = Object::get_for_update(&original_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Object not found"))?;
+        if original.object_type != ObjectType::OBJECT
+            || original.object_status == ObjectStatus::DELETED
+        {
+            bail!("Previews can only be created for existing objects");
+        }
+        let parent = Object::get_object_with_relations(&original_id, transaction_client)
+            .await?
+            .inbound_belongs_to
+            .0
+            .iter()
+            .map(|relation| (relation.origin_pid, relation.origin_type))
+            .next()
+            .ok_or_else(|| anyhow!("Object has no parent"))?;
+
+        let mut preview = Object {
+            id: DieselUlid::generate(),
+            revision_number: 0,
+            name: thumbnail.object_name(&original_id),
+            title: format!("Preview of {}", original.name),
+            description: String::new(),
+            created_at: None,
+            created_by: user_id,
+            authors: Json(vec![]),
+            content_len: 0,
+            count: 1,
+            key_values: Json(KeyValues(vec![])),
+            object_status: ObjectStatus::INITIALIZING,
+            data_class: original.data_class.clone(),
+            object_type: ObjectType::OBJECT,
+            external_relations: Json(ExternalRelations(DashMap::default())),
+            hashes: Json(Hashes(vec![])),
+            dynamic: false,
+            endpoints: original.endpoints.clone(),
+            metadata_license: original.metadata_license.clone(),
+            data_license: original.data_license.clone(),
+        };
+        preview.create(transaction_client).await?;
+        for (origin_pid, origin_type, relation_name) in [
+            (parent.0, parent.1, INTERNAL_RELATION_VARIANT_BELONGS_TO),
+            (
+                original_id,
+                ObjectType::OBJECT,
+                INTERNAL_RELATION_VARIANT_PREVIEW,
+            ),
+        ] {
+            InternalRelation {
+                id: DieselUlid::generate(),
+                origin_pid,
+                origin_type,
+                relation_name: relation_name.to_string(),
+                target_pid: preview.id,
+                target_type: ObjectType::OBJECT,
+                target_name: preview.name.clone(),
+            }
+            .create(transaction_client)
+            .await?;
+        }
+        original.key_values.0 .0.retain(|kv| kv.key != PREVIEW_KEY);
+        original.key_values.0 .0.push(KeyValue {
+            key: PREVIEW_KEY.to_string(),
+            value: preview.id.to_string(),
+            variant: KeyValueVariant::LABEL,
+            value_type: None,
+        });
+        original.update(transaction_client).await?;
+        DatabaseHandler::init_staging_deadline(preview.id, user_id, transaction_client).await?;
+        transaction.commit().await?;
+
+        let updated =
+            Object::get_objects_with_relations(&vec![parent.0, original_id, preview.id], &client)
+                .await?;
+        for resource in updated {
+            self.cache.upsert_object(&resource.object.id, resource);
+        }
+        Ok(preview)
+    }
+
+    /// Returns the latest preview of an object
+    pub async fn get_preview(&self, request: GetPreview) -> Result<ObjectWithRelations> {
+        let object_id = request.get_id()?;
+        let object = self
+            .cache
+            .get_object(&object_id)
+            .ok_or_else(|| anyhow!("Object not found"))?;
+        let preview_id = object
+            .outbound
+            .0
+            .iter()
+            .filter(|relation| relation.relation_name == INTERNAL_RELATION_VARIANT_PREVIEW)
+            .map(|relation| relation.target_pid)
+            .max()
+            .ok_or_else(|| anyhow!("Object has no preview"))?;
+        let preview = self
+            .cache
+            .get_object(&preview_id)
+            .ok_or_else(|| anyhow!("Preview not found"))?;
+        if preview.object.object_status != ObjectStatus::AVAILABLE {
+            bail!("Preview is not available yet");
+        }
+        Ok(preview)
+    }
+}
@@ -0,0 +1,15 @@
+use anyhow::Result;
+use diesel_ulid::DieselUlid;
+use std::str::FromStr;
+
+/// Preview of an object generated by the built-in preview hook.
+#[derive(Debug, Clone)]
+pub struct GetPreview {
+    pub object_id: String,
+}
+
+impl GetPreview {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.object_id)?)
+    }
+}
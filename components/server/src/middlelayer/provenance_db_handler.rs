@@ -0,0 +1,152 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::object_dsl::Object;
+use crate::database::dsls::provenance_dsl::Provenance;
+use crate::database::enums::{ObjectStatus, ObjectType};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::provenance_request_types::{
+    GetLineage, Lineage, LineageNode, RecordProvenance,
+};
+use crate::utils::traversal_utils::{TraversalGuard, TraversalLimits, TRAVERSAL_LIMITS};
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+use tokio_postgres::Client;
+
+impl DatabaseHandler {
+    /// Stores the derivation of an object which a compute task reported as its result
+    pub async fn record_provenance(
+        &self,
+        request: RecordProvenance,
+        user_id: DieselUlid,
+    ) -> Result<Provenance> {
+        let derived_id = request.get_derived_id()?;
+        let source_ids = request.get_source_ids()?;
+        let (task_id, tool, tool_version) = request.get_task()?;
+
+        let client = self.database.get_client().await?;
+        let derived = Object::get(derived_id, &client)
+            .await?
+            .ok_or_else(|| anyhow!("Derived object not found"))?;
+        if derived.object_type != ObjectType::OBJECT {
+            bail!("Provenance can only be recorded for objects");
+        }
+        if derived.object_status == ObjectStatus::DELETED {
+            bail!("Derived object is deleted");
+        }
+        let sources = Object::get_objects(&source_ids, &client).await?;
+        if sources.len() != source_ids.len() {
+            bail!("Source object not found");
+        }
+        if let Some(source) = sources.iter().find(|source| {
+            source.object_type != ObjectType::OBJECT
+                || source.object_status == ObjectStatus::DELETED
+        }) {
+            bail!("Source {} is no available object", source.id);
+        }
+        // Derivation graphs are acyclic
+        let (ancestry, truncated) =
+            Self::collect_lineage(&source_ids, *TRAVERSAL_LIMITS, &client).await?;
+        if truncated {
+            bail!("Derivation graph of the sources exceeds the traversal limits");
+        }
+        if ancestry.contains_key(&derived_id) {
+            bail!("Source objects are derived from the derived object");
+        }
+
+        let mut provenance = Provenance {
+            id: DieselUlid::generate(),
+            derived_id,
+            source_ids,
+            task_id,
+            tool,
+            tool_version,
+            created_by: user_id,
+            created_at: Utc::now().naive_utc(),
+        };
+        provenance.create(&client).await?;
+        Ok(provenance)
+    }
+
+    /// Collects the upstream derivation graph of an object
+    pub async fn get_lineage(&self, request: GetLineage) -> Result<Lineage> {
+        let object_id = request.get_id()?;
+        let client = self.database.get_client().await?;
+        let (mut provenance, truncated) =
+            Self::collect_lineage(&[object_id], request.get_limits(), &client).await?;
+
+        let objects = Object::get_objects(&provenance.keys().cloned().collect_vec(), &client)
+            .await?
+            .into_iter()
+            .map(|object| (object.id, object))
+            .collect::<HashMap<_, _>>();
+        if !objects.contains_key(&object_id) {
+            bail!("Object not found");
+        }
+        let nodes = provenance
+            .drain()
+            .sorted_by_key(|(id, (distance, _))| (*distance, *id))
+            .map(|(object_id, (_, provenance))| {
+                let object = objects.get(&object_id);
+                LineageNode {
+                    object_id,
+                    name: object.map(|object| object.name.clone()),
+                    deleted: object
+                        .map_or(true, |object| object.object_status == ObjectStatus::DELETED),
+                    provenance,
+                }
+            })
+            .collect();
+        Ok(Lineage {
+            object_id,
+            nodes,
+            truncated,
+        })
+    }
+
+    /// Walks the derivations upstream from the given objects, returns all reached objects
+    /// with their distance and their derivation. Stops at the limits and reports whether
+    /// the graph was truncated.
+    async fn collect_lineage(
+        ids: &[DieselUlid],
+        limits: TraversalLimits,
+        client: &Client,
+    ) -> Result<(HashMap<DieselUlid, (usize, Option<Provenance>)>, bool)> {
+        let mut guard = TraversalGuard::new(limits);
+        let mut lineage = HashMap::new();
+        let mut visited = ids.iter().cloned().collect::<HashSet<_>>();
+        let mut current = ids.to_vec();
+        let mut distance = 0;
+        while !current.is_empty() {
+            if !guard.proceed(distance, lineage.len()) {
+                break;
+            }
+            let mut derivations = Provenance::get_by_derived(&current, client)
+                .await?
+                .into_iter()
+                .map(|provenance| (provenance.derived_id, provenance))
+                .collect::<HashMap<_, _>>();
+            let mut next = Vec::new();
+            for id in current {
+                if !guard.proceed(distance, lineage.len()) {
+                    break;
+                }
+                let provenance = derivations.remove(&id);
+                if let Some(provenance) = &provenance {
+                    next.extend(
+                        provenance
+                            .source_ids
+                            .iter()
+                            .filter(|source| visited.insert(**source))
+                            .cloned(),
+                    );
+                }
+                lineage.insert(id, (distance, provenance));
+            }
+            current = next;
+            distance += 1;
+        }
+        Ok((lineage, guard.is_truncated()))
+    }
+}
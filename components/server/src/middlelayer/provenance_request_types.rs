@@ -0,0 +1,106 @@
+use crate::database::dsls::provenance_dsl::Provenance;
+use crate::utils::traversal_utils::{TraversalLimits, TRAVERSAL_LIMITS};
+use anyhow::{bail, Result};
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+use std::str::FromStr;
+
+/// Records which source objects and which compute task produced an object.
+#[derive(Debug, Clone)]
+pub struct RecordProvenance {
+    pub derived_object_id: String,
+    pub source_object_ids: Vec<String>,
+    pub task_id: String,
+    pub tool: String,
+    pub tool_version: Option<String>,
+}
+
+impl RecordProvenance {
+    pub fn get_derived_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.derived_object_id)?)
+    }
+
+    pub fn get_source_ids(&self) -> Result<Vec<DieselUlid>> {
+        if self.source_object_ids.is_empty() {
+            bail!("Derived objects need at least one source");
+        }
+        let ids = self
+            .source_object_ids
+            .iter()
+            .map(|id| DieselUlid::from_str(id))
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .into_iter()
+            .unique()
+            .collect_vec();
+        if ids.contains(&self.get_derived_id()?) {
+            bail!("Objects can not be derived from themselves");
+        }
+        Ok(ids)
+    }
+
+    pub fn get_task(&self) -> Result<(String, String, Option<String>)> {
+        if self.task_id.trim().is_empty() || self.tool.trim().is_empty() {
+            bail!("Task id and tool must not be empty");
+        }
+        Ok((
+            self.task_id.trim().to_string(),
+            self.tool.trim().to_string(),
+            self.tool_version
+                .as_ref()
+                .map(|version| version.trim().to_string())
+                .filter(|version| !version.is_empty()),
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GetLineage {
+    pub object_id: String,
+    // Derivation steps to follow upstream, capped by TRAVERSAL_MAX_DEPTH
+    pub max_depth: Option<usize>,
+}
+
+impl GetLineage {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.object_id)?)
+    }
+
+    pub fn get_limits(&self) -> TraversalLimits {
+        TRAVERSAL_LIMITS.with_max_depth(self.max_depth)
+    }
+}
+
+/// Object of a derivation graph. Deleted objects are kept as tombstones, which only
+/// have a name if the object still exists in the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineageNode {
+    pub object_id: DieselUlid,
+    pub name: Option<String>,
+    pub deleted: bool,
+    // None for objects which were not derived by a recorded task
+    pub provenance: Option<Provenance>,
+}
+
+/// Upstream derivation graph of an object, nodes are ordered by their distance to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lineage {
+    pub object_id: DieselUlid,
+    pub nodes: Vec<LineageNode>,
+    // The graph continues beyond the nodes, the traversal stopped at its limits
+    pub truncated: bool,
+}
+
+impl Lineage {
+    pub fn get_node(&self, id: &DieselUlid) -> Option<&LineageNode> {
+        self.nodes.iter().find(|node| &node.object_id == id)
+    }
+
+    /// All objects the object was derived from, directly or indirectly
+    pub fn ancestors(&self) -> Vec<DieselUlid> {
+        self.nodes
+            .iter()
+            .map(|node| node.object_id)
+            .filter(|id| id != &self.object_id)
+            .collect()
+    }
+}
@@ -0,0 +1,223 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::object_dsl::{Object, ObjectWithRelations};
+use crate::database::dsls::persistent_notification_dsl::NotificationReference;
+use crate::database::dsls::publication_request_dsl::PublicationRequest;
+use crate::database::dsls::user_dsl::User;
+use crate::database::enums::{
+    DataClass, NotificationReferenceType, ObjectType, PersistentNotificationVariant,
+    PublicationStatus,
+};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::publication_request_types::{
+    pending_publication_label, DecidePublication, RequestPublication, PUBLICATION_STATE_KEY,
+};
+use crate::utils::user_notification_utils::{notify_user, USER_NOTIFICATION_CONFIG};
+use anyhow::{anyhow, bail, Result};
+use aruna_rust_api::api::notification::services::v2::EventVariant;
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+use tokio_postgres::Client;
+
+impl DatabaseHandler {
+    /// Requests to make a resource public. Without required approval the resource
+    /// is published immediately and the request is stored as approved by the requester.
+    pub async fn request_publication(
+        &self,
+        request: RequestPublication,
+        user_id: DieselUlid,
+        requires_approval: bool,
+    ) -> Result<(PublicationRequest, ObjectWithRelations)> {
+        let resource_id = request.get_id()?;
+
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+        let mut resource = Object::get_for_update(&resource_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Resource not found"))?;
+        if !matches!(
+            resource.object_type,
+            ObjectType::PROJECT | ObjectType::COLLECTION | ObjectType::DATASET
+        ) {
+            bail!("Only projects, collections and datasets can be published");
+        }
+        if resource.data_class == DataClass::PUBLIC {
+            bail!("Resource is already public");
+        }
+
+        let now = Utc::now().naive_utc();
+        let mut publication = PublicationRequest {
+            id: DieselUlid::generate(),
+            resource_id,
+            requested_by: user_id,
+            requested_at: now,
+            status: PublicationStatus::PENDING,
+            decided_by: None,
+            decided_at: None,
+            reason: None,
+        };
+        if requires_approval {
+            // Stays private with a pending state label until an approver decides
+            if resource
+                .key_values
+                .0
+                 .0
+                .iter()
+                .any(|kv| kv.key == PUBLICATION_STATE_KEY)
+            {
+                bail!("Publication was already requested");
+            }
+            resource.key_values.0 .0.push(pending_publication_label());
+        } else {
+            publication.status = PublicationStatus::APPROVED;
+            publication.decided_by = Some(user_id);
+            publication.decided_at = Some(now);
+            resource.data_class = DataClass::PUBLIC;
+        }
+        publication.create(transaction_client).await?;
+        resource.update(transaction_client).await?;
+        self.evaluate_rules(&vec![resource_id], transaction_client)
+            .await?;
+        transaction.commit().await?;
+
+        if requires_approval {
+            for admin_id in User::get_global_admin_ids(&client).await? {
+                self.notify_publication(
+                    &client,
+                    admin_id,
+                    PersistentNotificationVariant::PUBLICATION_REQUESTED,
+                    format!(
+                        "Publication of {} {} was requested",
+                        resource.name, resource_id
+                    ),
+                    &publication,
+                )
+                .await;
+            }
+        }
+
+        let resource = self.publication_updated(&resource_id, &client).await?;
+        Ok((publication, resource))
+    }
+
+    /// Approves or rejects a pending publication request
+    pub async fn decide_publication(
+        &self,
+        request: DecidePublication,
+        approver_id: DieselUlid,
+    ) -> Result<(PublicationRequest, ObjectWithRelations)> {
+        let request_id = request.get_id()?;
+        let reason = request.get_reason()?;
+
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+        let mut publication = PublicationRequest::get_for_update(&request_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Publication request not found"))?;
+        if publication.status != PublicationStatus::PENDING {
+            bail!("Publication request was already decided");
+        }
+        let mut resource = Object::get_for_update(&publication.resource_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Resource not found"))?;
+
+        let status = if request.approve {
+            resource.data_class = DataClass::PUBLIC;
+            PublicationStatus::APPROVED
+        } else {
+            PublicationStatus::REJECTED
+        };
+        publication
+            .decide(
+                status,
+                approver_id,
+                Utc::now().naive_utc(),
+                reason,
+                transaction_client,
+            )
+            .await?;
+        resource
+            .key_values
+            .0
+             .0
+            .retain(|kv| kv.key != PUBLICATION_STATE_KEY);
+        resource.update(transaction_client).await?;
+        self.evaluate_rules(&vec![resource.id], transaction_client)
+            .await?;
+        transaction.commit().await?;
+
+        let message = match &publication.reason {
+            Some(reason) => format!(
+                "Publication of {} {} was {:?}: {}",
+                resource.name, resource.id, status, reason
+            ),
+            None => format!(
+                "Publication of {} {} was {:?}",
+                resource.name, resource.id, status
+            ),
+        };
+        self.notify_publication(
+            &client,
+            publication.requested_by,
+            PersistentNotificationVariant::PUBLICATION_DECIDED,
+            message,
+            &publication,
+        )
+        .await;
+
+        let resource = self.publication_updated(&resource.id, &client).await?;
+        Ok((publication, resource))
+    }
+
+    async fn notify_publication(
+        &self,
+        client: &Client,
+        user_id: DieselUlid,
+        variant: PersistentNotificationVariant,
+        message: String,
+        publication: &PublicationRequest,
+    ) {
+        if let Err(err) = notify_user(
+            client,
+            &USER_NOTIFICATION_CONFIG,
+            user_id,
+            variant,
+            message,
+            vec![NotificationReference {
+                reference_type: NotificationReferenceType::Resource,
+                reference_name: publication.id.to_string(),
+                reference_value: publication.resource_id.to_string(),
+            }],
+        )
+        .await
+        {
+            log::error!("Publication notification failed: {}", err);
+        }
+    }
+
+    async fn publication_updated(
+        &self,
+        resource_id: &DieselUlid,
+        client: &Client,
+    ) -> Result<ObjectWithRelations> {
+        let resource = Object::get_object_with_relations(resource_id, client).await?;
+        self.cache.upsert_object(resource_id, resource.clone());
+
+        let hierarchies = resource.object.fetch_object_hierarchies(client).await?;
+        if let Err(err) = self
+            .natsio_handler
+            .register_resource_event(
+                &resource,
+                hierarchies,
+                EventVariant::Updated,
+                Some(&DieselUlid::generate()), // block_id for deduplication
+            )
+            .await
+        {
+            log::error!("{}", err);
+            return Err(anyhow!("Notification emission failed"));
+        }
+        Ok(resource)
+    }
+}
@@ -0,0 +1,73 @@
+use crate::database::dsls::object_dsl::{KeyValue, KeyValueVariant};
+use anyhow::{bail, Result};
+use diesel_ulid::DieselUlid;
+use lazy_static::lazy_static;
+use std::str::FromStr;
+
+/// Label of resources with a pending publication request
+pub const PUBLICATION_STATE_KEY: &str = "app.aruna-storage.org/publication-state";
+
+lazy_static! {
+    /// Resources are only made public after a global admin approved the request
+    pub static ref PUBLICATION_REQUIRES_APPROVAL: bool =
+        dotenvy::var("PUBLICATION_REQUIRES_APPROVAL")
+            .map(|var| var.parse::<bool>().unwrap_or(false))
+            .unwrap_or(false);
+}
+
+/// Public resources can only be created or made public directly by global admins
+/// if publications require approval
+pub fn publication_needs_admin(data_class: i32) -> bool {
+    *PUBLICATION_REQUIRES_APPROVAL && data_class == 1
+}
+
+pub fn pending_publication_label() -> KeyValue {
+    KeyValue {
+        key: PUBLICATION_STATE_KEY.to_string(),
+        value: "pending".to_string(),
+        variant: KeyValueVariant::LABEL,
+        value_type: None,
+    }
+}
+
+/// Request of a resource admin to make a project, collection or dataset public.
+#[derive(Debug, Clone)]
+pub struct RequestPublication {
+    pub resource_id: String,
+}
+
+impl RequestPublication {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.resource_id)?)
+    }
+}
+
+/// Decision of a global admin about a pending publication request.
+#[derive(Debug, Clone)]
+pub struct DecidePublication {
+    pub request_id: String,
+    pub approve: bool,
+    pub reason: Option<String>,
+}
+
+impl DecidePublication {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.request_id)?)
+    }
+
+    /// Rejections have to be explained to the requester
+    pub fn get_reason(&self) -> Result<Option<String>> {
+        let reason = self
+            .reason
+            .as_ref()
+            .map(|reason| reason.trim().to_string())
+            .filter(|reason| !reason.is_empty());
+        if !self.approve && reason.is_none() {
+            bail!("Rejections need a reason");
+        }
+        if reason.as_ref().is_some_and(|reason| reason.len() > 1023) {
+            bail!("Reason is too long");
+        }
+        Ok(reason)
+    }
+}
@@ -0,0 +1,146 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::internal_relation_dsl::InternalRelation;
+use crate::database::dsls::object_dsl::Object;
+use crate::database::dsls::relation_type_dsl::{RelationType, BUILT_IN_RELATION_TYPES};
+use crate::database::enums::ObjectType;
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::relation_type_request_types::{
+    CreateRelationType, DeleteRelationType, GetRelationTypes, GetRelationsByType,
+};
+use anyhow::{anyhow, bail, Result};
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+use tokio_postgres::Client;
+
+impl DatabaseHandler {
+    pub async fn create_relation_type(&self, request: CreateRelationType) -> Result<RelationType> {
+        let mut relation_type = request.get_relation_type()?;
+        let project_id = request.get_project_id()?;
+        let project = self
+            .cache
+            .get_object(&project_id)
+            .ok_or_else(|| anyhow!("Project not found"))?;
+        if project.object.object_type != ObjectType::PROJECT {
+            bail!("Relation types can only be defined by projects");
+        }
+
+        let client = self.database.get_client().await?;
+        if RelationType::get(relation_type.relation_name.clone(), &client)
+            .await?
+            .is_some()
+        {
+            bail!(
+                "Relation type {} exists already",
+                relation_type.relation_name
+            );
+        }
+        relation_type.create(&client).await?;
+        Ok(relation_type)
+    }
+
+    pub async fn get_relation_type(&self, name: &str) -> Result<RelationType> {
+        let client = self.database.get_client().await?;
+        RelationType::get(name.to_string(), &client)
+            .await?
+            .ok_or_else(|| anyhow!("Relation type not found"))
+    }
+
+    /// Deletes a custom relation type and, with cascade, all relations of the type.
+    /// Resources of deleted relations are updated in the cache.
+    pub async fn delete_relation_type(&self, request: DeleteRelationType) -> Result<RelationType> {
+        let mut client = self.database.get_client().await?;
+        let relation_type = RelationType::get(request.name.clone(), &client)
+            .await?
+            .ok_or_else(|| anyhow!("Relation type not found"))?;
+
+        let transaction = client.transaction().await?;
+        let deleted = relation_type
+            .delete_custom(request.cascade, transaction.client())
+            .await?;
+        transaction.commit().await?;
+
+        let affected = deleted
+            .iter()
+            .flat_map(|relation| [relation.origin_pid, relation.target_pid])
+            .unique()
+            .collect_vec();
+        if !affected.is_empty() {
+            for object in Object::get_objects_with_relations(&affected, &client).await? {
+                self.cache.upsert_object(&object.object.id, object);
+            }
+        }
+        Ok(relation_type)
+    }
+
+    pub async fn get_relation_types(&self, request: GetRelationTypes) -> Result<Vec<RelationType>> {
+        let client = self.database.get_client().await?;
+        RelationType::get_for_project(&request.get_project_id()?, &client).await
+    }
+
+    /// Inbound and outbound relations of a type of a resource
+    pub fn get_relations_by_type(
+        &self,
+        request: GetRelationsByType,
+    ) -> Result<Vec<InternalRelation>> {
+        let resource = self
+            .cache
+            .get_object(&request.get_resource_id()?)
+            .ok_or_else(|| anyhow!("Resource not found"))?;
+        Ok([
+            &resource.inbound,
+            &resource.inbound_belongs_to,
+            &resource.outbound,
+            &resource.outbound_belongs_to,
+        ]
+        .iter()
+        .flat_map(|relations| relations.0.iter().map(|entry| entry.value().clone()))
+        .filter(|relation| relation.relation_name == request.name)
+        .sorted_by_key(|relation| relation.id)
+        .collect())
+    }
+
+    /// Enforces the project and the limits of custom relation types after `relations`
+    /// were created with the transaction client
+    pub async fn check_custom_relations(
+        &self,
+        relations: &[InternalRelation],
+        client: &Client,
+    ) -> Result<()> {
+        let names = relations
+            .iter()
+            .map(|relation| relation.relation_name.clone())
+            .filter(|name| !BUILT_IN_RELATION_TYPES.contains(&name.as_str()))
+            .unique()
+            .collect_vec();
+        if names.is_empty() {
+            return Ok(());
+        }
+        for relation_type in RelationType::get_custom(&names, client).await? {
+            let Some(project_id) = relation_type.project_id else {
+                continue;
+            };
+            let typed = relations
+                .iter()
+                .filter(|relation| relation.relation_name == relation_type.relation_name)
+                .collect_vec();
+            for relation in &typed {
+                let in_project = |id: &DieselUlid| {
+                    *id == project_id || self.get_project_ids(id).contains(&project_id)
+                };
+                if !in_project(&relation.origin_pid) && !in_project(&relation.target_pid) {
+                    bail!(
+                        "Relation type {} is defined for another project",
+                        relation_type.relation_name
+                    );
+                }
+            }
+            let resources = typed
+                .iter()
+                .flat_map(|relation| [relation.origin_pid, relation.target_pid])
+                .unique()
+                .collect_vec();
+            relation_type.check_cardinality(&resources, client).await?;
+        }
+        Ok(())
+    }
+}
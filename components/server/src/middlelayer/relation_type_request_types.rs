@@ -0,0 +1,66 @@
+use crate::database::dsls::relation_type_dsl::RelationType;
+use anyhow::Result;
+use diesel_ulid::DieselUlid;
+use std::str::FromStr;
+
+/// Defines a custom relation type for the resources of a project.
+#[derive(Debug, Clone)]
+pub struct CreateRelationType {
+    pub project_id: String,
+    pub name: String,
+    pub directed: bool,
+    // Maximum relations of the type per origin, per resource for undirected types
+    pub max_outbound: Option<i32>,
+    // Maximum relations of the type per target
+    pub max_inbound: Option<i32>,
+}
+
+impl CreateRelationType {
+    pub fn get_project_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.project_id)?)
+    }
+
+    pub fn get_relation_type(&self) -> Result<RelationType> {
+        let relation_type = RelationType {
+            relation_name: self.name.clone(),
+            project_id: Some(self.get_project_id()?),
+            directed: self.directed,
+            max_outbound: self.max_outbound,
+            max_inbound: self.max_inbound,
+        };
+        relation_type.validate()?;
+        Ok(relation_type)
+    }
+}
+
+/// Deletes a custom relation type, relations of the type are only deleted with `cascade`.
+#[derive(Debug, Clone)]
+pub struct DeleteRelationType {
+    pub name: String,
+    pub cascade: bool,
+}
+
+/// Lists the built-in and the custom relation types of a project.
+#[derive(Debug, Clone)]
+pub struct GetRelationTypes {
+    pub project_id: String,
+}
+
+impl GetRelationTypes {
+    pub fn get_project_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.project_id)?)
+    }
+}
+
+/// Lists the inbound and outbound relations of a type of a resource.
+#[derive(Debug, Clone)]
+pub struct GetRelationsByType {
+    pub resource_id: String,
+    pub name: String,
+}
+
+impl GetRelationsByType {
+    pub fn get_resource_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.resource_id)?)
+    }
+}
@@ -3,14 +3,63 @@ use crate::database::dsls::internal_relation_dsl::{
 };
 use crate::database::dsls::object_dsl::Object;
 use crate::database::dsls::object_dsl::ObjectWithRelations;
+use crate::database::dsls::persistent_notification_dsl::NotificationReference;
+use crate::database::enums::{
+    DbPermissionLevel, NotificationReferenceType, ObjectMapping, PersistentNotificationVariant,
+};
 use crate::middlelayer::db_handler::DatabaseHandler;
 use crate::middlelayer::relations_request_types::{
     ModifyRelations, RelationsToAdd, RelationsToModify, RelationsToRemove,
 };
-use ahash::HashSet;
+use crate::utils::user_notification_utils::{notify_user, USER_NOTIFICATION_CONFIG};
+use ahash::{HashMap, HashSet};
 use anyhow::{anyhow, Result};
 use aruna_rust_api::api::notification::services::v2::EventVariant;
 use diesel_ulid::DieselUlid;
+use lazy_static::lazy_static;
+use std::error::Error;
+use std::fmt::Display;
+use tokio_postgres::Client;
+
+/// Project key-value which overrides the relation maximum for all resources of the project.
+/// Can only be changed by global admins.
+pub const MAX_RELATIONS_KEY: &str = "app.aruna-storage.org/max-relations";
+
+lazy_static! {
+    /// Maximum number of outbound relations (e.g. children) of a single resource
+    pub static ref MAX_RELATIONS: i64 = dotenvy::var("MAX_RELATIONS_PER_RESOURCE")
+        .map(|var| var.parse::<i64>().unwrap_or(100000))
+        .unwrap_or(100000);
+    /// Percentage of the relation maximum that notifies the project admins
+    pub static ref RELATION_WARNING_THRESHOLD: i64 = dotenvy::var("RELATION_WARNING_THRESHOLD")
+        .map(|var| var.parse::<i64>().unwrap_or(90).clamp(0, 100))
+        .unwrap_or(90);
+}
+
+/// Adding relations would exceed the relation maximum of a resource
+#[derive(Debug)]
+pub struct RelationLimitExceeded {
+    pub resource_id: DieselUlid,
+    pub limit: i64,
+}
+impl Display for RelationLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Resource {} exceeds the maximum of {} relations",
+            self.resource_id, self.limit
+        )
+    }
+}
+impl Error for RelationLimitExceeded {}
+
+/// Resource which crossed the warning threshold of its relation maximum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationLimitWarning {
+    pub resource_id: DieselUlid,
+    pub relations: i64,
+    pub limit: i64,
+}
 
 impl DatabaseHandler {
     pub async fn modify_relations(
@@ -31,6 +80,21 @@ impl DatabaseHandler {
 
         // Create client
         let mut client = self.database.get_client().await?;
+        let warnings = self
+            .check_relation_limits(
+                &relations_add
+                    .internal
+                    .iter()
+                    .map(|ir| ir.origin_pid)
+                    .collect::<Vec<_>>(),
+                &relations_remove
+                    .internal
+                    .iter()
+                    .map(|ir| ir.origin_pid)
+                    .collect::<Vec<_>>(),
+                &client,
+            )
+            .await?;
         // Check if BelongsTo relations are removed and at least one Version or BelongsTo relation remains
         let check_relations: Vec<InternalRelation> = relations_remove
             .internal
@@ -72,6 +136,7 @@ impl DatabaseHandler {
         }
         if !relations_add.internal.is_empty() {
             InternalRelation::batch_create(&relations_add.internal, transaction_client).await?;
+            self.check_custom_relations(&relations_add.internal, transaction_client).await?;
         }
         if !relations_remove.external.is_empty() {
             Object::remove_external_relation(
@@ -96,6 +161,7 @@ impl DatabaseHandler {
         )
         .await?;
         transaction.commit().await?;
+        self.notify_relation_limits(warnings, &client).await;
 
         // Try to emit object updated notification(s)
         let affected_ids = Vec::from_iter(affected_objects);
@@ -143,4 +209,118 @@ impl DatabaseHandler {
             request.get_relations(resource, &client).await?, // Client instead of transaction client is okay here, because only get requests are made before modifications
         ))
     }
+
+    /// Returns the relation maximum of a resource, i.e. the highest override of its projects
+    /// or the configured default.
+    pub fn get_relation_limit(&self, resource_id: &DieselUlid) -> i64 {
+        self.get_project_ids(resource_id)
+            .iter()
+            .filter_map(|id| self.cache.get_object(id))
+            .filter_map(|project| {
+                project
+                    .object
+                    .key_values
+                    .0
+                     .0
+                    .iter()
+                    .find(|kv| kv.key == MAX_RELATIONS_KEY)
+                    .and_then(|kv| kv.value.parse::<i64>().ok())
+            })
+            .max()
+            .unwrap_or(*MAX_RELATIONS)
+    }
+
+    /// Checks that the relations to add do not exceed the relation maximum of their origins.
+    /// Returns the origins which cross the warning threshold with the added relations.
+    pub async fn check_relation_limits(
+        &self,
+        added: &[DieselUlid],   // Origin of each added relation
+        removed: &[DieselUlid], // Origin of each removed relation
+        client: &Client,
+    ) -> Result<Vec<RelationLimitWarning>> {
+        let mut changes: HashMap<DieselUlid, i64> = HashMap::default();
+        for origin in added {
+            *changes.entry(*origin).or_default() += 1;
+        }
+        for origin in removed {
+            *changes.entry(*origin).or_default() -= 1;
+        }
+
+        let mut warnings = Vec::new();
+        for (resource_id, change) in changes {
+            if change <= 0 {
+                continue;
+            }
+            let existing = InternalRelation::count_outbound(&resource_id, client).await?;
+            let relations = existing + change;
+            let limit = self.get_relation_limit(&resource_id);
+            if relations > limit {
+                return Err(RelationLimitExceeded { resource_id, limit }.into());
+            }
+            let threshold = limit * *RELATION_WARNING_THRESHOLD / 100;
+            if existing < threshold && relations >= threshold {
+                warnings.push(RelationLimitWarning {
+                    resource_id,
+                    relations,
+                    limit,
+                });
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// Notifies the admins of all projects of resources approaching their relation maximum
+    pub async fn notify_relation_limits(
+        &self,
+        warnings: Vec<RelationLimitWarning>,
+        client: &Client,
+    ) {
+        for warning in warnings {
+            log::warn!(
+                "Resource {} reached {} of {} relations",
+                warning.resource_id,
+                warning.relations,
+                warning.limit
+            );
+            for project_id in self.get_project_ids(&warning.resource_id) {
+                for (user, permission) in self.cache.get_resource_users(&project_id) {
+                    if permission != DbPermissionLevel::ADMIN {
+                        continue;
+                    }
+                    if let Err(err) = notify_user(
+                        client,
+                        &USER_NOTIFICATION_CONFIG,
+                        user.id,
+                        PersistentNotificationVariant::RELATION_LIMIT_APPROACHING,
+                        format!(
+                            "Resource {} reached {} of its maximum of {} relations",
+                            warning.resource_id, warning.relations, warning.limit
+                        ),
+                        vec![NotificationReference {
+                            reference_type: NotificationReferenceType::Resource,
+                            reference_name: warning.resource_id.to_string(),
+                            reference_value: warning.resource_id.to_string(),
+                        }],
+                    )
+                    .await
+                    {
+                        log::error!("Relation limit notification failed: {}", err);
+                    }
+                }
+            }
+        }
+    }
+
+    // Projects of all hierarchies of a resource
+    pub(crate) fn get_project_ids(&self, resource_id: &DieselUlid) -> HashSet<DieselUlid> {
+        self.cache
+            .upstream_dfs_iterative(resource_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|hierarchy| match hierarchy.last() {
+                Some(ObjectMapping::PROJECT(id)) => Some(*id),
+                _ => None,
+            })
+            .collect()
+    }
 }
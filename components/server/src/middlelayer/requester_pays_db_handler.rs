@@ -0,0 +1,100 @@
+use crate::database::dsls::stats_dsl::DownloadStats;
+use crate::database::enums::{ObjectMapping, ObjectType};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::requester_pays_request_types::{
+    DownloadAttribution, RequesterPaysNotAcknowledged, REQUESTER_PAYS_KEY,
+};
+use anyhow::{anyhow, Result};
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+
+impl DatabaseHandler {
+    /// Attribution of a download of the object by the user. Users outside the projects of
+    /// requester pays objects are billed themselves and have to acknowledge it, otherwise
+    /// the download fails with `RequesterPaysNotAcknowledged`.
+    pub fn get_download_attribution(
+        &self,
+        object_id: &DieselUlid,
+        user_id: &DieselUlid,
+        acknowledged: bool,
+    ) -> Result<DownloadAttribution> {
+        let parents = self
+            .cache
+            .upstream_dfs_iterative(object_id)?
+            .into_iter()
+            .flatten()
+            .unique()
+            .collect::<Vec<_>>();
+        let project_ids = parents
+            .iter()
+            .filter_map(|parent| match parent {
+                ObjectMapping::PROJECT(project_id) => Some(*project_id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let project_id = *project_ids
+            .first()
+            .ok_or_else(|| anyhow!("Object has no project"))?;
+
+        let is_member = self.cache.get_user(user_id).is_some_and(|user| {
+            project_ids
+                .iter()
+                .any(|project_id| user.attributes.0.permissions.contains_key(project_id))
+        });
+        let requester_pays = !is_member
+            && parents
+                .iter()
+                .map(|parent| parent.into_inner())
+                .chain(std::iter::once(*object_id))
+                .any(|resource_id| {
+                    self.cache.get_object(&resource_id).is_some_and(|resource| {
+                        resource
+                            .object
+                            .key_values
+                            .0
+                             .0
+                            .iter()
+                            .any(|kv| kv.key == REQUESTER_PAYS_KEY)
+                    })
+                });
+        if requester_pays && !acknowledged {
+            return Err(RequesterPaysNotAcknowledged {
+                object_id: *object_id,
+            }
+            .into());
+        }
+        Ok(DownloadAttribution {
+            user_id: *user_id,
+            project_id,
+            requester_pays,
+        })
+    }
+
+    /// Authorizes the download of the object by the user and accounts its size to
+    /// whoever is billed for it. Downloads are accounted when their url is issued.
+    pub async fn account_download(
+        &self,
+        object_id: &DieselUlid,
+        user_id: &DieselUlid,
+        acknowledged: bool,
+    ) -> Result<DownloadAttribution> {
+        let attribution = self.get_download_attribution(object_id, user_id, acknowledged)?;
+        let size = self
+            .cache
+            .get_object(object_id)
+            .filter(|object| object.object.object_type == ObjectType::OBJECT)
+            .ok_or_else(|| anyhow!("Object not found"))?
+            .object
+            .content_len;
+        let client = self.database.get_client().await?;
+        DownloadStats::add_download(
+            &attribution.user_id,
+            &attribution.project_id,
+            attribution.requester_pays,
+            size,
+            &client,
+        )
+        .await?;
+        Ok(attribution)
+    }
+}
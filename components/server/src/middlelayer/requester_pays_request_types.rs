@@ -0,0 +1,38 @@
+use diesel_ulid::DieselUlid;
+use std::error::Error;
+use std::fmt::Display;
+
+/// Key-value of projects, collections, datasets and objects whose downloads by users outside
+/// the owning project are billed to the downloading user. Requesters have to acknowledge
+/// the charges with each download, like S3 requester pays buckets.
+pub const REQUESTER_PAYS_KEY: &str = "app.aruna-storage.org/requester-pays";
+
+/// Metadata of download requests which acknowledges requester pays charges with the value
+/// `requester`, like the `x-amz-request-payer` header of S3
+pub const REQUEST_PAYER_KEY: &str = "x-aruna-request-payer";
+
+/// Who is billed for the download of an object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadAttribution {
+    pub user_id: DieselUlid,
+    pub project_id: DieselUlid,
+    // Billed to the user instead of the project
+    pub requester_pays: bool,
+}
+
+/// Downloads of requester pays resources by users outside the owning project
+/// require the acknowledgment of the requester
+#[derive(Debug)]
+pub struct RequesterPaysNotAcknowledged {
+    pub object_id: DieselUlid,
+}
+impl Display for RequesterPaysNotAcknowledged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Object {} is requester pays, downloads have to acknowledge the charges",
+            self.object_id
+        )
+    }
+}
+impl Error for RequesterPaysNotAcknowledged {}
@@ -0,0 +1,195 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::hook_dsl::TriggerVariant;
+use crate::database::dsls::object_dsl::{
+    Algorithm, KeyValue, KeyValueVariant, Object, ObjectWithRelations,
+};
+use crate::database::dsls::persistent_notification_dsl::NotificationReference;
+use crate::database::dsls::scan_attestation_dsl::ScanAttestation;
+use crate::database::enums::{
+    NotificationReferenceType, ObjectStatus, PersistentNotificationVariant,
+};
+use crate::hooks::scan_hook::{
+    cached_verdict, ScanResponse, ScanVerdict, SCAN_TOKEN_KEY, SCAN_VERDICT_KEY,
+};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::utils::user_notification_utils::{notify_user, USER_NOTIFICATION_CONFIG};
+use anyhow::{anyhow, bail, Result};
+use aruna_rust_api::api::notification::services::v2::EventVariant;
+use diesel_ulid::DieselUlid;
+use postgres_types::Json;
+use std::time::Duration;
+use tokio_postgres::Client;
+
+impl DatabaseHandler {
+    /// Allows the scanner to download the object with `access_key` while it is scanned
+    pub async fn start_scan(
+        &self,
+        object_id: DieselUlid,
+        access_key: &str,
+    ) -> Result<ObjectWithRelations> {
+        let client = self.database.get_client().await?;
+        // Hook status key-values can not be set by users
+        Object::add_key_value(
+            &object_id,
+            &client,
+            KeyValue {
+                key: SCAN_TOKEN_KEY.to_string(),
+                value: access_key.to_string(),
+                variant: KeyValueVariant::HOOK_STATUS,
+                value_type: None,
+            },
+        )
+        .await?;
+        self.emit_scan_update(object_id, &client).await
+    }
+
+    /// Applies the verdict of the scanner. Clean objects become available and trigger
+    /// object finished hooks, infected objects are quarantined and their owner is notified.
+    pub async fn finish_scan(
+        &self,
+        object_id: DieselUlid,
+        verdict: &ScanVerdict,
+    ) -> Result<ObjectWithRelations> {
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+
+        let mut object = Object::get_for_update(&object_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Object not found"))?;
+        if object.object_status != ObjectStatus::VALIDATING {
+            bail!("Object is not being scanned");
+        }
+        object
+            .key_values
+            .0
+             .0
+            .retain(|kv| kv.key != SCAN_TOKEN_KEY && kv.key != SCAN_VERDICT_KEY);
+        object.key_values.0 .0.push(KeyValue {
+            key: SCAN_VERDICT_KEY.to_string(),
+            value: verdict.label(),
+            variant: KeyValueVariant::LABEL,
+            value_type: None,
+        });
+        object.update(transaction_client).await?;
+        Object::update_status(&object_id, verdict.object_status(), transaction_client).await?;
+        transaction.commit().await?;
+
+        let object = self.emit_scan_update(object_id, &client).await?;
+        match verdict {
+            ScanVerdict::Clean => {
+                let db_handler = DatabaseHandler {
+                    database: self.database.clone(),
+                    natsio_handler: self.natsio_handler.clone(),
+                    cache: self.cache.clone(),
+                    hook_sender: self.hook_sender.clone(),
+                };
+                let owr = object.clone();
+                tokio::spawn(async move {
+                    let call = db_handler
+                        .trigger_hooks(owr, vec![TriggerVariant::OBJECT_FINISHED], None)
+                        .await;
+                    if call.is_err() {
+                        log::error!("{:?}", call);
+                    }
+                });
+            }
+            ScanVerdict::Infected { .. } => {
+                notify_user(
+                    &client,
+                    &USER_NOTIFICATION_CONFIG,
+                    object.object.created_by,
+                    PersistentNotificationVariant::OBJECT_QUARANTINED,
+                    format!(
+                        "Object {} ({}) was quarantined after a malware scan",
+                        object.object.name, object_id
+                    ),
+                    vec![NotificationReference {
+                        reference_type: NotificationReferenceType::Resource,
+                        reference_name: object.object.name.clone(),
+                        reference_value: object_id.to_string(),
+                    }],
+                )
+                .await?;
+            }
+        }
+        Ok(object)
+    }
+
+    /// Verdict of an earlier scan of identical content, objects without sha256 are always scanned
+    pub async fn cached_scan_verdict(
+        &self,
+        object_id: DieselUlid,
+        ttl: Duration,
+    ) -> Result<Option<ScanVerdict>> {
+        let Some(hash) = self.content_hash(object_id) else {
+            return Ok(None);
+        };
+        let client = self.database.get_client().await?;
+        let Some(attestation) = ScanAttestation::get(hash, &client).await? else {
+            return Ok(None);
+        };
+        let current_version = ScanAttestation::latest_version(&client).await?;
+        Ok(cached_verdict(
+            &attestation,
+            current_version.as_deref(),
+            ttl,
+            chrono::Utc::now().naive_utc(),
+        ))
+    }
+
+    /// Remembers the verdict of the scanner for the content of the object
+    pub async fn attest_scan(&self, object_id: DieselUlid, response: &ScanResponse) -> Result<()> {
+        let Some(hash) = self.content_hash(object_id) else {
+            return Ok(());
+        };
+        let client = self.database.get_client().await?;
+        ScanAttestation {
+            hash,
+            verdict: Json(response.verdict.clone()),
+            scanner_version: response.version.clone(),
+            scanned_at: chrono::Utc::now().naive_utc(),
+        }
+        .create(&client)
+        .await
+    }
+
+    fn content_hash(&self, object_id: DieselUlid) -> Option<String> {
+        self.cache
+            .get_object(&object_id)?
+            .object
+            .hashes
+            .0
+             .0
+            .into_iter()
+            .find(|hash| hash.alg == Algorithm::SHA256)
+            .map(|hash| hash.hash.to_ascii_lowercase())
+    }
+
+    /// Updates the cached object and notifies data proxies about its new status
+    pub async fn emit_scan_update(
+        &self,
+        object_id: DieselUlid,
+        client: &Client,
+    ) -> Result<ObjectWithRelations> {
+        let object = Object::get_object_with_relations(&object_id, client).await?;
+        self.cache.upsert_object(&object_id, object.clone());
+
+        // Data proxies have to know about status changes to block or allow downloads
+        let hierarchies = object.object.fetch_object_hierarchies(client).await?;
+        if let Err(err) = self
+            .natsio_handler
+            .register_resource_event(
+                &object,
+                hierarchies,
+                EventVariant::Updated,
+                Some(&DieselUlid::generate()), // block_id for deduplication
+            )
+            .await
+        {
+            log::error!("{}", err);
+            return Err(anyhow!("Notification emission failed"));
+        }
+        Ok(object)
+    }
+}
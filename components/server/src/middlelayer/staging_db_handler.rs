@@ -0,0 +1,194 @@
+use crate::auth::permission_handler::PermissionHandler;
+use crate::database::crud::CrudDb;
+use crate::database::dsls::object_dsl::Object;
+use crate::database::dsls::staging_dsl::StagingDeadline;
+use crate::database::enums::ObjectStatus;
+use crate::middlelayer::db_handler::DatabaseHandler;
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+use diesel_ulid::DieselUlid;
+use lazy_static::lazy_static;
+use postgres_types::Json;
+use std::error::Error;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_postgres::Client;
+
+lazy_static! {
+    /// Time until an upload of a staging object gets aborted if it is not renewed
+    pub static ref STAGING_TTL: Duration = Duration::from_secs(
+        dotenvy::var("STAGING_TTL")
+            .map(|var| var.parse::<u64>().unwrap_or(86400))
+            .unwrap_or(86400)
+    );
+    /// Concurrent uploads of a user, unlimited if not set
+    pub static ref UPLOAD_LIMIT: Option<i64> = dotenvy::var("UPLOAD_LIMIT")
+        .ok()
+        .and_then(|var| var.parse::<i64>().ok());
+    /// Concurrent uploads of global admins and service accounts, UPLOAD_LIMIT if not set
+    pub static ref PRIVILEGED_UPLOAD_LIMIT: Option<i64> = dotenvy::var("PRIVILEGED_UPLOAD_LIMIT")
+        .ok()
+        .and_then(|var| var.parse::<i64>().ok())
+        .or(*UPLOAD_LIMIT);
+}
+
+/// Initializing another upload would exceed the concurrent uploads of a user
+#[derive(Debug)]
+pub struct UploadLimitExceeded {
+    pub user_id: DieselUlid,
+    pub uploads: i64,
+    pub limit: i64,
+}
+impl Display for UploadLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "User {} has {} of {} concurrent uploads in progress",
+            self.user_id, self.uploads, self.limit
+        )
+    }
+}
+impl Error for UploadLimitExceeded {}
+
+impl DatabaseHandler {
+    /// Rejects new staging objects of users which reached their limit of concurrent
+    /// uploads. Finished, aborted and deleted uploads do not count towards the limit.
+    pub async fn check_upload_limit(&self, user_id: &DieselUlid, client: &Client) -> Result<()> {
+        let privileged = self.cache.get_user(user_id).is_some_and(|user| {
+            user.attributes.0.global_admin || user.attributes.0.service_account
+        });
+        let limit = if privileged {
+            *PRIVILEGED_UPLOAD_LIMIT
+        } else {
+            *UPLOAD_LIMIT
+        };
+        match limit {
+            Some(limit) => DatabaseHandler::enforce_upload_limit(user_id, limit, client).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Fails with [`UploadLimitExceeded`] if the user has `limit` or more uploads in progress
+    pub async fn enforce_upload_limit(
+        user_id: &DieselUlid,
+        limit: i64,
+        client: &Client,
+    ) -> Result<()> {
+        let uploads = StagingDeadline::count_active(user_id, client).await?;
+        if uploads >= limit {
+            return Err(UploadLimitExceeded {
+                user_id: *user_id,
+                uploads,
+                limit,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Records the upload deadline for a newly initialized staging object.
+    pub async fn init_staging_deadline(
+        object_id: DieselUlid,
+        user_id: DieselUlid,
+        client: &Client,
+    ) -> Result<()> {
+        StagingDeadline {
+            object_id,
+            user_id,
+            deadline: Utc::now().naive_utc() + *STAGING_TTL,
+            upload_ids: Json(vec![]),
+        }
+        .create(client)
+        .await
+    }
+
+    /// Renews the upload deadline of a staging object and optionally registers
+    /// a multipart upload id which gets aborted on expiry.
+    ///
+    /// Returns false if the deadline already expired and the object has to be re-initialized.
+    pub async fn heartbeat_upload(
+        &self,
+        object_id: &DieselUlid,
+        upload_id: Option<String>,
+    ) -> Result<bool> {
+        let client = self.database.get_client().await?;
+        let now = Utc::now().naive_utc();
+
+        match StagingDeadline::get(*object_id, &client).await? {
+            Some(deadline) if deadline.is_expired(now) => Ok(false),
+            Some(_) => {
+                StagingDeadline::renew(object_id, now + *STAGING_TTL, upload_id, &client).await?;
+                Ok(true)
+            }
+            // Objects without deadline (e.g. finished or created before deadlines existed)
+            None => Ok(true),
+        }
+    }
+
+    /// Aborts all staging objects whose upload deadline expired before `now`.
+    /// Multipart uploads on the endpoints get aborted and the objects are set to ERROR.
+    ///
+    /// Returns the ids of all aborted objects.
+    pub async fn abort_expired_staging(
+        &self,
+        authorizer: Arc<PermissionHandler>,
+        now: NaiveDateTime,
+    ) -> Result<Vec<DieselUlid>> {
+        let client = self.database.get_client().await?;
+        let expired = StagingDeadline::get_expired(now, &client).await?;
+
+        let mut aborted = Vec::with_capacity(expired.len());
+        for deadline in expired {
+            // Aborting on the endpoint is best effort, the object gets unusable anyway
+            if let Err(err) = self
+                .abort_multipart_uploads(
+                    authorizer.clone(),
+                    deadline.object_id,
+                    deadline.user_id,
+                    &deadline.upload_ids.0,
+                )
+                .await
+            {
+                log::warn!(
+                    "Aborting uploads of expired object {} failed: {}",
+                    deadline.object_id,
+                    err
+                );
+            }
+
+            Object::update_status(&deadline.object_id, ObjectStatus::ERROR, &client).await?;
+            if let Some(mut owr) = self.cache.get_object(&deadline.object_id) {
+                owr.object.object_status = ObjectStatus::ERROR;
+                self.cache.upsert_object(&deadline.object_id, owr);
+            }
+            aborted.push(deadline.object_id);
+        }
+
+        Ok(aborted)
+    }
+}
+
+/// Periodically aborts staging objects with expired upload deadlines.
+pub async fn start_staging_cleanup_loop(
+    database_handler: Arc<DatabaseHandler>,
+    authorizer: Arc<PermissionHandler>,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+
+            match database_handler
+                .abort_expired_staging(authorizer.clone(), Utc::now().naive_utc())
+                .await
+            {
+                Ok(aborted) if !aborted.is_empty() => {
+                    log::info!("Aborted {} expired staging objects", aborted.len())
+                }
+                Ok(_) => {}
+                Err(err) => log::error!("Staging cleanup failed: {}", err),
+            }
+        }
+    });
+}
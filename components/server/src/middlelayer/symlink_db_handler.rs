@@ -0,0 +1,152 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::internal_relation_dsl::{
+    InternalRelation, INTERNAL_RELATION_VARIANT_SYMLINK,
+};
+use crate::database::dsls::object_dsl::{Object, ObjectWithRelations};
+use crate::database::enums::{ObjectStatus, ObjectType};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::middlelayer::symlink_request_types::{CreateSymlink, GetSymlink};
+use anyhow::{anyhow, bail, Result};
+use aruna_rust_api::api::notification::services::v2::EventVariant;
+use diesel_ulid::DieselUlid;
+use tokio_postgres::Client;
+
+impl DatabaseHandler {
+    /// Creates a symlink in the target collection, returns the updated collection.
+    /// Symlinks are inbound relations of their source and move with it to new revisions.
+    pub async fn create_symlink(&self, request: CreateSymlink) -> Result<ObjectWithRelations> {
+        let source_id = request.get_source_id()?;
+        let collection_id = request.get_collection_id()?;
+        let name = request.get_name()?;
+
+        let mut client = self.database.get_client().await?;
+        let warnings = self
+            .check_relation_limits(&[collection_id], &[], &client)
+            .await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+
+        let source = Object::get(source_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Source not found"))?;
+        if source.object_type != ObjectType::OBJECT || source.object_status == ObjectStatus::DELETED
+        {
+            bail!("Symlinks can only resolve to existing objects");
+        }
+        let collection = Object::get(collection_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Collection not found"))?;
+        if collection.object_type != ObjectType::COLLECTION
+            || collection.object_status == ObjectStatus::DELETED
+        {
+            bail!("Symlinks can only be created in existing collections");
+        }
+
+        let target_name = self
+            .get_name_normalization(&collection_id, transaction_client)
+            .await?
+            .normalize(&name);
+        if Object::get_child_by_name(&collection_id, &target_name, transaction_client)
+            .await?
+            .is_some()
+            || InternalRelation::get_symlink(&collection_id, &target_name, transaction_client)
+                .await?
+                .is_some()
+        {
+            bail!("Collection already contains {name}");
+        }
+
+        InternalRelation {
+            id: DieselUlid::generate(),
+            origin_pid: collection_id,
+            origin_type: ObjectType::COLLECTION,
+            relation_name: INTERNAL_RELATION_VARIANT_SYMLINK.to_string(),
+            target_pid: source_id,
+            target_type: ObjectType::OBJECT,
+            target_name,
+        }
+        .create(transaction_client)
+        .await?;
+        transaction.commit().await?;
+        self.notify_relation_limits(warnings, &client).await;
+
+        self.update_symlink_resources(collection_id, source_id, &client)
+            .await
+    }
+
+    /// Resolves a symlink to the latest revision of its source
+    pub async fn resolve_symlink(
+        &self,
+        request: &GetSymlink,
+    ) -> Result<(InternalRelation, ObjectWithRelations)> {
+        let client = self.database.get_client().await?;
+        let relation = self.get_symlink(request, &client).await?;
+        let source = Object::get_object_with_relations(&relation.target_pid, &client).await?;
+        if source.object.object_status == ObjectStatus::DELETED {
+            bail!("Source of symlink {} was deleted", request.name);
+        }
+        Ok((relation, source))
+    }
+
+    /// Removes a symlink, the source is not changed
+    pub async fn delete_symlink(&self, request: &GetSymlink) -> Result<ObjectWithRelations> {
+        let client = self.database.get_client().await?;
+        let relation = self.get_symlink(request, &client).await?;
+        relation.delete(&client).await?;
+        self.update_symlink_resources(relation.origin_pid, relation.target_pid, &client)
+            .await
+    }
+
+    async fn get_symlink(&self, request: &GetSymlink, client: &Client) -> Result<InternalRelation> {
+        let collection_id = request.get_collection_id()?;
+        let collection = Object::get(collection_id, client)
+            .await?
+            .ok_or_else(|| anyhow!("Collection not found"))?;
+        if collection.object_status == ObjectStatus::DELETED {
+            bail!("Collection was deleted");
+        }
+        let name = self
+            .get_name_normalization(&collection_id, client)
+            .await?
+            .normalize(&request.name);
+        InternalRelation::get_symlink(&collection_id, &name, client)
+            .await?
+            .ok_or_else(|| anyhow!("Symlink {} not found", request.name))
+    }
+
+    /// Updates the cache and emits update events for the collection and the source,
+    /// returns the updated collection
+    async fn update_symlink_resources(
+        &self,
+        collection_id: DieselUlid,
+        source_id: DieselUlid,
+        client: &Client,
+    ) -> Result<ObjectWithRelations> {
+        let updated =
+            Object::get_objects_with_relations(&vec![collection_id, source_id], client).await?;
+        for resource in &updated {
+            self.cache
+                .upsert_object(&resource.object.id, resource.clone());
+        }
+        for resource in &updated {
+            let hierarchies = resource.object.fetch_object_hierarchies(client).await?;
+            if let Err(err) = self
+                .natsio_handler
+                .register_resource_event(
+                    resource,
+                    hierarchies,
+                    EventVariant::Updated,
+                    Some(&DieselUlid::generate()), // block_id for deduplication
+                )
+                .await
+            {
+                log::error!("{}", err);
+                return Err(anyhow!("Notification emission failed"));
+            }
+        }
+        updated
+            .into_iter()
+            .find(|resource| resource.object.id == collection_id)
+            .ok_or_else(|| anyhow!("Collection not found"))
+    }
+}
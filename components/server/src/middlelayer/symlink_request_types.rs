@@ -0,0 +1,60 @@
+use crate::database::enums::ObjectType;
+use crate::utils::validation_utils::VALIDATION_RULES;
+use anyhow::Result;
+use diesel_ulid::DieselUlid;
+use lazy_static::lazy_static;
+use std::str::FromStr;
+
+/// What happens to symlinks if their source object is deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkSourceDeletion {
+    // Sources of symlinks can not be deleted
+    Error,
+    // Symlinks stay and fail to resolve
+    Tombstone,
+}
+
+lazy_static! {
+    pub static ref SYMLINK_SOURCE_DELETION: SymlinkSourceDeletion =
+        match dotenvy::var("SYMLINK_SOURCE_DELETION").as_deref() {
+            Ok("tombstone") => SymlinkSourceDeletion::Tombstone,
+            _ => SymlinkSourceDeletion::Error,
+        };
+}
+
+/// Named entry of a collection which resolves to the latest revision of an object
+/// in another collection without copying data.
+#[derive(Debug, Clone)]
+pub struct CreateSymlink {
+    pub source_id: String,
+    pub target_collection_id: String,
+    pub name: String,
+}
+
+impl CreateSymlink {
+    pub fn get_source_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.source_id)?)
+    }
+
+    pub fn get_collection_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.target_collection_id)?)
+    }
+
+    pub fn get_name(&self) -> Result<String> {
+        VALIDATION_RULES.validate_name(ObjectType::OBJECT, &self.name)?;
+        Ok(self.name.clone())
+    }
+}
+
+/// Symlink of a collection by its name, used to resolve, download and delete symlinks.
+#[derive(Debug, Clone)]
+pub struct GetSymlink {
+    pub collection_id: String,
+    pub name: String,
+}
+
+impl GetSymlink {
+    pub fn get_collection_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.collection_id)?)
+    }
+}
@@ -1,11 +1,15 @@
+use crate::database::crud::CrudDb;
 use crate::database::dsls::user_dsl::APIToken;
 use crate::database::dsls::user_dsl::User;
 use crate::middlelayer::db_handler::DatabaseHandler;
-use crate::middlelayer::token_request_types::{CreateToken, DeleteToken};
+use crate::middlelayer::token_request_types::{is_expired_storage_role, CreateToken, DeleteToken};
 use ahash::HashMap;
 use anyhow::Result;
 use aruna_rust_api::api::notification::services::v2::EventVariant;
+use chrono::{NaiveDateTime, Utc};
 use diesel_ulid::DieselUlid;
+use std::sync::Arc;
+use std::time::Duration;
 
 impl DatabaseHandler {
     pub async fn create_hook_token(
@@ -41,6 +45,18 @@ impl DatabaseHandler {
         // Return token_id and token
         Ok(token_ulid)
     }
+    /// Stores a child token which was validated against its parent by the PermissionHandler
+    pub async fn create_downscoped_token(
+        &self,
+        user_id: &DieselUlid,
+        token: APIToken,
+    ) -> Result<DieselUlid> {
+        if token.parent.is_none() {
+            return Err(anyhow::anyhow!("Downscoped token without parent"));
+        }
+        self.create_hook_token(user_id, token).await
+    }
+
     pub async fn create_token(
         &self,
         user_id: &DieselUlid,
@@ -82,11 +98,19 @@ impl DatabaseHandler {
     }
 
     pub async fn delete_token(&self, user_id: DieselUlid, request: DeleteToken) -> Result<()> {
-        let client = self.database.get_client().await?;
+        let mut client = self.database.get_client().await?;
         let token_id = request.get_token_id()?;
+        let transaction = client.transaction().await?;
+        let client = transaction.client();
 
-        // Remove token from user attributes in database
-        let user = User::remove_user_token(&client, &user_id, &token_id).await?;
+        // Remove token and all tokens downscoped from it from user attributes in database
+        let user = User::get(user_id, client)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("User not found"))?;
+        let user =
+            User::remove_user_tokens(client, &user_id, &user.get_token_with_children(&token_id))
+                .await?;
+        transaction.commit().await?;
 
         // Update user in cache
         self.cache.update_user(&user.id, user.clone());
@@ -129,4 +153,62 @@ impl DatabaseHandler {
 
         Ok(())
     }
+
+    /// Deletes the tokens of storage roles which expired at `now` together with their
+    /// children, the data proxies drop the credentials with the user update.
+    /// Returns the number of deleted tokens.
+    pub async fn expire_storage_roles(&self, now: NaiveDateTime) -> Result<usize> {
+        let mut deleted = 0;
+        for (user_id, token_ids) in self
+            .cache
+            .find_user_tokens(|token| is_expired_storage_role(token, now))
+        {
+            let mut client = self.database.get_client().await?;
+            let transaction = client.transaction().await?;
+            let client = transaction.client();
+            let Some(user) = User::get(user_id, client).await? else {
+                continue;
+            };
+            let token_ids = token_ids
+                .iter()
+                .flat_map(|token_id| user.get_token_with_children(token_id))
+                .collect::<Vec<_>>();
+            let user = User::remove_user_tokens(client, &user_id, &token_ids).await?;
+            transaction.commit().await?;
+            self.cache.update_user(&user.id, user.clone());
+            deleted += token_ids.len();
+
+            if let Err(err) = self
+                .natsio_handler
+                .register_user_event(&user, EventVariant::Updated)
+                .await
+            {
+                log::error!("Storage role expiry notification failed: {}", err);
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// Periodically deletes expired storage role tokens
+pub async fn start_storage_role_cleanup_loop(
+    database_handler: Arc<DatabaseHandler>,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+
+            match database_handler
+                .expire_storage_roles(Utc::now().naive_utc())
+                .await
+            {
+                Ok(deleted) if deleted > 0 => {
+                    log::info!("Deleted {} expired storage role tokens", deleted)
+                }
+                Ok(_) => {}
+                Err(err) => log::error!("Storage role cleanup failed: {}", err),
+            }
+        }
+    });
 }
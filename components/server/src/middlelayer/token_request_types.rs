@@ -1,21 +1,128 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use aruna_rust_api::api::storage::models::v2::permission::ResourceId;
+use aruna_rust_api::api::storage::models::v2::{Permission, PermissionLevel};
 use aruna_rust_api::api::storage::services::v2::{
     CreateApiTokenRequest, DeleteApiTokenRequest, GetApiTokenRequest,
 };
-use chrono::DateTime;
+use base64::engine::general_purpose;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime};
 use diesel_ulid::DieselUlid;
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use sha2::Sha256;
 use std::str::FromStr;
+use std::time::Duration;
 
 use crate::database::{
     dsls::user_dsl::APIToken,
     enums::{DbPermissionLevel, ObjectMapping},
 };
 
+type HmacSha256 = Hmac<Sha256>;
+
+/// Cookie of browser sessions which authorizes downloads from the data proxy
+pub const SIGNED_COOKIE_NAME: &str = "aruna-session";
+
+lazy_static! {
+    /// Longest lifetime of storage role credentials
+    pub static ref STORAGE_ROLE_MAX_TTL: Duration = Duration::from_secs(
+        dotenvy::var("STORAGE_ROLE_MAX_TTL")
+            .map(|var| var.parse::<u64>().unwrap_or(43200))
+            .unwrap_or(43200)
+    );
+    /// Time between runs of the sweeper which deletes expired storage role tokens
+    pub static ref STORAGE_ROLE_CLEANUP_INTERVAL: Duration = Duration::from_secs(
+        dotenvy::var("STORAGE_ROLE_CLEANUP_INTERVAL")
+            .map(|var| var.parse::<u64>().unwrap_or(60))
+            .unwrap_or(60)
+    );
+}
+
 #[derive(Clone)]
 pub struct CreateToken(pub CreateApiTokenRequest);
 pub struct DeleteToken(pub DeleteApiTokenRequest);
 pub struct GetToken(pub GetApiTokenRequest);
 
+/// Issues a child token with a subset of the permissions of `parent_token`.
+#[derive(Clone)]
+pub struct DownscopeToken {
+    // Secret of the parent token, which also authenticates the request
+    pub parent_token: String,
+    pub name: String,
+    // At most one scope, none keeps the scope of the parent
+    pub requested_scopes: Vec<Permission>,
+    // Lifetime in seconds, capped by the expiry of the parent
+    pub ttl: Option<u64>,
+}
+
+/// Mints short-lived S3 credentials restricted to a single resource, e.g. to sync a
+/// collection with standard S3 tools.
+#[derive(Debug, Clone)]
+pub struct AssumeStorageRole {
+    pub resource_scope: Permission,
+    // Lifetime in seconds, capped by STORAGE_ROLE_MAX_TTL and the expiry of the calling token
+    pub ttl: u64,
+    // Fullsync endpoint of the resource if unspecified
+    pub endpoint_id: Option<String>,
+}
+
+/// Temporary credentials of an assumed storage role. The access key is the id of the
+/// token behind the credentials, deleting the token revokes them.
+#[derive(Clone)]
+pub struct StorageRoleCredentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: String,
+    pub s3_endpoint_url: String,
+    // UNIX timestamp
+    pub expires_at: i64,
+}
+
+// Secrets must not be logged
+impl std::fmt::Debug for StorageRoleCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StorageRoleCredentials")
+            .field("access_key", &self.access_key)
+            .field("s3_endpoint_url", &self.s3_endpoint_url)
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Issues a signed cookie with read access below a resource for browser sessions.
+#[derive(Debug, Clone)]
+pub struct CreateSignedCookie {
+    pub resource_id: ResourceId,
+    // Lifetime in seconds, capped like the lifetime of storage roles
+    pub ttl: u64,
+    // Fullsync endpoint of the resource if unspecified
+    pub endpoint_id: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct SignedCookie {
+    pub name: String,
+    pub value: String,
+    // Access key of the storage role behind the cookie, deleting its token revokes the cookie
+    pub session_id: String,
+    pub s3_endpoint_url: String,
+    // UNIX timestamp
+    pub expires_at: i64,
+}
+
+// The cookie value is a secret and must not be logged
+impl std::fmt::Debug for SignedCookie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SignedCookie")
+            .field("name", &self.name)
+            .field("session_id", &self.session_id)
+            .field("s3_endpoint_url", &self.s3_endpoint_url)
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
 impl CreateToken {
     pub fn build_token(&self, pubkey_serial: i32) -> Result<APIToken> {
         let (resource_id, user_right) = if let Some(perm) = &self.0.permission {
@@ -47,10 +154,173 @@ impl CreateToken {
             },
             object_id: resource_id,
             user_rights: user_right,
+            parent: None,
+            session_scoped: false,
         })
     }
 }
 
+// The parent token is a secret and must not be logged
+impl std::fmt::Debug for DownscopeToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownscopeToken")
+            .field("name", &self.name)
+            .field("requested_scopes", &self.requested_scopes)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DownscopeToken {
+    pub fn get_requested_scope(
+        &self,
+    ) -> Result<Option<(ObjectMapping<DieselUlid>, DbPermissionLevel)>> {
+        let perm = match self.requested_scopes.as_slice() {
+            [] => return Ok(None),
+            [perm] => perm,
+            _ => bail!("Tokens can only be scoped to a single resource"),
+        };
+        let Some(resource_id) = &perm.resource_id else {
+            bail!("Missing resource id")
+        };
+        Ok(Some((
+            ObjectMapping::try_from(resource_id.clone())?,
+            DbPermissionLevel::try_from(perm.permission_level)?,
+        )))
+    }
+
+    /// Builds the child token of `parent` with an already intersected scope.
+    /// The expiry is never later than the expiry of the parent.
+    pub fn build_token(
+        &self,
+        pubkey_serial: i32,
+        parent_id: DieselUlid,
+        parent: &APIToken,
+        scope: Option<(ObjectMapping<DieselUlid>, DbPermissionLevel)>,
+    ) -> Result<APIToken> {
+        let now = chrono::Utc::now().naive_utc();
+        if parent.expires_at <= now {
+            bail!("Parent token is expired")
+        }
+        let expires_at = match self.ttl {
+            Some(ttl) => {
+                let ttl = chrono::Duration::try_seconds(i64::try_from(ttl)?)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid ttl"))?;
+                now.checked_add_signed(ttl)
+                    .map_or(parent.expires_at, |expiry| expiry.min(parent.expires_at))
+            }
+            None => parent.expires_at,
+        };
+        let (object_id, user_rights) = match scope {
+            Some((object_id, user_rights)) => (Some(object_id), user_rights),
+            None => (parent.object_id, parent.user_rights),
+        };
+
+        Ok(APIToken {
+            pub_key: pubkey_serial,
+            name: self.name.clone(),
+            created_at: now,
+            expires_at,
+            object_id,
+            user_rights,
+            parent: Some(parent_id),
+            // Downscoping keeps the session requirement of the parent
+            session_scoped: parent.session_scoped,
+        })
+    }
+}
+
+impl AssumeStorageRole {
+    pub fn get_scope(&self) -> Result<(ObjectMapping<DieselUlid>, DbPermissionLevel)> {
+        let Some(resource_id) = &self.resource_scope.resource_id else {
+            bail!("Missing resource id")
+        };
+        let level = DbPermissionLevel::try_from(self.resource_scope.permission_level)?;
+        if level <= DbPermissionLevel::NONE {
+            bail!("Storage roles need at least read permissions")
+        }
+        Ok((ObjectMapping::try_from(resource_id.clone())?, level))
+    }
+
+    /// Builds the token behind the credentials. Roles assumed with an API token are its
+    /// children, they expire no later than the parent and are revoked with it.
+    pub fn build_token(
+        &self,
+        pubkey_serial: i32,
+        parent: Option<(DieselUlid, &APIToken)>,
+    ) -> Result<APIToken> {
+        let (object_id, user_rights) = self.get_scope()?;
+        if self.ttl == 0 {
+            bail!("Storage roles need a ttl")
+        }
+        let now = chrono::Utc::now().naive_utc();
+        let ttl =
+            chrono::Duration::from_std(Duration::from_secs(self.ttl).min(*STORAGE_ROLE_MAX_TTL))?;
+        let mut expires_at = now + ttl;
+        if let Some((_, parent)) = parent {
+            if parent.expires_at <= now {
+                bail!("Parent token is expired")
+            }
+            expires_at = expires_at.min(parent.expires_at);
+        }
+
+        Ok(APIToken {
+            pub_key: pubkey_serial,
+            name: format!("storage-role:{object_id}"),
+            created_at: now,
+            expires_at,
+            object_id: Some(object_id),
+            user_rights,
+            parent: parent.map(|(parent_id, _)| parent_id),
+            session_scoped: true,
+        })
+    }
+}
+
+impl CreateSignedCookie {
+    pub fn get_resource_id(&self) -> Result<DieselUlid> {
+        Ok(ObjectMapping::try_from(self.resource_id.clone())?.into_inner())
+    }
+
+    /// Cookie sessions are read-only storage roles
+    pub fn as_storage_role(&self) -> AssumeStorageRole {
+        AssumeStorageRole {
+            resource_scope: Permission {
+                permission_level: PermissionLevel::Read as i32,
+                resource_id: Some(self.resource_id.clone()),
+            },
+            ttl: self.ttl,
+            endpoint_id: self.endpoint_id.clone(),
+        }
+    }
+}
+
+/// Tokens behind storage role credentials are only valid until their expiry
+pub fn is_expired_storage_role(token: &APIToken, now: NaiveDateTime) -> bool {
+    token.session_scoped && token.expires_at <= now
+}
+
+/// Creates the value of a signed cookie in the format `<payload>.<signature>`.
+///
+/// The payload is the url-safe base64 encoded `<access_key>:<resource_id>:<expires_at>`, the
+/// signature the url-safe base64 encoded HMAC-SHA256 of the decoded payload with the secret
+/// key of the storage role behind the cookie.
+pub fn sign_cookie(
+    secret_key: &str,
+    access_key: &str,
+    resource_id: &DieselUlid,
+    expires_at: i64,
+) -> Result<String> {
+    let payload = format!("{access_key}:{resource_id}:{expires_at}");
+    let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes())?;
+    mac.update(payload.as_bytes());
+    Ok(format!(
+        "{}.{}",
+        general_purpose::URL_SAFE_NO_PAD.encode(payload),
+        general_purpose::URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+    ))
+}
+
 impl DeleteToken {
     pub fn get_token_id(&self) -> Result<DieselUlid> {
         Ok(DieselUlid::from_str(&self.0.token_id)?)
@@ -1,18 +1,27 @@
 use super::update_request_types::{
-    LicenseUpdate, SetHashes, UpdateAuthor, UpdateObject, UpdateTitle,
+    LicenseUpdate, SetHashes, UpdateAuthor, UpdateObject, UpdateProjectDefaultEndpoint, UpdateTitle,
 };
 use crate::database::crud::CrudDb;
+use crate::database::dsls::endpoint_dsl::Endpoint;
 use crate::database::dsls::hook_dsl::TriggerVariant;
 use crate::database::dsls::internal_relation_dsl::{
     InternalRelation, INTERNAL_RELATION_VARIANT_VERSION,
 };
 use crate::database::dsls::license_dsl::ALL_RIGHTS_RESERVED;
-use crate::database::dsls::object_dsl::{KeyValue, KeyValueVariant, Object, ObjectWithRelations};
-use crate::database::enums::ObjectStatus;
+use crate::database::dsls::object_dsl::{
+    Algorithm, Hashes, KeyValue, KeyValueVariant, KeyValues, Object, ObjectWithRelations,
+};
+use crate::database::dsls::staging_dsl::StagingDeadline;
+use crate::database::enums::{ObjectMapping, ObjectStatus, ObjectType, ReplicationType};
+use crate::hooks::hook_handler::HookMessage;
+use crate::hooks::{scan_hook, validation_hook};
 use crate::middlelayer::db_handler::DatabaseHandler;
 use crate::middlelayer::update_request_types::{
     DataClassUpdate, DescriptionUpdate, KeyValueUpdate, NameUpdate,
 };
+use crate::utils::cache_utils::check_key_value_types;
+use crate::utils::request_id_utils::current_request_id;
+use crate::utils::user_notification_utils::{check_project_quota, USER_NOTIFICATION_CONFIG};
 use anyhow::{anyhow, Result};
 use aruna_rust_api::api::notification::services::v2::EventVariant;
 use aruna_rust_api::api::storage::services::v2::{FinishObjectStagingRequest, UpdateObjectRequest};
@@ -20,8 +29,20 @@ use deadpool_postgres::GenericClient;
 use diesel_ulid::DieselUlid;
 use itertools::Itertools;
 use postgres_types::Json;
+use std::error::Error;
+use std::fmt::Display;
 use std::str::FromStr;
 
+/// An object was already finished with differing hashes or content length
+#[derive(Debug)]
+pub struct FinishConflict;
+impl Display for FinishConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Object already finished with different content")
+    }
+}
+impl Error for FinishConflict {}
+
 impl DatabaseHandler {
     pub async fn update_dataclass(&self, request: DataClassUpdate) -> Result<ObjectWithRelations> {
         // Extract parameter from request
@@ -115,6 +136,7 @@ impl DatabaseHandler {
         let mut client = self.database.get_client().await?;
         let transaction = client.transaction().await?;
         let transaction_client = transaction.client();
+        request.validate()?;
         let description = request.get_description();
         let id = request.get_id()?;
         Object::update_description(id, description, transaction_client).await?;
@@ -161,6 +183,7 @@ impl DatabaseHandler {
             ));
         }
         if !add_key_values.0.is_empty() {
+            check_key_value_types(&self.cache, Some(&id), &add_key_values.0)?;
             for kv in add_key_values.0 {
                 match kv.variant {
                     KeyValueVariant::HOOK => {
@@ -241,6 +264,60 @@ impl DatabaseHandler {
         }
     }
 
+    /// Sets the endpoint new objects of a project are uploaded to.
+    /// The endpoint has to be one of the full sync endpoints of the project.
+    pub async fn update_default_endpoint(
+        &self,
+        request: UpdateProjectDefaultEndpoint,
+    ) -> Result<ObjectWithRelations> {
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+
+        let id = request.get_id()?;
+        let endpoint_id = request.get_endpoint_id()?;
+        let project = Object::get_for_update(&id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Project not found"))?;
+        if project.object_type != ObjectType::PROJECT {
+            return Err(anyhow!("Default endpoints can only be set for projects"));
+        }
+        if let Some(endpoint_id) = endpoint_id {
+            Endpoint::get(endpoint_id, transaction_client)
+                .await?
+                .ok_or_else(|| anyhow!("Endpoint does not exist"))?;
+            let full_sync = project
+                .endpoints
+                .0
+                .get(&endpoint_id)
+                .is_some_and(|info| matches!(info.replication, ReplicationType::FullSync));
+            if !full_sync {
+                return Err(anyhow!(
+                    "Endpoint is not a full sync endpoint of the project"
+                ));
+            }
+        }
+        Object::set_default_endpoint(&id, endpoint_id, transaction_client).await?;
+        transaction.commit().await?;
+
+        let project = Object::get_object_with_relations(&id, &client).await?;
+        let hierarchies = project.object.fetch_object_hierarchies(&client).await?;
+        if let Err(err) = self
+            .natsio_handler
+            .register_resource_event(
+                &project,
+                hierarchies,
+                EventVariant::Updated,
+                Some(&DieselUlid::generate()), // block_id for deduplication
+            )
+            .await
+        {
+            log::error!("{}", err);
+            return Err(anyhow::anyhow!("Notification emission failed"));
+        }
+        Ok(project)
+    }
+
     pub async fn update_license(&self, request: LicenseUpdate) -> Result<ObjectWithRelations> {
         let mut client = self.database.get_client().await?;
         let transaction = client.transaction().await?;
@@ -291,9 +368,21 @@ impl DatabaseHandler {
     )> {
         let mut client = self.database.get_client().await?;
         let req = UpdateObject(request.clone());
+        req.validate()?;
         let id = req.get_id()?;
+        self.check_manifest_mutation(&id, &client).await?;
         let owr = Object::get_object_with_relations(&id, &client).await?;
         let old = owr.object.clone();
+        check_key_value_types(
+            &self.cache,
+            Some(&id),
+            &KeyValues::try_from(&request.add_key_values)?.0,
+        )?;
+        // Objects are only validated against the metadata schema if their labels change
+        if !request.add_key_values.is_empty() || !request.remove_key_values.is_empty() {
+            self.check_metadata_schemas(&id, &req.get_all_kvs(old.clone())?.0, &client)
+                .await?;
+        }
         let transaction = client.transaction().await?;
         let transaction_client = transaction.client();
 
@@ -353,9 +442,18 @@ impl DatabaseHandler {
                 data_license,
             };
             create_object.create(transaction_client).await?;
+            if create_object.object_status == ObjectStatus::INITIALIZING {
+                self.check_upload_limit(&user_id, transaction_client)
+                    .await?;
+                DatabaseHandler::init_staging_deadline(id, user_id, transaction_client).await?;
+            }
 
             // Clone all relations of old object with new object id
-            let relations = UpdateObject::get_all_relations(owr.clone(), create_object.clone());
+            let normalization = self
+                .get_name_normalization(&old.id, transaction_client)
+                .await?;
+            let relations =
+                UpdateObject::get_all_relations(owr.clone(), create_object.clone(), normalization);
             let (mut new, (delete, mut affected)): (
                 Vec<InternalRelation>,
                 (Vec<DieselUlid>, Vec<DieselUlid>),
@@ -386,6 +484,10 @@ impl DatabaseHandler {
                     p.clone(),
                     create_object.name.to_string(),
                 )?;
+                relation.target_name = self
+                    .get_name_normalization(&relation.origin_pid, transaction_client)
+                    .await?
+                    .normalize(&relation.target_name);
                 relation.create(transaction_client).await?;
                 let changed = match p {
                     aruna_rust_api::api::storage::services::v2::update_object_request::Parent::ProjectId(id) => DieselUlid::from_str(&id)?,
@@ -430,6 +532,10 @@ impl DatabaseHandler {
                     p.clone(),
                     update_object.name.to_string(),
                 )?;
+                relation.target_name = self
+                    .get_name_normalization(&relation.origin_pid, transaction_client)
+                    .await?
+                    .normalize(&relation.target_name);
                 relation.create(transaction_client).await?;
                 let p_id = match p {
                     aruna_rust_api::api::storage::services::v2::update_object_request::Parent::ProjectId(id) => DieselUlid::from_str(&id)?,
@@ -583,16 +689,53 @@ impl DatabaseHandler {
         Ok((owr, is_new))
     }
 
+    /// Finishes a staging object. Concurrent finishes of the same object are serialized,
+    /// only the first one finalizes the object and the bool is true. Later finishes return the
+    /// already finished object or a FinishConflict if they differ in content length or in a
+    /// hash of the same algorithm.
+    /// Objects of projects with enabled scanning are not available until the scanner approves them,
+    /// objects with content validation rules in their collections not until they are validated.
     pub async fn finish_object(
         &self,
         request: FinishObjectStagingRequest,
         dataproxy_id: Option<DieselUlid>,
-    ) -> Result<ObjectWithRelations> {
+    ) -> Result<(ObjectWithRelations, bool)> {
         let mut client = self.database.get_client().await?;
         let id = DieselUlid::from_str(&request.object_id)?;
-        let object = Object::get(id, &client)
+        let hashes: Option<Hashes> = if request.hashes.is_empty() {
+            None
+        } else {
+            Some(request.hashes.try_into()?)
+        };
+        let content_len = request.content_len;
+
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+        // Concurrent finishes wait here until this transaction is committed
+        let object = Object::get_for_update(&id, transaction_client)
             .await?
             .ok_or_else(|| anyhow!("Object not found"))?;
+        // Objects in scan or quarantine were already finished
+        if matches!(
+            object.object_status,
+            ObjectStatus::AVAILABLE | ObjectStatus::VALIDATING | ObjectStatus::UNAVAILABLE
+        ) {
+            transaction.commit().await?;
+            // Only hashes of algorithms known on both sides can conflict
+            let conflicting_hashes = hashes.as_ref().is_some_and(|hashes| {
+                hashes.0.iter().any(|hash| {
+                    object.hashes.0 .0.iter().any(|stored| {
+                        stored.alg == hash.alg && !stored.hash.eq_ignore_ascii_case(&hash.hash)
+                    })
+                })
+            });
+            if object.content_len != content_len || conflicting_hashes {
+                return Err(anyhow!(FinishConflict));
+            }
+            let object = Object::get_object_with_relations(&id, &client).await?;
+            return Ok((object, false));
+        }
+
         let (endpoint_id, endpoint_info) = if let Some(id) = dataproxy_id {
             let temp = object
                 .endpoints
@@ -604,22 +747,22 @@ impl DatabaseHandler {
             return Err(anyhow!("Could not retrieve endpoint info"));
         };
 
-        let transaction = client.transaction().await?;
-        let transaction_client = transaction.client();
-        let hashes = if request.hashes.is_empty() {
-            None
+        check_project_quota(&USER_NOTIFICATION_CONFIG, &self.cache, &id, content_len)?;
+        self.check_metadata_schemas(&id, &object.key_values.0 .0, transaction_client)
+            .await?;
+        // Uploads with an expected Content-MD5 have to be finished with the same hash
+        let hashes = verify_expected_md5(&object.hashes.0, hashes)?;
+
+        let scan_project = scan_hook::scan_project(&self.cache, &id)?;
+        // Content is validated before it is scanned
+        let validate = !validation_hook::validation_rules(&self.cache, &object)?.is_empty();
+        let status = if scan_project.is_some() || validate {
+            ObjectStatus::VALIDATING
         } else {
-            Some(request.hashes.try_into()?)
+            ObjectStatus::AVAILABLE
         };
-        let content_len = request.content_len;
-        Object::finish_object_staging(
-            &id,
-            transaction_client,
-            hashes,
-            content_len,
-            ObjectStatus::AVAILABLE,
-        )
-        .await?;
+        Object::finish_object_staging(&id, transaction_client, hashes, content_len, status).await?;
+        StagingDeadline::remove(&id, transaction_client).await?;
         Object::update_endpoints(
             endpoint_id,
             crate::database::dsls::object_dsl::EndpointInfo {
@@ -635,21 +778,63 @@ impl DatabaseHandler {
         transaction.commit().await?;
 
         let object = Object::get_object_with_relations(&id, &client).await?;
-        let db_handler = DatabaseHandler {
-            database: self.database.clone(),
-            natsio_handler: self.natsio_handler.clone(),
-            cache: self.cache.clone(),
-            hook_sender: self.hook_sender.clone(),
-        };
-        let owr = object.clone();
-        tokio::spawn(async move {
-            let call = db_handler
-                .trigger_hooks(owr, vec![TriggerVariant::OBJECT_FINISHED], None)
-                .await;
-            if call.is_err() {
-                log::error!("{:?}", call);
-            }
-        });
+        if validate {
+            // Object finished hooks are triggered after a successful validation
+            let project_id = self
+                .cache
+                .upstream_dfs_iterative(&id)?
+                .into_iter()
+                .flatten()
+                .find_map(|parent| match parent {
+                    ObjectMapping::PROJECT(project_id) => Some(project_id),
+                    _ => None,
+                })
+                .ok_or_else(|| anyhow!("Project not found"))?;
+            let owner = self
+                .cache
+                .get_object(&project_id)
+                .ok_or_else(|| anyhow!("Project not found"))?
+                .object
+                .created_by;
+            self.enqueue_hook(HookMessage {
+                hook: validation_hook::validation_hook(project_id, owner)?,
+                object: object.clone(),
+                user_id: owner,
+                request_id: current_request_id(),
+            })
+            .await?;
+        } else if let Some(project_id) = scan_project {
+            // Object finished hooks are triggered after a clean scan
+            let owner = self
+                .cache
+                .get_object(&project_id)
+                .ok_or_else(|| anyhow!("Project not found"))?
+                .object
+                .created_by;
+            self.enqueue_hook(HookMessage {
+                hook: scan_hook::scan_hook(project_id, owner)?,
+                object: object.clone(),
+                user_id: owner,
+                request_id: current_request_id(),
+            })
+            .await?;
+        } else {
+            let db_handler = DatabaseHandler {
+                database: self.database.clone(),
+                natsio_handler: self.natsio_handler.clone(),
+                cache: self.cache.clone(),
+                hook_sender: self.hook_sender.clone(),
+            };
+            let owr = object.clone();
+            tokio::spawn(async move {
+                let call = db_handler
+                    .trigger_hooks(owr, vec![TriggerVariant::OBJECT_FINISHED], None)
+                    .await;
+                if call.is_err() {
+                    log::error!("{:?}", call);
+                }
+            });
+        }
 
         // Try to emit object updated notification(s)
         let hierarchies = object.object.fetch_object_hierarchies(&client).await?;
@@ -669,13 +854,14 @@ impl DatabaseHandler {
             Err(anyhow::anyhow!("Notification emission failed"))
         } else {
             //transaction.commit().await?;
-            Ok(object)
+            Ok((object, true))
         }
     }
     pub async fn update_title(&self, request: UpdateTitle) -> Result<ObjectWithRelations> {
         // Init
         let id = request.get_id()?;
         let mut client = self.database.get_client().await?;
+        self.check_manifest_mutation(&id, &client).await?;
         let transaction = client.transaction().await?;
         let transaction_client = transaction.client();
 
@@ -774,3 +960,24 @@ impl DatabaseHandler {
         }
     }
 }
+
+/// Checks the finishing hashes against an md5 hash recorded as expected for the upload.
+/// The expected hash is kept if the finishing hashes do not contain an md5 hash.
+fn verify_expected_md5(expected: &Hashes, hashes: Option<Hashes>) -> Result<Option<Hashes>> {
+    let Some(expected_md5) = expected.0.iter().find(|hash| hash.alg == Algorithm::MD5) else {
+        return Ok(hashes);
+    };
+    let Some(mut hashes) = hashes else {
+        return Ok(None);
+    };
+    match hashes.0.iter().find(|hash| hash.alg == Algorithm::MD5) {
+        Some(md5) if !md5.hash.eq_ignore_ascii_case(&expected_md5.hash) => {
+            Err(anyhow!("MD5 does not match the expected Content-MD5"))
+        }
+        Some(_) => Ok(Some(hashes)),
+        None => {
+            hashes.0.push(expected_md5.clone());
+            Ok(Some(hashes))
+        }
+    }
+}
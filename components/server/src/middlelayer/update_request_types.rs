@@ -1,6 +1,6 @@
 use crate::database::crud::CrudDb;
 use crate::database::dsls::internal_relation_dsl::{
-    InternalRelation, INTERNAL_RELATION_VARIANT_BELONGS_TO,
+    InternalRelation, INTERNAL_RELATION_VARIANT_BELONGS_TO, INTERNAL_RELATION_VARIANT_SYMLINK,
 };
 use crate::database::dsls::license_dsl::License;
 use crate::database::dsls::object_dsl::{
@@ -8,6 +8,8 @@ use crate::database::dsls::object_dsl::{
     ObjectWithRelations,
 };
 use crate::database::enums::{DataClass, ObjectType, ReplicationStatus};
+use crate::utils::name_utils::NameNormalization;
+use crate::utils::validation_utils::VALIDATION_RULES;
 use ahash::RandomState;
 use anyhow::{anyhow, Result};
 use aruna_rust_api::api::storage::services::v2::update_object_request::Parent as UpdateParent;
@@ -28,8 +30,6 @@ use itertools::Itertools;
 use std::str::FromStr;
 use tokio_postgres::Client;
 
-use super::create_request_types::{PROJECT_SCHEMA, S3_KEY_SCHEMA};
-
 pub struct UpdateObject(pub UpdateObjectRequest);
 
 pub enum DataClassUpdate {
@@ -76,6 +76,13 @@ pub enum UpdateAuthor {
 
 pub struct SetHashes(pub SetObjectHashesRequest);
 
+/// Sets the endpoint new objects of a project are uploaded to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateProjectDefaultEndpoint {
+    pub project_id: String,
+    pub endpoint_id: Option<String>, // Unsets the default endpoint if None
+}
+
 impl DataClassUpdate {
     pub fn get_dataclass(&self) -> Result<DataClass> {
         let class = match self {
@@ -85,6 +92,13 @@ impl DataClassUpdate {
         };
         Ok(class)
     }
+    pub fn get_raw_dataclass(&self) -> i32 {
+        match self {
+            DataClassUpdate::Project(req) => req.data_class,
+            DataClassUpdate::Collection(req) => req.data_class,
+            DataClassUpdate::Dataset(req) => req.data_class,
+        }
+    }
     pub fn get_id(&self) -> Result<DieselUlid> {
         let id = match self {
             DataClassUpdate::Project(req) => DieselUlid::from_str(&req.project_id)?,
@@ -97,32 +111,13 @@ impl DataClassUpdate {
 
 impl NameUpdate {
     pub fn get_name(&self) -> Result<String> {
-        match self {
-            NameUpdate::Project(req) => {
-                let name = req.name.to_string();
-                if !PROJECT_SCHEMA.is_match(&name) {
-                    Err(anyhow!("Invalid project name"))
-                } else {
-                    Ok(name)
-                }
-            }
-            NameUpdate::Collection(req) => {
-                let name = req.name.to_string();
-                if !S3_KEY_SCHEMA.is_match(&name) {
-                    Err(anyhow!("Invalid collection name"))
-                } else {
-                    Ok(name)
-                }
-            }
-            NameUpdate::Dataset(req) => {
-                let name = req.name.to_string();
-                if !S3_KEY_SCHEMA.is_match(&name) {
-                    Err(anyhow!("Invalid dataset name"))
-                } else {
-                    Ok(name)
-                }
-            }
-        }
+        let (name, resource) = match self {
+            NameUpdate::Project(req) => (req.name.to_string(), ObjectType::PROJECT),
+            NameUpdate::Collection(req) => (req.name.to_string(), ObjectType::COLLECTION),
+            NameUpdate::Dataset(req) => (req.name.to_string(), ObjectType::DATASET),
+        };
+        VALIDATION_RULES.validate_name(resource, &name)?;
+        Ok(name)
     }
     pub fn get_id(&self) -> Result<DieselUlid> {
         let id = match self {
@@ -142,6 +137,10 @@ impl DescriptionUpdate {
             DescriptionUpdate::Dataset(req) => req.description.to_string(),
         }
     }
+    pub fn validate(&self) -> Result<()> {
+        VALIDATION_RULES.validate_description(&self.get_description())?;
+        Ok(())
+    }
     pub fn get_id(&self) -> Result<DieselUlid> {
         let id = match self {
             DescriptionUpdate::Project(req) => DieselUlid::from_str(&req.project_id)?,
@@ -161,6 +160,15 @@ impl KeyValueUpdate {
         };
         Ok((add.try_into()?, rm.try_into()?))
     }
+    /// Checks if the update adds or removes a key-value with the provided key
+    pub fn contains_key(&self, key: &str) -> bool {
+        let (add, rm) = match self {
+            KeyValueUpdate::Project(req) => (&req.add_key_values, &req.remove_key_values),
+            KeyValueUpdate::Collection(req) => (&req.add_key_values, &req.remove_key_values),
+            KeyValueUpdate::Dataset(req) => (&req.add_key_values, &req.remove_key_values),
+        };
+        add.iter().chain(rm.iter()).any(|kv| kv.key == key)
+    }
     pub fn get_id(&self) -> Result<DieselUlid> {
         let id = match self {
             KeyValueUpdate::Project(req) => DieselUlid::from_str(&req.project_id)?,
@@ -263,6 +271,16 @@ impl UpdateObject {
     pub fn get_id(&self) -> Result<DieselUlid> {
         Ok(DieselUlid::from_str(&self.0.object_id)?)
     }
+    /// Checks the updated name and description against the configured validation rules
+    pub fn validate(&self) -> Result<()> {
+        if let Some(name) = &self.0.name {
+            VALIDATION_RULES.validate_name(ObjectType::OBJECT, name)?;
+        }
+        if let Some(description) = &self.0.description {
+            VALIDATION_RULES.validate_description(description)?;
+        }
+        Ok(())
+    }
     pub fn get_description(&self, old: Object) -> String {
         match self.0.description.clone() {
             Some(d) => d,
@@ -386,6 +404,7 @@ impl UpdateObject {
     pub fn get_all_relations(
         old_object: ObjectWithRelations,
         new_object: Object,
+        normalization: NameNormalization,
     ) -> Vec<(InternalRelation, (DieselUlid, DieselUlid))> {
         let mut relations: Vec<(InternalRelation, (DieselUlid, DieselUlid))> = old_object
             .inbound_belongs_to
@@ -400,7 +419,7 @@ impl UpdateObject {
                         relation_name: ir.1.relation_name,
                         target_pid: new_object.id,
                         target_type: new_object.object_type,
-                        target_name: new_object.name.clone(),
+                        target_name: normalization.normalize(&new_object.name),
                     },
                     (ir.1.id, ir.1.origin_pid),
                 )
@@ -412,6 +431,11 @@ impl UpdateObject {
                 .0
                 .into_iter()
                 .map(|ir| {
+                    // Symlinks keep their own name
+                    let target_name = match ir.1.relation_name.as_str() {
+                        INTERNAL_RELATION_VARIANT_SYMLINK => ir.1.target_name.clone(),
+                        _ => new_object.name.clone(),
+                    };
                     (
                         InternalRelation {
                             id: DieselUlid::generate(),
@@ -420,7 +444,7 @@ impl UpdateObject {
                             relation_name: ir.1.relation_name,
                             target_pid: new_object.id,
                             target_type: new_object.object_type,
-                            target_name: new_object.name.clone(),
+                            target_name,
                         },
                         (ir.1.id, ir.1.origin_pid),
                     )
@@ -603,3 +627,15 @@ impl SetHashes {
         self.0.hashes.clone().try_into()
     }
 }
+
+impl UpdateProjectDefaultEndpoint {
+    pub fn get_id(&self) -> Result<DieselUlid> {
+        Ok(DieselUlid::from_str(&self.project_id)?)
+    }
+    pub fn get_endpoint_id(&self) -> Result<Option<DieselUlid>> {
+        match &self.endpoint_id {
+            Some(id) if !id.is_empty() => Ok(Some(DieselUlid::from_str(id)?)),
+            _ => Ok(None),
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
 
+use crate::auth::bootstrap::AdminBootstrap;
 use crate::auth::token_handler::{Action, Intent, TokenHandler};
 use crate::database::crud::CrudDb;
 use crate::database::dsls::persistent_notification_dsl::{
@@ -139,6 +140,44 @@ impl DatabaseHandler {
         Ok(user)
     }
 
+    /// Promotes the user to an active global admin with the one-time bootstrap token
+    pub async fn bootstrap_admin(
+        &self,
+        bootstrap: &AdminBootstrap,
+        user_id: DieselUlid,
+        token: &str,
+    ) -> Result<User> {
+        let mut client = self.database.get_client().await?;
+        bootstrap.consume(token, User::global_admin_exists(&client).await?)?;
+
+        let promote = async {
+            let transaction = client.transaction().await?;
+            let transaction_client = transaction.client();
+            User::activate_user(transaction_client, &user_id).await?;
+            let user = User::set_user_global_admin(transaction_client, &user_id, true).await?;
+            transaction.commit().await?;
+            Ok::<_, anyhow::Error>(user)
+        };
+        let user = match promote.await {
+            Ok(user) => user,
+            Err(err) => {
+                bootstrap.release();
+                return Err(err);
+            }
+        };
+        self.cache.update_user(&user.id, user.clone());
+
+        if let Err(err) = self
+            .natsio_handler
+            .register_user_event(&user, EventVariant::Updated)
+            .await
+        {
+            log::error!("{}", err);
+            return Err(anyhow::anyhow!("Notification emission failed"));
+        }
+        Ok(user)
+    }
+
     pub async fn update_display_name(
         &self,
         request: UpdateUserName,
@@ -17,6 +17,19 @@ pub enum GetUser {
     GetUserRedacted(GetUserRedactedRequest),
 }
 
+/// Exchanges the one-time bootstrap token for global admin permissions.
+#[derive(Clone)]
+pub struct BootstrapAdmin {
+    pub bootstrap_token: String,
+}
+
+// The bootstrap token stays usable if the promotion fails and is never logged
+impl std::fmt::Debug for BootstrapAdmin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BootstrapAdmin").finish_non_exhaustive()
+    }
+}
+
 pub enum DeleteProxyAttributeSource {
     Proxy(DieselUlid),
     User(DieselUlid),
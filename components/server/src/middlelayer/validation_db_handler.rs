@@ -0,0 +1,97 @@
+use crate::database::crud::CrudDb;
+use crate::database::dsls::hook_dsl::TriggerVariant;
+use crate::database::dsls::object_dsl::{KeyValue, KeyValueVariant, Object, ObjectWithRelations};
+use crate::database::enums::ObjectStatus;
+use crate::hooks::hook_handler::HookMessage;
+use crate::hooks::scan_hook::{self, SCAN_TOKEN_KEY};
+use crate::hooks::validation_hook::{ValidationOutcome, VALIDATION_FAILED_KEY};
+use crate::middlelayer::db_handler::DatabaseHandler;
+use crate::utils::request_id_utils::current_request_id;
+use anyhow::{anyhow, bail, Result};
+use diesel_ulid::DieselUlid;
+
+impl DatabaseHandler {
+    /// Applies the outcome of a content validation. Rejected objects become unavailable,
+    /// flagged objects get a label with the details. Objects which are not rejected are
+    /// scanned next if their project requires it, otherwise they become available and
+    /// trigger object finished hooks.
+    pub async fn finish_validation(
+        &self,
+        object_id: DieselUlid,
+        outcome: &ValidationOutcome,
+    ) -> Result<ObjectWithRelations> {
+        let scan_project = match outcome {
+            ValidationOutcome::Rejected { .. } => None,
+            _ => scan_hook::scan_project(&self.cache, &object_id)?,
+        };
+
+        let mut client = self.database.get_client().await?;
+        let transaction = client.transaction().await?;
+        let transaction_client = transaction.client();
+
+        let mut object = Object::get_for_update(&object_id, transaction_client)
+            .await?
+            .ok_or_else(|| anyhow!("Object not found"))?;
+        if object.object_status != ObjectStatus::VALIDATING {
+            bail!("Object is not being validated");
+        }
+        object
+            .key_values
+            .0
+             .0
+            .retain(|kv| kv.key != SCAN_TOKEN_KEY && kv.key != VALIDATION_FAILED_KEY);
+        let (status, details) = match outcome {
+            ValidationOutcome::Passed => (ObjectStatus::AVAILABLE, None),
+            ValidationOutcome::Flagged { details } => (ObjectStatus::AVAILABLE, Some(details)),
+            ValidationOutcome::Rejected { details } => (ObjectStatus::UNAVAILABLE, Some(details)),
+        };
+        if let Some(details) = details {
+            object.key_values.0 .0.push(KeyValue {
+                key: VALIDATION_FAILED_KEY.to_string(),
+                value: details.clone(),
+                variant: KeyValueVariant::LABEL,
+                value_type: None,
+            });
+        }
+        object.update(transaction_client).await?;
+        if scan_project.is_none() {
+            Object::update_status(&object_id, status, transaction_client).await?;
+        }
+        transaction.commit().await?;
+
+        let object = self.emit_scan_update(object_id, &client).await?;
+        if let Some(project_id) = scan_project {
+            // Object finished hooks are triggered after a clean scan
+            let owner = self
+                .cache
+                .get_object(&project_id)
+                .ok_or_else(|| anyhow!("Project not found"))?
+                .object
+                .created_by;
+            self.enqueue_hook(HookMessage {
+                hook: scan_hook::scan_hook(project_id, owner)?,
+                object: object.clone(),
+                user_id: owner,
+                request_id: current_request_id(),
+            })
+            .await?;
+        } else if status == ObjectStatus::AVAILABLE {
+            let db_handler = DatabaseHandler {
+                database: self.database.clone(),
+                natsio_handler: self.natsio_handler.clone(),
+                cache: self.cache.clone(),
+                hook_sender: self.hook_sender.clone(),
+            };
+            let owr = object.clone();
+            tokio::spawn(async move {
+                let call = db_handler
+                    .trigger_hooks(owr, vec![TriggerVariant::OBJECT_FINISHED], None)
+                    .await;
+                if call.is_err() {
+                    log::error!("{:?}", call);
+                }
+            });
+        }
+        Ok(object)
+    }
+}
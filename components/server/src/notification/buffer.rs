@@ -0,0 +1,245 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// What happens with notifications if the buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    // Drop the oldest buffered notification
+    Drop,
+    // Append further notifications to the spill file
+    Persist,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "drop" => Ok(OverflowPolicy::Drop),
+            "persist" => Ok(OverflowPolicy::Persist),
+            _ => Err(anyhow::anyhow!("Invalid notification overflow policy: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationBufferConfig {
+    pub capacity: usize,
+    pub policy: OverflowPolicy,
+    pub spill_path: PathBuf,
+}
+
+impl Default for NotificationBufferConfig {
+    fn default() -> Self {
+        NotificationBufferConfig {
+            capacity: 10000,
+            policy: OverflowPolicy::Persist,
+            spill_path: PathBuf::from("./nats_buffer.jsonl"),
+        }
+    }
+}
+
+impl NotificationBufferConfig {
+    pub fn from_env() -> Self {
+        let mut config = NotificationBufferConfig::default();
+        if let Some(capacity) = dotenvy::var("NATS_BUFFER_SIZE")
+            .ok()
+            .and_then(|var| var.parse::<usize>().ok())
+        {
+            config.capacity = capacity;
+        }
+        if let Ok(policy) = dotenvy::var("NATS_BUFFER_OVERFLOW") {
+            match OverflowPolicy::from_str(&policy) {
+                Ok(policy) => config.policy = policy,
+                Err(err) => log::warn!("{}, using default", err),
+            }
+        }
+        if let Ok(path) = dotenvy::var("NATS_BUFFER_PATH") {
+            config.spill_path = PathBuf::from(path);
+        }
+        config
+    }
+}
+
+/// Notification which could not be published while Nats.io was unavailable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingNotification {
+    pub subject: String,
+    pub message_id: Option<String>,
+    // Unique per notification and kept for all publish attempts
+    #[serde(default = "generate_event_id")]
+    pub event_id: String,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    pub payload: String,
+}
+
+fn generate_event_id() -> String {
+    diesel_ulid::DieselUlid::generate().to_string()
+}
+
+/// Bounded queue of unpublished notifications in publishing order.
+/// Notifications exceeding the capacity are dropped or spilled to disk,
+/// spilled notifications are published after the in-memory ones.
+pub struct NotificationBuffer {
+    config: NotificationBufferConfig,
+    queue: Mutex<VecDeque<PendingNotification>>,
+}
+
+impl NotificationBuffer {
+    pub fn new(config: NotificationBufferConfig) -> Self {
+        NotificationBuffer {
+            config,
+            queue: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn push(&self, notification: PendingNotification) {
+        let mut queue = self.queue.lock().unwrap();
+        // Ordering is kept by spilling everything once the file is in use
+        if queue.len() < self.config.capacity && !self.has_spilled() {
+            queue.push_back(notification);
+            return;
+        }
+        match self.config.policy {
+            OverflowPolicy::Drop => {
+                if let Some(dropped) = queue.pop_front() {
+                    log::warn!("Notification buffer full, dropped {}", dropped.subject);
+                }
+                if self.config.capacity > 0 {
+                    queue.push_back(notification);
+                }
+            }
+            OverflowPolicy::Persist => {
+                if let Err(err) = self.spill(&notification) {
+                    log::error!(
+                        "Notification buffer full and spill failed, dropped {}: {}",
+                        notification.subject,
+                        err
+                    );
+                }
+            }
+        }
+    }
+
+    /// Re-inserts a notification which failed to publish at the front of the queue
+    pub fn push_front(&self, notification: PendingNotification) {
+        self.queue.lock().unwrap().push_front(notification);
+    }
+
+    /// Returns the next notification, refilling the queue from the spill file if empty
+    pub fn pop(&self) -> Option<PendingNotification> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.is_empty() && self.has_spilled() {
+            match self.take_spilled() {
+                Ok(spilled) => queue.extend(spilled),
+                Err(err) => log::error!("Reading spilled notifications failed: {}", err),
+            }
+        }
+        queue.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0 && !self.has_spilled()
+    }
+
+    fn has_spilled(&self) -> bool {
+        self.config.policy == OverflowPolicy::Persist
+            && std::fs::metadata(&self.config.spill_path)
+                .map(|meta| meta.len() > 0)
+                .unwrap_or(false)
+    }
+
+    fn spill(&self, notification: &PendingNotification) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.spill_path)?;
+        writeln!(file, "{}", serde_json::to_string(notification)?)?;
+        Ok(())
+    }
+
+    fn take_spilled(&self) -> Result<Vec<PendingNotification>> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(&self.config.spill_path)?;
+        let mut spilled = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<PendingNotification>(&line) {
+                Ok(notification) => spilled.push(notification),
+                Err(err) => log::error!("Skipping corrupt spilled notification: {}", err),
+            }
+        }
+        std::fs::remove_file(&self.config.spill_path)?;
+        Ok(spilled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(subject: &str) -> PendingNotification {
+        PendingNotification {
+            subject: subject.to_string(),
+            message_id: None,
+            event_id: generate_event_id(),
+            request_id: None,
+            payload: "{}".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let buffer = NotificationBuffer::new(NotificationBufferConfig {
+            capacity: 2,
+            policy: OverflowPolicy::Drop,
+            spill_path: PathBuf::from("/nonexistent"),
+        });
+        buffer.push(notification("a"));
+        buffer.push(notification("b"));
+        buffer.push(notification("c"));
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.pop().unwrap().subject, "b");
+        assert_eq!(buffer.pop().unwrap().subject, "c");
+        assert!(buffer.pop().is_none());
+    }
+
+    #[test]
+    fn test_persist_overflow() {
+        let spill_path = std::env::temp_dir().join(format!(
+            "nats_buffer_{}.jsonl",
+            diesel_ulid::DieselUlid::generate()
+        ));
+        let buffer = NotificationBuffer::new(NotificationBufferConfig {
+            capacity: 1,
+            policy: OverflowPolicy::Persist,
+            spill_path: spill_path.clone(),
+        });
+        buffer.push(notification("a"));
+        buffer.push(notification("b"));
+        buffer.push(notification("c"));
+        assert_eq!(buffer.len(), 1);
+        assert!(!buffer.is_empty());
+
+        // Spilled notifications follow in order
+        assert_eq!(buffer.pop().unwrap().subject, "a");
+        assert_eq!(buffer.pop().unwrap().subject, "b");
+        assert_eq!(buffer.pop().unwrap().subject, "c");
+        assert!(buffer.is_empty());
+        assert!(!spill_path.exists());
+    }
+}
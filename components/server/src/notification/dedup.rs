@@ -0,0 +1,82 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+/// Header with the unique id of an event. Nats.io discards publishes with an id
+/// which was already stored in the stream within the duplicate window.
+pub const EVENT_ID_KEY: &str = "Nats-Msg-Id";
+
+/// Window in which Nats.io detects re-published events, e.g. after a reconnect
+pub fn duplicate_window_from_env() -> Duration {
+    Duration::from_secs(
+        dotenvy::var("NATS_DUPLICATE_WINDOW")
+            .ok()
+            .and_then(|var| var.parse::<u64>().ok())
+            .unwrap_or(120),
+    )
+}
+
+/// Remembers the stream sequences of the most recently processed events.
+///
+/// Events are delivered at least once: unacknowledged events are redelivered with
+/// their original stream sequence, which is monotonic for all events of the stream.
+/// Redeliveries within the window are recognized as duplicates and can be discarded.
+pub struct DeliveryDeduplicator {
+    capacity: usize,
+    seen: HashSet<u64>,
+    order: VecDeque<u64>,
+}
+
+impl DeliveryDeduplicator {
+    pub fn new(capacity: usize) -> Self {
+        DeliveryDeduplicator {
+            capacity,
+            seen: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        DeliveryDeduplicator::new(
+            dotenvy::var("NATS_DEDUP_WINDOW")
+                .ok()
+                .and_then(|var| var.parse::<usize>().ok())
+                .unwrap_or(10000),
+        )
+    }
+
+    /// Records the sequence and returns true if it was already seen within the window
+    pub fn is_duplicate(&mut self, sequence: u64) -> bool {
+        if self.seen.contains(&sequence) {
+            return true;
+        }
+        if self.capacity == 0 {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(sequence);
+        self.order.push_back(sequence);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_within_window() {
+        let mut dedup = DeliveryDeduplicator::new(2);
+        assert!(!dedup.is_duplicate(1));
+        assert!(!dedup.is_duplicate(2));
+        assert!(dedup.is_duplicate(1));
+
+        // Oldest sequence leaves the window
+        assert!(!dedup.is_duplicate(3));
+        assert!(!dedup.is_duplicate(1));
+        assert!(dedup.is_duplicate(3));
+    }
+}
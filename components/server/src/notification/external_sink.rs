@@ -0,0 +1,456 @@
+use crate::caching::cache::Cache;
+use crate::database::connection::Database;
+use crate::database::crud::CrudDb;
+use crate::database::dsls::dead_letter_notification_dsl::DeadLetterNotification;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use diesel_ulid::DieselUlid;
+use futures::future::join_all;
+use postgres_types::Json;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Project key-value with the JSON config of an external notification sink, e.g.
+/// `{"url": "https://bridge.example.org/events", "events": ["CREATED", "DELETED"]}`.
+/// Projects can have multiple sinks, sinks without event filter receive all events.
+pub const NOTIFICATION_SINK_KEY: &str = "app.aruna-storage.org/notification-sink";
+
+/// Queue and retry limits of the fan-out to external sinks
+#[derive(Debug, Clone)]
+pub struct SinkQueueConfig {
+    pub capacity: usize,
+    pub batch_size: usize,
+    pub max_retries: u32,
+    pub retry_backoff: Duration,
+}
+
+fn var(key: &str, default: u64) -> u64 {
+    dotenvy::var(key)
+        .map(|var| var.parse::<u64>().unwrap_or(default))
+        .unwrap_or(default)
+}
+
+impl SinkQueueConfig {
+    pub fn from_env() -> Self {
+        SinkQueueConfig {
+            capacity: var("NOTIFICATION_SINK_QUEUE_SIZE", 1000).max(1) as usize,
+            batch_size: var("NOTIFICATION_SINK_BATCH_SIZE", 100).max(1) as usize,
+            max_retries: var("NOTIFICATION_SINK_MAX_RETRIES", 3) as u32,
+            retry_backoff: Duration::from_secs(var("NOTIFICATION_SINK_RETRY_BACKOFF", 1).max(1)),
+        }
+    }
+}
+
+/// Resource event as it is sent to external sinks. Events are delivered at least
+/// once, receivers discard redeliveries by `event_id`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExternalEvent {
+    pub event_id: String,
+    pub project_id: DieselUlid,
+    pub resource_id: DieselUlid,
+    pub resource_variant: String,
+    pub event_variant: String,
+    pub request_id: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Sink of a project with the event variants it receives
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SinkConfig {
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl SinkConfig {
+    pub fn accepts(&self, event: &ExternalEvent) -> bool {
+        self.events.is_empty()
+            || self
+                .events
+                .iter()
+                .any(|variant| variant.eq_ignore_ascii_case(&event.event_variant))
+    }
+}
+
+/// External system which receives the resource events of projects, e.g. a bridge
+/// into Kafka or SQS
+#[async_trait]
+pub trait ExternalSink: Send + Sync {
+    /// Identifies the sink in logs and dead letters
+    fn name(&self) -> String;
+
+    /// Delivers a batch of events, failed batches are retried as a whole
+    async fn deliver(&self, events: &[ExternalEvent]) -> Result<()>;
+}
+
+/// Sink which posts batches of events as JSON array to a webhook
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookSink {
+    pub fn new(client: reqwest::Client, url: String) -> Self {
+        WebhookSink { client, url }
+    }
+}
+
+#[async_trait]
+impl ExternalSink for WebhookSink {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    async fn deliver(&self, events: &[ExternalEvent]) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(events)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Resolves the sinks of a project
+pub trait SinkRegistry: Send + Sync {
+    fn sinks(&self, project_id: &DieselUlid) -> Vec<(SinkConfig, Arc<dyn ExternalSink>)>;
+}
+
+/// Webhook sinks configured by the key-values of the cached projects
+pub struct ProjectSinks {
+    cache: Arc<Cache>,
+    client: reqwest::Client,
+}
+
+impl ProjectSinks {
+    pub fn new(cache: Arc<Cache>) -> Self {
+        ProjectSinks {
+            cache,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl SinkRegistry for ProjectSinks {
+    fn sinks(&self, project_id: &DieselUlid) -> Vec<(SinkConfig, Arc<dyn ExternalSink>)> {
+        let Some(project) = self.cache.get_object(project_id) else {
+            return vec![];
+        };
+        project
+            .object
+            .key_values
+            .0
+             .0
+            .iter()
+            .filter(|kv| kv.key == NOTIFICATION_SINK_KEY)
+            .filter_map(|kv| match serde_json::from_str::<SinkConfig>(&kv.value) {
+                Ok(config) => Some(config),
+                Err(err) => {
+                    log::warn!("Invalid notification sink of project {project_id}: {err}");
+                    None
+                }
+            })
+            .map(|config| {
+                let sink: Arc<dyn ExternalSink> =
+                    Arc::new(WebhookSink::new(self.client.clone(), config.url.clone()));
+                (config, sink)
+            })
+            .collect()
+    }
+}
+
+/// Batch of events which could not be delivered to a sink after all retries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedDelivery {
+    pub sink: String,
+    pub project_id: DieselUlid,
+    pub events: Vec<ExternalEvent>,
+    pub reason: String,
+    pub attempts: u32,
+}
+
+impl FailedDelivery {
+    /// Persists the undelivered events as dead letter
+    pub async fn store(self, database: &Database) -> Result<()> {
+        let client = database.get_client().await?;
+        DeadLetterNotification {
+            id: DieselUlid::generate(),
+            sink: self.sink,
+            project_id: self.project_id,
+            payload: Json(serde_json::to_value(&self.events)?),
+            reason: self.reason,
+            attempts: self.attempts as i32,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+        .create(&client)
+        .await
+    }
+}
+
+/// Fans resource events out to the external sinks of their projects.
+///
+/// Events are queued and delivered in batches by a background worker, so slow or
+/// unavailable sinks do not delay the request which caused the event. Failed batches
+/// are retried with an exponential backoff and dead-lettered after the last retry.
+pub struct SinkFanout {
+    sender: async_channel::Sender<ExternalEvent>,
+}
+
+impl SinkFanout {
+    pub fn start(
+        config: SinkQueueConfig,
+        registry: Arc<dyn SinkRegistry>,
+        dead_letters: async_channel::Sender<FailedDelivery>,
+    ) -> Self {
+        let (sender, receiver) = async_channel::bounded(config.capacity);
+        tokio::spawn(async move {
+            while let Ok(event) = receiver.recv().await {
+                let mut batch = vec![event];
+                while batch.len() < config.batch_size {
+                    match receiver.try_recv() {
+                        Ok(event) => batch.push(event),
+                        Err(_) => break,
+                    }
+                }
+                for failure in deliver_batch(&batch, registry.as_ref(), &config).await {
+                    log::error!(
+                        "Dead-lettered {} events for notification sink {}: {}",
+                        failure.events.len(),
+                        failure.sink,
+                        failure.reason
+                    );
+                    if let Err(err) = dead_letters.send(failure).await {
+                        log::error!("Unable to store dead-lettered events: {err}");
+                    }
+                }
+            }
+        });
+        SinkFanout { sender }
+    }
+
+    /// Queues the event without waiting, a full queue is awaited in the background
+    pub fn publish(&self, event: ExternalEvent) {
+        match self.sender.try_send(event) {
+            Ok(_) => {}
+            Err(async_channel::TrySendError::Full(event)) => {
+                let sender = self.sender.clone();
+                tokio::spawn(async move { sender.send(event).await });
+            }
+            Err(async_channel::TrySendError::Closed(event)) => {
+                log::error!("Notification sink queue closed, dropped {}", event.event_id);
+            }
+        }
+    }
+}
+
+/// Delivers the events to the matching sinks of their projects, sinks are called
+/// concurrently. Returns the deliveries which failed after all retries.
+pub async fn deliver_batch(
+    events: &[ExternalEvent],
+    registry: &dyn SinkRegistry,
+    config: &SinkQueueConfig,
+) -> Vec<FailedDelivery> {
+    // Group by project and keep the order of the events
+    let mut projects: Vec<(DieselUlid, Vec<&ExternalEvent>)> = Vec::new();
+    for event in events {
+        match projects.iter_mut().find(|(id, _)| id == &event.project_id) {
+            Some((_, project_events)) => project_events.push(event),
+            None => projects.push((event.project_id, vec![event])),
+        }
+    }
+
+    let mut deliveries = Vec::new();
+    for (project_id, project_events) in projects {
+        for (sink_config, sink) in registry.sinks(&project_id) {
+            let matching = project_events
+                .iter()
+                .filter(|event| sink_config.accepts(event))
+                .map(|event| (*event).clone())
+                .collect::<Vec<_>>();
+            if !matching.is_empty() {
+                deliveries.push(deliver_with_retries(sink, project_id, matching, config));
+            }
+        }
+    }
+    join_all(deliveries)
+        .await
+        .into_iter()
+        .filter_map(|result| result.err())
+        .collect()
+}
+
+async fn deliver_with_retries(
+    sink: Arc<dyn ExternalSink>,
+    project_id: DieselUlid,
+    events: Vec<ExternalEvent>,
+    config: &SinkQueueConfig,
+) -> std::result::Result<(), FailedDelivery> {
+    let mut backoff = config.retry_backoff;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let reason = match sink.deliver(&events).await {
+            Ok(_) => return Ok(()),
+            Err(err) => err.to_string(),
+        };
+        if attempts > config.max_retries {
+            return Err(FailedDelivery {
+                sink: sink.name(),
+                project_id,
+                events,
+                reason,
+                attempts,
+            });
+        }
+        log::warn!(
+            "Notification sink {} failed (attempt {attempts}): {reason}",
+            sink.name()
+        );
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MockSink {
+        received: Mutex<Vec<ExternalEvent>>,
+        // Number of deliveries which fail before the sink accepts events
+        failures: AtomicU32,
+    }
+
+    #[async_trait]
+    impl ExternalSink for MockSink {
+        fn name(&self) -> String {
+            "mock".to_string()
+        }
+
+        async fn deliver(&self, events: &[ExternalEvent]) -> Result<()> {
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |f| f.checked_sub(1))
+                .is_ok()
+            {
+                anyhow::bail!("Sink unavailable");
+            }
+            self.received.lock().unwrap().extend_from_slice(events);
+            Ok(())
+        }
+    }
+
+    struct MockRegistry {
+        project_id: DieselUlid,
+        config: SinkConfig,
+        sink: Arc<MockSink>,
+    }
+
+    impl SinkRegistry for MockRegistry {
+        fn sinks(&self, project_id: &DieselUlid) -> Vec<(SinkConfig, Arc<dyn ExternalSink>)> {
+            if project_id != &self.project_id {
+                return vec![];
+            }
+            let sink: Arc<dyn ExternalSink> = self.sink.clone();
+            vec![(self.config.clone(), sink)]
+        }
+    }
+
+    fn event(project_id: DieselUlid, event_variant: &str) -> ExternalEvent {
+        ExternalEvent {
+            event_id: DieselUlid::generate().to_string(),
+            project_id,
+            resource_id: DieselUlid::generate(),
+            resource_variant: "OBJECT".to_string(),
+            event_variant: event_variant.to_string(),
+            request_id: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    fn config() -> SinkQueueConfig {
+        SinkQueueConfig {
+            capacity: 10,
+            batch_size: 10,
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filtered_events_delivered() {
+        let project_id = DieselUlid::generate();
+        let registry = MockRegistry {
+            project_id,
+            config: SinkConfig {
+                url: "http://localhost/events".to_string(),
+                events: vec!["created".to_string(), "DELETED".to_string()],
+            },
+            sink: Arc::new(MockSink::default()),
+        };
+        let created = event(project_id, "CREATED");
+        let deleted = event(project_id, "DELETED");
+        let events = vec![
+            created.clone(),
+            event(project_id, "UPDATED"),
+            event(DieselUlid::generate(), "CREATED"),
+            deleted.clone(),
+        ];
+
+        let failures = deliver_batch(&events, &registry, &config()).await;
+        assert!(failures.is_empty());
+        assert_eq!(
+            registry.sink.received.lock().unwrap().as_slice(),
+            &[created, deleted]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_deliveries_retried_and_dead_lettered() {
+        let project_id = DieselUlid::generate();
+        let sink = Arc::new(MockSink {
+            failures: AtomicU32::new(2),
+            ..Default::default()
+        });
+        let registry = Arc::new(MockRegistry {
+            project_id,
+            config: SinkConfig {
+                url: "http://localhost/events".to_string(),
+                events: vec![],
+            },
+            sink: sink.clone(),
+        });
+
+        // Succeeds with the last retry
+        let (dead_sender, dead_receiver) = async_channel::unbounded();
+        let fanout = SinkFanout::start(config(), registry.clone(), dead_sender);
+        let first = event(project_id, "UPDATED");
+        fanout.publish(first.clone());
+        for _ in 0..100 {
+            if !sink.received.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(sink.received.lock().unwrap().as_slice(), &[first]);
+        assert!(dead_receiver.is_empty());
+
+        // Dead-lettered after the last retry
+        sink.failures.store(3, Ordering::SeqCst);
+        let second = event(project_id, "DELETED");
+        fanout.publish(second.clone());
+        let failure = tokio::time::timeout(Duration::from_secs(1), dead_receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(failure.events, vec![second]);
+        assert_eq!(failure.attempts, 3);
+        assert_eq!(sink.received.lock().unwrap().len(), 1);
+    }
+}
@@ -22,6 +22,11 @@ pub enum EventType {
 }
 
 // An Event handler is the main connection of the underlying event message system like Nats.io
+//
+// Events are delivered at least once. Every event carries a unique id (`Nats-Msg-Id` header)
+// and a monotonic stream sequence which stays the same for redeliveries, e.g. after a reconnect
+// or a missing acknowledgement. Consumers have to either process events idempotently or
+// discard already processed ids/sequences (see `dedup::DeliveryDeduplicator`).
 #[async_trait]
 pub trait EventHandler {
     // Registers/Publishes an event into the event message system
@@ -1,3 +1,6 @@
+pub mod buffer;
+pub mod dedup;
+pub mod external_sink;
 pub mod handler;
 pub mod natsio_handler;
 pub mod utils;
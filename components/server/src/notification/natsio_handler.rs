@@ -1,6 +1,9 @@
 use anyhow::anyhow;
 use std::collections::hash_map::RandomState;
 use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use aruna_rust_api::api::notification::services::v2::announcement_event::EventVariant as AnnouncementVariant;
@@ -27,7 +30,11 @@ use crate::database::dsls::object_dsl::{
 };
 use crate::database::dsls::user_dsl::User;
 use crate::utils::grpc_utils::{checksum_resource, checksum_user, generic_object_without_rules};
+use crate::utils::request_id_utils::{current_request_id, REQUEST_ID_KEY};
 
+use super::buffer::{NotificationBuffer, NotificationBufferConfig, PendingNotification};
+use super::dedup::{duplicate_window_from_env, EVENT_ID_KEY};
+use super::external_sink::{ExternalEvent, SinkFanout};
 use super::handler::{EventHandler, EventStreamHandler, EventType};
 use super::utils::{
     generate_announcement_message_subject, generate_announcement_subject,
@@ -86,9 +93,14 @@ pub enum Deleted {
 
 // ----------------------------------------------------------- //
 pub struct NatsIoHandler {
+    client: async_nats::Client,
     jetstream_context: Context,
     stream: Stream,
     pub reply_secret: String,
+    available: AtomicBool,
+    buffer: Arc<NotificationBuffer>,
+    // Optional fan-out of resource events to the external sinks of projects
+    external_sinks: OnceLock<SinkFanout>,
 }
 
 #[derive(Debug, Clone)]
@@ -107,26 +119,17 @@ impl EventHandler for NatsIoHandler {
     ) -> anyhow::Result<()> {
         // Encode message
         let json_message = serde_json::to_string_pretty(&message_variant)?;
-        let message_bytes = Bytes::from(json_message);
 
-        // Create header with block_id for deduplication
-        let mut message_header: HeaderMap = HeaderMap::new();
-        if let Some(msg_id) = message_id {
-            message_header.append("block-id", msg_id.to_string().as_str())
-        }
-
-        // Publish message on stream
-        match self
-            .jetstream_context
-            .publish_with_headers(subject, message_header, message_bytes)
-            .await
-        {
-            Ok(_) => Ok(()),
-            Err(err) => {
-                log::error!("{}", err);
-                Err(err.into())
-            }
-        }
+        // Publish message on stream or buffer it while Nats.io is unavailable
+        self.publish_or_buffer(PendingNotification {
+            subject,
+            message_id: message_id.map(|id| id.to_string()),
+            event_id: DieselUlid::generate().to_string(),
+            request_id: current_request_id(),
+            payload: json_message,
+        })
+        .await;
+        Ok(())
     }
 
     ///ToDo: Rust Doc
@@ -338,18 +341,20 @@ impl NatsIoHandler {
         stream_name: Option<String>,
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         // Create Nats.io Jetstream client
-        let jetstream_context = async_nats::jetstream::new(nats_client);
+        let jetstream_context = async_nats::jetstream::new(nats_client.clone());
 
         // Evaluate stream name
         let stream_name = stream_name.unwrap_or_else(|| STREAM_NAME.to_string());
 
-        // Create minimalistic stream config
+        // Create minimalistic stream config, re-published events are
+        // discarded within the duplicate window
         let stream_config = async_nats::jetstream::stream::Config {
             name: stream_name.clone(),
             subjects: STREAM_SUBJECTS
                 .into_iter()
                 .map(|subject| subject.into())
                 .collect(),
+            duplicate_window: duplicate_window_from_env(),
             ..Default::default()
         };
 
@@ -359,12 +364,107 @@ impl NatsIoHandler {
             .await?;
 
         Ok(NatsIoHandler {
+            client: nats_client,
             jetstream_context,
             stream,
             reply_secret: secret,
+            available: AtomicBool::new(true),
+            buffer: Arc::new(NotificationBuffer::new(NotificationBufferConfig::from_env())),
+            external_sinks: OnceLock::new(),
         })
     }
 
+    /// Is false after a failed publish until the buffered notifications are delivered
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::Relaxed)
+            && self.client.connection_state() == async_nats::connection::State::Connected
+    }
+
+    pub fn set_available(&self, available: bool) {
+        self.available.store(available, Ordering::Relaxed)
+    }
+
+    pub fn set_external_sinks(&self, fanout: SinkFanout) {
+        if self.external_sinks.set(fanout).is_err() {
+            log::warn!("External notification sinks already set");
+        }
+    }
+
+    /// Number of notifications waiting in memory for Nats.io to recover
+    pub fn pending_notifications(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Publishes the notification or buffers it while Nats.io is unavailable.
+    /// Buffered notifications are published first to keep the order.
+    async fn publish_or_buffer(&self, notification: PendingNotification) {
+        if !self.is_available() || !self.buffer.is_empty() {
+            self.buffer.push(notification);
+            return;
+        }
+        if let Err(err) = self.publish(&notification).await {
+            log::warn!("Nats.io unavailable, buffering notifications: {}", err);
+            self.set_available(false);
+            self.buffer.push(notification);
+        }
+    }
+
+    async fn publish(&self, notification: &PendingNotification) -> anyhow::Result<()> {
+        // Create header with block_id for deduplication
+        let mut message_header: HeaderMap = HeaderMap::new();
+        if let Some(msg_id) = &notification.message_id {
+            message_header.append("block-id", msg_id.as_str())
+        }
+        // Stable event id, retries after a reconnect are not stored twice
+        message_header.append(EVENT_ID_KEY, notification.event_id.as_str());
+        // Correlation id of the request which caused the notification
+        if let Some(request_id) = &notification.request_id {
+            message_header.append(REQUEST_ID_KEY, request_id.as_str())
+        }
+
+        self.jetstream_context
+            .publish_with_headers(
+                notification.subject.clone(),
+                message_header,
+                Bytes::from(notification.payload.clone()),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Delivers all buffered notifications, stops at the first failed publish
+    pub async fn flush_buffer(&self) -> anyhow::Result<()> {
+        while let Some(notification) = self.buffer.pop() {
+            if let Err(err) = self.publish(&notification).await {
+                self.buffer.push_front(notification);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Periodically tries to deliver buffered notifications after Nats.io has recovered
+    pub fn start_recovery_loop(self: Arc<Self>, interval: Duration) {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+
+                if self.buffer.is_empty()
+                    || self.client.connection_state() != async_nats::connection::State::Connected
+                {
+                    continue;
+                }
+                match self.flush_buffer().await {
+                    Ok(_) => {
+                        log::info!("Nats.io available again, delivered buffered notifications");
+                        self.set_available(true);
+                    }
+                    Err(err) => log::warn!("Delivering buffered notifications failed: {}", err),
+                }
+            }
+        });
+    }
+
     ///ToDo: Rust Doc
     pub async fn get_pull_consumer(
         &self,
@@ -466,6 +566,33 @@ impl NatsIoHandler {
             "Checksum calculation failed"
         );
 
+        // Queue the event for the external sinks of all projects of the resource
+        if let Some(sinks) = self.external_sinks.get() {
+            let mut project_ids = object_hierarchies
+                .iter()
+                .filter_map(|hierarchy| DieselUlid::from_str(&hierarchy.project_id).ok())
+                .collect::<Vec<_>>();
+            project_ids.sort();
+            project_ids.dedup();
+            for project_id in project_ids {
+                sinks.publish(ExternalEvent {
+                    event_id: DieselUlid::generate().to_string(),
+                    project_id,
+                    resource_id: object.object.id,
+                    resource_variant: ResourceVariant::from(object.object.object_type)
+                        .as_str_name()
+                        .trim_start_matches("RESOURCE_VARIANT_")
+                        .to_string(),
+                    event_variant: event_variant
+                        .as_str_name()
+                        .trim_start_matches("EVENT_VARIANT_")
+                        .to_string(),
+                    request_id: current_request_id(),
+                    created_at: chrono::Utc::now().naive_utc(),
+                });
+            }
+        }
+
         // Evaluate number of notifications and the corresponding subjects
         let mut subjects = generate_resource_message_subjects(object_hierarchies);
 
@@ -562,13 +689,20 @@ impl NatsIoHandler {
         let message_json = serde_json::to_string_pretty(&event_variant)?;
 
         // Create subject depending on ServerEvent
-        let (subject, message) = match event_variant {
-            ServerEvents::MVREFRESH(_) => ("AOS.SERVER.MVREFRESH", Bytes::from(message_json)),
-            ServerEvents::CACHEUPDATE(_) => ("AOS.SERVER.CACHEUPDATE", Bytes::from(message_json)),
+        let subject = match event_variant {
+            ServerEvents::MVREFRESH(_) => "AOS.SERVER.MVREFRESH",
+            ServerEvents::CACHEUPDATE(_) => "AOS.SERVER.CACHEUPDATE",
         };
 
         // Publish message in Nats.io
-        self.jetstream_context.publish(subject, message).await?;
+        self.publish_or_buffer(PendingNotification {
+            subject: subject.to_string(),
+            message_id: None,
+            event_id: DieselUlid::generate().to_string(),
+            request_id: current_request_id(),
+            payload: message_json,
+        })
+        .await;
 
         Ok(())
     }
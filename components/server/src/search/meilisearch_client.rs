@@ -1,21 +1,34 @@
+use crate::database::connection::Database;
 use crate::database::dsls::object_dsl::Author;
+use crate::database::dsls::search_index_queue_dsl::QueuedIndexUpdate;
 use crate::database::{
     dsls::object_dsl::{KeyValue, KeyValueVariant, Object as DbObject},
     enums::{DataClass, ObjectStatus, ObjectType},
 };
+use crate::utils::conversions::objects::{format_typed_value, typed_value_to_json};
 use anyhow::bail;
 use aruna_rust_api::api::storage::models::v2::{
     generic_resource::Resource, Collection, Dataset, KeyValue as ApiKeyValue,
     KeyValueVariant as ApiKeyValueVariant, Object, Project, Stats, Status as ApiStatus,
 };
+use dashmap::DashMap;
 use diesel_ulid::DieselUlid;
+use lazy_static::lazy_static;
 use log::debug;
 use meilisearch_sdk::{
-    indexes::Index, settings::PaginationSetting, task_info::TaskInfo, Client, Task,
+    client::SwapIndexes, indexes::Index, settings::PaginationSetting, task_info::TaskInfo, Client,
+    Task,
 };
 use prost_wkt_types::Timestamp;
+use regex::Regex;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use std::{fmt::Display, str::FromStr};
+use tokio::sync::OwnedMutexGuard;
 
 // Enum for the different index variants (multi-index search?)
 #[derive(Serialize)]
@@ -51,6 +64,10 @@ pub struct ObjectDocument {
     pub count: i64,
     pub size: i64,
     pub labels: Vec<KeyValue>, // Without specific internal labels
+    #[serde(default)]
+    pub typed_labels: BTreeMap<String, JsonValue>, // Native values of typed labels for range filters
+    #[serde(default)]
+    pub effective_labels: Vec<KeyValue>, // Own labels and labels inherited from ancestors
     pub data_class: DataClass,
     pub created_at: i64, // Converted to UNIX timestamp for filtering/sorting
     pub dynamic: bool,   // Archived/Snapshot i.e. mutable/immutable
@@ -82,6 +99,8 @@ impl From<DbObject> for ObjectDocument {
             authors: db_object.authors.0,
             count: db_object.count,
             size: db_object.content_len,
+            typed_labels: convert_typed_labels(&filtered_labels),
+            effective_labels: filtered_labels.clone(),
             labels: filtered_labels,
             data_class: db_object.data_class,
             created_at: db_object
@@ -169,6 +188,7 @@ impl TryFrom<Project> for ObjectDocument {
         });
 
         // Build and return ObjectDocument
+        let labels = convert_proto_to_key_value(project.key_values)?;
         Ok(ObjectDocument {
             id: DieselUlid::from_str(&project.id)?,
             object_type: ObjectType::PROJECT,
@@ -184,7 +204,9 @@ impl TryFrom<Project> for ObjectDocument {
                 .collect::<Result<Vec<Author>, anyhow::Error>>()?,
             count: stats.count,
             size: stats.size,
-            labels: convert_proto_to_key_value(project.key_values)?,
+            typed_labels: convert_typed_labels(&labels),
+            effective_labels: labels.clone(),
+            labels,
             data_class: DataClass::try_from(project.data_class)?,
             created_at: project.created_at.unwrap_or_default().seconds,
             dynamic: project.dynamic,
@@ -242,6 +264,7 @@ impl TryFrom<Collection> for ObjectDocument {
         });
 
         // Build and return ObjectDocument
+        let labels = convert_proto_to_key_value(collection.key_values)?;
         Ok(ObjectDocument {
             id: DieselUlid::from_str(&collection.id)?,
             object_type: ObjectType::COLLECTION,
@@ -257,7 +280,9 @@ impl TryFrom<Collection> for ObjectDocument {
             description: collection.description,
             count: stats.count,
             size: stats.size,
-            labels: convert_proto_to_key_value(collection.key_values)?,
+            typed_labels: convert_typed_labels(&labels),
+            effective_labels: labels.clone(),
+            labels,
             data_class: DataClass::try_from(collection.data_class)?,
             created_at: collection.created_at.unwrap_or_default().seconds,
             dynamic: collection.dynamic,
@@ -315,6 +340,7 @@ impl TryFrom<Dataset> for ObjectDocument {
         });
 
         // Build and return ObjectDocument
+        let labels = convert_proto_to_key_value(dataset.key_values)?;
         Ok(ObjectDocument {
             id: DieselUlid::from_str(&dataset.id)?,
             object_type: ObjectType::DATASET,
@@ -325,7 +351,9 @@ impl TryFrom<Dataset> for ObjectDocument {
             description: dataset.description,
             count: stats.count,
             size: stats.size,
-            labels: convert_proto_to_key_value(dataset.key_values)?,
+            typed_labels: convert_typed_labels(&labels),
+            effective_labels: labels.clone(),
+            labels,
             data_class: DataClass::try_from(dataset.data_class)?,
             created_at: dataset.created_at.unwrap_or_default().seconds,
             authors: dataset
@@ -378,6 +406,7 @@ impl TryFrom<Object> for ObjectDocument {
 
     fn try_from(object: Object) -> Result<Self, Self::Error> {
         // Build and return ObjectDocument
+        let labels = convert_proto_to_key_value(object.key_values)?;
         Ok(ObjectDocument {
             id: DieselUlid::from_str(&object.id)?,
             object_type: ObjectType::OBJECT,
@@ -388,7 +417,9 @@ impl TryFrom<Object> for ObjectDocument {
             description: object.description,
             count: 1,
             size: object.content_len,
-            labels: convert_proto_to_key_value(object.key_values)?,
+            typed_labels: convert_typed_labels(&labels),
+            effective_labels: labels.clone(),
+            labels,
             data_class: DataClass::try_from(object.data_class)?,
             created_at: object.created_at.unwrap_or_default().seconds,
             authors: object
@@ -426,22 +457,66 @@ fn convert_proto_to_key_value(key_values: Vec<ApiKeyValue>) -> anyhow::Result<Ve
     }
 }
 
+// Typed labels are additionally indexed with their native value, e.g. typed_labels.size > 1000
+fn convert_typed_labels(labels: &[KeyValue]) -> BTreeMap<String, JsonValue> {
+    labels
+        .iter()
+        .filter_map(|kv| typed_value_to_json(kv).map(|value| (kv.key.clone(), value)))
+        .collect()
+}
+
 fn convert_labels_to_proto(labels: Vec<KeyValue>) -> Vec<ApiKeyValue> {
     labels
         .into_iter()
         .map(|l| ApiKeyValue {
+            value: format_typed_value(&l.value, l.value_type),
             key: l.key,
-            value: l.value,
             variant: Into::<ApiKeyValueVariant>::into(l.variant) as i32,
         })
         .collect()
 }
 
+// Metadata key to include inherited labels in label filters of a search
+pub const INHERITED_LABELS_KEY: &str = "x-aruna-inherited-labels";
+
+lazy_static! {
+    static ref LABEL_FILTER: Regex = Regex::new(r"\blabels\.").expect("Regex must be valid");
+}
+
+/// Rewrites label conditions of a search filter to also match labels inherited
+/// from ancestors, e.g. `labels.key = project` matches all objects in datasets
+/// labeled with the key `project`. Opt-in per query, as it widens result sets.
+pub fn include_inherited_labels(filter: &str) -> String {
+    LABEL_FILTER
+        .replace_all(filter, "effective_labels.")
+        .into_owned()
+}
+
+/// Progress of a full reindex which is built in a staging index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReindexProgress {
+    pub resumed: bool, // Continued from the checkpoint of an interrupted reindex
+    pub last_id: Option<DieselUlid>,
+    pub processed: u64,
+    pub total: u64, // Resources in the database when the reindex was started
+}
+
+/// Name of the index into which a full reindex of `index_name` is built
+pub fn staging_index_name(index_name: &str) -> String {
+    format!("{index_name}_reindex")
+}
+
 #[derive(Clone)]
 pub struct MeilisearchClient {
     _server_url: String,
     _api_key: Option<String>,
     pub client: Client,
+    available: Arc<AtomicBool>,
+    // Database in which undelivered index updates are queued
+    index_queue: Option<Arc<Database>>,
+    // Indexes with a staging index which also receives all updates
+    reindexes: Arc<DashMap<String, ReindexProgress>>,
+    reindex_lock: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl MeilisearchClient {
@@ -457,9 +532,95 @@ impl MeilisearchClient {
             _server_url: meilisearch_instance_url.to_string(),
             _api_key: meilisearch_instance_api_key.map(|api_key| api_key.to_string()),
             client: meilisearch_client,
+            available: Arc::new(AtomicBool::new(true)),
+            index_queue: None,
+            reindexes: Arc::new(DashMap::new()),
+            reindex_lock: Arc::new(tokio::sync::Mutex::new(())),
         })
     }
 
+    /// Queues index updates in the database while Meilisearch is unavailable
+    pub fn with_index_queue(mut self, database: Arc<Database>) -> Self {
+        self.index_queue = Some(database);
+        self
+    }
+
+    /// Progress of the reindex of `index_name`, None if no reindex is in progress
+    pub fn reindex_progress(&self, index_name: &str) -> Option<ReindexProgress> {
+        self.reindexes
+            .get(index_name)
+            .map(|progress| progress.clone())
+    }
+
+    /// Updates the progress of a reindex, updates of the index are mirrored
+    /// into its staging index until the reindex is finished
+    pub fn set_reindex_progress(&self, index_name: &str, progress: ReindexProgress) {
+        self.reindexes.insert(index_name.to_string(), progress);
+    }
+
+    pub fn finish_reindex(&self, index_name: &str) {
+        self.reindexes.remove(index_name);
+    }
+
+    /// Returns None if a reindex is already running in this instance
+    pub fn try_lock_reindex(&self) -> Option<OwnedMutexGuard<()>> {
+        self.reindex_lock.clone().try_lock_owned().ok()
+    }
+
+    /// Returns false if the last interaction with Meilisearch failed and
+    /// the server has not recovered yet.
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::Relaxed)
+    }
+
+    pub fn set_available(&self, available: bool) {
+        self.available.store(available, Ordering::Relaxed)
+    }
+
+    /// Queues index updates or deletions for delivery after Meilisearch has recovered
+    pub async fn queue_index_updates(
+        &self,
+        ids: &[DieselUlid],
+        deletion: bool,
+    ) -> anyhow::Result<()> {
+        let Some(database) = &self.index_queue else {
+            bail!("No index queue configured")
+        };
+        let client = database.get_client().await?;
+        QueuedIndexUpdate::queue(ids, deletion, &client).await
+    }
+
+    /// Oldest queued index updates
+    pub async fn queued_index_updates(&self, limit: i64) -> anyhow::Result<Vec<QueuedIndexUpdate>> {
+        let Some(database) = &self.index_queue else {
+            return Ok(vec![]);
+        };
+        let client = database.get_client().await?;
+        QueuedIndexUpdate::get_oldest(limit, &client).await
+    }
+
+    /// Removes delivered index updates from the queue
+    pub async fn dequeue_index_updates(
+        &self,
+        delivered: &[QueuedIndexUpdate],
+    ) -> anyhow::Result<()> {
+        let Some(database) = &self.index_queue else {
+            return Ok(());
+        };
+        let client = database.get_client().await?;
+        QueuedIndexUpdate::dequeue(delivered, &client).await
+    }
+
+    /// Checks if the error was caused by an unreachable Meilisearch server
+    pub fn is_connection_error(err: &anyhow::Error) -> bool {
+        matches!(
+            err.downcast_ref::<meilisearch_sdk::errors::Error>(),
+            Some(meilisearch_sdk::errors::Error::UnreachableServer)
+                | Some(meilisearch_sdk::errors::Error::HttpError(_))
+                | Some(meilisearch_sdk::errors::Error::Timeout)
+        )
+    }
+
     ///ToDo: Rust Doc
     pub async fn get_or_create_index(
         &self,
@@ -504,11 +665,15 @@ impl MeilisearchClient {
                     "size",           // e.g. size > 12345
                     "labels.key",
                     "labels.value",
-                    "labels.variant",   // e.g. labels.variant = "LABEL"
-                    "data_class",       // e.g. data_class = "PUBLIC"
-                    "created_at",       // e.g. created_at < 1692824072 (2023-08-23T20:54:32+00:00)
+                    "labels.variant",       // e.g. labels.variant = "LABEL"
+                    "effective_labels.key", // Opt-in filter on inherited labels
+                    "effective_labels.value",
+                    "effective_labels.variant",
+                    "typed_labels", // e.g. typed_labels.size > 1000000 or typed_labels.created > 1704067200
+                    "data_class",   // e.g. data_class = "PUBLIC"
+                    "created_at",   // e.g. created_at < 1692824072 (2023-08-23T20:54:32+00:00)
                     "metadata_license", // e.g. metadata_license = CC0
-                    "data_license",     // e.g. data_license = CC0
+                    "data_license", // e.g. data_license = CC0
                 ])
                 .await?
                 .wait_for_completion(&self.client, None, None)
@@ -521,7 +686,7 @@ impl MeilisearchClient {
             // Set the sortable attributes of the index
             //TODO: Implement in API
             match index
-                .set_sortable_attributes(["size", "object_type_id", "created_at"])
+                .set_sortable_attributes(["size", "object_type_id", "created_at", "typed_labels"])
                 .await?
                 .wait_for_completion(&self.client, None, None)
                 .await?
@@ -530,8 +695,27 @@ impl MeilisearchClient {
                 _ => bail!("Search index creation failed: Could not set sortable attributes"),
             };
 
-            //TODO: Exclude fields from search?
-            //index.set_searchable_attributes(&[]).await?;
+            // Set the searchable attributes of the index, matches in earlier attributes
+            // rank higher, e.g. name matches above description matches
+            match index
+                .set_searchable_attributes([
+                    "name",
+                    "title",
+                    "description",
+                    "labels.key",
+                    "labels.value",
+                    "authors",
+                    "id",
+                    "metadata_license",
+                    "data_license",
+                ])
+                .await?
+                .wait_for_completion(&self.client, None, None)
+                .await?
+            {
+                Task::Succeeded { .. } => {}
+                _ => bail!("Search index creation failed: Could not set searchable attributes"),
+            };
 
             // Set pagination configuration
             match index
@@ -550,6 +734,39 @@ impl MeilisearchClient {
         })
     }
 
+    /// Atomically exchanges the documents and settings of two existing indexes
+    pub async fn swap_indexes(&self, first: &str, second: &str) -> anyhow::Result<()> {
+        match self
+            .client
+            .swap_indexes([&SwapIndexes {
+                indexes: (first.to_string(), second.to_string()),
+            }])
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?
+        {
+            Task::Succeeded { .. } => Ok(()),
+            _ => bail!("Search index swap of {first} and {second} failed"),
+        }
+    }
+
+    /// Deletes an index by name, missing indexes are ignored
+    pub async fn delete_index_if_exists(&self, index_name: &str) -> anyhow::Result<()> {
+        if self.client.get_index(index_name).await.is_err() {
+            return Ok(());
+        }
+        match self
+            .client
+            .delete_index(index_name)
+            .await?
+            .wait_for_completion(&self.client, None, None)
+            .await?
+        {
+            Task::Succeeded { .. } => Ok(()),
+            _ => bail!("Search index deletion of {index_name} failed"),
+        }
+    }
+
     ///ToDo: Rust Doc
     pub async fn delete_index(&self, index: MeilisearchIndexes) -> anyhow::Result<()> {
         // Extract index name of enum variant
@@ -591,7 +808,8 @@ impl MeilisearchClient {
         Ok(document_objects)
     }
 
-    ///ToDo: Rust Doc
+    /// Adds documents or replaces them by their id, so repeated updates
+    /// with the same documents are idempotent.
     pub async fn add_or_update_stuff<S: Serialize>(
         &self,
         stuff: &[S], // Slice of ... whatever is in the index
@@ -600,6 +818,18 @@ impl MeilisearchClient {
         // Extract index name of provided enum variant
         let index_name = stuff_type.to_string();
 
+        // Keep the staging index of a running reindex up to date
+        if self.reindexes.contains_key(&index_name) {
+            if let Err(err) = self
+                .client
+                .index(staging_index_name(&index_name))
+                .add_or_replace(stuff, Some("id"))
+                .await
+            {
+                log::warn!("Search reindex update failed: {}", err);
+            }
+        }
+
         // Add or update documents in index
         Ok(self
             .client
@@ -608,6 +838,26 @@ impl MeilisearchClient {
             .await?)
     }
 
+    /// Adds documents to an index by name and waits until they are indexed
+    pub async fn add_and_wait<S: Serialize>(
+        &self,
+        index_name: &str,
+        stuff: &[S],
+        timeout: Duration,
+    ) -> anyhow::Result<()> {
+        match self
+            .client
+            .index(index_name)
+            .add_or_replace(stuff, Some("id"))
+            .await?
+            .wait_for_completion(&self.client, None, Some(timeout))
+            .await?
+        {
+            Task::Succeeded { .. } => Ok(()),
+            _ => bail!("Adding documents to search index {index_name} failed"),
+        }
+    }
+
     ///ToDo: Rust Doc
     pub async fn delete_stuff<S: Serialize + Display + std::fmt::Debug>(
         &self,
@@ -617,6 +867,17 @@ impl MeilisearchClient {
         // Extract index name of enum variant
         let index_name = stuff_type.to_string();
 
+        if self.reindexes.contains_key(&index_name) {
+            if let Err(err) = self
+                .client
+                .index(staging_index_name(&index_name))
+                .delete_documents(stuff)
+                .await
+            {
+                log::warn!("Search reindex update failed: {}", err);
+            }
+        }
+
         // Delete documents to search
         Ok(self
             .client
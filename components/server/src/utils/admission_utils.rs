@@ -0,0 +1,348 @@
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tonic::body::BoxBody;
+use tonic::codegen::http::{HeaderValue, Request, Response};
+use tonic::codegen::{BoxFuture, Service};
+use tonic::Status;
+use tower::Layer;
+
+/// Metadata key of the retry hint of shed requests (gRPC retry pushback)
+pub const RETRY_PUSHBACK_KEY: &str = "grpc-retry-pushback-ms";
+
+lazy_static! {
+    pub static ref ADMISSION_CONFIG: AdmissionConfig = AdmissionConfig::from_env();
+}
+
+/// Limits of concurrently handled requests and of requests waiting for admission
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdmissionConfig {
+    pub max_concurrency: usize,
+    pub min_concurrency: usize,
+    pub max_queue: usize,
+    pub max_wait: Duration,
+    // Adapts the concurrency limit to keep handler latencies below this target
+    pub target_latency: Option<Duration>,
+}
+
+fn var(key: &str, default: u64) -> u64 {
+    dotenvy::var(key)
+        .map(|var| var.parse::<u64>().unwrap_or(default))
+        .unwrap_or(default)
+}
+
+impl AdmissionConfig {
+    pub fn from_env() -> Self {
+        let max_concurrency = var("ADMISSION_MAX_CONCURRENCY", 1024).max(1) as usize;
+        AdmissionConfig {
+            max_concurrency,
+            min_concurrency: (var("ADMISSION_MIN_CONCURRENCY", 16).max(1) as usize)
+                .min(max_concurrency),
+            max_queue: var("ADMISSION_MAX_QUEUE", 512) as usize,
+            max_wait: Duration::from_millis(var("ADMISSION_MAX_WAIT_MS", 1000)),
+            target_latency: match var("ADMISSION_TARGET_LATENCY_MS", 0) {
+                0 => None,
+                millis => Some(Duration::from_millis(millis)),
+            },
+        }
+    }
+}
+
+/// Concurrency limit shared by all connections of the server. With a target latency
+/// the limit decreases multiplicatively if requests are slow and increases by one
+/// for every fast request that was handled while the limit was reached.
+#[derive(Debug)]
+pub struct AdmissionLimiter {
+    config: AdmissionConfig,
+    limit: AtomicUsize,
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+    released: Notify,
+}
+
+/// Slot of an admitted request, released on drop
+#[derive(Debug)]
+pub struct AdmissionPermit {
+    limiter: Arc<AdmissionLimiter>,
+    admitted_at: Instant,
+    saturated: bool,
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        self.limiter
+            .release(self.admitted_at.elapsed(), self.saturated);
+    }
+}
+
+// Decrements the queue length if a waiting request is admitted, shed or cancelled
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl AdmissionLimiter {
+    pub fn new(config: AdmissionConfig) -> Self {
+        AdmissionLimiter {
+            limit: AtomicUsize::new(config.max_concurrency),
+            config,
+            in_flight: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            released: Notify::new(),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit.load(Ordering::SeqCst)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    fn try_admit(self: &Arc<Self>) -> Option<AdmissionPermit> {
+        let limit = self.limit();
+        self.in_flight
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |in_flight| {
+                (in_flight < limit).then_some(in_flight + 1)
+            })
+            .ok()
+            .map(|in_flight| AdmissionPermit {
+                limiter: self.clone(),
+                admitted_at: Instant::now(),
+                saturated: in_flight + 1 >= limit,
+            })
+    }
+
+    /// Waits for a free slot, returns None if the request is shed because
+    /// the queue is full or no slot was released within the maximum wait time
+    pub async fn admit(self: &Arc<Self>) -> Option<AdmissionPermit> {
+        if let Some(permit) = self.try_admit() {
+            return Some(permit);
+        }
+        if self.queued.fetch_add(1, Ordering::SeqCst) >= self.config.max_queue {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        let _slot = QueueSlot(&self.queued);
+        let deadline = tokio::time::Instant::now() + self.config.max_wait;
+        loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            if let Some(permit) = self.try_admit() {
+                return Some(permit);
+            }
+            if tokio::time::timeout_at(deadline, released).await.is_err() {
+                return None;
+            }
+        }
+    }
+
+    fn release(&self, latency: Duration, saturated: bool) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if let Some(target) = self.config.target_latency {
+            let (min, max) = (self.config.min_concurrency, self.config.max_concurrency);
+            let _ = self
+                .limit
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |limit| {
+                    if latency > target {
+                        Some((limit * 9 / 10).max(min))
+                    } else if saturated {
+                        Some((limit + 1).min(max))
+                    } else {
+                        None
+                    }
+                });
+        }
+        self.released.notify_one();
+    }
+
+    /// Suggested delay before shed requests are retried
+    pub fn retry_after(&self) -> Duration {
+        self.config.max_wait.max(Duration::from_millis(100))
+    }
+}
+
+/// Sheds requests with `ResourceExhausted` if the server is saturated instead of
+/// queueing them without bounds. Shed responses carry a retry hint in milliseconds.
+#[derive(Debug, Clone)]
+pub struct AdmissionLayer {
+    limiter: Arc<AdmissionLimiter>,
+}
+
+impl AdmissionLayer {
+    pub fn new(config: AdmissionConfig) -> Self {
+        AdmissionLayer {
+            limiter: Arc::new(AdmissionLimiter::new(config)),
+        }
+    }
+}
+
+impl<S> Layer<S> for AdmissionLayer {
+    type Service = AdmissionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AdmissionService {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AdmissionService<S> {
+    inner: S,
+    limiter: Arc<AdmissionLimiter>,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for AdmissionService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        // Clone of a ready service, see tower documentation
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+        Box::pin(async move {
+            let Some(_permit) = limiter.admit().await else {
+                let retry_after = limiter.retry_after().as_millis();
+                log::warn!(
+                    "Request to {} shed, {} requests in flight",
+                    request.uri().path(),
+                    limiter.in_flight()
+                );
+                let mut response = Status::resource_exhausted(format!(
+                    "Server overloaded, retry in {retry_after}ms"
+                ))
+                .to_http();
+                if let Ok(value) = HeaderValue::from_str(&retry_after.to_string()) {
+                    response.headers_mut().insert(RETRY_PUSHBACK_KEY, value);
+                }
+                return Ok(response);
+            };
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    // Responds after a fixed delay
+    #[derive(Clone)]
+    struct SlowService(Duration);
+
+    impl Service<Request<()>> for SlowService {
+        type Response = Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Request<()>) -> Self::Future {
+            let delay = self.0;
+            Box::pin(async move {
+                tokio::time::sleep(delay).await;
+                Ok(Response::new(tonic::body::empty_body()))
+            })
+        }
+    }
+
+    fn grpc_status(response: &Response<BoxBody>) -> tonic::Code {
+        response
+            .headers()
+            .get("grpc-status")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<i32>().ok())
+            .map_or(tonic::Code::Ok, tonic::Code::from)
+    }
+
+    #[tokio::test]
+    async fn test_admission_sheds_excess_requests() {
+        let config = AdmissionConfig {
+            max_concurrency: 2,
+            min_concurrency: 1,
+            max_queue: 2,
+            max_wait: Duration::from_millis(50),
+            target_latency: None,
+        };
+        let layer = AdmissionLayer::new(config);
+        let service = layer.layer(SlowService(Duration::from_millis(500)));
+
+        let handles = (0..10)
+            .map(|_| {
+                let mut service = service.clone();
+                tokio::spawn(async move {
+                    let started = Instant::now();
+                    let response = service.call(Request::new(())).await.unwrap();
+                    (grpc_status(&response), response, started.elapsed())
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut served = 0;
+        for handle in handles {
+            let (code, response, elapsed) = handle.await.unwrap();
+            match code {
+                tonic::Code::Ok => served += 1,
+                tonic::Code::ResourceExhausted => {
+                    // Shed within the wait budget instead of waiting for the slow handlers
+                    assert!(elapsed < Duration::from_millis(400));
+                    assert!(response.headers().contains_key(RETRY_PUSHBACK_KEY));
+                }
+                code => panic!("Unexpected status {code:?}"),
+            }
+        }
+        assert_eq!(served, 2);
+        assert_eq!(layer.limiter.in_flight(), 0);
+        assert_eq!(layer.limiter.queued.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_limit() {
+        let config = AdmissionConfig {
+            max_concurrency: 10,
+            min_concurrency: 2,
+            max_queue: 0,
+            max_wait: Duration::from_millis(50),
+            target_latency: Some(Duration::from_millis(10)),
+        };
+        let limiter = Arc::new(AdmissionLimiter::new(config));
+
+        // Slow requests shrink the limit down to the minimum
+        for _ in 0..20 {
+            limiter.in_flight.fetch_add(1, Ordering::SeqCst);
+            limiter.release(Duration::from_millis(100), false);
+        }
+        assert_eq!(limiter.limit(), 2);
+
+        // Fast requests at the limit grow it again
+        let permits = (0..2)
+            .filter_map(|_| limiter.try_admit())
+            .collect::<Vec<_>>();
+        assert!(limiter.try_admit().is_none());
+        drop(permits);
+        assert_eq!(limiter.limit(), 3);
+    }
+}
@@ -1,9 +1,12 @@
 use crate::caching::cache::Cache;
-use crate::database::dsls::object_dsl::ObjectWithRelations;
+use crate::database::dsls::object_dsl::{KeyValue, KeyValueType, ObjectWithRelations};
 use crate::database::enums::ObjectType;
+use ahash::{HashMap, HashSet};
+use anyhow::{bail, Result};
 use aruna_rust_api::api::storage::services::v2::{
     CollectionRelations, DatasetRelations, ProjectRelations,
 };
+use diesel_ulid::DieselUlid;
 
 pub fn get_object_children(input: &ObjectWithRelations) -> Vec<String> {
     Vec::from_iter(
@@ -78,3 +81,60 @@ pub fn get_project_children(input: &ObjectWithRelations, cache: &Cache) -> Proje
         object_children,
     }
 }
+
+/// Validates that typed key values use the same type for a given key within all projects
+/// of `resource_id` (the resource itself or the parent of a new resource).
+/// Untyped key values are plain strings and are not checked.
+pub fn check_key_value_types(
+    cache: &Cache,
+    resource_id: Option<&DieselUlid>,
+    key_values: &[KeyValue],
+) -> Result<()> {
+    let mut types: HashMap<&str, KeyValueType> = HashMap::default();
+    for kv in key_values {
+        if let Some(value_type) = kv.value_type {
+            match types.insert(&kv.key, value_type) {
+                Some(existing) if existing != value_type => {
+                    bail!("Conflicting types for key {}", kv.key)
+                }
+                _ => {}
+            }
+        }
+    }
+    let Some(resource_id) = resource_id else {
+        return Ok(());
+    };
+    if types.is_empty() {
+        return Ok(());
+    }
+
+    // Last element of each upstream hierarchy is the project
+    let projects = cache
+        .upstream_dfs_iterative(resource_id)?
+        .into_iter()
+        .filter_map(|hierarchy| hierarchy.last().map(|project| project.into_inner()))
+        .collect::<HashSet<_>>();
+    for project_id in projects {
+        let mut resources = cache.get_subresources(&project_id)?;
+        resources.push(project_id);
+        for id in resources {
+            let Some(owr) = cache.get_object(&id) else {
+                continue;
+            };
+            for kv in &owr.object.key_values.0 .0 {
+                match (types.get(kv.key.as_str()), kv.value_type) {
+                    (Some(expected), Some(existing)) if *expected != existing => {
+                        bail!(
+                            "Key {} is already used with type {:?} in project {}",
+                            kv.key,
+                            existing,
+                            project_id
+                        )
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use tonic::codec::CompressionEncoding;
+
+lazy_static! {
+    pub static ref COMPRESSION_CONFIG: CompressionConfig = CompressionConfig::from_env();
+}
+
+/// Compression of gRPC responses. Responses are only compressed with encodings the
+/// client advertises in `grpc-accept-encoding`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressionConfig {
+    pub encodings: Vec<CompressionEncoding>,
+    // Smaller responses are sent uncompressed
+    pub min_size: usize,
+}
+
+impl CompressionConfig {
+    pub fn from_env() -> Self {
+        CompressionConfig {
+            encodings: parse_encodings(
+                &dotenvy::var("GRPC_COMPRESSION").unwrap_or_else(|_| "gzip,zstd".to_string()),
+            ),
+            min_size: dotenvy::var("GRPC_COMPRESSION_MIN_SIZE")
+                .ok()
+                .and_then(|var| var.trim().parse::<usize>().ok())
+                .unwrap_or(1024),
+        }
+    }
+
+    /// Disables the compression of responses below the minimum size
+    pub fn apply<T>(&self, response: &mut tonic::Response<T>, encoded_len: Option<usize>) {
+        if encoded_len.is_some_and(|len| len < self.min_size) {
+            response.disable_compression();
+        }
+    }
+}
+
+/// Parses a comma separated list of encodings, `none` disables compression
+fn parse_encodings(value: &str) -> Vec<CompressionEncoding> {
+    value
+        .split(',')
+        .filter_map(
+            |encoding| match encoding.trim().to_ascii_lowercase().as_str() {
+                "gzip" => Some(CompressionEncoding::Gzip),
+                "zstd" => Some(CompressionEncoding::Zstd),
+                "none" | "" => None,
+                other => {
+                    log::warn!("Unknown gRPC compression encoding {other} is ignored");
+                    None
+                }
+            },
+        )
+        .unique()
+        .collect()
+}
+
+/// Encoded size of a response. Responses of methods outside of the API are no
+/// protobuf messages, they have no size and are never sent by the generated servers.
+pub struct EncodedLen<'a, T>(pub &'a T);
+
+pub trait MessageLen {
+    fn message_len(&self) -> Option<usize>;
+}
+
+impl<T: prost::Message> MessageLen for EncodedLen<'_, T> {
+    fn message_len(&self) -> Option<usize> {
+        Some(prost::Message::encoded_len(self.0))
+    }
+}
+
+// Only used for types which are no messages, see `return_with_log!`
+pub trait OtherLen {
+    fn message_len(&self) -> Option<usize>;
+}
+
+impl<T> OtherLen for &EncodedLen<'_, T> {
+    fn message_len(&self) -> Option<usize> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_config() {
+        assert_eq!(
+            parse_encodings("gzip, ZSTD,gzip"),
+            vec![CompressionEncoding::Gzip, CompressionEncoding::Zstd]
+        );
+        assert!(parse_encodings("none").is_empty());
+        assert!(parse_encodings("brotli").is_empty());
+
+        // Messages are sized by their encoding, other responses are never skipped
+        let message = "a".repeat(2048);
+        assert_eq!((&EncodedLen(&message)).message_len(), Some(2051));
+        assert_eq!((&EncodedLen(&vec![message])).message_len(), None);
+    }
+}
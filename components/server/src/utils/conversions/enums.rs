@@ -1,7 +1,7 @@
 use crate::database::{
     dsls::{
         hook_dsl::{Filter, Method},
-        object_dsl::{Algorithm, Hashes, KeyValueVariant},
+        object_dsl::{Algorithm, Hashes, KeyValueType, KeyValueVariant},
     },
     enums::{
         DataClass, DataProxyFeature, DbPermissionLevel, EndpointStatus, EndpointVariant,
@@ -34,6 +34,33 @@ impl TryFrom<i32> for KeyValueVariant {
     }
 }
 
+// Typed key values are encoded as RDF typed literals, e.g. "1000^^xsd:integer"
+impl TryFrom<&str> for KeyValueType {
+    type Error = anyhow::Error;
+    fn try_from(type_name: &str) -> Result<Self> {
+        match type_name {
+            "xsd:string" => Ok(KeyValueType::STRING),
+            "xsd:integer" => Ok(KeyValueType::INTEGER),
+            "xsd:double" => Ok(KeyValueType::FLOAT),
+            "xsd:boolean" => Ok(KeyValueType::BOOL),
+            "xsd:dateTime" => Ok(KeyValueType::TIMESTAMP),
+            _ => Err(anyhow!("KeyValue type not defined.")),
+        }
+    }
+}
+
+impl From<KeyValueType> for &'static str {
+    fn from(value_type: KeyValueType) -> Self {
+        match value_type {
+            KeyValueType::STRING => "xsd:string",
+            KeyValueType::INTEGER => "xsd:integer",
+            KeyValueType::FLOAT => "xsd:double",
+            KeyValueType::BOOL => "xsd:boolean",
+            KeyValueType::TIMESTAMP => "xsd:dateTime",
+        }
+    }
+}
+
 impl TryFrom<APIDataClass> for DataClass {
     type Error = anyhow::Error;
 
@@ -297,6 +324,8 @@ impl From<EndpointStatus> for i32 {
             EndpointStatus::DEGRADED => 3,
             EndpointStatus::UNAVAILABLE => 4,
             EndpointStatus::MAINTENANCE => 5,
+            // Pending endpoints are not initialized from the users point of view
+            EndpointStatus::PENDING => 1,
         }
     }
 }
@@ -411,7 +440,17 @@ impl From<PersistentNotificationVariant> for PersonalNotificationVariant {
             PersistentNotificationVariant::PERMISSION_UPDATED => {
                 PersonalNotificationVariant::PermissionUpdated
             }
-            PersistentNotificationVariant::ANNOUNCEMENT => {
+            // User-scoped account events have no dedicated API variant (yet)
+            PersistentNotificationVariant::ANNOUNCEMENT
+            | PersistentNotificationVariant::TOKEN_EXPIRING
+            | PersistentNotificationVariant::QUOTA_THRESHOLD_REACHED
+            | PersistentNotificationVariant::PROJECT_DELETED
+            | PersistentNotificationVariant::OBJECT_QUARANTINED
+            | PersistentNotificationVariant::RELATION_LIMIT_APPROACHING
+            | PersistentNotificationVariant::PUBLICATION_REQUESTED
+            | PersistentNotificationVariant::PUBLICATION_DECIDED
+            | PersistentNotificationVariant::OBJECT_EXPIRED
+            | PersistentNotificationVariant::QUOTA_EXCEEDED => {
                 PersonalNotificationVariant::Announcement
             }
         }
@@ -426,6 +465,7 @@ impl TryFrom<APIFilter> for Filter {
                 FilterVariant::KeyValue(kv) => Ok(Filter::KeyValue(crate::database::dsls::object_dsl::KeyValue {
                     key: kv.key.clone(),
                     value: kv.value.clone(),
+                    value_type: None,
                     variant: match kv.variant() {
                         aruna_rust_api::api::storage::models::v2::KeyValueVariant::Unspecified => {
                             return Err(anyhow!("Invalid key value variant"));
@@ -453,9 +493,46 @@ impl TryFrom<APIFilter> for Filter {
 // Conversion tests
 #[cfg(test)]
 mod tests {
+    use crate::database::dsls::object_dsl::KeyValueType;
     use crate::database::enums::DataClass;
+    use crate::utils::conversions::objects::{format_typed_value, parse_typed_value};
     use aruna_rust_api::api::storage::models::v2::DataClass as APIDataClass;
 
+    #[test]
+    fn key_value_type_conversion_tests() {
+        // Untyped values stay plain strings
+        assert_eq!(
+            parse_typed_value("a^^b").unwrap(),
+            ("a^^b".to_string(), None)
+        );
+        assert_eq!(
+            parse_typed_value("1000^^xsd:integer").unwrap(),
+            ("1000".to_string(), Some(KeyValueType::INTEGER))
+        );
+        assert_eq!(
+            parse_typed_value("2024-01-01T12:00:00Z^^xsd:dateTime").unwrap(),
+            (
+                "2024-01-01T12:00:00Z".to_string(),
+                Some(KeyValueType::TIMESTAMP)
+            )
+        );
+        assert!(parse_typed_value("2024-01-01^^xsd:dateTime").is_ok());
+        assert!(parse_typed_value("1.5^^xsd:integer").is_err());
+        assert!(parse_typed_value("yes^^xsd:boolean").is_err());
+        assert!(parse_typed_value("1^^xsd:unknown").is_err());
+
+        // Type suffix round trip
+        for value in [
+            "1.5^^xsd:double",
+            "true^^xsd:boolean",
+            "text^^xsd:string",
+            "text",
+        ] {
+            let (raw, value_type) = parse_typed_value(value).unwrap();
+            assert_eq!(format_typed_value(&raw, value_type), value);
+        }
+    }
+
     #[test]
     fn data_class_conversion_tests() {
         // Direct enum conversion in both directions
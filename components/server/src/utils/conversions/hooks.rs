@@ -11,6 +11,9 @@ use crate::database::dsls::{
     hook_dsl::{Filter, Hook},
     object_dsl::KeyValueVariant,
 };
+use crate::hooks::preview_hook::PREVIEW_KEY;
+use crate::hooks::scan_hook::SCAN_VERDICT_KEY;
+use crate::hooks::validation_hook::VALIDATION_FAILED_KEY;
 
 impl From<Hook> for HookInfo {
     fn from(hook: Hook) -> HookInfo {
@@ -85,6 +88,27 @@ impl Hook {
                             relation: Some(relation.clone()),
                         })
                     }
+                    // The scan hook has no API representation, it reports its verdict as label
+                    crate::database::dsls::hook_dsl::InternalHook::Scan => {
+                        InternalAction::AddLabel(AddLabel {
+                            key: SCAN_VERDICT_KEY.to_string(),
+                            value: String::new(),
+                        })
+                    }
+                    // Like the scan hook, the preview hook links its result as label
+                    crate::database::dsls::hook_dsl::InternalHook::Preview => {
+                        InternalAction::AddLabel(AddLabel {
+                            key: PREVIEW_KEY.to_string(),
+                            value: String::new(),
+                        })
+                    }
+                    // Failed validations are reported as label
+                    crate::database::dsls::hook_dsl::InternalHook::Validate => {
+                        InternalAction::AddLabel(AddLabel {
+                            key: VALIDATION_FAILED_KEY.to_string(),
+                            value: String::new(),
+                        })
+                    }
                 };
                 APIHook {
                     hook_type: Some(HookType::InternalHook(InternalHook {
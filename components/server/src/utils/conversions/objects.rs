@@ -1,8 +1,8 @@
 use crate::caching::structs::ObjectWrapper;
 use crate::database::dsls::license_dsl::License;
 use crate::database::dsls::object_dsl::{
-    Author as DBAuthor, Hash as DBHash, Hashes, KeyValue as DBKeyValue, KeyValueVariant, KeyValues,
-    Object,
+    Author as DBAuthor, Hash as DBHash, Hashes, KeyValue as DBKeyValue, KeyValueType,
+    KeyValueVariant, KeyValues, Object,
 };
 use crate::database::dsls::workspaces_dsl::WorkspaceTemplate;
 use crate::database::dsls::{
@@ -11,7 +11,7 @@ use crate::database::dsls::{
 use crate::database::enums::ObjectType;
 use crate::middlelayer::create_request_types::Parent;
 use crate::utils::conversions::relations::from_db_internal_relation;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use aruna_rust_api::api::storage::models::v2::{
     generic_resource, relation::Relation as RelationEnum, Collection as GRPCCollection,
     Dataset as GRPCDataset, Hash, License as APILicense, Object as GRPCObject,
@@ -27,6 +27,7 @@ use aruna_rust_api::api::storage::services::v2::{
 use diesel_ulid::DieselUlid;
 use serde::ser::SerializeStruct;
 use serde::Serialize;
+use serde_json::Value as JsonValue;
 use std::str::FromStr;
 
 impl From<ObjectWrapper> for generic_resource::Resource {
@@ -318,13 +319,73 @@ impl TryFrom<&Vec<KeyValue>> for KeyValues {
 impl TryFrom<&KeyValue> for DBKeyValue {
     type Error = anyhow::Error;
     fn try_from(key_val: &KeyValue) -> Result<Self> {
+        let (value, value_type) = parse_typed_value(&key_val.value)?;
         Ok(DBKeyValue {
             key: key_val.key.clone(),
-            value: key_val.value.clone(),
+            value,
             variant: key_val.variant.try_into()?,
+            value_type,
         })
     }
 }
+
+/// Splits an optional RDF typed literal suffix (e.g. "1000^^xsd:integer") from a key value
+/// and validates the value against its type. Values without xsd suffix stay plain strings.
+pub fn parse_typed_value(value: &str) -> Result<(String, Option<KeyValueType>)> {
+    let Some((raw_value, type_name)) = value.rsplit_once("^^") else {
+        return Ok((value.to_string(), None));
+    };
+    if !type_name.starts_with("xsd:") {
+        return Ok((value.to_string(), None));
+    }
+    let value_type = KeyValueType::try_from(type_name)?;
+    let valid = match value_type {
+        KeyValueType::STRING => true,
+        KeyValueType::INTEGER => raw_value.parse::<i64>().is_ok(),
+        KeyValueType::FLOAT => raw_value.parse::<f64>().is_ok_and(|f| f.is_finite()),
+        KeyValueType::BOOL => raw_value.parse::<bool>().is_ok(),
+        KeyValueType::TIMESTAMP => parse_timestamp(raw_value).is_some(),
+    };
+    if !valid {
+        return Err(anyhow!(
+            "Invalid value for type {}: {}",
+            type_name,
+            raw_value
+        ));
+    }
+    Ok((raw_value.to_string(), Some(value_type)))
+}
+
+/// Re-appends the type suffix of typed key values for the API representation
+pub fn format_typed_value(value: &str, value_type: Option<KeyValueType>) -> String {
+    match value_type {
+        Some(value_type) => format!("{}^^{}", value, Into::<&str>::into(value_type)),
+        None => value.to_string(),
+    }
+}
+
+/// Converts a typed key value into its native json representation for the search index.
+/// Timestamps are converted to UNIX timestamps for filtering/sorting.
+pub fn typed_value_to_json(key_value: &DBKeyValue) -> Option<JsonValue> {
+    match key_value.value_type? {
+        KeyValueType::STRING => Some(JsonValue::from(key_value.value.clone())),
+        KeyValueType::INTEGER => key_value.value.parse::<i64>().ok().map(JsonValue::from),
+        KeyValueType::FLOAT => key_value.value.parse::<f64>().ok().map(JsonValue::from),
+        KeyValueType::BOOL => key_value.value.parse::<bool>().ok().map(JsonValue::from),
+        KeyValueType::TIMESTAMP => parse_timestamp(&key_value.value).map(JsonValue::from),
+    }
+}
+
+// Accepts RFC3339 timestamps or plain dates (YYYY-MM-DD) and returns UNIX seconds
+fn parse_timestamp(value: &str) -> Option<i64> {
+    if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.timestamp());
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc().timestamp())
+}
 impl From<KeyValues> for Vec<KeyValue> {
     //noinspection ALL
     fn from(keyval: KeyValues) -> Self {
@@ -332,8 +393,8 @@ impl From<KeyValues> for Vec<KeyValue> {
             .0
             .into_iter()
             .map(|kv| KeyValue {
+                value: format_typed_value(&kv.value, kv.value_type),
                 key: kv.key,
-                value: kv.value,
                 variant: match kv.variant {
                     KeyValueVariant::LABEL => 1,
                     KeyValueVariant::STATIC_LABEL => 2,
@@ -397,6 +458,8 @@ impl From<CreateLicenseRequest> for License {
             text: req.text,
 
             url: req.url,
+            embargo_until: None,
+            access_days: None,
         }
     }
 }
@@ -9,6 +9,7 @@ use crate::{
                 InternalRelation, INTERNAL_RELATION_VARIANT_BELONGS_TO,
                 INTERNAL_RELATION_VARIANT_DELETED, INTERNAL_RELATION_VARIANT_METADATA,
                 INTERNAL_RELATION_VARIANT_ORIGIN, INTERNAL_RELATION_VARIANT_POLICY,
+                INTERNAL_RELATION_VARIANT_PREVIEW, INTERNAL_RELATION_VARIANT_SYMLINK,
                 INTERNAL_RELATION_VARIANT_VERSION,
             },
             object_dsl::{
@@ -213,7 +214,17 @@ impl IntoRelationName for i32 {
             4 => Ok(INTERNAL_RELATION_VARIANT_METADATA.to_string()),
             5 => Ok(INTERNAL_RELATION_VARIANT_POLICY.to_string()),
             6 => Ok(INTERNAL_RELATION_VARIANT_DELETED.to_string()),
-            7 => Ok(name.ok_or_else(|| anyhow!("Custom relation variant not found"))?),
+            // Symlinks are only created with their own request to validate their name
+            7 => match name.ok_or_else(|| anyhow!("Custom relation variant not found"))? {
+                name if name == INTERNAL_RELATION_VARIANT_SYMLINK => {
+                    bail!("Symlinks can not be created as custom relations")
+                }
+                // Previews are only linked by the preview hook
+                name if name == INTERNAL_RELATION_VARIANT_PREVIEW => {
+                    bail!("Previews can not be created as custom relations")
+                }
+                name => Ok(name),
+            },
             _ => bail!("Invalid relation variant"),
         }
     }
@@ -6,6 +6,7 @@ use crate::database::{
     },
     enums::{DbPermissionLevel, ObjectMapping},
 };
+use aruna_common::session_token::SESSION_SCOPED_TOKEN_ATTRIBUTE;
 use aruna_rust_api::api::storage::{
     models::v2::{
         permission::ResourceId, CustomAttribute, DataProxyAttribute, OidcMapping, Permission,
@@ -72,6 +73,16 @@ pub fn convert_token_to_proto(token_id: &DieselUlid, db_token: APIToken) -> Toke
 
 impl From<DBUserAttributes> for UserAttributes {
     fn from(attr: DBUserAttributes) -> Self {
+        // Proxies require session tokens for the credentials of these tokens
+        let session_scoped = attr
+            .tokens
+            .iter()
+            .filter(|t| t.value().session_scoped)
+            .map(|t| CustomAttribute {
+                attribute_name: SESSION_SCOPED_TOKEN_ATTRIBUTE.to_string(),
+                attribute_value: t.key().to_string(),
+            })
+            .collect::<Vec<_>>();
         let tokens: Vec<Token> = attr
             .tokens
             .into_iter()
@@ -104,7 +115,8 @@ impl From<DBUserAttributes> for UserAttributes {
             custom_attributes: attr
                 .custom_attributes
                 .into_iter()
-                .map(|c| c.into())
+                .map(CustomAttribute::from)
+                .chain(session_scoped)
                 .collect(),
             personal_permissions,
             trusted_endpoints: attr
@@ -0,0 +1,236 @@
+use anyhow::{anyhow, bail, Result};
+use futures::{Stream, StreamExt};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::Sleep;
+use tonic::transport::server::{Connected, TcpIncoming};
+use tonic::transport::Server;
+
+/// Largest HTTP/2 flow control window (2^31 - 1)
+const MAX_WINDOW_SIZE: u32 = 2_147_483_647;
+/// HTTP/2 default flow control window, connection windows can not be smaller
+const DEFAULT_WINDOW_SIZE: u32 = 65_535;
+
+/// HTTP/2 settings of the gRPC server, unset values use the defaults of hyper
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcSettings {
+    pub keepalive_interval: Option<Duration>,
+    pub keepalive_timeout: Option<Duration>,
+    pub max_concurrent_streams: Option<u32>,
+    pub initial_stream_window_size: Option<u32>,
+    pub initial_connection_window_size: Option<u32>,
+    pub max_connection_age: Option<Duration>,
+}
+
+impl Default for GrpcSettings {
+    fn default() -> Self {
+        GrpcSettings {
+            keepalive_interval: Some(Duration::from_secs(15)),
+            keepalive_timeout: None,
+            max_concurrent_streams: None,
+            initial_stream_window_size: None,
+            initial_connection_window_size: None,
+            max_connection_age: None,
+        }
+    }
+}
+
+fn var<T: FromStr>(key: &str) -> Result<Option<T>> {
+    match dotenvy::var(key) {
+        Ok(value) if !value.trim().is_empty() => Ok(Some(
+            value
+                .trim()
+                .parse::<T>()
+                .map_err(|_| anyhow!("Invalid value for {key}"))?,
+        )),
+        _ => Ok(None),
+    }
+}
+
+impl GrpcSettings {
+    pub fn from_env() -> Result<Self> {
+        let default = GrpcSettings::default();
+        let settings = GrpcSettings {
+            // Keepalive pings are disabled with an interval of 0
+            keepalive_interval: match var::<u64>("GRPC_KEEPALIVE_INTERVAL")? {
+                Some(0) => None,
+                Some(secs) => Some(Duration::from_secs(secs)),
+                None => default.keepalive_interval,
+            },
+            keepalive_timeout: var::<u64>("GRPC_KEEPALIVE_TIMEOUT")?.map(Duration::from_secs),
+            max_concurrent_streams: var("GRPC_MAX_CONCURRENT_STREAMS")?,
+            initial_stream_window_size: var("GRPC_INITIAL_STREAM_WINDOW_SIZE")?,
+            initial_connection_window_size: var("GRPC_INITIAL_CONNECTION_WINDOW_SIZE")?,
+            max_connection_age: var::<u64>("GRPC_MAX_CONNECTION_AGE")?.map(Duration::from_secs),
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.keepalive_timeout == Some(Duration::ZERO) {
+            bail!("GRPC_KEEPALIVE_TIMEOUT must be at least 1");
+        }
+        if self.keepalive_timeout.is_some() && self.keepalive_interval.is_none() {
+            bail!("GRPC_KEEPALIVE_TIMEOUT needs keepalive pings");
+        }
+        if self.max_concurrent_streams == Some(0) {
+            bail!("GRPC_MAX_CONCURRENT_STREAMS must be at least 1");
+        }
+        if self
+            .initial_stream_window_size
+            .is_some_and(|size| size == 0 || size > MAX_WINDOW_SIZE)
+        {
+            bail!("GRPC_INITIAL_STREAM_WINDOW_SIZE must be between 1 and {MAX_WINDOW_SIZE}");
+        }
+        if self
+            .initial_connection_window_size
+            .is_some_and(|size| !(DEFAULT_WINDOW_SIZE..=MAX_WINDOW_SIZE).contains(&size))
+        {
+            bail!(
+                "GRPC_INITIAL_CONNECTION_WINDOW_SIZE must be between {DEFAULT_WINDOW_SIZE} and {MAX_WINDOW_SIZE}"
+            );
+        }
+        if self.max_connection_age == Some(Duration::ZERO) {
+            bail!("GRPC_MAX_CONNECTION_AGE must be at least 1");
+        }
+        Ok(())
+    }
+
+    pub fn log(&self) {
+        log::info!(
+            "gRPC settings: keepalive_interval={:?} keepalive_timeout={:?} max_concurrent_streams={:?} \
+            initial_stream_window_size={:?} initial_connection_window_size={:?} max_connection_age={:?}",
+            self.keepalive_interval,
+            self.keepalive_timeout,
+            self.max_concurrent_streams,
+            self.initial_stream_window_size,
+            self.initial_connection_window_size,
+            self.max_connection_age,
+        );
+    }
+
+    pub fn builder(&self) -> Server {
+        Server::builder()
+            .http2_keepalive_interval(self.keepalive_interval)
+            .http2_keepalive_timeout(self.keepalive_timeout)
+            .max_concurrent_streams(self.max_concurrent_streams)
+            .initial_stream_window_size(self.initial_stream_window_size)
+            .initial_connection_window_size(self.initial_connection_window_size)
+    }
+
+    /// Accepted connections, which are closed after the maximum connection age
+    pub fn incoming(
+        &self,
+        addr: SocketAddr,
+    ) -> Result<
+        impl Stream<
+            Item = std::io::Result<
+                AgedConnection<impl AsyncRead + AsyncWrite + Connected + Unpin + Send + 'static>,
+            >,
+        >,
+    > {
+        let max_age = self.max_connection_age;
+        let incoming = TcpIncoming::new(addr, false, None).map_err(|err| anyhow!(err))?;
+        Ok(incoming.map(move |conn| conn.map(|stream| AgedConnection::new(stream, max_age))))
+    }
+}
+
+/// Connection which reads EOF after its maximum age, which makes the server close it.
+/// Clients have to reconnect, e.g. to be balanced onto other instances.
+pub struct AgedConnection<IO> {
+    inner: IO,
+    deadline: Option<Pin<Box<Sleep>>>,
+}
+
+impl<IO> AgedConnection<IO> {
+    fn new(inner: IO, max_age: Option<Duration>) -> Self {
+        AgedConnection {
+            inner,
+            deadline: max_age.map(|age| Box::pin(tokio::time::sleep(age))),
+        }
+    }
+}
+
+impl<IO: Connected> Connected for AgedConnection<IO> {
+    type ConnectInfo = IO::ConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.inner.connect_info()
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for AgedConnection<IO> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if let Some(deadline) = self.deadline.as_mut() {
+            if deadline.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(Ok(()));
+            }
+        }
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for AgedConnection<IO> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grpc_settings_validation() {
+        assert!(GrpcSettings::default().validate().is_ok());
+        assert!(GrpcSettings {
+            max_concurrent_streams: Some(0),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(GrpcSettings {
+            initial_connection_window_size: Some(1024),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(GrpcSettings {
+            keepalive_interval: None,
+            keepalive_timeout: Some(Duration::from_secs(20)),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(GrpcSettings {
+            initial_stream_window_size: Some(1048576),
+            initial_connection_window_size: Some(1048576),
+            max_connection_age: Some(Duration::from_secs(600)),
+            ..Default::default()
+        }
+        .validate()
+        .is_ok());
+    }
+}
@@ -1,8 +1,28 @@
 use crate::caching::cache::Cache;
 use crate::database::dsls::internal_relation_dsl::InternalRelation;
+use crate::database::dsls::metadata_schema_dsl::SchemaViolations;
 use crate::database::dsls::object_dsl::ObjectWithRelations;
 use crate::database::enums::{DbPermissionLevel, ObjectType};
 use crate::grpc::users::UserServiceImpl;
+use crate::middlelayer::create_request_types::{
+    ExistingObjectMode, IF_EXISTS_KEY, NAME_RESERVATION_KEY,
+};
+use crate::middlelayer::delete_request_types::{DeleteOnHold, DeleteTooEarly};
+use crate::middlelayer::manifest_request_types::ManifestImmutable;
+use crate::middlelayer::presigned_url_handler::{
+    ContentDisposition, DispositionType, PartPlan, CONTENT_DISPOSITION_KEY, CONTENT_LENGTH_KEY,
+    CONTENT_MD5_KEY, DOWNLOAD_FILENAME_KEY, PART_COUNT_KEY, PART_SIZE_KEY, RESTRICT_TO_CIDR_KEY,
+    SINGLE_USE_KEY,
+};
+use crate::middlelayer::relations_db_handler::RelationLimitExceeded;
+use crate::middlelayer::requester_pays_request_types::REQUEST_PAYER_KEY;
+use crate::middlelayer::staging_db_handler::UploadLimitExceeded;
+use crate::search::meilisearch_client::INHERITED_LABELS_KEY;
+use crate::utils::pagination_utils::{
+    PageInfo, PageRequest, HAS_NEXT_PAGE_KEY, NEXT_CURSOR_KEY, PAGE_CURSOR_KEY, PAGE_SIZE_KEY,
+    TOTAL_COUNT_KEY, WITH_TOTAL_COUNT_KEY,
+};
+use crate::utils::user_notification_utils::QuotaExceeded;
 use crate::{auth::structs::Context, database::enums::ObjectMapping};
 use anyhow::{anyhow, Result as AnyhowResult};
 use aruna_rust_api::api::storage::models::v2::relation::Relation as RelationEnum;
@@ -12,10 +32,12 @@ use aruna_rust_api::api::storage::models::v2::{
 };
 use base64::{engine::general_purpose, Engine};
 use diesel_ulid::DieselUlid;
+use ipnet::IpNet;
 use rusty_ulid::DecodingError;
+use std::net::IpAddr;
 use std::str::FromStr;
 use std::sync::Arc;
-use tonic::metadata::MetadataMap;
+use tonic::metadata::{MetadataMap, MetadataValue};
 use tonic::{Result, Status};
 use xxhash_rust::xxh3::xxh3_128;
 
@@ -340,7 +362,221 @@ pub fn get_id_and_ctx(ids: Vec<String>) -> Result<(Vec<DieselUlid>, Vec<Context>
 pub fn query(cache: &Arc<Cache>, id: &DieselUlid) -> Result<generic_resource::Resource, Status> {
     cache
         .get_protobuf_object(id)
-        .ok_or_else(|| Status::not_found("Resource not found"))
+        .ok_or_else(|| not_found(cache, "Resource not found"))
+}
+
+/// Resources missing in the cache may not be loaded yet while the cache is warming up
+pub fn not_found(cache: &Cache, message: &str) -> Status {
+    if cache.is_warming() {
+        Status::unavailable("Cache is warming up, resource is not loaded yet")
+    } else {
+        Status::not_found(message)
+    }
+}
+
+/// Exceeded relation maximums, project quotas and upload limits are reported as
+/// ResourceExhausted, labels violating the metadata schema as InvalidArgument, all other
+/// errors as internal
+pub fn write_error_status(err: anyhow::Error, message: &str) -> Status {
+    if err.is::<SchemaViolations>() {
+        return metadata_schema_status(err, message);
+    }
+    log::error!(
+        "[{}] {}",
+        crate::utils::request_id_utils::current_request_id().unwrap_or_default(),
+        err
+    );
+    if let Some(exceeded) = err.downcast_ref::<QuotaExceeded>() {
+        return Status::resource_exhausted(exceeded.to_string());
+    }
+    if let Some(exceeded) = err.downcast_ref::<UploadLimitExceeded>() {
+        return Status::resource_exhausted(exceeded.to_string());
+    }
+    match err.downcast_ref::<RelationLimitExceeded>() {
+        Some(exceeded) => Status::resource_exhausted(exceeded.to_string()),
+        None => Status::internal(format!("{} : {}", message, err)),
+    }
+}
+
+/// Labels violating the metadata schema of a project are reported as InvalidArgument
+/// with all violated fields, changes of immutable signed objects as FailedPrecondition,
+/// all other errors as internal
+pub fn metadata_schema_status(err: anyhow::Error, message: &str) -> Status {
+    log::error!(
+        "[{}] {}",
+        crate::utils::request_id_utils::current_request_id().unwrap_or_default(),
+        err
+    );
+    if let Some(immutable) = err.downcast_ref::<ManifestImmutable>() {
+        return Status::failed_precondition(immutable.to_string());
+    }
+    match err.downcast_ref::<SchemaViolations>() {
+        Some(violations) => Status::invalid_argument(violations.to_string()),
+        None => Status::internal(format!("{} : {}", message, err)),
+    }
+}
+
+/// Deletes of too young objects or objects under legal hold are reported as
+/// FailedPrecondition, all other errors as internal
+pub fn delete_status(err: anyhow::Error, message: &str) -> Status {
+    log::error!(
+        "[{}] {}",
+        crate::utils::request_id_utils::current_request_id().unwrap_or_default(),
+        err
+    );
+    if let Some(on_hold) = err.downcast_ref::<DeleteOnHold>() {
+        return Status::failed_precondition(on_hold.to_string());
+    }
+    match err.downcast_ref::<DeleteTooEarly>() {
+        Some(too_early) => Status::failed_precondition(too_early.to_string()),
+        None => Status::internal(format!("{} : {}", message, err)),
+    }
+}
+
+/// Extracts the optional client CIDR restriction for presigned download urls from the metadata.
+/// Single ip addresses are converted into host networks.
+pub fn get_cidr_restriction_from_md(md: &MetadataMap) -> AnyhowResult<Option<IpNet>> {
+    let Some(value) = md.get(RESTRICT_TO_CIDR_KEY) else {
+        return Ok(None);
+    };
+    let value = value.to_str()?.trim();
+    let network = match IpNet::from_str(value) {
+        Ok(network) => network.trunc(),
+        Err(_) => IpNet::from(IpAddr::from_str(value)?),
+    };
+    Ok(Some(network))
+}
+
+/// Extracts the optional hex encoded md5 hash the data of a presigned upload url has to match.
+pub fn get_content_md5_from_md(md: &MetadataMap) -> AnyhowResult<Option<String>> {
+    let Some(value) = md.get(CONTENT_MD5_KEY) else {
+        return Ok(None);
+    };
+    Ok(Some(value.to_str()?.trim().to_string()))
+}
+
+/// Extracts how object creation handles existing objects with the same name from the metadata.
+pub fn get_if_exists_from_md(md: &MetadataMap) -> AnyhowResult<ExistingObjectMode> {
+    match md.get(IF_EXISTS_KEY) {
+        Some(value) => ExistingObjectMode::from_str(value.to_str()?),
+        None => Ok(ExistingObjectMode::default()),
+    }
+}
+
+/// Extracts the optional token of the name reservation object creation uses from the metadata.
+pub fn get_name_reservation_from_md(md: &MetadataMap) -> AnyhowResult<Option<DieselUlid>> {
+    let Some(value) = md.get(NAME_RESERVATION_KEY) else {
+        return Ok(None);
+    };
+    Ok(Some(DieselUlid::from_str(value.to_str()?.trim())?))
+}
+
+/// Extracts the optional declared size of an upload from the metadata.
+pub fn get_content_length_from_md(md: &MetadataMap) -> AnyhowResult<Option<u64>> {
+    let Some(value) = md.get(CONTENT_LENGTH_KEY) else {
+        return Ok(None);
+    };
+    Ok(Some(value.to_str()?.trim().parse::<u64>()?))
+}
+
+/// Extracts the requested page of a list response from the metadata.
+pub fn get_page_request_from_md(md: &MetadataMap) -> AnyhowResult<PageRequest> {
+    let page_size = match md.get(PAGE_SIZE_KEY) {
+        Some(value) => Some(value.to_str()?.trim().parse::<usize>()?),
+        None => None,
+    };
+    let cursor = match md.get(PAGE_CURSOR_KEY) {
+        Some(value) => Some(value.to_str()?.trim().to_string()),
+        None => None,
+    };
+    let with_total_count = match md.get(WITH_TOTAL_COUNT_KEY) {
+        Some(value) => value.to_str()?.trim().parse::<bool>()?,
+        None => false,
+    };
+    PageRequest::new(page_size, cursor, with_total_count)
+}
+
+/// Response metadata with the pagination info of a list response.
+pub fn page_info_to_md(info: &PageInfo) -> MetadataMap {
+    let mut md = MetadataMap::new();
+    if let Some(total_count) = info.total_count {
+        md.insert(TOTAL_COUNT_KEY, total_count.into());
+    }
+    md.insert(
+        HAS_NEXT_PAGE_KEY,
+        if info.has_next_page {
+            MetadataValue::from_static("true")
+        } else {
+            MetadataValue::from_static("false")
+        },
+    );
+    if let Some(value) = info
+        .next_cursor
+        .as_ref()
+        .and_then(|cursor| MetadataValue::try_from(cursor.as_str()).ok())
+    {
+        md.insert(NEXT_CURSOR_KEY, value);
+    }
+    md
+}
+
+/// Response metadata with the recommended part size and count of a multipart upload.
+pub fn part_plan_to_md(plan: &PartPlan) -> MetadataMap {
+    let mut md = MetadataMap::new();
+    md.insert(PART_SIZE_KEY, plan.part_size.into());
+    md.insert(PART_COUNT_KEY, plan.part_count.into());
+    md
+}
+
+/// Extracts the optional disposition and filename of a presigned download url from the metadata.
+pub fn get_content_disposition_from_md(
+    md: &MetadataMap,
+) -> AnyhowResult<Option<ContentDisposition>> {
+    let disposition = match md.get(CONTENT_DISPOSITION_KEY) {
+        Some(value) => Some(DispositionType::from_str(value.to_str()?)?),
+        None => None,
+    };
+    let filename = match md.get_bin(DOWNLOAD_FILENAME_KEY) {
+        Some(value) => {
+            let filename = String::from_utf8(value.to_bytes()?.to_vec())?;
+            if filename.is_empty() || filename.chars().any(char::is_control) {
+                return Err(anyhow!("Invalid download filename"));
+            }
+            Some(filename)
+        }
+        None => None,
+    };
+    if disposition.is_none() && filename.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(ContentDisposition {
+        disposition,
+        filename,
+    }))
+}
+
+/// Checks if a search request opted into matching labels inherited from ancestors.
+pub fn get_inherited_labels_from_md(md: &MetadataMap) -> bool {
+    md.get(INHERITED_LABELS_KEY)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Checks if a download url request opted into a single-use url.
+pub fn get_single_use_from_md(md: &MetadataMap) -> bool {
+    md.get(SINGLE_USE_KEY)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Checks if a download request acknowledged the charges of requester pays objects.
+pub fn get_request_payer_from_md(md: &MetadataMap) -> bool {
+    md.get(REQUEST_PAYER_KEY)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().eq_ignore_ascii_case("requester"))
+        .unwrap_or(false)
 }
 
 pub fn get_token_from_md(md: &MetadataMap) -> AnyhowResult<String> {
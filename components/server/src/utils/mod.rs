@@ -1,6 +1,16 @@
+pub mod admission_utils;
 pub mod cache_utils;
+pub mod compression_utils;
 pub mod conversions;
 pub mod database_utils;
+pub mod grpc_settings;
 pub mod grpc_utils;
 pub mod mailclient;
+pub mod name_utils;
+pub mod pagination_utils;
+pub mod request_id_utils;
 pub mod search_utils;
+pub mod sequence_utils;
+pub mod traversal_utils;
+pub mod user_notification_utils;
+pub mod validation_utils;
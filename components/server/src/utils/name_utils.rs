@@ -0,0 +1,101 @@
+use crate::database::dsls::object_dsl::Object;
+use anyhow::{bail, Result};
+use std::str::FromStr;
+use unicode_normalization::UnicodeNormalization;
+
+/// Project key-value which configures how names of the project resources are compared
+pub const NAME_NORMALIZATION_KEY: &str = "app.aruna-storage.org/name-normalization";
+
+/// Normalization of resource names before they are compared.
+/// Resources keep their original name for display, but names are unique and looked up in
+/// their normalized form. Changing the policy of a project only affects names created afterwards.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NameNormalization {
+    // Names are compared byte by byte
+    None,
+    // Canonically equivalent names (e.g. precomposed and combining characters) are equal
+    #[default]
+    Nfc,
+    // Like NFC and additionally case-insensitive
+    NfcCaseFold,
+}
+
+impl FromStr for NameNormalization {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(NameNormalization::None),
+            "nfc" => Ok(NameNormalization::Nfc),
+            "nfc-casefold" => Ok(NameNormalization::NfcCaseFold),
+            _ => bail!("Invalid name normalization {s}, expected none, nfc or nfc-casefold"),
+        }
+    }
+}
+
+impl NameNormalization {
+    /// Parses the name normalization key-value of a project, projects without it use NFC
+    pub fn from_project(project: &Object) -> Result<Self> {
+        project
+            .key_values
+            .0
+             .0
+            .iter()
+            .find(|kv| kv.key == NAME_NORMALIZATION_KEY)
+            .map_or(Ok(NameNormalization::default()), |kv| {
+                NameNormalization::from_str(&kv.value)
+            })
+    }
+
+    pub fn normalize(&self, name: &str) -> String {
+        match self {
+            NameNormalization::None => name.to_string(),
+            NameNormalization::Nfc => name.nfc().collect(),
+            NameNormalization::NfcCaseFold => name
+                .nfc()
+                .collect::<String>()
+                .to_lowercase()
+                .nfc()
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_normalization() {
+        let precomposed = "caf\u{e9}";
+        let combining = "cafe\u{301}";
+        assert_ne!(precomposed, combining);
+
+        // Differently normalized inputs are the same name under NFC
+        assert_eq!(
+            NameNormalization::Nfc.normalize(precomposed),
+            NameNormalization::Nfc.normalize(combining)
+        );
+        assert_eq!(NameNormalization::Nfc.normalize(combining), precomposed);
+        assert_ne!(
+            NameNormalization::None.normalize(precomposed),
+            NameNormalization::None.normalize(combining)
+        );
+
+        // Case is only ignored with case folding
+        assert_ne!(
+            NameNormalization::Nfc.normalize("Data"),
+            NameNormalization::Nfc.normalize("data")
+        );
+        assert_eq!(
+            NameNormalization::NfcCaseFold.normalize("CAFE\u{301}"),
+            NameNormalization::NfcCaseFold.normalize("caf\u{e9}")
+        );
+
+        assert_eq!(
+            NameNormalization::from_str("nfc-casefold").unwrap(),
+            NameNormalization::NfcCaseFold
+        );
+        assert!(NameNormalization::from_str("NFKC").is_err());
+    }
+}
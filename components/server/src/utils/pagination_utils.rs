@@ -0,0 +1,154 @@
+use anyhow::{bail, Result};
+
+/// Metadata key with the maximum number of entries of a list response
+pub const PAGE_SIZE_KEY: &str = "x-aruna-page-size";
+/// Metadata key with the cursor after which the requested page starts
+pub const PAGE_CURSOR_KEY: &str = "x-aruna-page-cursor";
+/// Metadata key which requests the total number of entries, e.g. `true`
+pub const WITH_TOTAL_COUNT_KEY: &str = "x-aruna-with-total-count";
+/// Response metadata key with the total number of entries
+pub const TOTAL_COUNT_KEY: &str = "x-aruna-total-count";
+/// Response metadata key which tells if more entries follow the returned page
+pub const HAS_NEXT_PAGE_KEY: &str = "x-aruna-has-next-page";
+/// Response metadata key with the cursor of the next page
+pub const NEXT_CURSOR_KEY: &str = "x-aruna-next-cursor";
+
+/// Page of a list response requested via metadata.
+/// Lists without page size and cursor are returned completely and in their original order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageRequest {
+    pub page_size: Option<usize>,
+    pub cursor: Option<String>,
+    pub with_total_count: bool,
+}
+
+impl PageRequest {
+    pub fn new(
+        page_size: Option<usize>,
+        cursor: Option<String>,
+        with_total_count: bool,
+    ) -> Result<Self> {
+        if page_size == Some(0) {
+            bail!("Page size must be greater than zero");
+        }
+        Ok(PageRequest {
+            page_size,
+            cursor: cursor.filter(|cursor| !cursor.is_empty()),
+            with_total_count,
+        })
+    }
+
+    fn is_paged(&self) -> bool {
+        self.page_size.is_some() || self.cursor.is_some()
+    }
+}
+
+/// Pagination metadata of a list response. The total count is only
+/// included if it was requested.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageInfo {
+    pub total_count: Option<u64>,
+    pub has_next_page: bool,
+    pub next_cursor: Option<String>,
+}
+
+/// Cuts the requested page out of a complete list. Paged lists are ordered by
+/// `key`, the cursor is the key of the last entry of the previous page.
+/// Counting uses the already loaded list, no additional queries are needed.
+pub fn paginate<T, F: Fn(&T) -> String>(
+    mut entries: Vec<T>,
+    page: &PageRequest,
+    key: F,
+) -> (Vec<T>, PageInfo) {
+    let total_count = page.with_total_count.then_some(entries.len() as u64);
+    if !page.is_paged() {
+        return (
+            entries,
+            PageInfo {
+                total_count,
+                ..Default::default()
+            },
+        );
+    }
+
+    entries.sort_by_key(&key);
+    let start = match &page.cursor {
+        Some(cursor) => entries.partition_point(|entry| key(entry) <= *cursor),
+        None => 0,
+    };
+    let mut entries = entries.split_off(start);
+    let has_next_page = page
+        .page_size
+        .is_some_and(|page_size| entries.len() > page_size);
+    if let Some(page_size) = page.page_size {
+        entries.truncate(page_size);
+    }
+    let next_cursor = if has_next_page {
+        entries.last().map(&key)
+    } else {
+        None
+    };
+    (
+        entries,
+        PageInfo {
+            total_count,
+            has_next_page,
+            next_cursor,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(page_size: Option<usize>, cursor: Option<&str>) -> PageRequest {
+        PageRequest::new(page_size, cursor.map(str::to_string), true).unwrap()
+    }
+
+    #[test]
+    fn test_paginate_boundaries() {
+        let entries = vec!["d", "b", "a", "c"];
+        let key = |entry: &&str| entry.to_string();
+
+        // Page size equal to the number of entries has no next page
+        let (result, info) = paginate(entries.clone(), &page(Some(4), None), key);
+        assert_eq!(result, vec!["a", "b", "c", "d"]);
+        assert_eq!(
+            info,
+            PageInfo {
+                total_count: Some(4),
+                has_next_page: false,
+                next_cursor: None,
+            }
+        );
+
+        // One entry less leaves exactly one entry for the next page
+        let (result, info) = paginate(entries.clone(), &page(Some(3), None), key);
+        assert_eq!(result, vec!["a", "b", "c"]);
+        assert!(info.has_next_page);
+        assert_eq!(info.next_cursor.as_deref(), Some("c"));
+        let (result, info) = paginate(entries.clone(), &page(Some(3), Some("c")), key);
+        assert_eq!(result, vec!["d"]);
+        assert!(!info.has_next_page);
+        assert_eq!(info.next_cursor, None);
+        assert_eq!(info.total_count, Some(4));
+
+        // Pages which end on the last entry have no next page
+        let (result, info) = paginate(entries.clone(), &page(Some(2), Some("b")), key);
+        assert_eq!(result, vec!["c", "d"]);
+        assert!(!info.has_next_page);
+
+        // Cursors after the last entry return an empty page
+        let (result, info) = paginate(entries.clone(), &page(Some(2), Some("d")), key);
+        assert!(result.is_empty());
+        assert!(!info.has_next_page);
+
+        // Unpaged lists keep their order and are not counted unless requested
+        let (result, info) = paginate(entries.clone(), &PageRequest::default(), key);
+        assert_eq!(result, entries);
+        assert_eq!(info, PageInfo::default());
+
+        assert!(PageRequest::new(Some(0), None, false).is_err());
+    }
+}
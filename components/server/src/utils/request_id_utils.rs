@@ -0,0 +1,143 @@
+use diesel_ulid::DieselUlid;
+use std::task::{Context, Poll};
+use tonic::codegen::http::{HeaderValue, Request, Response};
+use tonic::codegen::{BoxFuture, Service};
+use tower::Layer;
+
+/// Metadata key of the correlation id of a request
+pub const REQUEST_ID_KEY: &str = "x-request-id";
+// Longer ids are replaced to keep logs and headers readable
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Returns the correlation id of the request currently handled by this task
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Runs a future with the provided correlation id, e.g. for work
+/// detached from the original request like hook executions.
+pub async fn with_request_id<F: std::future::Future>(
+    request_id: Option<String>,
+    future: F,
+) -> F::Output {
+    match request_id {
+        Some(request_id) => REQUEST_ID.scope(request_id, future).await,
+        None => future.await,
+    }
+}
+
+/// Takes the valid correlation id of incoming request headers or mints a new one
+pub fn request_id_from_header(value: Option<&HeaderValue>) -> String {
+    value
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LENGTH)
+        .map(str::to_string)
+        .unwrap_or_else(|| DieselUlid::generate().to_string())
+}
+
+/// Propagates the `x-request-id` of incoming requests or mints a new one.
+/// The id is available to handlers via `current_request_id()` and its metadata,
+/// and is echoed back in the response metadata (including error responses).
+#[derive(Debug, Clone, Default)]
+pub struct RequestIdLayer;
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for RequestIdService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let request_id = request_id_from_header(request.headers().get(REQUEST_ID_KEY));
+        let header = HeaderValue::from_str(&request_id).ok();
+        if let Some(header) = &header {
+            request.headers_mut().insert(REQUEST_ID_KEY, header.clone());
+        }
+
+        // Clone of a ready service, see tower documentation
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(REQUEST_ID.scope(request_id, async move {
+            let mut response = inner.call(request).await?;
+            if let Some(header) = header {
+                response.headers_mut().insert(REQUEST_ID_KEY, header);
+            }
+            Ok(response)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::str::FromStr;
+
+    // Responds with the correlation id visible to the handler
+    #[derive(Clone)]
+    struct EchoService;
+
+    impl Service<Request<()>> for EchoService {
+        type Response = Response<String>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Request<()>) -> Self::Future {
+            Box::pin(async { Ok(Response::new(current_request_id().unwrap_or_default())) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_id_echoed() {
+        let mut service = RequestIdLayer.layer(EchoService);
+
+        // Incoming id is propagated
+        let request = Request::builder()
+            .header(REQUEST_ID_KEY, "my-request")
+            .body(())
+            .unwrap();
+        let response = service.call(request).await.unwrap();
+        assert_eq!(
+            response.headers().get(REQUEST_ID_KEY).unwrap(),
+            "my-request"
+        );
+        assert_eq!(response.body(), "my-request");
+
+        // Missing id is minted
+        let response = service.call(Request::new(())).await.unwrap();
+        let request_id = response.headers().get(REQUEST_ID_KEY).unwrap();
+        assert!(DieselUlid::from_str(request_id.to_str().unwrap()).is_ok());
+        assert_eq!(response.body(), request_id.to_str().unwrap());
+        assert!(current_request_id().is_none());
+    }
+}
@@ -2,11 +2,22 @@ use crate::caching::cache::Cache;
 use crate::database::connection::Database;
 use crate::database::crud::CrudDb;
 use crate::database::dsls::object_dsl::Object;
+use crate::database::dsls::search_reindex_dsl::SearchReindexCheckpoint;
 use crate::database::enums::{DataClass, ObjectStatus};
-use crate::search::meilisearch_client::{MeilisearchClient, MeilisearchIndexes, ObjectDocument};
+use crate::search::meilisearch_client::{
+    staging_index_name, MeilisearchClient, MeilisearchIndexes, ObjectDocument, ReindexProgress,
+};
+use chrono::Utc;
 use diesel_ulid::DieselUlid;
 use itertools::Itertools;
 use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_REINDEX_BATCH_SIZE: i64 = 10000;
+// Maximum time Meilisearch may take to index a single batch
+const REINDEX_BATCH_TIMEOUT: Duration = Duration::from_secs(600);
+// Queued index updates delivered at once after an outage
+const INDEX_QUEUE_BATCH_SIZE: i64 = 1000;
 
 /// Removes the specific resources from the search index
 pub async fn remove_from_search_index(
@@ -15,23 +26,105 @@ pub async fn remove_from_search_index(
 ) {
     let client_clone = search_client.clone();
     tokio::spawn(async move {
+        // Queue deletions while Meilisearch is unavailable
+        if !client_clone.is_available() {
+            queue_index_updates(&client_clone, &index_updates, true).await;
+            return;
+        }
+
         if let Err(err) = client_clone
             .delete_stuff::<DieselUlid>(index_updates.as_slice(), MeilisearchIndexes::OBJECT)
             .await
         {
-            log::warn!("Search index update failed: {}", err)
+            log::warn!("Search index update failed: {}", err);
+            if MeilisearchClient::is_connection_error(&err) {
+                client_clone.set_available(false);
+                queue_index_updates(&client_clone, &index_updates, true).await;
+            }
         }
     });
 }
 
+// Updates which could not be queued are caught up by the next full sync
+async fn queue_index_updates(
+    search_client: &MeilisearchClient,
+    ids: &[DieselUlid],
+    deletion: bool,
+) {
+    if let Err(err) = search_client.queue_index_updates(ids, deletion).await {
+        log::error!("Queueing search index updates failed: {}", err);
+    }
+}
+
 /// Updates the resource search index in a background thread.
 pub async fn update_search_index(
     search_client: &Arc<MeilisearchClient>,
     cache: &Arc<Cache>,
     index_updates: Vec<ObjectDocument>,
 ) {
-    // Remove confidential/workspace objects and add stats
-    let final_updates = index_updates
+    let final_updates = prepare_index_updates(cache, index_updates);
+
+    // Update remaining objects in search index
+    let client_clone = search_client.clone();
+    tokio::spawn(async move {
+        // Queue updates while Meilisearch is unavailable
+        let ids = final_updates.iter().map(|od| od.id).collect_vec();
+        if !client_clone.is_available() {
+            queue_index_updates(&client_clone, &ids, false).await;
+            return;
+        }
+
+        if let Err(err) = client_clone
+            .add_or_update_stuff::<ObjectDocument>(
+                final_updates.as_slice(),
+                MeilisearchIndexes::OBJECT,
+            )
+            .await
+        {
+            log::warn!("Search index update failed: {}", err);
+            if MeilisearchClient::is_connection_error(&err) {
+                client_clone.set_available(false);
+                queue_index_updates(&client_clone, &ids, false).await;
+            }
+        }
+    });
+}
+
+/// Re-indexes all subresources of a resource whose labels changed, so that
+/// their effective labels contain the current labels of their ancestors.
+///
+/// Materializing inherited labels keeps label queries on the index cheap but
+/// increases the index size and lets a label change on a project or collection
+/// re-index its whole subtree.
+pub async fn update_inherited_labels(
+    search_client: &Arc<MeilisearchClient>,
+    cache: &Arc<Cache>,
+    resource_id: &DieselUlid,
+) {
+    let subresources = match cache.get_subresources(resource_id) {
+        Ok(subresources) => subresources,
+        Err(err) => {
+            log::warn!(
+                "Fetching subresources for label propagation failed: {}",
+                err
+            );
+            return;
+        }
+    };
+    if subresources.is_empty() {
+        return;
+    }
+
+    let index_updates = subresources
+        .iter()
+        .filter_map(|id| cache.get_object_document(id))
+        .collect_vec();
+    update_search_index(search_client, cache, index_updates).await;
+}
+
+/// Removes confidential/workspace objects and adds the current stats and inherited labels.
+fn prepare_index_updates(cache: &Cache, index_updates: Vec<ObjectDocument>) -> Vec<ObjectDocument> {
+    index_updates
         .into_iter()
         .filter_map(|mut od| match od.data_class {
             DataClass::PUBLIC | DataClass::PRIVATE => {
@@ -41,55 +134,291 @@ pub async fn update_search_index(
                         od.size = stats.size;
                     }
                 }
+                od.effective_labels = od
+                    .labels
+                    .iter()
+                    .cloned()
+                    .chain(cache.get_inherited_labels(&od.id))
+                    .collect();
                 Some(od)
             }
             _ => None,
         })
-        .collect::<Vec<_>>();
+        .collect::<Vec<_>>()
+}
 
-    // Update remaining objects in search index
-    let client_clone = search_client.clone();
-    tokio::spawn(async move {
-        if let Err(err) = client_clone
-            .add_or_update_stuff::<ObjectDocument>(
-                final_updates.as_slice(),
-                MeilisearchIndexes::OBJECT,
-            )
-            .await
+/// Converts searchable objects into their index document with current stats and inherited labels
+fn to_index_document(cache: &Cache, mut object: Object) -> Option<ObjectDocument> {
+    if !matches!(object.data_class, DataClass::PUBLIC | DataClass::PRIVATE)
+        || object.object_status == ObjectStatus::DELETED
+    {
+        return None;
+    }
+    if let Some(stats) = cache.get_object_stats(&object.id) {
+        object.count = stats.count;
+        object.content_len = stats.size;
+    }
+    let mut od: ObjectDocument = object.into();
+    od.effective_labels
+        .extend(cache.get_inherited_labels(&od.id));
+    Some(od)
+}
+
+/// Full rebuild of a search index from the database. Documents are written into a
+/// staging index which atomically replaces the live index once it is complete, so
+/// queries are served by the previous index in the meantime. Progress is checkpointed
+/// after every batch and an interrupted reindex resumes from its last checkpoint.
+pub struct SearchReindex {
+    database_conn: Arc<Database>,
+    cache: Arc<Cache>,
+    search_client: Arc<MeilisearchClient>,
+    checkpoint: SearchReindexCheckpoint,
+    staging_index: String,
+    batch_size: i64,
+    progress: ReindexProgress,
+}
+
+impl SearchReindex {
+    /// Resumes the reindex of `index_name` from its checkpoint or starts a new one
+    pub async fn start(
+        database_conn: Arc<Database>,
+        cache: Arc<Cache>,
+        search_client: Arc<MeilisearchClient>,
+        index_name: &str,
+        batch_size: i64,
+    ) -> anyhow::Result<Self> {
+        let client = database_conn.get_client().await?;
+        let staging_index = staging_index_name(index_name);
+
+        // Queries are served by the live index until the reindex is finished
+        search_client
+            .get_or_create_index(index_name, Some("id"))
+            .await?;
+
+        // Checkpoints are only valid as long as their staging index exists
+        let checkpoint = match SearchReindexCheckpoint::get(index_name.to_string(), &client).await?
         {
-            log::warn!("Search index update failed: {}", err)
+            Some(checkpoint) if search_client.client.get_index(&staging_index).await.is_ok() => {
+                Some(checkpoint)
+            }
+            _ => None,
+        };
+        let resumed = checkpoint.is_some();
+        let checkpoint = match checkpoint {
+            Some(checkpoint) => {
+                log::info!(
+                    "Resuming search reindex of {} after {} resources",
+                    index_name,
+                    checkpoint.processed
+                );
+                checkpoint
+            }
+            None => {
+                // Staging index is created with the current index configuration
+                search_client.delete_index_if_exists(&staging_index).await?;
+                search_client
+                    .get_or_create_index(&staging_index, Some("id"))
+                    .await?;
+                let now = Utc::now().naive_utc();
+                let mut checkpoint = SearchReindexCheckpoint {
+                    index_name: index_name.to_string(),
+                    last_id: None,
+                    processed: 0,
+                    started_at: now,
+                    updated_at: now,
+                };
+                checkpoint.create(&client).await?;
+                checkpoint
+            }
+        };
+
+        let progress = ReindexProgress {
+            resumed,
+            last_id: checkpoint.last_id,
+            processed: checkpoint.processed as u64,
+            total: Object::count_all(&client).await? as u64,
+        };
+        search_client.set_reindex_progress(index_name, progress.clone());
+
+        Ok(SearchReindex {
+            database_conn,
+            cache,
+            search_client,
+            checkpoint,
+            staging_index,
+            batch_size: batch_size.max(1),
+            progress,
+        })
+    }
+
+    pub fn progress(&self) -> &ReindexProgress {
+        &self.progress
+    }
+
+    /// Indexes the next batch of resources and checkpoints the progress,
+    /// returns false once all resources are processed
+    pub async fn next_batch(&mut self) -> anyhow::Result<bool> {
+        let client = self.database_conn.get_client().await?;
+        let objects = Object::get_page(self.checkpoint.last_id, self.batch_size, &client).await?;
+        let Some(last_id) = objects.last().map(|object| object.id) else {
+            return Ok(false);
+        };
+        let processed = objects.len() as i64;
+
+        let documents = objects
+            .into_iter()
+            .filter_map(|object| to_index_document(&self.cache, object))
+            .collect_vec();
+        if !documents.is_empty() {
+            self.search_client
+                .add_and_wait(&self.staging_index, &documents, REINDEX_BATCH_TIMEOUT)
+                .await?;
         }
-    });
+
+        self.checkpoint.last_id = Some(last_id);
+        self.checkpoint.processed += processed;
+        self.checkpoint.updated_at = Utc::now().naive_utc();
+        self.checkpoint.create(&client).await?;
+
+        self.progress.last_id = self.checkpoint.last_id;
+        self.progress.processed = self.checkpoint.processed as u64;
+        self.search_client
+            .set_reindex_progress(&self.checkpoint.index_name, self.progress.clone());
+        Ok(true)
+    }
+
+    /// Replaces the live index with the completed staging index
+    pub async fn finish(self) -> anyhow::Result<()> {
+        let index_name = &self.checkpoint.index_name;
+
+        // Removed before the swap, so that the previous documents are never resumed
+        let client = self.database_conn.get_client().await?;
+        self.checkpoint.delete(&client).await?;
+
+        self.search_client
+            .swap_indexes(index_name, &self.staging_index)
+            .await?;
+        self.search_client.finish_reindex(index_name);
+        // Staging index contains the previous documents after the swap
+        self.search_client
+            .delete_index_if_exists(&self.staging_index)
+            .await?;
+
+        log::info!(
+            "Search reindex of {} finished with {} resources",
+            index_name,
+            self.progress.processed
+        );
+        Ok(())
+    }
 }
 
-/// Fetches all Objects from the database and full syncs the search index in
-/// chunks of 100.000 elements.
+/// Rebuilds the object search index from the database without clearing it,
+/// see [`SearchReindex`]. Only one reindex runs at a time per instance.
 pub async fn full_sync_search_index(
     database_conn: Arc<Database>,
     cache: Arc<Cache>,
     search_client: Arc<MeilisearchClient>,
 ) -> anyhow::Result<()> {
-    let client = database_conn.get_client().await?; // No transaction; only read
-    let filtered_objects: Vec<ObjectDocument> = Object::all(&client)
-        .await?
-        .into_iter()
-        .filter(|o| o.data_class == DataClass::PUBLIC || o.data_class == DataClass::PRIVATE)
-        .filter(|o| o.object_status != ObjectStatus::DELETED)
-        .map(|mut o| {
-            if let Some(stats) = cache.get_object_stats(&o.id) {
-                o.count = stats.count;
-                o.content_len = stats.size;
-            }
-            o.into()
-        })
-        .collect_vec();
+    let Some(_lock) = search_client.try_lock_reindex() else {
+        log::info!("Search reindex already running");
+        return Ok(());
+    };
+    let batch_size = dotenvy::var("SEARCH_REINDEX_BATCH_SIZE")
+        .ok()
+        .and_then(|size| size.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_REINDEX_BATCH_SIZE);
 
-    // Update search index in chunks of 100.000 Objects
-    for chunk in filtered_objects.chunks(100000) {
-        search_client
-            .add_or_update_stuff::<ObjectDocument>(chunk, MeilisearchIndexes::OBJECT)
+    let mut reindex = SearchReindex::start(
+        database_conn,
+        cache,
+        search_client,
+        &MeilisearchIndexes::OBJECT.to_string(),
+        batch_size,
+    )
+    .await?;
+    while reindex.next_batch().await? {
+        let progress = reindex.progress();
+        log::debug!(
+            "Search reindex: {} of {} resources",
+            progress.processed,
+            progress.total
+        );
+    }
+    reindex.finish().await
+}
+
+/// Delivers all queued index updates, updates are removed from the queue once delivered
+pub async fn deliver_queued_index_updates(
+    cache: &Arc<Cache>,
+    search_client: &Arc<MeilisearchClient>,
+) -> anyhow::Result<usize> {
+    let mut delivered = 0;
+    loop {
+        let queued = search_client
+            .queued_index_updates(INDEX_QUEUE_BATCH_SIZE)
             .await?;
+        if queued.is_empty() {
+            return Ok(delivered);
+        }
+        let (deletions, updates): (Vec<_>, Vec<_>) =
+            queued.iter().partition(|update| update.deletion);
+        let updates = prepare_index_updates(
+            cache,
+            updates
+                .iter()
+                .filter_map(|update| cache.get_object_document(&update.id))
+                .collect_vec(),
+        );
+        let deletions = deletions.iter().map(|update| update.id).collect_vec();
+        if !updates.is_empty() {
+            search_client
+                .add_or_update_stuff(&updates, MeilisearchIndexes::OBJECT)
+                .await?;
+        }
+        if !deletions.is_empty() {
+            search_client
+                .delete_stuff(&deletions, MeilisearchIndexes::OBJECT)
+                .await?;
+        }
+        search_client.dequeue_index_updates(&queued).await?;
+        delivered += queued.len();
     }
+}
+
+/// Periodically checks if an unavailable Meilisearch server has recovered.
+/// On recovery all queued index updates are delivered and a reconciliation
+/// full sync catches up updates which could not be queued.
+pub async fn start_search_recovery_loop(
+    database_conn: Arc<Database>,
+    cache: Arc<Cache>,
+    search_client: Arc<MeilisearchClient>,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
 
-    Ok(())
+            if search_client.is_available() || !search_client.client.is_healthy().await {
+                continue;
+            }
+            log::info!("Search index available again, delivering queued updates");
+            search_client.set_available(true);
+
+            let delivery = async {
+                search_client
+                    .get_or_create_index(&MeilisearchIndexes::OBJECT.to_string(), Some("id"))
+                    .await?;
+                deliver_queued_index_updates(&cache, &search_client).await?;
+                full_sync_search_index(database_conn.clone(), cache.clone(), search_client.clone())
+                    .await
+            };
+
+            // Undelivered updates stay queued for the next recovery
+            if let Err(err) = delivery.await {
+                log::warn!("Search index recovery failed: {}", err);
+                search_client.set_available(false);
+            }
+        }
+    });
 }
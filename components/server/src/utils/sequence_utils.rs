@@ -0,0 +1,70 @@
+use crate::database::connection::Database;
+use crate::database::dsls::sequence_dsl::Sequence;
+use ahash::RandomState;
+use anyhow::{bail, Result};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use std::ops::Range;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+lazy_static! {
+    /// Configured number of values reserved per database round trip
+    pub static ref SEQUENCE_BATCH_SIZE: i64 = dotenvy::var("SEQUENCE_BATCH_SIZE")
+        .map(|var| var.parse::<i64>().unwrap_or(100))
+        .unwrap_or(100)
+        .max(1);
+}
+
+/// Allocates values of named sequences, e.g. event sequence numbers or access counters.
+///
+/// Values are reserved from the database in batches and handed out from memory, so most
+/// allocations need no round trip. Values are unique across all server instances and
+/// strictly increasing within each instance. Reserved values which are not handed out
+/// before the instance stops are skipped. With a batch size of 1 every allocation goes
+/// to the database and values are strictly increasing across instances.
+pub struct SequenceAllocator {
+    database: Arc<Database>,
+    batch_size: i64,
+    // Reserved and not yet allocated values of each namespace
+    reserved: DashMap<String, Arc<Mutex<Range<i64>>>, RandomState>,
+}
+
+impl SequenceAllocator {
+    pub fn new(database: Arc<Database>, batch_size: i64) -> Self {
+        SequenceAllocator {
+            database,
+            batch_size: batch_size.max(1),
+            reserved: DashMap::default(),
+        }
+    }
+
+    /// Next value of the namespace
+    pub async fn next_sequence(&self, namespace: &str) -> Result<i64> {
+        Ok(self.allocate_block(namespace, 1).await?.start)
+    }
+
+    /// `count` consecutive values of the namespace
+    pub async fn allocate_block(&self, namespace: &str, count: i64) -> Result<Range<i64>> {
+        if count < 1 {
+            bail!("At least one value has to be allocated");
+        }
+        let reserved = self
+            .reserved
+            .entry(namespace.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(0..0)))
+            .clone();
+        // Allocations of a namespace are serialized to keep them increasing
+        let mut reserved = reserved.lock().await;
+        if reserved.end - reserved.start < count {
+            // Remaining values are skipped, blocks are never split across reservations
+            let reserve = count.max(self.batch_size);
+            let client = self.database.get_client().await?;
+            let last = Sequence::allocate(namespace, reserve, &client).await?;
+            *reserved = (last - reserve + 1)..(last + 1);
+        }
+        let block = reserved.start..(reserved.start + count);
+        reserved.start = block.end;
+        Ok(block)
+    }
+}
@@ -0,0 +1,134 @@
+use lazy_static::lazy_static;
+use std::time::{Duration, Instant};
+
+/// Response metadata key which is set if a traversal stopped at its limits
+pub const TRUNCATED_KEY: &str = "x-aruna-truncated";
+
+lazy_static! {
+    pub static ref TRAVERSAL_LIMITS: TraversalLimits = TraversalLimits::from_env();
+}
+
+/// Guardrails of graph traversals like lineages, descendant listings and integrity scans.
+/// Traversals which reach the depth or node limit return the part of the graph visited
+/// so far, single queries which exceed the budget fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraversalLimits {
+    pub max_depth: usize,
+    pub max_nodes: usize,
+    // Wall-clock time of a single traversal
+    pub budget: Duration,
+}
+
+fn var(key: &str, default: u64) -> u64 {
+    dotenvy::var(key)
+        .map(|var| var.parse::<u64>().unwrap_or(default))
+        .unwrap_or(default)
+}
+
+impl TraversalLimits {
+    pub fn from_env() -> Self {
+        TraversalLimits {
+            max_depth: var("TRAVERSAL_MAX_DEPTH", 256).max(1) as usize,
+            max_nodes: var("TRAVERSAL_MAX_NODES", 1000000).max(1) as usize,
+            budget: Duration::from_secs(var("TRAVERSAL_BUDGET", 30).max(1)),
+        }
+    }
+
+    /// Limits with a depth which is at most `max_depth`, e.g. requested by a client
+    pub fn with_max_depth(self, max_depth: Option<usize>) -> Self {
+        TraversalLimits {
+            max_depth: max_depth.map_or(self.max_depth, |depth| depth.min(self.max_depth)),
+            ..self
+        }
+    }
+}
+
+/// Tracks the progress of a traversal against its limits
+#[derive(Debug)]
+pub struct TraversalGuard {
+    limits: TraversalLimits,
+    started: Instant,
+    truncated: bool,
+}
+
+impl TraversalGuard {
+    pub fn new(limits: TraversalLimits) -> Self {
+        TraversalGuard {
+            limits,
+            started: Instant::now(),
+            truncated: false,
+        }
+    }
+
+    /// Whether the traversal may continue at `depth` with `visited` nodes,
+    /// marks the traversal as truncated otherwise
+    pub fn proceed(&mut self, depth: usize, visited: usize) -> bool {
+        if depth > self.limits.max_depth
+            || visited >= self.limits.max_nodes
+            || self.started.elapsed() > self.limits.budget
+        {
+            self.truncated = true;
+        }
+        !self.truncated
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_depth: usize, max_nodes: usize) -> TraversalLimits {
+        TraversalLimits {
+            max_depth,
+            max_nodes,
+            budget: Duration::from_secs(60),
+        }
+    }
+
+    // Walks a chain of `len` nodes, returns the number of visited nodes
+    fn walk_chain(len: usize, guard: &mut TraversalGuard) -> usize {
+        let mut visited = 0;
+        for depth in 0..len {
+            if !guard.proceed(depth, visited) {
+                break;
+            }
+            visited += 1;
+        }
+        visited
+    }
+
+    #[test]
+    fn test_deep_chain_truncated() {
+        let mut guard = TraversalGuard::new(limits(10, 1000));
+        assert_eq!(walk_chain(10000, &mut guard), 11);
+        assert!(guard.is_truncated());
+
+        let mut guard = TraversalGuard::new(limits(10, 5));
+        assert_eq!(walk_chain(10000, &mut guard), 5);
+        assert!(guard.is_truncated());
+
+        let mut guard = TraversalGuard::new(limits(10, 1000));
+        assert_eq!(walk_chain(8, &mut guard), 8);
+        assert!(!guard.is_truncated());
+
+        let mut guard = TraversalGuard::new(TraversalLimits {
+            budget: Duration::ZERO,
+            ..limits(10, 1000)
+        });
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(walk_chain(8, &mut guard), 0);
+        assert!(guard.is_truncated());
+    }
+
+    #[test]
+    fn test_requested_depth_capped() {
+        let limits = limits(10, 1000);
+        assert_eq!(limits.with_max_depth(Some(3)).max_depth, 3);
+        assert_eq!(limits.with_max_depth(Some(50)).max_depth, 10);
+        assert_eq!(limits.with_max_depth(None).max_depth, 10);
+    }
+}
@@ -0,0 +1,326 @@
+use crate::caching::cache::Cache;
+use crate::database::dsls::persistent_notification_dsl::{
+    NotificationReference, NotificationReferences, PersistentNotification,
+};
+use crate::database::dsls::stats_dsl::ObjectStats;
+use crate::database::dsls::user_dsl::User;
+use crate::database::enums::{
+    DbPermissionLevel, NotificationReferenceType, ObjectType, PersistentNotificationVariant,
+};
+use anyhow::Result;
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use postgres_types::Json;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use tokio_postgres::Client;
+
+lazy_static! {
+    pub static ref USER_NOTIFICATION_CONFIG: UserNotificationConfig =
+        UserNotificationConfig::from_env();
+}
+
+/// Configuration for user-scoped account event notifications
+#[derive(Debug, Clone)]
+pub struct UserNotificationConfig {
+    /// Equal notifications for the same user are only created once within this window
+    pub dedup_window: Duration,
+    /// Tokens expiring within this window trigger a notification
+    pub token_expiry_window: Duration,
+    /// Size quota per project in bytes; quota notifications are disabled if None
+    pub project_quota: Option<i64>,
+    /// Percentage of the project quota that triggers a notification (soft threshold)
+    pub quota_threshold: u8,
+    /// Percentage of the project quota above which new objects are rejected (hard threshold);
+    /// the quota is not enforced if None
+    pub hard_quota_threshold: Option<u8>,
+}
+
+/// Write rejected because the project reached the hard threshold of its quota
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub project_id: DieselUlid,
+    pub size: i64,
+    pub limit: i64,
+}
+
+impl Display for QuotaExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Project {} exceeds its quota ({} of {} bytes)",
+            self.project_id, self.size, self.limit
+        )
+    }
+}
+
+impl Error for QuotaExceeded {}
+
+impl UserNotificationConfig {
+    pub fn from_env() -> Self {
+        UserNotificationConfig {
+            dedup_window: Duration::from_secs(
+                dotenvy::var("USER_NOTIFICATION_DEDUP_WINDOW")
+                    .map(|var| var.parse::<u64>().unwrap_or(86400))
+                    .unwrap_or(86400),
+            ),
+            token_expiry_window: Duration::from_secs(
+                dotenvy::var("TOKEN_EXPIRY_NOTIFICATION_DAYS")
+                    .map(|var| var.parse::<u64>().unwrap_or(7))
+                    .unwrap_or(7)
+                    * 86400,
+            ),
+            project_quota: dotenvy::var("PROJECT_QUOTA")
+                .ok()
+                .and_then(|var| var.parse::<i64>().ok())
+                .filter(|quota| *quota > 0),
+            quota_threshold: dotenvy::var("QUOTA_NOTIFICATION_THRESHOLD")
+                .map(|var| var.parse::<u8>().unwrap_or(80).min(100))
+                .unwrap_or(80),
+            hard_quota_threshold: dotenvy::var("QUOTA_HARD_THRESHOLD")
+                .ok()
+                .and_then(|var| var.parse::<u8>().ok())
+                .map(|threshold| threshold.min(100)),
+        }
+    }
+
+    fn threshold_size(quota: i64, percent: u8) -> i64 {
+        (quota as i128 * percent as i128 / 100) as i64
+    }
+
+    /// Size at which writes are rejected, if the quota is enforced
+    pub fn hard_limit(&self) -> Option<i64> {
+        Some(Self::threshold_size(
+            self.project_quota?,
+            self.hard_quota_threshold?,
+        ))
+    }
+
+    /// Returns true if the hard threshold was crossed between the previous and the current size,
+    /// projects at the hard limit reject writes like `check_project_quota`
+    pub fn hard_threshold_crossed(&self, previous_size: i64, current_size: i64) -> bool {
+        match self.hard_limit() {
+            Some(limit) => previous_size < limit && current_size >= limit,
+            None => false,
+        }
+    }
+
+    /// Returns true if the quota threshold was crossed between the previous and the current size
+    pub fn quota_threshold_crossed(&self, previous_size: i64, current_size: i64) -> bool {
+        match self.project_quota {
+            Some(quota) => {
+                let threshold = Self::threshold_size(quota, self.quota_threshold);
+                previous_size < threshold && current_size >= threshold
+            }
+            None => false,
+        }
+    }
+}
+
+/// Creates a persistent notification for the user if no equal notification
+/// was created within the configured deduplication window.
+pub async fn notify_user(
+    client: &Client,
+    config: &UserNotificationConfig,
+    user_id: DieselUlid,
+    variant: PersistentNotificationVariant,
+    message: String,
+    references: Vec<NotificationReference>,
+) -> Result<bool> {
+    let mut notification = PersistentNotification {
+        id: DieselUlid::generate(),
+        user_id,
+        notification_variant: variant,
+        message,
+        refs: Json(NotificationReferences(references)),
+    };
+    notification
+        .create_deduplicated(config.dedup_window, client)
+        .await
+}
+
+/// Notifies the user if the token expires within the configured window.
+pub async fn notify_token_expiry(
+    client: &Client,
+    config: &UserNotificationConfig,
+    user: &User,
+    token_id: &DieselUlid,
+) -> Result<bool> {
+    let (name, expires_at) = match user.attributes.0.tokens.get(token_id) {
+        Some(token) => (token.name.clone(), token.expires_at),
+        None => return Ok(false),
+    };
+
+    let expiry_deadline = Utc::now().naive_utc() + config.token_expiry_window;
+    if expires_at > expiry_deadline {
+        return Ok(false);
+    }
+
+    notify_user(
+        client,
+        config,
+        user.id,
+        PersistentNotificationVariant::TOKEN_EXPIRING,
+        format!("Token {} ({}) expires at {}", name, token_id, expires_at),
+        vec![NotificationReference {
+            reference_type: NotificationReferenceType::User,
+            reference_name: name,
+            reference_value: token_id.to_string(),
+        }],
+    )
+    .await
+}
+
+/// Notifies the owners of all tokens which expire within the configured window.
+/// Every token is only notified once per deduplication window, returns the number
+/// of created notifications.
+pub async fn notify_expiring_tokens(
+    client: &Client,
+    config: &UserNotificationConfig,
+    cache: &Cache,
+) -> Result<usize> {
+    let now = Utc::now().naive_utc();
+    let expiry_deadline = now + config.token_expiry_window;
+    let mut created = 0;
+    for (user_id, token_ids) in cache
+        .find_user_tokens(|token| token.expires_at > now && token.expires_at <= expiry_deadline)
+    {
+        let Some(user) = cache.get_user(&user_id) else {
+            continue;
+        };
+        for token_id in token_ids {
+            if notify_token_expiry(client, config, &user, &token_id).await? {
+                created += 1;
+            }
+        }
+    }
+    Ok(created)
+}
+
+/// Notifies all project admins of projects which crossed the soft or the hard quota threshold.
+/// Every crossing is only notified once, returns the number of created notifications.
+pub async fn notify_quota_thresholds(
+    client: &Client,
+    config: &UserNotificationConfig,
+    cache: &Cache,
+    stats: &[(ObjectStats, i64)], // Current stats with previous size
+) -> Result<usize> {
+    let mut created = 0;
+    for (current, previous_size) in stats {
+        // Separate variants, so the rejection is not deduplicated against the earlier warning
+        let (variant, message) = if config.hard_threshold_crossed(*previous_size, current.size) {
+            (
+                PersistentNotificationVariant::QUOTA_EXCEEDED,
+                "exceeded its quota, new objects are rejected".to_string(),
+            )
+        } else if config.quota_threshold_crossed(*previous_size, current.size) {
+            (
+                PersistentNotificationVariant::QUOTA_THRESHOLD_REACHED,
+                format!("reached {}% of its quota", config.quota_threshold),
+            )
+        } else {
+            continue;
+        };
+
+        let project = match cache.get_object(&current.origin_pid) {
+            Some(object) if object.object.object_type == ObjectType::PROJECT => object.object,
+            _ => continue,
+        };
+
+        for (user, permission) in cache.get_resource_users(&project.id) {
+            if permission != DbPermissionLevel::ADMIN {
+                continue;
+            }
+            if notify_user(
+                client,
+                config,
+                user.id,
+                variant,
+                format!(
+                    "Project {} ({}) {} ({} of {} bytes)",
+                    project.name,
+                    project.id,
+                    message,
+                    current.size,
+                    config.project_quota.unwrap_or_default()
+                ),
+                vec![NotificationReference {
+                    reference_type: NotificationReferenceType::Resource,
+                    reference_name: project.name.clone(),
+                    reference_value: project.id.to_string(),
+                }],
+            )
+            .await?
+            {
+                created += 1;
+            }
+        }
+    }
+
+    Ok(created)
+}
+
+/// Rejects writes of `additional` bytes to `resource_id` (the object or the parent of a
+/// new object) if one of its projects is above the hard threshold of its quota afterwards.
+/// Project sizes are taken from the periodically refreshed stats in the cache.
+pub fn check_project_quota(
+    config: &UserNotificationConfig,
+    cache: &Cache,
+    resource_id: &DieselUlid,
+    additional: i64,
+) -> Result<()> {
+    let Some(limit) = config.hard_limit() else {
+        return Ok(());
+    };
+    // Last element of each upstream hierarchy is the project
+    let projects = cache
+        .upstream_dfs_iterative(resource_id)?
+        .into_iter()
+        .filter_map(|hierarchy| hierarchy.last().map(|project| project.into_inner()))
+        .unique();
+    for project_id in projects {
+        let current = cache
+            .get_object_stats(&project_id)
+            .map(|stats| stats.size)
+            .unwrap_or_default();
+        let size = current.saturating_add(additional.max(0));
+        if current >= limit || size > limit {
+            return Err(QuotaExceeded {
+                project_id,
+                size,
+                limit,
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Notifies all users with permissions on the deleted project.
+pub async fn notify_project_deleted(
+    client: &Client,
+    config: &UserNotificationConfig,
+    cache: &Cache,
+    project_id: &DieselUlid,
+    project_name: &str,
+) -> Result<()> {
+    for (user, _) in cache.get_resource_users(project_id) {
+        notify_user(
+            client,
+            config,
+            user.id,
+            PersistentNotificationVariant::PROJECT_DELETED,
+            format!("Project {} ({}) was deleted", project_name, project_id),
+            vec![NotificationReference {
+                reference_type: NotificationReferenceType::Resource,
+                reference_name: project_name.to_string(),
+                reference_value: project_id.to_string(),
+            }],
+        )
+        .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,285 @@
+use crate::database::enums::ObjectType;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+pub const DEFAULT_MAX_NAME_LENGTH: usize = 1024;
+pub const DEFAULT_MAX_DESCRIPTION_LENGTH: usize = 4096;
+/// Names which are always rejected, because they can not be used as path segments
+const ALWAYS_RESERVED: [&str; 2] = [".", ".."];
+
+lazy_static! {
+    pub static ref PROJECT_SCHEMA: Regex =
+        Regex::new(r"^[a-z0-9\-]+$").expect("Regex must be valid");
+    // Unicode letters are allowed, names are compared in their normalized form
+    pub static ref S3_KEY_SCHEMA: Regex =
+        Regex::new(r"^[\p{L}\p{M}\p{N}\-\!\_\.\*\_\'\(\)]+$").expect("Regex must be valid");
+    pub static ref OBJECT_SCHEMA: Regex =
+        Regex::new(r"^[\p{L}\p{M}\p{N}\-\!\_\.\*\_\'\(\)\/]+$").expect("Regex must be valid");
+    pub static ref VALIDATION_RULES: ValidationRules =
+        ValidationRules::from_env().expect("Invalid validation rules");
+}
+
+/// Rule violated by a resource name or description
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    Empty,
+    TooLong { field: &'static str, max: usize },
+    ControlCharacter { field: &'static str },
+    SurroundingWhitespace,
+    InvalidCharacters { resource: &'static str },
+    EmptyPathSegment,
+    Reserved { name: String },
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::Empty => write!(f, "name must not be empty"),
+            ValidationError::TooLong { field, max } => {
+                write!(f, "{field} must not be longer than {max} characters")
+            }
+            ValidationError::ControlCharacter { field } => {
+                write!(f, "{field} must not contain control characters")
+            }
+            ValidationError::SurroundingWhitespace => {
+                write!(f, "name must not start or end with whitespace")
+            }
+            ValidationError::InvalidCharacters { resource } => {
+                write!(f, "name contains characters not allowed for {resource}s")
+            }
+            ValidationError::EmptyPathSegment => {
+                write!(f, "object name must not contain empty path segments")
+            }
+            ValidationError::Reserved { name } => write!(f, "name '{name}' is reserved"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Rules applied to names and descriptions of all resources on create and update
+#[derive(Debug, Clone)]
+pub struct ValidationRules {
+    pub max_name_length: usize,
+    pub max_description_length: usize,
+    pub reserved_names: HashSet<String>,
+    /// Additional pattern all names have to match
+    pub allowed_characters: Option<Regex>,
+}
+
+impl Default for ValidationRules {
+    fn default() -> Self {
+        ValidationRules {
+            max_name_length: DEFAULT_MAX_NAME_LENGTH,
+            max_description_length: DEFAULT_MAX_DESCRIPTION_LENGTH,
+            reserved_names: ALWAYS_RESERVED
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            allowed_characters: None,
+        }
+    }
+}
+
+impl ValidationRules {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut rules = ValidationRules::default();
+        if let Ok(max) = dotenvy::var("MAX_NAME_LENGTH") {
+            rules.max_name_length = max.parse()?;
+        }
+        if let Ok(max) = dotenvy::var("MAX_DESCRIPTION_LENGTH") {
+            rules.max_description_length = max.parse()?;
+        }
+        if let Ok(names) = dotenvy::var("RESERVED_NAMES") {
+            rules.reserved_names.extend(
+                names
+                    .split(',')
+                    .map(|name| name.trim().to_lowercase())
+                    .filter(|name| !name.is_empty()),
+            );
+        }
+        if let Ok(pattern) = dotenvy::var("NAME_ALLOWED_CHARACTERS") {
+            rules.allowed_characters = Some(Regex::new(&pattern)?);
+        }
+        Ok(rules)
+    }
+
+    pub fn validate_name(&self, resource: ObjectType, name: &str) -> Result<(), ValidationError> {
+        if name.is_empty() {
+            return Err(ValidationError::Empty);
+        }
+        if name.chars().count() > self.max_name_length {
+            return Err(ValidationError::TooLong {
+                field: "name",
+                max: self.max_name_length,
+            });
+        }
+        if name.chars().any(char::is_control) {
+            return Err(ValidationError::ControlCharacter { field: "name" });
+        }
+        if name.trim() != name {
+            return Err(ValidationError::SurroundingWhitespace);
+        }
+        let (schema, resource_name) = match resource {
+            ObjectType::PROJECT => (&*PROJECT_SCHEMA, "project"),
+            ObjectType::COLLECTION => (&*S3_KEY_SCHEMA, "collection"),
+            ObjectType::DATASET => (&*S3_KEY_SCHEMA, "dataset"),
+            ObjectType::OBJECT => (&*OBJECT_SCHEMA, "object"),
+        };
+        if !schema.is_match(name)
+            || self
+                .allowed_characters
+                .as_ref()
+                .is_some_and(|allowed| !allowed.is_match(name))
+        {
+            return Err(ValidationError::InvalidCharacters {
+                resource: resource_name,
+            });
+        }
+        // Object names are paths, so every segment has to be a valid name
+        for segment in name.split('/') {
+            if segment.is_empty() {
+                return Err(ValidationError::EmptyPathSegment);
+            }
+            if self.reserved_names.contains(&segment.to_lowercase()) {
+                return Err(ValidationError::Reserved {
+                    name: segment.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate_description(&self, description: &str) -> Result<(), ValidationError> {
+        if description.chars().count() > self.max_description_length {
+            return Err(ValidationError::TooLong {
+                field: "description",
+                max: self.max_description_length,
+            });
+        }
+        // Descriptions may span multiple lines
+        if description
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+        {
+            return Err(ValidationError::ControlCharacter {
+                field: "description",
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_rules() {
+        let rules = ValidationRules::default();
+        assert!(rules
+            .validate_name(ObjectType::PROJECT, "my-project")
+            .is_ok());
+        assert!(rules
+            .validate_name(ObjectType::OBJECT, "dir/sub/file.txt")
+            .is_ok());
+        assert!(rules
+            .validate_name(ObjectType::COLLECTION, "cafe\u{301}")
+            .is_ok());
+
+        assert_eq!(
+            rules.validate_name(ObjectType::DATASET, ""),
+            Err(ValidationError::Empty)
+        );
+        assert_eq!(
+            rules.validate_name(
+                ObjectType::COLLECTION,
+                &"a".repeat(DEFAULT_MAX_NAME_LENGTH + 1)
+            ),
+            Err(ValidationError::TooLong {
+                field: "name",
+                max: DEFAULT_MAX_NAME_LENGTH
+            })
+        );
+        assert_eq!(
+            rules.validate_name(ObjectType::OBJECT, "file\u{0}.txt"),
+            Err(ValidationError::ControlCharacter { field: "name" })
+        );
+        assert_eq!(
+            rules.validate_name(ObjectType::OBJECT, " file.txt"),
+            Err(ValidationError::SurroundingWhitespace)
+        );
+        assert_eq!(
+            rules.validate_name(ObjectType::PROJECT, "My_Project"),
+            Err(ValidationError::InvalidCharacters {
+                resource: "project"
+            })
+        );
+        // Path separators are only allowed in object names
+        assert_eq!(
+            rules.validate_name(ObjectType::DATASET, "a/b"),
+            Err(ValidationError::InvalidCharacters {
+                resource: "dataset"
+            })
+        );
+        assert_eq!(
+            rules.validate_name(ObjectType::OBJECT, "/a//b"),
+            Err(ValidationError::EmptyPathSegment)
+        );
+        assert_eq!(
+            rules.validate_name(ObjectType::OBJECT, "a/../b"),
+            Err(ValidationError::Reserved {
+                name: "..".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_configured_rules() {
+        let rules = ValidationRules {
+            max_name_length: 8,
+            reserved_names: HashSet::from_iter(["admin".to_string()]),
+            allowed_characters: Some(Regex::new("^[a-z/]+$").unwrap()),
+            ..Default::default()
+        };
+        assert!(rules.validate_name(ObjectType::COLLECTION, "data").is_ok());
+        assert!(rules
+            .validate_name(ObjectType::COLLECTION, "collection")
+            .is_err());
+        assert_eq!(
+            rules.validate_name(ObjectType::OBJECT, "ADMIN"),
+            Err(ValidationError::InvalidCharacters { resource: "object" })
+        );
+        assert_eq!(
+            rules.validate_name(ObjectType::OBJECT, "x/admin"),
+            Err(ValidationError::Reserved {
+                name: "admin".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_description_rules() {
+        let rules = ValidationRules {
+            max_description_length: 16,
+            ..Default::default()
+        };
+        assert!(rules.validate_description("").is_ok());
+        assert!(rules.validate_description("line\nline\t").is_ok());
+        assert_eq!(
+            rules.validate_description(&"a".repeat(17)),
+            Err(ValidationError::TooLong {
+                field: "description",
+                max: 16
+            })
+        );
+        assert_eq!(
+            rules.validate_description("bell\u{7}"),
+            Err(ValidationError::ControlCharacter {
+                field: "description"
+            })
+        );
+    }
+}
@@ -1,6 +1,13 @@
 pub mod common;
+use aruna_server::auth::issuer_handler::IssuerType;
+use aruna_server::auth::token_handler::TokenIntrospection;
 use aruna_server::database::dsls::user_dsl::APIToken;
 use chrono::Days;
+use common::test_utils::{
+    ADMIN_OIDC_TOKEN, ADMIN_USER_ULID, INVALID_OIDC_TOKEN, USER1_OIDC_TOKEN, USER1_ULID,
+};
+use diesel_ulid::DieselUlid;
+use std::str::FromStr;
 
 #[tokio::test]
 async fn server_authorization() {
@@ -23,7 +30,64 @@ async fn server_authorization() {
             .unwrap(),
         object_id: None,
         user_rights: aruna_server::database::enums::DbPermissionLevel::NONE,
+        parent: None,
+        session_scoped: false,
     };
     // - Context testing
     // - Permission testing
 }
+
+#[tokio::test]
+async fn token_introspection() {
+    // Init
+    let db_handler = common::init::init_database().await;
+    let cache = common::init::init_cache(db_handler.clone(), true).await;
+    let token_handler = common::init::init_token_handler(db_handler.clone(), cache.clone()).await;
+    let permission_handler =
+        common::init::init_permission_handler(cache.clone(), token_handler.clone()).await;
+    let user_id = DieselUlid::from_str(USER1_ULID).unwrap();
+    let admin_id = DieselUlid::from_str(ADMIN_USER_ULID).unwrap();
+
+    // Own tokens are introspected with full details
+    let own = permission_handler
+        .introspect_token(USER1_OIDC_TOKEN, USER1_OIDC_TOKEN)
+        .await
+        .unwrap();
+    assert!(own.active);
+    assert_eq!(own.sub, Some(user_id));
+    assert_eq!(own.token_id, None);
+    assert_eq!(own.token_type, Some(IssuerType::OIDC));
+    assert!(own.expires_at.is_some());
+    assert!(own.issuer.is_some());
+
+    // Foreign tokens are reported inactive for regular users ...
+    let foreign = permission_handler
+        .introspect_token(USER1_OIDC_TOKEN, ADMIN_OIDC_TOKEN)
+        .await
+        .unwrap();
+    assert_eq!(foreign, TokenIntrospection::inactive());
+
+    // ... but can be introspected by admins
+    let foreign = permission_handler
+        .introspect_token(ADMIN_OIDC_TOKEN, USER1_OIDC_TOKEN)
+        .await
+        .unwrap();
+    assert!(foreign.active);
+    assert_eq!(foreign.sub, Some(user_id));
+    let admin = permission_handler
+        .introspect_token(ADMIN_OIDC_TOKEN, ADMIN_OIDC_TOKEN)
+        .await
+        .unwrap();
+    assert_eq!(admin.sub, Some(admin_id));
+
+    // Invalid tokens are inactive instead of an error
+    let invalid = permission_handler
+        .introspect_token(USER1_OIDC_TOKEN, INVALID_OIDC_TOKEN)
+        .await
+        .unwrap();
+    assert!(!invalid.active);
+    assert!(permission_handler
+        .introspect_token(INVALID_OIDC_TOKEN, USER1_OIDC_TOKEN)
+        .await
+        .is_err());
+}
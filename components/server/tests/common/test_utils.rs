@@ -33,6 +33,7 @@ use dashmap::DashMap;
 use diesel_ulid::DieselUlid;
 use postgres_types::Json;
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use std::str::FromStr;
 use tonic::{
     metadata::{AsciiMetadataKey, AsciiMetadataValue},
     Request,
@@ -210,6 +211,18 @@ pub fn rand_string(length: usize) -> String {
         .collect()
 }
 
+/// Random ulid with the creation time `millis` since the epoch
+#[allow(dead_code)]
+pub fn ulid_at(millis: u64) -> DieselUlid {
+    const CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    let ulid = ((millis as u128) << 80) | (rand::random::<u128>() >> 48);
+    let encoded = (0..26)
+        .rev()
+        .map(|i| CROCKFORD[((ulid >> (i * 5)) & 31) as usize] as char)
+        .collect::<String>();
+    DieselUlid::from_str(&encoded).unwrap()
+}
+
 /* ----- Resource create convenience functions ---------- */
 #[allow(dead_code)]
 pub async fn fast_track_grpc_get_collection(
@@ -31,6 +31,7 @@ async fn create_hook() {
                 key: "TEST_KEY".to_string(),
                 value: "TEST_VALUE".to_string(),
                 variant: KeyValueVariant::HOOK,
+                value_type: None,
             })],
         }),
         timeout: chrono::Utc::now()
@@ -91,6 +92,7 @@ pub async fn get_hooks() {
                     key: "TEST_KEY".to_string(),
                     value: "TEST_VALUE".to_string(),
                     variant: KeyValueVariant::HOOK,
+                    value_type: None,
                 })],
             }),
             timeout: chrono::Utc::now()
@@ -123,6 +125,7 @@ pub async fn get_hooks() {
                 key: "TEST_KEY".to_string(),
                 value: "TEST_VALUE".to_string(),
                 variant: KeyValueVariant::HOOK,
+                value_type: None,
             })],
         }),
         timeout: chrono::Utc::now()
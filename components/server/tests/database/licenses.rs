@@ -15,6 +15,8 @@ async fn create_and_get_licenses() {
         name: "test license".to_string(),
         text: "this is a test license".to_string(),
         url: "test.org/test-license".to_string(),
+        embargo_until: None,
+        access_days: None,
     };
     license.create(&client).await.unwrap();
 
@@ -30,6 +32,8 @@ async fn create_and_get_licenses() {
         name: "error test license".to_string(),
         text: "this is a test license that cannot be created".to_string(),
         url: "test.org/error-test-license".to_string(),
+        embargo_until: None,
+        access_days: None,
     };
     assert!(err_license.create(&client).await.is_err());
 
@@ -39,6 +43,8 @@ async fn create_and_get_licenses() {
         name: "test license".to_string(),
         text: "this is a test license".to_string(),
         url: "test.org/test-license".to_string(),
+        embargo_until: None,
+        access_days: None,
     };
     assert!(ok_license.create(&client).await.is_ok());
 
@@ -61,6 +67,8 @@ async fn objects_and_licenses() {
         name: "another test license".to_string(),
         text: "this is another test license".to_string(),
         url: "test.org/another_test_license".to_string(),
+        embargo_until: None,
+        access_days: None,
     };
     let object_id = DieselUlid::generate();
     let mut user = test_utils::new_user(vec![ObjectMapping::PROJECT(object_id)]);
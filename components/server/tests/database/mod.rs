@@ -5,6 +5,8 @@ pub mod objects;
 pub mod pub_keys;
 pub mod relations;
 pub mod rules;
+pub mod schema_version;
+pub mod sequences;
 pub mod stats;
 pub mod users;
 pub mod workspaces;
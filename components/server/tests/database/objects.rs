@@ -421,6 +421,7 @@ async fn test_keyvals() {
         key: "one".to_string(),
         value: "two".to_string(),
         variant: KeyValueVariant::LABEL,
+        value_type: None,
     };
     Object::add_key_value(&obj_id, &client, kv.clone())
         .await
@@ -582,6 +583,7 @@ async fn test_updates() {
         key: "NewKey".to_string(),
         value: "NewValue".to_string(),
         variant: KeyValueVariant::LABEL,
+        value_type: None,
     }]));
     create_object.update(client).await.unwrap();
     let updated_object = Object::get(obj_id, client).await.unwrap().unwrap();
@@ -0,0 +1,54 @@
+use aruna_server::database::connection::{MIGRATIONS, SCHEMA_VERSION};
+
+use crate::common::init;
+
+#[tokio::test]
+async fn schema_version_test() {
+    // Init database connection, stamps the current schema version
+    let db = init::init_database().await;
+    db.check_schema_version(SCHEMA_VERSION, false)
+        .await
+        .unwrap();
+
+    // Servers of a newer version only start with an explicit migration
+    assert!(db
+        .check_schema_version(SCHEMA_VERSION + 1, false)
+        .await
+        .is_err());
+    db.check_schema_version(SCHEMA_VERSION + 1, true)
+        .await
+        .unwrap();
+
+    // Servers of an older version never start
+    assert!(db
+        .check_schema_version(SCHEMA_VERSION - 1, false)
+        .await
+        .is_err());
+    assert!(db
+        .check_schema_version(SCHEMA_VERSION - 1, true)
+        .await
+        .is_err());
+}
+
+#[test]
+fn migration_steps_test() {
+    // Every version after the initial one has exactly one step, the last one is current
+    let versions: Vec<i32> = MIGRATIONS.iter().map(|(version, _)| *version).collect();
+    let expected: Vec<i32> = (2..=SCHEMA_VERSION).collect();
+    assert_eq!(versions, expected);
+}
+
+#[tokio::test]
+async fn migrate_schema_test() {
+    let db = init::init_database().await;
+    let client = db.get_client().await.unwrap();
+
+    // Steps are idempotent and stamp the version they migrate to
+    db.migrate_schema(1, SCHEMA_VERSION).await.unwrap();
+    let version: i32 = client
+        .query_one("SELECT version FROM schema_version;", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(version, SCHEMA_VERSION);
+}
@@ -0,0 +1,63 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use aruna_server::database::dsls::sequence_dsl::Sequence;
+use aruna_server::utils::sequence_utils::SequenceAllocator;
+use diesel_ulid::DieselUlid;
+
+use crate::common::init;
+
+#[tokio::test]
+async fn concurrent_sequence_allocation() {
+    let db = init::init_database().await;
+    let namespace = format!("test-{}", DieselUlid::generate());
+
+    // Two server instances with batched and unbatched allocation
+    let allocators = [
+        Arc::new(SequenceAllocator::new(db.clone(), 7)),
+        Arc::new(SequenceAllocator::new(db.clone(), 1)),
+    ];
+    let mut tasks = Vec::new();
+    for task in 0..16 {
+        let allocator = allocators[task % 2].clone();
+        let namespace = namespace.clone();
+        tasks.push(tokio::spawn(async move {
+            let mut values = Vec::new();
+            for i in 0..25 {
+                if i % 5 == 0 {
+                    values.extend(allocator.allocate_block(&namespace, 3).await.unwrap());
+                } else {
+                    values.push(allocator.next_sequence(&namespace).await.unwrap());
+                }
+            }
+            values
+        }));
+    }
+
+    let mut all = HashSet::new();
+    for task in tasks {
+        let values = task.await.unwrap();
+        // Values of each caller are strictly increasing
+        assert!(values.windows(2).all(|pair| pair[0] < pair[1]));
+        for value in values {
+            assert!(value > 0);
+            // No value is allocated twice
+            assert!(all.insert(value), "Duplicate value {value}");
+        }
+    }
+    assert_eq!(all.len(), 16 * (20 + 5 * 3));
+
+    // Reserved values are never handed out again
+    let client = db.get_client().await.unwrap();
+    let last = Sequence::get(&namespace, &client)
+        .await
+        .unwrap()
+        .unwrap()
+        .value;
+    assert!(all.iter().all(|value| *value <= last));
+    let next = allocators[1].next_sequence(&namespace).await.unwrap();
+    assert_eq!(next, last + 1);
+
+    // Invalid block sizes are rejected
+    assert!(allocators[0].allocate_block(&namespace, 0).await.is_err());
+}
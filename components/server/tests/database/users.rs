@@ -539,6 +539,8 @@ async fn user_token_test() {
                         expires_at: chrono::Utc::now().naive_utc(),
                         object_id: Some(ObjectMapping::PROJECT(DieselUlid::generate())),
                         user_rights: DbPermissionLevel::ADMIN,
+                        parent: None,
+                        session_scoped: false,
                     },
                 ),
                 (
@@ -550,6 +552,8 @@ async fn user_token_test() {
                         expires_at: chrono::Utc::now().naive_utc(),
                         object_id: Some(ObjectMapping::COLLECTION(DieselUlid::generate())),
                         user_rights: DbPermissionLevel::ADMIN,
+                        parent: None,
+                        session_scoped: false,
                     },
                 ),
                 (
@@ -561,6 +565,8 @@ async fn user_token_test() {
                         expires_at: chrono::Utc::now().naive_utc(),
                         object_id: Some(ObjectMapping::DATASET(DieselUlid::generate())),
                         user_rights: DbPermissionLevel::ADMIN,
+                        parent: None,
+                        session_scoped: false,
                     },
                 ),
             ]
@@ -726,6 +732,8 @@ async fn add_token_test() {
                 expires_at: chrono::Utc::now().naive_utc(),
                 object_id: None,
                 user_rights: DbPermissionLevel::NONE,
+                parent: None,
+                session_scoped: false,
             },
         )]),
     )
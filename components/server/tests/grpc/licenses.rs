@@ -1,19 +1,27 @@
 use crate::common::{
-    init::init_service_block,
+    init::{init_licenses_service_manual, init_service_block},
     test_utils::{add_token, USER1_OIDC_TOKEN},
 };
 use aruna_rust_api::api::storage::{
     models::v2::{DataClass, License},
     services::v2::{
-        collection_service_server::CollectionService, dataset_service_server::DatasetService,
-        license_service_server::LicenseService, object_service_server::ObjectService,
-        project_service_server::ProjectService, CreateCollectionRequest, CreateDatasetRequest,
-        CreateLicenseRequest, CreateObjectRequest, CreateProjectRequest, GetLicenseRequest,
-        ListLicensesRequest,
+        collection_service_server::CollectionService,
+        dataset_service_server::DatasetService,
+        license_service_server::{LicenseService, LicenseServiceServer},
+        object_service_server::ObjectService,
+        project_service_server::ProjectService,
+        CreateCollectionRequest, CreateDatasetRequest, CreateLicenseRequest, CreateObjectRequest,
+        CreateProjectRequest, GetLicenseRequest, ListLicensesRequest,
     },
 };
 use aruna_server::database::dsls::license_dsl::ALL_RIGHTS_RESERVED;
+use aruna_server::grpc::licenses::LicensesServiceImpl;
+use aruna_server::with_compression;
 use itertools::Itertools;
+use prost::Message;
+use tonic::codegen::{http, Body};
+use tonic::server::NamedService;
+use tower::ServiceExt;
 
 #[tokio::test]
 async fn create_and_get_licenses() {
@@ -294,3 +302,102 @@ async fn default_licenses() {
     assert_eq!(object.metadata_license_tag, dataset.metadata_license_tag);
     assert_eq!(object.data_license_tag, dataset.default_data_license_tag);
 }
+
+/// Sends a unary call to the license service, returns the negotiated encoding and
+/// whether the response message is compressed
+async fn call_license_service(
+    server: LicenseServiceServer<LicensesServiceImpl>,
+    method: &str,
+    message: impl Message,
+    accept_encoding: Option<&str>,
+) -> (Option<String>, bool) {
+    let message = message.encode_to_vec();
+    let mut frame = vec![0u8];
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&message);
+    let mut request = http::Request::builder()
+        .method("POST")
+        .uri(format!(
+            "/{}/{method}",
+            <LicenseServiceServer<LicensesServiceImpl> as NamedService>::NAME
+        ))
+        .header("content-type", "application/grpc")
+        .header("te", "trailers")
+        .header("authorization", format!("Bearer {USER1_OIDC_TOKEN}"));
+    if let Some(encoding) = accept_encoding {
+        request = request.header("grpc-accept-encoding", encoding);
+    }
+    let response = server
+        .oneshot(request.body(tonic::transport::Body::from(frame)).unwrap())
+        .await
+        .unwrap();
+    let encoding = response
+        .headers()
+        .get("grpc-encoding")
+        .map(|encoding| encoding.to_str().unwrap().to_string());
+    let mut body = response.into_body();
+    let data = body.data().await.unwrap().unwrap();
+    (encoding, data[0] == 1)
+}
+
+#[tokio::test]
+async fn compressed_list_licenses() {
+    // Init
+    let services = init_service_block().await;
+    let server = || async {
+        with_compression!(LicenseServiceServer::new(
+            init_licenses_service_manual(
+                services.db_handler.clone(),
+                services.auth_handler.clone(),
+                services.cache.clone(),
+            )
+            .await
+        ))
+    };
+
+    // Enough licenses for a large list response
+    for i in 0..10 {
+        services
+            .license_service
+            .create_license(add_token(
+                tonic::Request::new(CreateLicenseRequest {
+                    tag: format!("compressed_grpc_license_test_{i}"),
+                    name: "grpc compression test".to_string(),
+                    text: "Tests compression of large responses ".repeat(20),
+                    url: "test.org/compressed-grpc-test-license".to_string(),
+                }),
+                USER1_OIDC_TOKEN,
+            ))
+            .await
+            .unwrap();
+    }
+
+    // Large responses are compressed when the client accepts the encoding
+    let (encoding, compressed) = call_license_service(
+        server().await,
+        "ListLicenses",
+        ListLicensesRequest {},
+        Some("gzip"),
+    )
+    .await;
+    assert_eq!(encoding.as_deref(), Some("gzip"));
+    assert!(compressed);
+
+    // Clients without compression support get uncompressed responses
+    let (encoding, compressed) =
+        call_license_service(server().await, "ListLicenses", ListLicensesRequest {}, None).await;
+    assert!(encoding.is_none());
+    assert!(!compressed);
+
+    // Small responses are not compressed
+    let (_, compressed) = call_license_service(
+        server().await,
+        "GetLicense",
+        GetLicenseRequest {
+            tag: ALL_RIGHTS_RESERVED.to_string(),
+        },
+        Some("gzip"),
+    )
+    .await;
+    assert!(!compressed);
+}
@@ -3,6 +3,7 @@ mod collection;
 mod dataset;
 mod endpoint;
 mod licenses;
+mod object;
 mod project;
 mod search;
 mod user;
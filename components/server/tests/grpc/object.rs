@@ -0,0 +1,413 @@
+use aruna_rust_api::api::storage::{
+    models::v2::{Hash, Hashalgorithm, KeyValue, KeyValueVariant},
+    services::v2::{
+        create_object_request::Parent, object_service_server::ObjectService,
+        project_service_server::ProjectService, CreateObjectRequest, UpdateObjectRequest,
+        UpdateProjectKeyValuesRequest,
+    },
+};
+use aruna_server::{
+    database::{
+        dsls::license_dsl::ALL_RIGHTS_RESERVED, dsls::object_dsl::Object, enums::ObjectStatus,
+    },
+    grpc::object::ObjectServiceImpl,
+    middlelayer::hash_db_handler::FindObjectsByHash,
+    middlelayer::lifecycle_request_types::{
+        GetObjectLifecycleState, TransitionObjectState, LIFECYCLE_KEY, LIFECYCLE_STATE_KEY,
+    },
+    middlelayer::presigned_url_handler::{
+        BatchDownloadUrlEntry, CreateDownloadLinksStream, DownloadLinksStreamMessage,
+        DownloadUrlOptions, GetDownloadUrlsBatch, MAX_DOWNLOAD_URL_BATCH_SIZE,
+    },
+};
+use diesel_ulid::DieselUlid;
+use rand::{thread_rng, Rng};
+use std::str::FromStr;
+use tokio_stream::StreamExt;
+use tonic::Request;
+
+use crate::common::{
+    init::init_grpc_services,
+    test_utils::{
+        add_token, fast_track_grpc_project_create, rand_string, ADMIN_OIDC_TOKEN, USER1_OIDC_TOKEN,
+    },
+};
+
+async fn create_available_object(
+    object_service: &ObjectServiceImpl,
+    token: &str,
+    project_id: &str,
+    sha256: &str,
+) -> String {
+    let request = CreateObjectRequest {
+        name: rand_string(32),
+        title: "".to_string(),
+        description: "".to_string(),
+        key_values: vec![],
+        relations: vec![],
+        data_class: 1,
+        hashes: vec![Hash {
+            alg: Hashalgorithm::Sha256 as i32,
+            hash: sha256.to_string(),
+        }],
+        metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        parent: Some(Parent::ProjectId(project_id.to_string())),
+        authors: vec![],
+    };
+    let object = object_service
+        .create_object(add_token(Request::new(request), token))
+        .await
+        .unwrap()
+        .into_inner()
+        .object
+        .unwrap();
+
+    // Only available objects are content lookup results
+    let client = object_service
+        .database_handler
+        .database
+        .get_client()
+        .await
+        .unwrap();
+    Object::update_status(
+        &DieselUlid::from_str(&object.id).unwrap(),
+        ObjectStatus::AVAILABLE,
+        &client,
+    )
+    .await
+    .unwrap();
+    object.id
+}
+
+fn lookup_request(hex_digest: &str, token: &str) -> Request<FindObjectsByHash> {
+    add_token(
+        Request::new(FindObjectsByHash {
+            algorithm: Hashalgorithm::Sha256 as i32,
+            hex_digest: hex_digest.to_string(),
+        }),
+        token,
+    )
+}
+
+#[tokio::test]
+async fn grpc_find_objects_by_hash() {
+    // Init gRPC services
+    let (_, project_service, _, _, object_service, _) = init_grpc_services().await;
+
+    // Same content in a project of the user and a foreign project
+    let sha256 = (0..64)
+        .map(|_| format!("{:x}", thread_rng().gen_range(0..16)))
+        .collect::<String>();
+    let own_project = fast_track_grpc_project_create(&project_service, USER1_OIDC_TOKEN).await;
+    let foreign_project = fast_track_grpc_project_create(&project_service, ADMIN_OIDC_TOKEN).await;
+    let own_object =
+        create_available_object(&object_service, USER1_OIDC_TOKEN, &own_project.id, &sha256).await;
+    let foreign_object = create_available_object(
+        &object_service,
+        ADMIN_OIDC_TOKEN,
+        &foreign_project.id,
+        &sha256,
+    )
+    .await;
+
+    // Exact match is filtered by read permissions
+    let found = object_service
+        .find_objects_by_hash(lookup_request(&sha256, USER1_OIDC_TOKEN))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(found, vec![own_object.clone()]);
+    let mut found = object_service
+        .find_objects_by_hash(lookup_request(&sha256.to_uppercase(), ADMIN_OIDC_TOKEN))
+        .await
+        .unwrap()
+        .into_inner();
+    found.sort();
+    let mut expected = vec![own_object, foreign_object];
+    expected.sort();
+    assert_eq!(found, expected);
+
+    // No match
+    let found = object_service
+        .find_objects_by_hash(lookup_request(&"0".repeat(64), ADMIN_OIDC_TOKEN))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(found.is_empty());
+
+    // Digest has to fit the algorithm
+    assert!(object_service
+        .find_objects_by_hash(lookup_request("abc", USER1_OIDC_TOKEN))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn grpc_get_download_urls_batch() {
+    // Init gRPC services
+    let (_, project_service, _, _, object_service, _) = init_grpc_services().await;
+
+    let sha256 = (0..64)
+        .map(|_| format!("{:x}", thread_rng().gen_range(0..16)))
+        .collect::<String>();
+    let own_project = fast_track_grpc_project_create(&project_service, USER1_OIDC_TOKEN).await;
+    let foreign_project = fast_track_grpc_project_create(&project_service, ADMIN_OIDC_TOKEN).await;
+    let own_object =
+        create_available_object(&object_service, USER1_OIDC_TOKEN, &own_project.id, &sha256).await;
+    let foreign_object = create_available_object(
+        &object_service,
+        ADMIN_OIDC_TOKEN,
+        &foreign_project.id,
+        &sha256,
+    )
+    .await;
+
+    // Every entry gets its own result, failing entries do not fail the batch
+    let entries = vec![
+        BatchDownloadUrlEntry {
+            object_id: own_object.clone(),
+            options: DownloadUrlOptions {
+                ttl: Some(0),
+                ..Default::default()
+            },
+        },
+        BatchDownloadUrlEntry {
+            object_id: foreign_object.clone(),
+            options: DownloadUrlOptions::default(),
+        },
+        BatchDownloadUrlEntry {
+            object_id: "invalid".to_string(),
+            options: DownloadUrlOptions::default(),
+        },
+    ];
+    let results = object_service
+        .get_download_urls_batch(add_token(
+            Request::new(GetDownloadUrlsBatch { entries }),
+            USER1_OIDC_TOKEN,
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].object_id, own_object);
+    assert!(results[0].url.is_none());
+    assert!(results[0].error.as_ref().unwrap().contains("ttl"));
+    assert_eq!(results[1].object_id, foreign_object);
+    assert!(results[1].url.is_none());
+    assert!(results[1]
+        .error
+        .as_ref()
+        .unwrap()
+        .starts_with("Unauthorized"));
+    assert!(results[2].url.is_none());
+    assert!(results[2].error.as_ref().unwrap().starts_with("Invalid id"));
+
+    // Batch size is capped
+    let entries = vec![
+        BatchDownloadUrlEntry {
+            object_id: own_object,
+            options: DownloadUrlOptions::default(),
+        };
+        MAX_DOWNLOAD_URL_BATCH_SIZE + 1
+    ];
+    assert!(object_service
+        .get_download_urls_batch(add_token(
+            Request::new(GetDownloadUrlsBatch { entries }),
+            USER1_OIDC_TOKEN,
+        ))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn grpc_create_download_links_stream() {
+    // Init gRPC services
+    let (_, project_service, _, _, object_service, _) = init_grpc_services().await;
+
+    let own_project = fast_track_grpc_project_create(&project_service, USER1_OIDC_TOKEN).await;
+    let foreign_project = fast_track_grpc_project_create(&project_service, ADMIN_OIDC_TOKEN).await;
+    let own_object = create_available_object(
+        &object_service,
+        USER1_OIDC_TOKEN,
+        &own_project.id,
+        &rand_string(64),
+    )
+    .await;
+    let foreign_object = create_available_object(
+        &object_service,
+        ADMIN_OIDC_TOKEN,
+        &foreign_project.id,
+        &rand_string(64),
+    )
+    .await;
+
+    let entries = [&own_object, &foreign_object, &own_object]
+        .into_iter()
+        .map(|id| BatchDownloadUrlEntry {
+            object_id: id.to_string(),
+            options: DownloadUrlOptions::default(),
+        })
+        .collect();
+    let messages = object_service
+        .create_download_links_stream(add_token(
+            Request::new(CreateDownloadLinksStream { entries }),
+            USER1_OIDC_TOKEN,
+        ))
+        .await
+        .unwrap()
+        .into_inner()
+        .collect::<Result<Vec<_>, _>>()
+        .await
+        .unwrap();
+
+    // The forbidden object is reported inline and the stream continues
+    assert_eq!(messages.len(), 4);
+    let links = messages[..3]
+        .iter()
+        .map(|message| match message {
+            DownloadLinksStreamMessage::Link(link) => link.clone(),
+            DownloadLinksStreamMessage::Summary { .. } => panic!("Summary before last link"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(links[1].object_id, foreign_object);
+    assert!(links[1].url.is_none());
+    assert!(links[1].error.as_ref().unwrap().starts_with("Unauthorized"));
+    assert_eq!(links[2].object_id, own_object);
+
+    let failed_links = links.iter().filter(|link| link.url.is_none()).count() as u64;
+    match &messages[3] {
+        DownloadLinksStreamMessage::Summary { succeeded, failed } => {
+            assert_eq!(*failed, failed_links);
+            assert_eq!(succeeded + failed, 3);
+        }
+        DownloadLinksStreamMessage::Link(_) => panic!("Missing summary"),
+    }
+}
+
+#[tokio::test]
+async fn grpc_object_lifecycle() {
+    // Init gRPC services
+    let (_, project_service, _, _, object_service, _) = init_grpc_services().await;
+    let project = fast_track_grpc_project_create(&project_service, ADMIN_OIDC_TOKEN).await;
+
+    // Reserved states can not be used
+    let lifecycle_kv = |value: &str| UpdateProjectKeyValuesRequest {
+        project_id: project.id.clone(),
+        add_key_values: vec![KeyValue {
+            key: LIFECYCLE_KEY.to_string(),
+            value: value.to_string(),
+            variant: KeyValueVariant::Label as i32,
+        }],
+        remove_key_values: vec![],
+    };
+    let reserved = r#"{"initial":"available","states":["available"],"transitions":[]}"#;
+    assert!(project_service
+        .update_project_key_values(add_token(
+            Request::new(lifecycle_kv(reserved)),
+            ADMIN_OIDC_TOKEN
+        ))
+        .await
+        .is_err());
+
+    // Two-state machine draft -> published
+    let lifecycle = r#"{
+        "initial": "draft",
+        "states": ["draft", "published"],
+        "transitions": [{"from": "draft", "to": "published"}]
+    }"#;
+    project_service
+        .update_project_key_values(add_token(
+            Request::new(lifecycle_kv(lifecycle)),
+            ADMIN_OIDC_TOKEN,
+        ))
+        .await
+        .unwrap();
+
+    let object_id = create_available_object(
+        &object_service,
+        ADMIN_OIDC_TOKEN,
+        &project.id,
+        &rand_string(64),
+    )
+    .await;
+    let get_state = || {
+        add_token(
+            Request::new(GetObjectLifecycleState {
+                object_id: object_id.clone(),
+            }),
+            ADMIN_OIDC_TOKEN,
+        )
+    };
+    let state = object_service
+        .get_object_lifecycle_state(get_state())
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(state.state, "draft");
+    assert_eq!(state.allowed_next_states, vec!["published".to_string()]);
+
+    // Undefined transitions are rejected by the update and the transition rpc
+    let update = UpdateObjectRequest {
+        object_id: object_id.clone(),
+        name: None,
+        description: None,
+        add_key_values: vec![KeyValue {
+            key: LIFECYCLE_STATE_KEY.to_string(),
+            value: "draft".to_string(),
+            variant: KeyValueVariant::Label as i32,
+        }],
+        remove_key_values: vec![],
+        data_class: 1,
+        hashes: vec![],
+        parent: None,
+        force_revision: false,
+        data_license_tag: None,
+        metadata_license_tag: None,
+    };
+    let err = object_service
+        .update_object(add_token(Request::new(update), ADMIN_OIDC_TOKEN))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+
+    let transition = |state: &str| {
+        add_token(
+            Request::new(TransitionObjectState {
+                object_id: object_id.clone(),
+                state: state.to_string(),
+            }),
+            ADMIN_OIDC_TOKEN,
+        )
+    };
+    let err = object_service
+        .transition_object_state(transition("archived"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+
+    // Valid transition
+    let object = object_service
+        .transition_object_state(transition("published"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(object
+        .key_values
+        .iter()
+        .any(|kv| kv.key == LIFECYCLE_STATE_KEY && kv.value == "published"));
+    let state = object_service
+        .get_object_lifecycle_state(get_state())
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(state.state, "published");
+    assert!(state.allowed_next_states.is_empty());
+
+    // Published is final
+    let err = object_service
+        .transition_object_state(transition("draft"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+}
@@ -1,20 +1,25 @@
 use std::str::FromStr;
 
+use aruna_rust_api::api::storage::models::v2::{
+    permission::ResourceId, Permission, PermissionLevel,
+};
 use aruna_rust_api::api::storage::services::v2::{
-    user_service_server::UserService, AcknowledgePersonalNotificationsRequest,
-    CreateApiTokenRequest, GetPersonalNotificationsRequest, PersonalNotificationVariant, Reference,
+    create_collection_request::Parent as CollectionParent, user_service_server::UserService,
+    AcknowledgePersonalNotificationsRequest, CreateApiTokenRequest,
+    GetPersonalNotificationsRequest, GetUserRequest, PersonalNotificationVariant, Reference,
     ReferenceType,
 };
-use aruna_server::database::enums::DbPermissionLevel;
+use aruna_server::database::enums::{DbPermissionLevel, ObjectMapping};
+use aruna_server::utils::pagination_utils::{NEXT_CURSOR_KEY, PAGE_CURSOR_KEY, PAGE_SIZE_KEY};
 use diesel_ulid::DieselUlid;
 use itertools::Itertools;
 
 use crate::common::{
     init::init_service_block,
     test_utils::{
-        add_token, fast_track_grpc_permission_add, fast_track_grpc_permission_delete,
-        fast_track_grpc_project_create, ADMIN_OIDC_TOKEN, USER1_OIDC_TOKEN, USER2_OIDC_TOKEN,
-        USER2_ULID,
+        add_token, fast_track_grpc_collection_create, fast_track_grpc_permission_add,
+        fast_track_grpc_permission_delete, fast_track_grpc_project_create, ADMIN_OIDC_TOKEN,
+        USER1_OIDC_TOKEN, USER2_OIDC_TOKEN, USER2_ULID,
     },
 };
 
@@ -204,3 +209,148 @@ async fn grpc_add_token() {
 
     //ToDo extend test
 }
+
+#[tokio::test]
+async fn grpc_who_am_i_effective_permissions() {
+    // Init gRPC services
+    let service_block = init_service_block().await;
+
+    // Create random project with collection
+    let project =
+        fast_track_grpc_project_create(&service_block.project_service, ADMIN_OIDC_TOKEN).await;
+    let collection = fast_track_grpc_collection_create(
+        &service_block.collection_service,
+        ADMIN_OIDC_TOKEN,
+        CollectionParent::ProjectId(project.id.clone()),
+    )
+    .await;
+
+    let project_ulid = DieselUlid::from_str(&project.id).unwrap();
+    let collection_ulid = DieselUlid::from_str(&collection.id).unwrap();
+    let user2_ulid = DieselUlid::from_str(USER2_ULID).unwrap();
+
+    // User is admin through the project and reader through a direct grant on the collection
+    fast_track_grpc_permission_add(
+        &service_block.auth_service,
+        ADMIN_OIDC_TOKEN,
+        &user2_ulid,
+        &project_ulid,
+        DbPermissionLevel::ADMIN,
+    )
+    .await;
+    fast_track_grpc_permission_add(
+        &service_block.auth_service,
+        ADMIN_OIDC_TOKEN,
+        &user2_ulid,
+        &collection_ulid,
+        DbPermissionLevel::READ,
+    )
+    .await;
+
+    // Own user only contains the direct grants
+    let user = service_block
+        .user_service
+        .get_user(add_token(
+            tonic::Request::new(GetUserRequest {
+                user_id: "".to_string(),
+            }),
+            USER2_OIDC_TOKEN,
+        ))
+        .await
+        .unwrap()
+        .into_inner()
+        .user
+        .unwrap();
+    assert!(user
+        .attributes
+        .unwrap()
+        .personal_permissions
+        .contains(&Permission {
+            permission_level: PermissionLevel::Read as i32,
+            resource_id: Some(ResourceId::CollectionId(collection.id.clone())),
+        }));
+
+    // Merged result contains the highest permission level
+    let effective_permissions = |page_size: Option<&str>, cursor: Option<&str>| {
+        let mut request = add_token(tonic::Request::new(()), USER2_OIDC_TOKEN);
+        if let Some(page_size) = page_size {
+            request
+                .metadata_mut()
+                .insert(PAGE_SIZE_KEY, page_size.parse().unwrap());
+        }
+        if let Some(cursor) = cursor {
+            request
+                .metadata_mut()
+                .insert(PAGE_CURSOR_KEY, cursor.parse().unwrap());
+        }
+        service_block
+            .user_service
+            .get_effective_permissions(request)
+    };
+    let permissions = effective_permissions(None, None)
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(permissions.contains(&Permission {
+        permission_level: PermissionLevel::Admin as i32,
+        resource_id: Some(ResourceId::ProjectId(project.id.clone())),
+    }));
+    assert!(permissions.contains(&Permission {
+        permission_level: PermissionLevel::Admin as i32,
+        resource_id: Some(ResourceId::CollectionId(collection.id.clone())),
+    }));
+    assert!(!permissions.contains(&Permission {
+        permission_level: PermissionLevel::Read as i32,
+        resource_id: Some(ResourceId::CollectionId(collection.id.clone())),
+    }));
+
+    // Effective permissions are paginated by resource id
+    let first_page = effective_permissions(Some("1"), None).await.unwrap();
+    let cursor = first_page
+        .metadata()
+        .get(NEXT_CURSOR_KEY)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(first_page.into_inner().len(), 1);
+    let second_page = effective_permissions(None, Some(cursor.as_str()))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(second_page.len(), permissions.len() - 1);
+
+    // New collections below granted resources invalidate the cached view
+    let new_collection = fast_track_grpc_collection_create(
+        &service_block.collection_service,
+        ADMIN_OIDC_TOKEN,
+        CollectionParent::ProjectId(project.id.clone()),
+    )
+    .await;
+    assert!(service_block
+        .cache
+        .get_effective_permissions(&user2_ulid)
+        .unwrap()
+        .contains(&(
+            DieselUlid::from_str(&new_collection.id).unwrap(),
+            ObjectMapping::COLLECTION(DbPermissionLevel::ADMIN)
+        )));
+
+    // Removing the project permission invalidates the cached view
+    fast_track_grpc_permission_delete(
+        &service_block.auth_service,
+        ADMIN_OIDC_TOKEN,
+        &user2_ulid,
+        &project_ulid,
+    )
+    .await;
+    let effective = service_block
+        .cache
+        .get_effective_permissions(&user2_ulid)
+        .unwrap();
+    assert!(effective.contains(&(
+        collection_ulid,
+        ObjectMapping::COLLECTION(DbPermissionLevel::READ)
+    )));
+    assert!(!effective.iter().any(|(id, _)| id == &project_ulid));
+}
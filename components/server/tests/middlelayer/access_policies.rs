@@ -0,0 +1,175 @@
+use crate::common::init::{
+    init_database_handler_middlelayer, init_permission_handler, init_token_handler,
+};
+use crate::common::test_utils;
+use aruna_rust_api::api::storage::services::v2::CreateApiTokenRequest;
+use aruna_server::auth::structs::Context;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::access_policy_dsl::{
+    LabelMatch, PolicyEffect, PolicyRule, SubjectMatch,
+};
+use aruna_server::database::dsls::object_dsl::{KeyValue, KeyValueVariant, KeyValues, Object};
+use aruna_server::database::dsls::user_dsl::CustomAttributes;
+use aruna_server::database::enums::{DbPermissionLevel, ObjectMapping, ObjectType};
+use aruna_server::middlelayer::access_policy_request_types::{GetAccessPolicy, SetAccessPolicy};
+use aruna_server::middlelayer::token_request_types::CreateToken;
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+use postgres_types::Json;
+
+fn rule(effect: PolicyEffect, classification: &str) -> PolicyRule {
+    PolicyRule {
+        effect,
+        subject: vec![SubjectMatch {
+            attribute_name: "group".to_string(),
+            attribute_value: "G".to_string(),
+        }],
+        labels: vec![LabelMatch {
+            key: "classification".to_string(),
+            value: Some(classification.to_string()),
+        }],
+        permission: DbPermissionLevel::READ,
+    }
+}
+
+#[tokio::test]
+async fn access_policies() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+    let token_handler = init_token_handler(db_handler.database.clone(), cache.clone()).await;
+    let authorizer = init_permission_handler(cache.clone(), token_handler.clone()).await;
+    let pubkey_serial = token_handler.get_current_pubkey_serial() as i32;
+
+    // member with write permissions on the project and outsider without permissions,
+    // both in group G
+    let project_id = DieselUlid::generate();
+    let mut member = test_utils::new_user(vec![ObjectMapping::PROJECT(project_id)]);
+    let mut outsider = test_utils::new_user(vec![]);
+    for user in [&mut member, &mut outsider] {
+        user.attributes.0.custom_attributes = vec![CustomAttributes {
+            attribute_name: "group".to_string(),
+            attribute_value: "G".to_string(),
+        }];
+        user.create(client).await.unwrap();
+        cache.add_user(user.id, user.clone());
+    }
+
+    // project with a public, a secret and an unlabeled object
+    let mut project = test_utils::new_object(member.id, project_id, ObjectType::PROJECT);
+    project.create(client).await.unwrap();
+    let mut objects = Vec::new();
+    for classification in [Some("public"), Some("secret"), None] {
+        let mut object =
+            test_utils::new_object(member.id, DieselUlid::generate(), ObjectType::OBJECT);
+        object.key_values = Json(KeyValues(
+            classification
+                .map(|value| KeyValue {
+                    key: "classification".to_string(),
+                    value: value.to_string(),
+                    variant: KeyValueVariant::LABEL,
+                    value_type: None,
+                })
+                .into_iter()
+                .collect(),
+        ));
+        object.create(client).await.unwrap();
+        test_utils::new_internal_relation(&project, &object)
+            .create(client)
+            .await
+            .unwrap();
+        objects.push(object.id);
+    }
+    for id in objects.iter().chain([&project.id]) {
+        cache.add_object(Object::get_object_with_relations(id, client).await.unwrap());
+    }
+    let (public, secret, unlabeled) = (objects[0], objects[1], objects[2]);
+
+    db_handler
+        .set_access_policy(
+            SetAccessPolicy {
+                project_id: project_id.to_string(),
+                rules: vec![
+                    rule(PolicyEffect::ALLOW, "public"),
+                    rule(PolicyEffect::DENY, "secret"),
+                ],
+            },
+            member.id,
+        )
+        .await
+        .unwrap();
+
+    // personal tokens of both users
+    let mut secrets = Vec::new();
+    for user in [&member, &outsider] {
+        let (token_id, token) = db_handler
+            .create_token(
+                &user.id,
+                pubkey_serial,
+                CreateToken(CreateApiTokenRequest {
+                    name: "personal".to_string(),
+                    permission: None,
+                    expires_at: Some(prost_wkt_types::Timestamp {
+                        seconds: Utc::now().timestamp() + 86400,
+                        nanos: 0,
+                    }),
+                }),
+            )
+            .await
+            .unwrap();
+        secrets.push(
+            token_handler
+                .sign_user_token(&user.id, &token_id, Some(token.expires_at.into()))
+                .unwrap(),
+        );
+    }
+    let (member_token, outsider_token) = (&secrets[0], &secrets[1]);
+    let check = |token: &String, id: DieselUlid, level: DbPermissionLevel| {
+        let authorizer = authorizer.clone();
+        let token = token.clone();
+        async move {
+            authorizer
+                .check_permissions(&token, vec![Context::res_ctx(id, level, true)])
+                .await
+        }
+    };
+
+    // allowed by label without any role
+    assert!(check(outsider_token, public, DbPermissionLevel::READ)
+        .await
+        .is_ok());
+    assert!(check(outsider_token, public, DbPermissionLevel::WRITE)
+        .await
+        .is_err());
+
+    // denied by label although the role allows it
+    let denied = check(member_token, secret, DbPermissionLevel::READ)
+        .await
+        .unwrap_err();
+    assert_eq!(denied.code(), tonic::Code::PermissionDenied);
+    assert!(check(member_token, unlabeled, DbPermissionLevel::WRITE)
+        .await
+        .is_ok());
+
+    // denied by default if neither a policy nor a role allows it
+    assert!(check(outsider_token, unlabeled, DbPermissionLevel::READ)
+        .await
+        .is_err());
+    assert!(check(outsider_token, secret, DbPermissionLevel::READ)
+        .await
+        .is_err());
+
+    // without the policy only roles apply
+    let request = GetAccessPolicy {
+        project_id: project_id.to_string(),
+    };
+    db_handler.delete_access_policy(&request).await.unwrap();
+    assert!(db_handler.get_access_policy(&request).await.is_err());
+    assert!(check(member_token, secret, DbPermissionLevel::READ)
+        .await
+        .is_ok());
+    assert!(check(outsider_token, public, DbPermissionLevel::READ)
+        .await
+        .is_err());
+}
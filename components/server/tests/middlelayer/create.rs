@@ -1,7 +1,8 @@
 use crate::common::init::init_database_handler_middlelayer;
 use crate::common::test_utils;
 use aruna_rust_api::api::storage::models::v2::{
-    relation, InternalRelationVariant, Relation, RelationDirection, ResourceVariant,
+    relation, InternalRelationVariant, KeyValue, KeyValueVariant, Relation, RelationDirection,
+    ResourceVariant,
 };
 use aruna_rust_api::api::storage::services::v2::create_collection_request::Parent as CollectionParent;
 use aruna_rust_api::api::storage::services::v2::create_dataset_request::Parent as DatasetParent;
@@ -14,7 +15,8 @@ use aruna_server::database::dsls::internal_relation_dsl::INTERNAL_RELATION_VARIA
 use aruna_server::database::dsls::license_dsl::ALL_RIGHTS_RESERVED;
 use aruna_server::database::dsls::object_dsl::{EndpointInfo, Object};
 use aruna_server::database::enums::{DataClass, ObjectStatus, ObjectType, ReplicationStatus};
-use aruna_server::middlelayer::create_request_types::CreateRequest;
+use aruna_server::middlelayer::create_request_types::{CreateRequest, ExistingObjectMode};
+use aruna_server::utils::name_utils::NAME_NORMALIZATION_KEY;
 use diesel_ulid::DieselUlid;
 use itertools::Itertools;
 use rand::distributions::Alphanumeric;
@@ -467,3 +469,240 @@ async fn create_object_with_relations() {
     );
     assert_eq!(outbound_relation.origin_pid, obj_2.object.id);
 }
+
+#[tokio::test]
+async fn create_or_get_object_race() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+
+    // create user
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+
+    // create parent
+    let parent = CreateRequest::Project(
+        CreateProjectRequest {
+            name: random_name().to_lowercase(),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            preferred_endpoint: "".to_string(),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            default_data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        },
+        DieselUlid::generate().to_string(),
+    );
+    let (parent, _) = db_handler
+        .create_resource(parent, user.id, false)
+        .await
+        .unwrap();
+    db_handler.cache.add_object(parent.clone());
+
+    let request = CreateObjectRequest {
+        name: random_name(),
+        title: "".to_string(),
+        description: "test".to_string(),
+        key_values: vec![],
+        relations: vec![],
+        data_class: 1,
+        hashes: vec![],
+        parent: Some(ObjectParent::ProjectId(parent.object.id.to_string())),
+        metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        authors: vec![],
+    };
+
+    // Concurrent creations with the same name all return the same object
+    let tasks = (0..8)
+        .map(|_| {
+            let db_handler = db_handler.clone();
+            let request = request.clone();
+            tokio::spawn(async move {
+                db_handler
+                    .create_or_get_object(
+                        request,
+                        user.id,
+                        false,
+                        false,
+                        ExistingObjectMode::Get,
+                        None,
+                    )
+                    .await
+            })
+        })
+        .collect_vec();
+    let mut results = Vec::new();
+    for task in tasks {
+        results.push(task.await.unwrap().unwrap());
+    }
+    assert_eq!(results.iter().filter(|(_, created)| *created).count(), 1);
+    let object_id = results[0].0.object.id;
+    assert!(results
+        .iter()
+        .all(|(object, _)| object.object.id == object_id));
+    let parent = Object::get_object_with_relations(&parent.object.id, client)
+        .await
+        .unwrap();
+    assert_eq!(
+        parent
+            .outbound_belongs_to
+            .0
+            .iter()
+            .filter(|rel| rel.target_name == request.name)
+            .count(),
+        1
+    );
+
+    // Without mode the name conflict fails
+    assert!(db_handler
+        .create_or_get_object(
+            request.clone(),
+            user.id,
+            false,
+            false,
+            ExistingObjectMode::Fail,
+            None,
+        )
+        .await
+        .is_err());
+
+    // Overwrite creates a new revision of the existing object
+    let (revision, created) = db_handler
+        .create_or_get_object(
+            request.clone(),
+            user.id,
+            false,
+            false,
+            ExistingObjectMode::Overwrite,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(!created);
+    assert_ne!(revision.object.id, object_id);
+    assert_eq!(revision.object.name, request.name);
+    assert_eq!(revision.object.revision_number, 1);
+    assert_eq!(revision.object.object_status, ObjectStatus::INITIALIZING);
+}
+
+#[tokio::test]
+async fn create_with_name_normalization() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+
+    let create_project = |policy: Option<&str>| {
+        CreateRequest::Project(
+            CreateProjectRequest {
+                name: random_name().to_lowercase(),
+                title: "".to_string(),
+                description: "test".to_string(),
+                key_values: policy
+                    .map(|policy| KeyValue {
+                        key: NAME_NORMALIZATION_KEY.to_string(),
+                        value: policy.to_string(),
+                        variant: KeyValueVariant::Label as i32,
+                    })
+                    .into_iter()
+                    .collect(),
+                relations: vec![],
+                data_class: 1,
+                preferred_endpoint: "".to_string(),
+                metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+                default_data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+                authors: vec![],
+            },
+            DieselUlid::generate().to_string(),
+        )
+    };
+    let create_collection = |project_id: DieselUlid, name: &str| {
+        CreateRequest::Collection(CreateCollectionRequest {
+            name: name.to_string(),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            parent: Some(CollectionParent::ProjectId(project_id.to_string())),
+            metadata_license_tag: Some(ALL_RIGHTS_RESERVED.to_string()),
+            default_data_license_tag: Some(ALL_RIGHTS_RESERVED.to_string()),
+            authors: vec![],
+        })
+    };
+    let precomposed = "caf\u{e9}";
+    let combining = "cafe\u{301}";
+
+    // NFC by default: differently normalized names are the same name
+    let (project, _) = db_handler
+        .create_resource(create_project(None), user.id, false)
+        .await
+        .unwrap();
+    db_handler.cache.add_object(project.clone());
+    let (collection, _) = db_handler
+        .create_resource(
+            create_collection(project.object.id, combining),
+            user.id,
+            false,
+        )
+        .await
+        .unwrap();
+    // Original name is kept for display
+    assert_eq!(collection.object.name, combining);
+    assert!(db_handler
+        .create_resource(
+            create_collection(project.object.id, precomposed),
+            user.id,
+            false
+        )
+        .await
+        .is_err());
+    let found = Object::get_child_by_name(&project.object.id, precomposed, client)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.object.id, collection.object.id);
+    // Case is significant without case folding
+    assert!(db_handler
+        .create_resource(create_collection(project.object.id, "Data"), user.id, false)
+        .await
+        .is_ok());
+    assert!(db_handler
+        .create_resource(create_collection(project.object.id, "data"), user.id, false)
+        .await
+        .is_ok());
+
+    // Case folding projects ignore the case
+    let (project, _) = db_handler
+        .create_resource(create_project(Some("nfc-casefold")), user.id, false)
+        .await
+        .unwrap();
+    db_handler.cache.add_object(project.clone());
+    db_handler
+        .create_resource(create_collection(project.object.id, "Data"), user.id, false)
+        .await
+        .unwrap();
+    assert!(db_handler
+        .create_resource(create_collection(project.object.id, "data"), user.id, false)
+        .await
+        .is_err());
+
+    // Projects without normalization compare names as they are
+    let (project, _) = db_handler
+        .create_resource(create_project(Some("none")), user.id, false)
+        .await
+        .unwrap();
+    db_handler.cache.add_object(project.clone());
+    for name in [precomposed, combining] {
+        let (collection, _) = db_handler
+            .create_resource(create_collection(project.object.id, name), user.id, false)
+            .await
+            .unwrap();
+        assert_eq!(collection.object.name, name);
+    }
+}
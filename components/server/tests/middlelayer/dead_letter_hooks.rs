@@ -0,0 +1,167 @@
+use crate::common::init::{init_cache, init_database, init_database_handler, init_nats_client};
+use crate::common::test_utils;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::hook_dsl::{
+    ExternalHook, Hook, HookVariant, HookWithAssociatedProject, Method, TemplateVariant, Trigger,
+    TriggerVariant,
+};
+use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::enums::{ObjectMapping, ObjectType};
+use aruna_server::hooks::hook_handler::HookMessage;
+use aruna_server::hooks::hook_queue::{send_with_retries, HookQueueConfig, OverflowPolicy};
+use aruna_server::middlelayer::hooks_request_types::{
+    DiscardDeadLetterHook, ListDeadLetterHooks, RetryDeadLetterHook,
+};
+use diesel_ulid::DieselUlid;
+use postgres_types::Json;
+use std::time::Duration;
+
+#[tokio::test]
+async fn dead_letter_hooks() {
+    // init with an observable hook queue
+    let db = init_database().await;
+    let nats = init_nats_client().await;
+    let cache = init_cache(db.clone(), true).await;
+    let (hook_sender, hook_receiver) = async_channel::unbounded();
+    let db_handler = init_database_handler(db, nats, cache.clone(), hook_sender).await;
+    let client = &db_handler.database.get_client().await.unwrap();
+
+    // create user, project, object and a hook which calls an unreachable host
+    let project_id = DieselUlid::generate();
+    let mut user = test_utils::new_user(vec![ObjectMapping::PROJECT(project_id)]);
+    user.create(client).await.unwrap();
+    let mut project = test_utils::new_object(user.id, project_id, ObjectType::PROJECT);
+    let mut object = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    for resource in [&mut project, &mut object] {
+        resource.create(client).await.unwrap();
+    }
+    test_utils::new_internal_relation(&project, &object)
+        .create(client)
+        .await
+        .unwrap();
+    for id in [project.id, object.id] {
+        cache.add_object(
+            Object::get_object_with_relations(&id, client)
+                .await
+                .unwrap(),
+        );
+    }
+    let mut hook = Hook {
+        id: DieselUlid::generate(),
+        name: "unreachable".to_string(),
+        description: "".to_string(),
+        project_ids: vec![project_id],
+        owner: user.id,
+        trigger: Json(Trigger {
+            variant: TriggerVariant::RESOURCE_CREATED,
+            filter: vec![],
+        }),
+        timeout: chrono::Utc::now()
+            .naive_utc()
+            .checked_add_days(chrono::Days::new(1))
+            .unwrap(),
+        hook: Json(HookVariant::External(ExternalHook {
+            url: "http://127.0.0.1:1/hook".to_string(),
+            credentials: None,
+            template: TemplateVariant::Basic,
+            method: Method::POST,
+        })),
+    };
+    hook.create(client).await.unwrap();
+    let message = HookMessage {
+        hook: HookWithAssociatedProject {
+            id: hook.id,
+            name: hook.name.clone(),
+            description: hook.description.clone(),
+            project_ids: hook.project_ids.clone(),
+            owner: hook.owner,
+            trigger: hook.trigger.clone(),
+            timeout: hook.timeout,
+            hook: hook.hook.clone(),
+            project_id,
+        },
+        object: cache.get_object(&object.id).unwrap(),
+        user_id: user.id,
+        request_id: Some("request".to_string()),
+    };
+
+    // delivery fails after all retries
+    let config = HookQueueConfig {
+        capacity: 10,
+        workers: 1,
+        workers_per_host: 1,
+        overflow: OverflowPolicy::Block,
+        replay_interval: Duration::from_secs(30),
+        metrics_interval: Duration::from_secs(60),
+        max_retries: 2,
+        retry_backoff: Duration::from_millis(10),
+    };
+    let request = reqwest::Client::new()
+        .post("http://127.0.0.1:1/hook")
+        .json(&serde_json::json!({"hook_id": hook.id}));
+    let failure = send_with_retries(request, &config).await.unwrap_err();
+    assert_eq!(failure.attempts, config.max_retries + 1);
+
+    // failed call lands in the dead-letter store
+    let dead_letter = db_handler
+        .dead_letter_hook(&message, failure.clone())
+        .await
+        .unwrap();
+    let listed = db_handler
+        .list_dead_letter_hooks(ListDeadLetterHooks {
+            hook_id: Some(hook.id.to_string()),
+        })
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, dead_letter.id);
+    assert_eq!(listed[0].object_id, object.id);
+    assert_eq!(listed[0].reason, failure.reason);
+    assert_eq!(listed[0].attempts, 3);
+
+    // manual retry queues the call again and removes it from the store
+    db_handler
+        .retry_dead_letter_hook(RetryDeadLetterHook {
+            id: dead_letter.id.to_string(),
+        })
+        .await
+        .unwrap();
+    let retried = hook_receiver.try_recv().unwrap();
+    assert_eq!(retried.hook.id, hook.id);
+    assert_eq!(retried.hook.project_id, project_id);
+    assert_eq!(retried.object.object.id, object.id);
+    assert_eq!(retried.request_id, Some("request".to_string()));
+    assert!(db_handler
+        .list_dead_letter_hooks(ListDeadLetterHooks {
+            hook_id: Some(hook.id.to_string()),
+        })
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(db_handler
+        .retry_dead_letter_hook(RetryDeadLetterHook {
+            id: dead_letter.id.to_string(),
+        })
+        .await
+        .is_err());
+
+    // discarded calls are not queued
+    let discarded = db_handler
+        .dead_letter_hook(&message, failure)
+        .await
+        .unwrap();
+    db_handler
+        .discard_dead_letter_hook(DiscardDeadLetterHook {
+            id: discarded.id.to_string(),
+        })
+        .await
+        .unwrap();
+    assert!(db_handler
+        .list_dead_letter_hooks(ListDeadLetterHooks {
+            hook_id: Some(hook.id.to_string()),
+        })
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(hook_receiver.is_empty());
+}
@@ -7,10 +7,16 @@ use aruna_server::database::crud::CrudDb;
 use aruna_server::database::dsls::internal_relation_dsl::{
     InternalRelation, INTERNAL_RELATION_VARIANT_BELONGS_TO, INTERNAL_RELATION_VARIANT_VERSION,
 };
-use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::dsls::object_dsl::{KeyValue, KeyValueVariant, KeyValues, Object};
 use aruna_server::database::enums::{ObjectStatus, ObjectType};
-use aruna_server::middlelayer::delete_request_types::DeleteRequest;
+use aruna_server::middlelayer::delete_request_types::{
+    BatchDeleteStatus, DeleteOnHold, DeleteRequest, DeleteTooEarly, LEGAL_HOLD_KEY,
+    MIN_DELETE_AGE_KEY,
+};
 use diesel_ulid::DieselUlid;
+use futures::StreamExt;
+use postgres_types::Json;
+use std::str::FromStr;
 
 #[tokio::test]
 async fn delete_project() {
@@ -422,3 +428,212 @@ async fn delete_hierarchies() {
         assert_eq!(&del_rel.1.relation_name, "DELETED")
     }
 }
+
+#[tokio::test]
+async fn min_delete_age() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = db_handler.database.get_client().await.unwrap();
+
+    // create project with a minimum delete age of one hour and two objects
+    let mut user = test_utils::new_user(vec![]);
+    user.create(&client).await.unwrap();
+    let mut project = new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    project.key_values = Json(KeyValues(vec![KeyValue {
+        key: MIN_DELETE_AGE_KEY.to_string(),
+        value: "3600".to_string(),
+        variant: KeyValueVariant::LABEL,
+        value_type: None,
+    }]));
+    project.create(&client).await.unwrap();
+    let mut young = new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    let mut old = new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    for object in [&mut young, &mut old] {
+        object.create(&client).await.unwrap();
+        new_internal_relation(&project, object)
+            .create(&client)
+            .await
+            .unwrap();
+    }
+    client
+        .execute(
+            "UPDATE objects SET created_at = NOW() - INTERVAL '2 hours' WHERE id = $1;",
+            &[&old.id],
+        )
+        .await
+        .unwrap();
+    for id in [project.id, young.id, old.id] {
+        db_handler.cache.add_object(
+            Object::get_object_with_relations(&id, &client)
+                .await
+                .unwrap(),
+        );
+    }
+    let delete = |object: &Object| {
+        DeleteRequest::Object(DeleteObjectRequest {
+            object_id: object.id.to_string(),
+            with_revisions: false,
+        })
+    };
+
+    // too young objects can not be deleted
+    let err = db_handler
+        .delete_resource_checked(delete(&young), false)
+        .await
+        .unwrap_err();
+    let too_early = err.downcast_ref::<DeleteTooEarly>().unwrap();
+    assert_eq!(too_early.object_id, young.id);
+    assert!(too_early.deletable_at > chrono::Utc::now().naive_utc());
+    assert_eq!(
+        Object::get(young.id, &client)
+            .await
+            .unwrap()
+            .unwrap()
+            .object_status,
+        ObjectStatus::AVAILABLE
+    );
+
+    // older objects can be deleted
+    db_handler
+        .delete_resource_checked(delete(&old), false)
+        .await
+        .unwrap();
+    assert_eq!(
+        Object::get(old.id, &client)
+            .await
+            .unwrap()
+            .unwrap()
+            .object_status,
+        ObjectStatus::DELETED
+    );
+
+    // admins can delete too young objects
+    db_handler
+        .delete_resource_checked(delete(&young), true)
+        .await
+        .unwrap();
+    assert_eq!(
+        Object::get(young.id, &client)
+            .await
+            .unwrap()
+            .unwrap()
+            .object_status,
+        ObjectStatus::DELETED
+    );
+}
+
+#[tokio::test]
+async fn batch_delete_legal_hold() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = db_handler.database.get_client().await.unwrap();
+    let legal_hold = || {
+        Json(KeyValues(vec![KeyValue {
+            key: LEGAL_HOLD_KEY.to_string(),
+            value: "case-42".to_string(),
+            variant: KeyValueVariant::LABEL,
+            value_type: None,
+        }]))
+    };
+
+    // project with a held collection, objects in the collection, a held object
+    // and free objects in the project
+    let mut user = test_utils::new_user(vec![]);
+    user.create(&client).await.unwrap();
+    let mut project = new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    project.create(&client).await.unwrap();
+    let mut collection = new_object(user.id, DieselUlid::generate(), ObjectType::COLLECTION);
+    collection.key_values = legal_hold();
+    collection.create(&client).await.unwrap();
+    new_internal_relation(&project, &collection)
+        .create(&client)
+        .await
+        .unwrap();
+    let mut held = Vec::new();
+    for _ in 0..2 {
+        let mut object = new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+        object.create(&client).await.unwrap();
+        new_internal_relation(&collection, &object)
+            .create(&client)
+            .await
+            .unwrap();
+        held.push(object.id);
+    }
+    let mut held_object = new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    held_object.key_values = legal_hold();
+    held_object.create(&client).await.unwrap();
+    new_internal_relation(&project, &held_object)
+        .create(&client)
+        .await
+        .unwrap();
+    held.push(held_object.id);
+    let mut free = Vec::new();
+    for _ in 0..3 {
+        let mut object = new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+        object.create(&client).await.unwrap();
+        new_internal_relation(&project, &object)
+            .create(&client)
+            .await
+            .unwrap();
+        free.push(object.id);
+    }
+    for id in [project.id, collection.id]
+        .iter()
+        .chain(held.iter())
+        .chain(free.iter())
+    {
+        db_handler.cache.add_object(
+            Object::get_object_with_relations(id, &client)
+                .await
+                .unwrap(),
+        );
+    }
+    let missing = DieselUlid::generate();
+
+    // held objects are skipped, unknown objects fail, all others are deleted
+    let ids = held
+        .iter()
+        .chain(free.iter())
+        .chain([missing].iter())
+        .cloned()
+        .collect::<Vec<_>>();
+    let results = db_handler
+        .delete_objects_batch(ids.clone(), false, false)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(results.len(), ids.len());
+    for (result, deleted) in results {
+        let id = DieselUlid::from_str(&result.object_id).unwrap();
+        if held.contains(&id) {
+            assert!(matches!(result.status, BatchDeleteStatus::Skipped(_)));
+            assert!(deleted.is_empty());
+        } else if free.contains(&id) {
+            assert_eq!(result.status, BatchDeleteStatus::Deleted);
+            assert_eq!(deleted, vec![id]);
+        } else {
+            assert_eq!(id, missing);
+            assert!(matches!(result.status, BatchDeleteStatus::Failed(_)));
+        }
+    }
+    for id in &held {
+        let object = Object::get(*id, &client).await.unwrap().unwrap();
+        assert_eq!(object.object_status, ObjectStatus::AVAILABLE);
+    }
+    for id in &free {
+        let object = Object::get(*id, &client).await.unwrap().unwrap();
+        assert_eq!(object.object_status, ObjectStatus::DELETED);
+    }
+
+    // legal holds also block admins and hold the resources below them
+    let err = db_handler
+        .delete_resource_checked(
+            DeleteRequest::Collection(DeleteCollectionRequest {
+                collection_id: collection.id.to_string(),
+            }),
+            true,
+        )
+        .await
+        .unwrap_err();
+    let on_hold = err.downcast_ref::<DeleteOnHold>().unwrap();
+    assert_eq!(on_hold.held_by, collection.id);
+}
@@ -0,0 +1,92 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::enums::{ObjectStatus, ObjectType};
+use aruna_server::middlelayer::expiry_request_types::{
+    expiry_label, get_expiry, is_expired, SetObjectExpiry,
+};
+use chrono::{Duration, Utc};
+use diesel_ulid::DieselUlid;
+
+#[tokio::test]
+async fn object_expiry() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+    let mut project = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    project.create(client).await.unwrap();
+
+    // Short lived object and an object which gets extended
+    let now = Utc::now().naive_utc();
+    let mut objects = vec![];
+    for _ in 0..2 {
+        let mut object =
+            test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+        object
+            .key_values
+            .0
+             .0
+            .push(expiry_label(now + Duration::seconds(1)));
+        object.create(client).await.unwrap();
+        test_utils::new_internal_relation(&project, &object)
+            .create(client)
+            .await
+            .unwrap();
+        objects.push(object);
+    }
+    let (short_lived, extended) = (&objects[0], &objects[1]);
+    assert!(!is_expired(short_lived, now));
+
+    // Expiry can only be set into the future
+    let mut extension = SetObjectExpiry {
+        object_id: extended.id.to_string(),
+        expires_at: (now - Duration::hours(1)).and_utc().to_rfc3339(),
+    };
+    assert!(db_handler
+        .set_object_expiry(extension.clone(), now)
+        .await
+        .is_err());
+    extension.expires_at = (now + Duration::hours(1)).and_utc().to_rfc3339();
+    let updated = db_handler
+        .set_object_expiry(extension.clone(), now)
+        .await
+        .unwrap();
+    assert_eq!(
+        get_expiry(&updated.object)
+            .unwrap()
+            .unwrap()
+            .and_utc()
+            .timestamp(),
+        (now + Duration::hours(1)).and_utc().timestamp()
+    );
+
+    // Expired before the sweeper runs
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    let now = Utc::now().naive_utc();
+    let short_lived = Object::get(short_lived.id, client).await.unwrap().unwrap();
+    assert!(is_expired(&short_lived, now));
+    assert_eq!(short_lived.object_status, ObjectStatus::AVAILABLE);
+    // Expired objects can not be extended anymore
+    assert!(db_handler
+        .set_object_expiry(
+            SetObjectExpiry {
+                object_id: short_lived.id.to_string(),
+                ..extension
+            },
+            now
+        )
+        .await
+        .is_err());
+
+    // Sweeper deletes only the expired object
+    let deleted = db_handler.expire_objects(now).await.unwrap();
+    assert!(deleted.contains(&short_lived.id));
+    assert!(!deleted.contains(&extended.id));
+    let short_lived = Object::get(short_lived.id, client).await.unwrap().unwrap();
+    assert_eq!(short_lived.object_status, ObjectStatus::DELETED);
+    let extended = Object::get(extended.id, client).await.unwrap().unwrap();
+    assert_eq!(extended.object_status, ObjectStatus::AVAILABLE);
+}
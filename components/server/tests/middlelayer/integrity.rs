@@ -0,0 +1,104 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::enums::{ObjectStatus, ObjectType};
+use aruna_server::middlelayer::integrity_request_types::{CheckIntegrity, LOST_AND_FOUND_NAME};
+use diesel_ulid::DieselUlid;
+
+#[tokio::test]
+async fn check_integrity() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+    let mut project = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    let mut collection =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::COLLECTION);
+    let mut object = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    let mut healthy = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    for resource in [&mut project, &mut collection, &mut object, &mut healthy] {
+        resource.create(client).await.unwrap();
+    }
+    let mut dangling = test_utils::new_internal_relation(&collection, &object);
+    for relation in [
+        &mut test_utils::new_internal_relation(&project, &collection),
+        &mut test_utils::new_internal_relation(&project, &healthy),
+        &mut dangling,
+    ] {
+        relation.create(client).await.unwrap();
+    }
+
+    // Consistent hierarchy
+    let request = CheckIntegrity {
+        project_id: project.id.to_string(),
+        repair: false,
+    };
+    let report = db_handler
+        .check_integrity(request.clone(), user.id)
+        .await
+        .unwrap();
+    assert!(report.is_consistent());
+    assert_eq!(report.scanned_resources, 4);
+    // Test objects are not finished on their endpoint yet
+    let mut missing = vec![object.id, healthy.id];
+    missing.sort();
+    assert_eq!(report.missing_blobs, missing);
+
+    // Seed an orphan: the parent is deleted but the relation to the object is left over
+    Object::set_deleted(&vec![collection.id], client)
+        .await
+        .unwrap();
+    let report = db_handler
+        .check_integrity(request.clone(), user.id)
+        .await
+        .unwrap();
+    assert_eq!(report.orphans, vec![object.id]);
+    assert_eq!(report.dangling_relations.len(), 1);
+    assert_eq!(report.dangling_relations[0].relation_id, dangling.id);
+    assert!(!report.repaired);
+    // Read-only scans do not change anything
+    let unchanged = Object::get_object_with_relations(&object.id, client)
+        .await
+        .unwrap();
+    assert!(unchanged.inbound_belongs_to.0.contains_key(&collection.id));
+    assert!(
+        Object::get_child_by_name(&project.id, LOST_AND_FOUND_NAME, client)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Repair moves the orphan into lost-and-found
+    let report = db_handler
+        .check_integrity(
+            CheckIntegrity {
+                repair: true,
+                ..request.clone()
+            },
+            user.id,
+        )
+        .await
+        .unwrap();
+    assert!(report.repaired);
+    assert_eq!(report.reparented, vec![object.id]);
+    let lost_and_found = Object::get_child_by_name(&project.id, LOST_AND_FOUND_NAME, client)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(Some(lost_and_found.object.id), report.lost_and_found);
+    assert_eq!(lost_and_found.object.object_type, ObjectType::COLLECTION);
+    assert_eq!(lost_and_found.object.object_status, ObjectStatus::AVAILABLE);
+    let repaired = Object::get_object_with_relations(&object.id, client)
+        .await
+        .unwrap();
+    assert!(repaired
+        .inbound_belongs_to
+        .0
+        .contains_key(&lost_and_found.object.id));
+    assert!(!repaired.inbound_belongs_to.0.contains_key(&collection.id));
+
+    let report = db_handler.check_integrity(request, user.id).await.unwrap();
+    assert!(report.is_consistent());
+}
@@ -1,6 +1,21 @@
 use crate::common::init::init_database_handler_middlelayer;
-use aruna_rust_api::api::storage::services::v2::CreateLicenseRequest;
-use aruna_server::database::dsls::license_dsl::License;
+use crate::common::test_utils;
+use aruna_rust_api::api::storage::services::v2::create_collection_request::Parent as CollectionParent;
+use aruna_rust_api::api::storage::services::v2::create_object_request::Parent as ObjectParent;
+use aruna_rust_api::api::storage::services::v2::{
+    CreateCollectionRequest, CreateLicenseRequest, CreateObjectRequest, CreateProjectRequest,
+};
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::license_dsl::{License, ALL_RIGHTS_RESERVED};
+use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::enums::{ObjectMapping, ObjectType};
+use aruna_server::middlelayer::create_request_types::CreateRequest;
+use aruna_server::middlelayer::license_request_types::{
+    GrantLicenseOverride, LicenseViolation, RevokeLicenseOverride, SetDefaultLicense,
+    SetLicenseTerms,
+};
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
 use itertools::Itertools;
 
 #[tokio::test]
@@ -50,3 +65,283 @@ async fn create_and_read_license() {
     assert!(all.iter().contains(&dummy_one));
     assert!(all.iter().contains(&dummy_two));
 }
+
+#[tokio::test]
+async fn license_terms() {
+    // Init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+
+    // Licenses with embargo and access expiry
+    let mut tags = Vec::new();
+    for tag in ["embargoed_license", "expiring_license"] {
+        let tag = format!("{tag}_{}", DieselUlid::generate());
+        db_handler
+            .create_license(CreateLicenseRequest {
+                tag: tag.clone(),
+                name: "time limited license".to_string(),
+                text: "Tests license terms".to_string(),
+                url: "test.org/time-limited-license".to_string(),
+            })
+            .await
+            .unwrap();
+        tags.push(tag);
+    }
+    let embargo_until = (Utc::now() + chrono::Duration::days(30)).to_rfc3339();
+    let embargoed = db_handler
+        .set_license_terms(SetLicenseTerms {
+            tag: tags[0].clone(),
+            embargo_until: Some(embargo_until),
+            access_days: None,
+        })
+        .await
+        .unwrap();
+    assert!(embargoed.embargo_until.is_some());
+    db_handler
+        .set_license_terms(SetLicenseTerms {
+            tag: tags[1].clone(),
+            embargo_until: None,
+            access_days: Some(7),
+        })
+        .await
+        .unwrap();
+
+    // Project with one object per license, the second one was created ten days ago
+    let project_id = DieselUlid::generate();
+    let mut user = test_utils::new_user(vec![ObjectMapping::PROJECT(project_id)]);
+    user.create(client).await.unwrap();
+    cache.add_user(user.id, user.clone());
+    let mut project = test_utils::new_object(user.id, project_id, ObjectType::PROJECT);
+    project.create(client).await.unwrap();
+    let mut objects = Vec::new();
+    for tag in &tags {
+        let mut object =
+            test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+        object.data_license.clone_from(tag);
+        object.create(client).await.unwrap();
+        test_utils::new_internal_relation(&project, &object)
+            .create(client)
+            .await
+            .unwrap();
+        objects.push(object.id);
+    }
+    client
+        .execute(
+            "UPDATE objects SET created_at = NOW() - INTERVAL '10 days' WHERE id = $1;",
+            &[&objects[1]],
+        )
+        .await
+        .unwrap();
+    for id in [project_id, objects[0], objects[1]] {
+        cache.add_object(
+            Object::get_object_with_relations(&id, client)
+                .await
+                .unwrap(),
+        );
+    }
+
+    // Access is denied before the embargo lifts
+    let err = db_handler
+        .check_license_terms(&objects[0], &user.id)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<LicenseViolation>(),
+        Some(LicenseViolation::Embargoed { tag, .. }) if tag == &tags[0]
+    ));
+
+    // Access is denied after it expired
+    let terms = db_handler
+        .get_object_license_terms(&objects[1])
+        .await
+        .unwrap();
+    assert!(terms.access_expires_at.unwrap() < Utc::now().naive_utc());
+    let err = db_handler
+        .check_license_terms(&objects[1], &user.id)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<LicenseViolation>(),
+        Some(LicenseViolation::AccessExpired { tag, .. }) if tag == &tags[1]
+    ));
+
+    // Overrides on a parent grant access to all objects until they are revoked
+    let grant = db_handler
+        .grant_license_override(
+            GrantLicenseOverride {
+                user_id: user.id.to_string(),
+                resource_id: project_id.to_string(),
+            },
+            user.id,
+        )
+        .await
+        .unwrap();
+    for object_id in &objects {
+        db_handler
+            .check_license_terms(object_id, &user.id)
+            .await
+            .unwrap();
+    }
+    db_handler
+        .revoke_license_override(RevokeLicenseOverride {
+            override_id: grant.id.to_string(),
+        })
+        .await
+        .unwrap();
+    assert!(db_handler
+        .check_license_terms(&objects[0], &user.id)
+        .await
+        .is_err());
+
+    // Licenses without terms do not restrict access
+    db_handler
+        .set_license_terms(SetLicenseTerms {
+            tag: tags[0].clone(),
+            embargo_until: None,
+            access_days: None,
+        })
+        .await
+        .unwrap();
+    db_handler
+        .check_license_terms(&objects[0], &user.id)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn default_license_inheritance() {
+    // Init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+    let tag = format!("default_license_{}", DieselUlid::generate());
+    db_handler
+        .create_license(CreateLicenseRequest {
+            tag: tag.clone(),
+            name: "default license".to_string(),
+            text: "Tests default license inheritance".to_string(),
+            url: "test.org/default-license".to_string(),
+        })
+        .await
+        .unwrap();
+
+    // Project -> collection with a default license
+    let project = CreateRequest::Project(
+        CreateProjectRequest {
+            name: test_utils::rand_string(32).to_lowercase(),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            preferred_endpoint: "".to_string(),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            default_data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        },
+        DieselUlid::generate().to_string(),
+    );
+    let (project, _) = db_handler
+        .create_resource(project, user.id, false)
+        .await
+        .unwrap();
+    cache.add_object(project.clone());
+    let collection = CreateRequest::Collection(CreateCollectionRequest {
+        name: test_utils::rand_string(32),
+        title: "".to_string(),
+        description: "test".to_string(),
+        key_values: vec![],
+        relations: vec![],
+        data_class: 1,
+        parent: Some(CollectionParent::ProjectId(project.object.id.to_string())),
+        default_data_license_tag: None,
+        metadata_license_tag: None,
+        authors: vec![],
+    });
+    let (collection, _) = db_handler
+        .create_resource(collection, user.id, false)
+        .await
+        .unwrap();
+    cache.add_object(collection.clone());
+
+    // Only projects and collections and existing licenses can be defaults
+    assert!(db_handler
+        .set_default_license(SetDefaultLicense {
+            resource_id: collection.object.id.to_string(),
+            license_tag: Some("not_a_license".to_string()),
+        })
+        .await
+        .is_err());
+    let collection = db_handler
+        .set_default_license(SetDefaultLicense {
+            resource_id: collection.object.id.to_string(),
+            license_tag: Some(tag.clone()),
+        })
+        .await
+        .unwrap();
+    cache.upsert_object(&collection.object.id, collection.clone());
+
+    // New objects inherit the default license, explicit licenses take precedence
+    let object_request = |data_license_tag: &str| {
+        CreateRequest::Object(CreateObjectRequest {
+            name: test_utils::rand_string(32),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            hashes: vec![],
+            parent: Some(ObjectParent::CollectionId(collection.object.id.to_string())),
+            metadata_license_tag: "".to_string(),
+            data_license_tag: data_license_tag.to_string(),
+            authors: vec![],
+        })
+    };
+    let (object, _) = db_handler
+        .create_resource(object_request(""), user.id, false)
+        .await
+        .unwrap();
+    cache.add_object(object.clone());
+    assert_eq!(object.object.data_license, tag);
+    assert_eq!(object.object.metadata_license, ALL_RIGHTS_RESERVED);
+    let (_, data_license) = object_request(ALL_RIGHTS_RESERVED)
+        .get_licenses(cache, client)
+        .await
+        .unwrap();
+    assert_eq!(data_license, ALL_RIGHTS_RESERVED);
+    assert!(db_handler
+        .set_default_license(SetDefaultLicense {
+            resource_id: object.object.id.to_string(),
+            license_tag: Some(tag.clone()),
+        })
+        .await
+        .is_err());
+
+    let effective = db_handler.get_effective_license(&object.object.id).unwrap();
+    assert_eq!(effective.data_license, tag);
+    assert_eq!(effective.default_license, tag);
+    assert_eq!(effective.inherited_from, collection.object.id);
+
+    // Unsetting the default does not change existing children
+    let collection = db_handler
+        .set_default_license(SetDefaultLicense {
+            resource_id: collection.object.id.to_string(),
+            license_tag: None,
+        })
+        .await
+        .unwrap();
+    cache.upsert_object(&collection.object.id, collection.clone());
+    let (_, data_license) = object_request("")
+        .get_licenses(cache, client)
+        .await
+        .unwrap();
+    assert_eq!(data_license, ALL_RIGHTS_RESERVED);
+    let object = Object::get(object.object.id, client)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(object.data_license, tag);
+}
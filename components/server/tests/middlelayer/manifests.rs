@@ -0,0 +1,182 @@
+use crate::common::init::{init_database_handler_middlelayer, init_token_handler};
+use crate::common::test_utils;
+use aruna_rust_api::api::storage::models::v2::{KeyValue as APIKeyValue, KeyValueVariant};
+use aruna_rust_api::api::storage::services::v2::UpdateObjectRequest;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::object_dsl::{
+    Algorithm, Hash, Hashes, KeyValue, KeyValueVariant as DBKeyValueVariant, KeyValues, Object,
+};
+use aruna_server::database::enums::ObjectType;
+use aruna_server::middlelayer::manifest_request_types::{
+    GetSignedManifest, ManifestImmutable, MANIFEST_POLICY_KEY,
+};
+use diesel_ulid::DieselUlid;
+use postgres_types::Json;
+
+fn add_label(object_id: DieselUlid, key: &str) -> UpdateObjectRequest {
+    UpdateObjectRequest {
+        object_id: object_id.to_string(),
+        name: None,
+        description: None,
+        add_key_values: vec![APIKeyValue {
+            key: key.to_string(),
+            value: "value".to_string(),
+            variant: KeyValueVariant::Label as i32,
+        }],
+        remove_key_values: vec![],
+        data_class: 0,
+        hashes: vec![],
+        force_revision: false,
+        metadata_license_tag: None,
+        data_license_tag: None,
+        parent: None,
+    }
+}
+
+#[tokio::test]
+async fn signed_manifests() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+    let token_handler = init_token_handler(db_handler.database.clone(), cache.clone()).await;
+
+    // Projects with immutable and re-signed manifests, each with a finished object
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+    let mut objects = Vec::new();
+    for policy in ["IMMUTABLE", "RESIGN"] {
+        let mut project =
+            test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+        project.key_values = Json(KeyValues(vec![KeyValue {
+            key: MANIFEST_POLICY_KEY.to_string(),
+            value: policy.to_string(),
+            variant: DBKeyValueVariant::LABEL,
+            value_type: None,
+        }]));
+        project.create(client).await.unwrap();
+        cache.add_object(
+            Object::get_object_with_relations(&project.id, client)
+                .await
+                .unwrap(),
+        );
+        let mut object =
+            test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+        object.hashes = Json(Hashes(vec![Hash {
+            alg: Algorithm::SHA256,
+            hash: "a".repeat(64),
+        }]));
+        object.create(client).await.unwrap();
+        test_utils::new_internal_relation(&project, &object)
+            .create(client)
+            .await
+            .unwrap();
+        cache.add_object(
+            Object::get_object_with_relations(&object.id, client)
+                .await
+                .unwrap(),
+        );
+        objects.push(object.id);
+    }
+    let [immutable, resigned] = objects[..] else {
+        panic!("Expected two objects");
+    };
+
+    // Objects without manifest policy are not signed
+    let mut unsigned = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    unsigned.create(client).await.unwrap();
+    let mut unsigned_project =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    unsigned_project.create(client).await.unwrap();
+    test_utils::new_internal_relation(&unsigned_project, &unsigned)
+        .create(client)
+        .await
+        .unwrap();
+    for id in [unsigned_project.id, unsigned.id] {
+        cache.add_object(
+            Object::get_object_with_relations(&id, client)
+                .await
+                .unwrap(),
+        );
+    }
+    assert!(db_handler
+        .sign_manifest(&unsigned.id, &token_handler)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Signed manifests verify and describe their object
+    let signed = db_handler
+        .sign_manifest(&immutable, &token_handler)
+        .await
+        .unwrap()
+        .unwrap();
+    let manifest = token_handler.verify_manifest(&signed).unwrap();
+    assert_eq!(manifest.object_id, immutable);
+    assert_eq!(manifest.content_len, 1337);
+    assert_eq!(manifest.sha256, Some("a".repeat(64)));
+    let object = Object::get(immutable, client).await.unwrap().unwrap();
+    assert!(manifest.describes(&object));
+    let stored = db_handler
+        .get_signed_manifest(GetSignedManifest {
+            object_id: immutable.to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(stored.signature, signed.signature);
+
+    // Unchanged objects keep their manifest
+    let again = db_handler
+        .sign_manifest(&immutable, &token_handler)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(again.revision, 0);
+    assert_eq!(again.signature, signed.signature);
+
+    // Tampered manifests and objects are detected
+    let mut tampered = signed.clone();
+    tampered.manifest.0.content_len = 42;
+    assert!(token_handler.verify_manifest(&tampered).is_err());
+    client
+        .execute(
+            "UPDATE objects SET content_len = 42 WHERE id = $1;",
+            &[&immutable],
+        )
+        .await
+        .unwrap();
+    let object = Object::get(immutable, client).await.unwrap().unwrap();
+    assert!(!manifest.describes(&object));
+
+    // Signed objects with immutable manifests can not be changed
+    let err = db_handler
+        .update_grpc_object(add_label(immutable, "changed"), user.id, false)
+        .await
+        .unwrap_err();
+    assert!(err.is::<ManifestImmutable>());
+
+    // Changes of other signed objects are signed as new manifest revision
+    let first = db_handler
+        .sign_manifest(&resigned, &token_handler)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.revision, 0);
+    db_handler
+        .update_grpc_object(add_label(resigned, "changed"), user.id, false)
+        .await
+        .unwrap();
+    let second = db_handler
+        .sign_manifest(&resigned, &token_handler)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(second.revision, 1);
+    assert!(second
+        .manifest
+        .0
+        .labels
+        .iter()
+        .any(|label| label.key == "changed"));
+    assert!(token_handler.verify_manifest(&second).is_ok());
+}
@@ -0,0 +1,168 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_rust_api::api::storage::models::v2::{KeyValue, KeyValueVariant};
+use aruna_rust_api::api::storage::services::v2::create_object_request::Parent as ObjectParent;
+use aruna_rust_api::api::storage::services::v2::{CreateObjectRequest, CreateProjectRequest};
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::license_dsl::ALL_RIGHTS_RESERVED;
+use aruna_server::database::dsls::metadata_schema_dsl::{
+    MetadataField, SchemaViolation, SchemaViolations,
+};
+use aruna_server::database::dsls::object_dsl::ObjectWithRelations;
+use aruna_server::middlelayer::create_request_types::CreateRequest;
+use aruna_server::middlelayer::metadata_schema_request_types::{
+    GetMetadataSchema, SetMetadataSchema,
+};
+use diesel_ulid::DieselUlid;
+
+fn object_request(
+    parent: &ObjectWithRelations,
+    name: &str,
+    labels: &[(&str, &str)],
+) -> CreateRequest {
+    CreateRequest::Object(CreateObjectRequest {
+        name: name.to_string(),
+        title: "".to_string(),
+        description: "test".to_string(),
+        key_values: labels
+            .iter()
+            .map(|(key, value)| KeyValue {
+                key: key.to_string(),
+                value: value.to_string(),
+                variant: KeyValueVariant::Label as i32,
+            })
+            .collect(),
+        relations: vec![],
+        data_class: 1,
+        hashes: vec![],
+        parent: Some(ObjectParent::ProjectId(parent.object.id.to_string())),
+        metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        authors: vec![],
+    })
+}
+
+#[tokio::test]
+async fn metadata_schemas() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+
+    let project = CreateRequest::Project(
+        CreateProjectRequest {
+            name: format!("schema-{}", DieselUlid::generate()).to_lowercase(),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            preferred_endpoint: "".to_string(),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            default_data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        },
+        DieselUlid::generate().to_string(),
+    );
+    let (project, _) = db_handler
+        .create_resource(project, user.id, false)
+        .await
+        .unwrap();
+    db_handler.cache.add_object(project.clone());
+
+    // objects created before the schema are not affected by it
+    let (unlabeled, _) = db_handler
+        .create_resource(object_request(&project, "before.txt", &[]), user.id, false)
+        .await
+        .unwrap();
+    db_handler.cache.add_object(unlabeled);
+
+    let schema = db_handler
+        .set_metadata_schema(
+            SetMetadataSchema {
+                project_id: project.object.id.to_string(),
+                fields: vec![
+                    MetadataField {
+                        key: "sample_id".to_string(),
+                        required: true,
+                        pattern: Some("S-[0-9]{4}".to_string()),
+                        allowed_values: vec![],
+                    },
+                    MetadataField {
+                        key: "condition".to_string(),
+                        required: false,
+                        pattern: None,
+                        allowed_values: vec!["control".to_string(), "treated".to_string()],
+                    },
+                ],
+            },
+            user.id,
+        )
+        .await
+        .unwrap();
+    let request = GetMetadataSchema {
+        project_id: project.object.id.to_string(),
+    };
+    let stored = db_handler.get_metadata_schema(&request).await.unwrap();
+    assert_eq!(stored.fields, schema.fields);
+    assert_eq!(stored.updated_by, user.id);
+
+    // conforming object
+    let (object, _) = db_handler
+        .create_resource(
+            object_request(
+                &project,
+                "conforming.txt",
+                &[("sample_id", "S-0001"), ("condition", "control")],
+            ),
+            user.id,
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(object.object.key_values.0 .0.len(), 2);
+
+    // missing required label
+    let err = db_handler
+        .create_resource(
+            object_request(&project, "missing.txt", &[("condition", "treated")]),
+            user.id,
+            false,
+        )
+        .await
+        .unwrap_err();
+    let violations = err.downcast_ref::<SchemaViolations>().unwrap();
+    assert_eq!(violations.project_id, project.object.id);
+    assert_eq!(
+        violations.violations,
+        vec![SchemaViolation::Missing {
+            key: "sample_id".to_string()
+        }]
+    );
+
+    // label value violating the pattern
+    let err = db_handler
+        .create_resource(
+            object_request(&project, "pattern.txt", &[("sample_id", "sample-1")]),
+            user.id,
+            false,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<SchemaViolations>().unwrap().violations,
+        vec![SchemaViolation::PatternMismatch {
+            key: "sample_id".to_string(),
+            value: "sample-1".to_string()
+        }]
+    );
+
+    // without a schema every object is accepted again
+    db_handler.delete_metadata_schema(&request).await.unwrap();
+    assert!(db_handler.get_metadata_schema(&request).await.is_err());
+    assert!(db_handler
+        .create_resource(object_request(&project, "after.txt", &[]), user.id, false)
+        .await
+        .is_ok());
+}
@@ -1,10 +1,28 @@
+mod access_policies;
 mod create;
+mod dead_letter_hooks;
 mod delete;
 mod endpoints;
+mod expiry;
+mod integrity;
 mod licenses;
+mod manifests;
+mod metadata_schemas;
+mod name_reservations;
+mod notification_retention;
+mod pinned_views;
+mod previews;
+mod provenance;
+mod publication;
+mod relation_types;
 mod relations;
+mod requester_pays;
 mod rules;
+mod scans;
 mod snapshots;
+mod staging;
+mod symlinks;
+mod tokens;
 mod updates;
 mod users;
 mod workspaces;
@@ -0,0 +1,188 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_rust_api::api::storage::services::v2::create_object_request::Parent as ObjectParent;
+use aruna_rust_api::api::storage::services::v2::CreateObjectRequest;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::license_dsl::ALL_RIGHTS_RESERVED;
+use aruna_server::database::dsls::name_reservation_dsl::NameReservation;
+use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::enums::ObjectType;
+use aruna_server::middlelayer::create_request_types::ExistingObjectMode;
+use aruna_server::middlelayer::name_reservation_request_types::{
+    ReleaseObjectName, ReserveObjectName,
+};
+use diesel_ulid::DieselUlid;
+use itertools::Itertools;
+
+#[tokio::test]
+async fn reserve_object_name() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+    let mut project = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    let mut collection =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::COLLECTION);
+    project.create(client).await.unwrap();
+    collection.create(client).await.unwrap();
+    test_utils::new_internal_relation(&project, &collection)
+        .create(client)
+        .await
+        .unwrap();
+    for id in [project.id, collection.id] {
+        cache.add_object(
+            Object::get_object_with_relations(&id, client)
+                .await
+                .unwrap(),
+        );
+    }
+
+    // Concurrent reservations of the same name, only one wins
+    let request = ReserveObjectName {
+        collection_id: collection.id.to_string(),
+        name: "upload.bin".to_string(),
+        ttl: 60,
+    };
+    let tasks = (0..8)
+        .map(|_| {
+            let db_handler = db_handler.clone();
+            let request = request.clone();
+            tokio::spawn(async move { db_handler.reserve_object_name(request, user.id).await })
+        })
+        .collect_vec();
+    let mut reservations = Vec::new();
+    for task in tasks {
+        if let Ok(reservation) = task.await.unwrap() {
+            reservations.push(reservation);
+        }
+    }
+    assert_eq!(reservations.len(), 1);
+    let reservation = reservations.remove(0);
+
+    // The name can only be used with the token of the reservation
+    let create = CreateObjectRequest {
+        name: request.name.clone(),
+        title: "".to_string(),
+        description: "test".to_string(),
+        key_values: vec![],
+        relations: vec![],
+        data_class: 1,
+        hashes: vec![],
+        parent: Some(ObjectParent::CollectionId(collection.id.to_string())),
+        metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        authors: vec![],
+    };
+    for token in [None, Some(DieselUlid::generate())] {
+        assert!(db_handler
+            .create_or_get_object(
+                create.clone(),
+                user.id,
+                false,
+                false,
+                ExistingObjectMode::Fail,
+                token,
+            )
+            .await
+            .is_err());
+    }
+    let (object, created) = db_handler
+        .create_or_get_object(
+            create.clone(),
+            user.id,
+            false,
+            false,
+            ExistingObjectMode::Fail,
+            Some(reservation.id),
+        )
+        .await
+        .unwrap();
+    assert!(created);
+    assert_eq!(object.object.name, request.name);
+    // Creation consumes the reservation and used names can not be reserved
+    assert!(NameReservation::get(reservation.id, client)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(db_handler
+        .reserve_object_name(request.clone(), user.id)
+        .await
+        .is_err());
+
+    // Expired reservations neither block the name nor can be used
+    let expiring = ReserveObjectName {
+        name: "expiring.bin".to_string(),
+        ..request.clone()
+    };
+    let expired = db_handler
+        .reserve_object_name(expiring.clone(), user.id)
+        .await
+        .unwrap();
+    client
+        .execute(
+            "UPDATE name_reservations SET expires_at = NOW() - INTERVAL '1 day' WHERE id = $1;",
+            &[&expired.id],
+        )
+        .await
+        .unwrap();
+    let renewed = db_handler
+        .reserve_object_name(expiring.clone(), user.id)
+        .await
+        .unwrap();
+    assert_ne!(renewed.id, expired.id);
+    assert!(db_handler
+        .create_or_get_object(
+            CreateObjectRequest {
+                name: expiring.name.clone(),
+                ..create.clone()
+            },
+            user.id,
+            false,
+            false,
+            ExistingObjectMode::Fail,
+            Some(expired.id),
+        )
+        .await
+        .is_err());
+
+    // Released names are free again
+    let release = ReleaseObjectName {
+        token: renewed.id.to_string(),
+    };
+    let released = db_handler.get_name_reservation(&release).await.unwrap();
+    db_handler.release_object_name(&released).await.unwrap();
+    let (object, created) = db_handler
+        .create_or_get_object(
+            CreateObjectRequest {
+                name: expiring.name.clone(),
+                ..create
+            },
+            user.id,
+            false,
+            false,
+            ExistingObjectMode::Fail,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(created);
+    assert_eq!(object.object.name, expiring.name);
+
+    // Invalid lifetimes are rejected
+    for ttl in [0, 86401] {
+        assert!(db_handler
+            .reserve_object_name(
+                ReserveObjectName {
+                    name: "invalid.bin".to_string(),
+                    ttl,
+                    ..request.clone()
+                },
+                user.id
+            )
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::object_dsl::{KeyValue, KeyValueVariant, KeyValues, Object};
+use aruna_server::database::dsls::persistent_notification_dsl::{
+    NotificationReference, NotificationReferences, PersistentNotification,
+};
+use aruna_server::database::enums::{
+    NotificationReferenceType, ObjectType, PersistentNotificationVariant,
+};
+use aruna_server::middlelayer::notification_retention_request_types::{
+    GetNotificationRetention, NotificationRetention, NotificationRetentionConfig,
+    NotificationsPruned, ReplayPersistentNotifications, NOTIFICATION_RETENTION_KEY,
+};
+use diesel_ulid::DieselUlid;
+use postgres_types::Json;
+use std::time::Duration;
+
+#[tokio::test]
+async fn notification_retention() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+
+    // Project which keeps notifications for an hour, with an object
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+    let mut project = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    project.key_values = Json(KeyValues(vec![KeyValue {
+        key: NOTIFICATION_RETENTION_KEY.to_string(),
+        value: r#"{"max_age": 3600}"#.to_string(),
+        variant: KeyValueVariant::LABEL,
+        value_type: None,
+    }]));
+    project.create(client).await.unwrap();
+    let mut object = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    object.create(client).await.unwrap();
+    test_utils::new_internal_relation(&project, &object)
+        .create(client)
+        .await
+        .unwrap();
+    for id in [project.id, object.id] {
+        cache.add_object(
+            Object::get_object_with_relations(&id, client)
+                .await
+                .unwrap(),
+        );
+    }
+    let settings = db_handler
+        .get_notification_retention(GetNotificationRetention {
+            project_id: project.id.to_string(),
+        })
+        .unwrap();
+    assert!(settings.project_configured);
+    assert_eq!(settings.retention.max_age, Some(3600));
+
+    // Notifications about the object created two hours and a minute ago,
+    // and an old notification without project
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let notification = |id: DieselUlid, resource_id: DieselUlid| PersistentNotification {
+        id,
+        user_id: user.id,
+        notification_variant: PersistentNotificationVariant::OBJECT_EXPIRED,
+        message: "Object expired".to_string(),
+        refs: Json(NotificationReferences(vec![NotificationReference {
+            reference_type: NotificationReferenceType::Resource,
+            reference_name: "object".to_string(),
+            reference_value: resource_id.to_string(),
+        }])),
+    };
+    let old = test_utils::ulid_at(now - 7_200_000);
+    let recent = test_utils::ulid_at(now - 60_000);
+    let unrelated = test_utils::ulid_at(now - 7_300_000);
+    for (id, resource_id) in [
+        (old, object.id),
+        (recent, object.id),
+        (unrelated, DieselUlid::generate()),
+    ] {
+        notification(id, resource_id).create(client).await.unwrap();
+    }
+
+    // The minimum retention keeps all notifications
+    let mut config = NotificationRetentionConfig {
+        default: NotificationRetention::default(),
+        min_age: Duration::from_secs(3 * 3600),
+        prune_interval: Duration::from_secs(3600),
+    };
+    db_handler
+        .prune_persistent_notifications(&config, now)
+        .await
+        .unwrap();
+    assert!(PersistentNotification::get(old, client)
+        .await
+        .unwrap()
+        .is_some());
+
+    // Notifications older than the retention of their project are pruned
+    config.min_age = Duration::ZERO;
+    db_handler
+        .prune_persistent_notifications(&config, now)
+        .await
+        .unwrap();
+    assert!(PersistentNotification::get(old, client)
+        .await
+        .unwrap()
+        .is_none());
+    for id in [recent, unrelated] {
+        assert!(PersistentNotification::get(id, client)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    // Replays from before the pruned notification require a resync
+    let replay = |after: Option<DieselUlid>| ReplayPersistentNotifications {
+        after: after.map(|id| id.to_string()),
+    };
+    let err = db_handler
+        .replay_persistent_notifications(user.id, replay(Some(unrelated)))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<NotificationsPruned>().unwrap().watermark,
+        old
+    );
+
+    // Replays from the watermark and resyncs see the remaining notifications
+    let replayed = db_handler
+        .replay_persistent_notifications(user.id, replay(Some(old)))
+        .await
+        .unwrap();
+    assert_eq!(
+        replayed.iter().map(|n| n.id).collect::<Vec<_>>(),
+        vec![recent]
+    );
+    let resynced = db_handler
+        .replay_persistent_notifications(user.id, replay(None))
+        .await
+        .unwrap();
+    assert_eq!(resynced.len(), 2);
+}
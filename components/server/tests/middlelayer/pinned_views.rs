@@ -0,0 +1,172 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_rust_api::api::storage::services::v2::{DeleteObjectRequest, UpdateObjectRequest};
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::enums::{ObjectStatus, ObjectType};
+use aruna_server::middlelayer::delete_request_types::DeleteRequest;
+use aruna_server::middlelayer::pinned_view_request_types::{
+    CreatePinnedView, ListPinnedViews, ResolvePinnedView,
+};
+use diesel_ulid::DieselUlid;
+
+#[tokio::test]
+async fn pinned_views() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+
+    // project -> collection -> (dataset -> object_a, object_b, deleted object_c)
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+    let mut project = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    let mut collection =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::COLLECTION);
+    let mut dataset = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::DATASET);
+    let mut object_a = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    let mut object_b = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    let mut object_c = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    object_c.object_status = ObjectStatus::DELETED;
+    for resource in [
+        &mut project,
+        &mut collection,
+        &mut dataset,
+        &mut object_a,
+        &mut object_b,
+        &mut object_c,
+    ] {
+        resource.create(client).await.unwrap();
+    }
+    for (origin, target) in [
+        (&project, &collection),
+        (&collection, &dataset),
+        (&dataset, &object_a),
+        (&collection, &object_b),
+        (&collection, &object_c),
+    ] {
+        test_utils::new_internal_relation(origin, target)
+            .create(client)
+            .await
+            .unwrap();
+    }
+    for resource in Object::get_objects_with_relations(
+        &vec![
+            project.id,
+            collection.id,
+            dataset.id,
+            object_a.id,
+            object_b.id,
+            object_c.id,
+        ],
+        client,
+    )
+    .await
+    .unwrap()
+    {
+        db_handler.cache.add_object(resource);
+    }
+
+    // pin the current revisions
+    let view = db_handler
+        .create_pinned_view(
+            CreatePinnedView {
+                collection_id: collection.id.to_string(),
+                name: "release-1".to_string(),
+            },
+            user.id,
+        )
+        .await
+        .unwrap();
+    let mut pinned = vec![object_a.id, object_b.id];
+    pinned.sort();
+    assert_eq!(view.revision_ids, pinned);
+
+    // names are unique per collection and only collections can be pinned
+    assert!(db_handler
+        .create_pinned_view(
+            CreatePinnedView {
+                collection_id: collection.id.to_string(),
+                name: "release-1".to_string(),
+            },
+            user.id,
+        )
+        .await
+        .is_err());
+    assert!(db_handler
+        .create_pinned_view(
+            CreatePinnedView {
+                collection_id: dataset.id.to_string(),
+                name: "release-1".to_string(),
+            },
+            user.id,
+        )
+        .await
+        .is_err());
+    let views = db_handler
+        .list_pinned_views(ListPinnedViews {
+            collection_id: collection.id.to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(views, vec![view.clone()]);
+
+    // a new revision does not change the view
+    let (revision, is_new) = db_handler
+        .update_grpc_object(
+            UpdateObjectRequest {
+                object_id: object_a.id.to_string(),
+                name: None,
+                description: None,
+                add_key_values: vec![],
+                remove_key_values: vec![],
+                data_class: 0,
+                hashes: vec![],
+                parent: None,
+                force_revision: true,
+                metadata_license_tag: None,
+                data_license_tag: None,
+            },
+            user.id,
+            false,
+        )
+        .await
+        .unwrap();
+    assert!(is_new);
+    assert_ne!(revision.object.id, object_a.id);
+    let resolve = ResolvePinnedView {
+        view_id: view.id.to_string(),
+    };
+    let view = db_handler.get_pinned_view(&resolve).await.unwrap();
+    let resolved = db_handler
+        .resolve_pinned_view(&view)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|object| object.object.id)
+        .collect::<Vec<_>>();
+    assert_eq!(resolved, pinned);
+
+    // pinned revisions can not be deleted
+    assert!(db_handler
+        .delete_resource(DeleteRequest::Object(DeleteObjectRequest {
+            object_id: revision.object.id.to_string(),
+            with_revisions: true,
+        }))
+        .await
+        .is_err());
+    assert!(db_handler
+        .delete_resource(DeleteRequest::Object(DeleteObjectRequest {
+            object_id: object_b.id.to_string(),
+            with_revisions: false,
+        }))
+        .await
+        .is_err());
+    assert_eq!(
+        Object::get(object_b.id, client)
+            .await
+            .unwrap()
+            .unwrap()
+            .object_status,
+        ObjectStatus::AVAILABLE
+    );
+}
@@ -0,0 +1,94 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::internal_relation_dsl::INTERNAL_RELATION_VARIANT_PREVIEW;
+use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::enums::{ObjectStatus, ObjectType};
+use aruna_server::hooks::preview_hook::{Thumbnail, PREVIEW_KEY};
+use aruna_server::middlelayer::preview_request_types::GetPreview;
+use diesel_ulid::DieselUlid;
+
+#[tokio::test]
+async fn previews() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+
+    // project -> collection -> image
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+    let mut project = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    let mut collection =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::COLLECTION);
+    let mut image = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    image.name = "microscopy.tiff".to_string();
+    for resource in [&mut project, &mut collection, &mut image] {
+        resource.create(client).await.unwrap();
+    }
+    for (origin, target) in [(&project, &collection), (&collection, &image)] {
+        test_utils::new_internal_relation(origin, target)
+            .create(client)
+            .await
+            .unwrap();
+    }
+    for resource in
+        Object::get_objects_with_relations(&vec![project.id, collection.id, image.id], client)
+            .await
+            .unwrap()
+    {
+        db_handler.cache.add_object(resource);
+    }
+    let request = GetPreview {
+        object_id: image.id.to_string(),
+    };
+    assert!(db_handler.get_preview(request.clone()).await.is_err());
+
+    // thumbnail returned by the generator is linked to the image
+    let thumbnail = Thumbnail {
+        content_type: "image/png".to_string(),
+        data: b"\x89PNG thumbnail".to_vec(),
+    };
+    let preview = db_handler
+        .create_preview(image.id, &thumbnail, user.id)
+        .await
+        .unwrap();
+    assert_eq!(preview.name, format!("preview-{}.png", image.id));
+    assert_eq!(preview.object_status, ObjectStatus::INITIALIZING);
+
+    let original = db_handler.cache.get_object(&image.id).unwrap();
+    assert!(original.outbound.0.iter().any(|relation| {
+        relation.relation_name == INTERNAL_RELATION_VARIANT_PREVIEW
+            && relation.target_pid == preview.id
+    }));
+    assert!(original
+        .object
+        .key_values
+        .0
+         .0
+        .iter()
+        .any(|kv| kv.key == PREVIEW_KEY && kv.value == preview.id.to_string()));
+    let parent = db_handler.cache.get_object(&collection.id).unwrap();
+    assert!(parent
+        .outbound_belongs_to
+        .0
+        .iter()
+        .any(|relation| relation.target_pid == preview.id));
+
+    // previews are returned once their upload is finished
+    assert!(db_handler.get_preview(request.clone()).await.is_err());
+    Object::update_status(&preview.id, ObjectStatus::AVAILABLE, client)
+        .await
+        .unwrap();
+    db_handler.cache.upsert_object(
+        &preview.id,
+        Object::get_object_with_relations(&preview.id, client)
+            .await
+            .unwrap(),
+    );
+    let found = db_handler.get_preview(request).await.unwrap();
+    assert_eq!(found.object.id, preview.id);
+    assert!(found.inbound.0.iter().any(|relation| {
+        relation.relation_name == INTERNAL_RELATION_VARIANT_PREVIEW
+            && relation.origin_pid == image.id
+    }));
+}
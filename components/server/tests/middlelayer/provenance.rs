@@ -0,0 +1,165 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::enums::ObjectType;
+use aruna_server::middlelayer::provenance_request_types::{GetLineage, RecordProvenance};
+use diesel_ulid::DieselUlid;
+
+#[tokio::test]
+async fn lineage() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+    let mut objects = Vec::new();
+    for _ in 0..5 {
+        let mut object =
+            test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+        object.create(client).await.unwrap();
+        objects.push(object.id);
+    }
+    let [raw_1, raw_2, raw_3, intermediate, result] = objects[..] else {
+        panic!("Expected five objects");
+    };
+
+    // Two step derivation: raw_1 + raw_2 -> intermediate, intermediate + raw_3 -> result
+    let record = |derived: DieselUlid, sources: Vec<DieselUlid>, task: &str| RecordProvenance {
+        derived_object_id: derived.to_string(),
+        source_object_ids: sources.iter().map(|id| id.to_string()).collect(),
+        task_id: task.to_string(),
+        tool: "aligner".to_string(),
+        tool_version: Some("1.2.0".to_string()),
+    };
+    db_handler
+        .record_provenance(record(intermediate, vec![raw_1, raw_2], "task-1"), user.id)
+        .await
+        .unwrap();
+    db_handler
+        .record_provenance(record(result, vec![intermediate, raw_3], "task-2"), user.id)
+        .await
+        .unwrap();
+
+    // Objects are derived only once and derivations can not form cycles
+    assert!(db_handler
+        .record_provenance(record(result, vec![raw_1], "task-3"), user.id)
+        .await
+        .is_err());
+    assert!(db_handler
+        .record_provenance(record(raw_1, vec![result], "task-3"), user.id)
+        .await
+        .is_err());
+    assert!(db_handler
+        .record_provenance(record(raw_3, vec![raw_3], "task-3"), user.id)
+        .await
+        .is_err());
+
+    // Deleted sources stay in the lineage as tombstones
+    client
+        .execute(
+            "UPDATE objects SET object_status = 'DELETED' WHERE id = $1;",
+            &[&raw_1],
+        )
+        .await
+        .unwrap();
+    client
+        .execute("DELETE FROM objects WHERE id = $1;", &[&raw_2])
+        .await
+        .unwrap();
+
+    let lineage = db_handler
+        .get_lineage(GetLineage {
+            object_id: result.to_string(),
+            max_depth: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(lineage.nodes.len(), 5);
+    assert!(!lineage.truncated);
+    assert_eq!(lineage.nodes[0].object_id, result);
+    let mut ancestors = lineage.ancestors();
+    ancestors.sort();
+    let mut expected = vec![raw_1, raw_2, raw_3, intermediate];
+    expected.sort();
+    assert_eq!(ancestors, expected);
+
+    let result_node = lineage.get_node(&result).unwrap();
+    let provenance = result_node.provenance.as_ref().unwrap();
+    assert_eq!(provenance.task_id, "task-2");
+    assert_eq!(provenance.source_ids, vec![intermediate, raw_3]);
+    let intermediate_node = lineage.get_node(&intermediate).unwrap();
+    assert_eq!(
+        intermediate_node.provenance.as_ref().unwrap().task_id,
+        "task-1"
+    );
+    assert!(!intermediate_node.deleted);
+    assert!(lineage.get_node(&raw_3).unwrap().provenance.is_none());
+
+    let deleted = lineage.get_node(&raw_1).unwrap();
+    assert!(deleted.deleted);
+    assert!(deleted.name.is_some());
+    let removed = lineage.get_node(&raw_2).unwrap();
+    assert!(removed.deleted);
+    assert!(removed.name.is_none());
+}
+
+#[tokio::test]
+async fn lineage_depth_limit() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+    let mut chain = Vec::new();
+    for _ in 0..7 {
+        let mut object =
+            test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+        object.create(client).await.unwrap();
+        chain.push(object.id);
+    }
+
+    // Chain of derivations: chain[0] -> chain[1] -> ... -> chain[6]
+    for (index, pair) in chain.windows(2).enumerate() {
+        db_handler
+            .record_provenance(
+                RecordProvenance {
+                    derived_object_id: pair[1].to_string(),
+                    source_object_ids: vec![pair[0].to_string()],
+                    task_id: format!("task-{index}"),
+                    tool: "aligner".to_string(),
+                    tool_version: None,
+                },
+                user.id,
+            )
+            .await
+            .unwrap();
+    }
+    let last = *chain.last().unwrap();
+
+    // Requested depth stops the traversal and marks the lineage as truncated
+    let lineage = db_handler
+        .get_lineage(GetLineage {
+            object_id: last.to_string(),
+            max_depth: Some(3),
+        })
+        .await
+        .unwrap();
+    assert!(lineage.truncated);
+    assert_eq!(lineage.nodes.len(), 4);
+    for object in &chain[3..] {
+        assert!(lineage.get_node(object).is_some());
+    }
+    assert!(lineage.get_node(&chain[2]).is_none());
+
+    let lineage = db_handler
+        .get_lineage(GetLineage {
+            object_id: last.to_string(),
+            max_depth: None,
+        })
+        .await
+        .unwrap();
+    assert!(!lineage.truncated);
+    assert_eq!(lineage.nodes.len(), 7);
+}
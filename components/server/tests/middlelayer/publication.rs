@@ -0,0 +1,141 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::dsls::publication_request_dsl::PublicationRequest;
+use aruna_server::database::enums::{DataClass, ObjectType, PublicationStatus};
+use aruna_server::middlelayer::publication_request_types::{
+    DecidePublication, RequestPublication, PUBLICATION_STATE_KEY,
+};
+use diesel_ulid::DieselUlid;
+
+#[tokio::test]
+async fn publication_request_approve_and_reject() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let mut owner = test_utils::new_user(vec![]);
+    owner.create(client).await.unwrap();
+    let mut approver = test_utils::new_user(vec![]);
+    approver.create(client).await.unwrap();
+
+    for approve in [true, false] {
+        let mut project =
+            test_utils::new_object(owner.id, DieselUlid::generate(), ObjectType::PROJECT);
+        project.data_class = DataClass::PRIVATE;
+        project.create(client).await.unwrap();
+
+        // Request keeps the project private with a pending state
+        let (publication, resource) = db_handler
+            .request_publication(
+                RequestPublication {
+                    resource_id: project.id.to_string(),
+                },
+                owner.id,
+                true,
+            )
+            .await
+            .unwrap();
+        assert_eq!(publication.status, PublicationStatus::PENDING);
+        assert_eq!(resource.object.data_class, DataClass::PRIVATE);
+        assert!(resource
+            .object
+            .key_values
+            .0
+             .0
+            .iter()
+            .any(|kv| kv.key == PUBLICATION_STATE_KEY && kv.value == "pending"));
+
+        // Only one pending request per resource
+        assert!(db_handler
+            .request_publication(
+                RequestPublication {
+                    resource_id: project.id.to_string(),
+                },
+                owner.id,
+                true,
+            )
+            .await
+            .is_err());
+
+        // Rejections need a reason
+        let decision = DecidePublication {
+            request_id: publication.id.to_string(),
+            approve,
+            reason: None,
+        };
+        if !approve {
+            assert!(db_handler
+                .decide_publication(decision.clone(), approver.id)
+                .await
+                .is_err());
+        }
+        let decision = DecidePublication {
+            reason: Some("Contains personal data".to_string()),
+            ..decision
+        };
+        let (decided, resource) = db_handler
+            .decide_publication(decision.clone(), approver.id)
+            .await
+            .unwrap();
+        assert!(!resource
+            .object
+            .key_values
+            .0
+             .0
+            .iter()
+            .any(|kv| kv.key == PUBLICATION_STATE_KEY));
+        if approve {
+            assert_eq!(decided.status, PublicationStatus::APPROVED);
+            assert_eq!(resource.object.data_class, DataClass::PUBLIC);
+        } else {
+            assert_eq!(decided.status, PublicationStatus::REJECTED);
+            assert_eq!(resource.object.data_class, DataClass::PRIVATE);
+        }
+
+        // Decision is stored
+        let stored = PublicationRequest::get(publication.id, client)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.status, decided.status);
+        assert_eq!(stored.decided_by, Some(approver.id));
+        assert!(stored.decided_at.is_some());
+        assert_eq!(stored.reason, Some("Contains personal data".to_string()));
+        let project = Object::get(project.id, client).await.unwrap().unwrap();
+        assert_eq!(project.data_class, resource.object.data_class);
+
+        // Decided requests can not be decided again
+        assert!(db_handler
+            .decide_publication(decision, approver.id)
+            .await
+            .is_err());
+    }
+}
+
+#[tokio::test]
+async fn publication_without_approval() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let mut owner = test_utils::new_user(vec![]);
+    owner.create(client).await.unwrap();
+    let mut project = test_utils::new_object(owner.id, DieselUlid::generate(), ObjectType::PROJECT);
+    project.data_class = DataClass::PRIVATE;
+    project.create(client).await.unwrap();
+
+    // Instances without required approval publish immediately
+    let (publication, resource) = db_handler
+        .request_publication(
+            RequestPublication {
+                resource_id: project.id.to_string(),
+            },
+            owner.id,
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(publication.status, PublicationStatus::APPROVED);
+    assert_eq!(publication.decided_by, Some(owner.id));
+    assert_eq!(resource.object.data_class, DataClass::PUBLIC);
+}
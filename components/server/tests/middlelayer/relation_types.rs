@@ -0,0 +1,146 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_rust_api::api::storage::models::v2::relation::Relation as RelationEnum;
+use aruna_rust_api::api::storage::models::v2::InternalRelation as APIInternalRelation;
+use aruna_rust_api::api::storage::models::v2::{Relation, RelationDirection};
+use aruna_rust_api::api::storage::services::v2::ModifyRelationsRequest;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::internal_relation_dsl::INTERNAL_RELATION_VARIANT_VERSION;
+use aruna_server::database::dsls::object_dsl::{Object, ObjectWithRelations};
+use aruna_server::database::enums::{ObjectMapping, ObjectType};
+use aruna_server::middlelayer::relation_type_request_types::{
+    CreateRelationType, DeleteRelationType, GetRelationTypes, GetRelationsByType,
+};
+use aruna_server::middlelayer::relations_request_types::ModifyRelations;
+use dashmap::DashMap;
+use diesel_ulid::DieselUlid;
+use postgres_types::Json;
+
+#[tokio::test]
+async fn test_custom_relation_types() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = db_handler.database.get_client().await.unwrap();
+    let project_id = DieselUlid::generate();
+    let first_id = DieselUlid::generate();
+    let second_id = DieselUlid::generate();
+    let mut user = test_utils::new_user(vec![
+        ObjectMapping::PROJECT(project_id),
+        ObjectMapping::OBJECT(first_id),
+        ObjectMapping::OBJECT(second_id),
+    ]);
+    user.create(&client).await.unwrap();
+    let objects = vec![
+        test_utils::new_object(user.id, project_id, ObjectType::PROJECT),
+        test_utils::new_object(user.id, first_id, ObjectType::OBJECT),
+        test_utils::new_object(user.id, second_id, ObjectType::OBJECT),
+    ];
+    Object::batch_create(&objects, &client).await.unwrap();
+    for obj in objects {
+        db_handler.cache.add_object(ObjectWithRelations {
+            object: obj.clone(),
+            inbound: Json(DashMap::default()),
+            inbound_belongs_to: Json(DashMap::default()),
+            outbound: Json(DashMap::default()),
+            outbound_belongs_to: Json(DashMap::default()),
+        });
+    }
+    let name = format!("primary-analysis-{project_id}");
+
+    // Built-in names can not be redefined
+    assert!(db_handler
+        .create_relation_type(CreateRelationType {
+            project_id: project_id.to_string(),
+            name: INTERNAL_RELATION_VARIANT_VERSION.to_string(),
+            directed: true,
+            max_outbound: None,
+            max_inbound: None,
+        })
+        .await
+        .is_err());
+
+    // Create type
+    let relation_type = db_handler
+        .create_relation_type(CreateRelationType {
+            project_id: project_id.to_string(),
+            name: name.clone(),
+            directed: true,
+            max_outbound: Some(1),
+            max_inbound: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(relation_type.project_id, Some(project_id));
+    assert!(db_handler
+        .get_relation_types(GetRelationTypes {
+            project_id: project_id.to_string(),
+        })
+        .await
+        .unwrap()
+        .contains(&relation_type));
+
+    // Only one outbound relation of the type is allowed
+    let add_relation = |target: DieselUlid| {
+        ModifyRelations(ModifyRelationsRequest {
+            resource_id: project_id.to_string(),
+            add_relations: vec![Relation {
+                relation: Some(RelationEnum::Internal(APIInternalRelation {
+                    resource_id: target.to_string(),
+                    defined_variant: 7, // CUSTOM
+                    custom_variant: Some(name.clone()),
+                    resource_variant: 4,
+                    direction: RelationDirection::Outbound as i32,
+                })),
+            }],
+            remove_relations: vec![],
+        })
+    };
+    let (obj, labels) = db_handler
+        .get_resource(add_relation(first_id))
+        .await
+        .unwrap();
+    db_handler
+        .modify_relations(obj, labels.relations_to_add, labels.relations_to_remove)
+        .await
+        .unwrap();
+    let (obj, labels) = db_handler
+        .get_resource(add_relation(second_id))
+        .await
+        .unwrap();
+    assert!(db_handler
+        .modify_relations(obj, labels.relations_to_add, labels.relations_to_remove)
+        .await
+        .is_err());
+
+    let relations = db_handler
+        .get_relations_by_type(GetRelationsByType {
+            resource_id: project_id.to_string(),
+            name: name.clone(),
+        })
+        .unwrap();
+    assert_eq!(relations.len(), 1);
+    assert_eq!(relations[0].target_pid, first_id);
+
+    // Used types are only deleted with cascade
+    assert!(db_handler
+        .delete_relation_type(DeleteRelationType {
+            name: name.clone(),
+            cascade: false,
+        })
+        .await
+        .is_err());
+    db_handler
+        .delete_relation_type(DeleteRelationType {
+            name: name.clone(),
+            cascade: true,
+        })
+        .await
+        .unwrap();
+    assert!(db_handler
+        .get_relations_by_type(GetRelationsByType {
+            resource_id: project_id.to_string(),
+            name,
+        })
+        .unwrap()
+        .is_empty());
+}
@@ -14,8 +14,11 @@ use aruna_server::database::dsls::internal_relation_dsl::{
     INTERNAL_RELATION_VARIANT_VERSION,
 };
 use aruna_server::database::dsls::object_dsl::ObjectWithRelations;
-use aruna_server::database::dsls::object_dsl::{DefinedVariant, ExternalRelation, Object};
+use aruna_server::database::dsls::object_dsl::{
+    DefinedVariant, ExternalRelation, KeyValue, KeyValueVariant, KeyValues, Object,
+};
 use aruna_server::database::enums::{ObjectMapping, ObjectType};
+use aruna_server::middlelayer::relations_db_handler::{RelationLimitExceeded, MAX_RELATIONS_KEY};
 use aruna_server::middlelayer::relations_request_types::ModifyRelations;
 use dashmap::DashMap;
 use diesel_ulid::DieselUlid;
@@ -333,3 +336,116 @@ async fn test_modify_relations_constraint() {
             .is_empty()
     );
 }
+
+#[tokio::test]
+async fn test_relation_limit() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = db_handler.database.get_client().await.unwrap();
+
+    // create project with a relation maximum of 2 and a collection with three objects
+    let project_id = DieselUlid::generate();
+    let collection_id = DieselUlid::generate();
+    let object_ids = [
+        DieselUlid::generate(),
+        DieselUlid::generate(),
+        DieselUlid::generate(),
+    ];
+    let mut user = test_utils::new_user(vec![
+        ObjectMapping::PROJECT(project_id),
+        ObjectMapping::COLLECTION(collection_id),
+    ]);
+    user.create(&client).await.unwrap();
+    let mut project = test_utils::new_object(user.id, project_id, ObjectType::PROJECT);
+    project.key_values = Json(KeyValues(vec![KeyValue {
+        key: MAX_RELATIONS_KEY.to_string(),
+        value: "2".to_string(),
+        variant: KeyValueVariant::LABEL,
+        value_type: None,
+    }]));
+    let collection = test_utils::new_object(user.id, collection_id, ObjectType::COLLECTION);
+    let mut objects = vec![project, collection];
+    for id in object_ids {
+        objects.push(test_utils::new_object(user.id, id, ObjectType::OBJECT));
+    }
+    Object::batch_create(&objects, &client).await.unwrap();
+    let proj_col = test_utils::new_internal_relation(&objects[0], &objects[1]);
+    InternalRelation::batch_create(&[proj_col.clone()], &client)
+        .await
+        .unwrap();
+    db_handler.cache.add_object(ObjectWithRelations {
+        object: objects[0].clone(),
+        inbound: Json(DashMap::default()),
+        inbound_belongs_to: Json(DashMap::default()),
+        outbound: Json(DashMap::default()),
+        outbound_belongs_to: Json(DashMap::from_iter([(collection_id, proj_col.clone())])),
+    });
+    db_handler.cache.add_object(ObjectWithRelations {
+        object: objects[1].clone(),
+        inbound: Json(DashMap::default()),
+        inbound_belongs_to: Json(DashMap::from_iter([(project_id, proj_col)])),
+        outbound: Json(DashMap::default()),
+        outbound_belongs_to: Json(DashMap::default()),
+    });
+    assert_eq!(db_handler.get_relation_limit(&collection_id), 2);
+
+    let policy_relation = |id: &DieselUlid| Relation {
+        relation: Some(RelationEnum::Internal(APIInternalRelation {
+            resource_id: id.to_string(),
+            defined_variant: 5, // POLICY
+            custom_variant: None,
+            resource_variant: ResourceVariant::Object as i32,
+            direction: RelationDirection::Outbound as i32,
+        })),
+    };
+
+    // test
+    // 1. relations up to the maximum can be added
+    let request = ModifyRelations(ModifyRelationsRequest {
+        resource_id: collection_id.to_string(),
+        add_relations: vec![
+            policy_relation(&object_ids[0]),
+            policy_relation(&object_ids[1]),
+        ],
+        remove_relations: vec![],
+    });
+    let (obj, mod_lab) = db_handler.get_resource(request).await.unwrap();
+    let owr = db_handler
+        .modify_relations(obj, mod_lab.relations_to_add, mod_lab.relations_to_remove)
+        .await
+        .unwrap();
+    assert_eq!(owr.outbound.0.len(), 2);
+
+    // 2. relations beyond the maximum are rejected
+    let request = ModifyRelations(ModifyRelationsRequest {
+        resource_id: collection_id.to_string(),
+        add_relations: vec![policy_relation(&object_ids[2])],
+        remove_relations: vec![],
+    });
+    let (obj, mod_lab) = db_handler.get_resource(request).await.unwrap();
+    let err = db_handler
+        .modify_relations(obj, mod_lab.relations_to_add, mod_lab.relations_to_remove)
+        .await
+        .unwrap_err();
+    let exceeded = err.downcast_ref::<RelationLimitExceeded>().unwrap();
+    assert_eq!(exceeded.resource_id, collection_id);
+    assert_eq!(exceeded.limit, 2);
+    assert_eq!(
+        InternalRelation::count_outbound(&collection_id, &client)
+            .await
+            .unwrap(),
+        2
+    );
+
+    // 3. replacing a relation stays within the maximum
+    let request = ModifyRelations(ModifyRelationsRequest {
+        resource_id: collection_id.to_string(),
+        add_relations: vec![policy_relation(&object_ids[2])],
+        remove_relations: vec![policy_relation(&object_ids[0])],
+    });
+    let (obj, mod_lab) = db_handler.get_resource(request).await.unwrap();
+    assert!(db_handler
+        .modify_relations(obj, mod_lab.relations_to_add, mod_lab.relations_to_remove)
+        .await
+        .is_ok());
+}
@@ -0,0 +1,96 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::object_dsl::{KeyValue, KeyValueVariant, KeyValues, Object};
+use aruna_server::database::dsls::stats_dsl::DownloadStats;
+use aruna_server::database::enums::{ObjectMapping, ObjectType};
+use aruna_server::middlelayer::requester_pays_request_types::{
+    RequesterPaysNotAcknowledged, REQUESTER_PAYS_KEY,
+};
+use diesel_ulid::DieselUlid;
+use postgres_types::Json;
+
+#[tokio::test]
+async fn requester_pays_downloads() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+
+    // Requester pays project with an object
+    let mut owner = test_utils::new_user(vec![]);
+    owner.create(client).await.unwrap();
+    let mut project = test_utils::new_object(owner.id, DieselUlid::generate(), ObjectType::PROJECT);
+    project.key_values = Json(KeyValues(vec![KeyValue {
+        key: REQUESTER_PAYS_KEY.to_string(),
+        value: "true".to_string(),
+        variant: KeyValueVariant::LABEL,
+        value_type: None,
+    }]));
+    project.create(client).await.unwrap();
+    let mut object = test_utils::new_object(owner.id, DieselUlid::generate(), ObjectType::OBJECT);
+    object.create(client).await.unwrap();
+    test_utils::new_internal_relation(&project, &object)
+        .create(client)
+        .await
+        .unwrap();
+    for id in [project.id, object.id] {
+        cache.add_object(
+            Object::get_object_with_relations(&id, client)
+                .await
+                .unwrap(),
+        );
+    }
+
+    // Project members and external users
+    let mut member = test_utils::new_user(vec![ObjectMapping::PROJECT(project.id)]);
+    let mut external = test_utils::new_user(vec![]);
+    for user in [&mut member, &mut external] {
+        user.create(client).await.unwrap();
+        cache.add_user(user.id, user.clone());
+    }
+
+    // External downloads without acknowledgment are rejected and not accounted
+    let err = db_handler
+        .account_download(&object.id, &external.id, false)
+        .await
+        .unwrap_err();
+    assert!(err.is::<RequesterPaysNotAcknowledged>());
+    assert!(DownloadStats::get_user_download_stats(&external.id, client)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Acknowledged external downloads are attributed to the requester
+    let attribution = db_handler
+        .account_download(&object.id, &external.id, true)
+        .await
+        .unwrap();
+    assert!(attribution.requester_pays);
+    assert_eq!(attribution.project_id, project.id);
+    db_handler
+        .account_download(&object.id, &external.id, true)
+        .await
+        .unwrap();
+    let stats = DownloadStats::get_user_download_stats(&external.id, client)
+        .await
+        .unwrap();
+    assert_eq!(stats.len(), 1);
+    assert!(stats[0].requester_pays);
+    assert_eq!(stats[0].project_id, project.id);
+    assert_eq!(stats[0].count, 2);
+    assert_eq!(stats[0].size, 2 * object.content_len);
+
+    // Members download without acknowledgment at the cost of the project
+    let attribution = db_handler
+        .account_download(&object.id, &member.id, false)
+        .await
+        .unwrap();
+    assert!(!attribution.requester_pays);
+    let stats = DownloadStats::get_user_download_stats(&member.id, client)
+        .await
+        .unwrap();
+    assert_eq!(stats.len(), 1);
+    assert!(!stats[0].requester_pays);
+    assert_eq!(stats[0].size, object.content_len);
+}
@@ -0,0 +1,143 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::object_dsl::{Algorithm, Hash, Hashes, Object};
+use aruna_server::database::enums::{ObjectMapping, ObjectStatus, ObjectType};
+use aruna_server::hooks::scan_hook::{ScanResponse, ScanVerdict};
+use diesel_ulid::DieselUlid;
+use postgres_types::Json;
+use std::time::Duration;
+
+#[tokio::test]
+async fn scan_attestations() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+    let ttl = Duration::from_secs(3600);
+
+    // signature versions and content hashes unique to this test run
+    let old_version = DieselUlid::generate().to_string();
+    let new_version = DieselUlid::generate().to_string();
+    let hash = || format!("{:0>64}", DieselUlid::generate().to_string().to_lowercase());
+    let (clean_hash, infected_hash, other_hash) = (hash(), hash(), hash());
+
+    // create a project with scanned objects and re-uploads of their content
+    let project_id = DieselUlid::generate();
+    let mut user = test_utils::new_user(vec![ObjectMapping::PROJECT(project_id)]);
+    user.create(client).await.unwrap();
+    let mut project = test_utils::new_object(user.id, project_id, ObjectType::PROJECT);
+    project.create(client).await.unwrap();
+    cache.add_object(
+        Object::get_object_with_relations(&project.id, client)
+            .await
+            .unwrap(),
+    );
+    let mut objects = Vec::new();
+    for content in [
+        &clean_hash,
+        &infected_hash,
+        &other_hash,
+        &clean_hash,
+        &clean_hash,
+        &infected_hash,
+    ] {
+        let mut object =
+            test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+        object.object_status = ObjectStatus::VALIDATING;
+        object.hashes = Json(Hashes(vec![Hash {
+            alg: Algorithm::SHA256,
+            hash: content.clone(),
+        }]));
+        object.create(client).await.unwrap();
+        test_utils::new_internal_relation(&project, &object)
+            .create(client)
+            .await
+            .unwrap();
+        cache.add_object(
+            Object::get_object_with_relations(&object.id, client)
+                .await
+                .unwrap(),
+        );
+        objects.push(object.id);
+    }
+    let [clean, infected, other, reupload, late_reupload, infected_reupload] = objects[..] else {
+        panic!("Unexpected number of objects");
+    };
+
+    // content that was never scanned has no verdict
+    assert!(db_handler
+        .cached_scan_verdict(clean, ttl)
+        .await
+        .unwrap()
+        .is_none());
+
+    // scans with the old signatures
+    for (object_id, verdict) in [
+        (clean, ScanVerdict::Clean),
+        (
+            infected,
+            ScanVerdict::Infected {
+                details: Some("Eicar-Test-Signature".to_string()),
+            },
+        ),
+    ] {
+        db_handler
+            .attest_scan(
+                object_id,
+                &ScanResponse {
+                    verdict,
+                    version: Some(old_version.clone()),
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    // re-upload of clean content skips the scan and becomes available
+    let verdict = db_handler.cached_scan_verdict(reupload, ttl).await.unwrap();
+    assert_eq!(verdict, Some(ScanVerdict::Clean));
+    let object = db_handler
+        .finish_scan(reupload, &verdict.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(object.object.object_status, ObjectStatus::AVAILABLE);
+
+    // a scan with new signatures invalidates clean verdicts of the old signatures
+    db_handler
+        .attest_scan(
+            other,
+            &ScanResponse {
+                verdict: ScanVerdict::Clean,
+                version: Some(new_version.clone()),
+            },
+        )
+        .await
+        .unwrap();
+    assert!(db_handler
+        .cached_scan_verdict(late_reupload, ttl)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        db_handler.cached_scan_verdict(other, ttl).await.unwrap(),
+        Some(ScanVerdict::Clean)
+    );
+
+    // quarantined content stays rejected
+    let verdict = db_handler
+        .cached_scan_verdict(infected_reupload, ttl)
+        .await
+        .unwrap();
+    assert_eq!(
+        verdict,
+        Some(ScanVerdict::Infected {
+            details: Some("Eicar-Test-Signature".to_string()),
+        })
+    );
+    let object = db_handler
+        .finish_scan(infected_reupload, &verdict.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(object.object.object_status, ObjectStatus::UNAVAILABLE);
+}
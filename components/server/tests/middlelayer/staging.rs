@@ -0,0 +1,537 @@
+use crate::common::init::{
+    init_database_handler_middlelayer, init_permission_handler, init_token_handler,
+};
+use crate::common::test_utils;
+use aruna_rust_api::api::storage::models::v2::{Hash, Hashalgorithm};
+use aruna_rust_api::api::storage::services::v2::create_object_request::Parent as ObjectParent;
+use aruna_rust_api::api::storage::services::v2::{
+    CreateObjectRequest, CreateProjectRequest, FinishObjectStagingRequest,
+};
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::license_dsl::ALL_RIGHTS_RESERVED;
+use aruna_server::database::dsls::object_dsl::{Algorithm, EndpointInfo, Hash as DBHash, Object};
+use aruna_server::database::dsls::staging_dsl::StagingDeadline;
+use aruna_server::database::enums::{ObjectStatus, ReplicationType};
+use aruna_server::middlelayer::create_request_types::CreateRequest;
+use aruna_server::middlelayer::db_handler::DatabaseHandler;
+use aruna_server::middlelayer::presigned_url_handler::{
+    PartPlan, UploadSizePolicy, MAX_PARTS, MIN_PART_SIZE,
+};
+use aruna_server::middlelayer::staging_db_handler::UploadLimitExceeded;
+use aruna_server::middlelayer::update_db_handler::FinishConflict;
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+
+#[tokio::test]
+async fn staging_expiry() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+    let token_handler = init_token_handler(db_handler.database.clone(), cache.clone()).await;
+    let authorizer = init_permission_handler(cache.clone(), token_handler).await;
+
+    // create user
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+
+    // create project and staging object
+    let project = CreateRequest::Project(
+        CreateProjectRequest {
+            name: test_utils::rand_string(32).to_lowercase(),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            preferred_endpoint: "".to_string(),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            default_data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        },
+        DieselUlid::generate().to_string(),
+    );
+    let (project, _) = db_handler
+        .create_resource(project, user.id, false)
+        .await
+        .unwrap();
+    cache.add_object(project.clone());
+
+    let request = CreateRequest::Object(CreateObjectRequest {
+        name: test_utils::rand_string(32),
+        title: "".to_string(),
+        description: "test".to_string(),
+        key_values: vec![],
+        relations: vec![],
+        data_class: 1,
+        hashes: vec![],
+        parent: Some(ObjectParent::ProjectId(project.object.id.to_string())),
+        metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        authors: vec![],
+    });
+    let (object, _) = db_handler
+        .create_resource(request, user.id, false)
+        .await
+        .unwrap();
+    cache.add_object(object.clone());
+
+    // Deadline is recorded on initialization and can be renewed
+    let deadline = StagingDeadline::get(object.object.id, client)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(deadline.user_id, user.id);
+    assert!(deadline.deadline > Utc::now().naive_utc());
+    assert!(db_handler
+        .heartbeat_upload(&object.object.id, None)
+        .await
+        .unwrap());
+
+    // Nothing expired yet
+    let aborted = db_handler
+        .abort_expired_staging(authorizer.clone(), Utc::now().naive_utc())
+        .await
+        .unwrap();
+    assert!(!aborted.contains(&object.object.id));
+
+    // Expire deadline, renewal is rejected afterwards
+    StagingDeadline::renew(
+        &object.object.id,
+        Utc::now().naive_utc() - chrono::Duration::seconds(1),
+        None,
+        client,
+    )
+    .await
+    .unwrap();
+    assert!(!db_handler
+        .heartbeat_upload(&object.object.id, None)
+        .await
+        .unwrap());
+
+    // Expired object gets aborted
+    let aborted = db_handler
+        .abort_expired_staging(authorizer, Utc::now().naive_utc())
+        .await
+        .unwrap();
+    assert!(aborted.contains(&object.object.id));
+
+    let aborted_object = Object::get(object.object.id, client)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(aborted_object.object_status, ObjectStatus::ERROR);
+    assert_eq!(
+        cache
+            .get_object(&object.object.id)
+            .unwrap()
+            .object
+            .object_status,
+        ObjectStatus::ERROR
+    );
+}
+
+#[tokio::test]
+async fn concurrent_finish() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+
+    // create user
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+
+    // create project and staging object
+    let project = CreateRequest::Project(
+        CreateProjectRequest {
+            name: test_utils::rand_string(32).to_lowercase(),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            preferred_endpoint: "".to_string(),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            default_data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        },
+        DieselUlid::generate().to_string(),
+    );
+    let (project, _) = db_handler
+        .create_resource(project, user.id, false)
+        .await
+        .unwrap();
+    cache.add_object(project.clone());
+
+    let request = CreateRequest::Object(CreateObjectRequest {
+        name: test_utils::rand_string(32),
+        title: "".to_string(),
+        description: "test".to_string(),
+        key_values: vec![],
+        relations: vec![],
+        data_class: 1,
+        hashes: vec![],
+        parent: Some(ObjectParent::ProjectId(project.object.id.to_string())),
+        metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        authors: vec![],
+    });
+    let (object, _) = db_handler
+        .create_resource(request, user.id, false)
+        .await
+        .unwrap();
+    cache.add_object(object.clone());
+
+    let endpoint_id = DieselUlid::generate();
+    Object::update_endpoints(
+        endpoint_id,
+        EndpointInfo {
+            replication: ReplicationType::FullSync,
+            status: None,
+        },
+        vec![object.object.id],
+        client,
+    )
+    .await
+    .unwrap();
+
+    // Double submit of the same finish
+    let md5 = Hash {
+        alg: Hashalgorithm::Md5 as i32,
+        hash: "0cc175b9c0f1b6a831c399e269772661".to_string(),
+    };
+    let sha256 = Hash {
+        alg: Hashalgorithm::Sha256 as i32,
+        hash: "ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb".to_string(),
+    };
+    let finish = FinishObjectStagingRequest {
+        object_id: object.object.id.to_string(),
+        content_len: 1234,
+        hashes: vec![md5.clone(), sha256.clone()],
+        completed_parts: vec![],
+    };
+    let (first, second) = tokio::join!(
+        db_handler.finish_object(finish.clone(), Some(endpoint_id)),
+        db_handler.finish_object(finish.clone(), Some(endpoint_id)),
+    );
+    let (first, first_finalized) = first.unwrap();
+    let (second, second_finalized) = second.unwrap();
+
+    // Exactly one request finalizes the object, both return the finished object
+    assert!(first_finalized ^ second_finalized);
+    assert_eq!(first.object.id, second.object.id);
+    assert_eq!(first.object.object_status, ObjectStatus::AVAILABLE);
+    assert_eq!(second.object.object_status, ObjectStatus::AVAILABLE);
+    assert_eq!(second.object.content_len, 1234);
+
+    // Finishes with a subset of the hashes return the finished object
+    for hashes in [vec![sha256.clone()], vec![md5], vec![]] {
+        let subset = FinishObjectStagingRequest {
+            hashes,
+            ..finish.clone()
+        };
+        let (object, finalized) = db_handler
+            .finish_object(subset, Some(endpoint_id))
+            .await
+            .unwrap();
+        assert!(!finalized);
+        assert_eq!(object.object.id, first.object.id);
+    }
+
+    // Finishes with different content are rejected
+    let conflicting_hash = FinishObjectStagingRequest {
+        hashes: vec![Hash {
+            hash: "3e23e8160039594a33894f6564e1b1348bbd7a0088d42c4acb73eeaed59c009d".to_string(),
+            ..sha256
+        }],
+        ..finish.clone()
+    };
+    let conflicting_len = FinishObjectStagingRequest {
+        content_len: 4321,
+        ..finish
+    };
+    for conflicting in [conflicting_hash, conflicting_len] {
+        let err = db_handler
+            .finish_object(conflicting, Some(endpoint_id))
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<FinishConflict>().is_some());
+    }
+}
+
+#[tokio::test]
+async fn expected_content_md5() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+
+    // create user
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+
+    // create project and two staging objects
+    let project = CreateRequest::Project(
+        CreateProjectRequest {
+            name: test_utils::rand_string(32).to_lowercase(),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            preferred_endpoint: "".to_string(),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            default_data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        },
+        DieselUlid::generate().to_string(),
+    );
+    let (project, _) = db_handler
+        .create_resource(project, user.id, false)
+        .await
+        .unwrap();
+    cache.add_object(project.clone());
+
+    let endpoint_id = DieselUlid::generate();
+    let mut objects = Vec::new();
+    for _ in 0..2 {
+        let request = CreateRequest::Object(CreateObjectRequest {
+            name: test_utils::rand_string(32),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            hashes: vec![],
+            parent: Some(ObjectParent::ProjectId(project.object.id.to_string())),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        });
+        let (object, _) = db_handler
+            .create_resource(request, user.id, false)
+            .await
+            .unwrap();
+        cache.add_object(object.clone());
+        Object::update_endpoints(
+            endpoint_id,
+            EndpointInfo {
+                replication: ReplicationType::FullSync,
+                status: None,
+            },
+            vec![object.object.id],
+            client,
+        )
+        .await
+        .unwrap();
+        objects.push(object.object.id);
+    }
+
+    // Expected md5 is recorded when the upload url is requested
+    let expected_md5 = "0cc175b9c0f1b6a831c399e269772661";
+    for id in &objects {
+        db_handler
+            .set_expected_md5(*id, &expected_md5.to_uppercase())
+            .await
+            .unwrap();
+    }
+    let md5_hash = |hash: &str| Hash {
+        alg: Hashalgorithm::Md5 as i32,
+        hash: hash.to_string(),
+    };
+
+    // Matching md5 finishes the object
+    let finish = FinishObjectStagingRequest {
+        object_id: objects[0].to_string(),
+        content_len: 1,
+        hashes: vec![md5_hash(expected_md5)],
+        completed_parts: vec![],
+    };
+    let (finished, _) = db_handler
+        .finish_object(finish, Some(endpoint_id))
+        .await
+        .unwrap();
+    assert_eq!(finished.object.object_status, ObjectStatus::AVAILABLE);
+    assert_eq!(
+        finished.object.hashes.0 .0,
+        vec![DBHash {
+            alg: Algorithm::MD5,
+            hash: expected_md5.to_string()
+        }]
+    );
+
+    // Mismatching md5 is rejected and the object keeps staging
+    let finish = FinishObjectStagingRequest {
+        object_id: objects[1].to_string(),
+        content_len: 1,
+        hashes: vec![md5_hash("92eb5ffee6ae2fec3ad71c777531578f")],
+        completed_parts: vec![],
+    };
+    assert!(db_handler
+        .finish_object(finish, Some(endpoint_id))
+        .await
+        .is_err());
+    let staging = Object::get(objects[1], client).await.unwrap().unwrap();
+    assert_eq!(staging.object_status, ObjectStatus::INITIALIZING);
+
+    // Finished objects can not get a new expected md5
+    assert!(db_handler
+        .set_expected_md5(objects[0], expected_md5)
+        .await
+        .is_err());
+}
+
+#[test]
+fn multipart_part_plan() {
+    // Small uploads use the minimal part size
+    let plan = PartPlan::recommend(Some(12 * 1024 * 1024)).unwrap();
+    assert_eq!(plan.part_size, MIN_PART_SIZE);
+    assert_eq!(plan.part_count, 3);
+    assert!(plan.check_part_number(3).is_ok());
+    assert!(plan.check_part_number(4).is_err());
+    assert!(plan.check_part_number(0).is_err());
+
+    // Huge uploads get bigger parts to stay within the part limit
+    let content_len = 5 * 1024 * 1024 * 1024 * 1024; // 5 TiB
+    let plan = PartPlan::recommend(Some(content_len)).unwrap();
+    assert!(plan.part_count <= MAX_PARTS);
+    assert!(plan.part_size * plan.part_count >= content_len);
+    assert_eq!(plan.part_size % (1024 * 1024), 0);
+
+    // Unknown sizes get the default part size
+    let plan = PartPlan::recommend(None).unwrap();
+    assert!(plan.part_size >= MIN_PART_SIZE);
+    assert_eq!(plan.part_count, MAX_PARTS);
+
+    // Parts above 5 GiB are not supported
+    assert!(PartPlan::recommend(Some(u64::MAX)).is_err());
+}
+
+#[test]
+fn upload_size_range() {
+    // Exact uploads have the declared size
+    let range = UploadSizePolicy::Exact
+        .upload_range(Some(1000), None, 1)
+        .unwrap();
+    assert_eq!((range.min, range.max), (1000, 1000));
+    assert_eq!(range.to_string(), "1000-1000");
+
+    // Tolerated uploads may be smaller or larger by the factor
+    let range = UploadSizePolicy::Tolerance(1.25)
+        .upload_range(Some(1000), None, 1)
+        .unwrap();
+    assert_eq!((range.min, range.max), (800, 1250));
+
+    // Parts of multipart uploads are bound by their share of the declared size
+    let content_len = 12 * 1024 * 1024;
+    let plan = PartPlan::recommend(Some(content_len)).unwrap();
+    let first = UploadSizePolicy::Exact
+        .upload_range(Some(content_len), Some(&plan), 1)
+        .unwrap();
+    assert_eq!(first.max, MIN_PART_SIZE);
+    let last = UploadSizePolicy::Exact
+        .upload_range(Some(content_len), Some(&plan), 3)
+        .unwrap();
+    assert_eq!(last.max, content_len - 2 * MIN_PART_SIZE);
+
+    // Uploads without declared size are not bound
+    assert!(UploadSizePolicy::Exact
+        .upload_range(None, None, 1)
+        .is_none());
+}
+
+#[tokio::test]
+async fn upload_limit() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+
+    // create user
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+
+    // create project and two staging objects
+    let project = CreateRequest::Project(
+        CreateProjectRequest {
+            name: test_utils::rand_string(32).to_lowercase(),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            preferred_endpoint: "".to_string(),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            default_data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        },
+        DieselUlid::generate().to_string(),
+    );
+    let (project, _) = db_handler
+        .create_resource(project, user.id, false)
+        .await
+        .unwrap();
+    cache.add_object(project.clone());
+
+    let mut objects = Vec::new();
+    for _ in 0..2 {
+        let request = CreateRequest::Object(CreateObjectRequest {
+            name: test_utils::rand_string(32),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            hashes: vec![],
+            parent: Some(ObjectParent::ProjectId(project.object.id.to_string())),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        });
+        let (object, _) = db_handler
+            .create_resource(request, user.id, false)
+            .await
+            .unwrap();
+        cache.add_object(object.clone());
+        objects.push(object.object.id);
+    }
+
+    // A third upload exceeds a limit of two
+    let err = DatabaseHandler::enforce_upload_limit(&user.id, 2, client)
+        .await
+        .unwrap_err();
+    let exceeded = err.downcast_ref::<UploadLimitExceeded>().unwrap();
+    assert_eq!(exceeded.uploads, 2);
+    assert_eq!(exceeded.limit, 2);
+    DatabaseHandler::enforce_upload_limit(&user.id, 3, client)
+        .await
+        .unwrap();
+
+    // Finishing an upload frees a slot
+    let endpoint_id = DieselUlid::generate();
+    Object::update_endpoints(
+        endpoint_id,
+        EndpointInfo {
+            replication: ReplicationType::FullSync,
+            status: None,
+        },
+        vec![objects[0]],
+        client,
+    )
+    .await
+    .unwrap();
+    let finish = FinishObjectStagingRequest {
+        object_id: objects[0].to_string(),
+        content_len: 1234,
+        hashes: vec![],
+        completed_parts: vec![],
+    };
+    db_handler
+        .finish_object(finish, Some(endpoint_id))
+        .await
+        .unwrap();
+    DatabaseHandler::enforce_upload_limit(&user.id, 2, client)
+        .await
+        .unwrap();
+}
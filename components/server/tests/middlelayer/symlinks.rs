@@ -0,0 +1,158 @@
+use crate::common::init::init_database_handler_middlelayer;
+use crate::common::test_utils;
+use aruna_rust_api::api::storage::services::v2::{DeleteObjectRequest, UpdateObjectRequest};
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::internal_relation_dsl::INTERNAL_RELATION_VARIANT_SYMLINK;
+use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::enums::{ObjectStatus, ObjectType};
+use aruna_server::middlelayer::delete_request_types::DeleteRequest;
+use aruna_server::middlelayer::symlink_request_types::{CreateSymlink, GetSymlink};
+use diesel_ulid::DieselUlid;
+
+#[tokio::test]
+async fn symlinks() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+
+    // project -> (collection_a -> source, collection_b)
+    let mut user = test_utils::new_user(vec![]);
+    user.create(client).await.unwrap();
+    let mut project = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    let mut collection_a =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::COLLECTION);
+    let mut collection_b =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::COLLECTION);
+    let mut source = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    let mut other = test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::OBJECT);
+    other.name = "existing.txt".to_string();
+    for resource in [
+        &mut project,
+        &mut collection_a,
+        &mut collection_b,
+        &mut source,
+        &mut other,
+    ] {
+        resource.create(client).await.unwrap();
+    }
+    for (origin, target) in [
+        (&project, &collection_a),
+        (&project, &collection_b),
+        (&collection_a, &source),
+        (&collection_b, &other),
+    ] {
+        test_utils::new_internal_relation(origin, target)
+            .create(client)
+            .await
+            .unwrap();
+    }
+    for resource in Object::get_objects_with_relations(
+        &vec![
+            project.id,
+            collection_a.id,
+            collection_b.id,
+            source.id,
+            other.id,
+        ],
+        client,
+    )
+    .await
+    .unwrap()
+    {
+        db_handler.cache.add_object(resource);
+    }
+
+    // symlink in collection_b to the source in collection_a
+    let create = CreateSymlink {
+        source_id: source.id.to_string(),
+        target_collection_id: collection_b.id.to_string(),
+        name: "link.txt".to_string(),
+    };
+    let collection = db_handler.create_symlink(create.clone()).await.unwrap();
+    assert!(collection
+        .outbound
+        .0
+        .iter()
+        .any(
+            |relation| relation.relation_name == INTERNAL_RELATION_VARIANT_SYMLINK
+                && relation.target_pid == source.id
+        ));
+
+    // names are unique within the collection, also against regular children
+    assert!(db_handler.create_symlink(create).await.is_err());
+    assert!(db_handler
+        .create_symlink(CreateSymlink {
+            source_id: source.id.to_string(),
+            target_collection_id: collection_b.id.to_string(),
+            name: other.name.clone(),
+        })
+        .await
+        .is_err());
+
+    // a new revision of the source is resolved without updating the symlink
+    let (revision, is_new) = db_handler
+        .update_grpc_object(
+            UpdateObjectRequest {
+                object_id: source.id.to_string(),
+                name: Some("renamed.txt".to_string()),
+                description: None,
+                add_key_values: vec![],
+                remove_key_values: vec![],
+                data_class: 0,
+                hashes: vec![],
+                parent: None,
+                force_revision: true,
+                metadata_license_tag: None,
+                data_license_tag: None,
+            },
+            user.id,
+            false,
+        )
+        .await
+        .unwrap();
+    assert!(is_new);
+    let symlink = GetSymlink {
+        collection_id: collection_b.id.to_string(),
+        name: "link.txt".to_string(),
+    };
+    let (relation, resolved) = db_handler.resolve_symlink(&symlink).await.unwrap();
+    assert_eq!(resolved.object.id, revision.object.id);
+    assert_eq!(resolved.object.name, "renamed.txt");
+    assert_eq!(relation.target_name, "link.txt");
+    assert!(db_handler
+        .resolve_symlink(&GetSymlink {
+            collection_id: collection_b.id.to_string(),
+            name: "missing.txt".to_string(),
+        })
+        .await
+        .is_err());
+
+    // sources of symlinks can not be deleted
+    assert!(db_handler
+        .delete_resource(DeleteRequest::Object(DeleteObjectRequest {
+            object_id: revision.object.id.to_string(),
+            with_revisions: true,
+        }))
+        .await
+        .is_err());
+    assert_eq!(
+        Object::get(revision.object.id, client)
+            .await
+            .unwrap()
+            .unwrap()
+            .object_status,
+        ObjectStatus::AVAILABLE
+    );
+
+    // removing the symlink keeps the source
+    db_handler.delete_symlink(&symlink).await.unwrap();
+    assert!(db_handler.resolve_symlink(&symlink).await.is_err());
+    assert_eq!(
+        Object::get(revision.object.id, client)
+            .await
+            .unwrap()
+            .unwrap()
+            .object_status,
+        ObjectStatus::AVAILABLE
+    );
+}
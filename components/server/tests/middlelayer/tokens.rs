@@ -0,0 +1,337 @@
+use crate::common::init::{
+    init_database_handler_middlelayer, init_permission_handler, init_token_handler,
+};
+use crate::common::test_utils;
+use aruna_rust_api::api::storage::models::v2::permission::ResourceId;
+use aruna_rust_api::api::storage::models::v2::{Permission, PermissionLevel};
+use aruna_rust_api::api::storage::services::v2::{CreateApiTokenRequest, DeleteApiTokenRequest};
+use aruna_server::auth::structs::Context;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::enums::{DbPermissionLevel, ObjectMapping, ObjectType};
+use aruna_server::middlelayer::token_request_types::{
+    AssumeStorageRole, CreateToken, DeleteToken, DownscopeToken,
+};
+use chrono::Utc;
+use diesel_ulid::DieselUlid;
+
+#[tokio::test]
+async fn downscope_token() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+    let token_handler = init_token_handler(db_handler.database.clone(), cache.clone()).await;
+    let authorizer = init_permission_handler(cache.clone(), token_handler.clone()).await;
+    let pubkey_serial = token_handler.get_current_pubkey_serial() as i32;
+
+    // create hierarchy and user with write permissions on the project
+    let project_id = DieselUlid::generate();
+    let mut user = test_utils::new_user(vec![ObjectMapping::PROJECT(project_id)]);
+    user.create(client).await.unwrap();
+    cache.add_user(user.id, user.clone());
+    let mut project = test_utils::new_object(user.id, project_id, ObjectType::PROJECT);
+    let mut collection =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::COLLECTION);
+    let mut other_project =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    for resource in [&mut project, &mut collection, &mut other_project] {
+        resource.create(client).await.unwrap();
+    }
+    test_utils::new_internal_relation(&project, &collection)
+        .create(client)
+        .await
+        .unwrap();
+    for id in [project.id, collection.id, other_project.id] {
+        cache.add_object(
+            Object::get_object_with_relations(&id, client)
+                .await
+                .unwrap(),
+        );
+    }
+
+    // parent token with read permissions on the project
+    let parent_expiry = Utc::now().timestamp() + 86400;
+    let (parent_id, parent) = db_handler
+        .create_token(
+            &user.id,
+            pubkey_serial,
+            CreateToken(CreateApiTokenRequest {
+                name: "parent".to_string(),
+                permission: Some(Permission {
+                    permission_level: PermissionLevel::Read as i32,
+                    resource_id: Some(ResourceId::ProjectId(project.id.to_string())),
+                }),
+                expires_at: Some(prost_wkt_types::Timestamp {
+                    seconds: parent_expiry,
+                    nanos: 0,
+                }),
+            }),
+        )
+        .await
+        .unwrap();
+    let parent_secret = token_handler
+        .sign_user_token(&user.id, &parent_id, Some(parent.expires_at.into()))
+        .unwrap();
+
+    // child can not exceed the permission level or the lifetime of the parent
+    let request = DownscopeToken {
+        parent_token: parent_secret.clone(),
+        name: "child".to_string(),
+        requested_scopes: vec![Permission {
+            permission_level: PermissionLevel::Admin as i32,
+            resource_id: Some(ResourceId::CollectionId(collection.id.to_string())),
+        }],
+        ttl: Some(10 * 86400),
+    };
+    let (user_id, child) = authorizer
+        .downscope_token(&request, pubkey_serial)
+        .await
+        .unwrap();
+    assert_eq!(user_id, user.id);
+    assert_eq!(child.parent, Some(parent_id));
+    assert_eq!(
+        child.object_id,
+        Some(ObjectMapping::COLLECTION(collection.id))
+    );
+    assert_eq!(child.user_rights, DbPermissionLevel::READ);
+    assert_eq!(child.expires_at, parent.expires_at);
+
+    // child can not leave the scope of the parent
+    let outside = DownscopeToken {
+        requested_scopes: vec![Permission {
+            permission_level: PermissionLevel::Read as i32,
+            resource_id: Some(ResourceId::ProjectId(other_project.id.to_string())),
+        }],
+        ..request.clone()
+    };
+    assert!(authorizer
+        .downscope_token(&outside, pubkey_serial)
+        .await
+        .is_err());
+
+    // store child and downscope it again
+    let child_id = db_handler
+        .create_downscoped_token(&user.id, child.clone())
+        .await
+        .unwrap();
+    let child_secret = token_handler
+        .sign_user_token(&user.id, &child_id, Some(child.expires_at.into()))
+        .unwrap();
+    let grandchild_request = DownscopeToken {
+        parent_token: child_secret.clone(),
+        name: "grandchild".to_string(),
+        requested_scopes: vec![],
+        ttl: Some(60),
+    };
+    let (_, grandchild) = authorizer
+        .downscope_token(&grandchild_request, pubkey_serial)
+        .await
+        .unwrap();
+    assert_eq!(grandchild.object_id, child.object_id);
+    assert_eq!(grandchild.user_rights, DbPermissionLevel::READ);
+    assert!(grandchild.expires_at < child.expires_at);
+    let grandchild_id = db_handler
+        .create_downscoped_token(&user.id, grandchild)
+        .await
+        .unwrap();
+
+    assert!(authorizer
+        .check_permissions(
+            &child_secret,
+            vec![Context::res_ctx(
+                collection.id,
+                DbPermissionLevel::READ,
+                true
+            )]
+        )
+        .await
+        .is_ok());
+    assert!(authorizer
+        .check_permissions(
+            &child_secret,
+            vec![Context::res_ctx(
+                collection.id,
+                DbPermissionLevel::WRITE,
+                true
+            )]
+        )
+        .await
+        .is_err());
+
+    // revoking the parent revokes all downscoped tokens
+    db_handler
+        .delete_token(
+            user.id,
+            DeleteToken(DeleteApiTokenRequest {
+                token_id: parent_id.to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+    let user = cache.get_user(&user.id).unwrap();
+    for token_id in [parent_id, child_id, grandchild_id] {
+        assert!(!user.attributes.0.tokens.contains_key(&token_id));
+    }
+    assert!(authorizer
+        .check_permissions(
+            &child_secret,
+            vec![Context::res_ctx(
+                collection.id,
+                DbPermissionLevel::READ,
+                true
+            )]
+        )
+        .await
+        .is_err());
+    assert!(authorizer
+        .downscope_token(&grandchild_request, pubkey_serial)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn assume_storage_role() {
+    // init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = &db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+    let token_handler = init_token_handler(db_handler.database.clone(), cache.clone()).await;
+    let authorizer = init_permission_handler(cache.clone(), token_handler.clone()).await;
+    let pubkey_serial = token_handler.get_current_pubkey_serial() as i32;
+
+    // create project with two collections and a user with write permissions on it
+    let project_id = DieselUlid::generate();
+    let mut user = test_utils::new_user(vec![ObjectMapping::PROJECT(project_id)]);
+    user.create(client).await.unwrap();
+    cache.add_user(user.id, user.clone());
+    let mut project = test_utils::new_object(user.id, project_id, ObjectType::PROJECT);
+    let mut collection_a =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::COLLECTION);
+    let mut collection_b =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::COLLECTION);
+    for resource in [&mut project, &mut collection_a, &mut collection_b] {
+        resource.create(client).await.unwrap();
+    }
+    for collection in [&collection_a, &collection_b] {
+        test_utils::new_internal_relation(&project, collection)
+            .create(client)
+            .await
+            .unwrap();
+    }
+    for id in [project.id, collection_a.id, collection_b.id] {
+        cache.add_object(
+            Object::get_object_with_relations(&id, client)
+                .await
+                .unwrap(),
+        );
+    }
+    let (token_id, token) = db_handler
+        .create_token(
+            &user.id,
+            pubkey_serial,
+            CreateToken(CreateApiTokenRequest {
+                name: "sync".to_string(),
+                permission: Some(Permission {
+                    permission_level: PermissionLevel::Write as i32,
+                    resource_id: Some(ResourceId::ProjectId(project.id.to_string())),
+                }),
+                expires_at: None,
+            }),
+        )
+        .await
+        .unwrap();
+    let secret = token_handler
+        .sign_user_token(&user.id, &token_id, Some(token.expires_at.into()))
+        .unwrap();
+
+    // role scoped to collection A
+    let request = AssumeStorageRole {
+        resource_scope: Permission {
+            permission_level: PermissionLevel::Read as i32,
+            resource_id: Some(ResourceId::CollectionId(collection_a.id.to_string())),
+        },
+        ttl: 3600,
+        endpoint_id: None,
+    };
+    let (user_id, role) = authorizer
+        .assume_storage_role(&secret, &request, pubkey_serial)
+        .await
+        .unwrap();
+    assert_eq!(user_id, user.id);
+    assert_eq!(role.parent, Some(token_id));
+    assert!(role.expires_at <= Utc::now().naive_utc() + chrono::Duration::seconds(3600));
+    let role_id = db_handler
+        .create_hook_token(&user.id, role.clone())
+        .await
+        .unwrap();
+    let role_secret = token_handler
+        .sign_user_token(&user.id, &role_id, Some(role.expires_at.into()))
+        .unwrap();
+
+    // credentials scoped to collection A can not access collection B
+    assert!(authorizer
+        .check_permissions(
+            &role_secret,
+            vec![Context::res_ctx(
+                collection_a.id,
+                DbPermissionLevel::READ,
+                true
+            )]
+        )
+        .await
+        .is_ok());
+    for (resource, level) in [
+        (collection_b.id, DbPermissionLevel::READ),
+        (project.id, DbPermissionLevel::READ),
+        (collection_a.id, DbPermissionLevel::WRITE),
+    ] {
+        assert!(authorizer
+            .check_permissions(&role_secret, vec![Context::res_ctx(resource, level, true)])
+            .await
+            .is_err());
+    }
+
+    // roles can not exceed the permissions of the caller
+    let exceeding = AssumeStorageRole {
+        resource_scope: Permission {
+            permission_level: PermissionLevel::Admin as i32,
+            resource_id: Some(ResourceId::CollectionId(collection_b.id.to_string())),
+        },
+        ..request.clone()
+    };
+    assert!(authorizer
+        .assume_storage_role(&secret, &exceeding, pubkey_serial)
+        .await
+        .is_err());
+
+    // expired roles are deleted, the calling token is kept
+    assert_eq!(
+        db_handler
+            .expire_storage_roles(Utc::now().naive_utc())
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        db_handler
+            .expire_storage_roles(role.expires_at)
+            .await
+            .unwrap(),
+        1
+    );
+    let user = cache.get_user(&user.id).unwrap();
+    assert!(!user.attributes.0.tokens.contains_key(&role_id));
+    assert!(user.attributes.0.tokens.contains_key(&token_id));
+    assert!(authorizer
+        .check_permissions(
+            &role_secret,
+            vec![Context::res_ctx(
+                collection_a.id,
+                DbPermissionLevel::READ,
+                true
+            )]
+        )
+        .await
+        .is_err());
+}
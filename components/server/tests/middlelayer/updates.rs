@@ -1,19 +1,28 @@
 use crate::common::init::init_database_handler_middlelayer;
 use crate::common::test_utils;
 use aruna_rust_api::api::storage::models::v2::{Hash, KeyValue as APIKeyValue};
+use aruna_rust_api::api::storage::services::v2::create_object_request::Parent as ObjectParent;
 use aruna_rust_api::api::storage::services::v2::{
-    UpdateCollectionDataClassRequest, UpdateCollectionDescriptionRequest,
-    UpdateCollectionKeyValuesRequest, UpdateCollectionNameRequest, UpdateDatasetDataClassRequest,
-    UpdateDatasetDescriptionRequest, UpdateDatasetKeyValuesRequest, UpdateDatasetNameRequest,
-    UpdateObjectRequest, UpdateProjectDataClassRequest, UpdateProjectDescriptionRequest,
-    UpdateProjectKeyValuesRequest, UpdateProjectNameRequest,
+    CreateObjectRequest, CreateProjectRequest, UpdateCollectionDataClassRequest,
+    UpdateCollectionDescriptionRequest, UpdateCollectionKeyValuesRequest,
+    UpdateCollectionNameRequest, UpdateDatasetDataClassRequest, UpdateDatasetDescriptionRequest,
+    UpdateDatasetKeyValuesRequest, UpdateDatasetNameRequest, UpdateObjectRequest,
+    UpdateProjectDataClassRequest, UpdateProjectDescriptionRequest, UpdateProjectKeyValuesRequest,
+    UpdateProjectNameRequest,
 };
 use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::endpoint_dsl::{Endpoint, HostConfigs};
 use aruna_server::database::dsls::license_dsl::ALL_RIGHTS_RESERVED;
-use aruna_server::database::dsls::object_dsl::{KeyValue, KeyValueVariant, KeyValues, Object};
-use aruna_server::database::enums::{DataClass, ObjectMapping, ObjectStatus, ObjectType};
+use aruna_server::database::dsls::object_dsl::{
+    EndpointInfo, KeyValue, KeyValueType, KeyValueVariant, KeyValues, Object,
+};
+use aruna_server::database::enums::{
+    DataClass, EndpointStatus, EndpointVariant, ObjectMapping, ObjectStatus, ObjectType,
+    ReplicationType,
+};
+use aruna_server::middlelayer::create_request_types::CreateRequest;
 use aruna_server::middlelayer::update_request_types::{
-    DataClassUpdate, DescriptionUpdate, KeyValueUpdate, NameUpdate,
+    DataClassUpdate, DescriptionUpdate, KeyValueUpdate, NameUpdate, UpdateProjectDefaultEndpoint,
 };
 use diesel_ulid::DieselUlid;
 use itertools::Itertools;
@@ -245,6 +254,7 @@ async fn test_update_keyvals() {
         key: "DELETE".to_string(),
         value: "This key will be deleted".to_string(),
         variant: KeyValueVariant::LABEL,
+        value_type: None,
     };
     for r in resources {
         let mut o = test_utils::object_from_mapping(user.id, r);
@@ -272,6 +282,7 @@ async fn test_update_keyvals() {
         key: "ADDED".to_string(),
         value: "This label gets added".to_string(),
         variant: KeyValueVariant::LABEL,
+        value_type: None,
     };
     let static_kv = APIKeyValue {
         key: "ADDED".to_string(),
@@ -282,6 +293,7 @@ async fn test_update_keyvals() {
         key: "ADDED".to_string(),
         value: "This label gets added".to_string(),
         variant: KeyValueVariant::STATIC_LABEL,
+        value_type: None,
     };
     let deleted = APIKeyValue {
         key: "DELETE".to_string(),
@@ -401,6 +413,7 @@ async fn update_object_test() {
         key: "to_delete".to_string(),
         value: "deleted".to_string(),
         variant: KeyValueVariant::LABEL,
+        value_type: None,
     });
     let client = db_handler.database.get_client().await.unwrap();
     user.create(&client).await.unwrap();
@@ -447,6 +460,7 @@ async fn update_object_test() {
         key: "New".to_string(),
         value: "value".to_string(),
         variant: KeyValueVariant::LABEL,
+        value_type: None,
     }));
     assert_eq!(updated.object.data_class, DataClass::PUBLIC);
     assert!(updated.inbound_belongs_to.0.contains_key(&parent_id));
@@ -483,6 +497,7 @@ async fn update_object_test() {
         key: "to_delete".to_string(),
         value: "deleted".to_string(),
         variant: KeyValueVariant::LABEL,
+        value_type: None,
     }));
     assert!(!new.object.hashes.0 .0.is_empty());
 
@@ -537,3 +552,223 @@ async fn update_object_test() {
         Some(license_updated.object.data_license)
     )
 }
+
+#[tokio::test]
+async fn test_typed_keyvals() {
+    // Init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = db_handler.database.get_client().await.unwrap();
+    let mut user = test_utils::new_user(vec![]);
+    user.create(&client).await.unwrap();
+
+    let typed_kv = |key: &str, value: &str| APIKeyValue {
+        key: key.to_string(),
+        value: value.to_string(),
+        variant: 1,
+    };
+    let object_request = |parent: &DieselUlid, key_values: Vec<APIKeyValue>| {
+        CreateRequest::Object(CreateObjectRequest {
+            name: test_utils::rand_string(32),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values,
+            relations: vec![],
+            data_class: 1,
+            hashes: vec![],
+            parent: Some(ObjectParent::ProjectId(parent.to_string())),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        })
+    };
+
+    // Typed values are persisted with their type
+    let project = CreateRequest::Project(
+        CreateProjectRequest {
+            name: test_utils::rand_string(32).to_lowercase(),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![
+                typed_kv("size", "1000000^^xsd:integer"),
+                typed_kv("created", "2024-01-01^^xsd:dateTime"),
+                typed_kv("untyped", "plain string"),
+            ],
+            relations: vec![],
+            data_class: 1,
+            preferred_endpoint: "".to_string(),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            default_data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        },
+        DieselUlid::generate().to_string(),
+    );
+    let (project, _) = db_handler
+        .create_resource(project, user.id, false)
+        .await
+        .unwrap();
+    let key_values = &project.object.key_values.0;
+    assert!(key_values.0.contains(&KeyValue {
+        key: "size".to_string(),
+        value: "1000000".to_string(),
+        variant: KeyValueVariant::LABEL,
+        value_type: Some(KeyValueType::INTEGER),
+    }));
+    assert!(key_values.0.contains(&KeyValue {
+        key: "untyped".to_string(),
+        value: "plain string".to_string(),
+        variant: KeyValueVariant::LABEL,
+        value_type: None,
+    }));
+    // API representation keeps the type suffix
+    let converted: Vec<APIKeyValue> = key_values.clone().into();
+    assert!(converted.contains(&typed_kv("size", "1000000^^xsd:integer")));
+    assert!(converted.contains(&typed_kv("untyped", "plain string")));
+
+    // Values have to match their type
+    assert!(db_handler
+        .create_resource(
+            object_request(
+                &project.object.id,
+                vec![typed_kv("count", "abc^^xsd:integer")]
+            ),
+            user.id,
+            false
+        )
+        .await
+        .is_err());
+    // Keys must keep their type within a project
+    assert!(db_handler
+        .create_resource(
+            object_request(
+                &project.object.id,
+                vec![typed_kv("size", "1.5^^xsd:double")]
+            ),
+            user.id,
+            false
+        )
+        .await
+        .is_err());
+    db_handler
+        .create_resource(
+            object_request(
+                &project.object.id,
+                vec![typed_kv("size", "42^^xsd:integer")],
+            ),
+            user.id,
+            false,
+        )
+        .await
+        .unwrap();
+    let request = KeyValueUpdate::Project(UpdateProjectKeyValuesRequest {
+        project_id: project.object.id.to_string(),
+        add_key_values: vec![typed_kv("created", "true^^xsd:boolean")],
+        remove_key_values: vec![],
+    });
+    assert!(db_handler.update_keyvals(request).await.is_err());
+}
+
+#[tokio::test]
+async fn test_project_default_endpoint() {
+    // Init
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+    let mut user = test_utils::new_user(vec![]);
+    user.create(&client).await.unwrap();
+
+    // Create two endpoints
+    let mut endpoint_ids = Vec::new();
+    for _ in 0..2 {
+        let mut endpoint = Endpoint {
+            id: DieselUlid::generate(),
+            name: test_utils::rand_string(16),
+            host_config: Json(HostConfigs(Vec::new())),
+            endpoint_variant: EndpointVariant::PERSISTENT,
+            documentation_object: None,
+            is_public: true,
+            status: EndpointStatus::AVAILABLE,
+        };
+        endpoint.create(&client).await.unwrap();
+        endpoint_ids.push(endpoint.id);
+    }
+    let (first_endpoint, second_endpoint) = (endpoint_ids[0], endpoint_ids[1]);
+
+    // Create project with both endpoints as full sync endpoints
+    let request = CreateRequest::Project(
+        CreateProjectRequest {
+            name: test_utils::rand_string(32).to_lowercase(),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            preferred_endpoint: first_endpoint.to_string(),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            default_data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        },
+        "".to_string(),
+    );
+    let (project, _) = db_handler
+        .create_resource(request, user.id, false)
+        .await
+        .unwrap();
+    let project_id = project.object.id;
+    Object::update_endpoints(
+        second_endpoint,
+        EndpointInfo {
+            replication: ReplicationType::FullSync,
+            status: None,
+        },
+        vec![project_id],
+        &client,
+    )
+    .await
+    .unwrap();
+
+    // Endpoints which are not used by the project are rejected
+    let unknown = UpdateProjectDefaultEndpoint {
+        project_id: project_id.to_string(),
+        endpoint_id: Some(DieselUlid::generate().to_string()),
+    };
+    assert!(db_handler.update_default_endpoint(unknown).await.is_err());
+
+    // Set default endpoint
+    let request = UpdateProjectDefaultEndpoint {
+        project_id: project_id.to_string(),
+        endpoint_id: Some(second_endpoint.to_string()),
+    };
+    let project = db_handler.update_default_endpoint(request).await.unwrap();
+    assert_eq!(project.object.get_default_endpoint(), Some(second_endpoint));
+    cache.upsert_object(&project_id, project);
+
+    // Objects created in the project land on the default endpoint
+    let request = CreateRequest::Object(CreateObjectRequest {
+        name: test_utils::rand_string(32),
+        title: "".to_string(),
+        description: "test".to_string(),
+        key_values: vec![],
+        relations: vec![],
+        data_class: 1,
+        hashes: vec![],
+        parent: Some(ObjectParent::ProjectId(project_id.to_string())),
+        metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        authors: vec![],
+    });
+    let (object, _) = db_handler
+        .create_resource(request, user.id, false)
+        .await
+        .unwrap();
+    assert!(object.object.endpoints.0.contains_key(&second_endpoint));
+    let endpoint = db_handler.get_fullsync_endpoint(project_id).await.unwrap();
+    assert_eq!(endpoint.id, second_endpoint);
+
+    // Unset falls back to the remaining endpoints
+    let request = UpdateProjectDefaultEndpoint {
+        project_id: project_id.to_string(),
+        endpoint_id: None,
+    };
+    let project = db_handler.update_default_endpoint(request).await.unwrap();
+    assert!(project.object.get_default_endpoint().is_none());
+}
@@ -4,11 +4,25 @@ use aruna_rust_api::api::storage::services::v2::{
     ActivateUserRequest, DeactivateUserRequest, UpdateUserDisplayNameRequest,
     UpdateUserEmailRequest,
 };
+use aruna_server::auth::bootstrap::AdminBootstrap;
 use aruna_server::database::crud::CrudDb;
-use aruna_server::database::dsls::user_dsl::User;
+use aruna_server::database::dsls::object_dsl::Object;
+use aruna_server::database::dsls::persistent_notification_dsl::PersistentNotification;
+use aruna_server::database::dsls::stats_dsl::ObjectStats;
+use aruna_server::database::dsls::user_dsl::{APIToken, User};
+use aruna_server::database::enums::{
+    DbPermissionLevel, ObjectMapping, ObjectType, PersistentNotificationVariant,
+};
 use aruna_server::middlelayer::user_request_types::{
     ActivateUser, DeactivateUser, UpdateUserEmail, UpdateUserName,
 };
+use aruna_server::utils::user_notification_utils::{
+    check_project_quota, notify_expiring_tokens, notify_quota_thresholds, QuotaExceeded,
+    UserNotificationConfig,
+};
+use chrono::{NaiveDateTime, Utc};
+use diesel_ulid::DieselUlid;
+use std::time::Duration;
 
 /*
 #[tokio::test]
@@ -66,6 +80,27 @@ async fn test_activate_user() {
     assert!(!db_user.active);
 }
 
+#[tokio::test]
+async fn test_bootstrap_admin_with_existing_admin() {
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = db_handler.database.get_client().await.unwrap();
+    let mut user = test_utils::new_user(vec![]);
+    user.active = false;
+    user.create(&client).await.unwrap();
+
+    // Initial data contains global admins, so the bootstrap is disabled
+    assert!(User::global_admin_exists(&client).await.unwrap());
+    let token = "a-one-time-token-for-the-first-admin";
+    let bootstrap = AdminBootstrap::new(token);
+    assert!(db_handler
+        .bootstrap_admin(&bootstrap, user.id, token)
+        .await
+        .is_err());
+    let db_user = User::get(user.id, &client).await.unwrap().unwrap();
+    assert!(!db_user.attributes.0.global_admin);
+    assert!(!db_user.active);
+}
+
 #[tokio::test]
 async fn test_update_display_name() {
     let db_handler = init_database_handler_middlelayer().await;
@@ -105,3 +140,235 @@ async fn test_update_email() {
     let db_user = User::get(user.id, &client).await.unwrap().unwrap();
     assert_eq!(&db_user.email, &new_email);
 }
+
+#[tokio::test]
+async fn test_token_expiry_notification() {
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = db_handler.database.get_client().await.unwrap();
+
+    // User with an expiring, a valid and an already expired token
+    let mut user = test_utils::new_user(vec![]);
+    let now = Utc::now().naive_utc();
+    let token = |name: &str, expires_at| APIToken {
+        pub_key: 1,
+        name: name.to_string(),
+        created_at: now,
+        expires_at,
+        object_id: None,
+        user_rights: DbPermissionLevel::READ,
+        parent: None,
+        session_scoped: false,
+    };
+    let expiring = DieselUlid::generate();
+    for (id, name, expires_at) in [
+        (expiring, "expiring", now + chrono::Duration::minutes(30)),
+        (
+            DieselUlid::generate(),
+            "valid",
+            now + chrono::Duration::days(30),
+        ),
+        (
+            DieselUlid::generate(),
+            "expired",
+            now - chrono::Duration::minutes(30),
+        ),
+    ] {
+        user.attributes.0.tokens.insert(id, token(name, expires_at));
+    }
+    user.create(&client).await.unwrap();
+    db_handler.cache.add_user(user.id, user.clone());
+
+    let config = UserNotificationConfig {
+        dedup_window: Duration::from_secs(3600),
+        token_expiry_window: Duration::from_secs(3600),
+        project_quota: None,
+        quota_threshold: 80,
+        hard_quota_threshold: None,
+    };
+
+    // Only the expiring token is notified, repeated sweeps are deduplicated
+    for _ in 0..2 {
+        notify_expiring_tokens(&client, &config, &db_handler.cache)
+            .await
+            .unwrap();
+    }
+    let notifications = PersistentNotification::get_user_notifications(&user.id, &client)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|n| n.notification_variant == PersistentNotificationVariant::TOKEN_EXPIRING)
+        .collect::<Vec<_>>();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(
+        notifications[0].refs.0 .0[0].reference_value,
+        expiring.to_string()
+    );
+}
+
+#[tokio::test]
+async fn test_quota_threshold_notification() {
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = db_handler.database.get_client().await.unwrap();
+
+    // Create project with admin user
+    let project_id = DieselUlid::generate();
+    let mut user = test_utils::new_user(vec![]);
+    user.attributes
+        .0
+        .permissions
+        .insert(project_id, ObjectMapping::PROJECT(DbPermissionLevel::ADMIN));
+    user.create(&client).await.unwrap();
+    db_handler.cache.add_user(user.id, user.clone());
+
+    let mut project = test_utils::new_object(user.id, project_id, ObjectType::PROJECT);
+    project.create(&client).await.unwrap();
+    db_handler.cache.add_object(
+        Object::get_object_with_relations(&project_id, &client)
+            .await
+            .unwrap(),
+    );
+
+    let config = UserNotificationConfig {
+        dedup_window: Duration::from_secs(3600),
+        token_expiry_window: Duration::from_secs(3600),
+        project_quota: Some(1000),
+        quota_threshold: 80,
+        hard_quota_threshold: None,
+    };
+    let stats = |size| ObjectStats {
+        origin_pid: project_id,
+        count: 1,
+        size,
+        last_refresh: NaiveDateTime::default(),
+    };
+
+    // Below threshold -> No notification
+    let created = notify_quota_thresholds(&client, &config, &db_handler.cache, &[(stats(500), 0)])
+        .await
+        .unwrap();
+    assert_eq!(created, 0);
+
+    // Cross threshold twice -> Only one notification
+    for _ in 0..2 {
+        notify_quota_thresholds(&client, &config, &db_handler.cache, &[(stats(850), 500)])
+            .await
+            .unwrap();
+    }
+    // Stay above threshold -> No new notification
+    notify_quota_thresholds(&client, &config, &db_handler.cache, &[(stats(900), 850)])
+        .await
+        .unwrap();
+
+    let notifications = PersistentNotification::get_user_notifications(&user.id, &client)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|n| {
+            n.notification_variant == PersistentNotificationVariant::QUOTA_THRESHOLD_REACHED
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(
+        notifications[0].refs.0 .0[0].reference_value,
+        project_id.to_string()
+    );
+}
+
+#[tokio::test]
+async fn test_soft_and_hard_quota() {
+    let db_handler = init_database_handler_middlelayer().await;
+    let client = db_handler.database.get_client().await.unwrap();
+    let cache = &db_handler.cache;
+
+    // Project with admin user and a collection for new objects
+    let project_id = DieselUlid::generate();
+    let mut user = test_utils::new_user(vec![]);
+    user.attributes
+        .0
+        .permissions
+        .insert(project_id, ObjectMapping::PROJECT(DbPermissionLevel::ADMIN));
+    user.create(&client).await.unwrap();
+    cache.add_user(user.id, user.clone());
+    let mut project = test_utils::new_object(user.id, project_id, ObjectType::PROJECT);
+    let mut collection =
+        test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::COLLECTION);
+    project.create(&client).await.unwrap();
+    collection.create(&client).await.unwrap();
+    test_utils::new_internal_relation(&project, &collection)
+        .create(&client)
+        .await
+        .unwrap();
+    for id in [project.id, collection.id] {
+        cache.add_object(
+            Object::get_object_with_relations(&id, &client)
+                .await
+                .unwrap(),
+        );
+    }
+
+    let config = UserNotificationConfig {
+        dedup_window: Duration::from_secs(3600),
+        token_expiry_window: Duration::from_secs(3600),
+        project_quota: Some(1000),
+        quota_threshold: 80,
+        hard_quota_threshold: Some(100),
+    };
+    let stats = |size| ObjectStats {
+        origin_pid: project_id,
+        count: 1,
+        size,
+        last_refresh: NaiveDateTime::default(),
+    };
+    let user_id = user.id;
+    let quota_notifications = |variant| {
+        let client = &client;
+        async move {
+            PersistentNotification::get_user_notifications(&user_id, client)
+                .await
+                .unwrap()
+                .into_iter()
+                .filter(|n| n.notification_variant == variant)
+                .count()
+        }
+    };
+
+    // Crossing the soft threshold warns once, but writes are still accepted
+    cache.upsert_object_stats(vec![stats(850)]).await.unwrap();
+    for previous_size in [500, 850] {
+        notify_quota_thresholds(&client, &config, cache, &[(stats(850), previous_size)])
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        quota_notifications(PersistentNotificationVariant::QUOTA_THRESHOLD_REACHED).await,
+        1
+    );
+    assert!(check_project_quota(&config, cache, &collection.id, 0).is_ok());
+    assert!(check_project_quota(&config, cache, &collection.id, 150).is_ok());
+
+    // Writes which would cross the hard threshold are rejected
+    let err = check_project_quota(&config, cache, &collection.id, 151).unwrap_err();
+    let exceeded = err.downcast_ref::<QuotaExceeded>().unwrap();
+    assert_eq!(exceeded.project_id, project_id);
+    assert_eq!(exceeded.limit, 1000);
+
+    // Reaching the hard threshold warns once more and rejects every new object
+    cache.upsert_object_stats(vec![stats(1000)]).await.unwrap();
+    for previous_size in [850, 1000] {
+        notify_quota_thresholds(&client, &config, cache, &[(stats(1000), previous_size)])
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        quota_notifications(PersistentNotificationVariant::QUOTA_EXCEEDED).await,
+        1
+    );
+    assert!(check_project_quota(&config, cache, &collection.id, 0).is_err());
+
+    // Quotas are not enforced without a hard threshold
+    let soft_only = UserNotificationConfig {
+        hard_quota_threshold: None,
+        ..config
+    };
+    assert!(check_project_quota(&soft_only, cache, &collection.id, 0).is_ok());
+}
@@ -149,6 +149,7 @@ async fn create_and_delete_workspace() {
                 key: "some_key".to_string(),
                 value: "some_value".to_string(),
                 variant: KeyValueVariant::LABEL,
+                value_type: None,
             })],
         }),
         timeout: chrono::Utc::now()
@@ -1,22 +1,32 @@
-use aruna_rust_api::api::notification::services::v2::EventVariant;
+use aruna_rust_api::api::notification::services::v2::{
+    event_message::MessageVariant, EventVariant, Resource, ResourceEvent,
+};
+use aruna_rust_api::api::storage::models::v2::ResourceVariant;
+use aruna_rust_api::api::storage::services::v2::create_object_request::Parent as ObjectParent;
+use aruna_rust_api::api::storage::services::v2::{CreateObjectRequest, CreateProjectRequest};
 use aruna_server::{
+    caching::notifications_handler::NotificationHandler,
     database::{
         crud::CrudDb,
         dsls::{
-            internal_relation_dsl::InternalRelation, notification_dsl::StreamConsumer,
-            object_dsl::Object,
+            internal_relation_dsl::InternalRelation, license_dsl::ALL_RIGHTS_RESERVED,
+            notification_dsl::StreamConsumer, object_dsl::Object,
         },
         enums::ObjectType,
     },
+    middlelayer::create_request_types::CreateRequest,
     notification::{
         handler::{EventHandler, EventType},
         natsio_handler::NatsIoHandler,
     },
+    search::meilisearch_client::ObjectDocument,
 };
 use async_nats::jetstream::consumer::{Config, DeliverPolicy};
 use diesel_ulid::DieselUlid;
 
-use crate::common::init::init_database;
+use crate::common::init::{
+    init_cache, init_database, init_database_handler, init_nats_client, init_search_client,
+};
 
 mod common;
 
@@ -276,3 +286,126 @@ async fn resource_notification_test() {
 
     assert_eq!(proj_003_messages.len(), 1);
 }
+
+#[tokio::test]
+async fn nats_outage_test() {
+    // Init database handler with its own Nats.io handler
+    let db = init_database().await;
+    let client = db.get_client().await.unwrap();
+    let nats_handler = init_nats_client().await;
+    let cache = init_cache(db.clone(), true).await;
+    let (hook_sender, _hook_reciever) = async_channel::unbounded();
+    let db_handler =
+        init_database_handler(db.clone(), nats_handler.clone(), cache.clone(), hook_sender).await;
+
+    // Create user and project while Nats.io is available
+    let mut user = common::test_utils::new_user(vec![]);
+    user.create(&client).await.unwrap();
+    let project = CreateRequest::Project(
+        CreateProjectRequest {
+            name: common::test_utils::rand_string(32).to_lowercase(),
+            title: "".to_string(),
+            description: "test".to_string(),
+            key_values: vec![],
+            relations: vec![],
+            data_class: 1,
+            preferred_endpoint: "".to_string(),
+            metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            default_data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+            authors: vec![],
+        },
+        DieselUlid::generate().to_string(),
+    );
+    let (project, _) = db_handler
+        .create_resource(project, user.id, false)
+        .await
+        .unwrap();
+    cache.add_object(project.clone());
+    assert_eq!(nats_handler.pending_notifications(), 0);
+
+    // Object creation succeeds while Nats.io is unreachable ...
+    nats_handler.set_available(false);
+    let request = CreateRequest::Object(CreateObjectRequest {
+        name: common::test_utils::rand_string(32),
+        title: "".to_string(),
+        description: "test".to_string(),
+        key_values: vec![],
+        relations: vec![],
+        data_class: 1,
+        hashes: vec![],
+        parent: Some(ObjectParent::ProjectId(project.object.id.to_string())),
+        metadata_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        data_license_tag: ALL_RIGHTS_RESERVED.to_string(),
+        authors: vec![],
+    });
+    let (object, _) = db_handler
+        .create_resource(request, user.id, false)
+        .await
+        .unwrap();
+    assert!(Object::get(object.object.id, &client)
+        .await
+        .unwrap()
+        .is_some());
+
+    // ... and its notifications are queued for later delivery
+    assert!(nats_handler.pending_notifications() > 0);
+    nats_handler.flush_buffer().await.unwrap();
+    nats_handler.set_available(true);
+    assert_eq!(nats_handler.pending_notifications(), 0);
+}
+
+#[tokio::test]
+async fn duplicate_event_delivery_test() {
+    // Init internal components
+    let db = init_database().await;
+    let client = db.get_client().await.unwrap();
+    let cache = init_cache(db.clone(), false).await;
+    let search_client = init_search_client().await;
+
+    // Create public project which is not cached yet
+    let mut user = common::test_utils::new_user(vec![]);
+    user.create(&client).await.unwrap();
+    let mut project =
+        common::test_utils::new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    project.create(&client).await.unwrap();
+
+    // Deliver the same event twice
+    let event = MessageVariant::ResourceEvent(ResourceEvent {
+        resource: Some(Resource {
+            resource_id: project.id.to_string(),
+            persistent_resource_id: false,
+            checksum: "".to_string(),
+            resource_variant: ResourceVariant::Project as i32,
+        }),
+        event_variant: EventVariant::Created as i32,
+        reply: None,
+    });
+    for _ in 0..2 {
+        NotificationHandler::update_server_cache(
+            event.clone(),
+            cache.clone(),
+            db.clone(),
+            search_client.clone(),
+        )
+        .await
+        .unwrap();
+    }
+    assert!(cache.get_object(&project.id).is_some());
+
+    // Search index contains the resource exactly once
+    let search_query = format!("\"{}\"", project.id);
+    let mut hits = Vec::new();
+    for _ in 0..50 {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        (hits, _) = search_client
+            .query_generic_stuff::<ObjectDocument>("objects", &search_query, "", 1000, 0)
+            .await
+            .unwrap();
+        if !hits.is_empty() {
+            break;
+        }
+    }
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].id, project.id);
+    assert_eq!(hits[0].name, project.name);
+}
@@ -1,15 +1,30 @@
 use aruna_rust_api::api::storage::models::v2::generic_resource;
-use aruna_server::database::dsls::object_dsl::Author;
+use aruna_server::caching::cache::Cache;
+use aruna_server::database::crud::CrudDb;
+use aruna_server::database::dsls::object_dsl::{Author, KeyValues, ObjectWithRelations};
+use aruna_server::database::dsls::search_index_queue_dsl::QueuedIndexUpdate;
+use aruna_server::database::dsls::search_reindex_dsl::SearchReindexCheckpoint;
+use aruna_server::utils::search_utils::{
+    remove_from_search_index, update_search_index, SearchReindex,
+};
 use aruna_server::{
     database::{
         dsls::object_dsl::{KeyValue, KeyValueVariant},
         enums::{DataClass, ObjectStatus, ObjectType},
     },
-    search::meilisearch_client::{MeilisearchClient, MeilisearchIndexes, ObjectDocument},
+    search::meilisearch_client::{
+        include_inherited_labels, MeilisearchClient, MeilisearchIndexes, ObjectDocument,
+    },
 };
 use chrono::NaiveDateTime;
+use common::init::{init_cache, init_database};
+use common::test_utils::{new_internal_relation, new_object, new_user};
+use dashmap::DashMap;
 use diesel_ulid::DieselUlid;
+use postgres_types::Json;
 use rand::{seq::IteratorRandom, thread_rng, Rng};
+use std::sync::Arc;
+use std::time::Duration;
 
 mod common;
 
@@ -109,6 +124,301 @@ async fn search_test() {
     }
 }
 
+#[tokio::test]
+async fn search_outage_test() {
+    // Create Meilisearch client for an unreachable server which queues updates in the database
+    let db = init_database().await;
+    let meilisearch_client = Arc::new(
+        MeilisearchClient::new("http://localhost:1", Some("MASTER_KEY"))
+            .unwrap()
+            .with_index_queue(db.clone()),
+    );
+    let cache = Cache::new();
+    assert!(meilisearch_client.is_available());
+
+    // Index updates must not fail but get queued
+    let mut document = generate_random_object_document();
+    document.data_class = DataClass::PUBLIC;
+    document.object_type_id = 4;
+    update_search_index(&meilisearch_client, &cache, vec![document.clone()]).await;
+
+    let mut retries = 0;
+    while meilisearch_client.is_available() && retries < 50 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        retries += 1;
+    }
+    assert!(!meilisearch_client.is_available());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let client = db.get_client().await.unwrap();
+    let queued = QueuedIndexUpdate::get(document.id, &client)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!queued.deletion);
+
+    // Further updates get queued directly while unavailable and
+    // deletions supersede queued updates of the same resource
+    update_search_index(&meilisearch_client, &cache, vec![document.clone()]).await;
+    remove_from_search_index(&meilisearch_client, vec![document.id]).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let requeued = QueuedIndexUpdate::get(document.id, &client)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(requeued.deletion);
+
+    // Updates queued again after being read are kept on dequeue
+    QueuedIndexUpdate::dequeue(&[queued], &client)
+        .await
+        .unwrap();
+    assert!(QueuedIndexUpdate::get(document.id, &client)
+        .await
+        .unwrap()
+        .is_some());
+    QueuedIndexUpdate::dequeue(&[requeued], &client)
+        .await
+        .unwrap();
+    assert!(QueuedIndexUpdate::get(document.id, &client)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn search_inherited_labels_test() {
+    // Create Meilisearch client and index
+    let meilisearch_client =
+        MeilisearchClient::new("http://localhost:7700", Some("MASTER_KEY")).unwrap();
+    meilisearch_client
+        .get_or_create_index("objects", Some("id"))
+        .await
+        .unwrap();
+
+    // Cache dataset with label and contained object without labels
+    let cache = Cache::new();
+    let user_id = DieselUlid::generate();
+    let label_value = DieselUlid::generate().to_string();
+    let mut dataset = new_object(user_id, DieselUlid::generate(), ObjectType::DATASET);
+    dataset.key_values = Json(KeyValues(vec![KeyValue {
+        key: "project".to_string(),
+        value: label_value.clone(),
+        variant: KeyValueVariant::LABEL,
+        value_type: None,
+    }]));
+    let object = new_object(user_id, DieselUlid::generate(), ObjectType::OBJECT);
+    let relation = new_internal_relation(&dataset, &object);
+
+    cache.upsert_object(
+        &dataset.id,
+        ObjectWithRelations {
+            object: dataset.clone(),
+            inbound: Json(DashMap::default()),
+            inbound_belongs_to: Json(DashMap::default()),
+            outbound: Json(DashMap::default()),
+            outbound_belongs_to: Json(DashMap::from_iter([(object.id, relation.clone())])),
+        },
+    );
+    cache.upsert_object(
+        &object.id,
+        ObjectWithRelations {
+            object: object.clone(),
+            inbound: Json(DashMap::default()),
+            inbound_belongs_to: Json(DashMap::from_iter([(dataset.id, relation)])),
+            outbound: Json(DashMap::default()),
+            outbound_belongs_to: Json(DashMap::default()),
+        },
+    );
+
+    // Dataset label is part of the effective labels of the object only
+    let object_document = cache.get_object_document(&object.id).unwrap();
+    assert!(object_document.labels.is_empty());
+    assert!(object_document
+        .effective_labels
+        .iter()
+        .any(|kv| kv.key == "project" && kv.value == label_value));
+
+    meilisearch_client
+        .add_or_update_stuff(&[object_document], MeilisearchIndexes::OBJECT)
+        .await
+        .unwrap()
+        .wait_for_completion(&meilisearch_client.client, None, None)
+        .await
+        .unwrap();
+
+    // Object is not found by the dataset label without opt-in ...
+    let filter = format!("labels.key = project AND labels.value = '{}'", label_value);
+    let (hits, _) = meilisearch_client
+        .query_generic_stuff::<ObjectDocument>("objects", "", &filter, 1000, 0)
+        .await
+        .unwrap();
+    assert!(!hits.iter().any(|hit| hit.id == object.id));
+
+    // ... but with inherited labels included
+    let (hits, _) = meilisearch_client
+        .query_generic_stuff::<ObjectDocument>(
+            "objects",
+            "",
+            &include_inherited_labels(&filter),
+            1000,
+            0,
+        )
+        .await
+        .unwrap();
+    assert!(hits.iter().any(|hit| hit.id == object.id));
+}
+
+#[tokio::test]
+async fn search_description_test() {
+    // Searchable attributes are set on creation, so a fresh index is used
+    let meilisearch_client =
+        MeilisearchClient::new("http://localhost:7700", Some("MASTER_KEY")).unwrap();
+    let index_name = format!("objects-{}", DieselUlid::generate());
+    meilisearch_client
+        .get_or_create_index(&index_name, Some("id"))
+        .await
+        .unwrap();
+
+    // Distinctive word in the description of one and the name of another object
+    let word = "zymoglyphic";
+    let mut described = generate_random_object_document();
+    described.description = format!("Reads of a {word} sample");
+    let mut named = generate_random_object_document();
+    named.name = format!("{word}-reads");
+    let other = generate_random_object_document();
+    meilisearch_client
+        .add_and_wait(
+            &index_name,
+            &[described.clone(), named.clone(), other],
+            Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+
+    // Both are found by the word, the name match ranks above the description match
+    let (hits, estimated_total) = meilisearch_client
+        .query_generic_stuff::<ObjectDocument>(&index_name, word, "", 1000, 0)
+        .await
+        .unwrap();
+    assert_eq!(estimated_total, 2);
+    assert_eq!(
+        hits.iter().map(|hit| hit.id).collect::<Vec<_>>(),
+        vec![named.id, described.id]
+    );
+
+    meilisearch_client
+        .delete_index_if_exists(&index_name)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn search_reindex_test() {
+    let db = init_database().await;
+    let client = db.get_client().await.unwrap();
+    let cache = init_cache(db.clone(), false).await;
+    let meilisearch_client =
+        Arc::new(MeilisearchClient::new("http://localhost:7700", Some("MASTER_KEY")).unwrap());
+    // Separate index, the reindex replaces all documents
+    let index_name = "objects_reindex_test";
+    if let Some(leftover) = SearchReindexCheckpoint::get(index_name.to_string(), &client)
+        .await
+        .unwrap()
+    {
+        leftover.delete(&client).await.unwrap();
+    }
+
+    // Resource in the database which is not yet indexed
+    let mut user = new_user(vec![]);
+    user.create(&client).await.unwrap();
+    let mut project = new_object(user.id, DieselUlid::generate(), ObjectType::PROJECT);
+    project.create(&client).await.unwrap();
+
+    // Previous index content
+    let previous = generate_random_object_document();
+    meilisearch_client
+        .get_or_create_index(index_name, Some("id"))
+        .await
+        .unwrap();
+    meilisearch_client
+        .add_and_wait(index_name, &[previous.clone()], Duration::from_secs(60))
+        .await
+        .unwrap();
+    let hits = |id: DieselUlid| {
+        let meilisearch_client = meilisearch_client.clone();
+        async move {
+            meilisearch_client
+                .query_generic_stuff::<ObjectDocument>(
+                    index_name,
+                    &format!("\"{}\"", id),
+                    "",
+                    1000,
+                    0,
+                )
+                .await
+                .unwrap()
+                .0
+                .len()
+        }
+    };
+    assert_eq!(hits(previous.id).await, 1);
+
+    // Reindex gets interrupted after its first batch
+    let mut reindex = SearchReindex::start(
+        db.clone(),
+        cache.clone(),
+        meilisearch_client.clone(),
+        index_name,
+        1,
+    )
+    .await
+    .unwrap();
+    assert!(reindex.next_batch().await.unwrap());
+    assert_eq!(hits(previous.id).await, 1);
+    drop(reindex);
+    let checkpoint = SearchReindexCheckpoint::get(index_name.to_string(), &client)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(checkpoint.processed, 1);
+
+    // Resumed reindex continues after the checkpoint, queries are served by the previous index
+    let mut reindex = SearchReindex::start(
+        db.clone(),
+        cache.clone(),
+        meilisearch_client.clone(),
+        index_name,
+        1000,
+    )
+    .await
+    .unwrap();
+    assert!(reindex.progress().resumed);
+    assert_eq!(reindex.progress().processed, 1);
+    while reindex.next_batch().await.unwrap() {
+        assert_eq!(hits(previous.id).await, 1);
+        assert_eq!(
+            meilisearch_client.reindex_progress(index_name).as_ref(),
+            Some(reindex.progress())
+        );
+    }
+    assert!(reindex.progress().processed >= reindex.progress().total);
+    assert_eq!(hits(previous.id).await, 1);
+    assert_eq!(hits(project.id).await, 0);
+
+    // Rebuilt index replaces the previous index
+    reindex.finish().await.unwrap();
+    assert_eq!(hits(previous.id).await, 0);
+    assert_eq!(hits(project.id).await, 1);
+    assert!(meilisearch_client.reindex_progress(index_name).is_none());
+    assert!(
+        SearchReindexCheckpoint::get(index_name.to_string(), &client)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
 fn generate_random_object_document() -> ObjectDocument {
     let mut rng = thread_rng();
     let name_parts = vec![
@@ -147,21 +457,26 @@ fn generate_random_object_document() -> ObjectDocument {
         }],
         count: rand_count,
         size: rand_size,
+        typed_labels: Default::default(),
+        effective_labels: vec![],
         labels: vec![
             KeyValue {
                 key: "validated".to_string(),
                 value: hook_run_success.clone(),
                 variant: KeyValueVariant::LABEL,
+                value_type: None,
             },
             KeyValue {
                 key: "submitted".to_string(),
                 value: hook_run_success,
                 variant: KeyValueVariant::LABEL,
+                value_type: None,
             },
             KeyValue {
                 key: "validate_and_submit".to_string(),
                 value: "fastq;ENA".to_string(),
                 variant: KeyValueVariant::HOOK,
+                value_type: None,
             },
         ],
         data_class: DataClass::PUBLIC,